@@ -0,0 +1,728 @@
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use crate::xtream::XtreamProfile;
+use rusqlite::{params, Connection};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::State;
+
+/// Runtime-only flag; demo mode is never persisted so a restart always shows real data
+static DEMO_MODE: AtomicBool = AtomicBool::new(false);
+
+/// JSON keys whose values identify the user or the provider
+const SENSITIVE_KEYS: &[&str] = &["username", "password", "url", "server_url", "host", "pairing_token"];
+
+/// Columns holding credentials or provider URLs, redacted in exported databases
+const REDACTED_COLUMNS: &[(&str, &str)] = &[
+    ("xtream_profiles", "url"),
+    ("xtream_profiles", "username"),
+    ("channel_lists", "source"),
+    ("channel_lists", "filepath"),
+    ("channel_lists", "epg_url"),
+    ("channels", "url"),
+    ("history", "url"),
+    ("xtream_channels", "direct_source"),
+    ("xtream_movies", "direct_source"),
+    ("xtream_episodes", "direct_source"),
+    ("xtream_channel_url_overrides", "url"),
+    ("xtream_account_status", "user_info"),
+    ("recordings", "stream_url"),
+    ("xtream_sync_history", "errors"),
+    ("playlist_link_failures", "url"),
+    ("image_cache_index", "url"),
+    ("image_decode_failures", "url"),
+];
+
+pub fn is_demo_mode() -> bool {
+    DEMO_MODE.load(Ordering::Relaxed)
+}
+
+/// Hash a sensitive value into a short, stable placeholder
+///
+/// The same input always yields the same placeholder, so relationships between
+/// rows survive redaction while the value itself does not.
+pub fn redact(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let hex: String = digest[..6].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("redacted-{}", hex)
+}
+
+/// Mask the identifying fields of a profile (name is kept)
+pub fn mask_profile(mut profile: XtreamProfile) -> XtreamProfile {
+    profile.url = format!("http://{}.invalid", redact(&profile.url));
+    profile.username = redact(&profile.username);
+    profile
+}
+
+/// Mask sensitive keys anywhere in a JSON value, such as an authentication response
+pub fn mask_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if SENSITIVE_KEYS.contains(&key.as_str()) {
+                    if let Some(s) = item.as_str() {
+                        *item = serde_json::Value::String(redact(s));
+                        continue;
+                    }
+                }
+                mask_json(item);
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_json),
+        _ => {}
+    }
+}
+
+/// Mask every string value that looks like a URL, whatever its scheme
+fn mask_urls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) if s.contains("://") => {
+            *s = redact(s);
+        }
+        serde_json::Value::Object(map) => map.values_mut().for_each(mask_urls),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_urls),
+        _ => {}
+    }
+}
+
+/// Mask URLs, credentials and server names in a command result when demo mode is on
+///
+/// The result round-trips through JSON so one pass covers every result type:
+/// sensitive keys are hashed wherever they appear and so is any URL-like string.
+/// Fails rather than return the unmasked value if the round trip does.
+pub fn mask_result<T: Serialize + DeserializeOwned>(value: T) -> std::result::Result<T, String> {
+    if !is_demo_mode() {
+        return Ok(value);
+    }
+    mask_value(value)
+}
+
+fn mask_value<T: Serialize + DeserializeOwned>(value: T) -> std::result::Result<T, String> {
+    let mut json = serde_json::to_value(&value).map_err(|e| format!("Failed to mask response: {}", e))?;
+    mask_response(&mut json);
+    serde_json::from_value(json).map_err(|e| format!("Failed to mask response: {}", e))
+}
+
+/// Mask sensitive keys and URL-like strings in a serialized response
+pub fn mask_response(json: &mut serde_json::Value) {
+    mask_json(json);
+    mask_urls(json);
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(columns.iter().any(|c| c == column))
+}
+
+fn redact_column(conn: &Connection, table: &str, column: &str) -> Result<()> {
+    if !column_exists(conn, table, column)? {
+        return Ok(());
+    }
+
+    let rows = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, {} FROM {} WHERE {} IS NOT NULL AND {} != ''",
+            column, table, column, column
+        ))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    for (rowid, value) in rows {
+        conn.execute(
+            &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table, column),
+            params![redact(&value), rowid],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn redact_content_data(conn: &Connection, table: &str) -> Result<()> {
+    if !column_exists(conn, table, "content_data")? {
+        return Ok(());
+    }
+
+    let rows = {
+        let mut stmt = conn.prepare(&format!("SELECT rowid, content_data FROM {}", table))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    for (rowid, data) in rows {
+        let Ok(mut content_data) = serde_json::from_slice::<serde_json::Value>(&data) else {
+            continue;
+        };
+        mask_urls(&mut content_data);
+        let data = serde_json::to_vec(&content_data)
+            .map_err(|e| XTauriError::internal(format!("Failed to serialize content data: {}", e)))?;
+        conn.execute(
+            &format!("UPDATE {} SET content_data = ?1 WHERE rowid = ?2", table),
+            params![data, rowid],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Write a redacted copy of the database to `dest`
+///
+/// Usernames, URLs and stream sources are hashed, encrypted credentials, tokens and
+/// raw API responses are dropped. Channel, movie and series names are preserved.
+pub fn create_redacted_copy(conn: &Connection, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        return Err(XTauriError::internal(format!(
+            "Refusing to overwrite existing file: {}",
+            dest.display()
+        )));
+    }
+
+    conn.execute("VACUUM INTO ?1", [dest.to_string_lossy()])?;
+
+    // VACUUM INTO keeps the source's encryption and key
+    let copy = Connection::open(dest)?;
+    crate::database::apply_key(&copy)?;
+    let tx = copy.unchecked_transaction()?;
+
+    for (table, column) in REDACTED_COLUMNS {
+        redact_column(&tx, table, column)?;
+    }
+
+    if column_exists(&tx, "xtream_profiles", "encrypted_credentials")? {
+        tx.execute("UPDATE xtream_profiles SET encrypted_credentials = X''", [])?;
+    }
+    if column_exists(&tx, "settings", "remote_control_token")? {
+        tx.execute("UPDATE settings SET remote_control_token = NULL", [])?;
+    }
+    if column_exists(&tx, "xtream_content_cache", "data")? {
+        tx.execute("DELETE FROM xtream_content_cache", [])?;
+    }
+
+    redact_content_data(&tx, "xtream_favorites")?;
+    redact_content_data(&tx, "xtream_history")?;
+
+    tx.commit()?;
+    copy.execute("VACUUM", [])?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn export_redacted_database(state: State<DbState>, path: String) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    create_redacted_copy(&db, Path::new(&path)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_demo_mode() -> bool {
+    is_demo_mode()
+}
+
+/// Turn masking on or off for every command returning URLs, credentials or server names
+///
+/// Stream and artwork URLs are masked too, so playback and uncached images are
+/// unavailable until demo mode is turned off again.
+#[tauri::command]
+pub fn set_demo_mode(enabled: bool) {
+    DEMO_MODE.store(enabled, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pagination::Page;
+
+    #[test]
+    fn test_redact_is_stable_and_hides_value() {
+        assert_eq!(redact("secret-user"), redact("secret-user"));
+        assert_ne!(redact("secret-user"), redact("other-user"));
+        assert!(!redact("secret-user").contains("secret"));
+    }
+
+    #[test]
+    fn test_mask_json_hides_credentials() {
+        let mut auth = serde_json::json!({
+            "user_info": { "username": "alice", "password": "hunter2", "status": "Active" },
+            "server_info": { "url": "provider.example.com", "port": "8080" }
+        });
+        mask_json(&mut auth);
+
+        assert_eq!(auth["user_info"]["username"], redact("alice"));
+        assert_eq!(auth["user_info"]["password"], redact("hunter2"));
+        assert_eq!(auth["user_info"]["status"], "Active");
+        assert_eq!(auth["server_info"]["url"], redact("provider.example.com"));
+    }
+
+    /// Registered commands whose results carry URLs, credentials or server names
+    ///
+    /// Each of them passes its result through `mask_result` (or `ipc_payload::respond`,
+    /// which does the same) so demo mode covers the whole command surface.
+    const MASKED_COMMANDS: &[&str] = &[
+        // M3U channels, playlists and recordings
+        "get_channels",
+        "get_channels_paginated",
+        "get_channels_async",
+        "search_channels",
+        "search_channels_paginated",
+        "search_channels_async",
+        "get_history",
+        "get_history_async",
+        "get_channel_lists",
+        "get_channel_epg",
+        "start_recording",
+        "list_recordings",
+        // Remote control
+        "get_remote_control_settings",
+        "set_remote_control_enabled",
+        "set_remote_control_port",
+        "rotate_remote_control_token",
+        // Xtream profiles and provider API
+        "get_xtream_profiles",
+        "get_xtream_profile",
+        "validate_xtream_credentials",
+        "test_xtream_connection",
+        "authenticate_xtream_profile",
+        "get_xtream_account_status",
+        "get_xtream_channels",
+        "get_xtream_channels_paginated",
+        "get_xtream_movies",
+        "get_xtream_movies_paginated",
+        "get_xtream_movie_info",
+        "get_xtream_series",
+        "get_xtream_series_paginated",
+        "get_xtream_series_info",
+        "filter_channels_advanced",
+        "filter_movies_advanced",
+        "filter_series_advanced",
+        "search_all_xtream_content",
+        "get_epg_icon_urls",
+        "generate_xtream_stream_url",
+        "generate_xtream_episode_stream_url",
+        "resolve_xtream_stream_url",
+        "get_xtream_playback_history",
+        // Content cache
+        "get_cached_xtream_channels",
+        "get_cached_xtream_movies",
+        "filter_cached_xtream_movies",
+        "get_cached_xtream_series",
+        "get_cached_xtream_series_details",
+        "get_xtream_epg_grid",
+        "get_cached_xtream_epg",
+        "search_cached_xtream_channels",
+        "search_cached_xtream_movies",
+        "search_cached_xtream_series",
+        "search_all_content",
+        "get_xtream_channel_url_overrides",
+        "preview_xtream_profile",
+        "get_playback_candidates",
+        "report_playback_failure",
+        "report_playback_event",
+        "get_similar",
+        "get_sync_history",
+        // Favorites, history and watchlist
+        "get_xtream_favorites",
+        "get_xtream_favorites_by_type",
+        "get_xtream_history",
+        "get_xtream_history_by_type",
+        "get_xtream_history_item",
+        "get_continue_watching",
+        "get_xtream_content_by_tag",
+        "add_to_watchlist",
+        "get_watchlist",
+    ];
+
+    /// Result types that carry URLs, credentials or server names
+    const SENSITIVE_RESULT_TYPES: &[&str] = &[
+        "Response",
+        "Value",
+        "Channel",
+        "ChannelListing",
+        "ChannelList",
+        "Recording",
+        "RemoteControlSettings",
+        "XtreamProfile",
+        "AuthenticationResult",
+        "ConnectionTestResult",
+        "AccountStatus",
+        "XtreamChannel",
+        "XtreamMovie",
+        "XtreamSeries",
+        "XtreamChannelListing",
+        "XtreamMovieListing",
+        "XtreamSeriesListing",
+        "XtreamSeriesDetails",
+        "EpgProgramme",
+        "ResolvedStreamUrl",
+        "ChannelUrlOverride",
+        "ProfilePreview",
+        "PlaybackCandidate",
+        "SimilarItem",
+        "UnifiedSearchResults",
+        "SearchResult",
+        "XtreamFavorite",
+        "XtreamHistory",
+        "ContinueWatchingItem",
+        "XtreamContentTag",
+        "WatchlistItem",
+        "SyncHistoryEntry",
+    ];
+
+    /// Commands returning a sensitive type that holds nothing sensitive in practice
+    const UNMASKED_COMMANDS: &[&str] = &[
+        // Category names and counts
+        "get_xtream_channel_categories",
+        "get_xtream_movie_categories",
+        "get_xtream_series_categories",
+        "get_xtream_channel_counts_by_category",
+        // Programme titles and times
+        "get_xtream_short_epg",
+        "get_xtream_full_epg",
+        "get_xtream_epg_by_date_range",
+        "get_xtream_current_and_next_epg",
+        // Layout state the frontend saved itself
+        "get_ui_state",
+        // Reshape listings the frontend passes in, already masked when it fetched them
+        "filter_xtream_channels",
+        "sort_xtream_channels",
+        "search_xtream_channels",
+        "filter_xtream_movies",
+        "sort_xtream_movies",
+        "search_xtream_movies",
+        "filter_xtream_series",
+        "sort_xtream_series",
+        "search_xtream_series",
+        // Reshape EPG data the frontend passes in
+        "parse_epg_programs",
+        "parse_and_enhance_epg_data",
+        "filter_epg_by_time_range",
+        "search_epg_programs",
+    ];
+
+    fn registered_commands() -> Vec<String> {
+        let lib = include_str!("lib.rs");
+        let start = lib.find("generate_handler![").unwrap();
+        let end = start + lib[start..].find(']').unwrap();
+        lib[start + "generate_handler![".len()..end]
+            .lines()
+            .map(|line| line.split("//").next().unwrap().trim().trim_end_matches(','))
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn sources(dir: &Path, out: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                sources(&path, out);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                out.push(std::fs::read_to_string(&path).unwrap());
+            }
+        }
+    }
+
+    /// Return type and body of each `#[tauri::command]` function
+    fn command_definitions() -> std::collections::HashMap<String, (String, String)> {
+        let mut files = Vec::new();
+        sources(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut files);
+
+        let mut definitions = std::collections::HashMap::new();
+        for file in &files {
+            for (at, _) in file.match_indices("#[tauri::command]") {
+                let rest = &file[at..];
+                let Some(fn_at) = rest.find("fn ") else { continue };
+                let rest = &rest[fn_at + 3..];
+                let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+                let Some(open) = rest
+                    .find(") ->")
+                    .map(|i| i + 1)
+                    .or_else(|| rest.find(") {").map(|i| i + 1))
+                else {
+                    continue;
+                };
+                let body_at = open + rest[open..].find('{').unwrap();
+                let returns = rest[open..body_at].trim().trim_start_matches("->").trim().to_string();
+
+                let mut depth = 0;
+                let mut body_end = body_at;
+                for (i, c) in rest[body_at..].char_indices() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                body_end = body_at + i;
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                definitions.insert(name, (returns, rest[body_at..body_end].to_string()));
+            }
+        }
+        definitions
+    }
+
+    fn mentions_type(returns: &str, ty: &str) -> bool {
+        returns
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .any(|word| word == ty)
+    }
+
+    /// Whether a body calls a command function directly, not a same-named method or path
+    fn calls(body: &str, name: &str) -> bool {
+        body.match_indices(&format!("{}(", name)).any(|(at, _)| {
+            !body[..at]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == ':' || c == '.')
+        })
+    }
+
+    #[test]
+    fn test_every_command_returning_sensitive_data_is_masked() {
+        let commands = registered_commands();
+        let definitions = command_definitions();
+
+        for masked in MASKED_COMMANDS {
+            assert!(
+                commands.iter().any(|c| c == masked),
+                "{} is not a registered command",
+                masked
+            );
+        }
+
+        let mut unmasked = Vec::new();
+        for command in &commands {
+            let (returns, body) = definitions
+                .get(command)
+                .unwrap_or_else(|| panic!("No #[tauri::command] definition for {}", command));
+            let sensitive = SENSITIVE_RESULT_TYPES.iter().any(|ty| mentions_type(returns, ty))
+                || (command.contains("url") && mentions_type(&returns.replace(", String>", ">"), "String"));
+
+            if MASKED_COMMANDS.contains(&command.as_str()) {
+                // Masks its result itself, or returns another masked command's result
+                let masks = ["mask_result", "mask_profile", "mask_json", "ipc_payload::respond"]
+                    .iter()
+                    .any(|mask| body.contains(mask))
+                    || MASKED_COMMANDS
+                        .iter()
+                        .any(|other| other != command && calls(body, other));
+                if !masks {
+                    unmasked.push(format!("{} is listed as masked but never masks its result", command));
+                }
+            } else if sensitive && !UNMASKED_COMMANDS.contains(&command.as_str()) {
+                unmasked.push(format!(
+                    "{} returns {} without masking it in demo mode",
+                    command, returns
+                ));
+            }
+        }
+        assert!(unmasked.is_empty(), "{}", unmasked.join("\n"));
+    }
+
+    #[test]
+    fn test_mask_value_hides_urls_and_tokens() {
+        let settings = crate::remote_control::RemoteControlSettings {
+            enabled: true,
+            port: 8787,
+            pairing_token: "secret-token".to_string(),
+        };
+        let masked = mask_value(settings).unwrap();
+        assert_eq!(masked.port, 8787);
+        assert_eq!(masked.pairing_token, redact("secret-token"));
+
+        let urls = mask_value(vec!["rtmp://provider.example.com/live/1".to_string()]).unwrap();
+        assert!(!urls[0].contains("provider"));
+    }
+
+    #[test]
+    fn test_mask_value_keeps_flattened_listings() {
+        let listing: crate::content_cache::thumbnails::XtreamMovieListing = serde_json::from_value(serde_json::json!({
+            "stream_id": 7,
+            "name": "Movie",
+            "stream_icon": "http://provider.example.com/icon.png",
+            "direct_source": "http://provider.example.com/movie/alice/pw/7.mkv",
+            "rating": 7.5,
+            "cached_icon": "/cache/icon.png"
+        }))
+        .unwrap();
+
+        let masked = mask_value(Page::from_vec(vec![listing], None, None)).unwrap();
+        let movie = &masked.items[0];
+        assert_eq!(movie.movie.name, "Movie");
+        assert_eq!(movie.movie.rating, Some(7.5));
+        assert_eq!(movie.cached_icon.as_deref(), Some("/cache/icon.png"));
+        assert!(!movie.movie.direct_source.as_deref().unwrap().contains("alice"));
+        assert!(!movie.movie.stream_icon.as_deref().unwrap().contains("provider"));
+    }
+
+    #[test]
+    fn test_redacted_copy_hides_recording_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::recordings::create_table(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO recordings (id, name, stream_url, file_path, status, started_at)
+             VALUES ('r1', 'News HD', 'http://provider.example.com/live/alice/pw/1.ts', '/rec/news.ts', 'completed', 0)",
+            [],
+        )
+        .unwrap();
+
+        let dest = dir.path().join("redacted.sqlite");
+        create_redacted_copy(&conn, &dest).unwrap();
+
+        let copy = Connection::open(&dest).unwrap();
+        let (name, stream_url): (String, String) = copy
+            .query_row("SELECT name, stream_url FROM recordings", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(name, "News HD");
+        assert_eq!(stream_url, redact("http://provider.example.com/live/alice/pw/1.ts"));
+    }
+
+    #[test]
+    fn test_redacted_copy_hides_playlist_guide_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE channel_lists (id INTEGER PRIMARY KEY, name TEXT, source TEXT, filepath TEXT, epg_url TEXT);
+             INSERT INTO channel_lists VALUES (1, 'Home', 'http://provider.example.com/get.php', NULL,
+                                               'http://provider.example.com/xmltv.php?username=alice');",
+        )
+        .unwrap();
+
+        let dest = dir.path().join("redacted.sqlite");
+        create_redacted_copy(&conn, &dest).unwrap();
+
+        let copy = Connection::open(&dest).unwrap();
+        let (name, epg_url): (String, String) = copy
+            .query_row("SELECT name, epg_url FROM channel_lists", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(name, "Home");
+        assert_eq!(epg_url, redact("http://provider.example.com/xmltv.php?username=alice"));
+    }
+
+    #[test]
+    fn test_redacted_copy_hides_failed_playlist_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE playlist_link_failures (channel_list_id INTEGER, url TEXT, failed_at INTEGER);
+             INSERT INTO playlist_link_failures VALUES (1, 'http://provider.example.com/alice/pw/7.ts', 100);",
+        )
+        .unwrap();
+
+        let dest = dir.path().join("redacted.sqlite");
+        create_redacted_copy(&conn, &dest).unwrap();
+
+        let copy = Connection::open(&dest).unwrap();
+        let url: String = copy.query_row("SELECT url FROM playlist_link_failures", [], |row| row.get(0)).unwrap();
+        assert_eq!(url, redact("http://provider.example.com/alice/pw/7.ts"));
+    }
+
+    #[test]
+    fn test_redacted_copy_hides_cached_image_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        crate::images::create_index_table(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO image_cache_index (url, path) VALUES ('http://provider.example.com/images/alice/1.png', '/cache/1')",
+            [],
+        )
+        .unwrap();
+
+        let dest = dir.path().join("redacted.sqlite");
+        create_redacted_copy(&conn, &dest).unwrap();
+
+        let copy = Connection::open(&dest).unwrap();
+        let (url, path): (String, String) = copy
+            .query_row("SELECT url, path FROM image_cache_index", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(url, redact("http://provider.example.com/images/alice/1.png"));
+        assert_eq!(path, "/cache/1");
+    }
+
+    #[test]
+    fn test_redacted_copy_hides_image_decode_failure_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE image_decode_failures (url TEXT PRIMARY KEY, format TEXT, error TEXT, failures INTEGER, last_failed_at INTEGER);
+             INSERT INTO image_decode_failures VALUES ('http://provider.example.com/images/alice/2.avif', 'avif', 'bad', 1, 0);",
+        )
+        .unwrap();
+
+        let dest = dir.path().join("redacted.sqlite");
+        create_redacted_copy(&conn, &dest).unwrap();
+
+        let copy = Connection::open(&dest).unwrap();
+        let url: String = copy.query_row("SELECT url FROM image_decode_failures", [], |row| row.get(0)).unwrap();
+        assert_eq!(url, redact("http://provider.example.com/images/alice/2.avif"));
+    }
+
+    #[test]
+    fn test_redacted_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, name TEXT, url TEXT, username TEXT, encrypted_credentials BLOB);
+             INSERT INTO xtream_profiles VALUES ('p1', 'Home', 'http://provider.example.com', 'alice', X'0102');
+             CREATE TABLE channels (id INTEGER PRIMARY KEY, name TEXT, url TEXT);
+             INSERT INTO channels VALUES (1, 'News HD', 'http://provider.example.com/live/alice/pw/1.ts');
+             CREATE TABLE xtream_favorites (id TEXT PRIMARY KEY, content_data BLOB);",
+        )
+        .unwrap();
+        let favorite = serde_json::json!({ "name": "News HD", "direct_source": "http://provider.example.com/1.ts" });
+        conn.execute(
+            "INSERT INTO xtream_favorites VALUES ('f1', ?1)",
+            [serde_json::to_vec(&favorite).unwrap()],
+        )
+        .unwrap();
+
+        let dest = dir.path().join("redacted.sqlite");
+        create_redacted_copy(&conn, &dest).unwrap();
+        assert!(create_redacted_copy(&conn, &dest).is_err());
+
+        let copy = Connection::open(&dest).unwrap();
+        let (name, url, username, creds): (String, String, String, Vec<u8>) = copy
+            .query_row(
+                "SELECT name, url, username, encrypted_credentials FROM xtream_profiles",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(name, "Home");
+        assert_eq!(url, redact("http://provider.example.com"));
+        assert_eq!(username, redact("alice"));
+        assert!(creds.is_empty());
+
+        let (channel_name, channel_url): (String, String) = copy
+            .query_row("SELECT name, url FROM channels", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!(channel_name, "News HD");
+        assert!(!channel_url.contains("alice"));
+
+        let data: Vec<u8> = copy
+            .query_row("SELECT content_data FROM xtream_favorites", [], |row| row.get(0))
+            .unwrap();
+        let favorite: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(favorite["name"], "News HD");
+        assert_eq!(favorite["direct_source"], redact("http://provider.example.com/1.ts"));
+
+        // The source database is untouched
+        let original: String = conn
+            .query_row("SELECT username FROM xtream_profiles", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(original, "alice");
+    }
+}
@@ -0,0 +1,689 @@
+// Encrypted backup and restore
+//
+// A backup is a single file holding everything a user set up: Xtream profiles,
+// playlists with their downloaded files, favorites, history, settings and
+// saved filters. Content caches are left out; they come back with a sync.
+// Stored credentials are sealed with a key that never leaves this machine, so
+// they go into the archive decrypted and are encrypted again with the key of
+// the machine that restores it. The archive itself is gzipped JSON encrypted
+// with AES-256-CBC under a key derived from the user's passphrase (PBKDF2),
+// and authenticated with HMAC-SHA256 so a wrong passphrase or a damaged file
+// is rejected before anything is restored.
+use crate::error::{Result, XTauriError};
+use crate::state::{ChannelCacheState, DbState};
+use crate::xtream::types::ProfileCredentials;
+use crate::xtream::XtreamState;
+use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::Aes256;
+use base64::{engine::general_purpose, Engine as _};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac;
+use rand::{thread_rng, RngCore};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+const MAGIC: &[u8; 8] = b"XTAURIBK";
+const FORMAT_VERSION: u8 = 1;
+const KDF_ROUNDS: u32 = 200_000;
+/// Highest KDF round count a backup header may ask for, so a crafted file
+/// can't keep the importer busy for hours before the MAC check fails
+const MAX_KDF_ROUNDS: u32 = 10_000_000;
+const MIN_PASSPHRASE_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+const BLOCK_LEN: usize = 16;
+const MAC_LEN: usize = 32;
+/// Magic, format version, KDF rounds, salt and IV
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN + BLOCK_LEN;
+
+/// Tables in the backup, in the order they are restored
+const TABLES: &[&str] = &[
+    "settings",
+    "settings_profiles",
+    "ui_state",
+    "channel_lists",
+    "group_selections",
+    "saved_filters",
+    "history",
+    "xtream_profiles",
+    "xtream_favorites",
+    "xtream_history",
+    "xtream_saved_filters",
+    "xtream_content_tags",
+    "xtream_epg_reminders",
+    "xtream_channel_url_overrides",
+    "xtream_watchlist",
+    "xtream_followed_categories",
+];
+
+/// One SQLite value, in a form that survives JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+enum Cell {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    /// Base64
+    Blob(String),
+}
+
+impl From<Value> for Cell {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Cell::Null,
+            Value::Integer(i) => Cell::Integer(i),
+            Value::Real(f) => Cell::Real(f),
+            Value::Text(s) => Cell::Text(s),
+            Value::Blob(b) => Cell::Blob(general_purpose::STANDARD.encode(b)),
+        }
+    }
+}
+
+impl Cell {
+    fn into_value(self) -> Result<Value> {
+        Ok(match self {
+            Cell::Null => Value::Null,
+            Cell::Integer(i) => Value::Integer(i),
+            Cell::Real(f) => Value::Real(f),
+            Cell::Text(s) => Value::Text(s),
+            Cell::Blob(b) => Value::Blob(
+                general_purpose::STANDARD
+                    .decode(b)
+                    .map_err(|e| XTauriError::internal(format!("Invalid blob in backup: {}", e)))?,
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TableDump {
+    name: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl TableDump {
+    fn column(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileSecret {
+    profile_id: String,
+    credentials: ProfileCredentials,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PlaylistFile {
+    channel_list_id: i64,
+    /// Base64
+    content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Backup {
+    created_at: String,
+    app_version: String,
+    tables: Vec<TableDump>,
+    credentials: Vec<ProfileSecret>,
+    playlist_files: Vec<PlaylistFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct BackupTableCount {
+    pub table: String,
+    pub rows: u32,
+}
+
+/// What a backup holds, reported after it was written or restored
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct BackupSummary {
+    pub created_at: String,
+    /// Version of the app that wrote the backup
+    pub app_version: String,
+    pub tables: Vec<BackupTableCount>,
+    pub profiles: u32,
+    pub playlist_files: u32,
+}
+
+impl Backup {
+    fn summary(&self) -> BackupSummary {
+        BackupSummary {
+            created_at: self.created_at.clone(),
+            app_version: self.app_version.clone(),
+            tables: self
+                .tables
+                .iter()
+                .map(|table| BackupTableCount { table: table.name.clone(), rows: table.rows.len() as u32 })
+                .collect(),
+            profiles: self.credentials.len() as u32,
+            playlist_files: self.playlist_files.len() as u32,
+        }
+    }
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let exists = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns = stmt.query_map([table], |row| row.get(0))?.collect::<std::result::Result<_, _>>()?;
+    Ok(columns)
+}
+
+fn dump_table(conn: &Connection, table: &str) -> Result<TableDump> {
+    let columns = table_columns(conn, table)?;
+    let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\"", table))?;
+    let rows = stmt
+        .query_map([], |row| {
+            (0..columns.len()).map(|i| row.get::<_, Value>(i).map(Cell::from)).collect()
+        })?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(TableDump { name: table.to_string(), columns, rows })
+}
+
+/// Collect the backup; `credentials` looks up the decrypted credentials of a profile
+///
+/// Channel lists name their playlist file relative to `playlist_dir`.
+fn create_backup(
+    conn: &Connection,
+    playlist_dir: &Path,
+    mut credentials: impl FnMut(&str) -> Result<ProfileCredentials>,
+) -> Result<Backup> {
+    let mut backup = Backup {
+        created_at: chrono::Utc::now().to_rfc3339(),
+        app_version: crate::updates::current_version().to_string(),
+        tables: Vec::new(),
+        credentials: Vec::new(),
+        playlist_files: Vec::new(),
+    };
+
+    for table in TABLES {
+        if !table_exists(conn, table)? {
+            continue;
+        }
+        let mut dump = dump_table(conn, table)?;
+        match *table {
+            "xtream_profiles" => {
+                let (Some(id), Some(secret)) = (dump.column("id"), dump.column("encrypted_credentials")) else {
+                    return Err(XTauriError::internal("Unexpected xtream_profiles table".to_string()));
+                };
+                for row in &mut dump.rows {
+                    let Cell::Text(profile_id) = &row[id] else { continue };
+                    backup.credentials.push(ProfileSecret {
+                        profile_id: profile_id.clone(),
+                        credentials: credentials(profile_id)?,
+                    });
+                    // Sealed with this machine's key, useless anywhere else
+                    row[secret] = Cell::Text(String::new());
+                }
+            }
+            "channel_lists" => {
+                if let (Some(id), Some(filepath)) = (dump.column("id"), dump.column("filepath")) {
+                    for row in &dump.rows {
+                        let (Cell::Integer(channel_list_id), Cell::Text(path)) = (&row[id], &row[filepath]) else {
+                            continue;
+                        };
+                        match fs::read(playlist_dir.join(path)) {
+                            Ok(content) => backup.playlist_files.push(PlaylistFile {
+                                channel_list_id: *channel_list_id,
+                                content: general_purpose::STANDARD.encode(content),
+                            }),
+                            Err(e) => println!("[BACKUP] Skipping playlist file {}: {}", path, e),
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        backup.tables.push(dump);
+    }
+    Ok(backup)
+}
+
+/// Write a backup into this database
+///
+/// `encode` seals a profile's credentials the way `xtream_profiles` stores
+/// them, and playlist files are written into `playlist_dir` under new names
+/// that `channel_lists.filepath` then holds. Rows replace
+/// local ones with the same key; columns this release doesn't know are dropped.
+fn restore_backup(
+    conn: &Connection,
+    backup: Backup,
+    mut encode: impl FnMut(&ProfileCredentials) -> Result<String>,
+    playlist_dir: &Path,
+) -> Result<BackupSummary> {
+    let summary = backup.summary();
+
+    let mut playlist_paths: HashMap<i64, String> = HashMap::new();
+    if !backup.playlist_files.is_empty() {
+        fs::create_dir_all(playlist_dir)?;
+    }
+    for file in &backup.playlist_files {
+        let content = general_purpose::STANDARD
+            .decode(&file.content)
+            .map_err(|e| XTauriError::internal(format!("Invalid playlist file in backup: {}", e)))?;
+        let name = format!("{}.m3u", uuid::Uuid::new_v4());
+        fs::write(playlist_dir.join(&name), content)?;
+        playlist_paths.insert(file.channel_list_id, name);
+    }
+    let secrets: HashMap<&str, &ProfileCredentials> = backup
+        .credentials
+        .iter()
+        .map(|secret| (secret.profile_id.as_str(), &secret.credentials))
+        .collect();
+
+    let tx = conn.unchecked_transaction()?;
+    for mut dump in backup.tables.clone() {
+        if !TABLES.contains(&dump.name.as_str()) || !table_exists(&tx, &dump.name)? {
+            continue;
+        }
+        match dump.name.as_str() {
+            "xtream_profiles" => {
+                if let (Some(id), Some(secret)) = (dump.column("id"), dump.column("encrypted_credentials")) {
+                    for row in &mut dump.rows {
+                        let Cell::Text(profile_id) = &row[id] else { continue };
+                        let credentials = secrets.get(profile_id.as_str()).ok_or_else(|| {
+                            XTauriError::internal(format!("Backup has no credentials for profile {}", profile_id))
+                        })?;
+                        row[secret] = Cell::Text(encode(credentials)?);
+                    }
+                }
+            }
+            "channel_lists" => {
+                if let (Some(id), Some(filepath)) = (dump.column("id"), dump.column("filepath")) {
+                    let last_fetched = dump.column("last_fetched");
+                    for row in &mut dump.rows {
+                        let name = match &row[id] {
+                            Cell::Integer(channel_list_id) => playlist_paths.get(channel_list_id),
+                            _ => None,
+                        };
+                        match name {
+                            Some(name) => row[filepath] = Cell::Text(name.clone()),
+                            None => {
+                                // Fetched again on first use
+                                row[filepath] = Cell::Null;
+                                if let Some(last_fetched) = last_fetched {
+                                    row[last_fetched] = Cell::Null;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let local = table_columns(&tx, &dump.name)?;
+        let kept: Vec<usize> = (0..dump.columns.len()).filter(|&i| local.contains(&dump.columns[i])).collect();
+        if kept.is_empty() {
+            continue;
+        }
+        let sql = format!(
+            "INSERT OR REPLACE INTO \"{}\" ({}) VALUES ({})",
+            dump.name,
+            kept.iter().map(|&i| format!("\"{}\"", dump.columns[i])).collect::<Vec<_>>().join(", "),
+            vec!["?"; kept.len()].join(", ")
+        );
+        let mut stmt = tx.prepare(&sql)?;
+        for row in dump.rows {
+            let mut row: Vec<Option<Cell>> = row.into_iter().map(Some).collect();
+            let values = kept
+                .iter()
+                .map(|&i| row.get_mut(i).and_then(Option::take).unwrap_or(Cell::Null).into_value())
+                .collect::<Result<Vec<_>>>()?;
+            stmt.execute(params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+fn derive_keys(passphrase: &str, salt: &[u8], rounds: u32) -> ([u8; 32], [u8; 32]) {
+    let mut key = [0u8; 64];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+    let mut enc_key = [0u8; 32];
+    let mut mac_key = [0u8; 32];
+    enc_key.copy_from_slice(&key[..32]);
+    mac_key.copy_from_slice(&key[32..]);
+    key.fill(0);
+    (enc_key, mac_key)
+}
+
+fn mac(mac_key: &[u8; 32]) -> Hmac<Sha256> {
+    <Hmac<Sha256> as Mac>::new_from_slice(mac_key).expect("HMAC accepts any key length")
+}
+
+fn encrypt_archive(plain: &[u8], passphrase: &str, rounds: u32) -> Vec<u8> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut iv = [0u8; BLOCK_LEN];
+    thread_rng().fill_bytes(&mut salt);
+    thread_rng().fill_bytes(&mut iv);
+    let (mut enc_key, mac_key) = derive_keys(passphrase, &salt, rounds);
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plain.len() + BLOCK_LEN + MAC_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&rounds.to_be_bytes());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&iv);
+
+    // PKCS7, always at least one byte
+    let padding = BLOCK_LEN - plain.len() % BLOCK_LEN;
+    let mut data = plain.to_vec();
+    data.resize(data.len() + padding, padding as u8);
+
+    let cipher = Aes256::new(GenericArray::from_slice(&enc_key));
+    let mut previous = iv;
+    for chunk in data.chunks_mut(BLOCK_LEN) {
+        for (byte, prev) in chunk.iter_mut().zip(previous.iter()) {
+            *byte ^= prev;
+        }
+        let mut block = GenericArray::clone_from_slice(chunk);
+        cipher.encrypt_block(&mut block);
+        chunk.copy_from_slice(&block);
+        previous.copy_from_slice(chunk);
+    }
+    enc_key.fill(0);
+    out.extend_from_slice(&data);
+
+    let mut hmac = mac(&mac_key);
+    hmac.update(&out);
+    out.extend_from_slice(&hmac.finalize().into_bytes());
+    out
+}
+
+fn decrypt_archive(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let not_a_backup = || XTauriError::internal("Not an xTauri backup".to_string());
+    if data.len() < HEADER_LEN + BLOCK_LEN + MAC_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err(not_a_backup());
+    }
+    let version = data[MAGIC.len()];
+    if version != FORMAT_VERSION {
+        return Err(XTauriError::internal(format!(
+            "Backup format {} needs a newer version of the app",
+            version
+        )));
+    }
+    let mut offset = MAGIC.len() + 1;
+    let rounds = u32::from_be_bytes(data[offset..offset + 4].try_into().map_err(|_| not_a_backup())?);
+    offset += 4;
+    let salt = &data[offset..offset + SALT_LEN];
+    offset += SALT_LEN;
+    let iv = &data[offset..offset + BLOCK_LEN];
+    let (body, tag) = data.split_at(data.len() - MAC_LEN);
+    let ciphertext = &body[HEADER_LEN..];
+    if rounds == 0 || rounds > MAX_KDF_ROUNDS || ciphertext.len() % BLOCK_LEN != 0 {
+        return Err(not_a_backup());
+    }
+
+    let (mut enc_key, mac_key) = derive_keys(passphrase, salt, rounds);
+    let mut hmac = mac(&mac_key);
+    hmac.update(body);
+    if hmac.verify_slice(tag).is_err() {
+        enc_key.fill(0);
+        return Err(XTauriError::internal("Wrong passphrase or damaged backup".to_string()));
+    }
+
+    let cipher = Aes256::new(GenericArray::from_slice(&enc_key));
+    let mut plain = ciphertext.to_vec();
+    let mut previous = [0u8; BLOCK_LEN];
+    previous.copy_from_slice(iv);
+    for chunk in plain.chunks_mut(BLOCK_LEN) {
+        let mut original = [0u8; BLOCK_LEN];
+        original.copy_from_slice(chunk);
+        let mut block = GenericArray::clone_from_slice(chunk);
+        cipher.decrypt_block(&mut block);
+        for (byte, prev) in block.iter_mut().zip(previous.iter()) {
+            *byte ^= prev;
+        }
+        chunk.copy_from_slice(&block);
+        previous = original;
+    }
+    enc_key.fill(0);
+
+    let padding = *plain.last().ok_or_else(not_a_backup)? as usize;
+    if padding == 0 || padding > BLOCK_LEN || plain[plain.len() - padding..].iter().any(|&b| b as usize != padding) {
+        return Err(not_a_backup());
+    }
+    plain.truncate(plain.len() - padding);
+    Ok(plain)
+}
+
+fn seal(backup: &Backup, passphrase: &str, rounds: u32) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(backup).map_err(|e| XTauriError::internal(e.to_string()))?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    Ok(encrypt_archive(&encoder.finish()?, passphrase, rounds))
+}
+
+fn open(data: &[u8], passphrase: &str) -> Result<Backup> {
+    let compressed = decrypt_archive(data, passphrase)?;
+    let mut json = Vec::new();
+    GzDecoder::new(compressed.as_slice()).read_to_end(&mut json)?;
+    serde_json::from_slice(&json).map_err(|e| XTauriError::internal(format!("Damaged backup: {}", e)))
+}
+
+fn playlist_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join("xtauri/channel_lists"))
+        .ok_or_else(|| XTauriError::internal("No data directory".to_string()))
+}
+
+#[tauri::command]
+pub async fn export_app_backup(
+    db_state: State<'_, DbState>,
+    xtream_state: State<'_, XtreamState>,
+    path: String,
+    passphrase: String,
+) -> std::result::Result<BackupSummary, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!("The passphrase needs at least {} characters", MIN_PASSPHRASE_LEN));
+    }
+    let profile_manager = xtream_state.profile_manager.clone();
+    let playlist_dir = playlist_dir().map_err(|e| e.to_string())?;
+    let backup = {
+        let db = db_state.db.lock().map_err(|e| e.to_string())?;
+        create_backup(&db, &playlist_dir, |profile_id| profile_manager.get_profile_credentials(profile_id))
+            .map_err(|e| e.to_string())?
+    };
+    let data = seal(&backup, &passphrase, KDF_ROUNDS).map_err(|e| e.to_string())?;
+
+    // Never leave a half-written backup behind
+    let tmp = PathBuf::from(format!("{}.partial", path));
+    fs::write(&tmp, data).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    let summary = backup.summary();
+    println!(
+        "[BACKUP] Exported {} tables, {} profiles and {} playlist files to {}",
+        summary.tables.len(),
+        summary.profiles,
+        summary.playlist_files,
+        path
+    );
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn import_app_backup(
+    app_handle: AppHandle,
+    path: String,
+    passphrase: String,
+) -> std::result::Result<BackupSummary, String> {
+    let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let backup = open(&data, &passphrase).map_err(|e| e.to_string())?;
+    let profile_ids: Vec<String> = backup.credentials.iter().map(|secret| secret.profile_id.clone()).collect();
+
+    let profile_manager = match app_handle.try_state::<XtreamState>() {
+        Some(state) => Some(state.profile_manager.clone()),
+        None if profile_ids.is_empty() => None,
+        None => return Err("Xtream profiles are unavailable, the backup can't be restored".to_string()),
+    };
+    let playlist_dir = playlist_dir().map_err(|e| e.to_string())?;
+    let summary = {
+        let db_state = app_handle.state::<DbState>();
+        let db = db_state.db.lock().map_err(|e| e.to_string())?;
+        let summary = restore_backup(
+            &db,
+            backup,
+            |credentials| match &profile_manager {
+                Some(profile_manager) => profile_manager.encode_credentials(credentials),
+                None => Err(XTauriError::internal("Xtream profiles are unavailable".to_string())),
+            },
+            &playlist_dir,
+        )
+        .map_err(|e| e.to_string())?;
+        crate::startup::load_memory_settings(&db);
+        summary
+    };
+
+    if let Some(profile_manager) = &profile_manager {
+        for profile_id in &profile_ids {
+            if let Err(e) = profile_manager.forget_cached_credentials(profile_id) {
+                eprintln!("[BACKUP] Failed to clear cached credentials of {}: {}", profile_id, e);
+            }
+        }
+    }
+    crate::playlists::reload_watch_dir(&app_handle);
+    crate::channels::invalidate_channel_cache(app_handle.state::<ChannelCacheState>())?;
+
+    println!(
+        "[BACKUP] Restored {} tables, {} profiles and {} playlist files from {}",
+        summary.tables.len(),
+        summary.profiles,
+        summary.playlist_files,
+        path
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ROUNDS: u32 = 1_000;
+
+    const SCHEMA: &str = "
+        CREATE TABLE settings (id INTEGER PRIMARY KEY, player_command TEXT);
+        CREATE TABLE channel_lists (
+            id INTEGER PRIMARY KEY, name TEXT NOT NULL, source TEXT, filepath TEXT, last_fetched INTEGER
+        );
+        CREATE TABLE xtream_profiles (
+            id TEXT PRIMARY KEY, name TEXT NOT NULL, url TEXT NOT NULL, username TEXT NOT NULL,
+            encrypted_credentials BLOB NOT NULL
+        );
+        CREATE TABLE xtream_favorites (
+            id TEXT PRIMARY KEY, profile_id TEXT NOT NULL, content_type TEXT NOT NULL, content_id TEXT NOT NULL
+        );";
+
+    fn credentials(password: &str) -> ProfileCredentials {
+        ProfileCredentials {
+            url: "http://provider.example".to_string(),
+            username: "user".to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_archive_rejects_wrong_passphrase_and_tampering() {
+        let plain = b"sixteen byte msg".repeat(3);
+        let sealed = encrypt_archive(&plain, "correct horse", TEST_ROUNDS);
+        assert_eq!(decrypt_archive(&sealed, "correct horse").unwrap(), plain);
+        assert!(decrypt_archive(&sealed, "wrong horse").is_err());
+
+        let mut tampered = sealed.clone();
+        tampered[HEADER_LEN + 3] ^= 1;
+        assert!(decrypt_archive(&tampered, "correct horse").is_err());
+        assert!(decrypt_archive(b"not a backup at all", "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_archive_rejects_excessive_kdf_rounds() {
+        let plain = b"sixteen byte msg".to_vec();
+        let mut sealed = encrypt_archive(&plain, "correct horse", TEST_ROUNDS);
+        let offset = MAGIC.len() + 1;
+        sealed[offset..offset + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decrypt_archive(&sealed, "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_backup_restores_on_another_database() {
+        // Each side has its own data directory; `filepath` holds names inside it
+        let dir = tempfile::tempdir().unwrap();
+        let source_dir = dir.path().join("source");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(source_dir.join("original.m3u"), "#EXTM3U\n#EXTINF:-1,News\nhttp://example/news\n").unwrap();
+
+        let source = Connection::open_in_memory().unwrap();
+        source.execute_batch(SCHEMA).unwrap();
+        source
+            .execute_batch(
+                "INSERT INTO settings VALUES (1, 'mpv');
+                INSERT INTO channel_lists VALUES (1, 'Local', 'http://example/list.m3u', 'original.m3u', 100);
+                INSERT INTO channel_lists VALUES (2, 'Gone', 'http://example/gone.m3u', 'missing.m3u', 200);
+                INSERT INTO xtream_profiles VALUES ('p1', 'Home', 'http://provider.example', 'user', x'00ff');
+                INSERT INTO xtream_favorites VALUES ('f1', 'p1', 'movie', '42');",
+            )
+            .unwrap();
+
+        let backup = create_backup(&source, &source_dir, |profile_id| {
+            assert_eq!(profile_id, "p1");
+            Ok(credentials("secret"))
+        })
+        .unwrap();
+        let sealed = seal(&backup, "passphrase", TEST_ROUNDS).unwrap();
+        assert!(open(&sealed, "other passphrase").is_err());
+        let backup = open(&sealed, "passphrase").unwrap();
+
+        // The target is older and doesn't know the settings column yet
+        let target = Connection::open_in_memory().unwrap();
+        target.execute_batch(&SCHEMA.replace(", player_command TEXT", "")).unwrap();
+        let restored_dir = dir.path().join("restored");
+        let summary =
+            restore_backup(&target, backup, |c| Ok(format!("sealed:{}", c.password)), &restored_dir).unwrap();
+        assert_eq!(summary.profiles, 1);
+        assert_eq!(summary.playlist_files, 1);
+
+        let sealed_credentials: String = target
+            .query_row("SELECT encrypted_credentials FROM xtream_profiles WHERE id = 'p1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sealed_credentials, "sealed:secret");
+        let favorites: i64 = target.query_row("SELECT COUNT(*) FROM xtream_favorites", [], |row| row.get(0)).unwrap();
+        assert_eq!(favorites, 1);
+
+        let filepath: String =
+            target.query_row("SELECT filepath FROM channel_lists WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert!(!filepath.contains(std::path::MAIN_SEPARATOR));
+        assert!(fs::read_to_string(restored_dir.join(&filepath)).unwrap().contains("News"));
+
+        // The startup cleanup keeps the restored file and removes only real orphans
+        fs::write(restored_dir.join("orphan.m3u"), "#EXTM3U\n").unwrap();
+        crate::utils::cleanup_orphaned_files_in(&target, &restored_dir).unwrap();
+        assert!(restored_dir.join(&filepath).exists());
+        assert!(!restored_dir.join("orphan.m3u").exists());
+        let (filepath, last_fetched): (Option<String>, Option<i64>) = target
+            .query_row("SELECT filepath, last_fetched FROM channel_lists WHERE id = 2", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((filepath, last_fetched), (None, None));
+    }
+}
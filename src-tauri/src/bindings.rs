@@ -0,0 +1,287 @@
+// TypeScript definitions for the types commands take and return
+//
+// These types derive `specta::Type`. `cargo test` writes their definitions to
+// `src/types/bindings.ts`, so the frontend imports generated types instead of
+// keeping its own copies. Commit the regenerated file along with the Rust change.
+// Commands report errors as plain strings, so there is no error type to export.
+use crate::backup::{BackupSummary, BackupTableCount};
+use crate::build_features::BuildFeatures;
+use crate::cache_ttl::CacheTtls;
+use crate::channels::{ChannelListing, ChannelLoadingStatus};
+use crate::concurrency::{ConcurrencyLimits, Subsystem, SubsystemLoad};
+use crate::connection::ConnectionStrategy;
+use crate::content_cache::epg::{EpgChannelIcon, EpgGrid, EpgProgramme, XtreamChannelListing};
+use crate::content_cache::epg_mapping::{EpgMappingReport, EpgMappingSuggestion};
+use crate::content_cache::followed_categories::{FollowedCategory, FollowedCategoryUpdate};
+use crate::content_cache::failover::{PlaybackCandidate, PlaybackEvent};
+use crate::content_cache::genres::{GenreInfo, GenreMapping, ProfileGenre};
+use crate::content_cache::maintenance::{MaintenanceRun, MaintenanceWindow};
+use crate::content_cache::ordering::{ChannelOrderScope, ChannelSortMode};
+use crate::content_cache::popularity::VodSortMode;
+use crate::content_cache::preview::ProfilePreview;
+use crate::content_cache::search_syntax::{SearchMode, SearchSyntaxHelp};
+use crate::content_cache::similarity::{SimilarContentType, SimilarItem};
+use crate::content_cache::throttle::SyncLimits;
+use crate::content_cache::thumbnails::{XtreamMovieListing, XtreamSeriesListing};
+use crate::content_cache::watchlist::WatchlistItem;
+use crate::content_cache::{
+    ChannelListingOptions, ChannelUrlOverride, ProfileSyncProgress, SyncHistoryEntry, SyncProgress, SyncSettings,
+    VodListingOptions, XtreamChannel, XtreamMovie, XtreamSeries, XtreamSeriesDetails,
+};
+use crate::country::CountryCount;
+use crate::digest::{CategoryDigest, Digest, DigestSettings, ProfileDigest, SeriesDigest, StorageUsage};
+use crate::expiry::{ContentExpiry, ExpirySource};
+use crate::group_analysis::{GroupAction, GroupAnalysis, GroupStats, GroupSuggestion};
+use crate::image_formats::{ImageDecodeFailure, ImageFormat, ImageFormatSettings, TranscodeTarget};
+use crate::image_prefetch::{ImagePrefetchReport, ImagePrefetchSettings};
+use crate::images::{ImageBatch, ImageFetched, ImageGroup};
+use crate::ipc_payload::PayloadEncoding;
+use crate::m3u_parser::Channel;
+use crate::memory::{MemoryReport, TrimResult};
+use crate::notifications::{InboxNotification, NotificationKind, NotificationRoute};
+use crate::pagination::Page;
+use crate::parental::ParentalAuditEntry;
+use crate::playlists::{EncodingReport, M3uExportFilter, PlaylistFetchStatus, WatchImportEvent};
+use crate::post_processors::{PostProcessReport, PostProcessorInfo};
+use crate::power::{LowPowerMode, PowerState};
+use crate::profiling::ProfileDump;
+use crate::recordings::{Recording, RecordingRequest, RecordingStatus};
+use crate::remote_control::{RemoteControlSettings, RemotePlayRequest};
+use crate::search::{CacheKeyClassStats, CacheStats, SearchProgress};
+use crate::session_state::SessionState;
+use crate::settings_profiles::{SettingChange, SettingsProfile};
+use crate::startup::{StartupReport, StartupSubsystem, SubsystemFailure};
+use crate::state::ChannelList;
+use crate::storage_policy::StoragePolicy;
+use crate::tasks::BackgroundTask;
+use crate::transfer::BandwidthSourceStats;
+use crate::unified_search::{
+    SearchContentType, SearchGroup, SearchHit, SearchItem, SearchSource, UnifiedSearchResults,
+};
+use crate::updates::{UpdateInfo, UpdateSettings};
+use crate::user_config::{ConfigSource, ConfigValue, EffectiveConfig};
+use crate::xtream::archive_resume::ArchiveResume;
+use crate::xtream::automation::{
+    AutomationScript, CreateAutomationScriptRequest, ScriptRunResult, UpdateAutomationScriptRequest,
+};
+use crate::xtream::backlog::{BacklogGenre, BacklogStats};
+use crate::xtream::filter::{ChannelFilter, MovieFilter, SeriesFilter};
+use crate::xtream::reminders::{CreateEpgReminderRequest, XtreamEpgReminder};
+use crate::xtream::portable::{PortableGroup, PortableItem, ProfilePortables};
+use crate::xtream::profile_import::{ProfileImportReport, ProfileImportRow};
+use crate::xtream::rate_limit::RequestLimits;
+use crate::xtream::recording::ProviderMode;
+use crate::xtream::year_review::{BusiestDay, WatchTotal, YearInReview};
+use crate::xtream::saved_filters::{CreateSavedFilterRequest, SavedFilter, UpdateSavedFilterRequest};
+use crate::xtream::search::{ProviderSearchResults, SearchOptions, SearchResult, TieredSearchStart};
+use crate::xtream::search_history::{AddSearchHistoryRequest, SearchHistoryItem};
+use crate::xtream::startup_auth::AccountStatus;
+use crate::xtream::tags::XtreamContentTag;
+use crate::xtream::{
+    AddFavoriteRequest, AddHistoryRequest, AuthenticationResult, ConnectionTestResult,
+    ContinueWatchingItem, CreateProfileRequest, EpgBatchResult, FavoritesChange,
+    ProfileCredentials, ResolvedStreamUrl, SeriesProgress, UpdatePositionRequest,
+    UpdateProfileRequest, XtreamFavorite, XtreamHistory, XtreamProfile,
+};
+use specta::TypeCollection;
+
+/// Every type a command takes or returns, plus the event payloads
+fn command_types() -> TypeCollection {
+    let mut types = TypeCollection::default();
+    types
+        // Playlists and channels
+        .register::<Channel>()
+        .register::<ChannelList>()
+        .register::<ChannelListing>()
+        .register::<CountryCount>()
+        .register::<EncodingReport>()
+        .register::<M3uExportFilter>()
+        .register::<crate::database::SavedFilter>()
+        .register::<CacheStats>()
+        .register::<CacheKeyClassStats>()
+        .register::<SettingsProfile>()
+        .register::<SettingChange>()
+        .register::<PostProcessReport>()
+        .register::<PostProcessorInfo>()
+        // Xtream profiles
+        .register::<XtreamProfile>()
+        .register::<ProfileCredentials>()
+        .register::<CreateProfileRequest>()
+        .register::<ProfileImportRow>()
+        .register::<ProfileImportReport>()
+        .register::<UpdateProfileRequest>()
+        .register::<AuthenticationResult>()
+        .register::<ConnectionTestResult>()
+        .register::<AccountStatus>()
+        .register::<ProviderMode>()
+        .register::<ResolvedStreamUrl>()
+        .register::<EpgBatchResult>()
+        // Content cache
+        .register::<Page<XtreamChannel>>()
+        .register::<XtreamChannel>()
+        .register::<XtreamChannelListing>()
+        .register::<EpgProgramme>()
+        .register::<EpgGrid>()
+        .register::<EpgChannelIcon>()
+        .register::<XtreamMovie>()
+        .register::<XtreamSeries>()
+        .register::<XtreamMovieListing>()
+        .register::<XtreamSeriesListing>()
+        .register::<XtreamSeriesDetails>()
+        .register::<ChannelFilter>()
+        .register::<MovieFilter>()
+        .register::<SeriesFilter>()
+        .register::<ChannelListingOptions>()
+        .register::<VodListingOptions>()
+        .register::<ChannelSortMode>()
+        .register::<VodSortMode>()
+        .register::<ChannelOrderScope>()
+        .register::<ChannelUrlOverride>()
+        .register::<PlaybackCandidate>()
+        .register::<PlaybackEvent>()
+        .register::<ProfilePreview>()
+        .register::<SearchMode>()
+        .register::<SearchSyntaxHelp>()
+        .register::<SearchOptions>()
+        .register::<SearchResult>()
+        .register::<TieredSearchStart>()
+        .register::<ProviderSearchResults>()
+        .register::<SyncProgress>()
+        .register::<ProfileSyncProgress>()
+        .register::<SyncSettings>()
+        .register::<SyncHistoryEntry>()
+        .register::<MaintenanceWindow>()
+        .register::<MaintenanceRun>()
+        // Favorites, history and per-profile lists
+        .register::<XtreamFavorite>()
+        .register::<AddFavoriteRequest>()
+        .register::<XtreamHistory>()
+        .register::<AddHistoryRequest>()
+        .register::<UpdatePositionRequest>()
+        .register::<SeriesProgress>()
+        .register::<ContinueWatchingItem>()
+        .register::<SearchHistoryItem>()
+        .register::<AddSearchHistoryRequest>()
+        .register::<SavedFilter>()
+        .register::<CreateSavedFilterRequest>()
+        .register::<UpdateSavedFilterRequest>()
+        .register::<XtreamContentTag>()
+        .register::<XtreamEpgReminder>()
+        .register::<CreateEpgReminderRequest>()
+        .register::<YearInReview>()
+        .register::<WatchTotal>()
+        .register::<BusiestDay>()
+        .register::<BacklogStats>()
+        .register::<RequestLimits>()
+        .register::<EpgMappingReport>()
+        .register::<EpgMappingSuggestion>()
+        .register::<FollowedCategory>()
+        .register::<FollowedCategoryUpdate>()
+        .register::<WatchlistItem>()
+        .register::<BacklogGenre>()
+        .register::<AutomationScript>()
+        .register::<CreateAutomationScriptRequest>()
+        .register::<UpdateAutomationScriptRequest>()
+        .register::<ScriptRunResult>()
+        .register::<PortableItem>()
+        .register::<PortableGroup>()
+        .register::<ProfilePortables>()
+        // Application
+        .register::<ConnectionStrategy>()
+        .register::<RemoteControlSettings>()
+        .register::<UpdateSettings>()
+        .register::<UpdateInfo>()
+        .register::<BuildFeatures>()
+        .register::<ExpirySource>()
+        .register::<ContentExpiry>()
+        .register::<GroupAnalysis>()
+        .register::<GroupStats>()
+        .register::<GroupSuggestion>()
+        .register::<GroupAction>()
+        .register::<NotificationKind>()
+        .register::<InboxNotification>()
+        .register::<NotificationRoute>()
+        .register::<ParentalAuditEntry>()
+        .register::<SyncLimits>()
+        .register::<SimilarContentType>()
+        .register::<SimilarItem>()
+        .register::<ArchiveResume>()
+        .register::<GenreInfo>()
+        .register::<GenreMapping>()
+        .register::<ProfileGenre>()
+        .register::<StartupSubsystem>()
+        .register::<SubsystemFailure>()
+        .register::<StartupReport>()
+        .register::<SearchSource>()
+        .register::<SearchContentType>()
+        .register::<SearchItem>()
+        .register::<SearchHit>()
+        .register::<SearchGroup>()
+        .register::<UnifiedSearchResults>()
+        .register::<PayloadEncoding>()
+        .register::<ImageGroup>()
+        .register::<ImageBatch>()
+        .register::<BackgroundTask>()
+        .register::<Recording>()
+        .register::<RecordingRequest>()
+        .register::<RecordingStatus>()
+        .register::<BandwidthSourceStats>()
+        .register::<MemoryReport>()
+        .register::<TrimResult>()
+        .register::<SessionState>()
+        .register::<LowPowerMode>()
+        .register::<PowerState>()
+        .register::<StoragePolicy>()
+        .register::<Subsystem>()
+        .register::<ConcurrencyLimits>()
+        .register::<CacheTtls>()
+        .register::<DigestSettings>()
+        .register::<Digest>()
+        .register::<ProfileDigest>()
+        .register::<CategoryDigest>()
+        .register::<SeriesDigest>()
+        .register::<StorageUsage>()
+        .register::<SubsystemLoad>()
+        .register::<ProfileDump>()
+        .register::<ConfigSource>()
+        .register::<ConfigValue>()
+        .register::<EffectiveConfig>()
+        .register::<BackupSummary>()
+        .register::<BackupTableCount>()
+        // Event payloads (see events.rs)
+        .register::<SearchProgress>()
+        .register::<ChannelLoadingStatus>()
+        .register::<ImageFetched>()
+        .register::<ImageFormat>()
+        .register::<TranscodeTarget>()
+        .register::<ImageFormatSettings>()
+        .register::<ImageDecodeFailure>()
+        .register::<ImagePrefetchSettings>()
+        .register::<ImagePrefetchReport>()
+        .register::<PlaylistFetchStatus>()
+        .register::<WatchImportEvent>()
+        .register::<FavoritesChange>()
+        .register::<RemotePlayRequest>();
+    types
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specta_typescript::{BigIntExportBehavior, Typescript};
+
+    #[test]
+    fn test_export_bindings() {
+        // serde_json writes 64-bit integers as plain numbers; IDs and timestamps fit in one
+        let bindings = Typescript::default()
+            .bigint(BigIntExportBehavior::Number)
+            .export(&command_types())
+            .unwrap();
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../src/types/bindings.ts");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(bindings.as_str()) {
+            std::fs::write(&path, bindings).unwrap();
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// Optional subsystems compiled into this build
+//
+// Heavy features sit behind Cargo features so mobile builds can leave them
+// out (see `[features]` in Cargo.toml). The frontend asks for this list once
+// and hides the UI of anything that isn't there.
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct BuildFeatures {
+    pub recordings: bool,
+    /// User automation scripts
+    pub scripting: bool,
+    /// Database built on SQLCipher
+    pub sqlcipher: bool,
+    pub profiling: bool,
+    /// AVIF and HEIC logo decoding through libheif
+    pub avif: bool,
+    pub heic: bool,
+}
+
+impl BuildFeatures {
+    pub fn current() -> Self {
+        Self {
+            recordings: cfg!(feature = "recordings"),
+            scripting: cfg!(feature = "scripting"),
+            sqlcipher: cfg!(feature = "sqlcipher"),
+            profiling: crate::profiling::is_enabled(),
+            avif: cfg!(feature = "avif"),
+            heic: cfg!(feature = "heic"),
+        }
+    }
+}
+
+/// Get the optional features compiled into this build
+#[tauri::command]
+pub fn get_build_features() -> BuildFeatures {
+    BuildFeatures::current()
+}
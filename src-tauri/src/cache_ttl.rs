@@ -0,0 +1,245 @@
+// How long provider responses stay in the Xtream client's cache
+//
+// Each kind of response has its own time to live, kept in the `cache_ttl_*`
+// settings and mirrored here. `XtreamClient` asks for the TTL whenever it
+// caches a response, so a change applies to the next response cached; those
+// already in the cache keep the TTL they were stored with.
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+use tauri::State;
+
+/// Longest TTL a kind can be given: a week
+pub const MAX_TTL_MINUTES: u32 = 7 * 24 * 60;
+
+/// Kinds of provider responses with their own TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    /// Live, movie and series categories
+    Categories,
+    Channels,
+    Movies,
+    Series,
+    EpgShort,
+    EpgFull,
+    /// Movie and series details
+    Details,
+}
+
+/// Minutes each kind of response is cached for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct CacheTtls {
+    pub categories: u32,
+    pub channels: u32,
+    pub movies: u32,
+    pub series: u32,
+    pub epg_short: u32,
+    pub epg_full: u32,
+    pub details: u32,
+}
+
+impl Default for CacheTtls {
+    fn default() -> Self {
+        Self {
+            categories: 30,
+            channels: 10,
+            movies: 15,
+            series: 15,
+            epg_short: 15,
+            epg_full: 30,
+            details: 60,
+        }
+    }
+}
+
+impl CacheTtls {
+    fn minutes(&self, kind: CacheKind) -> u32 {
+        match kind {
+            CacheKind::Categories => self.categories,
+            CacheKind::Channels => self.channels,
+            CacheKind::Movies => self.movies,
+            CacheKind::Series => self.series,
+            CacheKind::EpgShort => self.epg_short,
+            CacheKind::EpgFull => self.epg_full,
+            CacheKind::Details => self.details,
+        }
+    }
+
+    /// Values by settings column
+    fn columns(&self) -> [(&'static str, u32); 7] {
+        [
+            ("cache_ttl_categories", self.categories),
+            ("cache_ttl_channels", self.channels),
+            ("cache_ttl_movies", self.movies),
+            ("cache_ttl_series", self.series),
+            ("cache_ttl_epg_short", self.epg_short),
+            ("cache_ttl_epg_full", self.epg_full),
+            ("cache_ttl_details", self.details),
+        ]
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self
+            .columns()
+            .iter()
+            .find(|(_, minutes)| !(1..=MAX_TTL_MINUTES).contains(minutes))
+        {
+            Some((key, minutes)) => Err(XTauriError::InvalidSetting {
+                key: key.to_string(),
+                value: minutes.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+static TTLS: LazyLock<RwLock<CacheTtls>> = LazyLock::new(|| RwLock::new(CacheTtls::default()));
+
+/// How long to cache a response of `kind`
+pub fn ttl(kind: CacheKind) -> Duration {
+    let minutes = TTLS
+        .read()
+        .map(|ttls| ttls.minutes(kind))
+        .unwrap_or_else(|_| CacheTtls::default().minutes(kind));
+    Duration::from_secs(u64::from(minutes) * 60)
+}
+
+fn current_ttls() -> CacheTtls {
+    TTLS.read().map(|ttls| *ttls).unwrap_or_default()
+}
+
+fn apply(ttls: CacheTtls) {
+    if let Ok(mut current) = TTLS.write() {
+        *current = ttls;
+    }
+}
+
+/// Add the TTL columns to the settings table, with the TTLs used before they were configurable
+pub fn migrate(conn: &Connection) -> Result<()> {
+    for (column, minutes) in CacheTtls::default().columns() {
+        conn.execute(
+            &format!(
+                "ALTER TABLE settings ADD COLUMN {} INTEGER NOT NULL DEFAULT {}",
+                column, minutes
+            ),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn read_ttls(conn: &Connection) -> Result<CacheTtls> {
+    let ttls = conn.query_row(
+        "SELECT cache_ttl_categories, cache_ttl_channels, cache_ttl_movies, cache_ttl_series,
+                cache_ttl_epg_short, cache_ttl_epg_full, cache_ttl_details
+         FROM settings WHERE id = 1",
+        [],
+        |row| {
+            let value = |index: usize| -> rusqlite::Result<u32> {
+                Ok(row.get::<_, i64>(index)?.clamp(1, i64::from(MAX_TTL_MINUTES)) as u32)
+            };
+            Ok(CacheTtls {
+                categories: value(0)?,
+                channels: value(1)?,
+                movies: value(2)?,
+                series: value(3)?,
+                epg_short: value(4)?,
+                epg_full: value(5)?,
+                details: value(6)?,
+            })
+        },
+    )?;
+    Ok(ttls)
+}
+
+fn store_ttls(conn: &Connection, ttls: &CacheTtls) -> Result<()> {
+    conn.execute(
+        "UPDATE settings SET cache_ttl_categories = ?1, cache_ttl_channels = ?2, cache_ttl_movies = ?3,
+                cache_ttl_series = ?4, cache_ttl_epg_short = ?5, cache_ttl_epg_full = ?6, cache_ttl_details = ?7
+         WHERE id = 1",
+        ttls.columns().map(|(_, minutes)| minutes),
+    )?;
+    Ok(())
+}
+
+/// Load the TTLs into memory; called at startup and when settings are switched
+pub fn load_settings(conn: &Connection) -> Result<CacheTtls> {
+    let ttls = read_ttls(conn)?;
+    apply(ttls);
+    Ok(ttls)
+}
+
+#[tauri::command]
+pub fn get_cache_ttls() -> CacheTtls {
+    current_ttls()
+}
+
+/// Change the TTLs; responses already cached keep theirs
+#[tauri::command]
+pub fn set_cache_ttls(state: State<DbState>, ttls: CacheTtls) -> std::result::Result<(), String> {
+    ttls.validate().map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    store_ttls(&db, &ttls).map_err(|e| e.to_string())?;
+    apply(ttls);
+    Ok(())
+}
+
+/// Go back to the default TTLs and return them
+#[tauri::command]
+pub fn reset_cache_ttls(state: State<DbState>) -> std::result::Result<CacheTtls, String> {
+    let ttls = CacheTtls::default();
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    store_ttls(&db, &ttls).map_err(|e| e.to_string())?;
+    apply(ttls);
+    Ok(ttls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_out_of_range_ttls() {
+        assert!(CacheTtls::default().validate().is_ok());
+
+        let zero = CacheTtls {
+            channels: 0,
+            ..CacheTtls::default()
+        };
+        match zero.validate() {
+            Err(XTauriError::InvalidSetting { key, value }) => {
+                assert_eq!(key, "cache_ttl_channels");
+                assert_eq!(value, "0");
+            }
+            other => panic!("expected an invalid setting, got {:?}", other),
+        }
+
+        let too_long = CacheTtls {
+            epg_full: MAX_TTL_MINUTES + 1,
+            ..CacheTtls::default()
+        };
+        assert!(too_long.validate().is_err());
+    }
+
+    #[test]
+    fn test_ttls_round_trip_through_settings() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE settings (id INTEGER PRIMARY KEY)", []).unwrap();
+        conn.execute("INSERT INTO settings (id) VALUES (1)", []).unwrap();
+        migrate(&conn).unwrap();
+        assert_eq!(read_ttls(&conn).unwrap(), CacheTtls::default());
+
+        let ttls = CacheTtls {
+            channels: 5,
+            details: 24 * 60,
+            ..CacheTtls::default()
+        };
+        store_ttls(&conn, &ttls).unwrap();
+        assert_eq!(read_ttls(&conn).unwrap(), ttls);
+        assert_eq!(ttls.minutes(CacheKind::Details), 24 * 60);
+    }
+}
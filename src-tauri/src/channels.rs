@@ -1,18 +1,116 @@
+use crate::anonymize;
+use crate::content_cache::epg::{self, NowPlaying};
+use crate::country::{self, CountryCount};
+use crate::events::{self, AppEvent};
+use crate::expiry;
+use crate::ipc_payload;
 use crate::m3u_parser::{self, Channel};
 use crate::m3u_parser_helpers::{get_m3u_content, parse_m3u_with_progress};
-use crate::search::clear_advanced_cache;
-use crate::state::{ChannelCache, ChannelCacheState, DbState};
+use crate::pagination::Page;
+use crate::search::{clear_advanced_cache, CacheKeyClassStats};
+use crate::state::{ChannelCache, ChannelCacheKey, ChannelCacheState, DbState};
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, MutexGuard};
-use tauri::{AppHandle, Emitter, State};
+use tauri::ipc::Response;
+use tauri::{AppHandle, State, Window};
 
 // Helper function for safe mutex locking with timeout
 fn lock_with_timeout<'a, T>(mutex: &'a Mutex<T>, resource_name: &str) -> Result<MutexGuard<'a, T>, String> {
     mutex.lock().map_err(|_| format!("Failed to acquire lock for {}", resource_name))
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Most listings kept in the channel cache; the least recently built are dropped first
+const MAX_CHANNEL_CACHE_ENTRIES: usize = 8;
+
+// Channel cache lookups, by key class
+static LIST_HITS: AtomicU64 = AtomicU64::new(0);
+static LIST_MISSES: AtomicU64 = AtomicU64::new(0);
+static VIEW_HITS: AtomicU64 = AtomicU64::new(0);
+static VIEW_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Order-independent fingerprint of a set of enabled groups
+fn group_fingerprint(groups: &HashSet<String>) -> u64 {
+    let mut sorted: Vec<&String> = groups.iter().collect();
+    sorted.sort();
+    let mut hasher = DefaultHasher::new();
+    sorted.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of the filters applied to a listing, 0 when there are none
+fn filter_hash(country: Option<&str>) -> u64 {
+    let Some(country) = country else {
+        return 0;
+    };
+    let mut hasher = DefaultHasher::new();
+    country::filter_code(country).hash(&mut hasher);
+    hasher.finish().max(1)
+}
+
+/// Look up a listing, counting the hit or miss for its key class
+fn cache_lookup(cache: &HashMap<ChannelCacheKey, ChannelCache>, key: &ChannelCacheKey) -> Option<Vec<Channel>> {
+    let found = cache.get(key).map(|cached| cached.channels.clone());
+    let counter = match (key.class(), found.is_some()) {
+        ("list", true) => &LIST_HITS,
+        ("list", false) => &LIST_MISSES,
+        (_, true) => &VIEW_HITS,
+        (_, false) => &VIEW_MISSES,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+    found
+}
+
+/// Store a listing, replacing views of the same list built for another group selection
+fn cache_store(cache: &mut HashMap<ChannelCacheKey, ChannelCache>, key: ChannelCacheKey, channels: Vec<Channel>) {
+    if key.groups.is_some() {
+        cache.retain(|cached, _| {
+            cached.channel_list_id != key.channel_list_id || cached.groups.is_none() || cached.groups == key.groups
+        });
+    }
+    cache.insert(
+        key,
+        ChannelCache {
+            channels,
+            last_updated: SystemTime::now(),
+        },
+    );
+
+    while cache.len() > MAX_CHANNEL_CACHE_ENTRIES {
+        let oldest = cache
+            .iter()
+            .filter(|(cached, _)| **cached != key)
+            .min_by_key(|(_, cached)| cached.last_updated)
+            .map(|(cached, _)| *cached);
+        match oldest {
+            Some(oldest) => cache.remove(&oldest),
+            None => break,
+        };
+    }
+}
+
+/// Hit and miss counts of the channel cache, by key class
+pub fn channel_cache_stats() -> Vec<CacheKeyClassStats> {
+    vec![
+        CacheKeyClassStats {
+            class: "list".to_string(),
+            hits: LIST_HITS.load(Ordering::Relaxed),
+            misses: LIST_MISSES.load(Ordering::Relaxed),
+        },
+        CacheKeyClassStats {
+            class: "view".to_string(),
+            hits: VIEW_HITS.load(Ordering::Relaxed),
+            misses: VIEW_MISSES.load(Ordering::Relaxed),
+        },
+    ]
+}
+
+#[derive(Clone, Serialize, Deserialize, specta::Type)]
 pub struct ChannelLoadingStatus {
     pub progress: f32,
     pub message: String,
@@ -20,13 +118,82 @@ pub struct ChannelLoadingStatus {
     pub is_complete: bool,
 }
 
+/// A channel as listed to the UI, optionally with the programme airing now
+#[derive(Clone, Serialize, Deserialize, specta::Type)]
+pub struct ChannelListing {
+    #[serde(flatten)]
+    pub channel: Channel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub now_playing: Option<NowPlaying>,
+}
+
 #[tauri::command]
 pub fn get_channels(
+    window: Window,
     db_state: State<DbState>,
     cache_state: State<ChannelCacheState>,
     id: Option<i32>,
-) -> std::result::Result<Vec<Channel>, String> {
-    get_cached_channels(db_state, cache_state, id)
+    include_now_playing: Option<bool>,
+    country: Option<String>,
+) -> std::result::Result<Response, String> {
+    ipc_payload::respond(&window, &channel_listings(db_state, cache_state, id, include_now_playing, country)?)
+}
+
+fn channel_listings(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    id: Option<i32>,
+    include_now_playing: Option<bool>,
+    country: Option<String>,
+) -> std::result::Result<Vec<ChannelListing>, String> {
+    let channels = get_cached_channels(db_state.clone(), cache_state, id)?;
+    let channels = filter_by_country(channels, country.as_deref());
+
+    // Guide data comes from the Xtream EPG store, matched on the channel's tvg-id
+    let airing = if include_now_playing.unwrap_or(false) {
+        let db = db_state.pool.read().map_err(|e| e.to_string())?;
+        epg::now_playing_by_channel(&db, None, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?
+    } else {
+        HashMap::new()
+    };
+
+    Ok(channels
+        .into_iter()
+        .map(|channel| ChannelListing {
+            now_playing: airing.get(&channel.tvg_id).cloned(),
+            channel,
+        })
+        .collect())
+}
+
+/// One page of a playlist's channels, with the playlist's channel count
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn get_channels_paginated(
+    window: Window,
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    id: Option<i32>,
+    include_now_playing: Option<bool>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    country: Option<String>,
+) -> std::result::Result<Response, String> {
+    let channels = channel_listings(db_state, cache_state, id, include_now_playing, country)?;
+    ipc_payload::respond(&window, &Page::from_vec(channels, limit, offset))
+}
+
+/// Whether a list's channels are hidden because it expired; `None` is the default list
+///
+/// Expired trial lists keep their channels but list none. Only looks the list
+/// up when some list has expired.
+pub(crate) fn list_expired(db_state: &DbState, id: Option<i32>) -> Result<bool, String> {
+    if !expiry::has_hidden_lists() {
+        return Ok(false);
+    }
+    let db = db_state.pool.read().map_err(|e| e.to_string())?;
+    Ok(expiry::list_hidden(&db, id))
 }
 
 #[tauri::command]
@@ -35,14 +202,16 @@ pub fn get_cached_channels(
     cache_state: State<ChannelCacheState>,
     id: Option<i32>,
 ) -> std::result::Result<Vec<Channel>, String> {
+    if list_expired(&db_state, id)? {
+        return Ok(Vec::new());
+    }
+
     let mut cache = lock_with_timeout(&cache_state.cache, "channel_cache")?;
+    let key = ChannelCacheKey::list(id);
 
-    // Check if we have valid cache
-    if let Some(ref cached) = *cache {
-        if cached.channel_list_id == id {
-            // Cache hit - return a clone of cached channels to keep original pristine
-            return Ok(cached.channels.clone());
-        }
+    // Cache hit - return a clone of cached channels to keep original pristine
+    if let Some(channels) = cache_lookup(&cache, &key) {
+        return Ok(channels);
     }
 
     // Cache miss - load channels and update cache
@@ -52,20 +221,47 @@ pub fn get_cached_channels(
     println!("Loaded {} channels for list {:?}", channels.len(), id);
 
     // Store original channels in cache for future use
-    *cache = Some(ChannelCache {
-        channel_list_id: id,
-        channels: channels.clone(), // Store a copy in cache
-        last_updated: SystemTime::now(),
-    });
+    cache_store(&mut cache, key, channels.clone());
 
     // Return a clone to keep the cached original untouched
     Ok(channels)
 }
 
+/// Keep the channels from one country; `country` may be an ISO code, a tag like "UK" or a name
+pub fn filter_by_country(channels: Vec<Channel>, country: Option<&str>) -> Vec<Channel> {
+    let Some(country) = country else {
+        return channels;
+    };
+    let code = country::filter_code(country);
+    channels.into_iter().filter(|channel| channel.country == code).collect()
+}
+
+/// Countries detected in a playlist, with the number of channels from each, most channels first
+#[tauri::command]
+pub fn get_channel_countries(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    id: Option<i32>,
+) -> std::result::Result<Vec<CountryCount>, String> {
+    let channels = get_cached_channels(db_state, cache_state, id)?;
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for channel in channels.iter().filter(|channel| !channel.country.is_empty()) {
+        *counts.entry(channel.country.as_str()).or_default() += 1;
+    }
+
+    let mut countries: Vec<CountryCount> = counts
+        .into_iter()
+        .map(|(code, channels)| CountryCount::new(code, channels))
+        .collect();
+    countries.sort_by(|a, b| b.channels.cmp(&a.channels).then_with(|| a.name.cmp(&b.name)));
+    Ok(countries)
+}
+
 #[tauri::command]
 pub fn invalidate_channel_cache(cache_state: State<ChannelCacheState>) -> Result<(), String> {
     let mut cache = cache_state.cache.lock().unwrap();
-    *cache = None;
+    cache.clear();
 
     // Also clear search cache since channel data has changed
     clear_advanced_cache();
@@ -73,46 +269,151 @@ pub fn invalidate_channel_cache(cache_state: State<ChannelCacheState>) -> Result
     Ok(())
 }
 
-
+/// Drop the cached group views of a list, keeping its parsed channels
+///
+/// Views of the default list are dropped too, as it may be the same list.
+pub fn invalidate_group_views(cache_state: &ChannelCacheState, channel_list_id: i64) -> Result<(), String> {
+    let mut cache = lock_with_timeout(&cache_state.cache, "channel_cache")?;
+    let list_id = Some(channel_list_id as i32);
+    cache.retain(|key, _| key.groups.is_none() || (key.channel_list_id != list_id && key.channel_list_id.is_some()));
+    Ok(())
+}
 
 // NEW ASYNC COMMANDS
+/// Load a list's channels, optionally limited to its enabled groups and a country
+///
+/// Each combination is cached under its own key, which includes a fingerprint
+/// of the enabled groups, so toggling a group leaves the parsed list and other
+/// lists cached.
 #[tauri::command]
 pub async fn get_channels_async(
     app_handle: AppHandle,
     db_state: State<'_, DbState>,
     cache_state: State<'_, ChannelCacheState>,
     id: Option<i32>,
+    enabled_groups_only: Option<bool>,
+    country: Option<String>,
 ) -> Result<Vec<Channel>, String> {
     // Emit loading start
-    let _ = app_handle.emit(
-        "channel_loading",
-        ChannelLoadingStatus {
+    events::emit(
+        &app_handle,
+        AppEvent::ChannelLoading(ChannelLoadingStatus {
             progress: 0.0,
             message: "Starting to load channels...".to_string(),
             channel_count: None,
             is_complete: false,
-        },
+        }),
     );
 
+    if list_expired(&db_state, id)? {
+        events::emit(
+            &app_handle,
+            AppEvent::ChannelLoading(ChannelLoadingStatus {
+                progress: 1.0,
+                message: "Channel list has expired".to_string(),
+                channel_count: Some(0),
+                is_complete: true,
+            }),
+        );
+        return Ok(Vec::new());
+    }
+
+    let enabled_groups = if enabled_groups_only.unwrap_or(false) {
+        let db = db_state.pool.read().map_err(|e| e.to_string())?;
+        Some(enabled_groups_of(&db, id)?)
+    } else {
+        None
+    };
+    let key = ChannelCacheKey {
+        channel_list_id: id,
+        groups: enabled_groups.as_ref().map(group_fingerprint),
+        filter: filter_hash(country.as_deref()),
+    };
+
     // Check cache first (fast operation)
-    {
+    let cached_list = {
         let cache = cache_state.cache.lock().unwrap();
-        if let Some(ref cached) = *cache {
-            if cached.channel_list_id == id {
-                let _ = app_handle.emit(
-                    "channel_loading",
-                    ChannelLoadingStatus {
-                        progress: 1.0,
-                        message: "Loaded from cache instantly!".to_string(),
-                        channel_count: Some(cached.channels.len()),
-                        is_complete: true,
-                    },
-                );
-                return Ok(cached.channels.clone());
-            }
+        if let Some(channels) = cache_lookup(&cache, &key) {
+            events::emit(
+                &app_handle,
+                AppEvent::ChannelLoading(ChannelLoadingStatus {
+                    progress: 1.0,
+                    message: "Loaded from cache instantly!".to_string(),
+                    channel_count: Some(channels.len()),
+                    is_complete: true,
+                }),
+            );
+            return anonymize::mask_result(channels);
         }
-    }
 
+        // A view can still be built from the cached list
+        if key.class() == "view" {
+            cache_lookup(&cache, &ChannelCacheKey::list(id))
+        } else {
+            None
+        }
+    };
+
+    let channels = match cached_list {
+        Some(channels) => channels,
+        None => parse_list(&app_handle, &db_state, &cache_state, id).await?,
+    };
+
+    let channels = if key.class() == "view" {
+        let view: Vec<Channel> = filter_by_country(channels, country.as_deref())
+            .into_iter()
+            .filter(|channel| {
+                enabled_groups
+                    .as_ref()
+                    .is_none_or(|groups| groups.contains(&channel.group_title))
+            })
+            .collect();
+        let mut cache = cache_state.cache.lock().unwrap();
+        cache_store(&mut cache, key, view.clone());
+        view
+    } else {
+        channels
+    };
+
+    // Emit completion
+    events::emit(
+        &app_handle,
+        AppEvent::ChannelLoading(ChannelLoadingStatus {
+            progress: 1.0,
+            message: "Channels loaded successfully!".to_string(),
+            channel_count: Some(channels.len()),
+            is_complete: true,
+        }),
+    );
+
+    anonymize::mask_result(channels)
+}
+
+/// Enabled groups of a list; `None` is the default list
+fn enabled_groups_of(db: &Connection, id: Option<i32>) -> Result<HashSet<String>, String> {
+    let list_id = match id {
+        Some(id) => Some(id as i64),
+        None => db
+            .query_row("SELECT id FROM channel_lists WHERE is_default = 1", [], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?,
+    };
+    let Some(list_id) = list_id else {
+        return Ok(HashSet::new());
+    };
+    Ok(crate::database::get_enabled_groups(db, list_id)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect())
+}
+
+/// Parse a list in the background, reporting progress, and cache its channels
+async fn parse_list(
+    app_handle: &AppHandle,
+    db_state: &State<'_, DbState>,
+    cache_state: &State<'_, ChannelCacheState>,
+    id: Option<i32>,
+) -> Result<Vec<Channel>, String> {
     // Get the file content on the main thread (database operations are fast)
     let m3u_content = {
         let mut db = db_state.db.lock().unwrap();
@@ -125,14 +426,14 @@ pub async fn get_channels_async(
     // Move only the heavy parsing to background thread
     let channels = tokio::task::spawn_blocking(move || {
         parse_m3u_with_progress(&m3u_content, |progress, message, count| {
-            let _ = app_handle_clone.emit(
-                "channel_loading",
-                ChannelLoadingStatus {
+            events::emit(
+                &app_handle_clone,
+                AppEvent::ChannelLoading(ChannelLoadingStatus {
                     progress,
                     message,
                     channel_count: if count > 0 { Some(count) } else { None },
                     is_complete: false,
-                },
+                }),
             );
         })
     })
@@ -142,26 +443,97 @@ pub async fn get_channels_async(
     // Update cache with new channels
     {
         let mut cache = cache_state.cache.lock().unwrap();
-        *cache = Some(ChannelCache {
-            channel_list_id: id,
-            channels: channels.clone(),
-            last_updated: SystemTime::now(),
-        });
+        cache_store(&mut cache, ChannelCacheKey::list(id), channels.clone());
     }
 
     // Clear search cache since channel data has changed
     clear_advanced_cache();
 
-    // Emit completion
-    let _ = app_handle.emit(
-        "channel_loading",
-        ChannelLoadingStatus {
-            progress: 1.0,
-            message: "Channels loaded successfully!".to_string(),
-            channel_count: Some(channels.len()),
-            is_complete: true,
-        },
-    );
-
     Ok(channels)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(name: &str, group: &str) -> Channel {
+        Channel {
+            name: name.to_string(),
+            logo: String::new(),
+            url: format!("http://example.com/{}", name),
+            group_title: group.to_string(),
+            tvg_id: String::new(),
+            resolution: String::new(),
+            extra_info: String::new(),
+            country: String::new(),
+            country_flag: String::new(),
+        }
+    }
+
+    fn groups(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_group_fingerprint_ignores_order() {
+        assert_eq!(group_fingerprint(&groups(&["News", "Sports"])), group_fingerprint(&groups(&["Sports", "News"])));
+        assert_ne!(group_fingerprint(&groups(&["News"])), group_fingerprint(&groups(&["News", "Sports"])));
+        assert_eq!(filter_hash(None), 0);
+        assert_ne!(filter_hash(Some("uk")), 0);
+    }
+
+    #[test]
+    fn test_group_toggle_only_replaces_views_of_that_list() {
+        let mut cache = HashMap::new();
+        let view = |list: i32, enabled: &[&str]| ChannelCacheKey {
+            channel_list_id: Some(list),
+            groups: Some(group_fingerprint(&groups(enabled))),
+            filter: 0,
+        };
+        cache_store(&mut cache, ChannelCacheKey::list(Some(1)), vec![channel("a", "News"), channel("b", "Sports")]);
+        cache_store(&mut cache, view(1, &["News"]), vec![channel("a", "News")]);
+        cache_store(&mut cache, view(2, &["News"]), vec![channel("c", "News")]);
+
+        // Enabling Sports on list 1 builds a new view and drops its old one only
+        cache_store(&mut cache, view(1, &["News", "Sports"]), vec![channel("a", "News"), channel("b", "Sports")]);
+        assert!(cache.contains_key(&ChannelCacheKey::list(Some(1))));
+        assert!(!cache.contains_key(&view(1, &["News"])));
+        assert!(cache.contains_key(&view(1, &["News", "Sports"])));
+        assert!(cache.contains_key(&view(2, &["News"])));
+
+        let state = ChannelCacheState { cache: Mutex::new(cache) };
+        invalidate_group_views(&state, 2).unwrap();
+        let cache = state.cache.lock().unwrap();
+        assert!(!cache.contains_key(&view(2, &["News"])));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_cache_keeps_most_recent_listings() {
+        let mut cache = HashMap::new();
+        for list in 0..(MAX_CHANNEL_CACHE_ENTRIES as i32 + 2) {
+            cache_store(&mut cache, ChannelCacheKey::list(Some(list)), Vec::new());
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        assert_eq!(cache.len(), MAX_CHANNEL_CACHE_ENTRIES);
+        assert!(!cache.contains_key(&ChannelCacheKey::list(Some(0))));
+        assert!(cache.contains_key(&ChannelCacheKey::list(Some(MAX_CHANNEL_CACHE_ENTRIES as i32 + 1))));
+    }
+
+    #[test]
+    fn test_expired_list_lists_no_channels() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE channel_lists (id INTEGER PRIMARY KEY, is_default BOOLEAN NOT NULL DEFAULT 0);
+             INSERT INTO channel_lists VALUES (9001, 1), (9002, 0);",
+        )
+        .unwrap();
+        let pool = std::sync::Arc::new(crate::db_pool::DbPool::shared(std::sync::Arc::new(Mutex::new(conn))));
+        let state = DbState { db: pool.writer(), pool };
+        let _guard = expiry::expire_for_test(expiry::ExpirySource::ChannelList, "9001");
+
+        assert!(list_expired(&state, Some(9001)).unwrap());
+        assert!(list_expired(&state, None).unwrap());
+        assert!(!list_expired(&state, Some(9002)).unwrap());
+    }
+}
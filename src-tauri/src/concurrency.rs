@@ -0,0 +1,378 @@
+// Per-subsystem command concurrency limits
+//
+// A frontend loop gone wrong can fire hundreds of image, search, sync or EPG
+// calls at once. Commands in those subsystems take a slot from the
+// subsystem's gate before doing any work; calls beyond the limit wait for a
+// slot, and once more than `max_queued` calls are waiting, new ones fail
+// straight away with `XTauriError::Overloaded` so the frontend can back off.
+// The limits are kept in the `concurrency_*` settings and mirrored here.
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use tauri::State;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Highest limit a subsystem or its queue can be given
+pub const MAX_LIMIT: usize = 256;
+
+/// Groups of commands that are limited together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Images,
+    Search,
+    Sync,
+    Epg,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 4] = [Subsystem::Images, Subsystem::Search, Subsystem::Sync, Subsystem::Epg];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Subsystem::Images => "images",
+            Subsystem::Search => "search",
+            Subsystem::Sync => "sync",
+            Subsystem::Epg => "epg",
+        }
+    }
+
+    fn gate(self) -> &'static Gate {
+        &GATES[self as usize]
+    }
+}
+
+/// How many calls each subsystem runs at once, and how many may wait
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct ConcurrencyLimits {
+    pub images: usize,
+    pub search: usize,
+    pub sync: usize,
+    pub epg: usize,
+    /// Calls that may wait for a slot in each subsystem before new ones are rejected
+    pub max_queued: usize,
+}
+
+impl Default for ConcurrencyLimits {
+    fn default() -> Self {
+        Self {
+            images: 8,
+            search: 4,
+            sync: 2,
+            epg: 4,
+            max_queued: 32,
+        }
+    }
+}
+
+impl ConcurrencyLimits {
+    fn limit(&self, subsystem: Subsystem) -> usize {
+        match subsystem {
+            Subsystem::Images => self.images,
+            Subsystem::Search => self.search,
+            Subsystem::Sync => self.sync,
+            Subsystem::Epg => self.epg,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let values = [
+            ("concurrency_images", self.images),
+            ("concurrency_search", self.search),
+            ("concurrency_sync", self.sync),
+            ("concurrency_epg", self.epg),
+            ("concurrency_max_queued", self.max_queued),
+        ];
+        match values.iter().find(|(_, value)| !(1..=MAX_LIMIT).contains(value)) {
+            Some((key, value)) => Err(XTauriError::InvalidSetting {
+                key: key.to_string(),
+                value: value.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How busy a subsystem is
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct SubsystemLoad {
+    pub subsystem: Subsystem,
+    pub limit: usize,
+    pub running: usize,
+    pub queued: usize,
+    /// Calls rejected as overloaded since startup
+    pub rejected: u64,
+}
+
+struct Gate {
+    semaphore: Arc<Semaphore>,
+    limit: AtomicUsize,
+    /// Slots to retire as they are released after the limit was lowered
+    excess: AtomicUsize,
+    running: AtomicUsize,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+impl Gate {
+    fn new(limit: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: AtomicUsize::new(limit),
+            excess: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    fn set_limit(&self, limit: usize) {
+        let previous = self.limit.swap(limit, Ordering::SeqCst);
+        if limit > previous {
+            let mut grow = limit - previous;
+            // Slots still owed from an earlier reduction are cancelled first
+            while grow > 0 {
+                let excess = self.excess.load(Ordering::SeqCst);
+                if excess == 0 {
+                    break;
+                }
+                let cancel = excess.min(grow);
+                if self
+                    .excess
+                    .compare_exchange(excess, excess - cancel, Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+                {
+                    grow -= cancel;
+                }
+            }
+            self.semaphore.add_permits(grow);
+        } else if limit < previous {
+            let shrink = previous - limit;
+            let forgotten = self.semaphore.forget_permits(shrink);
+            // Slots in use are retired as their calls finish
+            self.excess.fetch_add(shrink - forgotten, Ordering::SeqCst);
+        }
+    }
+
+    fn retire_excess(&self) -> bool {
+        self.excess
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |excess| excess.checked_sub(1))
+            .is_ok()
+    }
+}
+
+static GATES: LazyLock<[Gate; 4]> = LazyLock::new(|| {
+    let limits = ConcurrencyLimits::default();
+    Subsystem::ALL.map(|subsystem| Gate::new(limits.limit(subsystem)))
+});
+
+/// Mirrors the `concurrency_max_queued` setting
+static MAX_QUEUED: AtomicUsize = AtomicUsize::new(32);
+
+/// A slot in a subsystem, held for the length of a command
+pub struct Slot {
+    gate: &'static Gate,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.gate.running.fetch_sub(1, Ordering::SeqCst);
+        if let Some(permit) = self.permit.take() {
+            if self.gate.retire_excess() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+/// Counts a caller as waiting until it gets a slot or gives up
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Take a slot in a subsystem, waiting for one if they are all in use
+///
+/// Fails with `XTauriError::Overloaded` when the subsystem's queue is full.
+pub async fn acquire(subsystem: Subsystem) -> Result<Slot> {
+    let gate = subsystem.gate();
+    let permit = match gate.semaphore.clone().try_acquire_owned() {
+        Ok(permit) => permit,
+        Err(_) => {
+            let queued = gate.queued.fetch_add(1, Ordering::SeqCst);
+            let _waiting = Waiting(&gate.queued);
+            if queued >= MAX_QUEUED.load(Ordering::Relaxed) {
+                gate.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(XTauriError::overloaded(subsystem.as_str(), queued));
+            }
+            gate.semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|_| XTauriError::internal("Concurrency gate closed"))?
+        }
+    };
+    gate.running.fetch_add(1, Ordering::SeqCst);
+    Ok(Slot {
+        gate,
+        permit: Some(permit),
+    })
+}
+
+fn apply(limits: &ConcurrencyLimits) {
+    for subsystem in Subsystem::ALL {
+        subsystem.gate().set_limit(limits.limit(subsystem));
+    }
+    MAX_QUEUED.store(limits.max_queued, Ordering::Relaxed);
+}
+
+fn current_limits() -> ConcurrencyLimits {
+    let limit = |subsystem: Subsystem| subsystem.gate().limit.load(Ordering::SeqCst);
+    ConcurrencyLimits {
+        images: limit(Subsystem::Images),
+        search: limit(Subsystem::Search),
+        sync: limit(Subsystem::Sync),
+        epg: limit(Subsystem::Epg),
+        max_queued: MAX_QUEUED.load(Ordering::Relaxed),
+    }
+}
+
+fn read_limits(conn: &Connection) -> Result<ConcurrencyLimits> {
+    let limits = conn.query_row(
+        "SELECT concurrency_images, concurrency_search, concurrency_sync, concurrency_epg,
+                concurrency_max_queued
+         FROM settings WHERE id = 1",
+        [],
+        |row| {
+            let value = |index: usize| -> rusqlite::Result<usize> {
+                Ok(row.get::<_, i64>(index)?.clamp(1, MAX_LIMIT as i64) as usize)
+            };
+            Ok(ConcurrencyLimits {
+                images: value(0)?,
+                search: value(1)?,
+                sync: value(2)?,
+                epg: value(3)?,
+                max_queued: value(4)?,
+            })
+        },
+    )?;
+    Ok(limits)
+}
+
+/// Load the limits into memory; called at startup and when settings are switched
+pub fn load_settings(conn: &Connection) -> Result<ConcurrencyLimits> {
+    let limits = read_limits(conn)?;
+    apply(&limits);
+    Ok(limits)
+}
+
+#[tauri::command]
+pub fn get_concurrency_limits() -> ConcurrencyLimits {
+    current_limits()
+}
+
+/// Change the limits; calls already running keep their slots
+#[tauri::command]
+pub fn set_concurrency_limits(state: State<DbState>, limits: ConcurrencyLimits) -> std::result::Result<(), String> {
+    limits.validate().map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET concurrency_images = ?1, concurrency_search = ?2, concurrency_sync = ?3,
+                concurrency_epg = ?4, concurrency_max_queued = ?5
+         WHERE id = 1",
+        [limits.images, limits.search, limits.sync, limits.epg, limits.max_queued].map(|value| value as i64),
+    )
+    .map_err(|e| e.to_string())?;
+    apply(&limits);
+    Ok(())
+}
+
+/// Calls running and waiting in each subsystem
+#[tauri::command]
+pub fn get_concurrency_load() -> Vec<SubsystemLoad> {
+    Subsystem::ALL
+        .into_iter()
+        .map(|subsystem| {
+            let gate = subsystem.gate();
+            SubsystemLoad {
+                subsystem,
+                limit: gate.limit.load(Ordering::SeqCst),
+                running: gate.running.load(Ordering::SeqCst),
+                queued: gate.queued.load(Ordering::SeqCst),
+                rejected: gate.rejected.load(Ordering::Relaxed),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lowering_the_limit_retires_slots_in_use() {
+        let gate = Gate::new(3);
+        let held: Vec<_> = (0..3).map(|_| gate.semaphore.clone().try_acquire_owned().unwrap()).collect();
+
+        gate.set_limit(1);
+        assert_eq!(gate.excess.load(Ordering::SeqCst), 2);
+        for permit in held {
+            if gate.retire_excess() {
+                permit.forget();
+            }
+        }
+        assert_eq!(gate.semaphore.available_permits(), 1);
+
+        gate.set_limit(4);
+        assert_eq!(gate.semaphore.available_permits(), 4);
+
+        // Raising the limit again cancels slots still owed
+        let _held = gate.semaphore.clone().try_acquire_many_owned(4).unwrap();
+        gate.set_limit(2);
+        gate.set_limit(3);
+        assert_eq!(gate.excess.load(Ordering::SeqCst), 1);
+        assert_eq!(gate.semaphore.available_permits(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_with_overloaded() {
+        // The sync gate is only used by this test
+        let limits = ConcurrencyLimits {
+            sync: 1,
+            max_queued: 1,
+            ..ConcurrencyLimits::default()
+        };
+        apply(&limits);
+
+        let slot = acquire(Subsystem::Sync).await.unwrap();
+        let waiter = tokio::spawn(acquire(Subsystem::Sync));
+        while Subsystem::Sync.gate().queued.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        match acquire(Subsystem::Sync).await {
+            Err(XTauriError::Overloaded { subsystem, queued }) => {
+                assert_eq!((subsystem.as_str(), queued), ("sync", 1));
+            }
+            other => panic!("expected an overloaded error, got {:?}", other.map(|_| ())),
+        }
+
+        drop(slot);
+        let second = waiter.await.unwrap().unwrap();
+        let load = get_concurrency_load();
+        let sync = load.iter().find(|load| load.subsystem == Subsystem::Sync).unwrap();
+        assert_eq!((sync.running, sync.queued, sync.rejected), (1, 0, 1));
+        drop(second);
+
+        assert!(ConcurrencyLimits { epg: 0, ..limits }.validate().is_err());
+        apply(&ConcurrencyLimits::default());
+    }
+}
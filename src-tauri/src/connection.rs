@@ -0,0 +1,355 @@
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+/// How long each address gets to accept a TCP connection during diagnostics
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Mirrors the `connection_strategy` setting so client construction doesn't need the database
+static STRATEGY: AtomicU8 = AtomicU8::new(ConnectionStrategy::HappyEyeballs as u8);
+
+/// Which address families outgoing connections use
+///
+/// The prefer variants only fall back to the other family when the host has no
+/// address of the preferred one, so a broken AAAA record can't stall a request
+/// under `PreferIpv4`. `HappyEyeballs` hands every address to the connector,
+/// which races the second family shortly after the first one starts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStrategy {
+    PreferIpv4 = 0,
+    PreferIpv6 = 1,
+    HappyEyeballs = 2,
+}
+
+impl ConnectionStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConnectionStrategy::PreferIpv4 => "prefer_ipv4",
+            ConnectionStrategy::PreferIpv6 => "prefer_ipv6",
+            ConnectionStrategy::HappyEyeballs => "happy_eyeballs",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "prefer_ipv4" => Ok(ConnectionStrategy::PreferIpv4),
+            "prefer_ipv6" => Ok(ConnectionStrategy::PreferIpv6),
+            "happy_eyeballs" => Ok(ConnectionStrategy::HappyEyeballs),
+            other => Err(XTauriError::internal(format!(
+                "Unknown connection strategy: {}",
+                other
+            ))),
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => ConnectionStrategy::PreferIpv4,
+            1 => ConnectionStrategy::PreferIpv6,
+            _ => ConnectionStrategy::HappyEyeballs,
+        }
+    }
+
+    /// Order and filter resolved addresses for this strategy
+    pub fn arrange(&self, addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        let (v4, v6): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv4);
+
+        match self {
+            ConnectionStrategy::PreferIpv4 if !v4.is_empty() => v4,
+            ConnectionStrategy::PreferIpv6 if !v6.is_empty() => v6,
+            ConnectionStrategy::PreferIpv4 | ConnectionStrategy::PreferIpv6 => {
+                v4.into_iter().chain(v6).collect()
+            }
+            // RFC 8305: interleave families, starting with IPv6
+            ConnectionStrategy::HappyEyeballs => {
+                let mut arranged = Vec::with_capacity(v4.len() + v6.len());
+                let mut v4 = v4.into_iter();
+                let mut v6 = v6.into_iter();
+                loop {
+                    match (v6.next(), v4.next()) {
+                        (None, None) => break,
+                        (a, b) => arranged.extend(a.into_iter().chain(b)),
+                    }
+                }
+                arranged
+            }
+        }
+    }
+}
+
+/// Strategy in effect: the configuration file's, else the saved setting
+pub fn current_strategy() -> ConnectionStrategy {
+    crate::user_config::connection_strategy()
+        .unwrap_or_else(|| ConnectionStrategy::from_u8(STRATEGY.load(Ordering::Relaxed)))
+}
+
+/// DNS resolver that applies the configured connection strategy
+///
+/// The strategy is read on every lookup, so clients built before a settings
+/// change pick it up without being recreated.
+struct StrategyResolver;
+
+impl Resolve for StrategyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let addrs: Addrs = Box::new(current_strategy().arrange(addrs).into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Async client builder with the connection strategy and the configuration file's proxy and connect timeout applied
+pub fn client_builder() -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder().dns_resolver(Arc::new(StrategyResolver));
+    if let Some(proxy) = crate::user_config::proxy() {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(timeout) = crate::user_config::connect_timeout() {
+        builder = builder.connect_timeout(timeout);
+    }
+    builder
+}
+
+/// Blocking client builder with the same settings as `client_builder`
+pub fn blocking_client_builder() -> reqwest::blocking::ClientBuilder {
+    let mut builder = reqwest::blocking::Client::builder().dns_resolver(Arc::new(StrategyResolver));
+    if let Some(proxy) = crate::user_config::proxy() {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(timeout) = crate::user_config::connect_timeout() {
+        builder = builder.connect_timeout(timeout);
+    }
+    builder
+}
+
+/// Outcome of a TCP connect to one resolved address
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct AddressProbe {
+    pub address: String,
+    pub family: String,
+    pub reachable: bool,
+    pub connect_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// DNS and reachability report for a provider host
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct DnsDiagnostics {
+    pub host: String,
+    pub port: u16,
+    pub strategy: ConnectionStrategy,
+    pub resolve_ms: u64,
+    pub ipv4_addresses: Vec<String>,
+    pub ipv6_addresses: Vec<String>,
+    /// Addresses in the order the current strategy would try them
+    pub connection_order: Vec<String>,
+    pub probes: Vec<AddressProbe>,
+    pub error: Option<String>,
+}
+
+impl DnsDiagnostics {
+    /// Hide the host and its addresses, for demo mode
+    pub fn redacted(mut self) -> Self {
+        let redact = |values: &mut Vec<String>| {
+            values.iter_mut().for_each(|v| *v = crate::anonymize::redact(v))
+        };
+        self.host = crate::anonymize::redact(&self.host);
+        redact(&mut self.ipv4_addresses);
+        redact(&mut self.ipv6_addresses);
+        redact(&mut self.connection_order);
+        for probe in &mut self.probes {
+            probe.address = crate::anonymize::redact(&probe.address);
+        }
+        self
+    }
+}
+
+async fn probe_address(addr: SocketAddr) -> AddressProbe {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect(addr)).await;
+    let elapsed = started.elapsed().as_millis() as u64;
+
+    let (reachable, connect_ms, error) = match outcome {
+        Ok(Ok(_)) => (true, Some(elapsed), None),
+        Ok(Err(e)) => (false, None, Some(e.to_string())),
+        Err(_) => (
+            false,
+            None,
+            Some(format!("No response within {}s", PROBE_TIMEOUT.as_secs())),
+        ),
+    };
+
+    AddressProbe {
+        address: addr.ip().to_string(),
+        family: if addr.is_ipv4() { "ipv4" } else { "ipv6" }.to_string(),
+        reachable,
+        connect_ms,
+        error,
+    }
+}
+
+/// Resolve the host of `url` and try a TCP connection to every address it returns
+pub async fn diagnose(url: &str) -> Result<DnsDiagnostics> {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    let parsed = url::Url::parse(&url)
+        .map_err(|e| XTauriError::internal(format!("Invalid URL: {}", e)))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| XTauriError::internal("URL has no host".to_string()))?
+        .trim_matches(|c| c == '[' || c == ']')
+        .to_string();
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let strategy = current_strategy();
+
+    let started = Instant::now();
+    let lookup = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map(|addrs| addrs.collect::<Vec<SocketAddr>>());
+    let resolve_ms = started.elapsed().as_millis() as u64;
+
+    let mut diagnostics = DnsDiagnostics {
+        host,
+        port,
+        strategy,
+        resolve_ms,
+        ipv4_addresses: Vec::new(),
+        ipv6_addresses: Vec::new(),
+        connection_order: Vec::new(),
+        probes: Vec::new(),
+        error: None,
+    };
+
+    let mut addrs: Vec<SocketAddr> = match lookup {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            diagnostics.error = Some(format!("DNS lookup failed: {}", e));
+            return Ok(diagnostics);
+        }
+    };
+    addrs.dedup();
+
+    for addr in &addrs {
+        match addr.ip() {
+            IpAddr::V4(ip) => diagnostics.ipv4_addresses.push(ip.to_string()),
+            IpAddr::V6(ip) => diagnostics.ipv6_addresses.push(ip.to_string()),
+        }
+    }
+    diagnostics.connection_order = strategy
+        .arrange(addrs.clone())
+        .iter()
+        .map(|addr| addr.ip().to_string())
+        .collect();
+
+    let mut probes = tokio::task::JoinSet::new();
+    for (index, addr) in addrs.into_iter().enumerate() {
+        probes.spawn(async move { (index, probe_address(addr).await) });
+    }
+    let mut results = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        if let Ok(probe) = result {
+            results.push(probe);
+        }
+    }
+    results.sort_by_key(|(index, _)| *index);
+    diagnostics.probes = results.into_iter().map(|(_, probe)| probe).collect();
+
+    Ok(diagnostics)
+}
+
+/// Load the connection strategy into memory; called once at startup
+pub fn load_settings(conn: &Connection) -> Result<ConnectionStrategy> {
+    let value: String = conn.query_row(
+        "SELECT connection_strategy FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    let strategy = ConnectionStrategy::parse(&value)?;
+    STRATEGY.store(strategy as u8, Ordering::Relaxed);
+    Ok(strategy)
+}
+
+#[tauri::command]
+pub fn get_connection_strategy() -> ConnectionStrategy {
+    current_strategy()
+}
+
+#[tauri::command]
+pub fn set_connection_strategy(
+    state: State<DbState>,
+    strategy: ConnectionStrategy,
+) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET connection_strategy = ?1 WHERE id = 1",
+        [strategy.as_str()],
+    )
+    .map_err(|e| e.to_string())?;
+    STRATEGY.store(strategy as u8, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs() -> Vec<SocketAddr> {
+        vec![
+            "192.0.2.1:80".parse().unwrap(),
+            "192.0.2.2:80".parse().unwrap(),
+            "[2001:db8::1]:80".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_prefer_ipv4_drops_ipv6() {
+        let arranged = ConnectionStrategy::PreferIpv4.arrange(addrs());
+        assert_eq!(arranged.len(), 2);
+        assert!(arranged.iter().all(SocketAddr::is_ipv4));
+    }
+
+    #[test]
+    fn test_prefer_ipv6_drops_ipv4() {
+        let arranged = ConnectionStrategy::PreferIpv6.arrange(addrs());
+        assert_eq!(arranged, vec!["[2001:db8::1]:80".parse::<SocketAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn test_prefer_falls_back_when_family_missing() {
+        let v4_only: Vec<SocketAddr> = vec!["192.0.2.1:80".parse().unwrap()];
+        assert_eq!(ConnectionStrategy::PreferIpv6.arrange(v4_only.clone()), v4_only);
+    }
+
+    #[test]
+    fn test_happy_eyeballs_interleaves_families() {
+        let arranged = ConnectionStrategy::HappyEyeballs.arrange(addrs());
+        let families: Vec<bool> = arranged.iter().map(SocketAddr::is_ipv4).collect();
+        assert_eq!(families, vec![false, true, true]);
+    }
+
+    #[test]
+    fn test_strategy_round_trip() {
+        for strategy in [
+            ConnectionStrategy::PreferIpv4,
+            ConnectionStrategy::PreferIpv6,
+            ConnectionStrategy::HappyEyeballs,
+        ] {
+            assert_eq!(ConnectionStrategy::parse(strategy.as_str()).unwrap(), strategy);
+            assert_eq!(ConnectionStrategy::from_u8(strategy as u8), strategy);
+        }
+        assert!(ConnectionStrategy::parse("ipv5").is_err());
+    }
+}
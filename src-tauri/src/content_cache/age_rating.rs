@@ -0,0 +1,168 @@
+// Age ratings for VOD content and the parental age limit
+//
+// Providers report certificates in several places and formats: MPAA and TV
+// ratings, CSA ("-12"), FSK/PEGI ("FSK 16") or a bare age ("16+"). They are
+// normalized to the minimum viewer age so movies and series can be filtered
+// with one number.
+use crate::error::{Result, XTauriError};
+use crate::parental;
+use crate::state::DbState;
+use rusqlite::Connection;
+use serde_json::Value;
+use std::sync::atomic::{AtomicI64, Ordering};
+use tauri::State;
+
+/// Oldest age a rating can require
+pub const MAX_AGE_RATING: i64 = 21;
+
+/// Item fields checked for a rating, in order of preference
+const RATING_FIELDS: [&str; 5] = ["age", "mpaa_rating", "mpaa", "certification", "rating"];
+
+/// Mirrors the `parental_max_age_rating` setting; negative when there is no limit
+static PARENTAL_MAX_AGE: AtomicI64 = AtomicI64::new(-1);
+
+/// Minimum viewer age for a certificate, or `None` if it isn't one
+///
+/// Plain numbers with a decimal point are review scores, not ages, and are ignored.
+pub fn parse_age_rating(text: &str) -> Option<i64> {
+    let upper = text.trim().to_uppercase();
+    let rating = upper.strip_prefix("RATED").unwrap_or(&upper).trim();
+    if rating.is_empty() || rating.contains('.') {
+        return None;
+    }
+
+    let named = match rating {
+        "G" | "U" | "TP" | "ALL" | "TV-G" | "TV-Y" | "L" => Some(0),
+        "TV-Y7" => Some(7),
+        "PG" | "TV-PG" => Some(10),
+        "PG-13" => Some(13),
+        "TV-14" => Some(14),
+        "R" | "TV-MA" => Some(17),
+        "NC-17" | "X" | "XXX" => Some(18),
+        "NR" | "UNRATED" | "NOT RATED" => return None,
+        _ => None,
+    };
+    if named.is_some() {
+        return named;
+    }
+
+    // "16+", "-12", "FSK 16", "PEGI 18", "12A": the first number is the age
+    let digits: String = rating
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits
+        .parse::<i64>()
+        .ok()
+        .filter(|age| (0..=MAX_AGE_RATING).contains(age))
+}
+
+fn field_age_rating(item: &Value) -> Option<i64> {
+    RATING_FIELDS.iter().find_map(|field| match item.get(field)? {
+        Value::String(text) => parse_age_rating(text),
+        // A numeric "rating" is a review score; other numeric fields are ages
+        Value::Number(age) if *field != "rating" => age.as_i64().filter(|age| (0..=MAX_AGE_RATING).contains(age)),
+        _ => None,
+    })
+}
+
+/// Age rating of a VOD list item or info response, if it has one
+///
+/// Info responses keep their details under `info`, which is checked as well.
+pub fn extract_age_rating(item: &Value) -> Option<i64> {
+    field_age_rating(item).or_else(|| item.get("info").and_then(field_age_rating))
+}
+
+/// The parental age limit, if one is set
+pub fn parental_age_limit() -> Option<i64> {
+    let limit = PARENTAL_MAX_AGE.load(Ordering::Relaxed);
+    (limit >= 0).then_some(limit)
+}
+
+/// The maximum age rating to list: the requested one, lowered to the parental limit
+pub fn effective_max_age(requested: Option<i64>) -> Option<i64> {
+    match (requested, parental_age_limit()) {
+        (Some(requested), Some(limit)) => Some(requested.min(limit)),
+        (requested, limit) => requested.or(limit),
+    }
+}
+
+pub fn load_settings(conn: &Connection) -> Result<Option<i64>> {
+    let limit: Option<i64> = conn.query_row(
+        "SELECT parental_max_age_rating FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    PARENTAL_MAX_AGE.store(limit.unwrap_or(-1), Ordering::Relaxed);
+    Ok(limit)
+}
+
+#[tauri::command]
+pub fn get_parental_age_limit() -> Option<i64> {
+    parental_age_limit()
+}
+
+/// Hide movies and series rated above `max_age` from cached listings and searches
+///
+/// Content without a rating is hidden too while a limit is set. `None` removes the limit.
+/// Once a parental PIN is set it is required, and the attempt is audited.
+#[tauri::command]
+pub fn set_parental_age_limit(
+    state: State<DbState>,
+    max_age: Option<i64>,
+    pin: Option<String>,
+) -> std::result::Result<(), String> {
+    if let Some(age) = max_age {
+        if !(0..=MAX_AGE_RATING).contains(&age) {
+            return Err(XTauriError::internal(format!(
+                "Parental age limit must be between 0 and {}",
+                MAX_AGE_RATING
+            ))
+            .to_string());
+        }
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    parental::require_pin(&db, pin.as_deref(), parental::TARGET_AGE_LIMIT, chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET parental_max_age_rating = ?1 WHERE id = 1",
+        [max_age],
+    )
+    .map_err(|e| e.to_string())?;
+    PARENTAL_MAX_AGE.store(max_age.unwrap_or(-1), Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_age_rating() {
+        assert_eq!(parse_age_rating("PG-13"), Some(13));
+        assert_eq!(parse_age_rating("Rated R"), Some(17));
+        assert_eq!(parse_age_rating("tv-ma"), Some(17));
+        assert_eq!(parse_age_rating("-12"), Some(12));
+        assert_eq!(parse_age_rating("TP"), Some(0));
+        assert_eq!(parse_age_rating("FSK 16"), Some(16));
+        assert_eq!(parse_age_rating("16+"), Some(16));
+        assert_eq!(parse_age_rating("12A"), Some(12));
+        assert_eq!(parse_age_rating("7.5"), None);
+        assert_eq!(parse_age_rating("NR"), None);
+        assert_eq!(parse_age_rating("1999"), None);
+        assert_eq!(parse_age_rating(""), None);
+    }
+
+    #[test]
+    fn test_extract_age_rating() {
+        // A numeric score in "rating" is not an age
+        assert_eq!(extract_age_rating(&json!({"rating": 7.2, "rating_5based": 3.6})), None);
+        assert_eq!(extract_age_rating(&json!({"rating": "7.2", "age": "16+"})), Some(16));
+        assert_eq!(extract_age_rating(&json!({"rating": "PG"})), Some(10));
+        assert_eq!(extract_age_rating(&json!({"age": 18})), Some(18));
+        assert_eq!(extract_age_rating(&json!({"info": {"mpaa_rating": "PG-13"}})), Some(13));
+    }
+}
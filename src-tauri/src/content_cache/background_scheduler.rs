@@ -1,5 +1,6 @@
 // Background scheduler for automatic content synchronization
 use crate::error::{Result, XTauriError};
+use crate::notifications::{Notification, NotificationKind};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::interval;
@@ -60,7 +61,7 @@ impl BackgroundScheduler {
                 
                 #[cfg(debug_assertions)]
                 println!("[DEBUG] Background scheduler: Checking for syncs...");
-                
+
                 // Get current profile list
                 let profiles = {
                     let profiles_guard = match profile_ids.lock() {
@@ -160,26 +161,20 @@ pub fn is_wifi_connected() -> bool {
     true
 }
 
-/// Notification utility for sync completion
-/// 
-/// Sends a system notification when sync is complete
-pub fn send_sync_notification(profile_name: &str, success: bool) -> Result<()> {
-    // TODO: Implement system notifications
-    // Use tauri::api::notification or a notification crate
-    
-    let message = if success {
+/// Notification for a finished sync, sent when the profile's sync settings ask for one
+pub fn sync_notification(profile_id: &str, profile_name: &str, success: bool) -> Notification {
+    let title = if success {
         format!("Content sync completed for {}", profile_name)
     } else {
         format!("Content sync failed for {}", profile_name)
     };
     
-    #[cfg(debug_assertions)]
-    println!("[DEBUG] Notification: {}", message);
-    
-    // For now, just log the notification
-    // In production, this should use Tauri's notification API
-    
-    Ok(())
+    Notification {
+        kind: NotificationKind::SyncResult,
+        title,
+        body: String::new(),
+        profile_id: Some(profile_id.to_string()),
+    }
 }
 
 #[cfg(test)]
@@ -218,6 +213,7 @@ mod tests {
                 sync_interval_hours INTEGER DEFAULT 24,
                 wifi_only BOOLEAN DEFAULT 1,
                 notify_on_complete BOOLEAN DEFAULT 0,
+                favorites_only BOOLEAN NOT NULL DEFAULT 0,
                 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                 updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                 FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
@@ -400,12 +396,12 @@ mod tests {
     
     #[test]
     fn test_sync_notification() {
-        // Verify notification doesn't panic
-        let result = send_sync_notification("Test Profile", true);
-        assert!(result.is_ok());
+        let notification = sync_notification("p1", "Test Profile", true);
+        assert_eq!(notification.kind, NotificationKind::SyncResult);
+        assert_eq!(notification.title, "Content sync completed for Test Profile");
         
-        let result = send_sync_notification("Test Profile", false);
-        assert!(result.is_ok());
+        let notification = sync_notification("p1", "Test Profile", false);
+        assert_eq!(notification.title, "Content sync failed for Test Profile");
     }
     
     #[tokio::test]
@@ -1,9 +1,37 @@
 // Tauri commands for content cache operations
-use crate::content_cache::{ContentCache, ChannelFilter, XtreamChannel, SyncScheduler, SyncProgress, SyncSettings};
+use crate::content_cache::age_rating;
+use crate::content_cache::background_scheduler::sync_notification;
+use crate::content_cache::epg::{self, EpgProgramme, XtreamChannelListing};
+use crate::content_cache::favorites_sync;
+use crate::content_cache::followed_categories::{self, CategorySnapshot, NewCategoryItems};
+use crate::content_cache::failover::{self, PlaybackCandidate, PlaybackEvent};
+use crate::content_cache::ordering::{ChannelOrderScope, ChannelSortMode};
+use crate::content_cache::popularity::VodSortMode;
+use crate::content_cache::preview::{self, ProfilePreview};
+use crate::content_cache::search_syntax::{self, SearchMode, SearchSyntaxHelp};
+use crate::content_cache::similarity::{self, SimilarContentType, SimilarItem};
+use crate::content_cache::throttle::SyncLimits;
+use crate::content_cache::thumbnails::{self, XtreamMovieListing, XtreamSeriesListing};
+use crate::content_cache::{
+    ContentCache, ChannelFilter, ChannelUrlOverride, ProfileSyncProgress, SyncScheduler, SyncProgress, SyncSettings,
+};
+use crate::concurrency::{self, Subsystem};
+use crate::country::CountryCount;
+use crate::db_pool::DbPool;
 use crate::error::Result;
+use crate::events::{self, AppEvent};
+use crate::ipc_payload;
+use crate::notifications::{self, Notification, NotificationKind};
+use crate::pagination::Page;
+use crate::profiling::profile_future;
+use crate::search_session;
+use crate::xtream::{FavoriteIdentity, FavoritesChange};
 use rusqlite::Connection;
-use std::sync::{Arc, Mutex};
-use tauri::State;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::ipc::Response;
+use tauri::{AppHandle, State, Window};
 
 /// State wrapper for ContentCache and SyncScheduler
 pub struct ContentCacheState {
@@ -12,9 +40,10 @@ pub struct ContentCacheState {
 }
 
 impl ContentCacheState {
-    pub fn new(db: Arc<Mutex<Connection>>) -> Result<Self> {
-        let cache = ContentCache::new(Arc::clone(&db))?;
-        let sync_scheduler = SyncScheduler::new(db);
+    /// Write through the pool's writer and read from the pool
+    pub fn new(pool: Arc<DbPool>) -> Result<Self> {
+        let cache = ContentCache::new(pool.writer())?.with_read_pool(pool.clone());
+        let sync_scheduler = SyncScheduler::new(pool.writer());
         Ok(Self {
             cache: Arc::new(cache),
             sync_scheduler: Arc::new(sync_scheduler),
@@ -22,6 +51,64 @@ impl ContentCacheState {
     }
 }
 
+/// Options of the cached channel listing and search commands
+///
+/// Every field may be left out. Searches rank by relevance, so they ignore `sort_mode`;
+/// plain listings ignore the search-only fields.
+#[derive(Debug, Clone, Default, Deserialize, specta::Type)]
+#[serde(default)]
+pub struct ChannelListingOptions {
+    /// `custom` to use the category's manual order set by `move_xtream_channel`
+    pub sort_mode: ChannelSortMode,
+    /// Country filter: an ISO code such as `GB`, a tag such as `UK`, or a name
+    pub country: Option<String>,
+    /// `advanced` to allow wildcards and quoted phrases (see `get_search_syntax_help`)
+    pub search_mode: SearchMode,
+    /// Search session; a newer search in the session cancels this one
+    pub session_id: Option<String>,
+    /// Add each search result's current programme from the local EPG store
+    pub include_now_playing: bool,
+    /// Return each icon's file in `cached_icon` when the image cache already holds it
+    pub resolve_thumbnails: bool,
+}
+
+/// Options of the cached movie and series listing and search commands
+///
+/// Every field may be left out. Searches rank by relevance, so they ignore `sort_mode`;
+/// plain listings ignore the search-only fields.
+#[derive(Debug, Clone, Default, Deserialize, specta::Type)]
+#[serde(default)]
+pub struct VodListingOptions {
+    /// `popularity` to list the most watched, favorite and recently added first
+    pub sort_mode: VodSortMode,
+    /// Age rating bounds; the parental limit caps the maximum
+    pub min_age_rating: Option<i64>,
+    pub max_age_rating: Option<i64>,
+    /// `advanced` to allow wildcards and quoted phrases (see `get_search_syntax_help`)
+    pub search_mode: SearchMode,
+    /// Search session; a newer search in the session cancels this one
+    pub session_id: Option<String>,
+    /// Return each icon's file in `cached_icon` when the image cache already holds it
+    pub resolve_thumbnails: bool,
+}
+
+/// Convert a page into listings, with a reader for the cached icon lookup when `resolve_thumbnails` is set
+fn with_cached_icons<T>(
+    state: &ContentCacheState,
+    resolve_thumbnails: bool,
+    listings: impl FnOnce(Option<&Connection>) -> Result<T>,
+) -> std::result::Result<T, String> {
+    if resolve_thumbnails {
+        let reader = state.cache.reader().map_err(|e| e.to_string())?;
+        listings(Some(&reader)).map_err(|e| e.to_string())
+    } else {
+        listings(None).map_err(|e| e.to_string())
+    }
+}
+
+// ==================== Search Sessions ====================
+
+
 // ==================== Channel Commands ====================
 
 /// Get cached Xtream channels for a profile with optional filtering
@@ -31,28 +118,38 @@ impl ContentCacheState {
 /// * `category_id` - Optional category filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Sort order, country filter and icon lookup
 /// 
 /// # Returns
-/// Vector of cached channels matching the filter criteria
+/// One page of cached channels matching the filter criteria, with the total
 #[tauri::command]
 pub async fn get_cached_xtream_channels(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     category_id: Option<String>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<XtreamChannel>, String> {
+    options: Option<ChannelListingOptions>,
+) -> std::result::Result<Response, String> {
+    let options = options.unwrap_or_default();
     let filter = ChannelFilter {
         category_id,
         name_contains: None,
+        country: options.country,
         limit,
         offset,
+        sort_mode: options.sort_mode,
+        search_mode: SearchMode::Simple,
     };
     
-    state
+    let page = state
         .cache
-        .get_channels(&profile_id, Some(filter))
-        .map_err(|e| e.to_string())
+        .get_channels_page(&profile_id, Some(filter))
+        .map_err(|e| e.to_string())?
+        .map(|channel| XtreamChannelListing { channel, now_playing: None, cached_icon: None });
+    let page = with_cached_icons(&state, options.resolve_thumbnails, |conn| thumbnails::channel_listings(conn, page))?;
+    ipc_payload::respond(&window, &page)
 }
 
 /// Search cached Xtream channels with fuzzy matching
@@ -63,29 +160,290 @@ pub async fn get_cached_xtream_channels(
 /// * `category_id` - Optional category filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Search mode and session, country filter, now playing and icon lookup
 /// 
 /// # Returns
-/// Vector of channels matching the search query, ordered by relevance
+/// One page of channels matching the search query, ordered by relevance, with the total
 #[tauri::command]
 pub async fn search_cached_xtream_channels(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     query: String,
     category_id: Option<String>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<XtreamChannel>, String> {
+    options: Option<ChannelListingOptions>,
+) -> std::result::Result<Page<XtreamChannelListing>, String> {
+    let options = options.unwrap_or_default();
     let filter = ChannelFilter {
         category_id,
         name_contains: None,
+        country: options.country,
         limit,
         offset,
+        sort_mode: ChannelSortMode::Default,
+        search_mode: options.search_mode,
     };
     
+    let ticket = search_session::begin_debounced(window.label(), options.session_id.as_deref()).await?;
+    let _slot = concurrency::acquire(Subsystem::Search).await.map_err(|e| e.to_string())?;
+    let page = {
+        // A newer search interrupts this one's query on the reader it runs on
+        let reader = state.cache.reader().map_err(|e| e.to_string())?;
+        ticket.run_interruptible(&reader, |conn| {
+            state.cache.search_channels_page_on(conn, &profile_id, &query, Some(filter))
+        })?
+    };
+
+    let page = if options.include_now_playing {
+        let db = state.cache.get_db();
+        let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let items = epg::annotate_channels(&conn, &profile_id, page.items, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?;
+        Page::new(items, page.total, page.limit, page.offset)
+    } else {
+        page.map(|channel| XtreamChannelListing { channel, now_playing: None, cached_icon: None })
+    };
+    with_cached_icons(&state, options.resolve_thumbnails, |conn| thumbnails::channel_listings(conn, page))
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Countries of a profile's cached channels, for the country filter
+/// 
+/// # Returns
+/// Each country with its flag and number of channels, most channels first
+#[tauri::command]
+pub async fn get_cached_xtream_channel_countries(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+) -> std::result::Result<Vec<CountryCount>, String> {
+    state
+        .cache
+        .get_channel_countries(&profile_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Guide rows for a set of EPG channels from the local EPG store
+/// 
+/// Programme images and channel logos that aren't in the image cache yet are
+/// queued for download, reported by `image_fetched` events.
+/// 
+/// # Returns
+/// An `EpgGrid` with each channel's programmes between the two timestamps, in
+/// start order, with gaps in the provider's guide covered by synthetic
+/// "No information" entries
+#[tauri::command]
+pub async fn get_xtream_epg_grid(
+    app_handle: AppHandle,
+    window: Window,
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    epg_channel_ids: Vec<String>,
+    start_timestamp: i64,
+    end_timestamp: i64,
+) -> std::result::Result<Response, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
+    let mut grid = {
+        let db = state.cache.get_db();
+        let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let ids: Vec<&str> = epg_channel_ids.iter().map(String::as_str).collect();
+        epg::guide_grid(&conn, &profile_id, &ids, start_timestamp, end_timestamp).map_err(|e| e.to_string())?
+    };
+
+    let groups = grid.uncached_icons();
+    if !groups.is_empty() {
+        // The guide is still usable without its images
+        match crate::images::fetch_images_batch(app_handle, groups).await {
+            Ok(batch) => grid.icon_batch = Some(batch),
+            Err(e) => eprintln!("[EPG] Failed to queue guide images: {}", e),
+        }
+    }
+    ipc_payload::respond(&window, &grid)
+}
+
+/// Stored programmes of one EPG channel, available without reaching the provider
+/// 
+/// # Returns
+/// The channel's programmes between the two timestamps, in start order
+#[tauri::command]
+pub async fn get_cached_xtream_epg(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    epg_channel_id: String,
+    start_timestamp: i64,
+    end_timestamp: i64,
+) -> std::result::Result<Vec<EpgProgramme>, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
+    state
+        .cache
+        .get_epg_programs(&profile_id, &epg_channel_id, start_timestamp, end_timestamp)
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Delete stored programmes for one EPG channel, or every channel when none is given
+/// 
+/// # Returns
+/// Number of programmes deleted
+#[tauri::command]
+pub async fn clear_cached_xtream_epg(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    epg_channel_id: Option<String>,
+) -> std::result::Result<usize, String> {
+    state
+        .cache
+        .delete_epg_programs(&profile_id, epg_channel_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Cached movies or series most like the given one, for a "More like this" shelf
+/// 
+/// # Returns
+/// Up to `limit` items ranked by shared genres, cast, directors and plot keywords
+#[tauri::command]
+pub async fn get_similar(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    content_type: SimilarContentType,
+    id: i64,
+    limit: Option<usize>,
+) -> std::result::Result<Vec<SimilarItem>, String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    similarity::similar(&conn, &profile_id, content_type, id, limit.unwrap_or(20))
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Set a custom stream URL for a channel, used instead of the provider URL
+#[tauri::command]
+pub async fn set_xtream_channel_url_override(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    stream_id: i64,
+    url: String,
+) -> std::result::Result<(), String> {
+    state
+        .cache
+        .set_channel_url_override(&profile_id, stream_id, &url)
+        .map_err(|e| e.to_string())
+}
+
+/// Remove a channel's custom stream URL, returning whether one was set
+#[tauri::command]
+pub async fn remove_xtream_channel_url_override(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    stream_id: i64,
+) -> std::result::Result<bool, String> {
+    state
+        .cache
+        .remove_channel_url_override(&profile_id, stream_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Get all custom channel stream URLs for a profile
+#[tauri::command]
+pub async fn get_xtream_channel_url_overrides(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+) -> std::result::Result<Vec<ChannelUrlOverride>, String> {
     state
         .cache
-        .search_channels(&profile_id, &query, Some(filter))
+        .get_channel_url_overrides(&profile_id)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Move a channel within a category or the channel favorites to just after another
+///
+/// `after_stream_id` of `None` moves the channel to the top. Listings show the
+/// resulting order when called with the `custom` sort mode.
+#[tauri::command]
+pub async fn move_xtream_channel(
+    app_handle: AppHandle,
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    scope: ChannelOrderScope,
+    stream_id: i64,
+    after_stream_id: Option<i64>,
+) -> std::result::Result<(), String> {
+    state
+        .cache
+        .move_channel(&profile_id, &scope, stream_id, after_stream_id)
+        .map_err(|e| e.to_string())?;
+
+    if scope == ChannelOrderScope::Favorites {
+        FavoritesChange::moved(&profile_id, FavoriteIdentity::new("channel", &stream_id.to_string()))
+            .emit(&app_handle);
+    }
+    Ok(())
+}
+
+/// Get every cached copy of a channel across profiles, best playback candidate first
+#[tauri::command]
+pub async fn get_playback_candidates(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    stream_id: i64,
+) -> std::result::Result<Vec<PlaybackCandidate>, String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    failover::get_playback_candidates(&conn, &profile_id, stream_id, chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Mark a channel source as failing and get the next candidate to try
+#[tauri::command]
+pub async fn report_playback_failure(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    stream_id: i64,
+    error: Option<String>,
+) -> std::result::Result<Option<PlaybackCandidate>, String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    failover::report_playback_failure(
+        &conn,
+        &profile_id,
+        stream_id,
+        error.as_deref(),
+        chrono::Utc::now().timestamp(),
+    )
+    .map_err(|e| e.to_string())
+    .and_then(crate::anonymize::mask_result)
+}
+
+/// Report a playback event for a channel source, for its reliability score
+///
+/// Failures (with the player's error code, if any) also mark the source as
+/// failing, and the next candidate to try is returned as with
+/// `report_playback_failure`.
+#[tauri::command]
+pub async fn report_playback_event(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    stream_id: i64,
+    event: PlaybackEvent,
+    error_code: Option<String>,
+) -> std::result::Result<Option<PlaybackCandidate>, String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let now = chrono::Utc::now().timestamp();
+
+    if event == PlaybackEvent::Failed {
+        return failover::report_playback_failure(&conn, &profile_id, stream_id, error_code.as_deref(), now)
+            .map_err(|e| e.to_string())
+            .and_then(crate::anonymize::mask_result);
+    }
+    failover::record_playback_event(&conn, &profile_id, stream_id, event, error_code.as_deref(), now)
+        .map_err(|e| e.to_string())?;
+    Ok(None)
 }
 
 // ==================== Movie Commands ====================
@@ -100,11 +458,13 @@ pub async fn search_cached_xtream_channels(
 /// * `min_rating` - Optional minimum rating filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Sort order, age rating bounds and icon lookup
 /// 
 /// # Returns
-/// Vector of cached movies matching the filter criteria
+/// One page of cached movies matching the filter criteria, with the total
 #[tauri::command]
 pub async fn get_cached_xtream_movies(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     category_id: Option<String>,
@@ -113,23 +473,31 @@ pub async fn get_cached_xtream_movies(
     min_rating: Option<f64>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<crate::content_cache::XtreamMovie>, String> {
+    options: Option<VodListingOptions>,
+) -> std::result::Result<Response, String> {
     use crate::content_cache::MovieFilter;
     
+    let options = options.unwrap_or_default();
     let filter = MovieFilter {
         category_id,
         name_contains: None,
         genre,
         year,
         min_rating,
+        min_age_rating: options.min_age_rating,
+        max_age_rating: age_rating::effective_max_age(options.max_age_rating),
         limit,
         offset,
+        sort_mode: options.sort_mode,
+        search_mode: SearchMode::Simple,
     };
     
-    state
+    let page = state
         .cache
-        .get_movies(&profile_id, Some(filter), None, None)
-        .map_err(|e| e.to_string())
+        .get_movies_page(&profile_id, Some(filter), None, None)
+        .map_err(|e| e.to_string())?;
+    let page = with_cached_icons(&state, options.resolve_thumbnails, |conn| thumbnails::movie_listings(conn, page))?;
+    ipc_payload::respond(&window, &page)
 }
 
 /// Search cached Xtream movies with fuzzy matching
@@ -143,11 +511,13 @@ pub async fn get_cached_xtream_movies(
 /// * `min_rating` - Optional minimum rating filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Search mode and session, age rating bounds and icon lookup
 /// 
 /// # Returns
-/// Vector of movies matching the search query, ordered by relevance
+/// One page of movies matching the search query, ordered by relevance, with the total
 #[tauri::command]
 pub async fn search_cached_xtream_movies(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     query: String,
@@ -157,23 +527,34 @@ pub async fn search_cached_xtream_movies(
     min_rating: Option<f64>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<crate::content_cache::XtreamMovie>, String> {
+    options: Option<VodListingOptions>,
+) -> std::result::Result<Page<XtreamMovieListing>, String> {
     use crate::content_cache::MovieFilter;
     
+    let options = options.unwrap_or_default();
     let filter = MovieFilter {
         category_id,
         name_contains: None,
         genre,
         year,
         min_rating,
+        min_age_rating: options.min_age_rating,
+        max_age_rating: age_rating::effective_max_age(options.max_age_rating),
         limit,
         offset,
+        sort_mode: VodSortMode::Default,
+        search_mode: options.search_mode,
     };
     
-    state
-        .cache
-        .search_movies(&profile_id, &query, Some(filter), None, None)
+    let ticket = search_session::begin_debounced(window.label(), options.session_id.as_deref()).await?;
+    let _slot = concurrency::acquire(Subsystem::Search).await.map_err(|e| e.to_string())?;
+    let reader = state.cache.reader().map_err(|e| e.to_string())?;
+    let page = ticket.run_interruptible(&reader, |conn| {
+        state.cache.search_movies_page_on(conn, &profile_id, &query, Some(filter), None, None)
+    })?;
+    thumbnails::movie_listings(options.resolve_thumbnails.then_some(&*reader), page)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Filter cached Xtream movies with advanced criteria
@@ -188,11 +569,13 @@ pub async fn search_cached_xtream_movies(
 /// * `min_rating` - Optional minimum rating filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Sort order, age rating bounds and icon lookup
 /// 
 /// # Returns
-/// Vector of cached movies matching the filter criteria
+/// One page of cached movies matching the filter criteria, with the total
 #[tauri::command]
 pub async fn filter_cached_xtream_movies(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     category_id: Option<String>,
@@ -201,9 +584,11 @@ pub async fn filter_cached_xtream_movies(
     min_rating: Option<f64>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<crate::content_cache::XtreamMovie>, String> {
+    options: Option<VodListingOptions>,
+) -> std::result::Result<Response, String> {
     // This is essentially the same as get_cached_xtream_movies
     get_cached_xtream_movies(
+        window,
         state,
         profile_id,
         category_id,
@@ -212,6 +597,7 @@ pub async fn filter_cached_xtream_movies(
         min_rating,
         limit,
         offset,
+        options,
     )
     .await
 }
@@ -228,11 +614,13 @@ pub async fn filter_cached_xtream_movies(
 /// * `min_rating` - Optional minimum rating filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Sort order, age rating bounds and icon lookup
 /// 
 /// # Returns
-/// Vector of cached series matching the filter criteria
+/// One page of cached series matching the filter criteria, with the total
 #[tauri::command]
 pub async fn get_cached_xtream_series(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     category_id: Option<String>,
@@ -241,23 +629,31 @@ pub async fn get_cached_xtream_series(
     min_rating: Option<f64>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<crate::content_cache::XtreamSeries>, String> {
+    options: Option<VodListingOptions>,
+) -> std::result::Result<Response, String> {
     use crate::content_cache::SeriesFilter;
     
+    let options = options.unwrap_or_default();
     let filter = SeriesFilter {
         category_id,
         name_contains: None,
         genre,
         year,
         min_rating,
+        min_age_rating: options.min_age_rating,
+        max_age_rating: age_rating::effective_max_age(options.max_age_rating),
         limit,
         offset,
+        sort_mode: options.sort_mode,
+        search_mode: SearchMode::Simple,
     };
     
-    state
+    let page = state
         .cache
-        .get_series(&profile_id, Some(filter))
-        .map_err(|e| e.to_string())
+        .get_series_page(&profile_id, Some(filter))
+        .map_err(|e| e.to_string())?;
+    let page = with_cached_icons(&state, options.resolve_thumbnails, |conn| thumbnails::series_listings(conn, page))?;
+    ipc_payload::respond(&window, &page)
 }
 
 /// Get cached Xtream series details including seasons and episodes
@@ -278,6 +674,7 @@ pub async fn get_cached_xtream_series_details(
         .cache
         .get_series_details(&profile_id, series_id)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Search cached Xtream series with fuzzy matching
@@ -291,11 +688,13 @@ pub async fn get_cached_xtream_series_details(
 /// * `min_rating` - Optional minimum rating filter
 /// * `limit` - Optional limit for pagination
 /// * `offset` - Optional offset for pagination
+/// * `options` - Search mode and session, age rating bounds and icon lookup
 /// 
 /// # Returns
-/// Vector of series matching the search query, ordered by relevance
+/// One page of series matching the search query, ordered by relevance, with the total
 #[tauri::command]
 pub async fn search_cached_xtream_series(
+    window: Window,
     state: State<'_, ContentCacheState>,
     profile_id: String,
     query: String,
@@ -305,23 +704,40 @@ pub async fn search_cached_xtream_series(
     min_rating: Option<f64>,
     limit: Option<usize>,
     offset: Option<usize>,
-) -> std::result::Result<Vec<crate::content_cache::XtreamSeries>, String> {
+    options: Option<VodListingOptions>,
+) -> std::result::Result<Page<XtreamSeriesListing>, String> {
     use crate::content_cache::SeriesFilter;
     
+    let options = options.unwrap_or_default();
     let filter = SeriesFilter {
         category_id,
         name_contains: None,
         genre,
         year,
         min_rating,
+        min_age_rating: options.min_age_rating,
+        max_age_rating: age_rating::effective_max_age(options.max_age_rating),
         limit,
         offset,
+        sort_mode: VodSortMode::Default,
+        search_mode: options.search_mode,
     };
     
-    state
-        .cache
-        .fts_search_series(&profile_id, &query, Some(filter))
+    let ticket = search_session::begin_debounced(window.label(), options.session_id.as_deref()).await?;
+    let _slot = concurrency::acquire(Subsystem::Search).await.map_err(|e| e.to_string())?;
+    let reader = state.cache.reader().map_err(|e| e.to_string())?;
+    let page = ticket.run_interruptible(&reader, |conn| {
+        state.cache.fts_search_series_page_on(conn, &profile_id, &query, Some(filter))
+    })?;
+    thumbnails::series_listings(options.resolve_thumbnails.then_some(&*reader), page)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Describe the search modes and the syntax advanced mode accepts
+#[tauri::command]
+pub fn get_search_syntax_help() -> SearchSyntaxHelp {
+    search_syntax::search_syntax_help()
 }
 
 // ==================== Sync Control Commands ====================
@@ -329,24 +745,35 @@ pub async fn search_cached_xtream_series(
 /// Start content synchronization for a profile
 /// 
 /// This command initiates either a full or incremental sync based on the `full_sync` parameter.
-/// The sync runs in the background and progress can be monitored via `get_sync_progress`.
+/// The sync runs in the background and progress can be monitored via `get_sync_progress`
+/// or the `sync://progress` and `sync://complete` events.
 /// 
 /// # Arguments
 /// * `cache_state` - Content cache state containing the sync scheduler
 /// * `xtream_state` - Xtream state for accessing profile credentials
 /// * `profile_id` - The profile ID to sync
-/// * `full_sync` - If true, performs full sync; if false, performs incremental sync
+/// * `full_sync` - If true, performs full sync; if false, performs incremental sync, or a
+///   favorites-only sync when the profile's sync settings ask for one
+/// * `run_now` - Start a full sync even outside the maintenance window
 /// 
 /// # Returns
-/// Ok(()) if sync started successfully, error otherwise
+/// None if the sync started, or when a full sync deferred to the maintenance window will start.
+/// A deferred sync is listed as a background task while it waits, but the profile doesn't count
+/// as syncing until it starts.
 #[tauri::command]
 pub async fn start_content_sync(
+    app_handle: AppHandle,
     cache_state: State<'_, ContentCacheState>,
     xtream_state: State<'_, crate::xtream::XtreamState>,
+    automation_state: State<'_, crate::xtream::AutomationState>,
     profile_id: String,
     full_sync: bool,
-) -> std::result::Result<(), String> {
+    run_now: Option<bool>,
+) -> std::result::Result<Option<String>, String> {
     use tokio::sync::mpsc;
+
+    // Held by the sync task until the sync ends, so the limit caps running syncs
+    let slot = concurrency::acquire(Subsystem::Sync).await.map_err(|e| e.to_string())?;
     
     // Check if sync is already active
     if cache_state.sync_scheduler.is_sync_active(&profile_id).map_err(|e| e.to_string())? {
@@ -367,29 +794,122 @@ pub async fn start_content_sync(
         .ok_or_else(|| format!("Profile not found: {}", profile_id))?;
     
     // Create progress channel
-    let (progress_tx, mut progress_rx) = mpsc::channel(100);
+    let (progress_tx, mut progress_rx) = mpsc::channel::<SyncProgress>(100);
     
     // Create cancellation token
     let cancel_token = tokio_util::sync::CancellationToken::new();
     
-    // Register the sync
-    cache_state
+    let sync_settings = cache_state
         .sync_scheduler
-        .register_sync(&profile_id, cancel_token.clone())
+        .get_sync_settings(&profile_id)
         .map_err(|e| e.to_string())?;
     
+    // Profiles set to favorites only refresh just their favorites between full syncs
+    let favorites_only = !full_sync && sync_settings.favorites_only;
+    
+    // Full syncs are heavy work, so outside the maintenance window they wait for it
+    let wait = if full_sync {
+        crate::content_cache::maintenance::time_until_window(run_now.unwrap_or(false))
+    } else {
+        None
+    };
+    let deferred_until = wait.map(crate::content_cache::maintenance::deferred_until);
+    
+    // A deferred sync registers and takes a sync slot once the window opens, so
+    // the profile and other syncs can run meanwhile
+    let slot = if wait.is_none() {
+        cache_state
+            .sync_scheduler
+            .register_sync(&profile_id, cancel_token.clone())
+            .map_err(|e| e.to_string())?;
+        Some(slot)
+    } else {
+        None
+    };
+    
+    let task = crate::tasks::register_cancellable(
+        crate::tasks::TaskKind::Sync,
+        if full_sync {
+            "Full sync"
+        } else if favorites_only {
+            "Favorites sync"
+        } else {
+            "Incremental sync"
+        },
+        Some(&profile_id),
+        cancel_token.clone(),
+    );
+    let task_id = task.id().to_string();
+    
     // Clone necessary data for the async task
     let scheduler = Arc::clone(&cache_state.sync_scheduler);
     let cache = Arc::clone(&cache_state.cache);
     let profile_id_clone = profile_id.clone();
+    let profile_name = profile.name.clone();
+    let notify_on_complete = sync_settings.notify_on_complete;
     let base_url = profile.url.clone();
     let username = credentials.username.clone();
     let password = credentials.password.clone();
+    let automation = Arc::clone(&automation_state.engine);
+    
+    // Forward progress updates to the task list and the frontend until the sync ends
+    let progress_app = app_handle.clone();
+    let progress_profile_id = profile_id.clone();
+    let forwarder = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            crate::tasks::set_progress(&task_id, progress.progress, Some(progress.current_step.clone()));
+            events::emit(
+                &progress_app,
+                AppEvent::SyncProgress(ProfileSyncProgress {
+                    profile_id: progress_profile_id.clone(),
+                    progress,
+                }),
+            );
+        }
+    });
     
     // Spawn sync task
     tokio::spawn(async move {
+        if let Some(wait) = wait {
+            task.set_progress(0, Some("Waiting for the maintenance window".to_string()));
+            if !crate::content_cache::maintenance::wait_for_window(wait, &cancel_token).await {
+                return;
+            }
+            if let Err(e) = scheduler.register_sync(&profile_id_clone, cancel_token.clone()) {
+                eprintln!("[WARN] Deferred full sync for profile {} not started: {}", profile_id_clone, e);
+                return;
+            }
+        }
+        let sync_slot = match slot {
+            Some(slot) => slot,
+            None => match concurrency::acquire(Subsystem::Sync).await {
+                Ok(slot) => slot,
+                Err(e) => {
+                    eprintln!("[WARN] Deferred full sync for profile {} not started: {}", profile_id_clone, e);
+                    let _ = scheduler.unregister_sync(&profile_id_clone);
+                    return;
+                }
+            },
+        };
+
+        let started_at = chrono::Utc::now();
+        let started = std::time::Instant::now();
+        let counts_before = cache.get_content_counts(&profile_id_clone).unwrap_or_default();
+        let episodes_before = favorite_episode_counts(&cache, &profile_id_clone);
+        let followed_before = followed_category_items(&cache, &profile_id_clone);
+        
         let result = if full_sync {
-            scheduler.run_full_sync(
+            profile_future!("sync", "run_full_sync", scheduler.run_full_sync(
+                &profile_id_clone,
+                &base_url,
+                &username,
+                &password,
+                &cache,
+                &progress_tx,
+                &cancel_token,
+            )).await
+        } else if favorites_only {
+            profile_future!("sync", "run_favorites_sync", scheduler.run_favorites_sync(
                 &profile_id_clone,
                 &base_url,
                 &username,
@@ -397,9 +917,9 @@ pub async fn start_content_sync(
                 &cache,
                 &progress_tx,
                 &cancel_token,
-            ).await
+            )).await
         } else {
-            scheduler.run_incremental_sync(
+            profile_future!("sync", "run_incremental_sync", scheduler.run_incremental_sync(
                 &profile_id_clone,
                 &base_url,
                 &username,
@@ -407,16 +927,125 @@ pub async fn start_content_sync(
                 &cache,
                 &progress_tx,
                 &cancel_token,
-            ).await
+            )).await
         };
-        
+
+        // Every progress update goes out before the completion event
+        drop(progress_tx);
+        let _ = forwarder.await;
+        let final_progress = match &result {
+            Ok(progress) => progress.clone(),
+            Err(e) => SyncProgress {
+                status: crate::content_cache::SyncStatus::Failed,
+                errors: vec![e.to_string()],
+                ..scheduler.get_sync_status(&profile_id_clone).unwrap_or_default()
+            },
+        };
+        events::emit(
+            &app_handle,
+            AppEvent::SyncComplete(ProfileSyncProgress {
+                profile_id: profile_id_clone.clone(),
+                progress: final_progress,
+            }),
+        );
+
+        // Saved rows lose their name order, so large categories are ordered again
+        if let Err(e) = cache.refresh_sort_indexes(&profile_id_clone) {
+            eprintln!("[WARN] Failed to refresh name order for profile {}: {}", profile_id_clone, e);
+        }
+
+        // New and changed items need their tokens for "more like this"
+        if let Err(e) = cache.refresh_similarity_tokens(&profile_id_clone) {
+            eprintln!("[WARN] Failed to refresh similar content for profile {}: {}", profile_id_clone, e);
+        }
+        if let Err(e) = cache.refresh_popularity(&profile_id_clone) {
+            eprintln!("[WARN] Failed to refresh popularity for profile {}: {}", profile_id_clone, e);
+        }
+
         // Unregister sync when complete
         let _ = scheduler.unregister_sync(&profile_id_clone);
+        drop(sync_slot);
+        drop(task);
+        
+        // Record the run so trends in duration and list size can be shown
+        let (channels_count, movies_count, series_count) =
+            cache.get_content_counts(&profile_id_clone).unwrap_or(counts_before);
+        let (status, errors) = match &result {
+            Ok(progress) => (progress.status.clone(), progress.errors.clone()),
+            Err(e) => (crate::content_cache::SyncStatus::Failed, vec![e.to_string()]),
+        };
+        let entry = crate::content_cache::SyncHistoryEntry {
+            id: 0,
+            profile_id: profile_id_clone.clone(),
+            full_sync,
+            status,
+            started_at: started_at.to_rfc3339(),
+            duration_ms: started.elapsed().as_millis() as i64,
+            channels_count,
+            movies_count,
+            series_count,
+            channels_delta: channels_count as i64 - counts_before.0 as i64,
+            movies_delta: movies_count as i64 - counts_before.1 as i64,
+            series_delta: series_count as i64 - counts_before.2 as i64,
+            errors,
+        };
+        if let Err(e) = scheduler.record_sync_run(&entry) {
+            eprintln!("[WARN] Failed to record sync history for profile {}: {}", profile_id_clone, e);
+        }
+        
+        // Favorited series that gained episodes get an alert whatever the sync settings
+        let episodes_after = favorite_episode_counts(&cache, &profile_id_clone);
+        for (series_name, added) in favorites_sync::new_episodes(&episodes_before, &episodes_after) {
+            notifications::notify(
+                &app_handle,
+                Notification {
+                    kind: NotificationKind::NewEpisode,
+                    title: format!("New episodes of {}", series_name),
+                    body: format!("{} new episode{}", added, if added == 1 { "" } else { "s" }),
+                    profile_id: Some(profile_id_clone.clone()),
+                },
+            );
+        }
+        // So do followed categories that gained items, which also go into their feed
+        for added in new_followed_category_items(&cache, &profile_id_clone, &followed_before) {
+            notifications::notify(
+                &app_handle,
+                Notification {
+                    kind: NotificationKind::FollowedCategory,
+                    title: format!("New in {}", added.category_name),
+                    body: added.summary(),
+                    profile_id: Some(profile_id_clone.clone()),
+                },
+            );
+        }
+        if notify_on_complete {
+            let success = matches!(&result, Ok(progress) if progress.status != crate::content_cache::SyncStatus::Failed);
+            notifications::notify(&app_handle, sync_notification(&profile_id_clone, &profile_name, success));
+        }
         
         // Log result
         match result {
             Ok(progress) => {
                 println!("[INFO] Sync completed for profile {}: {:?}", profile_id_clone, progress.status);
+                
+                if progress.status == crate::content_cache::SyncStatus::Completed {
+                    tokio::spawn(crate::image_prefetch::after_sync(
+                        app_handle.clone(),
+                        Arc::clone(&cache),
+                        profile_id_clone.clone(),
+                    ));
+                    crate::xtream::automation::spawn_hooks(
+                        automation,
+                        profile_id_clone.clone(),
+                        crate::xtream::AutomationEvent::AfterSync,
+                        serde_json::json!({
+                            "full_sync": full_sync,
+                            "channels_synced": progress.channels_synced,
+                            "movies_synced": progress.movies_synced,
+                            "series_synced": progress.series_synced,
+                        }),
+                    );
+                }
             }
             Err(e) => {
                 eprintln!("[ERROR] Sync failed for profile {}: {}", profile_id_clone, e);
@@ -424,17 +1053,70 @@ pub async fn start_content_sync(
         }
     });
     
-    // Spawn a task to consume progress updates (in a real app, you'd emit these as events)
-    tokio::spawn(async move {
-        while let Some(progress) = progress_rx.recv().await {
-            // In a real implementation, you would emit this as a Tauri event
-            // For now, just log it
-            println!("[SYNC PROGRESS] Profile: {}, Status: {:?}, Progress: {}%", 
-                     profile_id, progress.status, progress.progress);
-        }
-    });
-    
-    Ok(())
+    Ok(deferred_until)
+}
+
+/// Cached episode counts of a profile's favorited series; empty if they can't be read
+fn favorite_episode_counts(cache: &ContentCache, profile_id: &str) -> HashMap<i64, (String, i64)> {
+    let db = cache.get_db();
+    let counts = match db.lock() {
+        Ok(conn) => favorites_sync::favorite_series_episode_counts(&conn, profile_id),
+        Err(_) => return HashMap::new(),
+    };
+    counts.unwrap_or_else(|e| {
+        eprintln!("[WARN] Failed to count episodes of favorite series for profile {}: {}", profile_id, e);
+        HashMap::new()
+    })
+}
+
+/// Items in a profile's followed categories; empty if they can't be read
+fn followed_category_items(cache: &ContentCache, profile_id: &str) -> CategorySnapshot {
+    let db = cache.get_db();
+    let snapshot = match db.lock() {
+        Ok(conn) => followed_categories::snapshot(&conn, profile_id),
+        Err(_) => return CategorySnapshot::new(),
+    };
+    snapshot.unwrap_or_else(|e| {
+        eprintln!("[WARN] Failed to read followed categories for profile {}: {}", profile_id, e);
+        CategorySnapshot::new()
+    })
+}
+
+/// Add the items followed categories gained since `before` to their feed
+fn new_followed_category_items(cache: &ContentCache, profile_id: &str, before: &CategorySnapshot) -> Vec<NewCategoryItems> {
+    if before.is_empty() {
+        return Vec::new();
+    }
+    let db = cache.get_db();
+    let added = match db.lock() {
+        Ok(conn) => followed_categories::record_new_items(&conn, profile_id, before, chrono::Utc::now().timestamp()),
+        Err(_) => return Vec::new(),
+    };
+    added.unwrap_or_else(|e| {
+        eprintln!("[WARN] Failed to record new items of followed categories for profile {}: {}", profile_id, e);
+        Vec::new()
+    })
+}
+
+/// Start a complete sync of a profile's categories, channels, movies and series
+/// 
+/// The sync runs in a background task, starting right away even outside the
+/// maintenance window. Its progress is saved to the profile's sync status and
+/// sent as `sync://progress` events, followed by one `sync://complete` event.
+/// 
+/// # Returns
+/// Ok(()) once the sync has started, error if one is already running for the profile
+#[tauri::command]
+pub async fn start_profile_sync(
+    app_handle: AppHandle,
+    cache_state: State<'_, ContentCacheState>,
+    xtream_state: State<'_, crate::xtream::XtreamState>,
+    automation_state: State<'_, crate::xtream::AutomationState>,
+    profile_id: String,
+) -> std::result::Result<(), String> {
+    start_content_sync(app_handle, cache_state, xtream_state, automation_state, profile_id, true, Some(true))
+        .await
+        .map(|_| ())
 }
 
 /// Cancel an active content synchronization
@@ -497,6 +1179,87 @@ pub async fn get_sync_status(
     get_sync_progress(state, profile_id).await
 }
 
+/// Get the most recent sync runs for a profile
+/// 
+/// # Arguments
+/// * `state` - Content cache state containing the sync scheduler
+/// * `profile_id` - The profile ID to get history for
+/// * `limit` - Maximum number of runs to return (default: 20)
+/// 
+/// # Returns
+/// Sync runs, newest first, with durations, item deltas and errors
+#[tauri::command]
+pub async fn get_sync_history(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+    limit: Option<usize>,
+) -> std::result::Result<Vec<crate::content_cache::SyncHistoryEntry>, String> {
+    state
+        .sync_scheduler
+        .get_sync_history(&profile_id, limit.unwrap_or(20))
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Preview what syncing a profile would fetch, without saving any content
+/// 
+/// Authenticates, then fetches each content type's categories and counts its
+/// items. Nothing is written to the content cache.
+/// 
+/// # Arguments
+/// * `cache_state` - Content cache state, for the profile's sync history
+/// * `xtream_state` - Xtream state for accessing profile credentials
+/// * `profile_id` - A saved profile to preview
+/// * `credentials` - Credentials to preview instead, for a profile not created yet
+/// 
+/// # Returns
+/// Categories and item counts per content type, with an estimated sync time
+#[tauri::command]
+pub async fn preview_xtream_profile(
+    cache_state: State<'_, ContentCacheState>,
+    xtream_state: State<'_, crate::xtream::XtreamState>,
+    profile_id: Option<String>,
+    credentials: Option<crate::xtream::ProfileCredentials>,
+) -> std::result::Result<ProfilePreview, String> {
+    let (credentials, history) = match (profile_id, credentials) {
+        (Some(profile_id), _) => {
+            let credentials = xtream_state
+                .profile_manager
+                .get_profile_credentials(&profile_id)
+                .map_err(|e| format!("Failed to get profile credentials: {}", e))?;
+            let history = cache_state
+                .sync_scheduler
+                .get_sync_history(&profile_id, 20)
+                .unwrap_or_default();
+            (credentials, history)
+        }
+        (None, Some(credentials)) => (credentials, Vec::new()),
+        (None, None) => return Err("A profile ID or credentials are required".to_string()),
+    };
+    
+    let client = crate::xtream::XtreamClient::new(credentials.clone(), xtream_state.content_cache.clone())
+        .map_err(|e| e.user_message())?;
+    let mut auth = client.authenticate().await.map_err(|e| e.user_message())?;
+    
+    let mut preview = preview::preview_content(
+        credentials.url.trim_end_matches('/'),
+        &credentials.username,
+        &credentials.password,
+        &history,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    
+    preview.user_info = auth.get_mut("user_info").map(serde_json::Value::take);
+    if crate::anonymize::is_demo_mode() {
+        if let Some(user_info) = preview.user_info.as_mut() {
+            crate::anonymize::mask_json(user_info);
+        }
+    }
+    
+    Ok(preview)
+}
+
 /// Get sync settings for a profile
 /// 
 /// # Arguments
@@ -516,6 +1279,33 @@ pub async fn get_sync_settings(
         .map_err(|e| e.to_string())
 }
 
+/// Request limits learned from a profile's provider health
+/// 
+/// Shows whether server errors and timeouts have throttled the profile's syncs,
+/// and how far.
+#[tauri::command]
+pub async fn get_sync_limits(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+) -> std::result::Result<SyncLimits, String> {
+    state
+        .sync_scheduler
+        .get_sync_limits(&profile_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Forget a profile's learned request limits, so its next sync runs unthrottled
+#[tauri::command]
+pub async fn reset_sync_limits(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+) -> std::result::Result<(), String> {
+    state
+        .sync_scheduler
+        .reset_sync_limits(&profile_id)
+        .map_err(|e| e.to_string())
+}
+
 /// Update sync settings for a profile
 /// 
 /// # Arguments
@@ -641,6 +1431,10 @@ mod tests {
             tv_archive: Some(0),
             direct_source: None,
             tv_archive_duration: Some(0),
+            url_overridden: false,
+            country: None,
+            country_flag: None,
+            reliability: None,
         }
     }
     
@@ -674,6 +1468,7 @@ mod tests {
             director: None,
             plot: None,
             youtube_trailer: None,
+            age_rating: None,
         }
     }
     
@@ -702,6 +1497,7 @@ mod tests {
             rating_5based: rating,
             episode_run_time: None,
             category_id: Some(category_id.to_string()),
+            age_rating: None,
         }
     }
     
@@ -753,15 +1549,49 @@ mod tests {
         let filter = ChannelFilter {
             category_id: Some("news".to_string()),
             name_contains: None,
+            country: None,
             limit: None,
             offset: None,
+            sort_mode: ChannelSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_channels("test_profile", Some(filter)).unwrap();
         assert_eq!(result.len(), 2);
         assert!(result.iter().all(|c| c.category_id.as_ref().unwrap() == "news"));
     }
-    
+
+    #[test]
+    fn test_get_cached_channels_with_country_filter() {
+        let db = create_test_db();
+        insert_test_profile(&db, "test_profile");
+        let cache = ContentCache::new(db).unwrap();
+        cache.initialize_profile("test_profile").unwrap();
+
+        let channels = vec![
+            create_test_channel(1, "UK: BBC One", "news"),
+            create_test_channel(2, "|US| CNN", "news"),
+            create_test_channel(3, "UK: Sky News", "news"),
+            create_test_channel(4, "ESPN", "sports"),
+        ];
+
+        cache.save_channels("test_profile", channels).unwrap();
+
+        let filter = ChannelFilter {
+            country: Some("uk".to_string()),
+            ..Default::default()
+        };
+
+        let result = cache.get_channels("test_profile", Some(filter.clone())).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().all(|c| c.country.as_deref() == Some("GB")));
+        assert_eq!(result[0].country_flag.as_deref(), Some("🇬🇧"));
+        assert_eq!(cache.count_channels("test_profile", Some(filter)).unwrap(), 2);
+
+        let countries = cache.get_channel_countries("test_profile").unwrap();
+        assert_eq!(countries, vec![CountryCount::new("GB", 2), CountryCount::new("US", 1)]);
+    }
+
     #[test]
     fn test_get_cached_channels_with_pagination() {
         let db = create_test_db();
@@ -783,8 +1613,11 @@ mod tests {
         let filter = ChannelFilter {
             category_id: None,
             name_contains: None,
+            country: None,
             limit: Some(2),
             offset: Some(0),
+            sort_mode: ChannelSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_channels("test_profile", Some(filter)).unwrap();
@@ -794,8 +1627,11 @@ mod tests {
         let filter = ChannelFilter {
             category_id: None,
             name_contains: None,
+            country: None,
             limit: Some(2),
             offset: Some(2),
+            sort_mode: ChannelSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_channels("test_profile", Some(filter)).unwrap();
@@ -881,8 +1717,11 @@ mod tests {
         let filter = ChannelFilter {
             category_id: Some("news".to_string()),
             name_contains: None,
+            country: None,
             limit: None,
             offset: None,
+            sort_mode: ChannelSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         // Search for "World" but only in "news" category - should find BBC World News but not ESPN Sports News
@@ -1009,8 +1848,12 @@ mod tests {
             genre: None,
             year: None,
             min_rating: None,
+            min_age_rating: None,
+            max_age_rating: None,
             limit: None,
             offset: None,
+            sort_mode: VodSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_movies("test_profile", Some(filter), None, None).unwrap();
@@ -1040,8 +1883,12 @@ mod tests {
             genre: Some("Sci-Fi".to_string()),
             year: None,
             min_rating: None,
+            min_age_rating: None,
+            max_age_rating: None,
             limit: None,
             offset: None,
+            sort_mode: VodSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_movies("test_profile", Some(filter), None, None).unwrap();
@@ -1071,8 +1918,12 @@ mod tests {
             genre: None,
             year: None,
             min_rating: Some(4.7),
+            min_age_rating: None,
+            max_age_rating: None,
             limit: None,
             offset: None,
+            sort_mode: VodSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_movies("test_profile", Some(filter), None, None).unwrap();
@@ -1080,6 +1931,38 @@ mod tests {
         assert!(result.iter().all(|m| m.rating.unwrap_or(0.0) >= 4.7));
     }
     
+    #[test]
+    fn test_get_cached_movies_with_age_rating_filter() {
+        let db = create_test_db();
+        insert_test_profile(&db, "test_profile");
+        let cache = ContentCache::new(db).unwrap();
+        cache.initialize_profile("test_profile").unwrap();
+        
+        let mut movies = vec![
+            create_test_movie(1, "Toy Story", "family", None, None, None),
+            create_test_movie(2, "Inception", "action", None, None, None),
+            create_test_movie(3, "Alien", "horror", None, None, None),
+            create_test_movie(4, "Unrated Doc", "docs", None, None, None),
+        ];
+        movies[0].age_rating = Some(0);
+        movies[1].age_rating = Some(13);
+        movies[2].age_rating = Some(17);
+        
+        cache.save_movies("test_profile", movies).unwrap();
+        
+        use crate::content_cache::MovieFilter;
+        let filter = MovieFilter {
+            min_age_rating: Some(10),
+            max_age_rating: Some(16),
+            ..Default::default()
+        };
+        
+        let result = cache.get_movies("test_profile", Some(filter), None, None).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "Inception");
+        assert_eq!(result[0].age_rating, Some(13));
+    }
+    
     #[test]
     fn test_search_cached_movies() {
         let db = create_test_db();
@@ -1141,8 +2024,12 @@ mod tests {
             genre: None,
             year: None,
             min_rating: Some(4.5),
+            min_age_rating: None,
+            max_age_rating: None,
             limit: None,
             offset: None,
+            sort_mode: VodSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.search_movies("test_profile", "Matrix", Some(filter), None, None).unwrap();
@@ -1150,6 +2037,55 @@ mod tests {
         assert_eq!(result[0].name, "The Matrix");
     }
     
+    #[test]
+    fn test_hidden_content_is_left_out_of_listings_and_searches() {
+        use crate::xtream::tags::{XtreamTagsDb, HIDDEN_TAG};
+
+        let db = create_test_db();
+        insert_test_profile(&db, "test_profile");
+        let cache = ContentCache::new(db.clone()).unwrap();
+        cache.initialize_profile("test_profile").unwrap();
+
+        cache
+            .save_channels(
+                "test_profile",
+                vec![
+                    create_test_channel(1, "CNN International", "news"),
+                    create_test_channel(2, "CNN Adult", "news"),
+                ],
+            )
+            .unwrap();
+        cache
+            .save_movies(
+                "test_profile",
+                vec![
+                    create_test_movie(1, "The Matrix", "action", None, None, None),
+                    create_test_movie(2, "The Matrix Reloaded", "action", None, None, None),
+                ],
+            )
+            .unwrap();
+        {
+            let conn = db.lock().unwrap();
+            XtreamTagsDb::add_tag(&conn, "test_profile", "channel", "2", HIDDEN_TAG).unwrap();
+            XtreamTagsDb::add_tag(&conn, "test_profile", "vod", "2", HIDDEN_TAG).unwrap();
+        }
+
+        let names = |channels: Vec<XtreamChannel>| channels.into_iter().map(|c| c.name).collect::<Vec<_>>();
+        assert_eq!(names(cache.get_channels("test_profile", None).unwrap()), vec!["CNN International"]);
+        assert_eq!(names(cache.search_channels("test_profile", "CNN", None).unwrap()), vec!["CNN International"]);
+        assert_eq!(names(cache.fts_search_channels("test_profile", "CNN", None).unwrap()), vec!["CNN International"]);
+        assert_eq!(cache.count_channels("test_profile", None).unwrap(), 1);
+
+        let titles = |movies: Vec<crate::content_cache::XtreamMovie>| movies.into_iter().map(|m| m.name).collect::<Vec<_>>();
+        assert_eq!(titles(cache.get_movies("test_profile", None, None, None).unwrap()), vec!["The Matrix"]);
+        assert_eq!(
+            titles(cache.search_movies("test_profile", "Matrix", None, None, None).unwrap()),
+            vec!["The Matrix"]
+        );
+        assert_eq!(titles(cache.fts_search_movies("test_profile", "Matrix", None).unwrap()), vec!["The Matrix"]);
+        assert_eq!(cache.count_movies("test_profile", None).unwrap(), 1);
+    }
+    
     // ==================== Series Tests ====================
     
     #[test]
@@ -1204,8 +2140,12 @@ mod tests {
             genre: None,
             year: None,
             min_rating: None,
+            min_age_rating: None,
+            max_age_rating: None,
             limit: None,
             offset: None,
+            sort_mode: VodSortMode::Default,
+            search_mode: SearchMode::Simple,
         };
         
         let result = cache.get_series("test_profile", Some(filter)).unwrap();
@@ -1341,4 +2281,145 @@ mod tests {
         let result = cache.get_series_details("test_profile", 999);
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_rewrite_stream_urls() {
+        let cache = setup_test_cache();
+        insert_test_profile(&cache.get_db(), "test_profile");
+        insert_test_profile(&cache.get_db(), "other_profile");
+        
+        let mut channel = create_test_channel(1, "News", "1");
+        channel.direct_source = Some("http://test.com/live/olduser/oldpass/1.ts".to_string());
+        let mut untouched = create_test_channel(2, "Sports", "1");
+        untouched.direct_source = Some("http://cdn.test.com/2.ts".to_string());
+        cache.save_channels("test_profile", vec![channel.clone(), untouched]).unwrap();
+        cache.save_channels("other_profile", vec![channel]).unwrap();
+        
+        let rewritten = crate::content_cache::rewrite_profile_stream_urls(
+            &cache.get_db().lock().unwrap(),
+            "test_profile",
+            "/olduser/oldpass/",
+            "/newuser/newpass/",
+        )
+        .unwrap();
+        assert_eq!(rewritten, 1);
+        
+        let channels = cache.get_channels("test_profile", None).unwrap();
+        let news = channels.iter().find(|c| c.stream_id == 1).unwrap();
+        assert_eq!(
+            news.direct_source.as_deref(),
+            Some("http://test.com/live/newuser/newpass/1.ts")
+        );
+        let sports = channels.iter().find(|c| c.stream_id == 2).unwrap();
+        assert_eq!(sports.direct_source.as_deref(), Some("http://cdn.test.com/2.ts"));
+        
+        // Other profiles are left alone
+        let other = cache.get_channels("other_profile", None).unwrap();
+        assert!(other[0].direct_source.as_deref().unwrap().contains("/olduser/oldpass/"));
+    }
+    
+    #[test]
+    fn test_rewrite_stream_url_prefix() {
+        let cache = setup_test_cache();
+        insert_test_profile(&cache.get_db(), "test_profile");
+        
+        let mut moved = create_test_channel(1, "News", "1");
+        moved.direct_source = Some("http://old.test.com:8080/live/u/p/1.ts".to_string());
+        let mut other_port = create_test_channel(2, "Sports", "1");
+        other_port.direct_source = Some("http://old.test.com:80801/live/u/p/2.ts".to_string());
+        cache.save_channels("test_profile", vec![moved, other_port]).unwrap();
+        
+        let rewritten = crate::content_cache::rewrite_profile_stream_url_prefix(
+            &cache.get_db().lock().unwrap(),
+            "test_profile",
+            "http://old.test.com:8080",
+            "https://new.test.com",
+        )
+        .unwrap();
+        assert_eq!(rewritten, 1);
+        
+        let channels = cache.get_channels("test_profile", None).unwrap();
+        let news = channels.iter().find(|c| c.stream_id == 1).unwrap();
+        assert_eq!(news.direct_source.as_deref(), Some("https://new.test.com/live/u/p/1.ts"));
+        let sports = channels.iter().find(|c| c.stream_id == 2).unwrap();
+        assert_eq!(
+            sports.direct_source.as_deref(),
+            Some("http://old.test.com:80801/live/u/p/2.ts")
+        );
+    }
+    
+    #[test]
+    fn test_channel_url_override() {
+        let cache = setup_test_cache();
+        insert_test_profile(&cache.get_db(), "test_profile");
+        
+        let mut channel = create_test_channel(1, "News", "1");
+        channel.direct_source = Some("http://test.com/live/u/p/1.ts".to_string());
+        cache.save_channels("test_profile", vec![channel.clone(), create_test_channel(2, "Sports", "1")]).unwrap();
+        
+        assert!(cache.set_channel_url_override("test_profile", 1, "ftp://mirror.test.com/1").is_err());
+        cache
+            .set_channel_url_override("test_profile", 1, "https://mirror.test.com/news.m3u8")
+            .unwrap();
+        
+        let channels = cache.get_channels("test_profile", None).unwrap();
+        let news = channels.iter().find(|c| c.stream_id == 1).unwrap();
+        assert!(news.url_overridden);
+        assert_eq!(news.direct_source.as_deref(), Some("https://mirror.test.com/news.m3u8"));
+        let sports = channels.iter().find(|c| c.stream_id == 2).unwrap();
+        assert!(!sports.url_overridden);
+        
+        let found = cache.search_channels("test_profile", "News", None).unwrap();
+        assert!(found[0].url_overridden);
+        
+        // Overrides survive the channel being dropped and re-synced
+        cache.delete_channels("test_profile", None).unwrap();
+        cache.save_channels("test_profile", vec![channel]).unwrap();
+        assert_eq!(
+            cache.get_channel_url_override("test_profile", 1).unwrap().as_deref(),
+            Some("https://mirror.test.com/news.m3u8")
+        );
+        assert!(cache.get_channels("test_profile", None).unwrap()[0].url_overridden);
+        assert_eq!(cache.get_channel_url_overrides("test_profile").unwrap().len(), 1);
+        
+        assert!(cache.remove_channel_url_override("test_profile", 1).unwrap());
+        assert!(!cache.remove_channel_url_override("test_profile", 1).unwrap());
+        let channels = cache.get_channels("test_profile", None).unwrap();
+        assert!(!channels[0].url_overridden);
+        assert_eq!(channels[0].direct_source.as_deref(), Some("http://test.com/live/u/p/1.ts"));
+    }
+    
+    #[test]
+    fn test_epg_programs_round_trip() {
+        let cache = setup_test_cache();
+        insert_test_profile(&cache.get_db(), "test_profile");
+        
+        let now = chrono::Utc::now().timestamp();
+        let listing = |channel: &str, title: &str, start: i64, stop: i64| {
+            serde_json::json!({
+                "channel_id": channel,
+                "title": title,
+                "start_timestamp": start.to_string(),
+                "stop_timestamp": stop.to_string()
+            })
+        };
+        let epg = serde_json::json!({
+            "epg_listings": [
+                listing("news.uk", "Headlines", now, now + 1800),
+                listing("news.uk", "Weather", now + 1800, now + 3600),
+                listing("sport.uk", "Match", now, now + 5400)
+            ]
+        });
+        assert_eq!(cache.save_epg_programs("test_profile", None, &epg).unwrap(), 3);
+        
+        let news = cache.get_epg_programs("test_profile", "news.uk", now + 2000, now + 7200).unwrap();
+        assert_eq!(news.len(), 1);
+        assert_eq!(news[0].title, "Weather");
+        assert_eq!(cache.get_epg_programs("test_profile", "news.uk", now, now + 3600).unwrap().len(), 2);
+        
+        assert_eq!(cache.delete_epg_programs("test_profile", Some("news.uk")).unwrap(), 2);
+        assert!(cache.get_epg_programs("test_profile", "news.uk", now, now + 3600).unwrap().is_empty());
+        assert_eq!(cache.get_epg_programs("test_profile", "sport.uk", now, now + 3600).unwrap().len(), 1);
+        assert_eq!(cache.delete_epg_programs("test_profile", None).unwrap(), 1);
+    }
 }
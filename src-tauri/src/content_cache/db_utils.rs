@@ -1,6 +1,69 @@
 use crate::error::{Result, XTauriError};
-use rusqlite::{Connection, Transaction};
-use std::time::Instant;
+use rusqlite::{Connection, ErrorCode, Transaction, TransactionBehavior};
+use std::time::{Duration, Instant};
+
+/// First pause before retrying a busy database; doubles on each attempt
+pub const BUSY_RETRY_INITIAL_DELAY: Duration = Duration::from_millis(5);
+
+/// Longest pause between two attempts
+pub const BUSY_RETRY_MAX_DELAY: Duration = Duration::from_millis(100);
+
+/// Total time spent waiting before a busy database is reported as an error
+pub const BUSY_RETRY_MAX_WAIT: Duration = Duration::from_secs(2);
+
+/// Whether an error is SQLite reporting the database as busy or locked
+///
+/// These are transient: another connection holds a lock that it will release shortly.
+pub fn is_busy_error(error: &XTauriError) -> bool {
+    matches!(
+        error,
+        XTauriError::Database(rusqlite::Error::SqliteFailure(e, _))
+            if matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Run a database operation, retrying while the database is busy or locked
+///
+/// Retries back off from `BUSY_RETRY_INITIAL_DELAY` and stop once
+/// `BUSY_RETRY_MAX_WAIT` has been spent waiting; the operation then fails with
+/// `XTauriError::DatabaseBusy`. Other errors are returned straight away, so the
+/// operation must leave nothing behind when it fails busy, e.g. by running in a
+/// transaction.
+pub fn with_busy_retry<T, F>(operation_name: &str, query_fn: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    retry_while_busy(operation_name, BUSY_RETRY_MAX_WAIT, query_fn)
+}
+
+fn retry_while_busy<T, F>(operation_name: &str, max_wait: Duration, mut query_fn: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let mut attempts = 0;
+    let mut waited = Duration::ZERO;
+    let mut delay = BUSY_RETRY_INITIAL_DELAY;
+
+    loop {
+        attempts += 1;
+        match query_fn() {
+            Err(e) if is_busy_error(&e) => {
+                if waited >= max_wait {
+                    eprintln!(
+                        "[ERROR] Database still busy after {} attempts: {}",
+                        attempts, operation_name
+                    );
+                    return Err(XTauriError::database_busy(operation_name, attempts));
+                }
+                let pause = delay.min(max_wait - waited);
+                std::thread::sleep(pause);
+                waited += pause;
+                delay = (delay * 2).min(BUSY_RETRY_MAX_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
 
 /// Transaction helper that provides automatic rollback on error
 /// 
@@ -21,8 +84,13 @@ impl<'conn> TransactionHelper<'conn> {
     /// 
     /// # Returns
     /// A new TransactionHelper with an active transaction
+    ///
+    /// The write lock is taken up front, waiting while another connection holds it.
     pub fn new(conn: &'conn mut Connection, operation_name: &str) -> Result<Self> {
-        let tx = conn.unchecked_transaction()?;
+        let conn: &'conn Connection = conn;
+        let tx = with_busy_retry(operation_name, || {
+            Ok(Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?)
+        })?;
         
         #[cfg(debug_assertions)]
         println!("[DEBUG] Starting transaction: {}", operation_name);
@@ -705,4 +773,67 @@ mod tests {
         
         assert!(error_result.is_err());
     }
+    
+    fn contended_db(dir: &tempfile::TempDir) -> (Connection, Connection) {
+        let path = dir.path().join("contended.sqlite");
+        let holder = Connection::open(&path).unwrap();
+        holder
+            .execute("CREATE TABLE test_items (id INTEGER PRIMARY KEY, name TEXT NOT NULL)", [])
+            .unwrap();
+        let waiter = Connection::open(&path).unwrap();
+        // Surface SQLITE_BUSY at once instead of waiting in SQLite's own busy handler
+        waiter.busy_timeout(Duration::ZERO).unwrap();
+        (holder, waiter)
+    }
+    
+    #[test]
+    fn test_busy_retry_waits_for_lock_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let (holder, waiter) = contended_db(&dir);
+        
+        holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            holder.execute_batch("COMMIT").unwrap();
+        });
+        
+        let inserted = with_busy_retry("insert_while_locked", || {
+            Ok(waiter.execute("INSERT INTO test_items (name) VALUES ('a')", [])?)
+        })
+        .unwrap();
+        release.join().unwrap();
+        
+        assert_eq!(inserted, 1);
+    }
+    
+    #[test]
+    fn test_busy_retry_gives_up_with_typed_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let (holder, waiter) = contended_db(&dir);
+        
+        holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let result = retry_while_busy("insert_while_locked", Duration::from_millis(20), || {
+            Ok(waiter.execute("INSERT INTO test_items (name) VALUES ('a')", [])?)
+        });
+        
+        match result {
+            Err(XTauriError::DatabaseBusy { operation, attempts }) => {
+                assert_eq!(operation, "insert_while_locked");
+                assert!(attempts > 1);
+            }
+            other => panic!("expected DatabaseBusy, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_busy_retry_does_not_retry_other_errors() {
+        let mut attempts = 0;
+        let result: Result<()> = with_busy_retry("failing_query", || {
+            attempts += 1;
+            Err(XTauriError::content_cache("not busy".to_string()))
+        });
+        
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
 }
@@ -0,0 +1,245 @@
+// Delta syncs: apply only what changed on the provider
+//
+// Every item saved to the cache gets a fingerprint of the provider's data for
+// it, taken before post-processing. An incremental sync fingerprints the
+// provider's current list and compares it with the cached fingerprints: new
+// ids are inserted, ids whose fingerprint differs are replaced, and cached ids
+// the provider no longer lists are deleted. Unchanged items are not written.
+use crate::content_cache::{ContentCache, XtreamChannel, XtreamMovie, XtreamSeries};
+use crate::error::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// A content item that can be synced by delta
+pub trait DeltaItem: Serialize + Sized {
+    /// Content type as named in the cache ("channels", "movies" or "series")
+    const CONTENT_TYPE: &'static str;
+
+    fn content_id(&self) -> i64;
+
+    /// Save items to the cache
+    fn store(cache: &ContentCache, profile_id: &str, items: Vec<Self>) -> Result<usize>;
+}
+
+impl DeltaItem for XtreamChannel {
+    const CONTENT_TYPE: &'static str = "channels";
+
+    fn content_id(&self) -> i64 {
+        self.stream_id
+    }
+
+    fn store(cache: &ContentCache, profile_id: &str, items: Vec<Self>) -> Result<usize> {
+        cache.save_channels(profile_id, items)
+    }
+}
+
+impl DeltaItem for XtreamMovie {
+    const CONTENT_TYPE: &'static str = "movies";
+
+    fn content_id(&self) -> i64 {
+        self.stream_id
+    }
+
+    fn store(cache: &ContentCache, profile_id: &str, items: Vec<Self>) -> Result<usize> {
+        cache.save_movies(profile_id, items)
+    }
+}
+
+impl DeltaItem for XtreamSeries {
+    const CONTENT_TYPE: &'static str = "series";
+
+    fn content_id(&self) -> i64 {
+        self.series_id
+    }
+
+    fn store(cache: &ContentCache, profile_id: &str, items: Vec<Self>) -> Result<usize> {
+        cache.save_series(profile_id, items)
+    }
+}
+
+/// What a delta sync has to write for one content type
+#[derive(Debug)]
+pub struct ContentDelta<T> {
+    /// Items that are new or changed since they were cached
+    pub upserts: Vec<T>,
+    /// Ids of the upserts that replace a cached item
+    pub replaced: Vec<i64>,
+    /// Cached ids the provider no longer lists
+    pub removed: Vec<i64>,
+    pub unchanged: usize,
+}
+
+impl<T> ContentDelta<T> {
+    pub fn is_empty(&self) -> bool {
+        self.upserts.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Fingerprint of an item's provider data
+pub fn fingerprint<T: Serialize>(item: &T) -> String {
+    let data = serde_json::to_vec(item).unwrap_or_default();
+    Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn fingerprints<T: DeltaItem>(items: &[T]) -> Vec<(i64, String)> {
+    items.iter().map(|item| (item.content_id(), fingerprint(item))).collect()
+}
+
+/// Remember the fingerprints of saved items
+pub fn record_fingerprints(
+    conn: &Connection,
+    profile_id: &str,
+    content_type: &str,
+    fingerprints: &[(i64, String)],
+) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO xtream_content_fingerprints (profile_id, content_type, content_id, fingerprint)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (content_id, fingerprint) in fingerprints {
+            stmt.execute(params![profile_id, content_type, content_id, fingerprint])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Forget the fingerprints of deleted items
+pub fn forget_fingerprints(conn: &Connection, profile_id: &str, content_type: &str, ids: &[i64]) -> Result<()> {
+    let ids = serde_json::to_string(ids).unwrap_or_default();
+    conn.execute(
+        "DELETE FROM xtream_content_fingerprints
+         WHERE profile_id = ?1 AND content_type = ?2 AND content_id IN (SELECT value FROM json_each(?3))",
+        params![profile_id, content_type, ids],
+    )?;
+    Ok(())
+}
+
+/// Compare the provider's items with the cached fingerprints, keyed by cached id
+///
+/// A cached item without a fingerprint, such as one saved before fingerprints
+/// were kept, counts as changed.
+pub fn plan<T: DeltaItem>(server_items: Vec<T>, cached: &HashMap<i64, Option<String>>) -> ContentDelta<T> {
+    let mut delta = ContentDelta {
+        upserts: Vec::new(),
+        replaced: Vec::new(),
+        removed: Vec::new(),
+        unchanged: 0,
+    };
+    let mut listed = std::collections::HashSet::with_capacity(server_items.len());
+
+    for item in server_items {
+        let id = item.content_id();
+        if !listed.insert(id) {
+            continue;
+        }
+        match cached.get(&id) {
+            None => delta.upserts.push(item),
+            Some(Some(cached)) if *cached == fingerprint(&item) => delta.unchanged += 1,
+            Some(_) => {
+                delta.replaced.push(id);
+                delta.upserts.push(item);
+            }
+        }
+    }
+
+    delta.removed = cached.keys().filter(|id| !listed.contains(id)).copied().collect();
+    delta.removed.sort_unstable();
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn setup_cache() -> ContentCache {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("PRAGMA foreign_keys = ON", []).unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('p1', 'Test', 'http://test.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+        ContentCache::new(Arc::new(Mutex::new(conn))).unwrap()
+    }
+
+    fn series(series_id: i64, name: &str) -> XtreamSeries {
+        serde_json::from_value(serde_json::json!({ "series_id": series_id, "name": name })).unwrap()
+    }
+
+    #[test]
+    fn test_plan_sorts_items_into_new_changed_and_removed() {
+        let cached = HashMap::from([
+            (1, Some(fingerprint(&series(1, "Same")))),
+            (2, Some(fingerprint(&series(2, "Old name")))),
+            (3, None),
+            (4, Some(fingerprint(&series(4, "Gone")))),
+        ]);
+        let server = vec![
+            series(1, "Same"),
+            series(2, "New name"),
+            series(3, "Unknown"),
+            series(5, "Added"),
+            series(5, "Added twice"),
+        ];
+
+        let delta = plan(server, &cached);
+        let upserts: Vec<&str> = delta.upserts.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(upserts, vec!["New name", "Unknown", "Added"]);
+        assert_eq!(delta.replaced, vec![2, 3]);
+        assert_eq!(delta.removed, vec![4]);
+        assert_eq!(delta.unchanged, 1);
+        assert!(!delta.is_empty());
+
+        let delta = plan(vec![series(1, "Same")], &HashMap::from([(1, Some(fingerprint(&series(1, "Same"))))]));
+        assert!(delta.is_empty());
+    }
+
+    #[test]
+    fn test_delta_sync_writes_only_changes_and_keeps_search_current() {
+        let cache = setup_cache();
+        cache
+            .save_series("p1", vec![series(1, "Breaking Point"), series(2, "Old Show"), series(3, "Lost Signal")])
+            .unwrap();
+        let fingerprints = cache.get_content_fingerprints("p1", "series").unwrap();
+        assert!(fingerprints.values().all(Option::is_some));
+
+        let server = vec![series(1, "Breaking Point"), series(2, "Renamed Show"), series(4, "Fresh Start")];
+        let delta = plan(server, &fingerprints);
+        assert_eq!(delta.unchanged, 1);
+        assert_eq!(cache.apply_content_delta("p1", delta).unwrap(), 3);
+
+        let mut ids = cache.get_content_ids("p1", "series").unwrap();
+        ids.sort_unstable();
+        assert_eq!(ids, vec![1, 2, 4]);
+        let found = cache.fts_search_series("p1", "renamed", None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].series_id, 2);
+        assert!(cache.fts_search_series("p1", "old", None).unwrap().is_empty());
+        assert!(cache.fts_search_series("p1", "signal", None).unwrap().is_empty());
+        assert_eq!(cache.fts_search_series("p1", "fresh", None).unwrap().len(), 1);
+
+        let delta = plan(
+            vec![series(1, "Breaking Point"), series(2, "Renamed Show"), series(4, "Fresh Start")],
+            &cache.get_content_fingerprints("p1", "series").unwrap(),
+        );
+        assert!(delta.is_empty());
+    }
+}
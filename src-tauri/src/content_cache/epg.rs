@@ -0,0 +1,629 @@
+// Local EPG programme store used to enrich channel listings with what's on now
+use crate::content_cache::XtreamChannel;
+use crate::error::{Result, XTauriError};
+use crate::images::{self, ImageBatch, ImageGroup};
+use crate::xtream::XtreamClient;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// How long programmes are kept after they end
+pub const EPG_RETENTION_SECS: i64 = 24 * 60 * 60;
+
+/// Title of the filler programmes that cover gaps between listings
+pub const FILLER_TITLE: &str = "No information";
+
+/// The programme currently airing on a channel
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct NowPlaying {
+    pub title: String,
+    pub start_timestamp: i64,
+    pub stop_timestamp: i64,
+    pub progress_percent: u8,
+}
+
+impl NowPlaying {
+    fn new(title: String, start_timestamp: i64, stop_timestamp: i64, now: i64) -> Self {
+        let length = (stop_timestamp - start_timestamp).max(1);
+        let elapsed = (now - start_timestamp).clamp(0, length);
+        Self {
+            title,
+            start_timestamp,
+            stop_timestamp,
+            progress_percent: (elapsed * 100 / length) as u8,
+        }
+    }
+}
+
+/// Read a timestamp that providers send either as a number or a numeric string
+fn listing_timestamp(program: &Value, keys: &[&str]) -> Option<i64> {
+    keys.iter().filter_map(|key| program.get(*key)).find_map(|value| {
+        value
+            .as_i64()
+            .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+    })
+}
+
+/// A programme in the guide grid
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct EpgProgramme {
+    pub title: String,
+    pub description: Option<String>,
+    pub start_timestamp: i64,
+    pub stop_timestamp: i64,
+    /// A filler covering a gap the provider left between programmes
+    pub synthetic: bool,
+    /// Image the guide lists for the programme
+    pub icon_url: Option<String>,
+    /// Cached file of `icon_url`; only looked up for the guide grid
+    pub cached_icon: Option<String>,
+}
+
+/// Replace a channel's filler programmes with ones covering its current gaps
+///
+/// Fillers are rebuilt from the real listings each time, so a listing that
+/// arrives later for a gap takes its place.
+fn fill_gaps(conn: &Connection, profile_id: &str, epg_channel_id: &str) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM xtream_epg_programs WHERE profile_id = ?1 AND epg_channel_id = ?2 AND synthetic = 1",
+        params![profile_id, epg_channel_id],
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT start_timestamp, stop_timestamp FROM xtream_epg_programs
+         WHERE profile_id = ?1 AND epg_channel_id = ?2
+         ORDER BY start_timestamp",
+    )?;
+    let spans = stmt
+        .query_map(params![profile_id, epg_channel_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut insert = conn.prepare(
+        "INSERT INTO xtream_epg_programs
+         (profile_id, epg_channel_id, start_timestamp, stop_timestamp, title, synthetic)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+    )?;
+    let mut filled = 0;
+    let mut covered_until: Option<i64> = None;
+    for (start, stop) in spans {
+        // Measured from the latest end so far, since listings can overlap
+        if let Some(gap_start) = covered_until.filter(|&end| start > end) {
+            insert.execute(params![profile_id, epg_channel_id, gap_start, start, FILLER_TITLE])?;
+            filled += 1;
+        }
+        covered_until = Some(covered_until.map_or(stop, |end| end.max(stop)));
+    }
+    Ok(filled)
+}
+
+/// Save the programmes from an EPG response and drop ones that ended long ago
+///
+/// Listings name their EPG channel in `channel_id` or `epg_id`; when they don't,
+/// the EPG id of the cached channel `stream_id` is used. Gaps between a
+/// channel's programmes are covered with synthetic "No information" fillers.
+/// Returns the number of programmes stored, not counting fillers.
+pub fn store_epg_programs(
+    conn: &Connection,
+    profile_id: &str,
+    stream_id: Option<&str>,
+    epg_data: &Value,
+    now: i64,
+) -> Result<usize> {
+    let fallback_channel_id: Option<String> = match stream_id.and_then(|id| id.parse::<i64>().ok()) {
+        Some(stream_id) => conn
+            .query_row(
+                "SELECT epg_channel_id FROM xtream_channels WHERE profile_id = ?1 AND stream_id = ?2",
+                params![profile_id, stream_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten(),
+        None => None,
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    let mut stored = 0;
+    let mut channels = BTreeSet::new();
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO xtream_epg_programs
+             (profile_id, epg_channel_id, start_timestamp, stop_timestamp, title, description, icon_url)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+
+        for program in XtreamClient::parse_epg_programs(epg_data)? {
+            let channel_id = ["channel_id", "epg_id"]
+                .iter()
+                .filter_map(|key| program.get(*key).and_then(|v| v.as_str()))
+                .map(str::trim)
+                .find(|id| !id.is_empty())
+                .map(str::to_string)
+                .or_else(|| fallback_channel_id.clone());
+            let start = listing_timestamp(&program, &["start_timestamp", "start"]);
+            let stop = listing_timestamp(&program, &["stop_timestamp", "stop"]);
+            let title = program.get("title").and_then(|v| v.as_str()).map(str::trim);
+
+            let (Some(channel_id), Some(start), Some(stop), Some(title)) = (channel_id, start, stop, title) else {
+                continue;
+            };
+            if stop <= start || title.is_empty() {
+                continue;
+            }
+
+            let description = program.get("description").and_then(|v| v.as_str());
+            let icon_url = XtreamClient::epg_icon_url(&program);
+            stmt.execute(params![profile_id, channel_id, start, stop, title, description, icon_url])?;
+            stored += 1;
+            channels.insert(channel_id);
+        }
+
+        for channel_id in &channels {
+            fill_gaps(&tx, profile_id, channel_id)?;
+        }
+
+        tx.execute(
+            "DELETE FROM xtream_epg_programs WHERE profile_id = ?1 AND stop_timestamp < ?2",
+            params![profile_id, now - EPG_RETENTION_SECS],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(stored)
+}
+
+/// Current programme for every EPG channel with guide data, keyed by EPG channel id
+///
+/// Looks across all profiles when `profile_id` is `None`, which is how M3U channels
+/// borrow guide data through their `tvg-id`.
+pub fn now_playing_by_channel(
+    conn: &Connection,
+    profile_id: Option<&str>,
+    now: i64,
+) -> Result<HashMap<String, NowPlaying>> {
+    let mut stmt = conn.prepare(
+        "SELECT epg_channel_id, title, start_timestamp, stop_timestamp
+         FROM xtream_epg_programs
+         WHERE (?1 IS NULL OR profile_id = ?1)
+           AND start_timestamp <= ?2 AND stop_timestamp > ?2
+           AND synthetic = 0
+         ORDER BY start_timestamp",
+    )?;
+
+    let rows = stmt
+        .query_map(params![profile_id, now], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                NowPlaying::new(row.get(1)?, row.get(2)?, row.get(3)?, now),
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    // Overlapping listings resolve to the one that started last
+    Ok(rows.into_iter().collect())
+}
+
+/// Current programme for the given EPG channels only, in one query per 500 ids
+///
+/// For annotating search results, where scanning every airing programme would
+/// cost more than the handful of channels shown.
+pub fn now_playing_for_channels(
+    conn: &Connection,
+    profile_id: Option<&str>,
+    epg_channel_ids: &[&str],
+    now: i64,
+) -> Result<HashMap<String, NowPlaying>> {
+    let mut airing = HashMap::new();
+    for chunk in epg_channel_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT epg_channel_id, title, start_timestamp, stop_timestamp
+             FROM xtream_epg_programs
+             WHERE (?1 IS NULL OR profile_id = ?1)
+               AND start_timestamp <= ?2 AND stop_timestamp > ?2
+               AND synthetic = 0
+           AND synthetic = 0
+               AND epg_channel_id IN ({})
+             ORDER BY start_timestamp",
+            placeholders
+        ))?;
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&profile_id, &now];
+        params.extend(chunk.iter().map(|id| id as &dyn rusqlite::ToSql));
+        let rows = stmt.query_map(params.as_slice(), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                NowPlaying::new(row.get(1)?, row.get(2)?, row.get(3)?, now),
+            ))
+        })?;
+        // Overlapping listings resolve to the one that started last
+        for row in rows {
+            let (epg_channel_id, now_playing) = row?;
+            airing.insert(epg_channel_id, now_playing);
+        }
+    }
+    Ok(airing)
+}
+
+/// Programmes between `start` and `end` for each of the given EPG channels
+///
+/// Rows come back in start order with the provider's gaps covered by fillers,
+/// so a guide can lay them end to end.
+pub fn programme_grid(
+    conn: &Connection,
+    profile_id: &str,
+    epg_channel_ids: &[&str],
+    start: i64,
+    end: i64,
+) -> Result<HashMap<String, Vec<EpgProgramme>>> {
+    let mut grid: HashMap<String, Vec<EpgProgramme>> = HashMap::new();
+    for chunk in epg_channel_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT epg_channel_id, title, description, start_timestamp, stop_timestamp, synthetic, icon_url
+             FROM xtream_epg_programs
+             WHERE profile_id = ?1 AND stop_timestamp > ?2 AND start_timestamp < ?3
+               AND epg_channel_id IN ({})
+             ORDER BY start_timestamp",
+            placeholders
+        ))?;
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&profile_id, &start, &end];
+        params.extend(chunk.iter().map(|id| id as &dyn rusqlite::ToSql));
+        let rows = stmt.query_map(params.as_slice(), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                EpgProgramme {
+                    title: row.get(1)?,
+                    description: row.get(2)?,
+                    start_timestamp: row.get(3)?,
+                    stop_timestamp: row.get(4)?,
+                    synthetic: row.get(5)?,
+                    icon_url: row.get(6)?,
+                    cached_icon: None,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (epg_channel_id, programme) = row?;
+            grid.entry(epg_channel_id).or_default().push(programme);
+        }
+    }
+    Ok(grid)
+}
+
+/// Logo of each of the given EPG channels, from the cached channel that carries it
+///
+/// Channels sharing an EPG id resolve to the lowest stream id with a logo.
+pub fn channel_icons(conn: &Connection, profile_id: &str, epg_channel_ids: &[&str]) -> Result<HashMap<String, String>> {
+    let mut icons = HashMap::new();
+    for chunk in epg_channel_ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT epg_channel_id, stream_icon FROM xtream_channels
+             WHERE profile_id = ?1 AND stream_icon IS NOT NULL AND stream_icon <> ''
+               AND epg_channel_id IN ({})
+             ORDER BY stream_id DESC",
+            placeholders
+        ))?;
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&profile_id];
+        params.extend(chunk.iter().map(|id| id as &dyn rusqlite::ToSql));
+        let rows = stmt.query_map(params.as_slice(), |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        // Later rows win, so the lowest stream id is kept
+        for row in rows {
+            let (epg_channel_id, icon_url) = row?;
+            icons.insert(epg_channel_id, icon_url);
+        }
+    }
+    Ok(icons)
+}
+
+/// A channel logo in the guide grid
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct EpgChannelIcon {
+    pub icon_url: String,
+    /// Cached file of `icon_url`, when it is cached
+    pub cached_icon: Option<String>,
+}
+
+/// The guide for a set of EPG channels, with the images it shows
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct EpgGrid {
+    /// Programmes by EPG channel id, in start order
+    pub programmes: HashMap<String, Vec<EpgProgramme>>,
+    /// Logo of each channel that has one, by EPG channel id
+    pub channel_icons: HashMap<String, EpgChannelIcon>,
+    /// Download of the images that weren't cached yet; an `image_fetched` event follows for each
+    pub icon_batch: Option<ImageBatch>,
+}
+
+impl EpgGrid {
+    /// Images to fetch, channel logos ahead of programme images
+    pub fn uncached_icons(&self) -> Vec<ImageGroup> {
+        let logos = self
+            .channel_icons
+            .values()
+            .filter(|icon| icon.cached_icon.is_none())
+            .map(|icon| icon.icon_url.clone())
+            .collect();
+        let programme_images = self
+            .programmes
+            .values()
+            .flatten()
+            .filter(|programme| programme.cached_icon.is_none())
+            .filter_map(|programme| programme.icon_url.clone())
+            .collect();
+
+        [ImageGroup { priority: 1, urls: logos }, ImageGroup { priority: 0, urls: programme_images }]
+            .into_iter()
+            .filter(|group| !group.urls.is_empty())
+            .collect()
+    }
+}
+
+/// Programmes and channel logos for a guide, with the images already cached filled in
+///
+/// `icon_batch` is left for the caller, which queues `uncached_icons`.
+pub fn guide_grid(conn: &Connection, profile_id: &str, epg_channel_ids: &[&str], start: i64, end: i64) -> Result<EpgGrid> {
+    let mut programmes = programme_grid(conn, profile_id, epg_channel_ids, start, end)?;
+    let logos = channel_icons(conn, profile_id, epg_channel_ids)?;
+
+    let urls: Vec<&str> = logos
+        .values()
+        .map(String::as_str)
+        .chain(programmes.values().flatten().filter_map(|programme| programme.icon_url.as_deref()))
+        .collect();
+    let cached = images::cached_paths(conn, &urls)?;
+
+    for programme in programmes.values_mut().flatten() {
+        programme.cached_icon = programme.icon_url.as_ref().and_then(|url| cached.get(url)).cloned();
+    }
+    let channel_icons = logos
+        .into_iter()
+        .map(|(epg_channel_id, icon_url)| {
+            let cached_icon = cached.get(&icon_url).cloned();
+            (epg_channel_id, EpgChannelIcon { icon_url, cached_icon })
+        })
+        .collect();
+
+    Ok(EpgGrid { programmes, channel_icons, icon_batch: None })
+}
+
+/// Remove a profile's stored programmes, fillers included, for one EPG channel or all of them
+///
+/// Returns the number of rows removed.
+pub fn delete_epg_programs(conn: &Connection, profile_id: &str, epg_channel_id: Option<&str>) -> Result<usize> {
+    let removed = conn.execute(
+        "DELETE FROM xtream_epg_programs WHERE profile_id = ?1 AND (?2 IS NULL OR epg_channel_id = ?2)",
+        params![profile_id, epg_channel_id],
+    )?;
+    Ok(removed)
+}
+
+/// A cached Xtream channel, optionally with the programme airing now
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct XtreamChannelListing {
+    #[serde(flatten)]
+    pub channel: XtreamChannel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub now_playing: Option<NowPlaying>,
+    /// Cached file of `stream_icon`; `None` when it isn't cached or wasn't asked for
+    pub cached_icon: Option<String>,
+}
+
+/// Pair cached channels with their current programme, looked up in one batch
+pub fn annotate_channels(
+    conn: &Connection,
+    profile_id: &str,
+    channels: Vec<XtreamChannel>,
+    now: i64,
+) -> Result<Vec<XtreamChannelListing>> {
+    let epg_channel_ids: Vec<&str> = channels
+        .iter()
+        .filter_map(|channel| channel.epg_channel_id.as_deref())
+        .filter(|id| !id.is_empty())
+        .collect();
+    let airing = now_playing_for_channels(conn, Some(profile_id), &epg_channel_ids, now)?;
+
+    Ok(channels
+        .into_iter()
+        .map(|channel| XtreamChannelListing {
+            now_playing: channel
+                .epg_channel_id
+                .as_deref()
+                .and_then(|id| airing.get(id))
+                .cloned(),
+            channel,
+            cached_icon: None,
+        })
+        .collect())
+}
+
+/// Add a `now_playing` field to each channel in an Xtream channel list response
+pub fn attach_now_playing(conn: &Connection, profile_id: &str, channels: &mut Value, now: i64) -> Result<()> {
+    let Some(channels) = channels.as_array_mut() else {
+        return Ok(());
+    };
+
+    let airing = now_playing_by_channel(conn, Some(profile_id), now)?;
+    for channel in channels.iter_mut().filter(|c| c.is_object()) {
+        let now_playing = channel
+            .get("epg_channel_id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| airing.get(id))
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| XTauriError::content_cache(format!("Failed to serialize programme: {}", e)))?
+            .unwrap_or(Value::Null);
+        channel["now_playing"] = now_playing;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('p1', 'p1', 'http://test.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO xtream_channels (profile_id, stream_id, name, epg_channel_id)
+             VALUES ('p1', 7, 'News', 'news.uk')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_store_and_attach_now_playing() {
+        let conn = create_test_db();
+        let epg = json!({
+            "epg_listings": [
+                {"channel_id": "sport.uk", "title": "Match", "start_timestamp": "1000", "stop_timestamp": "2000"},
+                {"channel_id": "sport.uk", "title": "Studio", "start_timestamp": "2000", "stop_timestamp": "3000"},
+                {"title": "Headlines", "start": "900", "stop": "1900"},
+                {"channel_id": "sport.uk", "title": "Broken", "start_timestamp": "5000", "stop_timestamp": "4000"}
+            ]
+        });
+        assert_eq!(store_epg_programs(&conn, "p1", Some("7"), &epg, 1_500).unwrap(), 3);
+
+        let mut channels = json!([
+            {"stream_id": 7, "name": "News", "epg_channel_id": "news.uk"},
+            {"stream_id": 8, "name": "Sport", "epg_channel_id": "sport.uk"},
+            {"stream_id": 9, "name": "Music", "epg_channel_id": null}
+        ]);
+        attach_now_playing(&conn, "p1", &mut channels, 1_500).unwrap();
+
+        assert_eq!(channels[0]["now_playing"]["title"], "Headlines");
+        assert_eq!(channels[0]["now_playing"]["progress_percent"], 60);
+        assert_eq!(channels[1]["now_playing"]["title"], "Match");
+        assert_eq!(channels[1]["now_playing"]["progress_percent"], 50);
+        assert!(channels[2]["now_playing"].is_null());
+
+        let later = now_playing_by_channel(&conn, None, 2_500).unwrap();
+        assert_eq!(later.get("sport.uk").map(|p| p.title.as_str()), Some("Studio"));
+        assert!(!later.contains_key("news.uk"));
+    }
+
+    #[test]
+    fn test_now_playing_for_listed_channels_only() {
+        let conn = create_test_db();
+        let epg = json!([
+            {"channel_id": "news.uk", "title": "Headlines", "start": "900", "stop": "1900"},
+            {"channel_id": "sport.uk", "title": "Match", "start": "1000", "stop": "2000"}
+        ]);
+        store_epg_programs(&conn, "p1", None, &epg, 1_500).unwrap();
+
+        let airing = now_playing_for_channels(&conn, Some("p1"), &["sport.uk", "music.uk"], 1_500).unwrap();
+        assert_eq!(airing.len(), 1);
+        assert_eq!(airing["sport.uk"].title, "Match");
+        assert!(now_playing_for_channels(&conn, Some("p1"), &[], 1_500).unwrap().is_empty());
+        assert!(now_playing_for_channels(&conn, Some("other"), &["sport.uk"], 1_500).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gaps_are_filled_until_real_listings_arrive() {
+        let conn = create_test_db();
+        let epg = json!([
+            {"channel_id": "news.uk", "title": "Morning", "start": "1000", "stop": "2000"},
+            {"channel_id": "news.uk", "title": "Noon", "start": "3000", "stop": "4000"},
+            {"channel_id": "news.uk", "title": "Evening", "start": "3500", "stop": "5000"},
+            {"channel_id": "news.uk", "title": "Night", "start": "6000", "stop": "7000"}
+        ]);
+        assert_eq!(store_epg_programs(&conn, "p1", None, &epg, 1_500).unwrap(), 4);
+
+        let grid = programme_grid(&conn, "p1", &["news.uk", "sport.uk"], 0, 10_000).unwrap();
+        let spans: Vec<(i64, i64, bool)> = grid["news.uk"]
+            .iter()
+            .map(|p| (p.start_timestamp, p.stop_timestamp, p.synthetic))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (1000, 2000, false),
+                (2000, 3000, true),
+                (3000, 4000, false),
+                (3500, 5000, false),
+                (5000, 6000, true),
+                (6000, 7000, false)
+            ]
+        );
+        assert_eq!(grid["news.uk"][1].title, FILLER_TITLE);
+        assert!(!grid.contains_key("sport.uk"));
+        assert!(now_playing_by_channel(&conn, Some("p1"), 2_500).unwrap().is_empty());
+
+        let late = json!([{"channel_id": "news.uk", "title": "Lunch", "start": "2000", "stop": "3000"}]);
+        store_epg_programs(&conn, "p1", None, &late, 1_500).unwrap();
+        let grid = programme_grid(&conn, "p1", &["news.uk"], 1_500, 3_200).unwrap();
+        let titles: Vec<&str> = grid["news.uk"].iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["Morning", "Lunch", "Noon"]);
+    }
+
+    #[test]
+    fn test_guide_grid_carries_icons_and_cached_files() {
+        let conn = create_test_db();
+        images::create_index_table(&conn).unwrap();
+        conn.execute(
+            "UPDATE xtream_channels SET stream_icon = 'http://img.test/news.png' WHERE stream_id = 7",
+            [],
+        )
+        .unwrap();
+        let epg = json!([
+            {"channel_id": "news.uk", "title": "Morning", "start": "1000", "stop": "2000", "image": "http://img.test/morning.jpg"},
+            {"channel_id": "news.uk", "title": "Noon", "start": "2000", "stop": "3000", "icon": "not a url"}
+        ]);
+        store_epg_programs(&conn, "p1", None, &epg, 1_500).unwrap();
+        images::record_cached(&conn, "http://img.test/morning.jpg", std::path::Path::new("/cache/morning")).unwrap();
+
+        let grid = guide_grid(&conn, "p1", &["news.uk"], 0, 10_000).unwrap();
+        let programmes = &grid.programmes["news.uk"];
+        assert_eq!(programmes[0].icon_url.as_deref(), Some("http://img.test/morning.jpg"));
+        assert_eq!(programmes[0].cached_icon.as_deref(), Some("/cache/morning"));
+        assert_eq!(programmes[1].icon_url, None);
+        assert_eq!(
+            grid.channel_icons["news.uk"],
+            EpgChannelIcon { icon_url: "http://img.test/news.png".to_string(), cached_icon: None }
+        );
+
+        // Only the logo is left to fetch
+        let groups = grid.uncached_icons();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].priority, 1);
+        assert_eq!(groups[0].urls, vec!["http://img.test/news.png"]);
+    }
+
+    #[test]
+    fn test_store_prunes_old_programmes() {
+        let conn = create_test_db();
+        let epg = json!([{"channel_id": "news.uk", "title": "Old", "start": "0", "stop": "100"}]);
+        store_epg_programs(&conn, "p1", None, &epg, 200).unwrap();
+        store_epg_programs(&conn, "p1", None, &json!([]), 100 + EPG_RETENTION_SECS + 1).unwrap();
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM xtream_epg_programs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,444 @@
+// EPG ids for channels the provider left without one
+//
+// Many channels come without an `epg_channel_id`, so they show no guide. The
+// provider's XMLTV file lists its EPG channels by display name; each unmapped
+// channel's name is compared with those, normalized the way the failover
+// matcher does. A match that is both close and clearly better than the next one
+// is applied straight away; weaker ones are kept as suggestions to review.
+// Applied ids are remembered in `xtream_epg_mappings` and filled in again when
+// a sync saves the channels, unless the provider sends an id of its own.
+use crate::content_cache::failover::channel_identity_key;
+use crate::content_cache::XtreamChannel;
+use crate::epg_xmltv::XmltvChannel;
+use crate::error::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Matches at least this close are applied without review
+pub const AUTO_APPLY_CONFIDENCE: f64 = 0.9;
+
+/// Matches below this aren't suggested at all
+pub const SUGGEST_CONFIDENCE: f64 = 0.6;
+
+/// A runner-up this close to the best match makes it ambiguous
+const AMBIGUITY_MARGIN: f64 = 0.05;
+
+/// Candidates kept per channel for review
+const MAX_CANDIDATES: usize = 3;
+
+const STATUS_APPLIED: &str = "applied";
+const STATUS_SUGGESTED: &str = "suggested";
+const STATUS_REJECTED: &str = "rejected";
+
+/// An EPG channel a channel might show the guide of
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct EpgMappingCandidate {
+    pub epg_channel_id: String,
+    pub display_name: String,
+    /// From 0 to 1
+    pub confidence: f64,
+}
+
+/// A channel with the EPG channels it could be mapped to, best first
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct EpgMappingSuggestion {
+    pub stream_id: i64,
+    pub channel_name: String,
+    pub candidates: Vec<EpgMappingCandidate>,
+}
+
+/// What an auto-mapping pass did with the channels lacking an EPG id
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct EpgMappingReport {
+    pub applied: usize,
+    /// Channels left with suggestions to review
+    pub suggested: usize,
+    pub unmatched: usize,
+}
+
+/// Numbers in a normalized name, which tell "Sport 1" from "Sport 2" or a +1 channel
+fn numbers(key: &str) -> Vec<&str> {
+    key.split(' ').filter(|token| token.chars().all(|c| c.is_ascii_digit())).collect()
+}
+
+/// Character pairs of a name with its spaces taken out
+fn bigrams(key: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = key.chars().filter(|c| *c != ' ').collect();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// How likely two channel names are the same channel, from 0 to 1
+pub fn name_confidence(channel_name: &str, display_name: &str) -> f64 {
+    let (a, b) = (channel_identity_key(channel_name), channel_identity_key(display_name));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+
+    let (a_pairs, b_pairs) = (bigrams(&a), bigrams(&b));
+    let confidence = if a.replace(' ', "") == b.replace(' ', "") {
+        0.95
+    } else if a_pairs.is_empty() || b_pairs.is_empty() {
+        0.0
+    } else {
+        // Dice coefficient, each pair of one name matched at most once
+        let mut remaining = b_pairs.clone();
+        let shared = a_pairs
+            .iter()
+            .filter(|pair| match remaining.iter().position(|other| other == *pair) {
+                Some(index) => {
+                    remaining.swap_remove(index);
+                    true
+                }
+                None => false,
+            })
+            .count();
+        2.0 * shared as f64 / (a_pairs.len() + b_pairs.len()) as f64
+    };
+
+    if numbers(&a) == numbers(&b) {
+        confidence
+    } else {
+        confidence * 0.5
+    }
+}
+
+/// Whether an XMLTV id such as `bbc1.uk` carries the channel's country
+fn same_country(epg_channel_id: &str, country: Option<&str>) -> bool {
+    let Some(country) = country.map(str::to_lowercase) else {
+        return false;
+    };
+    let suffix = epg_channel_id.rsplit('.').next().unwrap_or_default().to_lowercase();
+    suffix == country || (country == "gb" && suffix == "uk")
+}
+
+/// Guide channels close enough to suggest, best first, with whether each is in the channel's country
+fn rank(name: &str, country: Option<&str>, guide: &[XmltvChannel]) -> Vec<(EpgMappingCandidate, bool)> {
+    let mut ranked: Vec<(EpgMappingCandidate, bool)> = guide
+        .iter()
+        .filter_map(|channel| {
+            let (display_name, confidence) = channel
+                .display_names
+                .iter()
+                .map(|display_name| (display_name, name_confidence(name, display_name)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))?;
+            (confidence >= SUGGEST_CONFIDENCE).then(|| {
+                let candidate = EpgMappingCandidate {
+                    epg_channel_id: channel.id.clone(),
+                    display_name: display_name.clone(),
+                    confidence,
+                };
+                (candidate, same_country(&channel.id, country))
+            })
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.0.confidence
+            .total_cmp(&a.0.confidence)
+            .then(b.1.cmp(&a.1))
+            .then_with(|| a.0.epg_channel_id.cmp(&b.0.epg_channel_id))
+    });
+    ranked.truncate(MAX_CANDIDATES);
+    ranked
+}
+
+/// Whether the best candidate is safe to apply without review
+fn is_confident(ranked: &[(EpgMappingCandidate, bool)]) -> bool {
+    let Some((best, best_in_country)) = ranked.first() else {
+        return false;
+    };
+    if best.confidence < AUTO_APPLY_CONFIDENCE {
+        return false;
+    }
+    match ranked.get(1) {
+        None => true,
+        Some((runner_up, runner_up_in_country)) => {
+            runner_up.confidence < best.confidence - AMBIGUITY_MARGIN || (*best_in_country && !runner_up_in_country)
+        }
+    }
+}
+
+fn apply(conn: &Connection, profile_id: &str, stream_id: i64, candidate: &EpgMappingCandidate, now: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM xtream_epg_mappings WHERE profile_id = ?1 AND stream_id = ?2",
+        params![profile_id, stream_id],
+    )?;
+    conn.execute(
+        "INSERT INTO xtream_epg_mappings
+         (profile_id, stream_id, epg_channel_id, display_name, confidence, status, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            profile_id,
+            stream_id,
+            candidate.epg_channel_id,
+            candidate.display_name,
+            candidate.confidence,
+            STATUS_APPLIED,
+            now
+        ],
+    )?;
+    conn.execute(
+        "UPDATE xtream_channels SET epg_channel_id = ?3 WHERE profile_id = ?1 AND stream_id = ?2",
+        params![profile_id, stream_id, candidate.epg_channel_id],
+    )?;
+    Ok(())
+}
+
+/// Match a profile's channels without an EPG id against the guide's channels
+///
+/// Suggestions from an earlier pass are replaced; channels whose mapping was
+/// rejected are left alone.
+pub fn auto_map(conn: &Connection, profile_id: &str, guide: &[XmltvChannel], now: i64) -> Result<EpgMappingReport> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM xtream_epg_mappings WHERE profile_id = ?1 AND status = ?2",
+        params![profile_id, STATUS_SUGGESTED],
+    )?;
+
+    let unmapped = {
+        let mut stmt = tx.prepare(
+            "SELECT stream_id, name, country FROM xtream_channels c
+             WHERE profile_id = ?1 AND COALESCE(TRIM(epg_channel_id), '') = ''
+               AND NOT EXISTS (
+                   SELECT 1 FROM xtream_epg_mappings m
+                   WHERE m.profile_id = c.profile_id AND m.stream_id = c.stream_id AND m.status = ?2
+               )",
+        )?;
+        let rows = stmt.query_map(params![profile_id, STATUS_REJECTED], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+        })?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()?
+    };
+
+    let mut report = EpgMappingReport::default();
+    {
+        let mut suggest = tx.prepare(
+            "INSERT OR REPLACE INTO xtream_epg_mappings
+             (profile_id, stream_id, epg_channel_id, display_name, confidence, status, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for (stream_id, name, country) in unmapped {
+            let ranked = rank(&name, country.as_deref(), guide);
+            if is_confident(&ranked) {
+                apply(&tx, profile_id, stream_id, &ranked[0].0, now)?;
+                report.applied += 1;
+            } else if ranked.is_empty() {
+                report.unmatched += 1;
+            } else {
+                for (candidate, _) in &ranked {
+                    suggest.execute(params![
+                        profile_id,
+                        stream_id,
+                        candidate.epg_channel_id,
+                        candidate.display_name,
+                        candidate.confidence,
+                        STATUS_SUGGESTED,
+                        now
+                    ])?;
+                }
+                report.suggested += 1;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(report)
+}
+
+/// Channels with suggestions to review, the most likely matches first
+pub fn suggestions(conn: &Connection, profile_id: &str) -> Result<Vec<EpgMappingSuggestion>> {
+    let mut stmt = conn.prepare(
+        "SELECT m.stream_id, c.name, m.epg_channel_id, m.display_name, m.confidence
+         FROM xtream_epg_mappings m
+         JOIN xtream_channels c ON c.profile_id = m.profile_id AND c.stream_id = m.stream_id
+         WHERE m.profile_id = ?1 AND m.status = ?2
+         ORDER BY m.stream_id, m.confidence DESC",
+    )?;
+    let rows = stmt.query_map(params![profile_id, STATUS_SUGGESTED], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            EpgMappingCandidate {
+                epg_channel_id: row.get(2)?,
+                display_name: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                confidence: row.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+            },
+        ))
+    })?;
+
+    let mut suggestions: Vec<EpgMappingSuggestion> = Vec::new();
+    for row in rows {
+        let (stream_id, channel_name, candidate) = row?;
+        match suggestions.last_mut() {
+            Some(last) if last.stream_id == stream_id => last.candidates.push(candidate),
+            _ => suggestions.push(EpgMappingSuggestion { stream_id, channel_name, candidates: vec![candidate] }),
+        }
+    }
+    suggestions.sort_by(|a, b| {
+        b.candidates[0]
+            .confidence
+            .total_cmp(&a.candidates[0].confidence)
+            .then_with(|| a.channel_name.cmp(&b.channel_name))
+    });
+    Ok(suggestions)
+}
+
+/// Settle a channel's mapping: apply an EPG id, or with `None` leave it without a guide
+///
+/// Rejecting also takes back an id an earlier pass applied, and keeps later
+/// passes from suggesting one again.
+pub fn resolve(
+    conn: &Connection,
+    profile_id: &str,
+    stream_id: i64,
+    epg_channel_id: Option<&str>,
+    now: i64,
+) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    match epg_channel_id.map(str::trim).filter(|id| !id.is_empty()) {
+        Some(epg_channel_id) => {
+            let known: Option<(Option<String>, Option<f64>)> = tx
+                .query_row(
+                    "SELECT display_name, confidence FROM xtream_epg_mappings
+                     WHERE profile_id = ?1 AND stream_id = ?2 AND epg_channel_id = ?3",
+                    params![profile_id, stream_id, epg_channel_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            let (display_name, confidence) = known.unwrap_or((None, None));
+            let candidate = EpgMappingCandidate {
+                epg_channel_id: epg_channel_id.to_string(),
+                display_name: display_name.unwrap_or_default(),
+                confidence: confidence.unwrap_or(1.0),
+            };
+            apply(&tx, profile_id, stream_id, &candidate, now)?;
+        }
+        None => {
+            tx.execute(
+                "UPDATE xtream_channels SET epg_channel_id = NULL
+                 WHERE profile_id = ?1 AND stream_id = ?2 AND epg_channel_id IN (
+                     SELECT epg_channel_id FROM xtream_epg_mappings
+                     WHERE profile_id = ?1 AND stream_id = ?2 AND status = ?3
+                 )",
+                params![profile_id, stream_id, STATUS_APPLIED],
+            )?;
+            tx.execute(
+                "DELETE FROM xtream_epg_mappings WHERE profile_id = ?1 AND stream_id = ?2",
+                params![profile_id, stream_id],
+            )?;
+            tx.execute(
+                "INSERT INTO xtream_epg_mappings (profile_id, stream_id, epg_channel_id, status, updated_at)
+                 VALUES (?1, ?2, '', ?3, ?4)",
+                params![profile_id, stream_id, STATUS_REJECTED, now],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Fill in applied EPG ids on channels about to be saved without one
+pub fn apply_saved(conn: &Connection, profile_id: &str, channels: &mut [XtreamChannel]) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT stream_id, epg_channel_id FROM xtream_epg_mappings WHERE profile_id = ?1 AND status = ?2",
+    )?;
+    let mapped = stmt
+        .query_map(params![profile_id, STATUS_APPLIED], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+    if mapped.is_empty() {
+        return Ok(());
+    }
+
+    for channel in channels.iter_mut() {
+        let has_id = channel.epg_channel_id.as_deref().is_some_and(|id| !id.trim().is_empty());
+        if let (false, Some(epg_channel_id)) = (has_id, mapped.get(&channel.stream_id)) {
+            channel.epg_channel_id = Some(epg_channel_id.clone());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guide_channel(id: &str, names: &[&str]) -> XmltvChannel {
+        XmltvChannel { id: id.to_string(), display_names: names.iter().map(|name| name.to_string()).collect() }
+    }
+
+    #[test]
+    fn test_name_confidence() {
+        assert_eq!(name_confidence("UK: BBC One FHD", "BBC One"), 1.0);
+        assert_eq!(name_confidence("BBCOne", "BBC One"), 0.95);
+        assert!(name_confidence("Sky Sports Main Event", "Sky Sport Main Event") > AUTO_APPLY_CONFIDENCE);
+        // Numbers tell channels of one family apart
+        assert!(name_confidence("Sky Sports 1", "Sky Sports 2") < SUGGEST_CONFIDENCE);
+        assert!(name_confidence("BBC One +1", "BBC One") < SUGGEST_CONFIDENCE);
+        assert!(name_confidence("Discovery", "Eurosport") < SUGGEST_CONFIDENCE);
+    }
+
+    #[test]
+    fn test_auto_map_applies_confident_matches_and_suggests_the_rest() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_channels (profile_id TEXT, stream_id INTEGER, name TEXT, country TEXT, epg_channel_id TEXT);
+             CREATE TABLE xtream_epg_mappings (
+                 profile_id TEXT NOT NULL, stream_id INTEGER NOT NULL, epg_channel_id TEXT NOT NULL,
+                 display_name TEXT, confidence REAL, status TEXT NOT NULL, updated_at INTEGER NOT NULL,
+                 PRIMARY KEY (profile_id, stream_id, epg_channel_id)
+             );
+             INSERT INTO xtream_channels VALUES
+                 ('p1', 1, 'UK: BBC One HD', 'GB', NULL),
+                 ('p1', 2, 'Channel Four', NULL, ''),
+                 ('p1', 3, 'Cartoon Time', NULL, NULL),
+                 ('p1', 4, 'Has Guide', NULL, 'own.id'),
+                 ('p1', 5, 'Channel 4', NULL, NULL);",
+        )
+        .unwrap();
+        let guide = [
+            guide_channel("bbc1.uk", &["BBC One"]),
+            guide_channel("bbc1.ie", &["BBC One"]),
+            guide_channel("ch4.uk", &["Channel 4"]),
+            guide_channel("channel5.uk", &["Channel 5"]),
+            guide_channel("fourmusic.uk", &["Channel Four Music"]),
+        ];
+
+        let report = auto_map(&conn, "p1", &guide, 100).unwrap();
+        // BBC One ties on name but only one is in the channel's country
+        assert_eq!(report, EpgMappingReport { applied: 2, suggested: 1, unmatched: 1 });
+        let epg_id = |stream_id: i64| -> Option<String> {
+            conn.query_row(
+                "SELECT epg_channel_id FROM xtream_channels WHERE stream_id = ?1",
+                [stream_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(epg_id(1).as_deref(), Some("bbc1.uk"));
+        assert_eq!(epg_id(5).as_deref(), Some("ch4.uk"));
+        assert_eq!(epg_id(4).as_deref(), Some("own.id"));
+
+        let review = suggestions(&conn, "p1").unwrap();
+        assert_eq!(review.len(), 1);
+        assert_eq!(review[0].channel_name, "Channel Four");
+        assert_eq!(review[0].candidates[0].epg_channel_id, "fourmusic.uk");
+
+        // A resync keeps applied ids where the provider still sends none
+        let channel: XtreamChannel =
+            serde_json::from_value(serde_json::json!({"stream_id": 1, "name": "UK: BBC One HD"})).unwrap();
+        let mut channels = vec![channel];
+        apply_saved(&conn, "p1", &mut channels).unwrap();
+        assert_eq!(channels[0].epg_channel_id.as_deref(), Some("bbc1.uk"));
+
+        // Rejecting takes the id back and keeps the channel out of later passes
+        resolve(&conn, "p1", 1, None, 200).unwrap();
+        assert_eq!(epg_id(1), None);
+        resolve(&conn, "p1", 2, Some("ch4.uk"), 200).unwrap();
+        assert_eq!(epg_id(2).as_deref(), Some("ch4.uk"));
+        let report = auto_map(&conn, "p1", &guide, 300).unwrap();
+        assert_eq!(report, EpgMappingReport { applied: 0, suggested: 0, unmatched: 1 });
+        assert!(suggestions(&conn, "p1").unwrap().is_empty());
+    }
+}
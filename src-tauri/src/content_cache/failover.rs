@@ -0,0 +1,406 @@
+// Playback failover between copies of the same channel across profiles
+use crate::error::Result;
+use crate::content_cache::XtreamChannel;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// How long a reported failure keeps a source ranked behind healthy alternatives
+pub const FAILURE_COOLDOWN_SECS: i64 = 30 * 60;
+
+/// Score assumed for sources without playback reports, when ranking alternatives
+const UNKNOWN_RELIABILITY: u8 = 50;
+
+/// Name tokens that describe quality or encoding rather than the channel itself
+const QUALITY_TOKENS: &[&str] = &[
+    "hd", "fhd", "uhd", "sd", "4k", "8k", "hevc", "h264", "h265", "hq", "lq", "backup", "raw",
+];
+
+/// A playable copy of a channel
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct PlaybackCandidate {
+    pub profile_id: String,
+    pub stream_id: i64,
+    pub name: String,
+    pub epg_channel_id: Option<String>,
+    /// Stored stream URL (with any user override applied); when absent the URL is
+    /// generated from the profile credentials
+    pub direct_source: Option<String>,
+    pub url_overridden: bool,
+    pub failure_count: i64,
+    pub last_failure_at: Option<i64>,
+    /// Whether the source failed within the cooldown window
+    pub failing: bool,
+    /// Reliability score from playback reports, see `reliability_score`
+    pub reliability: Option<u8>,
+}
+
+/// What happened during playback of a source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackEvent {
+    /// Playback got its first frames
+    Started,
+    /// Playback stalled to buffer
+    Buffered,
+    /// Playback could not start or stopped with an error
+    Failed,
+}
+
+/// Reliability score from 0 to 100, or `None` for a source that was never played
+///
+/// The share of playback attempts that started, reduced by up to half for
+/// sources that keep buffering once started.
+pub fn reliability_score(starts: i64, buffers: i64, failures: i64) -> Option<u8> {
+    let attempts = starts + failures;
+    if attempts <= 0 {
+        return None;
+    }
+    let success = starts as f64 / attempts as f64;
+    let buffering_penalty = (buffers as f64 / starts.max(1) as f64 * 0.1).min(0.5);
+    Some((success * (1.0 - buffering_penalty) * 100.0).round() as u8)
+}
+
+impl PlaybackCandidate {
+    fn is_source(&self, profile_id: &str, stream_id: i64) -> bool {
+        self.profile_id == profile_id && self.stream_id == stream_id
+    }
+}
+
+/// Normalize a channel name into a key shared by copies of the same channel
+///
+/// Drops bracketed notes, short country/provider prefixes ("UK:", "US |") and
+/// quality tokens, so "UK: BBC One FHD" and "BBC ONE (backup)" both become "bbc one".
+pub fn channel_identity_key(name: &str) -> String {
+    let mut stripped = String::with_capacity(name.len());
+    let mut depth = 0;
+    for c in name.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = (depth - 1).max(0),
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+
+    let without_prefix = match stripped.split_once([':', '|']) {
+        Some((prefix, rest)) if prefix.trim().chars().count() <= 4 => rest,
+        _ => stripped.as_str(),
+    };
+
+    without_prefix
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty() && !QUALITY_TOKENS.contains(token))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find every cached copy of a channel across all profiles, best candidate first
+///
+/// Copies match on EPG channel id or on the normalized name. Sources that failed
+/// within the cooldown are moved to the back; otherwise the requested source comes
+/// first, then other sources from the same profile, then more reliable ones and
+/// those with fewer failures.
+pub fn get_playback_candidates(
+    conn: &Connection,
+    profile_id: &str,
+    stream_id: i64,
+    now: i64,
+) -> Result<Vec<PlaybackCandidate>> {
+    let origin: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT name, epg_channel_id FROM xtream_channels WHERE profile_id = ?1 AND stream_id = ?2",
+            params![profile_id, stream_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    let Some((name, epg_channel_id)) = origin else {
+        return Ok(Vec::new());
+    };
+
+    let key = channel_identity_key(&name);
+    let epg_channel_id = epg_channel_id.filter(|id| !id.trim().is_empty());
+
+    // Narrow the scan with the longest name token; exact matching happens below
+    let longest_token = key.split(' ').max_by_key(|token| token.len()).unwrap_or("");
+    let name_pattern = format!("%{}%", longest_token);
+
+    let mut stmt = conn.prepare(
+        "SELECT c.profile_id, c.stream_id, c.name, c.epg_channel_id,
+                COALESCE(o.url, c.direct_source), o.url IS NOT NULL,
+                COALESCE(f.failure_count, 0), f.last_failure_at,
+                s.start_count, s.buffer_count, s.failure_count
+         FROM xtream_channels c
+         LEFT JOIN xtream_channel_url_overrides o
+                ON o.profile_id = c.profile_id AND o.stream_id = c.stream_id
+         LEFT JOIN xtream_playback_failures f
+                ON f.profile_id = c.profile_id AND f.stream_id = c.stream_id
+         LEFT JOIN xtream_playback_stats s
+                ON s.profile_id = c.profile_id AND s.stream_id = c.stream_id
+         WHERE (?1 IS NOT NULL AND c.epg_channel_id = ?1)
+            OR (?2 != '' AND LOWER(c.name) LIKE ?3)
+            OR (c.profile_id = ?4 AND c.stream_id = ?5)",
+    )?;
+
+    let rows = stmt
+        .query_map(
+            params![epg_channel_id, longest_token, name_pattern, profile_id, stream_id],
+            |row| {
+                let last_failure_at: Option<i64> = row.get(7)?;
+                Ok(PlaybackCandidate {
+                    profile_id: row.get(0)?,
+                    stream_id: row.get(1)?,
+                    name: row.get(2)?,
+                    epg_channel_id: row.get(3)?,
+                    direct_source: row.get(4)?,
+                    url_overridden: row.get(5)?,
+                    failure_count: row.get(6)?,
+                    last_failure_at,
+                    failing: last_failure_at
+                        .map(|at| now - at < FAILURE_COOLDOWN_SECS)
+                        .unwrap_or(false),
+                    reliability: reliability_score(
+                        row.get::<_, Option<i64>>(8)?.unwrap_or(0),
+                        row.get::<_, Option<i64>>(9)?.unwrap_or(0),
+                        row.get::<_, Option<i64>>(10)?.unwrap_or(0),
+                    ),
+                })
+            },
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut candidates: Vec<PlaybackCandidate> = rows
+        .into_iter()
+        .filter(|candidate| {
+            let is_origin = candidate.is_source(profile_id, stream_id);
+            let same_epg = epg_channel_id.is_some() && candidate.epg_channel_id == epg_channel_id;
+            is_origin || same_epg || (!key.is_empty() && channel_identity_key(&candidate.name) == key)
+        })
+        .collect();
+
+    candidates.sort_by_key(|candidate| {
+        (
+            candidate.failing,
+            !candidate.is_source(profile_id, stream_id),
+            candidate.profile_id != profile_id,
+            std::cmp::Reverse(candidate.reliability.unwrap_or(UNKNOWN_RELIABILITY)),
+            candidate.failure_count,
+            candidate.stream_id,
+        )
+    });
+
+    Ok(candidates)
+}
+
+/// Record a playback failure for a source
+pub fn record_playback_failure(
+    conn: &Connection,
+    profile_id: &str,
+    stream_id: i64,
+    error: Option<&str>,
+    now: i64,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO xtream_playback_failures (profile_id, stream_id, failure_count, last_error, last_failure_at)
+         VALUES (?1, ?2, 1, ?3, ?4)
+         ON CONFLICT(profile_id, stream_id) DO UPDATE SET
+             failure_count = failure_count + 1,
+             last_error = ?3,
+             last_failure_at = ?4",
+        params![profile_id, stream_id, error, now],
+    )?;
+    Ok(())
+}
+
+/// Count a playback event for a source; failures also start the failover cooldown
+pub fn record_playback_event(
+    conn: &Connection,
+    profile_id: &str,
+    stream_id: i64,
+    event: PlaybackEvent,
+    error_code: Option<&str>,
+    now: i64,
+) -> Result<()> {
+    let (starts, buffers, failures) = match event {
+        PlaybackEvent::Started => (1, 0, 0),
+        PlaybackEvent::Buffered => (0, 1, 0),
+        PlaybackEvent::Failed => (0, 0, 1),
+    };
+    conn.execute(
+        "INSERT INTO xtream_playback_stats
+             (profile_id, stream_id, start_count, buffer_count, failure_count, last_error_code, last_event_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(profile_id, stream_id) DO UPDATE SET
+             start_count = start_count + ?3,
+             buffer_count = buffer_count + ?4,
+             failure_count = failure_count + ?5,
+             last_error_code = COALESCE(?6, last_error_code),
+             last_event_at = ?7",
+        params![profile_id, stream_id, starts, buffers, failures, error_code, now],
+    )?;
+
+    if event == PlaybackEvent::Failed {
+        record_playback_failure(conn, profile_id, stream_id, error_code, now)?;
+    }
+    Ok(())
+}
+
+/// Fill in the reliability score of listed channels
+pub fn apply_reliability(conn: &Connection, profile_id: &str, channels: &mut [XtreamChannel]) -> Result<()> {
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT stream_id, start_count, buffer_count, failure_count FROM xtream_playback_stats WHERE profile_id = ?1",
+    )?;
+    let scores = stmt
+        .query_map([profile_id], |row| {
+            Ok((row.get::<_, i64>(0)?, reliability_score(row.get(1)?, row.get(2)?, row.get(3)?)))
+        })?
+        .collect::<std::result::Result<std::collections::HashMap<_, _>, _>>()?;
+
+    for channel in channels.iter_mut() {
+        channel.reliability = scores.get(&channel.stream_id).copied().flatten();
+    }
+
+    Ok(())
+}
+
+/// Mark a source as failing and return the next candidate to try, if any
+pub fn report_playback_failure(
+    conn: &Connection,
+    profile_id: &str,
+    stream_id: i64,
+    error: Option<&str>,
+    now: i64,
+) -> Result<Option<PlaybackCandidate>> {
+    record_playback_event(conn, profile_id, stream_id, PlaybackEvent::Failed, error, now)?;
+
+    let next = get_playback_candidates(conn, profile_id, stream_id, now)?
+        .into_iter()
+        .find(|candidate| !candidate.failing && !candidate.is_source(profile_id, stream_id));
+
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+
+        for profile in ["home", "backup"] {
+            conn.execute(
+                "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+                 VALUES (?1, ?1, 'http://test.com', 'user', X'00')",
+                [profile],
+            )
+            .unwrap();
+        }
+
+        let channels: &[(&str, i64, &str, Option<&str>)] = &[
+            ("home", 1, "UK: BBC One FHD", Some("bbc1.uk")),
+            ("home", 2, "BBC One (backup)", None),
+            ("home", 3, "BBC Two HD", Some("bbc2.uk")),
+            ("backup", 10, "BBC ONE", None),
+            ("backup", 11, "Sky Sports", Some("bbc1.uk")),
+        ];
+        for (profile, stream_id, name, epg) in channels {
+            conn.execute(
+                "INSERT INTO xtream_channels (profile_id, stream_id, name, epg_channel_id) VALUES (?1, ?2, ?3, ?4)",
+                params![profile, stream_id, name, epg],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_channel_identity_key() {
+        assert_eq!(channel_identity_key("UK: BBC One FHD"), "bbc one");
+        assert_eq!(channel_identity_key("BBC ONE (backup)"), "bbc one");
+        assert_eq!(channel_identity_key("US | ESPN 4K [Event]"), "espn");
+        assert_eq!(channel_identity_key("Discovery: Science"), "discovery science");
+    }
+
+    #[test]
+    fn test_candidates_ordering() {
+        let conn = create_test_db();
+        let candidates = get_playback_candidates(&conn, "home", 1, 1_000).unwrap();
+        let ids: Vec<(&str, i64)> = candidates
+            .iter()
+            .map(|c| (c.profile_id.as_str(), c.stream_id))
+            .collect();
+
+        // Origin first, then same profile, then other profiles; "BBC Two" is excluded
+        assert_eq!(ids, vec![("home", 1), ("home", 2), ("backup", 10), ("backup", 11)]);
+        assert!(get_playback_candidates(&conn, "home", 999, 1_000).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_report_failure_suggests_next_candidate() {
+        let conn = create_test_db();
+
+        let next = report_playback_failure(&conn, "home", 1, Some("HTTP 404"), 1_000).unwrap();
+        assert_eq!(next.map(|c| c.stream_id), Some(2));
+
+        let next = report_playback_failure(&conn, "home", 2, None, 1_010).unwrap();
+        assert_eq!(next.map(|c| (c.profile_id, c.stream_id)), Some(("backup".to_string(), 10)));
+
+        // Failing sources sink to the back until the cooldown passes
+        let candidates = get_playback_candidates(&conn, "home", 1, 1_020).unwrap();
+        assert!(!candidates[0].failing);
+        assert!(candidates.last().unwrap().failing);
+
+        let later = get_playback_candidates(&conn, "home", 1, 1_000 + FAILURE_COOLDOWN_SECS + 60).unwrap();
+        assert_eq!(later[0].stream_id, 1);
+        assert_eq!(later[0].failure_count, 1);
+        assert!(!later[0].failing);
+    }
+
+    #[test]
+    fn test_reliability_score() {
+        assert_eq!(reliability_score(0, 0, 0), None);
+        assert_eq!(reliability_score(4, 0, 0), Some(100));
+        assert_eq!(reliability_score(3, 0, 1), Some(75));
+        assert_eq!(reliability_score(2, 2, 0), Some(90));
+        assert_eq!(reliability_score(1, 50, 0), Some(50));
+        assert_eq!(reliability_score(0, 0, 3), Some(0));
+    }
+
+    #[test]
+    fn test_playback_events_rank_alternatives() {
+        let conn = create_test_db();
+
+        // In the other profile, the copy matched by name fails while the one matched by EPG id plays
+        record_playback_event(&conn, "backup", 10, PlaybackEvent::Failed, Some("HTTP 503"), 100).unwrap();
+        record_playback_event(&conn, "backup", 11, PlaybackEvent::Started, None, 900).unwrap();
+        record_playback_event(&conn, "backup", 11, PlaybackEvent::Buffered, None, 900).unwrap();
+        record_playback_event(&conn, "home", 2, PlaybackEvent::Started, None, 900).unwrap();
+        record_playback_event(&conn, "home", 2, PlaybackEvent::Failed, None, 100).unwrap();
+
+        let candidates = get_playback_candidates(&conn, "home", 1, 1_000 + FAILURE_COOLDOWN_SECS).unwrap();
+        let ids: Vec<i64> = candidates.iter().map(|c| c.stream_id).collect();
+        // Same-profile copies still come first, however unreliable
+        assert_eq!(ids, vec![1, 2, 11, 10]);
+        assert_eq!(candidates[1].reliability, Some(50));
+        assert_eq!(candidates[2].reliability, Some(90));
+        assert_eq!(candidates[3].reliability, Some(0));
+        assert_eq!(candidates[3].failure_count, 1);
+    }
+}
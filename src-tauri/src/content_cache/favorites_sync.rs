@@ -0,0 +1,394 @@
+// Favorites-only sync
+//
+// On a slow connection a catalog refresh can take many minutes and most of it
+// is content a set-top user never opens. With `favorites_only` in a profile's
+// sync settings, incremental syncs refresh just the favorited channels
+// (fetching only the categories they are in), the short EPG of those channels
+// and the seasons and episodes of favorited series. The rest of the cache is
+// left as it was; a full sync still fetches the whole catalog.
+use crate::content_cache::sync_scheduler::{SyncProgress, SyncScheduler, SyncStatus};
+use crate::content_cache::{epg, ContentCache, XtreamEpisode, XtreamSeason, XtreamSeries, XtreamSeriesDetails};
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Ids of a profile's favorites of one content type
+fn favorite_ids(conn: &Connection, profile_id: &str, content_type: &str) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT content_id FROM xtream_favorites WHERE profile_id = ?1 AND content_type = ?2",
+    )?;
+    let ids = stmt
+        .query_map(params![profile_id, content_type], |row| row.get::<_, String>(0))?
+        .filter_map(|id| id.ok()?.trim().parse().ok())
+        .collect();
+    Ok(ids)
+}
+
+/// Categories the cached channels among `stream_ids` are in
+fn channel_categories(conn: &Connection, profile_id: &str, stream_ids: &[i64]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT category_id FROM xtream_channels
+         WHERE profile_id = ?1 AND stream_id = ?2 AND category_id IS NOT NULL",
+    )?;
+    let mut categories = Vec::new();
+    for stream_id in stream_ids {
+        for category in stmt.query_map(params![profile_id, stream_id], |row| row.get::<_, String>(0))? {
+            let category = category?;
+            if !categories.contains(&category) {
+                categories.push(category);
+            }
+        }
+    }
+    Ok(categories)
+}
+
+/// Name and cached episode count of each of a profile's favorited series
+pub fn favorite_series_episode_counts(conn: &Connection, profile_id: &str) -> Result<HashMap<i64, (String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.series_id, s.name, COUNT(e.episode_id)
+         FROM xtream_series s
+         LEFT JOIN xtream_episodes e ON e.profile_id = s.profile_id AND e.series_id = s.series_id
+         WHERE s.profile_id = ?1
+           AND CAST(s.series_id AS TEXT) IN (
+               SELECT TRIM(content_id) FROM xtream_favorites WHERE profile_id = ?1 AND content_type = 'series'
+           )
+         GROUP BY s.series_id, s.name",
+    )?;
+    let counts = stmt
+        .query_map([profile_id], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(counts)
+}
+
+/// Favorited series that gained episodes between two counts, with how many
+///
+/// Series without cached episodes before are left out; their episodes were
+/// only just fetched, not released.
+pub fn new_episodes(before: &HashMap<i64, (String, i64)>, after: &HashMap<i64, (String, i64)>) -> Vec<(String, i64)> {
+    let mut added: Vec<(String, i64)> = after
+        .iter()
+        .filter_map(|(series_id, (name, count))| {
+            let (_, previous) = before.get(series_id)?;
+            (*previous > 0 && count > previous).then(|| (name.clone(), count - previous))
+        })
+        .collect();
+    added.sort();
+    added
+}
+
+/// A field providers send either as a string or a number, as a string
+fn text(item: &Value, key: &str) -> Option<String> {
+    match item.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn number(item: &Value, key: &str) -> Option<f64> {
+    let value = item.get(key)?;
+    value.as_f64().or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// Turn a `get_series_info` response into series details for a cached series
+pub fn parse_series_details(series: XtreamSeries, data: &Value) -> XtreamSeriesDetails {
+    let seasons = data
+        .get("seasons")
+        .and_then(Value::as_array)
+        .map(|seasons| {
+            seasons
+                .iter()
+                .filter_map(|season| {
+                    Some(XtreamSeason {
+                        season_number: number(season, "season_number")? as i64,
+                        name: text(season, "name"),
+                        episode_count: number(season, "episode_count").map(|count| count as i64),
+                        overview: text(season, "overview"),
+                        air_date: text(season, "air_date"),
+                        cover: text(season, "cover"),
+                        cover_big: text(season, "cover_big"),
+                        vote_average: number(season, "vote_average"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Episodes come keyed by season number, or from some panels as a list of lists
+    let episode_lists: Vec<&Value> = match data.get("episodes") {
+        Some(Value::Object(by_season)) => by_season.values().collect(),
+        Some(Value::Array(lists)) => lists.iter().collect(),
+        _ => Vec::new(),
+    };
+    let episodes = episode_lists
+        .into_iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|episode| {
+            Some(XtreamEpisode {
+                episode_id: text(episode, "id")?,
+                season_number: number(episode, "season").unwrap_or(0.0) as i64,
+                episode_num: text(episode, "episode_num").unwrap_or_default(),
+                title: text(episode, "title"),
+                container_extension: text(episode, "container_extension"),
+                custom_sid: text(episode, "custom_sid"),
+                added: text(episode, "added"),
+                direct_source: text(episode, "direct_source"),
+                info_json: episode.get("info").map(Value::to_string),
+            })
+        })
+        .collect();
+
+    XtreamSeriesDetails {
+        series,
+        seasons,
+        episodes,
+    }
+}
+
+impl SyncScheduler {
+    /// Refresh only a profile's favorited channels, their EPG and favorited series
+    ///
+    /// Favorites that aren't in the cache yet are skipped; a full sync adds them.
+    ///
+    /// # Returns
+    /// Final sync progress with status
+    #[allow(clippy::too_many_arguments)]
+    pub async fn run_favorites_sync(
+        &self,
+        profile_id: &str,
+        base_url: &str,
+        username: &str,
+        password: &str,
+        content_cache: &ContentCache,
+        progress_tx: &mpsc::Sender<SyncProgress>,
+        cancel_token: &CancellationToken,
+    ) -> Result<SyncProgress> {
+        let client = crate::connection::client_builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| XTauriError::internal(format!("Failed to create HTTP client: {}", e)))?;
+        let retry_config = self.retry_config_for(profile_id);
+
+        let (channel_ids, series_ids, categories) = {
+            let db = content_cache.get_db();
+            let conn = db
+                .lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            let channel_ids = favorite_ids(&conn, profile_id, "channel")?;
+            let series_ids = favorite_ids(&conn, profile_id, "series")?;
+            let categories = channel_categories(&conn, profile_id, &channel_ids)?;
+            (channel_ids, series_ids, categories)
+        };
+
+        let mut progress = SyncProgress {
+            status: SyncStatus::Syncing,
+            progress: 0,
+            current_step: "Syncing favorite channels...".to_string(),
+            channels_synced: 0,
+            movies_synced: 0,
+            series_synced: 0,
+            errors: Vec::new(),
+        };
+        self.update_sync_status(profile_id, &progress)?;
+        let _ = progress_tx.send(progress.clone()).await;
+
+        let total_steps = 3;
+
+        // Step 1: Refresh favorited channels from the categories they are in
+        let favorites: HashSet<i64> = channel_ids.iter().copied().collect();
+        let mut channels = Vec::new();
+        for category in &categories {
+            if cancel_token.is_cancelled() {
+                return Err(XTauriError::internal("Sync cancelled".to_string()));
+            }
+            let fetched = Self::fetch_content_with_retry(
+                &client,
+                base_url,
+                username,
+                password,
+                "channels",
+                Some(category),
+                &retry_config,
+                cancel_token,
+            )
+            .await
+            .and_then(|data| Self::parse_channels(&data));
+            match fetched {
+                Ok(fetched) => channels.extend(fetched.into_iter().filter(|channel| favorites.contains(&channel.stream_id))),
+                Err(e) => progress.errors.push(format!("Channel category {} sync failed: {}", category, e)),
+            }
+        }
+        match content_cache.save_channels(profile_id, channels) {
+            Ok(count) => {
+                progress.channels_synced = count;
+                if count > 0 {
+                    self.update_last_sync_timestamp(profile_id, "channels")?;
+                }
+            }
+            Err(e) => progress.errors.push(format!("Saving favorite channels failed: {}", e)),
+        }
+
+        // Step 2: Refresh the EPG of favorited channels
+        progress.current_step = "Syncing favorite channel EPG...".to_string();
+        progress.progress = Self::calculate_progress(1, total_steps, 0.0);
+        self.update_sync_status(profile_id, &progress)?;
+        let _ = progress_tx.send(progress.clone()).await;
+
+        for stream_id in &channel_ids {
+            if cancel_token.is_cancelled() {
+                return Err(XTauriError::internal("Sync cancelled".to_string()));
+            }
+            let url = format!(
+                "{}/player_api.php?username={}&password={}&action=get_short_epg&stream_id={}",
+                base_url, username, password, stream_id
+            );
+            let stored = match Self::fetch_with_retry(&client, &url, &retry_config, cancel_token).await {
+                Ok(data) => content_cache
+                    .get_db()
+                    .lock()
+                    .map_err(|_| XTauriError::lock_acquisition("database connection"))
+                    .and_then(|conn| {
+                        let stream_id = stream_id.to_string();
+                        epg::store_epg_programs(&conn, profile_id, Some(&stream_id), &data, chrono::Utc::now().timestamp())
+                    }),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = stored {
+                progress.errors.push(format!("EPG sync for channel {} failed: {}", stream_id, e));
+            }
+        }
+
+        // Step 3: Refresh seasons and episodes of favorited series
+        progress.current_step = "Syncing favorite series...".to_string();
+        progress.progress = Self::calculate_progress(2, total_steps, 0.0);
+        self.update_sync_status(profile_id, &progress)?;
+        let _ = progress_tx.send(progress.clone()).await;
+
+        for series_id in &series_ids {
+            if cancel_token.is_cancelled() {
+                return Err(XTauriError::internal("Sync cancelled".to_string()));
+            }
+            let Ok(cached) = content_cache.get_series_details(profile_id, *series_id) else {
+                continue;
+            };
+            let saved = Self::fetch_series_details_with_retry(
+                &client,
+                base_url,
+                username,
+                password,
+                *series_id,
+                &retry_config,
+                cancel_token,
+            )
+            .await
+            .and_then(|data| {
+                content_cache.save_series_details(profile_id, *series_id, parse_series_details(cached.series, &data))
+            });
+            match saved {
+                Ok(()) => progress.series_synced += 1,
+                Err(e) => progress.errors.push(format!("Series {} sync failed: {}", series_id, e)),
+            }
+        }
+        if progress.series_synced > 0 {
+            self.update_last_sync_timestamp(profile_id, "series")?;
+        }
+
+        progress.progress = 100;
+        progress.status = if progress.errors.is_empty() {
+            progress.current_step = "Favorites sync completed successfully".to_string();
+            SyncStatus::Completed
+        } else if progress.channels_synced > 0 || progress.series_synced > 0 {
+            progress.current_step = format!("Favorites sync completed with {} errors", progress.errors.len());
+            SyncStatus::Partial
+        } else {
+            progress.current_step = "Favorites sync failed".to_string();
+            SyncStatus::Failed
+        };
+        self.save_sync_limits(&retry_config);
+        self.update_sync_status(profile_id, &progress)?;
+        let _ = progress_tx.send(progress.clone()).await;
+
+        Ok(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_series_details() {
+        let series = SyncScheduler::parse_series(&json!([{ "series_id": 9, "name": "Show" }]))
+            .unwrap()
+            .remove(0);
+        let data = json!({
+            "seasons": [{ "season_number": 1, "name": "Season 1", "episode_count": "2", "vote_average": "7.5" }],
+            "episodes": {
+                "1": [
+                    { "id": "101", "episode_num": 1, "title": "Pilot", "season": 1, "container_extension": "mkv", "info": { "duration": "00:42:00" } },
+                    { "id": 102, "episode_num": "2", "season": "1" },
+                    { "title": "No id" }
+                ]
+            }
+        });
+
+        let details = parse_series_details(series, &data);
+        assert_eq!(details.series.series_id, 9);
+        assert_eq!(details.seasons.len(), 1);
+        assert_eq!(details.seasons[0].episode_count, Some(2));
+        assert_eq!(details.seasons[0].vote_average, Some(7.5));
+        assert_eq!(details.episodes.len(), 2);
+        assert_eq!(details.episodes[0].episode_num, "1");
+        assert_eq!(details.episodes[0].info_json.as_deref(), Some(r#"{"duration":"00:42:00"}"#));
+        assert_eq!(details.episodes[1].episode_id, "102");
+        assert_eq!(details.episodes[1].season_number, 1);
+    }
+
+    #[test]
+    fn test_favorite_channel_categories() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT);
+             CREATE TABLE xtream_channels (profile_id TEXT, stream_id INTEGER, category_id TEXT);
+             INSERT INTO xtream_favorites VALUES ('p1', 'channel', '1'), ('p1', 'channel', '2'), ('p1', 'channel', '3'),
+                                                 ('p1', 'series', '9'), ('p2', 'channel', '4');
+             INSERT INTO xtream_channels VALUES ('p1', 1, 'news'), ('p1', 2, 'news'), ('p1', 3, 'sport'), ('p1', 5, 'movies');",
+        )
+        .unwrap();
+
+        let channel_ids = favorite_ids(&conn, "p1", "channel").unwrap();
+        assert_eq!(channel_ids, vec![1, 2, 3]);
+        assert_eq!(favorite_ids(&conn, "p1", "series").unwrap(), vec![9]);
+        assert_eq!(channel_categories(&conn, "p1", &channel_ids).unwrap(), vec!["news", "sport"]);
+    }
+
+    #[test]
+    fn test_new_episodes_of_favorite_series() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT);
+             CREATE TABLE xtream_series (profile_id TEXT, series_id INTEGER, name TEXT);
+             CREATE TABLE xtream_episodes (profile_id TEXT, series_id INTEGER, episode_id TEXT);
+             INSERT INTO xtream_favorites VALUES ('p1', 'series', '9'), ('p1', 'series', '10');
+             INSERT INTO xtream_series VALUES ('p1', 9, 'Show'), ('p1', 10, 'Other'), ('p1', 11, 'Unfollowed');
+             INSERT INTO xtream_episodes VALUES ('p1', 9, '101'), ('p1', 11, '301');",
+        )
+        .unwrap();
+
+        let before = favorite_series_episode_counts(&conn, "p1").unwrap();
+        assert_eq!(before.len(), 2);
+        assert_eq!(before[&9], ("Show".to_string(), 1));
+        conn.execute_batch(
+            "INSERT INTO xtream_episodes VALUES ('p1', 9, '102'), ('p1', 9, '103'), ('p1', 10, '201'), ('p1', 11, '302');",
+        )
+        .unwrap();
+        let after = favorite_series_episode_counts(&conn, "p1").unwrap();
+        assert_eq!(new_episodes(&before, &after), vec![("Show".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,422 @@
+// Followed categories
+//
+// Following a category ("UFC PPV") watches it for new items. A sync takes a
+// snapshot of the items in the followed categories before it runs and
+// compares the categories with it afterwards; the new items go into a feed
+// with read state, and each category that gained items raises a notification.
+use crate::content_id::{canonical_type, catalog_table, ContentId};
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Feed entries kept per profile, newest first
+const FEED_LIMIT: i64 = 1000;
+
+/// Item names quoted in a notification; the rest are counted
+const NOTIFICATION_NAMES: usize = 3;
+
+/// Canonical content type of a category, one of `channel`, `movie` or `series`
+fn category_type(content_type: &str) -> Result<&'static str> {
+    match canonical_type(content_type) {
+        Some(canonical @ ("channel" | "movie" | "series")) => Ok(canonical),
+        _ => Err(XTauriError::internal(format!("Invalid category type: {}", content_type))),
+    }
+}
+
+fn category_table(content_type: &str) -> &'static str {
+    match content_type {
+        "channel" => "xtream_channel_categories",
+        "movie" => "xtream_movie_categories",
+        _ => "xtream_series_categories",
+    }
+}
+
+/// A category a profile follows
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct FollowedCategory {
+    /// `channel`, `movie` or `series`
+    pub content_type: String,
+    pub category_id: String,
+    pub category_name: String,
+    /// Unix timestamp
+    pub followed_at: i64,
+    /// Feed entries of this category not read yet
+    pub unread: i64,
+}
+
+/// An item that appeared in a followed category
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct FollowedCategoryUpdate {
+    pub id: i64,
+    /// `channel`, `movie` or `series`
+    pub content_type: String,
+    pub category_id: String,
+    pub category_name: String,
+    pub item: ContentId,
+    pub item_name: String,
+    /// Unix timestamp of the sync that found it
+    pub added_at: i64,
+    pub read: bool,
+}
+
+/// Items that appeared in one followed category during a sync
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewCategoryItems {
+    pub content_type: String,
+    pub category_name: String,
+    pub item_names: Vec<String>,
+}
+
+impl NewCategoryItems {
+    /// Notification body naming the first few items
+    pub fn summary(&self) -> String {
+        let count = self.item_names.len();
+        let mut names = self.item_names[..count.min(NOTIFICATION_NAMES)].join(", ");
+        if count > NOTIFICATION_NAMES {
+            names.push_str(&format!(" and {} more", count - NOTIFICATION_NAMES));
+        }
+        format!("{} new item{}: {}", count, if count == 1 { "" } else { "s" }, names)
+    }
+}
+
+/// Item ids in each followed category of a profile
+pub type CategorySnapshot = HashMap<(String, String), HashSet<i64>>;
+
+/// Follow a cached category; following it again changes nothing
+///
+/// The type accepts any spelling `ContentId` does.
+pub fn follow(
+    conn: &Connection,
+    profile_id: &str,
+    content_type: &str,
+    category_id: &str,
+    now: i64,
+) -> Result<FollowedCategory> {
+    let content_type = category_type(content_type)?;
+    let category_name: String = conn
+        .query_row(
+            &format!(
+                "SELECT category_name FROM {} WHERE profile_id = ?1 AND category_id = ?2",
+                category_table(content_type)
+            ),
+            params![profile_id, category_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| XTauriError::internal(format!("Category {} not found", category_id)))?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO xtream_followed_categories
+             (profile_id, content_type, category_id, category_name, followed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![profile_id, content_type, category_id, category_name, now],
+    )?;
+
+    followed(conn, profile_id)?
+        .into_iter()
+        .find(|category| category.content_type == content_type && category.category_id == category_id)
+        .ok_or_else(|| XTauriError::internal("Followed category disappeared"))
+}
+
+/// Stop following a category and drop its feed entries; returns whether it was followed
+pub fn unfollow(conn: &Connection, profile_id: &str, content_type: &str, category_id: &str) -> Result<bool> {
+    let content_type = category_type(content_type)?;
+    let removed = conn.execute(
+        "DELETE FROM xtream_followed_categories
+         WHERE profile_id = ?1 AND content_type = ?2 AND category_id = ?3",
+        params![profile_id, content_type, category_id],
+    )?;
+    conn.execute(
+        "DELETE FROM xtream_followed_category_updates
+         WHERE profile_id = ?1 AND content_type = ?2 AND category_id = ?3",
+        params![profile_id, content_type, category_id],
+    )?;
+    Ok(removed > 0)
+}
+
+/// The categories a profile follows, with their unread counts
+pub fn followed(conn: &Connection, profile_id: &str) -> Result<Vec<FollowedCategory>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.content_type, f.category_id, f.category_name, f.followed_at,
+                (SELECT COUNT(*) FROM xtream_followed_category_updates u
+                 WHERE u.profile_id = f.profile_id AND u.content_type = f.content_type
+                   AND u.category_id = f.category_id AND u.read_at IS NULL)
+         FROM xtream_followed_categories f
+         WHERE f.profile_id = ?1
+         ORDER BY f.category_name COLLATE NOCASE",
+    )?;
+    let categories = stmt
+        .query_map([profile_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?
+        .filter_map(|row| row.ok())
+        .map(|(content_type, category_id, category_name, followed_at, unread)| FollowedCategory {
+            content_type,
+            category_id,
+            category_name,
+            followed_at,
+            unread,
+        })
+        .collect();
+    Ok(categories)
+}
+
+/// Ids and names of the items in a category
+fn category_items(
+    conn: &Connection,
+    profile_id: &str,
+    content_type: &str,
+    category_id: &str,
+) -> Result<Vec<(i64, String)>> {
+    let (table, id_column) = catalog_table(category_type(content_type)?).expect("categories hold cached content");
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {}, name FROM {} WHERE profile_id = ?1 AND category_id = ?2",
+        id_column, table
+    ))?;
+    let items = stmt
+        .query_map(params![profile_id, category_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+/// Item ids in each of a profile's followed categories; taken before a sync
+pub fn snapshot(conn: &Connection, profile_id: &str) -> Result<CategorySnapshot> {
+    let mut snapshot = CategorySnapshot::new();
+    for category in followed(conn, profile_id)? {
+        let ids = category_items(conn, profile_id, &category.content_type, &category.category_id)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        snapshot.insert((category.content_type, category.category_id), ids);
+    }
+    Ok(snapshot)
+}
+
+/// Add the items that appeared since `before` to the feed, by category
+///
+/// Categories that were empty before are left out; their items were only just
+/// fetched, not added by the provider. So are categories followed while the
+/// sync ran.
+pub fn record_new_items(
+    conn: &Connection,
+    profile_id: &str,
+    before: &CategorySnapshot,
+    now: i64,
+) -> Result<Vec<NewCategoryItems>> {
+    let mut added = Vec::new();
+    let mut insert = conn.prepare_cached(
+        "INSERT OR IGNORE INTO xtream_followed_category_updates
+             (profile_id, content_type, category_id, category_name, item_id, item_name, added_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+
+    for category in followed(conn, profile_id)? {
+        let Some(known) = before.get(&(category.content_type.clone(), category.category_id.clone())) else {
+            continue;
+        };
+        if known.is_empty() {
+            continue;
+        }
+
+        let mut item_names = Vec::new();
+        for (item_id, item_name) in category_items(conn, profile_id, &category.content_type, &category.category_id)? {
+            if known.contains(&item_id) {
+                continue;
+            }
+            let inserted = insert.execute(params![
+                profile_id,
+                category.content_type,
+                category.category_id,
+                category.category_name,
+                item_id,
+                item_name,
+                now
+            ])?;
+            if inserted > 0 {
+                item_names.push(item_name);
+            }
+        }
+        if !item_names.is_empty() {
+            item_names.sort();
+            added.push(NewCategoryItems {
+                content_type: category.content_type,
+                category_name: category.category_name,
+                item_names,
+            });
+        }
+    }
+
+    conn.execute(
+        "DELETE FROM xtream_followed_category_updates
+         WHERE profile_id = ?1 AND id NOT IN (
+             SELECT id FROM xtream_followed_category_updates WHERE profile_id = ?1 ORDER BY id DESC LIMIT ?2
+         )",
+        params![profile_id, FEED_LIMIT],
+    )?;
+    Ok(added)
+}
+
+/// Feed entries of a profile, newest first
+pub fn updates(conn: &Connection, profile_id: &str, unread_only: bool, limit: i64) -> Result<Vec<FollowedCategoryUpdate>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, content_type, category_id, category_name, item_id, item_name, added_at, read_at IS NOT NULL
+         FROM xtream_followed_category_updates
+         WHERE profile_id = ?1 AND (?2 = 0 OR read_at IS NULL)
+         ORDER BY id DESC
+         LIMIT ?3",
+    )?;
+    let updates = stmt
+        .query_map(params![profile_id, unread_only, limit], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, i64>(6)?,
+                row.get::<_, bool>(7)?,
+            ))
+        })?
+        .filter_map(|row| row.ok())
+        .filter_map(
+            |(id, content_type, category_id, category_name, item_id, item_name, added_at, read)| {
+                Some(FollowedCategoryUpdate {
+                    item: ContentId::new(&content_type, &item_id.to_string()).ok()?,
+                    id,
+                    content_type,
+                    category_id,
+                    category_name,
+                    item_name,
+                    added_at,
+                    read,
+                })
+            },
+        )
+        .collect();
+    Ok(updates)
+}
+
+/// Mark feed entries read; all of the profile's when `ids` is `None`
+pub fn mark_read(conn: &Connection, profile_id: &str, ids: Option<&[i64]>, now: i64) -> Result<usize> {
+    let Some(ids) = ids else {
+        return Ok(conn.execute(
+            "UPDATE xtream_followed_category_updates SET read_at = ?2 WHERE profile_id = ?1 AND read_at IS NULL",
+            params![profile_id, now],
+        )?);
+    };
+    let mut stmt = conn.prepare(
+        "UPDATE xtream_followed_category_updates SET read_at = ?3
+         WHERE profile_id = ?1 AND id = ?2 AND read_at IS NULL",
+    )?;
+    let mut marked = 0;
+    for id in ids {
+        marked += stmt.execute(params![profile_id, id, now])?;
+    }
+    Ok(marked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, name TEXT NOT NULL)",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO xtream_profiles (id, name) VALUES ('p1', 'Test')", [])
+            .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO xtream_movie_categories (profile_id, category_id, category_name)
+             VALUES ('p1', '7', 'UFC PPV'), ('p1', '8', 'Documentaries')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add_movie(conn: &Connection, stream_id: i64, name: &str, category_id: &str) {
+        conn.execute(
+            "INSERT INTO xtream_movies (profile_id, stream_id, name, category_id) VALUES ('p1', ?1, ?2, ?3)",
+            params![stream_id, name, category_id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_new_items_in_followed_categories_reach_the_feed() {
+        let conn = create_test_db();
+        add_movie(&conn, 1, "UFC 300", "7");
+        add_movie(&conn, 2, "Planet Earth", "8");
+
+        let category = follow(&conn, "p1", "movie", "7", 100).unwrap();
+        assert_eq!(category.category_name, "UFC PPV");
+        assert!(follow(&conn, "p1", "movie", "99", 100).is_err());
+        assert!(follow(&conn, "p1", "episode", "7", 100).is_err());
+        // Other spellings of the type are the same category
+        assert_eq!(follow(&conn, "p1", "Movies", "7", 150).unwrap().followed_at, 100);
+
+        let before = snapshot(&conn, "p1").unwrap();
+        add_movie(&conn, 3, "UFC 302", "7");
+        add_movie(&conn, 4, "UFC 301", "7");
+        add_movie(&conn, 5, "Blue Planet", "8");
+
+        let added = record_new_items(&conn, "p1", &before, 200).unwrap();
+        assert_eq!(
+            added,
+            vec![NewCategoryItems {
+                content_type: "movie".to_string(),
+                category_name: "UFC PPV".to_string(),
+                item_names: vec!["UFC 301".to_string(), "UFC 302".to_string()],
+            }]
+        );
+        assert_eq!(added[0].summary(), "2 new items: UFC 301, UFC 302");
+
+        // A second pass over the same items finds nothing new
+        assert!(record_new_items(&conn, "p1", &before, 300).unwrap().is_empty());
+
+        let feed = updates(&conn, "p1", true, 10).unwrap();
+        assert_eq!(feed.len(), 2);
+        assert_eq!(feed[0].item, ContentId::Movie(4));
+        assert_eq!(followed(&conn, "p1").unwrap()[0].unread, 2);
+
+        assert_eq!(mark_read(&conn, "p1", Some(&[feed[0].id]), 400).unwrap(), 1);
+        assert_eq!(updates(&conn, "p1", true, 10).unwrap().len(), 1);
+        assert_eq!(updates(&conn, "p1", false, 10).unwrap().len(), 2);
+        assert_eq!(mark_read(&conn, "p1", None, 400).unwrap(), 1);
+
+        assert!(unfollow(&conn, "p1", "movie", "7").unwrap());
+        assert!(updates(&conn, "p1", false, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_categories_empty_before_the_sync_are_skipped() {
+        let conn = create_test_db();
+        follow(&conn, "p1", "movie", "7", 100).unwrap();
+
+        let before = snapshot(&conn, "p1").unwrap();
+        add_movie(&conn, 1, "UFC 300", "7");
+        assert!(record_new_items(&conn, "p1", &before, 200).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_summary_counts_items_beyond_the_first_few() {
+        let added = NewCategoryItems {
+            content_type: "channel".to_string(),
+            category_name: "Sports".to_string(),
+            item_names: ["A", "B", "C", "D", "E"].map(String::from).to_vec(),
+        };
+        assert_eq!(added.summary(), "5 new items: A, B, C and 2 more");
+    }
+}
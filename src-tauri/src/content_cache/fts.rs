@@ -3,9 +3,11 @@
 // This module implements SQLite FTS5 virtual tables for fast fuzzy search
 // across channels, movies, and series with relevance scoring.
 
-use crate::error::Result;
-use rusqlite::Connection;
+use crate::error::{Result, XTauriError};
+use rusqlite::{Connection, ErrorCode};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock, Mutex};
 
 /// Search result with relevance score
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,9 +160,21 @@ fn create_fts_triggers(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Remove a cached row that is about to be saved again
+///
+/// `INSERT OR REPLACE` drops a conflicting row without firing delete triggers,
+/// which would leave its old entry in the search index. Deleting it first lets
+/// the triggers keep the index current, so a save only touches its own rows.
+pub fn delete_for_replace(conn: &Connection, table: &str, id_column: &str, profile_id: &str, id: i64) -> Result<()> {
+    conn.prepare_cached(&format!("DELETE FROM {} WHERE profile_id = ?1 AND {} = ?2", table, id_column))?
+        .execute(rusqlite::params![profile_id, id])?;
+    Ok(())
+}
+
 /// Rebuild FTS index for a specific profile
 /// 
-/// This should be called after bulk inserts or when FTS tables get out of sync
+/// Saves keep the index current through the triggers; this is for maintenance,
+/// when the FTS tables got out of sync
 pub fn rebuild_fts_index(conn: &Connection, profile_id: &str) -> Result<()> {
     // Delete existing FTS entries for this profile
     conn.execute(
@@ -206,6 +220,93 @@ pub fn rebuild_fts_index(conn: &Connection, profile_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// One of the FTS tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FtsTable {
+    Channels,
+    Movies,
+    Series,
+}
+
+impl FtsTable {
+    pub fn name(self) -> &'static str {
+        match self {
+            FtsTable::Channels => "xtream_channels_fts",
+            FtsTable::Movies => "xtream_movies_fts",
+            FtsTable::Series => "xtream_series_fts",
+        }
+    }
+}
+
+/// FTS tables being rebuilt after they were found corrupted, by the connection they are rebuilt on
+static REBUILDING: LazyLock<Mutex<HashSet<(usize, FtsTable)>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn rebuild_key(db: &Arc<Mutex<Connection>>, table: FtsTable) -> (usize, FtsTable) {
+    (Arc::as_ptr(db) as usize, table)
+}
+
+/// Whether a search failed because its FTS table is damaged, as a crash can leave it
+///
+/// Errors of the query itself, like an FTS syntax error, don't count.
+pub fn is_corruption(error: &XTauriError) -> bool {
+    let XTauriError::Database(rusqlite::Error::SqliteFailure(failure, message)) = error else {
+        return false;
+    };
+    if failure.code == ErrorCode::DatabaseCorrupt {
+        return true;
+    }
+    let message = message.as_deref().unwrap_or_default();
+    message.contains("corrupt")
+        || message.contains("vtable constructor failed")
+        || (message.contains("no such table") && message.contains("_fts"))
+}
+
+/// Whether searches of a table have to do without its index until a rebuild finishes
+pub fn is_rebuilding(db: &Arc<Mutex<Connection>>, table: FtsTable) -> bool {
+    REBUILDING.lock().map(|tables| tables.contains(&rebuild_key(db, table))).unwrap_or(false)
+}
+
+/// Drop a damaged FTS table and index its content table again
+pub fn rebuild_table(conn: &Connection, table: FtsTable) -> Result<()> {
+    let name = table.name();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(&format!("DROP TABLE IF EXISTS {}", name), [])?;
+    // Only the dropped table is missing; its triggers are still in place
+    initialize_fts_tables(&tx)?;
+    tx.execute(&format!("INSERT INTO {0}({0}) VALUES ('rebuild')", name), [])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Rebuild a corrupted FTS table on its own thread unless that is already under way
+///
+/// The table counts as rebuilding until it is done. When the rebuild fails it
+/// stays that way, so searches keep using LIKE until the next start retries.
+pub fn rebuild_in_background(db: Arc<Mutex<Connection>>, table: FtsTable) {
+    let key = rebuild_key(&db, table);
+    let started = REBUILDING.lock().map(|mut tables| tables.insert(key)).unwrap_or(false);
+    if !started {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let start = std::time::Instant::now();
+        let rebuilt = db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))
+            .and_then(|conn| rebuild_table(&conn, table));
+        match rebuilt {
+            Ok(()) => {
+                println!("[FTS] Rebuilt {} in {:?}", table.name(), start.elapsed());
+                if let Ok(mut tables) = REBUILDING.lock() {
+                    tables.remove(&key);
+                }
+            }
+            Err(e) => eprintln!("[FTS] Failed to rebuild {}, searching it without the index: {}", table.name(), e),
+        }
+    });
+}
+
 /// Prepare FTS query string with proper escaping and operators
 /// 
 /// Converts user query into FTS5 query syntax:
@@ -242,7 +343,7 @@ pub fn prepare_fts_query(query: &str) -> String {
 /// - Exact matches vs partial matches
 /// - Matches in name/title vs plot
 /// - Earlier position in text
-fn calculate_relevance_score(
+pub(crate) fn calculate_relevance_score(
     query: &str,
     name: &Option<String>,
     title: &Option<String>,
@@ -391,4 +492,79 @@ mod tests {
         );
         assert_eq!(score, 0.0);
     }
+
+    #[test]
+    fn test_saving_again_updates_only_the_saved_rows_in_the_index() {
+        use crate::content_cache::{ContentCache, XtreamMovie};
+        use std::sync::{Arc, Mutex};
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY, name TEXT NOT NULL, url TEXT NOT NULL, username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            );
+            INSERT INTO xtream_profiles VALUES ('p1', 'Test', 'http://test.com', 'user', X'00');",
+        )
+        .unwrap();
+        let cache = ContentCache::new(Arc::new(Mutex::new(conn))).unwrap();
+        let movie = |stream_id: i64, name: &str| -> XtreamMovie {
+            serde_json::from_value(serde_json::json!({ "stream_id": stream_id, "name": name })).unwrap()
+        };
+
+        cache.save_movies("p1", vec![movie(1, "Old Harbor"), movie(2, "Night Train")]).unwrap();
+        cache.save_movies("p1", vec![movie(1, "New Harbor"), movie(1, "New Harbor")]).unwrap();
+
+        let db = cache.get_db();
+        let conn = db.lock().unwrap();
+        let matches = |query: &str| -> i64 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM xtream_movies_fts WHERE xtream_movies_fts MATCH ?1",
+                [query],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(matches("old"), 0);
+        assert_eq!(matches("harbor"), 1);
+        assert_eq!(matches("train"), 1);
+        // Fails when the index holds entries of rows that are gone
+        conn.execute("INSERT INTO xtream_movies_fts(xtream_movies_fts) VALUES ('integrity-check')", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_index_is_rebuilt_while_searches_fall_back_to_like() {
+        use crate::content_cache::{ContentCache, XtreamSeries};
+        use std::time::{Duration, Instant};
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY, name TEXT NOT NULL, url TEXT NOT NULL, username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            );
+            INSERT INTO xtream_profiles VALUES ('p1', 'Test', 'http://test.com', 'user', X'00');",
+        )
+        .unwrap();
+        let cache = ContentCache::new(Arc::new(Mutex::new(conn))).unwrap();
+        let series = |series_id: i64, name: &str| -> XtreamSeries {
+            serde_json::from_value(serde_json::json!({ "series_id": series_id, "name": name })).unwrap()
+        };
+        cache.save_series("p1", vec![series(1, "Harbor Lights"), series(2, "Night Train")]).unwrap();
+
+        let db = cache.get_db();
+        db.lock().unwrap().execute("UPDATE xtream_series_fts_data SET block = X'FFFFFFFFFFFF'", []).unwrap();
+
+        let names = |found: Vec<XtreamSeries>| -> Vec<String> { found.into_iter().map(|s| s.name).collect() };
+        assert_eq!(names(cache.fts_search_series("p1", "harbor", None).unwrap()), vec!["Harbor Lights"]);
+
+        let start = Instant::now();
+        while is_rebuilding(&db, FtsTable::Series) {
+            assert!(start.elapsed() < Duration::from_secs(10), "rebuild didn't finish");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        db.lock().unwrap().execute("INSERT INTO xtream_series_fts(xtream_series_fts) VALUES ('integrity-check')", []).unwrap();
+        assert_eq!(names(cache.fts_search_series("p1", "train", None).unwrap()), vec!["Night Train"]);
+    }
 }
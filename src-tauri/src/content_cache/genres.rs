@@ -0,0 +1,389 @@
+// Canonical genres for provider genre names
+//
+// Providers send genres as free text in their own language: one profile's
+// "Comedy" is another's "Comédie" or "Комедия". Each is mapped to a canonical
+// genre id, first by the user's overrides in `genre_mappings`, then by the
+// bundled aliases below. Genre filters use the mapping in reverse, so filtering
+// by a genre in any language, or by its id, matches every name mapped to it.
+// The frontend translates genres by id.
+use crate::content_cache::db_utils::sanitize_like_pattern;
+use crate::content_cache::ContentCacheState;
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::State;
+
+/// Canonical id, English name, and the names providers use for a genre
+///
+/// Aliases are lower case. Only genres common across providers are listed;
+/// anything else stays unmapped until the user maps it.
+const GENRES: &[(&str, &str, &[&str])] = &[
+    ("action", "Action", &[
+        "acción", "accion", "ação", "acao", "aktion", "azione", "actie", "aksiyon", "akcja", "akció", "боевик",
+        "экшн", "أكشن",
+    ]),
+    ("adventure", "Adventure", &[
+        "aventura", "aventure", "abenteuer", "avventura", "avontuur", "macera", "przygodowy", "приключения", "مغامرة",
+    ]),
+    ("animation", "Animation", &[
+        "animación", "animacion", "animação", "animacao", "animazione", "animatie", "animasyon", "animacja",
+        "анимация", "мультфильм", "رسوم متحركة", "cartoon", "cartoons",
+    ]),
+    ("comedy", "Comedy", &[
+        "comedia", "comédia", "comedie", "comédie", "komödie", "komodie", "commedia", "komedie", "komedi", "komedia",
+        "комедия", "كوميدي", "كوميديا",
+    ]),
+    ("crime", "Crime", &[
+        "crimen", "crimine", "krimi", "misdaad", "suç", "kryminał", "kryminal", "криминал", "جريمة", "policier",
+        "policial",
+    ]),
+    ("documentary", "Documentary", &[
+        "documental", "documentário", "documentario", "documentaire", "dokumentation", "dokumentarfilm", "belgesel",
+        "dokumentalny", "документальный", "وثائقي", "docu", "documentaries",
+    ]),
+    ("drama", "Drama", &["drame", "dramat", "драма", "دراما"]),
+    ("family", "Family", &[
+        "familia", "família", "familiar", "famille", "familie", "famiglia", "aile", "familijny", "семейный", "عائلي",
+    ]),
+    ("fantasy", "Fantasy", &["fantasía", "fantasia", "fantastique", "fantastyka", "fantastik", "фэнтези", "فانتازيا"]),
+    ("history", "History", &[
+        "historia", "história", "histoire", "historie", "storia", "geschiedenis", "tarih", "historyczny", "история",
+        "تاريخ", "historical",
+    ]),
+    ("horror", "Horror", &["terror", "horreur", "orrore", "korku", "ужасы", "رعب", "épouvante", "epouvante"]),
+    ("kids", "Kids", &[
+        "infantil", "enfants", "kinder", "bambini", "kinderen", "çocuk", "dla dzieci", "детский", "أطفال", "children",
+    ]),
+    ("music", "Music", &["música", "musica", "musique", "musik", "muziek", "müzik", "muzyczny", "музыка", "موسيقى", "musical"]),
+    ("mystery", "Mystery", &["misterio", "mistério", "mystère", "mystere", "mistero", "gizem", "tajemnica", "детектив", "غموض"]),
+    ("news", "News", &["noticias", "notícias", "actualités", "nachrichten", "notizie", "nieuws", "haber", "новости", "أخبار"]),
+    ("reality", "Reality", &["reality tv", "reality-tv", "telerrealidad", "téléréalité", "telerealite"]),
+    ("romance", "Romance", &[
+        "romántico", "romantico", "romântico", "romantik", "romantisch", "romantyczny", "romantique", "мелодрама",
+        "رومانسي",
+    ]),
+    ("science_fiction", "Science Fiction", &[
+        "sci-fi", "scifi", "science-fiction", "ciencia ficción", "ciencia ficcion", "ficção científica",
+        "ficcao cientifica", "fantascienza", "bilim kurgu", "фантастика", "خيال علمي",
+    ]),
+    ("sport", "Sport", &["sports", "deporte", "deportes", "esporte", "esportes", "spor", "спорт", "رياضة"]),
+    ("thriller", "Thriller", &["suspense", "suspenso", "gerilim", "триллер", "إثارة"]),
+    ("war", "War", &["guerra", "guerre", "krieg", "oorlog", "savaş", "wojenny", "военный", "حرب"]),
+    ("western", "Western", &["oeste", "faroeste", "vestern", "вестерн", "غربي"]),
+];
+
+/// A canonical genre
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct GenreInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// A user's mapping of a provider genre name to a canonical genre
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct GenreMapping {
+    pub provider_genre: String,
+    pub genre_id: String,
+}
+
+/// A genre as it appears in a profile's movies and series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct ProfileGenre {
+    /// Canonical id; `None` for provider names nothing maps yet
+    pub genre_id: Option<String>,
+    /// The canonical English name, or the provider's name when unmapped
+    pub name: String,
+    /// The provider's names for it, as they appear in the content
+    pub provider_names: Vec<String>,
+    /// Movies and series tagged with it
+    pub count: usize,
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Provider genre names in a genre field, e.g. "Action, Drama"
+pub fn split_genres(field: &str) -> impl Iterator<Item = &str> {
+    field.split([',', '/', '|', ';']).map(str::trim).filter(|name| !name.is_empty())
+}
+
+fn bundled(id: &str) -> Option<&'static (&'static str, &'static str, &'static [&'static str])> {
+    GENRES.iter().find(|(genre_id, _, _)| *genre_id == id)
+}
+
+/// The bundled genre a name belongs to, by id, English name or alias
+fn bundled_genre_id(name: &str) -> Option<&'static str> {
+    let name = normalize(name);
+    GENRES
+        .iter()
+        .find(|(id, english, aliases)| *id == name || english.to_lowercase() == name || aliases.contains(&name.as_str()))
+        .map(|(id, _, _)| *id)
+}
+
+pub fn mappings(conn: &Connection) -> Result<Vec<GenreMapping>> {
+    let mut stmt = conn.prepare("SELECT provider_genre, genre_id FROM genre_mappings ORDER BY provider_genre")?;
+    let mappings = stmt
+        .query_map([], |row| {
+            Ok(GenreMapping {
+                provider_genre: row.get(0)?,
+                genre_id: row.get(1)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(mappings)
+}
+
+/// Map a provider genre name to a canonical genre, or remove the user's mapping with `None`
+pub fn set_mapping(conn: &Connection, provider_genre: &str, genre_id: Option<&str>) -> Result<()> {
+    let provider_genre = normalize(provider_genre);
+    if provider_genre.is_empty() {
+        return Err(XTauriError::internal("Provider genre name is empty".to_string()));
+    }
+    match genre_id {
+        Some(genre_id) => {
+            if bundled(genre_id).is_none() {
+                return Err(XTauriError::internal(format!("Unknown genre: {}", genre_id)));
+            }
+            conn.execute(
+                "INSERT INTO genre_mappings (provider_genre, genre_id) VALUES (?1, ?2)
+                 ON CONFLICT(provider_genre) DO UPDATE SET genre_id = excluded.genre_id",
+                params![provider_genre, genre_id],
+            )?;
+        }
+        None => {
+            conn.execute("DELETE FROM genre_mappings WHERE provider_genre = ?1", [provider_genre])?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolves provider genre names with the user's mappings loaded once
+pub struct GenreResolver {
+    overrides: HashMap<String, String>,
+}
+
+impl GenreResolver {
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let overrides = mappings(conn)?
+            .into_iter()
+            .map(|mapping| (mapping.provider_genre, mapping.genre_id))
+            .collect();
+        Ok(Self { overrides })
+    }
+
+    /// Canonical genre id of a provider genre name
+    pub fn resolve(&self, provider_genre: &str) -> Option<String> {
+        self.overrides
+            .get(&normalize(provider_genre))
+            .cloned()
+            .or_else(|| bundled_genre_id(provider_genre).map(str::to_string))
+    }
+
+    /// Every name that means `genre_id`, except the ones the user mapped elsewhere
+    fn names_for(&self, genre_id: &str) -> Vec<String> {
+        let Some((id, english, aliases)) = bundled(genre_id) else {
+            return Vec::new();
+        };
+        let bundled_names = [id.replace('_', " "), english.to_lowercase()]
+            .into_iter()
+            .chain(aliases.iter().map(|alias| alias.to_string()));
+        let mut names: Vec<String> = bundled_names
+            .filter(|name| self.overrides.get(name).is_none_or(|mapped| mapped == genre_id))
+            .chain(
+                self.overrides
+                    .iter()
+                    .filter(|(_, mapped)| *mapped == genre_id)
+                    .map(|(name, _)| name.clone()),
+            )
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// SQL condition matching a genre filter against `column`, and its parameters
+///
+/// A filter that resolves to a canonical genre matches all of its names;
+/// anything else matches as typed. SQLite only folds case for ASCII, so other
+/// names are matched capitalized as well.
+pub fn filter_clause(conn: &Connection, column: &str, genre: &str) -> Result<(String, Vec<String>)> {
+    let resolver = GenreResolver::load(conn)?;
+    let mut names = resolver
+        .resolve(genre)
+        .map(|genre_id| resolver.names_for(&genre_id))
+        .unwrap_or_default();
+    if names.is_empty() {
+        names.push(genre.to_string());
+    }
+
+    let mut patterns = Vec::new();
+    for name in names {
+        if !name.is_ascii() {
+            let mut chars = name.chars();
+            if let Some(first) = chars.next() {
+                let capitalized: String = first.to_uppercase().chain(chars).collect();
+                if capitalized != name {
+                    patterns.push(format!("%{}%", sanitize_like_pattern(&capitalized)));
+                }
+            }
+        }
+        patterns.push(format!("%{}%", sanitize_like_pattern(&name)));
+    }
+
+    let conditions = vec![format!("{} LIKE ? ESCAPE '\\'", column); patterns.len()];
+    Ok((format!(" AND ({})", conditions.join(" OR ")), patterns))
+}
+
+/// A profile's genres, most used first, with provider names grouped by canonical genre
+pub fn profile_genres(conn: &Connection, profile_id: &str) -> Result<Vec<ProfileGenre>> {
+    let resolver = GenreResolver::load(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT genre FROM xtream_movies WHERE profile_id = ?1 AND genre IS NOT NULL
+         UNION ALL
+         SELECT genre FROM xtream_series WHERE profile_id = ?1 AND genre IS NOT NULL",
+    )?;
+    let mut rows = stmt.query([profile_id])?;
+
+    // Keyed by canonical id, or by the provider's name when unmapped
+    let mut genres: BTreeMap<(bool, String), ProfileGenre> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let field: String = row.get(0)?;
+        for provider_name in split_genres(&field) {
+            let genre_id = resolver.resolve(provider_name);
+            let key = match &genre_id {
+                Some(id) => (true, id.clone()),
+                None => (false, normalize(provider_name)),
+            };
+            let entry = genres.entry(key).or_insert_with(|| ProfileGenre {
+                name: genre_id
+                    .as_deref()
+                    .and_then(bundled)
+                    .map_or_else(|| provider_name.to_string(), |(_, english, _)| english.to_string()),
+                genre_id: genre_id.clone(),
+                provider_names: Vec::new(),
+                count: 0,
+            });
+            entry.count += 1;
+            if !entry.provider_names.iter().any(|name| name == provider_name) {
+                entry.provider_names.push(provider_name.to_string());
+            }
+        }
+    }
+
+    let mut genres: Vec<ProfileGenre> = genres.into_values().collect();
+    genres.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    Ok(genres)
+}
+
+/// The canonical genres provider names can be mapped to
+#[tauri::command]
+pub fn get_genre_catalog() -> Vec<GenreInfo> {
+    GENRES
+        .iter()
+        .map(|(id, name, _)| GenreInfo {
+            id: id.to_string(),
+            name: name.to_string(),
+        })
+        .collect()
+}
+
+/// Genres of a profile's cached movies and series, grouped by canonical genre
+#[tauri::command]
+pub async fn get_profile_genres(
+    state: State<'_, ContentCacheState>,
+    profile_id: String,
+) -> std::result::Result<Vec<ProfileGenre>, String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    profile_genres(&conn, &profile_id).map_err(|e| e.to_string())
+}
+
+/// The user's genre mappings, which take precedence over the bundled ones
+#[tauri::command]
+pub async fn get_genre_mappings(
+    state: State<'_, ContentCacheState>,
+) -> std::result::Result<Vec<GenreMapping>, String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    mappings(&conn).map_err(|e| e.to_string())
+}
+
+/// Map a provider genre name to a canonical genre for every profile; `None` restores the bundled mapping
+#[tauri::command]
+pub async fn set_genre_mapping(
+    state: State<'_, ContentCacheState>,
+    provider_genre: String,
+    genre_id: Option<String>,
+) -> std::result::Result<(), String> {
+    let db = state.cache.get_db();
+    let conn = db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    set_mapping(&conn, &provider_genre, genre_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE genre_mappings (provider_genre TEXT PRIMARY KEY, genre_id TEXT NOT NULL);
+             CREATE TABLE xtream_movies (profile_id TEXT NOT NULL, name TEXT NOT NULL, genre TEXT);
+             CREATE TABLE xtream_series (profile_id TEXT NOT NULL, name TEXT NOT NULL, genre TEXT);
+             INSERT INTO xtream_movies VALUES ('fr', 'Le Dîner', 'Comédie, Drame');
+             INSERT INTO xtream_movies VALUES ('fr', 'Les Visiteurs', 'Comédie');
+             INSERT INTO xtream_movies VALUES ('ru', 'Ирония судьбы', 'Комедия');
+             INSERT INTO xtream_series VALUES ('fr', 'Kaamelott', 'Comédie / Médiéval');
+             INSERT INTO xtream_movies VALUES ('en', 'Airplane!', 'Comedy');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn names_matching(conn: &Connection, genre: &str) -> Vec<String> {
+        let (clause, patterns) = filter_clause(conn, "genre", genre).unwrap();
+        let mut stmt = conn
+            .prepare(&format!("SELECT name FROM xtream_movies WHERE 1 = 1{} ORDER BY name", clause))
+            .unwrap();
+        stmt.query_map(rusqlite::params_from_iter(patterns), |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<Vec<String>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_genre_filters_match_across_languages() {
+        let conn = create_test_db();
+        let comedies = vec!["Airplane!", "Le Dîner", "Les Visiteurs", "Ирония судьбы"];
+        assert_eq!(names_matching(&conn, "comedy"), comedies);
+        assert_eq!(names_matching(&conn, "Comédie"), comedies);
+        assert_eq!(names_matching(&conn, "Drame"), vec!["Le Dîner"]);
+        assert_eq!(names_matching(&conn, "Médiéval"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_user_mappings_take_precedence() {
+        let conn = create_test_db();
+        let genres = profile_genres(&conn, "fr").unwrap();
+        assert_eq!(genres[0].genre_id.as_deref(), Some("comedy"));
+        assert_eq!(genres[0].count, 3);
+        assert_eq!(genres[0].provider_names, vec!["Comédie"]);
+        assert!(genres.iter().any(|genre| genre.genre_id.is_none() && genre.name == "Médiéval"));
+
+        set_mapping(&conn, "Médiéval", Some("history")).unwrap();
+        let genres = profile_genres(&conn, "fr").unwrap();
+        assert!(genres.iter().any(|genre| genre.genre_id.as_deref() == Some("history")));
+        assert!(set_mapping(&conn, "Médiéval", Some("not_a_genre")).is_err());
+
+        // Mapping a bundled alias elsewhere takes it out of its old genre's filter
+        set_mapping(&conn, "drame", Some("romance")).unwrap();
+        assert_eq!(names_matching(&conn, "drama"), Vec::<String>::new());
+        assert_eq!(names_matching(&conn, "romance"), vec!["Le Dîner"]);
+
+        set_mapping(&conn, "drame", None).unwrap();
+        assert_eq!(mappings(&conn).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,151 @@
+// Incremental parsing of large JSON array responses
+use crate::error::{Result, XTauriError};
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use std::fmt;
+use std::io::Read;
+
+/// Stream the elements of a top-level JSON array to `on_batch` in fixed-size batches
+///
+/// Only the current batch is materialized, so responses far larger than available
+/// memory can be processed. Errors returned by `on_batch` stop parsing and are
+/// returned unchanged.
+///
+/// # Returns
+/// Total number of elements processed
+pub fn for_each_array_batch<R, F>(reader: R, batch_size: usize, on_batch: F) -> Result<usize>
+where
+    R: Read,
+    F: FnMut(Vec<serde_json::Value>) -> Result<()>,
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut visitor = BatchVisitor {
+        batch_size: batch_size.max(1),
+        on_batch,
+        error: None,
+    };
+
+    let result = (&mut deserializer).deserialize_seq(&mut visitor);
+
+    if let Some(e) = visitor.error.take() {
+        return Err(e);
+    }
+
+    let count = result
+        .map_err(|e| XTauriError::internal(format!("Response is not a valid JSON array: {}", e)))?;
+
+    deserializer
+        .end()
+        .map_err(|e| XTauriError::internal(format!("Trailing data after JSON array: {}", e)))?;
+
+    Ok(count)
+}
+
+struct BatchVisitor<F> {
+    batch_size: usize,
+    on_batch: F,
+    error: Option<XTauriError>,
+}
+
+impl<F> BatchVisitor<F>
+where
+    F: FnMut(Vec<serde_json::Value>) -> Result<()>,
+{
+    fn flush<E: serde::de::Error>(&mut self, batch: &mut Vec<serde_json::Value>) -> std::result::Result<(), E> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let items = std::mem::replace(batch, Vec::with_capacity(self.batch_size));
+        (self.on_batch)(items).map_err(|e| {
+            self.error = Some(e);
+            E::custom("batch handler failed")
+        })
+    }
+}
+
+impl<'de, F> Visitor<'de> for &mut BatchVisitor<F>
+where
+    F: FnMut(Vec<serde_json::Value>) -> Result<()>,
+{
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON array")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<usize, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut count = 0;
+
+        while let Some(item) = seq.next_element::<serde_json::Value>()? {
+            batch.push(item);
+            count += 1;
+
+            if batch.len() >= self.batch_size {
+                self.flush(&mut batch)?;
+            }
+        }
+
+        self.flush(&mut batch)?;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_are_bounded() {
+        let data = serde_json::to_vec(&(0..10).map(|i| serde_json::json!({ "stream_id": i })).collect::<Vec<_>>()).unwrap();
+
+        let mut batch_sizes = Vec::new();
+        let mut ids = Vec::new();
+        let count = for_each_array_batch(data.as_slice(), 4, |batch| {
+            batch_sizes.push(batch.len());
+            ids.extend(batch.iter().map(|item| item["stream_id"].as_i64().unwrap()));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(count, 10);
+        assert_eq!(batch_sizes, vec![4, 4, 2]);
+        assert_eq!(ids, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_empty_array() {
+        let mut calls = 0;
+        let count = for_each_array_batch(&b"[]"[..], 4, |_| {
+            calls += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(count, 0);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn test_non_array_is_rejected() {
+        assert!(for_each_array_batch(&b"{\"user_info\": {}}"[..], 4, |_| Ok(())).is_err());
+        assert!(for_each_array_batch(&b"[1, 2] [3]"[..], 4, |_| Ok(())).is_err());
+        assert!(for_each_array_batch(&b"[1, 2"[..], 4, |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_handler_error_stops_parsing() {
+        let mut calls = 0;
+        let result = for_each_array_batch(&b"[1, 2, 3, 4, 5]"[..], 2, |_| {
+            calls += 1;
+            Err(XTauriError::internal("Sync cancelled by user".to_string()))
+        });
+
+        assert_eq!(calls, 1);
+        assert!(result.unwrap_err().to_string().contains("Sync cancelled"));
+    }
+}
@@ -0,0 +1,288 @@
+// Maintenance window for heavy background work
+//
+// VACUUM, search index rebuilds and full syncs keep the database busy for a
+// long time and, for syncs, load the provider too. Users can pick a daily
+// window in local time, such as 03:00-05:00, and heavy work started outside
+// it waits for it. Passing `run_now` to a heavy command runs it straight away.
+use crate::content_cache::{fts, ContentCache, ContentCacheState};
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use crate::tasks::{self, TaskKind};
+use chrono::{DateTime, Local, NaiveTime, Timelike};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tauri::State;
+use tokio_util::sync::CancellationToken;
+
+/// Format of the window's start and end times
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Mirrors the `maintenance_window_start` and `maintenance_window_end` settings
+static WINDOW: RwLock<Option<MaintenanceWindow>> = RwLock::new(None);
+
+/// Daily period in local time when heavy work may run
+///
+/// The end may be earlier than the start for a window that spans midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct MaintenanceWindow {
+    /// Start time as `HH:MM`
+    pub start: String,
+    /// End time as `HH:MM`
+    pub end: String,
+}
+
+impl MaintenanceWindow {
+    fn times(&self) -> Result<(NaiveTime, NaiveTime)> {
+        let parse = |key: &str, value: &str| {
+            NaiveTime::parse_from_str(value.trim(), TIME_FORMAT).map_err(|_| XTauriError::InvalidSetting {
+                key: key.to_string(),
+                value: format!("expected HH:MM, got {:?}", value),
+            })
+        };
+        let start = parse("maintenance_window_start", &self.start)?;
+        let end = parse("maintenance_window_end", &self.end)?;
+        if start == end {
+            return Err(XTauriError::InvalidSetting {
+                key: "maintenance_window_end".to_string(),
+                value: "must differ from the start".to_string(),
+            });
+        }
+        Ok((start, end))
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        let Ok((start, end)) = self.times() else {
+            return true;
+        };
+        if start < end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    /// How long from `now` until the window next opens; zero while it is open
+    fn wait_from(&self, now: NaiveTime) -> Duration {
+        let Ok((start, _)) = self.times() else {
+            return Duration::ZERO;
+        };
+        if self.contains(now) {
+            return Duration::ZERO;
+        }
+        let seconds = (start - now.with_nanosecond(0).unwrap_or(now)).num_seconds().rem_euclid(24 * 60 * 60);
+        Duration::from_secs(seconds as u64)
+    }
+}
+
+/// The configured window, if any
+pub fn window() -> Option<MaintenanceWindow> {
+    WINDOW.read().ok().and_then(|window| window.clone())
+}
+
+/// How long heavy work has to wait for the window, or `None` if it may start now
+pub fn time_until_window(run_now: bool) -> Option<Duration> {
+    if run_now {
+        return None;
+    }
+    let wait = window()?.wait_from(Local::now().time());
+    (!wait.is_zero()).then_some(wait)
+}
+
+/// When heavy work delayed by `wait` will start, as RFC 3339
+pub fn deferred_until(wait: Duration) -> String {
+    let start: DateTime<Local> = Local::now() + chrono::Duration::from_std(wait).unwrap_or_default();
+    start.to_rfc3339()
+}
+
+/// Sleep until the window opens; false if cancelled first
+pub async fn wait_for_window(wait: Duration, cancel_token: &CancellationToken) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(wait) => true,
+        _ = cancel_token.cancelled() => false,
+    }
+}
+
+pub fn load_settings(conn: &Connection) -> Result<Option<MaintenanceWindow>> {
+    let (start, end): (Option<String>, Option<String>) = conn.query_row(
+        "SELECT maintenance_window_start, maintenance_window_end FROM settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let loaded = start.zip(end).map(|(start, end)| MaintenanceWindow { start, end });
+    *WINDOW.write().map_err(|_| XTauriError::lock_acquisition("maintenance window"))? = loaded.clone();
+    Ok(loaded)
+}
+
+/// What a maintenance run did
+#[derive(Debug, Clone, Default, Serialize, specta::Type)]
+pub struct MaintenanceRun {
+    /// Set when the run waits for the maintenance window; it then happens in the background
+    pub deferred_until: Option<String>,
+    /// Profiles whose search index was rebuilt
+    pub fts_rebuilt: usize,
+    /// Whether the database was fragmented enough to VACUUM
+    pub vacuumed: bool,
+}
+
+/// Refresh query statistics, rebuild every profile's search index and VACUUM if worthwhile
+fn run_maintenance(cache: &ContentCache) -> Result<MaintenanceRun> {
+    let fts_rebuilt = {
+        let conn = cache
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        conn.execute("ANALYZE", [])?;
+
+        let profile_ids: Vec<String> = conn
+            .prepare("SELECT profile_id FROM xtream_content_sync")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for profile_id in &profile_ids {
+            fts::rebuild_fts_index(&conn, profile_id)?;
+        }
+        profile_ids.len()
+    };
+
+    let vacuumed = cache.should_vacuum()?;
+    if vacuumed {
+        cache.vacuum()?;
+    }
+
+    Ok(MaintenanceRun {
+        deferred_until: None,
+        fts_rebuilt,
+        vacuumed,
+    })
+}
+
+#[tauri::command]
+pub fn get_maintenance_window() -> Option<MaintenanceWindow> {
+    window()
+}
+
+/// Set the daily window for heavy work; `None` lets it run at any time
+#[tauri::command]
+pub fn set_maintenance_window(
+    state: State<DbState>,
+    window: Option<MaintenanceWindow>,
+) -> std::result::Result<(), String> {
+    // Stored normalized, so "3:00" reads back as "03:00"
+    let window = match window {
+        Some(window) => {
+            let (start, end) = window.times().map_err(|e| e.to_string())?;
+            Some(MaintenanceWindow {
+                start: start.format(TIME_FORMAT).to_string(),
+                end: end.format(TIME_FORMAT).to_string(),
+            })
+        }
+        None => None,
+    };
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET maintenance_window_start = ?1, maintenance_window_end = ?2 WHERE id = 1",
+        params![
+            window.as_ref().map(|window| &window.start),
+            window.as_ref().map(|window| &window.end)
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    *WINDOW.write().map_err(|e| e.to_string())? = window;
+    Ok(())
+}
+
+/// Rebuild search indexes, refresh query statistics and VACUUM the content cache
+///
+/// Outside the maintenance window the run waits for it in the background,
+/// unless `run_now` is set.
+#[tauri::command]
+pub async fn run_content_cache_maintenance(
+    state: State<'_, ContentCacheState>,
+    run_now: Option<bool>,
+) -> std::result::Result<MaintenanceRun, String> {
+    let cache = Arc::clone(&state.cache);
+
+    let Some(wait) = time_until_window(run_now.unwrap_or(false)) else {
+        let task = tasks::register(TaskKind::Maintenance, "Database maintenance", None);
+        let run = tokio::task::spawn_blocking(move || run_maintenance(&cache))
+            .await
+            .map_err(|e| e.to_string())?;
+        drop(task);
+        return run.map_err(|e| e.to_string());
+    };
+
+    let cancel_token = CancellationToken::new();
+    let task = tasks::register_cancellable(TaskKind::Maintenance, "Database maintenance", None, cancel_token.clone());
+    task.set_progress(0, Some("Waiting for the maintenance window".to_string()));
+    tokio::spawn(async move {
+        if !wait_for_window(wait, &cancel_token).await {
+            return;
+        }
+        task.set_progress(0, Some("Running".to_string()));
+        match tokio::task::spawn_blocking(move || run_maintenance(&cache)).await {
+            Ok(Ok(run)) => println!(
+                "[INFO] Deferred maintenance rebuilt {} search indexes, vacuumed: {}",
+                run.fts_rebuilt, run.vacuumed
+            ),
+            Ok(Err(e)) => eprintln!("[ERROR] Deferred maintenance failed: {}", e),
+            Err(e) => eprintln!("[ERROR] Deferred maintenance failed: {}", e),
+        }
+        drop(task);
+    });
+
+    Ok(MaintenanceRun {
+        deferred_until: Some(deferred_until(wait)),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(start: &str, end: &str) -> MaintenanceWindow {
+        MaintenanceWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+        }
+    }
+
+    fn time(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_window_contains() {
+        let night = window("03:00", "05:00");
+        assert!(night.contains(time("03:00")));
+        assert!(night.contains(time("04:59")));
+        assert!(!night.contains(time("05:00")));
+        assert!(!night.contains(time("12:00")));
+
+        // Spanning midnight
+        let late = window("23:30", "01:00");
+        assert!(late.contains(time("23:45")));
+        assert!(late.contains(time("00:30")));
+        assert!(!late.contains(time("01:00")));
+        assert!(!late.contains(time("22:00")));
+    }
+
+    #[test]
+    fn test_wait_until_window_opens() {
+        let night = window("03:00", "05:00");
+        assert_eq!(night.wait_from(time("04:00")), Duration::ZERO);
+        assert_eq!(night.wait_from(time("02:30")), Duration::from_secs(30 * 60));
+        // After today's window, wait for tomorrow's
+        assert_eq!(night.wait_from(time("05:00")), Duration::from_secs(22 * 60 * 60));
+    }
+
+    #[test]
+    fn test_invalid_window() {
+        assert!(window("3:00", "05:00").times().is_ok());
+        assert!(window("25:00", "05:00").times().is_err());
+        assert!(window("03:00", "03:00").times().is_err());
+        assert!(window("soon", "05:00").times().is_err());
+    }
+}
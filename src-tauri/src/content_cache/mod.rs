@@ -1,12 +1,31 @@
 // Content cache module for local Xtream content storage
+pub mod age_rating;
 pub mod background_scheduler;
 pub mod commands;
 pub mod db_performance;
 pub mod db_utils;
+pub mod delta;
+pub mod epg;
+pub mod epg_mapping;
+pub mod failover;
+pub mod favorites_sync;
+pub mod followed_categories;
 pub mod fts;
+pub mod genres;
+pub mod json_stream;
+pub mod maintenance;
+pub mod ordering;
+pub mod popularity;
+pub mod preview;
 pub mod query_optimizer;
 pub mod schema;
+pub mod search_syntax;
+pub mod similarity;
+pub mod sort_index;
 pub mod sync_scheduler;
+pub mod throttle;
+pub mod thumbnails;
+pub mod watchlist;
 
 
 
@@ -20,7 +39,7 @@ pub use schema::*;
 pub use sync_scheduler::*;
 
 /// Represents a channel from Xtream API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamChannel {
     pub stream_id: i64,
     pub num: Option<i64>,
@@ -35,6 +54,29 @@ pub struct XtreamChannel {
     pub tv_archive: Option<i64>,
     pub direct_source: Option<String>,
     pub tv_archive_duration: Option<i64>,
+    /// Set when `direct_source` is a user-supplied override rather than the provider URL
+    #[serde(default)]
+    pub url_overridden: bool,
+    /// ISO country code detected from the name or category when the channel was synced
+    #[serde(default)]
+    pub country: Option<String>,
+    /// Flag emoji for `country`
+    #[serde(default)]
+    pub country_flag: Option<String>,
+    /// Reliability score from 0 to 100 from local playback reports; `None` until played
+    #[serde(default)]
+    pub reliability: Option<u8>,
+}
+
+/// A user-supplied stream URL replacing a channel's provider URL
+///
+/// Stored separately from `xtream_channels`, so syncs and cache clears keep it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct ChannelUrlOverride {
+    pub profile_id: String,
+    pub stream_id: i64,
+    pub url: String,
+    pub updated_at: String,
 }
 
 /// Filter options for querying channels
@@ -42,12 +84,18 @@ pub struct XtreamChannel {
 pub struct ChannelFilter {
     pub category_id: Option<String>,
     pub name_contains: Option<String>,
+    /// ISO code, tag or name of the channels' country
+    pub country: Option<String>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    /// Custom order only applies within a category
+    pub sort_mode: ChannelSortMode,
+    /// How search queries are interpreted; listings ignore it
+    pub search_mode: SearchMode,
 }
 
 /// Represents a movie from Xtream API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamMovie {
     pub stream_id: i64,
     pub num: Option<i64>,
@@ -70,6 +118,9 @@ pub struct XtreamMovie {
     pub director: Option<String>,
     pub plot: Option<String>,
     pub youtube_trailer: Option<String>,
+    /// Minimum viewer age from the provider's certificate, if it gave one
+    #[serde(default)]
+    pub age_rating: Option<i64>,
 }
 
 /// Filter options for querying movies
@@ -80,8 +131,15 @@ pub struct MovieFilter {
     pub genre: Option<String>,
     pub year: Option<String>,
     pub min_rating: Option<f64>,
+    /// Age rating bounds; unrated content is excluded when either is set
+    pub min_age_rating: Option<i64>,
+    pub max_age_rating: Option<i64>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    /// How search queries are interpreted; listings ignore it
+    pub search_mode: SearchMode,
+    /// Listing order; searches order by relevance
+    pub sort_mode: VodSortMode,
 }
 
 /// Sort options for movies
@@ -113,7 +171,7 @@ impl Default for SortDirection {
 }
 
 /// Represents a series listing from Xtream API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamSeries {
     pub series_id: i64,
     pub num: Option<i64>,
@@ -131,10 +189,13 @@ pub struct XtreamSeries {
     pub rating_5based: Option<f64>,
     pub episode_run_time: Option<String>,
     pub category_id: Option<String>,
+    /// Minimum viewer age from the provider's certificate, if it gave one
+    #[serde(default)]
+    pub age_rating: Option<i64>,
 }
 
 /// Represents a season in a series
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamSeason {
     pub season_number: i64,
     pub name: Option<String>,
@@ -147,7 +208,7 @@ pub struct XtreamSeason {
 }
 
 /// Represents an episode in a series
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamEpisode {
     pub episode_id: String,
     pub season_number: i64,
@@ -161,7 +222,7 @@ pub struct XtreamEpisode {
 }
 
 /// Complete series details with seasons and episodes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamSeriesDetails {
     pub series: XtreamSeries,
     pub seasons: Vec<XtreamSeason>,
@@ -176,8 +237,15 @@ pub struct SeriesFilter {
     pub genre: Option<String>,
     pub year: Option<String>,
     pub min_rating: Option<f64>,
+    /// Age rating bounds; unrated content is excluded when either is set
+    pub min_age_rating: Option<i64>,
+    pub max_age_rating: Option<i64>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    /// How search queries are interpreted; listings ignore it
+    pub search_mode: SearchMode,
+    /// Listing order; searches order by relevance
+    pub sort_mode: VodSortMode,
 }
 
 /// Represents a category for content organization
@@ -232,17 +300,33 @@ pub struct CategoryFilter {
     pub name_contains: Option<String>,
 }
 
+use crate::country::{self, CountryCount};
+use crate::db_pool::{DbPool, DbRead};
 use crate::error::{Result, XTauriError};
-use rusqlite::{params, Connection};
+use crate::expiry;
+use crate::pagination::{query_page, Page};
+use crate::post_processors;
+use crate::profiling::profile_span;
+use crate::xtream::tags;
+use ordering::ChannelSortMode;
+use popularity::{PopularityKind, VodSortMode};
+use search_syntax::SearchMode;
+use sort_index::SortedTable;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 /// Manages local content cache for Xtream data
 ///
 /// This module provides persistent storage for Xtream content (channels, movies, series)
 /// in SQLite tables, enabling fast local-first access without repeated API calls.
+/// Clones share the same connections.
+#[derive(Clone)]
 pub struct ContentCache {
     db: Arc<Mutex<Connection>>,
+    /// Where listings and searches read from, so they don't wait for a sync holding `db`
+    reads: Arc<DbPool>,
 }
 
 impl ContentCache {
@@ -254,7 +338,8 @@ impl ContentCache {
     /// # Returns
     /// A new ContentCache instance with initialized tables
     pub fn new(db: Arc<Mutex<Connection>>) -> Result<Self> {
-        let cache = Self { db };
+        let reads = Arc::new(DbPool::shared(Arc::clone(&db)));
+        let cache = Self { db, reads };
         cache.initialize_tables()?;
         Ok(cache)
     }
@@ -284,6 +369,19 @@ impl ContentCache {
         Arc::clone(&self.db)
     }
 
+    /// Read listings and searches from a pool of connections to the database file
+    pub fn with_read_pool(mut self, reads: Arc<DbPool>) -> Self {
+        self.reads = reads;
+        self
+    }
+
+    /// A connection for queries that don't write
+    ///
+    /// Without a read pool this locks the shared connection like a write does.
+    pub fn reader(&self) -> Result<DbRead<'_>> {
+        self.reads.read()
+    }
+
     /// Check if the cache is initialized for a specific profile
     ///
     /// # Arguments
@@ -418,10 +516,7 @@ impl ContentCache {
     /// # Returns
     /// A tuple of (channels_count, movies_count, series_count)
     pub fn get_content_counts(&self, profile_id: &str) -> Result<(usize, usize, usize)> {
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let channels_count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM xtream_channels WHERE profile_id = ?1",
@@ -448,6 +543,108 @@ impl ContentCache {
         ))
     }
 
+    /// Count the cached movies of each category, leaving out movies without one
+    pub fn get_movie_counts_by_category(&self, profile_id: &str) -> Result<HashMap<String, usize>> {
+        validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
+
+        let conn = self.reader()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT category_id, COUNT(*) FROM xtream_movies
+             WHERE profile_id = ?1 AND category_id IS NOT NULL AND category_id != ''
+             GROUP BY category_id",
+        )?;
+        let counts = stmt
+            .query_map([profile_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(counts)
+    }
+
+    /// Replace the cached movies of one category
+    ///
+    /// Movies the category no longer lists are removed; the rest are saved as
+    /// by [`ContentCache::save_movies`].
+    pub fn replace_category_movies(
+        &self,
+        profile_id: &str,
+        category_id: &str,
+        movies: Vec<XtreamMovie>,
+    ) -> Result<usize> {
+        validate_profile_id(profile_id)?;
+
+        let kept: Vec<i64> = movies.iter().map(|movie| movie.stream_id).collect();
+        let kept = serde_json::to_string(&kept)
+            .map_err(|e| XTauriError::internal(format!("Failed to serialize stream IDs: {}", e)))?;
+
+        {
+            let conn = self
+                .db
+                .lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            let removed = conn.execute(
+                "DELETE FROM xtream_movies
+                 WHERE profile_id = ?1 AND category_id = ?2
+                   AND stream_id NOT IN (SELECT value FROM json_each(?3))",
+                params![profile_id, category_id, kept],
+            )?;
+
+            // save_movies refreshes the count, but not when there is nothing to save
+            if removed > 0 && movies.is_empty() {
+                conn.execute(
+                    "UPDATE xtream_content_sync
+                     SET movies_count = (SELECT COUNT(*) FROM xtream_movies WHERE profile_id = ?1),
+                         updated_at = CURRENT_TIMESTAMP
+                     WHERE profile_id = ?1",
+                    [profile_id],
+                )?;
+            }
+        }
+
+        self.save_movies(profile_id, movies)
+    }
+
+    /// Refresh the precomputed name order of a profile's large categories, if enabled
+    pub fn refresh_sort_indexes(&self, profile_id: &str) -> Result<usize> {
+        if !sort_index::is_enabled() {
+            return Ok(0);
+        }
+        validate_profile_id(profile_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        sort_index::rebuild(&conn, profile_id)
+    }
+
+    /// Recompute the tokens "more like this" compares a profile's movies and series by
+    pub fn refresh_similarity_tokens(&self, profile_id: &str) -> Result<usize> {
+        validate_profile_id(profile_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        similarity::rebuild(&conn, profile_id)
+    }
+
+    /// Recompute the popularity scores listings of a profile can be sorted by
+    pub fn refresh_popularity(&self, profile_id: &str) -> Result<usize> {
+        validate_profile_id(profile_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        popularity::refresh(&conn, profile_id, chrono::Utc::now().timestamp())
+    }
+
     /// Perform database maintenance operations
     ///
     /// Runs ANALYZE and VACUUM to optimize database performance
@@ -551,8 +748,8 @@ impl ContentCache {
 
     /// Save channels to the cache with batch insert
     ///
-    /// Uses UPSERT (INSERT OR REPLACE) to handle both new and updated channels.
-    /// All operations are performed in a single transaction for atomicity.
+    /// New channels are inserted and changed ones replaced, in a single transaction.
+    /// The FTS triggers update the search index for just the rows saved.
     ///
     /// # Arguments
     /// * `profile_id` - The profile ID these channels belong to
@@ -561,6 +758,7 @@ impl ContentCache {
     /// # Returns
     /// Number of channels successfully saved
     pub fn save_channels(&self, profile_id: &str, channels: Vec<XtreamChannel>) -> Result<usize> {
+        let _span = profile_span!("sync", "save_channels");
         validate_profile_id(profile_id)?;
 
         if channels.is_empty() {
@@ -571,16 +769,23 @@ impl ContentCache {
             .db
             .lock()
             .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let fingerprints = delta::fingerprints(&channels);
+        // Before post-processing, which may strip the country tag from names
+        let mut channels = channels;
+        detect_channel_countries(&conn, profile_id, &mut channels)?;
+        epg_mapping::apply_saved(&conn, profile_id, &mut channels)?;
+        let channels = post_processors::apply(&conn, &post_processors::xtream_source(profile_id), channels);
 
         let saved = batch_insert(&mut conn, "xtream_channels", &channels, |tx, channel| {
             validate_stream_id(channel.stream_id)?;
+            fts::delete_for_replace(tx, "xtream_channels", "stream_id", profile_id, channel.stream_id)?;
 
             tx.execute(
-                "INSERT OR REPLACE INTO xtream_channels (
+                "INSERT INTO xtream_channels (
                     profile_id, stream_id, num, name, stream_type, stream_icon,
                     thumbnail, epg_channel_id, added, category_id, custom_sid,
-                    tv_archive, direct_source, tv_archive_duration, updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, CURRENT_TIMESTAMP)",
+                    tv_archive, direct_source, tv_archive_duration, country, updated_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, CURRENT_TIMESTAMP)",
                 params![
                     profile_id,
                     channel.stream_id,
@@ -596,6 +801,7 @@ impl ContentCache {
                     channel.tv_archive,
                     channel.direct_source,
                     channel.tv_archive_duration,
+                    channel.country,
                 ],
             )?;
             Ok(())
@@ -611,9 +817,7 @@ impl ContentCache {
             [profile_id],
         )?;
 
-        // Rebuild FTS index to ensure search works correctly
-        // This is necessary because INSERT OR REPLACE may not trigger FTS updates properly
-        fts::rebuild_fts_index(&conn, profile_id)?;
+        delta::record_fingerprints(&conn, profile_id, "channels", &fingerprints)?;
 
         Ok(saved)
     }
@@ -631,12 +835,30 @@ impl ContentCache {
         profile_id: &str,
         filter: Option<ChannelFilter>,
     ) -> Result<Vec<XtreamChannel>> {
-        validate_profile_id(profile_id)?;
+        Ok(self.get_channels_page(profile_id, filter)?.items)
+    }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+    /// Get one page of channels along with the number matching the filter
+    pub fn get_channels_page(
+        &self,
+        profile_id: &str,
+        filter: Option<ChannelFilter>,
+    ) -> Result<Page<XtreamChannel>> {
+        let conn = self.reader()?;
+        self.get_channels_page_on(&conn, profile_id, filter)
+    }
+
+    /// Like `get_channels_page`, on a connection the caller holds, e.g. to interrupt the query
+    fn get_channels_page_on(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        filter: Option<ChannelFilter>,
+    ) -> Result<Page<XtreamChannel>> {
+        validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         let filter = filter.unwrap_or_default();
 
@@ -644,10 +866,11 @@ impl ContentCache {
         let mut query = String::from(
             "SELECT stream_id, num, name, stream_type, stream_icon, thumbnail,
                     epg_channel_id, added, category_id, custom_sid, tv_archive,
-                    direct_source, tv_archive_duration
+                    direct_source, tv_archive_duration, country, COUNT(*) OVER ()
              FROM xtream_channels
              WHERE profile_id = ?1",
         );
+        query.push_str(&not_hidden("xtream_channels", "channel", "stream_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(profile_id.to_string())];
 
@@ -656,47 +879,60 @@ impl ContentCache {
             params.push(Box::new(category_id.clone()));
         }
 
+        if let Some(country) = &filter.country {
+            query.push_str(" AND country = ?");
+            params.push(Box::new(country::filter_code(country)));
+        }
+
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND name LIKE ?");
+            query.push_str(" AND name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
 
-        query.push_str(" ORDER BY name COLLATE NOCASE");
-
-        if let Some(limit) = filter.limit {
-            query.push_str(&format!(" LIMIT {}", limit));
-        }
-
-        if let Some(offset) = filter.offset {
-            query.push_str(&format!(" OFFSET {}", offset));
+        if filter.sort_mode == ChannelSortMode::Custom && filter.category_id.is_some() {
+            query.push_str(
+                " ORDER BY (SELECT position FROM xtream_channel_positions p
+                            WHERE p.profile_id = xtream_channels.profile_id
+                              AND p.category_id = xtream_channels.category_id
+                              AND p.stream_id = xtream_channels.stream_id) NULLS LAST,
+                           name COLLATE NOCASE",
+            );
+        } else if filter.sort_mode == ChannelSortMode::Popularity {
+            query.push_str(&format!(" ORDER BY {}", popularity::order_clause(PopularityKind::Channel)));
+        } else {
+            query.push_str(" ORDER BY name COLLATE NOCASE");
         }
 
-        let mut stmt = conn.prepare(&query)?;
-
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let channels = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(XtreamChannel {
-                    stream_id: row.get(0)?,
-                    num: row.get(1)?,
-                    name: row.get(2)?,
-                    stream_type: row.get(3)?,
-                    stream_icon: row.get(4)?,
-                    thumbnail: row.get(5)?,
-                    epg_channel_id: row.get(6)?,
-                    added: row.get(7)?,
-                    category_id: row.get(8)?,
-                    custom_sid: row.get(9)?,
-                    tv_archive: row.get(10)?,
-                    direct_source: row.get(11)?,
-                    tv_archive_duration: row.get(12)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut page = query_page(conn, &query, &param_refs, filter.limit, filter.offset, |row| {
+            let country: Option<String> = row.get(13)?;
+            Ok(XtreamChannel {
+                stream_id: row.get(0)?,
+                num: row.get(1)?,
+                name: row.get(2)?,
+                stream_type: row.get(3)?,
+                stream_icon: row.get(4)?,
+                thumbnail: row.get(5)?,
+                epg_channel_id: row.get(6)?,
+                added: row.get(7)?,
+                category_id: row.get(8)?,
+                custom_sid: row.get(9)?,
+                tv_archive: row.get(10)?,
+                direct_source: row.get(11)?,
+                tv_archive_duration: row.get(12)?,
+                url_overridden: false,
+                country_flag: country.as_deref().map(country::flag_emoji),
+                reliability: None,
+                country,
+            })
+        })?;
 
-        Ok(channels)
+        apply_url_overrides(conn, profile_id, &mut page.items)?;
+        failover::apply_reliability(conn, profile_id, &mut page.items)?;
+
+        Ok(page)
     }
 
     /// Delete channels from the cache
@@ -786,38 +1022,61 @@ impl ContentCache {
         query: &str,
         filter: Option<ChannelFilter>,
     ) -> Result<Vec<XtreamChannel>> {
+        Ok(self.search_channels_page(profile_id, query, filter)?.items)
+    }
+
+    /// Search channels, returning one page along with the number of matches
+    pub fn search_channels_page(
+        &self,
+        profile_id: &str,
+        query: &str,
+        filter: Option<ChannelFilter>,
+    ) -> Result<Page<XtreamChannel>> {
+        let conn = self.reader()?;
+        self.search_channels_page_on(&conn, profile_id, query, filter)
+    }
+
+    /// Like `search_channels_page`, on a connection the caller holds, e.g. to interrupt the query
+    pub fn search_channels_page_on(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        query: &str,
+        filter: Option<ChannelFilter>,
+    ) -> Result<Page<XtreamChannel>> {
+        let _span = profile_span!("search", "search_channels");
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         if query.is_empty() {
-            return self.get_channels(profile_id, filter);
+            return self.get_channels_page_on(conn, profile_id, filter);
         }
 
         let start_time = std::time::Instant::now();
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
-
         let filter = filter.unwrap_or_default();
 
         // Build search query with fuzzy matching
         // Use LIKE for fuzzy search with wildcards
-        let search_pattern = format!("%{}%", sanitize_like_pattern(query));
+        let search_pattern = search_syntax::like_pattern(query, filter.search_mode);
 
         let mut sql = String::from(
             "SELECT stream_id, num, name, stream_type, stream_icon, thumbnail,
                     epg_channel_id, added, category_id, custom_sid, tv_archive,
-                    direct_source, tv_archive_duration,
+                    direct_source, tv_archive_duration, country,
                     CASE 
                         WHEN LOWER(name) = LOWER(?2) THEN 0
                         WHEN LOWER(name) LIKE LOWER(?2) || '%' THEN 1
                         WHEN LOWER(name) LIKE '%' || LOWER(?2) || '%' THEN 2
                         ELSE 3
-                    END as relevance
+                    END as relevance,
+                    COUNT(*) OVER ()
              FROM xtream_channels
-             WHERE profile_id = ?1 AND LOWER(name) LIKE LOWER(?3)",
+             WHERE profile_id = ?1 AND LOWER(name) LIKE LOWER(?3) ESCAPE '\\'",
         );
+        sql.push_str(&not_hidden("xtream_channels", "channel", "stream_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![
             Box::new(profile_id.to_string()),
@@ -830,39 +1089,40 @@ impl ContentCache {
             params.push(Box::new(category_id.clone()));
         }
 
-        sql.push_str(" ORDER BY relevance, name COLLATE NOCASE");
-
-        if let Some(limit) = filter.limit {
-            sql.push_str(&format!(" LIMIT {}", limit));
-        }
-
-        if let Some(offset) = filter.offset {
-            sql.push_str(&format!(" OFFSET {}", offset));
+        if let Some(country) = &filter.country {
+            sql.push_str(" AND country = ?");
+            params.push(Box::new(country::filter_code(country)));
         }
 
-        let mut stmt = conn.prepare(&sql)?;
+        sql.push_str(" ORDER BY relevance, name COLLATE NOCASE");
 
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let channels = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(XtreamChannel {
-                    stream_id: row.get(0)?,
-                    num: row.get(1)?,
-                    name: row.get(2)?,
-                    stream_type: row.get(3)?,
-                    stream_icon: row.get(4)?,
-                    thumbnail: row.get(5)?,
-                    epg_channel_id: row.get(6)?,
-                    added: row.get(7)?,
-                    category_id: row.get(8)?,
-                    custom_sid: row.get(9)?,
-                    tv_archive: row.get(10)?,
-                    direct_source: row.get(11)?,
-                    tv_archive_duration: row.get(12)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut page = query_page(conn, &sql, &param_refs, filter.limit, filter.offset, |row| {
+            let country: Option<String> = row.get(13)?;
+            Ok(XtreamChannel {
+                stream_id: row.get(0)?,
+                num: row.get(1)?,
+                name: row.get(2)?,
+                stream_type: row.get(3)?,
+                stream_icon: row.get(4)?,
+                thumbnail: row.get(5)?,
+                epg_channel_id: row.get(6)?,
+                added: row.get(7)?,
+                category_id: row.get(8)?,
+                custom_sid: row.get(9)?,
+                tv_archive: row.get(10)?,
+                direct_source: row.get(11)?,
+                tv_archive_duration: row.get(12)?,
+                url_overridden: false,
+                country_flag: country.as_deref().map(country::flag_emoji),
+                reliability: None,
+                country,
+            })
+        })?;
+
+        apply_url_overrides(conn, profile_id, &mut page.items)?;
+        failover::apply_reliability(conn, profile_id, &mut page.items)?;
 
         let duration = start_time.elapsed();
 
@@ -870,7 +1130,7 @@ impl ContentCache {
         println!(
             "[DEBUG] Channel search completed: query='{}', results={}, took {:?}",
             query,
-            channels.len(),
+            page.items.len(),
             duration
         );
 
@@ -882,7 +1142,7 @@ impl ContentCache {
             );
         }
 
-        Ok(channels)
+        Ok(page)
     }
 
     /// Get channel count for a specific filter
@@ -897,15 +1157,16 @@ impl ContentCache {
     /// Total count of channels matching the filter
     pub fn count_channels(&self, profile_id: &str, filter: Option<ChannelFilter>) -> Result<usize> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
 
         let mut query = String::from("SELECT COUNT(*) FROM xtream_channels WHERE profile_id = ?1");
+        query.push_str(&not_hidden("xtream_channels", "channel", "stream_id"));
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(profile_id.to_string())];
 
         if let Some(category_id) = &filter.category_id {
@@ -913,8 +1174,13 @@ impl ContentCache {
             params.push(Box::new(category_id.clone()));
         }
 
+        if let Some(country) = &filter.country {
+            query.push_str(" AND country = ?");
+            params.push(Box::new(country::filter_code(country)));
+        }
+
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND name LIKE ?");
+            query.push_str(" AND name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
@@ -926,12 +1192,39 @@ impl ContentCache {
         Ok(count as usize)
     }
 
+    /// Countries of a profile's cached channels, with the number of channels from each
+    ///
+    /// Most channels first; channels without a detected country are not counted.
+    pub fn get_channel_countries(&self, profile_id: &str) -> Result<Vec<CountryCount>> {
+        validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
+
+        let conn = self.reader()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT country, COUNT(*) FROM xtream_channels
+             WHERE profile_id = ?1 AND country IS NOT NULL
+             GROUP BY country",
+        )?;
+        let mut countries = stmt
+            .query_map([profile_id], |row| {
+                let code: String = row.get(0)?;
+                Ok(CountryCount::new(&code, row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        countries.sort_by(|a, b| b.channels.cmp(&a.channels).then_with(|| a.name.cmp(&b.name)));
+        Ok(countries)
+    }
+
     // ==================== Movie Operations ====================
 
     /// Save movies to the cache with batch insert
     ///
-    /// Uses UPSERT (INSERT OR REPLACE) to handle both new and updated movies.
-    /// All operations are performed in a single transaction for atomicity.
+    /// New movies are inserted and changed ones replaced, in a single transaction.
+    /// The FTS triggers update the search index for just the rows saved.
     ///
     /// # Arguments
     /// * `profile_id` - The profile ID these movies belong to
@@ -940,6 +1233,7 @@ impl ContentCache {
     /// # Returns
     /// Number of movies successfully saved
     pub fn save_movies(&self, profile_id: &str, movies: Vec<XtreamMovie>) -> Result<usize> {
+        let _span = profile_span!("sync", "save_movies");
         validate_profile_id(profile_id)?;
 
         if movies.is_empty() {
@@ -950,17 +1244,20 @@ impl ContentCache {
             .db
             .lock()
             .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let fingerprints = delta::fingerprints(&movies);
+        let movies = post_processors::apply(&conn, &post_processors::xtream_source(profile_id), movies);
 
         let saved = batch_insert(&mut conn, "xtream_movies", &movies, |tx, movie| {
             validate_stream_id(movie.stream_id)?;
+            fts::delete_for_replace(tx, "xtream_movies", "stream_id", profile_id, movie.stream_id)?;
 
             tx.execute(
-                "INSERT OR REPLACE INTO xtream_movies (
+                "INSERT INTO xtream_movies (
                     profile_id, stream_id, num, name, title, year, stream_type,
                     stream_icon, rating, rating_5based, genre, added, episode_run_time,
                     category_id, container_extension, custom_sid, direct_source,
-                    release_date, cast, director, plot, youtube_trailer, updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, CURRENT_TIMESTAMP)",
+                    release_date, cast, director, plot, youtube_trailer, age_rating, updated_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, CURRENT_TIMESTAMP)",
                 params![
                     profile_id,
                     movie.stream_id,
@@ -984,6 +1281,7 @@ impl ContentCache {
                     movie.director,
                     movie.plot,
                     movie.youtube_trailer,
+                    movie.age_rating,
                 ],
             )?;
             Ok(())
@@ -999,9 +1297,7 @@ impl ContentCache {
             [profile_id],
         )?;
 
-        // Rebuild FTS index to ensure search works correctly
-        // This is necessary because INSERT OR REPLACE may not trigger FTS updates properly
-        fts::rebuild_fts_index(&conn, profile_id)?;
+        delta::record_fingerprints(&conn, profile_id, "movies", &fingerprints)?;
 
         Ok(saved)
     }
@@ -1023,12 +1319,34 @@ impl ContentCache {
         sort_by: Option<MovieSortBy>,
         sort_direction: Option<SortDirection>,
     ) -> Result<Vec<XtreamMovie>> {
-        validate_profile_id(profile_id)?;
+        Ok(self.get_movies_page(profile_id, filter, sort_by, sort_direction)?.items)
+    }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+    /// Get one page of movies along with the number matching the filter
+    pub fn get_movies_page(
+        &self,
+        profile_id: &str,
+        filter: Option<MovieFilter>,
+        sort_by: Option<MovieSortBy>,
+        sort_direction: Option<SortDirection>,
+    ) -> Result<Page<XtreamMovie>> {
+        let conn = self.reader()?;
+        self.get_movies_page_on(&conn, profile_id, filter, sort_by, sort_direction)
+    }
+
+    /// Like `get_movies_page`, on a connection the caller holds, e.g. to interrupt the query
+    fn get_movies_page_on(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        filter: Option<MovieFilter>,
+        sort_by: Option<MovieSortBy>,
+        sort_direction: Option<SortDirection>,
+    ) -> Result<Page<XtreamMovie>> {
+        validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         let filter = filter.unwrap_or_default();
         let sort_by = sort_by.unwrap_or_default();
@@ -1039,10 +1357,11 @@ impl ContentCache {
             "SELECT stream_id, num, name, title, year, stream_type, stream_icon, \
              rating, rating_5based, genre, added, episode_run_time, category_id, \
              container_extension, custom_sid, direct_source, release_date, \
-             \"cast\", director, plot, youtube_trailer \
+             \"cast\", director, plot, youtube_trailer, age_rating, COUNT(*) OVER () \
              FROM xtream_movies \
              WHERE profile_id = ?1",
         );
+        query.push_str(&not_hidden("xtream_movies", "movie", "stream_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(profile_id.to_string())];
 
@@ -1052,15 +1371,15 @@ impl ContentCache {
         }
 
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND name LIKE ?");
+            query.push_str(" AND name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
 
         if let Some(genre) = &filter.genre {
-            query.push_str(" AND genre LIKE ?");
-            let pattern = format!("%{}%", sanitize_like_pattern(genre));
-            params.push(Box::new(pattern));
+            let (clause, patterns) = genres::filter_clause(conn, "genre", genre)?;
+            query.push_str(&clause);
+            params.extend(patterns.into_iter().map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>));
         }
 
         if let Some(year) = &filter.year {
@@ -1073,60 +1392,65 @@ impl ContentCache {
             params.push(Box::new(min_rating));
         }
 
-        // Add sorting
-        let sort_field = match sort_by {
-            MovieSortBy::Name => "name COLLATE NOCASE",
-            MovieSortBy::Rating => "rating",
-            MovieSortBy::Year => "year",
-            MovieSortBy::Added => "added",
-        };
+        if let Some(min_age) = filter.min_age_rating {
+            query.push_str(" AND age_rating >= ?");
+            params.push(Box::new(min_age));
+        }
 
-        let sort_dir = match sort_direction {
-            SortDirection::Asc => "ASC",
-            SortDirection::Desc => "DESC",
-        };
+        if let Some(max_age) = filter.max_age_rating {
+            query.push_str(" AND age_rating <= ?");
+            params.push(Box::new(max_age));
+        }
 
-        query.push_str(&format!(" ORDER BY {} {}", sort_field, sort_dir));
+        // Add sorting
+        if filter.sort_mode == VodSortMode::Popularity {
+            query.push_str(&format!(" ORDER BY {}", popularity::order_clause(PopularityKind::Movie)));
+        } else {
+            let sort_field = match sort_by {
+                MovieSortBy::Name => {
+                    sort_index::name_order(conn, SortedTable::Movies, profile_id, filter.category_id.as_deref())?
+                }
+                MovieSortBy::Rating => "rating",
+                MovieSortBy::Year => "year",
+                MovieSortBy::Added => "added",
+            };
 
-        if let Some(limit) = filter.limit {
-            query.push_str(&format!(" LIMIT {}", limit));
-        }
+            let sort_dir = match sort_direction {
+                SortDirection::Asc => "ASC",
+                SortDirection::Desc => "DESC",
+            };
 
-        if let Some(offset) = filter.offset {
-            query.push_str(&format!(" OFFSET {}", offset));
+            query.push_str(&format!(" ORDER BY {} {}", sort_field, sort_dir));
         }
 
-        let mut stmt = conn.prepare(&query)?;
-
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let movies = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(XtreamMovie {
-                    stream_id: row.get(0)?,
-                    num: row.get(1)?,
-                    name: row.get(2)?,
-                    title: row.get(3)?,
-                    year: row.get(4)?,
-                    stream_type: row.get(5)?,
-                    stream_icon: row.get(6)?,
-                    rating: row.get(7)?,
-                    rating_5based: row.get(8)?,
-                    genre: row.get(9)?,
-                    added: row.get(10)?,
-                    episode_run_time: row.get(11)?,
-                    category_id: row.get(12)?,
-                    container_extension: row.get(13)?,
-                    custom_sid: row.get(14)?,
-                    direct_source: row.get(15)?,
-                    release_date: row.get(16)?,
-                    cast: row.get(17)?,
-                    director: row.get(18)?,
-                    plot: row.get(19)?,
-                    youtube_trailer: row.get(20)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let movies = query_page(conn, &query, &param_refs, filter.limit, filter.offset, |row| {
+            Ok(XtreamMovie {
+                stream_id: row.get(0)?,
+                num: row.get(1)?,
+                name: row.get(2)?,
+                title: row.get(3)?,
+                year: row.get(4)?,
+                stream_type: row.get(5)?,
+                stream_icon: row.get(6)?,
+                rating: row.get(7)?,
+                rating_5based: row.get(8)?,
+                genre: row.get(9)?,
+                added: row.get(10)?,
+                episode_run_time: row.get(11)?,
+                category_id: row.get(12)?,
+                container_extension: row.get(13)?,
+                custom_sid: row.get(14)?,
+                direct_source: row.get(15)?,
+                release_date: row.get(16)?,
+                cast: row.get(17)?,
+                director: row.get(18)?,
+                plot: row.get(19)?,
+                youtube_trailer: row.get(20)?,
+                age_rating: row.get(21)?,
+            })
+        })?;
 
         Ok(movies)
     }
@@ -1222,38 +1546,64 @@ impl ContentCache {
         sort_by: Option<MovieSortBy>,
         sort_direction: Option<SortDirection>,
     ) -> Result<Vec<XtreamMovie>> {
+        Ok(self.search_movies_page(profile_id, query, filter, sort_by, sort_direction)?.items)
+    }
+
+    /// Search movies, returning one page along with the number of matches
+    pub fn search_movies_page(
+        &self,
+        profile_id: &str,
+        query: &str,
+        filter: Option<MovieFilter>,
+        sort_by: Option<MovieSortBy>,
+        sort_direction: Option<SortDirection>,
+    ) -> Result<Page<XtreamMovie>> {
+        let conn = self.reader()?;
+        self.search_movies_page_on(&conn, profile_id, query, filter, sort_by, sort_direction)
+    }
+
+    /// Like `search_movies_page`, on a connection the caller holds, e.g. to interrupt the query
+    pub fn search_movies_page_on(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        query: &str,
+        filter: Option<MovieFilter>,
+        sort_by: Option<MovieSortBy>,
+        sort_direction: Option<SortDirection>,
+    ) -> Result<Page<XtreamMovie>> {
+        let _span = profile_span!("search", "search_movies");
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         if query.is_empty() {
-            return self.get_movies(profile_id, filter, sort_by, sort_direction);
+            return self.get_movies_page_on(conn, profile_id, filter, sort_by, sort_direction);
         }
 
         let start_time = std::time::Instant::now();
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
-
         let filter = filter.unwrap_or_default();
         let sort_by = sort_by.unwrap_or_default();
         let sort_direction = sort_direction.unwrap_or_default();
 
         // Build search query with fuzzy matching
-        let search_pattern = format!("%{}%", sanitize_like_pattern(query));
+        let search_pattern = search_syntax::like_pattern(query, filter.search_mode);
 
         let mut sql = String::from(
             "SELECT stream_id, num, name, title, year, stream_type, stream_icon, \
              rating, rating_5based, genre, added, episode_run_time, category_id, \
              container_extension, custom_sid, direct_source, release_date, \
-             \"cast\", director, plot, youtube_trailer \
+             \"cast\", director, plot, youtube_trailer, age_rating, COUNT(*) OVER () \
              FROM xtream_movies \
              WHERE profile_id = ?1 AND (\
-                 LOWER(name) LIKE LOWER(?2) OR \
-                 LOWER(title) LIKE LOWER(?2) OR \
-                 LOWER(plot) LIKE LOWER(?2)\
+                 LOWER(name) LIKE LOWER(?2) ESCAPE '\\' OR \
+                 LOWER(title) LIKE LOWER(?2) ESCAPE '\\' OR \
+                 LOWER(plot) LIKE LOWER(?2) ESCAPE '\\'\
              )",
         );
+        sql.push_str(&not_hidden("xtream_movies", "movie", "stream_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![
             Box::new(profile_id.to_string()),
@@ -1266,9 +1616,9 @@ impl ContentCache {
         }
 
         if let Some(genre) = &filter.genre {
-            sql.push_str(" AND genre LIKE ?");
-            let pattern = format!("%{}%", sanitize_like_pattern(genre));
-            params.push(Box::new(pattern));
+            let (clause, patterns) = genres::filter_clause(conn, "genre", genre)?;
+            sql.push_str(&clause);
+            params.extend(patterns.into_iter().map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>));
         }
 
         if let Some(year) = &filter.year {
@@ -1281,6 +1631,16 @@ impl ContentCache {
             params.push(Box::new(min_rating));
         }
 
+        if let Some(min_age) = filter.min_age_rating {
+            sql.push_str(" AND age_rating >= ?");
+            params.push(Box::new(min_age));
+        }
+
+        if let Some(max_age) = filter.max_age_rating {
+            sql.push_str(" AND age_rating <= ?");
+            params.push(Box::new(max_age));
+        }
+
         // Add sorting
         let sort_field = match sort_by {
             MovieSortBy::Name => "name COLLATE NOCASE",
@@ -1296,45 +1656,34 @@ impl ContentCache {
 
         sql.push_str(&format!(" ORDER BY {} {}", sort_field, sort_dir));
 
-        if let Some(limit) = filter.limit {
-            sql.push_str(&format!(" LIMIT {}", limit));
-        }
-
-        if let Some(offset) = filter.offset {
-            sql.push_str(&format!(" OFFSET {}", offset));
-        }
-
-        let mut stmt = conn.prepare(&sql)?;
-
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let movies = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(XtreamMovie {
-                    stream_id: row.get(0)?,
-                    num: row.get(1)?,
-                    name: row.get(2)?,
-                    title: row.get(3)?,
-                    year: row.get(4)?,
-                    stream_type: row.get(5)?,
-                    stream_icon: row.get(6)?,
-                    rating: row.get(7)?,
-                    rating_5based: row.get(8)?,
-                    genre: row.get(9)?,
-                    added: row.get(10)?,
-                    episode_run_time: row.get(11)?,
-                    category_id: row.get(12)?,
-                    container_extension: row.get(13)?,
-                    custom_sid: row.get(14)?,
-                    direct_source: row.get(15)?,
-                    release_date: row.get(16)?,
-                    cast: row.get(17)?,
-                    director: row.get(18)?,
-                    plot: row.get(19)?,
-                    youtube_trailer: row.get(20)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let page = query_page(conn, &sql, &param_refs, filter.limit, filter.offset, |row| {
+            Ok(XtreamMovie {
+                stream_id: row.get(0)?,
+                num: row.get(1)?,
+                name: row.get(2)?,
+                title: row.get(3)?,
+                year: row.get(4)?,
+                stream_type: row.get(5)?,
+                stream_icon: row.get(6)?,
+                rating: row.get(7)?,
+                rating_5based: row.get(8)?,
+                genre: row.get(9)?,
+                added: row.get(10)?,
+                episode_run_time: row.get(11)?,
+                category_id: row.get(12)?,
+                container_extension: row.get(13)?,
+                custom_sid: row.get(14)?,
+                direct_source: row.get(15)?,
+                release_date: row.get(16)?,
+                cast: row.get(17)?,
+                director: row.get(18)?,
+                plot: row.get(19)?,
+                youtube_trailer: row.get(20)?,
+                age_rating: row.get(21)?,
+            })
+        })?;
 
         let duration = start_time.elapsed();
 
@@ -1342,7 +1691,7 @@ impl ContentCache {
         println!(
             "[DEBUG] Movie search completed: query='{}', results={}, took {:?}",
             query,
-            movies.len(),
+            page.items.len(),
             duration
         );
 
@@ -1354,7 +1703,7 @@ impl ContentCache {
             );
         }
 
-        Ok(movies)
+        Ok(page)
     }
 
     /// Get movie count for a specific filter
@@ -1369,15 +1718,16 @@ impl ContentCache {
     /// Total count of movies matching the filter
     pub fn count_movies(&self, profile_id: &str, filter: Option<MovieFilter>) -> Result<usize> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
 
         let mut query = String::from("SELECT COUNT(*) FROM xtream_movies WHERE profile_id = ?1");
+        query.push_str(&not_hidden("xtream_movies", "movie", "stream_id"));
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(profile_id.to_string())];
 
         if let Some(category_id) = &filter.category_id {
@@ -1386,15 +1736,15 @@ impl ContentCache {
         }
 
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND name LIKE ?");
+            query.push_str(" AND name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
 
         if let Some(genre) = &filter.genre {
-            query.push_str(" AND genre LIKE ?");
-            let pattern = format!("%{}%", sanitize_like_pattern(genre));
-            params.push(Box::new(pattern));
+            let (clause, patterns) = genres::filter_clause(&conn, "genre", genre)?;
+            query.push_str(&clause);
+            params.extend(patterns.into_iter().map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>));
         }
 
         if let Some(year) = &filter.year {
@@ -1407,6 +1757,16 @@ impl ContentCache {
             params.push(Box::new(min_rating));
         }
 
+        if let Some(min_age) = filter.min_age_rating {
+            query.push_str(" AND age_rating >= ?");
+            params.push(Box::new(min_age));
+        }
+
+        if let Some(max_age) = filter.max_age_rating {
+            query.push_str(" AND age_rating <= ?");
+            params.push(Box::new(max_age));
+        }
+
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
         let count: i64 = conn.query_row(&query, param_refs.as_slice(), |row| row.get(0))?;
@@ -1418,8 +1778,8 @@ impl ContentCache {
 
     /// Save series listings to the cache with batch insert
     ///
-    /// Uses UPSERT (INSERT OR REPLACE) to handle both new and updated series.
-    /// All operations are performed in a single transaction for atomicity.
+    /// New series are inserted and changed ones replaced, in a single transaction.
+    /// The FTS triggers update the search index for just the rows saved.
     ///
     /// # Arguments
     /// * `profile_id` - The profile ID these series belong to
@@ -1428,6 +1788,7 @@ impl ContentCache {
     /// # Returns
     /// Number of series successfully saved
     pub fn save_series(&self, profile_id: &str, series: Vec<XtreamSeries>) -> Result<usize> {
+        let _span = profile_span!("sync", "save_series");
         validate_profile_id(profile_id)?;
 
         if series.is_empty() {
@@ -1438,16 +1799,19 @@ impl ContentCache {
             .db
             .lock()
             .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let fingerprints = delta::fingerprints(&series);
+        let series = post_processors::apply(&conn, &post_processors::xtream_source(profile_id), series);
 
         let saved = batch_insert(&mut conn, "xtream_series", &series, |tx, s| {
             validate_stream_id(s.series_id)?;
+            fts::delete_for_replace(tx, "xtream_series", "series_id", profile_id, s.series_id)?;
 
             tx.execute(
-                "INSERT OR REPLACE INTO xtream_series (
+                "INSERT INTO xtream_series (
                     profile_id, series_id, num, name, title, year, cover, plot,
                     cast, director, genre, release_date, last_modified, rating,
-                    rating_5based, episode_run_time, category_id, updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, CURRENT_TIMESTAMP)",
+                    rating_5based, episode_run_time, category_id, age_rating, updated_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, CURRENT_TIMESTAMP)",
                 params![
                     profile_id,
                     s.series_id,
@@ -1466,6 +1830,7 @@ impl ContentCache {
                     s.rating_5based,
                     s.episode_run_time,
                     s.category_id,
+                    s.age_rating,
                 ],
             )?;
             Ok(())
@@ -1481,9 +1846,7 @@ impl ContentCache {
             [profile_id],
         )?;
 
-        // Rebuild FTS index to ensure search works correctly
-        // This is necessary because INSERT OR REPLACE may not trigger FTS updates properly
-        fts::rebuild_fts_index(&conn, profile_id)?;
+        delta::record_fingerprints(&conn, profile_id, "series", &fingerprints)?;
 
         Ok(saved)
     }
@@ -1517,12 +1880,13 @@ impl ContentCache {
         let tx = conn.unchecked_transaction()?;
 
         // Save series info
+        fts::delete_for_replace(&tx, "xtream_series", "series_id", profile_id, details.series.series_id)?;
         tx.execute(
-            "INSERT OR REPLACE INTO xtream_series (
+            "INSERT INTO xtream_series (
                 profile_id, series_id, num, name, title, year, cover, plot,
                 cast, director, genre, release_date, last_modified, rating,
-                rating_5based, episode_run_time, category_id, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, CURRENT_TIMESTAMP)",
+                rating_5based, episode_run_time, category_id, age_rating, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, CURRENT_TIMESTAMP)",
             params![
                 profile_id,
                 details.series.series_id,
@@ -1541,6 +1905,7 @@ impl ContentCache {
                 details.series.rating_5based,
                 details.series.episode_run_time,
                 details.series.category_id,
+                details.series.age_rating,
             ],
         )?;
 
@@ -1607,12 +1972,30 @@ impl ContentCache {
         profile_id: &str,
         filter: Option<SeriesFilter>,
     ) -> Result<Vec<XtreamSeries>> {
-        validate_profile_id(profile_id)?;
+        Ok(self.get_series_page(profile_id, filter)?.items)
+    }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+    /// Get one page of series along with the number matching the filter
+    pub fn get_series_page(
+        &self,
+        profile_id: &str,
+        filter: Option<SeriesFilter>,
+    ) -> Result<Page<XtreamSeries>> {
+        let conn = self.reader()?;
+        self.get_series_page_on(&conn, profile_id, filter)
+    }
+
+    /// Like `get_series_page`, on a connection the caller holds, e.g. to interrupt the query
+    fn get_series_page_on(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        filter: Option<SeriesFilter>,
+    ) -> Result<Page<XtreamSeries>> {
+        validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         let filter = filter.unwrap_or_default();
 
@@ -1620,10 +2003,11 @@ impl ContentCache {
         let mut query = String::from(
             "SELECT series_id, num, name, title, year, cover, plot, \"cast\", director,
                     genre, release_date, last_modified, rating, rating_5based,
-                    episode_run_time, category_id
+                    episode_run_time, category_id, age_rating, COUNT(*) OVER ()
              FROM xtream_series
              WHERE profile_id = ?1",
         );
+        query.push_str(&not_hidden("xtream_series", "series", "series_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(profile_id.to_string())];
 
@@ -1633,15 +2017,15 @@ impl ContentCache {
         }
 
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND name LIKE ?");
+            query.push_str(" AND name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
 
         if let Some(genre) = &filter.genre {
-            query.push_str(" AND genre LIKE ?");
-            let pattern = format!("%{}%", sanitize_like_pattern(genre));
-            params.push(Box::new(pattern));
+            let (clause, patterns) = genres::filter_clause(conn, "genre", genre)?;
+            query.push_str(&clause);
+            params.extend(patterns.into_iter().map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>));
         }
 
         if let Some(year) = &filter.year {
@@ -1654,42 +2038,47 @@ impl ContentCache {
             params.push(Box::new(min_rating));
         }
 
-        query.push_str(" ORDER BY name COLLATE NOCASE");
-
-        if let Some(limit) = filter.limit {
-            query.push_str(&format!(" LIMIT {}", limit));
+        if let Some(min_age) = filter.min_age_rating {
+            query.push_str(" AND age_rating >= ?");
+            params.push(Box::new(min_age));
         }
 
-        if let Some(offset) = filter.offset {
-            query.push_str(&format!(" OFFSET {}", offset));
+        if let Some(max_age) = filter.max_age_rating {
+            query.push_str(" AND age_rating <= ?");
+            params.push(Box::new(max_age));
         }
 
-        let mut stmt = conn.prepare(&query)?;
+        if filter.sort_mode == VodSortMode::Popularity {
+            query.push_str(&format!(" ORDER BY {}", popularity::order_clause(PopularityKind::Series)));
+        } else {
+            let sort_field =
+                sort_index::name_order(conn, SortedTable::Series, profile_id, filter.category_id.as_deref())?;
+            query.push_str(&format!(" ORDER BY {}", sort_field));
+        }
 
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let series = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(XtreamSeries {
-                    series_id: row.get(0)?,
-                    num: row.get(1)?,
-                    name: row.get(2)?,
-                    title: row.get(3)?,
-                    year: row.get(4)?,
-                    cover: row.get(5)?,
-                    plot: row.get(6)?,
-                    cast: row.get(7)?,
-                    director: row.get(8)?,
-                    genre: row.get(9)?,
-                    release_date: row.get(10)?,
-                    last_modified: row.get(11)?,
-                    rating: row.get(12)?,
-                    rating_5based: row.get(13)?,
-                    episode_run_time: row.get(14)?,
-                    category_id: row.get(15)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let series = query_page(conn, &query, &param_refs, filter.limit, filter.offset, |row| {
+            Ok(XtreamSeries {
+                series_id: row.get(0)?,
+                num: row.get(1)?,
+                name: row.get(2)?,
+                title: row.get(3)?,
+                year: row.get(4)?,
+                cover: row.get(5)?,
+                plot: row.get(6)?,
+                cast: row.get(7)?,
+                director: row.get(8)?,
+                genre: row.get(9)?,
+                release_date: row.get(10)?,
+                last_modified: row.get(11)?,
+                rating: row.get(12)?,
+                rating_5based: row.get(13)?,
+                episode_run_time: row.get(14)?,
+                category_id: row.get(15)?,
+                age_rating: row.get(16)?,
+            })
+        })?;
 
         Ok(series)
     }
@@ -1827,18 +2216,21 @@ impl ContentCache {
     ) -> Result<XtreamSeriesDetails> {
         validate_profile_id(profile_id)?;
         validate_stream_id(series_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Err(XTauriError::content_cache(format!(
+                "Series {} not found for profile {}",
+                series_id, profile_id
+            )));
+        }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         // Get series info
         let series = conn
             .query_row(
                 "SELECT series_id, num, name, title, year, cover, plot, \"cast\", director,
                     genre, release_date, last_modified, rating, rating_5based,
-                    episode_run_time, category_id
+                    episode_run_time, category_id, age_rating
              FROM xtream_series
              WHERE profile_id = ?1 AND series_id = ?2",
                 params![profile_id, series_id],
@@ -1860,6 +2252,7 @@ impl ContentCache {
                         rating_5based: row.get(13)?,
                         episode_run_time: row.get(14)?,
                         category_id: row.get(15)?,
+                        age_rating: row.get(16)?,
                     })
                 },
             )
@@ -1937,12 +2330,12 @@ impl ContentCache {
     /// Vector of seasons for the series
     pub fn get_seasons(&self, profile_id: &str, series_id: i64) -> Result<Vec<XtreamSeason>> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
         validate_stream_id(series_id)?;
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let mut stmt = conn.prepare(
             "SELECT season_number, name, episode_count, overview, air_date,
@@ -1986,12 +2379,12 @@ impl ContentCache {
         season_number: Option<i64>,
     ) -> Result<Vec<XtreamEpisode>> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
         validate_stream_id(series_id)?;
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let (query, params): (String, Vec<Box<dyn rusqlite::ToSql>>) =
             if let Some(season) = season_number {
@@ -2044,6 +2437,29 @@ impl ContentCache {
 
     // ==================== Full-Text Search Operations ====================
 
+    /// Run an FTS search, or `fallback` while the table's index can't be used
+    ///
+    /// A search that finds the FTS table corrupted starts rebuilding it in the
+    /// background; until that is done searches of the table go to `fallback`.
+    fn with_fts_recovery<T>(
+        &self,
+        table: FtsTable,
+        search: impl FnOnce() -> Result<T>,
+        fallback: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        if fts::is_rebuilding(&self.db, table) {
+            return fallback();
+        }
+        match search() {
+            Err(e) if fts::is_corruption(&e) => {
+                eprintln!("[FTS] {} is corrupted, rebuilding it: {}", table.name(), e);
+                fts::rebuild_in_background(self.get_db(), table);
+                fallback()
+            }
+            result => result,
+        }
+    }
+
     /// Search channels using FTS5 for fast fuzzy search
     ///
     /// Uses SQLite FTS5 virtual tables for high-performance full-text search
@@ -2061,8 +2477,24 @@ impl ContentCache {
         profile_id: &str,
         query: &str,
         filter: Option<ChannelFilter>,
+    ) -> Result<Vec<XtreamChannel>> {
+        self.with_fts_recovery(
+            FtsTable::Channels,
+            || self.fts_search_channels_indexed(profile_id, query, filter.clone()),
+            || self.search_channels(profile_id, query, filter.clone()),
+        )
+    }
+
+    fn fts_search_channels_indexed(
+        &self,
+        profile_id: &str,
+        query: &str,
+        filter: Option<ChannelFilter>,
     ) -> Result<Vec<XtreamChannel>> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         if query.is_empty() {
             return self.get_channels(profile_id, filter);
@@ -2070,17 +2502,15 @@ impl ContentCache {
 
         let start_time = std::time::Instant::now();
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
 
         // Prepare FTS query
-        let fts_query = fts::prepare_fts_query(query);
+        let fts_query = search_syntax::fts_query(query, filter.search_mode);
 
         if fts_query.is_empty() {
+            drop(conn);
             return self.get_channels(profile_id, Some(filter));
         }
 
@@ -2088,12 +2518,13 @@ impl ContentCache {
         let mut sql = String::from(
             "SELECT c.stream_id, c.num, c.name, c.stream_type, c.stream_icon, c.thumbnail,
                     c.epg_channel_id, c.added, c.category_id, c.custom_sid, c.tv_archive,
-                    c.direct_source, c.tv_archive_duration,
+                    c.direct_source, c.tv_archive_duration, c.country,
                     fts.rank
              FROM xtream_channels c
              INNER JOIN xtream_channels_fts fts ON c.id = fts.rowid
              WHERE fts.xtream_channels_fts MATCH ?1 AND c.profile_id = ?2",
         );
+        sql.push_str(&not_hidden("c", "channel", "stream_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> =
             vec![Box::new(fts_query), Box::new(profile_id.to_string())];
@@ -2103,6 +2534,11 @@ impl ContentCache {
             params.push(Box::new(category_id.clone()));
         }
 
+        if let Some(country) = &filter.country {
+            sql.push_str(" AND c.country = ?");
+            params.push(Box::new(country::filter_code(country)));
+        }
+
         // Order by FTS rank (lower rank = better match)
         sql.push_str(" ORDER BY fts.rank");
 
@@ -2121,8 +2557,9 @@ impl ContentCache {
 
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let channels = stmt
+        let mut channels = stmt
             .query_map(param_refs.as_slice(), |row| {
+                let country: Option<String> = row.get(13)?;
                 Ok(XtreamChannel {
                     stream_id: row.get(0)?,
                     num: row.get(1)?,
@@ -2137,10 +2574,17 @@ impl ContentCache {
                     tv_archive: row.get(10)?,
                     direct_source: row.get(11)?,
                     tv_archive_duration: row.get(12)?,
+                    url_overridden: false,
+                    country_flag: country.as_deref().map(country::flag_emoji),
+                    reliability: None,
+                    country,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
+        apply_url_overrides(&conn, profile_id, &mut channels)?;
+        failover::apply_reliability(&conn, profile_id, &mut channels)?;
+
         let duration = start_time.elapsed();
 
         #[cfg(debug_assertions)]
@@ -2179,8 +2623,24 @@ impl ContentCache {
         profile_id: &str,
         query: &str,
         filter: Option<MovieFilter>,
+    ) -> Result<Vec<XtreamMovie>> {
+        self.with_fts_recovery(
+            FtsTable::Movies,
+            || self.fts_search_movies_indexed(profile_id, query, filter.clone()),
+            || self.search_movies(profile_id, query, filter.clone(), None, None),
+        )
+    }
+
+    fn fts_search_movies_indexed(
+        &self,
+        profile_id: &str,
+        query: &str,
+        filter: Option<MovieFilter>,
     ) -> Result<Vec<XtreamMovie>> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         if query.is_empty() {
             return self.get_movies(profile_id, filter, None, None);
@@ -2188,17 +2648,15 @@ impl ContentCache {
 
         let start_time = std::time::Instant::now();
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
 
         // Prepare FTS query
-        let fts_query = fts::prepare_fts_query(query);
+        let fts_query = search_syntax::fts_query(query, filter.search_mode);
 
         if fts_query.is_empty() {
+            drop(conn);
             return self.get_movies(profile_id, Some(filter), None, None);
         }
 
@@ -2207,12 +2665,13 @@ impl ContentCache {
             "SELECT m.stream_id, m.num, m.name, m.title, m.year, m.stream_type, m.stream_icon,
                     m.rating, m.rating_5based, m.genre, m.added, m.episode_run_time, m.category_id,
                     m.container_extension, m.custom_sid, m.direct_source, m.release_date,
-                    m.cast, m.director, m.plot, m.youtube_trailer,
+                    m.cast, m.director, m.plot, m.youtube_trailer, m.age_rating,
                     fts.rank
              FROM xtream_movies m
              INNER JOIN xtream_movies_fts fts ON m.id = fts.rowid
              WHERE fts.xtream_movies_fts MATCH ?1 AND m.profile_id = ?2",
         );
+        sql.push_str(&not_hidden("m", "movie", "stream_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> =
             vec![Box::new(fts_query), Box::new(profile_id.to_string())];
@@ -2223,9 +2682,9 @@ impl ContentCache {
         }
 
         if let Some(genre) = &filter.genre {
-            sql.push_str(" AND m.genre LIKE ?");
-            let pattern = format!("%{}%", sanitize_like_pattern(genre));
-            params.push(Box::new(pattern));
+            let (clause, patterns) = genres::filter_clause(&conn, "m.genre", genre)?;
+            sql.push_str(&clause);
+            params.extend(patterns.into_iter().map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>));
         }
 
         if let Some(year) = &filter.year {
@@ -2238,6 +2697,16 @@ impl ContentCache {
             params.push(Box::new(min_rating));
         }
 
+        if let Some(min_age) = filter.min_age_rating {
+            sql.push_str(" AND m.age_rating >= ?");
+            params.push(Box::new(min_age));
+        }
+
+        if let Some(max_age) = filter.max_age_rating {
+            sql.push_str(" AND m.age_rating <= ?");
+            params.push(Box::new(max_age));
+        }
+
         // Order by FTS rank (lower rank = better match)
         sql.push_str(" ORDER BY fts.rank");
 
@@ -2280,6 +2749,7 @@ impl ContentCache {
                     director: row.get(18)?,
                     plot: row.get(19)?,
                     youtube_trailer: row.get(20)?,
+                    age_rating: row.get(21)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -2323,38 +2793,81 @@ impl ContentCache {
         query: &str,
         filter: Option<SeriesFilter>,
     ) -> Result<Vec<XtreamSeries>> {
+        Ok(self.fts_search_series_page(profile_id, query, filter)?.items)
+    }
+
+    /// Search series with FTS5, returning one page along with the number of matches
+    ///
+    /// Without a limit at most 1000 series are returned.
+    pub fn fts_search_series_page(
+        &self,
+        profile_id: &str,
+        query: &str,
+        filter: Option<SeriesFilter>,
+    ) -> Result<Page<XtreamSeries>> {
+        let conn = self.reader()?;
+        self.fts_search_series_page_on(&conn, profile_id, query, filter)
+    }
+
+    /// Like `fts_search_series_page`, on a connection the caller holds, e.g. to interrupt the query
+    pub fn fts_search_series_page_on(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        query: &str,
+        filter: Option<SeriesFilter>,
+    ) -> Result<Page<XtreamSeries>> {
+        self.with_fts_recovery(
+            FtsTable::Series,
+            || self.fts_search_series_indexed(conn, profile_id, query, filter.clone()),
+            || {
+                // Series have no LIKE search of their own, the name filter is one
+                let filter = SeriesFilter { name_contains: Some(query.to_string()), ..filter.clone().unwrap_or_default() };
+                self.get_series_page_on(conn, profile_id, Some(filter))
+            },
+        )
+    }
+
+    fn fts_search_series_indexed(
+        &self,
+        conn: &Connection,
+        profile_id: &str,
+        query: &str,
+        filter: Option<SeriesFilter>,
+    ) -> Result<Page<XtreamSeries>> {
+        let _span = profile_span!("search", "search_series");
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
         if query.is_empty() {
-            return self.get_series(profile_id, filter);
+            return self.get_series_page_on(conn, profile_id, filter);
         }
 
         let start_time = std::time::Instant::now();
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
-
         let filter = filter.unwrap_or_default();
 
         // Prepare FTS query
-        let fts_query = fts::prepare_fts_query(query);
+        let fts_query = search_syntax::fts_query(query, filter.search_mode);
 
         if fts_query.is_empty() {
-            return self.get_series(profile_id, Some(filter));
+            return self.get_series_page_on(conn, profile_id, Some(filter));
         }
 
         // Build FTS search query
         let mut sql = String::from(
             "SELECT s.series_id, s.num, s.name, s.title, s.year, s.cover, s.plot, s.cast, s.director,
                     s.genre, s.release_date, s.last_modified, s.rating, s.rating_5based,
-                    s.episode_run_time, s.category_id,
-                    fts.rank
+                    s.episode_run_time, s.category_id, s.age_rating,
+                    fts.rank,
+                    COUNT(*) OVER ()
              FROM xtream_series s
              INNER JOIN xtream_series_fts fts ON s.id = fts.rowid
              WHERE fts.xtream_series_fts MATCH ?1 AND s.profile_id = ?2"
         );
+        sql.push_str(&not_hidden("s", "series", "series_id"));
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> =
             vec![Box::new(fts_query), Box::new(profile_id.to_string())];
@@ -2365,9 +2878,9 @@ impl ContentCache {
         }
 
         if let Some(genre) = &filter.genre {
-            sql.push_str(" AND s.genre LIKE ?");
-            let pattern = format!("%{}%", sanitize_like_pattern(genre));
-            params.push(Box::new(pattern));
+            let (clause, patterns) = genres::filter_clause(conn, "s.genre", genre)?;
+            sql.push_str(&clause);
+            params.extend(patterns.into_iter().map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>));
         }
 
         if let Some(year) = &filter.year {
@@ -2380,46 +2893,45 @@ impl ContentCache {
             params.push(Box::new(min_rating));
         }
 
-        // Order by FTS rank (lower rank = better match)
-        sql.push_str(" ORDER BY fts.rank");
-
-        if let Some(limit) = filter.limit {
-            sql.push_str(&format!(" LIMIT {}", limit));
-        } else {
-            // Default limit for FTS to prevent huge result sets
-            sql.push_str(" LIMIT 1000");
+        if let Some(min_age) = filter.min_age_rating {
+            sql.push_str(" AND s.age_rating >= ?");
+            params.push(Box::new(min_age));
         }
 
-        if let Some(offset) = filter.offset {
-            sql.push_str(&format!(" OFFSET {}", offset));
+        if let Some(max_age) = filter.max_age_rating {
+            sql.push_str(" AND s.age_rating <= ?");
+            params.push(Box::new(max_age));
         }
 
-        let mut stmt = conn.prepare(&sql)?;
+        // Order by FTS rank (lower rank = better match)
+        sql.push_str(" ORDER BY fts.rank");
+
+        // Default limit for FTS to prevent huge result sets
+        let limit = filter.limit.or(Some(1000));
 
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
-        let series = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(XtreamSeries {
-                    series_id: row.get(0)?,
-                    num: row.get(1)?,
-                    name: row.get(2)?,
-                    title: row.get(3)?,
-                    year: row.get(4)?,
-                    cover: row.get(5)?,
-                    plot: row.get(6)?,
-                    cast: row.get(7)?,
-                    director: row.get(8)?,
-                    genre: row.get(9)?,
-                    release_date: row.get(10)?,
-                    last_modified: row.get(11)?,
-                    rating: row.get(12)?,
-                    rating_5based: row.get(13)?,
-                    episode_run_time: row.get(14)?,
-                    category_id: row.get(15)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let page = query_page(conn, &sql, &param_refs, limit, filter.offset, |row| {
+            Ok(XtreamSeries {
+                series_id: row.get(0)?,
+                num: row.get(1)?,
+                name: row.get(2)?,
+                title: row.get(3)?,
+                year: row.get(4)?,
+                cover: row.get(5)?,
+                plot: row.get(6)?,
+                cast: row.get(7)?,
+                director: row.get(8)?,
+                genre: row.get(9)?,
+                release_date: row.get(10)?,
+                last_modified: row.get(11)?,
+                rating: row.get(12)?,
+                rating_5based: row.get(13)?,
+                episode_run_time: row.get(14)?,
+                category_id: row.get(15)?,
+                age_rating: row.get(16)?,
+            })
+        })?;
 
         let duration = start_time.elapsed();
 
@@ -2427,7 +2939,7 @@ impl ContentCache {
         println!(
             "[DEBUG] FTS series search completed: query='{}', results={}, took {:?}",
             query,
-            series.len(),
+            page.items.len(),
             duration
         );
 
@@ -2439,7 +2951,7 @@ impl ContentCache {
             );
         }
 
-        Ok(series)
+        Ok(page)
     }
 
     /// Rebuild FTS index for a specific profile
@@ -2465,6 +2977,75 @@ impl ContentCache {
         Ok(())
     }
 
+    // ==================== EPG Operations ====================
+
+    /// Save the programmes from an Xtream EPG response
+    ///
+    /// Programmes outlive the JSON response cache, so the guide can be shown
+    /// after a restart or while the provider is unreachable.
+    ///
+    /// # Arguments
+    /// * `profile_id` - The profile the EPG was fetched for
+    /// * `stream_id` - The channel the EPG was requested for, used when listings don't name their EPG channel
+    /// * `epg_data` - The provider's EPG response
+    ///
+    /// # Returns
+    /// Number of programmes stored, not counting gap fillers
+    pub fn save_epg_programs(&self, profile_id: &str, stream_id: Option<&str>, epg_data: &serde_json::Value) -> Result<usize> {
+        validate_profile_id(profile_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        epg::store_epg_programs(&conn, profile_id, stream_id, epg_data, chrono::Utc::now().timestamp())
+    }
+
+    /// Get the stored programmes of one EPG channel that overlap a time range
+    ///
+    /// # Arguments
+    /// * `profile_id` - The profile ID to query
+    /// * `epg_channel_id` - The channel's EPG id
+    /// * `start` - Start of the range, as a Unix timestamp
+    /// * `end` - End of the range, as a Unix timestamp
+    ///
+    /// # Returns
+    /// Programmes in start order, with gaps covered by fillers
+    pub fn get_epg_programs(
+        &self,
+        profile_id: &str,
+        epg_channel_id: &str,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<epg::EpgProgramme>> {
+        validate_profile_id(profile_id)?;
+
+        let conn = self.reader()?;
+
+        let mut grid = epg::programme_grid(&conn, profile_id, &[epg_channel_id], start, end)?;
+        Ok(grid.remove(epg_channel_id).unwrap_or_default())
+    }
+
+    /// Delete stored programmes for one EPG channel, or for the whole profile
+    ///
+    /// # Arguments
+    /// * `profile_id` - The profile ID to clear
+    /// * `epg_channel_id` - The channel's EPG id, or `None` for every channel
+    ///
+    /// # Returns
+    /// Number of programmes deleted
+    pub fn delete_epg_programs(&self, profile_id: &str, epg_channel_id: Option<&str>) -> Result<usize> {
+        validate_profile_id(profile_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        epg::delete_epg_programs(&conn, profile_id, epg_channel_id)
+    }
+
     // ==================== Category Operations ====================
 
     /// Save categories to the cache with batch insert
@@ -2485,6 +3066,7 @@ impl ContentCache {
         content_type: ContentType,
         categories: Vec<XtreamCategory>,
     ) -> Result<usize> {
+        let _span = profile_span!("sync", "save_categories");
         validate_profile_id(profile_id)?;
 
         if categories.is_empty() {
@@ -2495,6 +3077,7 @@ impl ContentCache {
             .db
             .lock()
             .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let categories = post_processors::apply(&conn, &post_processors::xtream_source(profile_id), categories);
 
         let table_name = content_type.table_name();
 
@@ -2543,11 +3126,11 @@ impl ContentCache {
         filter: Option<CategoryFilter>,
     ) -> Result<Vec<XtreamCategory>> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
         let table_name = content_type.table_name();
@@ -2568,7 +3151,7 @@ impl ContentCache {
         }
 
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND category_name LIKE ?");
+            query.push_str(" AND category_name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
@@ -2611,11 +3194,11 @@ impl ContentCache {
         filter: Option<CategoryFilter>,
     ) -> Result<Vec<XtreamCategoryWithCount>> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
         let table_name = content_type.table_name();
@@ -2639,7 +3222,7 @@ impl ContentCache {
         }
 
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND c.category_name LIKE ?");
+            query.push_str(" AND c.category_name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
@@ -2753,11 +3336,11 @@ impl ContentCache {
         filter: Option<CategoryFilter>,
     ) -> Result<usize> {
         validate_profile_id(profile_id)?;
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Default::default());
+        }
 
-        let conn = self
-            .db
-            .lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let conn = self.reader()?;
 
         let filter = filter.unwrap_or_default();
         let table_name = content_type.table_name();
@@ -2771,7 +3354,7 @@ impl ContentCache {
         }
 
         if let Some(name_pattern) = &filter.name_contains {
-            query.push_str(" AND category_name LIKE ?");
+            query.push_str(" AND category_name LIKE ? ESCAPE '\\'");
             let pattern = format!("%{}%", sanitize_like_pattern(name_pattern));
             params.push(Box::new(pattern));
         }
@@ -2825,6 +3408,83 @@ impl ContentCache {
         Ok(ids)
     }
 
+    /// Get the fingerprint of every cached item of a content type, keyed by id
+    ///
+    /// Items saved before fingerprints were kept have none.
+    ///
+    /// # Arguments
+    /// * `profile_id` - The profile ID
+    /// * `content_type` - Type of content ("channels", "movies", or "series")
+    ///
+    /// # Returns
+    /// Map of cached content IDs to their fingerprints
+    pub fn get_content_fingerprints(
+        &self,
+        profile_id: &str,
+        content_type: &str,
+    ) -> Result<HashMap<i64, Option<String>>> {
+        validate_profile_id(profile_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        let (table, id_column) = match content_type {
+            "channels" => ("xtream_channels", "stream_id"),
+            "movies" => ("xtream_movies", "stream_id"),
+            "series" => ("xtream_series", "series_id"),
+            _ => {
+                return Err(XTauriError::internal(format!(
+                    "Invalid content type: {}",
+                    content_type
+                )))
+            }
+        };
+
+        let query = format!(
+            "SELECT c.{id}, f.fingerprint FROM {table} c
+             LEFT JOIN xtream_content_fingerprints f
+               ON f.profile_id = c.profile_id AND f.content_type = ?2 AND f.content_id = c.{id}
+             WHERE c.profile_id = ?1",
+            id = id_column,
+            table = table
+        );
+
+        let mut stmt = conn.prepare(&query)?;
+        let fingerprints = stmt
+            .query_map(params![profile_id, content_type], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(fingerprints)
+    }
+
+    /// Apply a delta sync: replace changed items, insert new ones and delete removed ones
+    ///
+    /// Replaced and removed rows are deleted before the upserts are inserted, so
+    /// the FTS triggers keep the search index current without a full rebuild.
+    ///
+    /// # Arguments
+    /// * `profile_id` - The profile ID
+    /// * `delta` - The changes planned by `delta::plan`
+    ///
+    /// # Returns
+    /// Number of items inserted, replaced or deleted
+    pub fn apply_content_delta<T: delta::DeltaItem>(
+        &self,
+        profile_id: &str,
+        delta: delta::ContentDelta<T>,
+    ) -> Result<usize> {
+        let _span = profile_span!("sync", "apply_content_delta");
+        let stale: Vec<i64> = delta.replaced.iter().chain(&delta.removed).copied().collect();
+        for ids in stale.chunks(500) {
+            self.delete_content_by_ids(profile_id, T::CONTENT_TYPE, ids)?;
+        }
+
+        let saved = T::store(self, profile_id, delta.upserts)?;
+        Ok(saved + delta.removed.len())
+    }
+
     /// Delete content by IDs for a specific content type
     ///
     /// Used for incremental sync to remove deleted items
@@ -2886,6 +3546,7 @@ impl ContentCache {
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
         let deleted = conn.execute(&query, param_refs.as_slice())?;
+        delta::forget_fingerprints(&conn, profile_id, content_type, ids)?;
 
         // Update sync metadata count
         let count_column = match content_type {
@@ -2907,4 +3568,265 @@ impl ContentCache {
 
         Ok(deleted)
     }
+
+    /// Set a custom stream URL for a channel, replacing any previous override
+    pub fn set_channel_url_override(&self, profile_id: &str, stream_id: i64, url: &str) -> Result<()> {
+        validate_profile_id(profile_id)?;
+        validate_stream_id(stream_id)?;
+
+        let url = url.trim();
+        let parsed = url::Url::parse(url)
+            .map_err(|e| XTauriError::internal(format!("Invalid stream URL: {}", e)))?;
+        if !matches!(parsed.scheme(), "http" | "https" | "rtmp" | "rtsp" | "udp" | "rtp") {
+            return Err(XTauriError::internal(format!(
+                "Unsupported stream URL scheme: {}",
+                parsed.scheme()
+            )));
+        }
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        conn.execute(
+            "INSERT INTO xtream_channel_url_overrides (profile_id, stream_id, url, updated_at)
+             VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+             ON CONFLICT(profile_id, stream_id) DO UPDATE SET url = ?3, updated_at = CURRENT_TIMESTAMP",
+            rusqlite::params![profile_id, stream_id, url],
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove a channel's custom stream URL
+    ///
+    /// # Returns
+    /// Whether an override existed
+    pub fn remove_channel_url_override(&self, profile_id: &str, stream_id: i64) -> Result<bool> {
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        let removed = conn.execute(
+            "DELETE FROM xtream_channel_url_overrides WHERE profile_id = ?1 AND stream_id = ?2",
+            rusqlite::params![profile_id, stream_id],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    /// Get the custom stream URL for a channel, if one is set
+    pub fn get_channel_url_override(&self, profile_id: &str, stream_id: i64) -> Result<Option<String>> {
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        let url = conn
+            .query_row(
+                "SELECT url FROM xtream_channel_url_overrides WHERE profile_id = ?1 AND stream_id = ?2",
+                rusqlite::params![profile_id, stream_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(url)
+    }
+
+    /// Get all custom stream URLs for a profile
+    pub fn get_channel_url_overrides(&self, profile_id: &str) -> Result<Vec<ChannelUrlOverride>> {
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT profile_id, stream_id, url, updated_at
+             FROM xtream_channel_url_overrides
+             WHERE profile_id = ?1
+             ORDER BY stream_id",
+        )?;
+
+        let overrides = stmt
+            .query_map([profile_id], |row| {
+                Ok(ChannelUrlOverride {
+                    profile_id: row.get(0)?,
+                    stream_id: row.get(1)?,
+                    url: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(overrides)
+    }
+
+    /// Move a channel in its category or the favorites to just after another
+    pub fn move_channel(
+        &self,
+        profile_id: &str,
+        scope: &ordering::ChannelOrderScope,
+        stream_id: i64,
+        after_stream_id: Option<i64>,
+    ) -> Result<()> {
+        validate_profile_id(profile_id)?;
+        validate_stream_id(stream_id)?;
+
+        let conn = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+        ordering::move_channel(&conn, profile_id, scope, stream_id, after_stream_id)
+    }
+}
+
+/// Tables holding a profile's cached content, sync state and channel customizations
+const PROFILE_TABLES: [&str; 25] = [
+    "xtream_channels",
+    "xtream_movies",
+    "xtream_series",
+    "xtream_seasons",
+    "xtream_episodes",
+    "xtream_channel_categories",
+    "xtream_movie_categories",
+    "xtream_series_categories",
+    "xtream_content_sync",
+    "xtream_sync_settings",
+    "xtream_sync_history",
+    "xtream_sync_limits",
+    "xtream_request_limits",
+    "xtream_channel_url_overrides",
+    "xtream_playback_failures",
+    "xtream_playback_stats",
+    "xtream_epg_programs",
+    "xtream_channel_positions",
+    "xtream_similarity_tokens",
+    "xtream_content_fingerprints",
+    "xtream_popularity",
+    "xtream_epg_mappings",
+    "xtream_followed_categories",
+    "xtream_followed_category_updates",
+    "xtream_watchlist",
+];
+
+/// Delete everything the content cache keeps for a profile, returning the rows removed
+///
+/// Runs on the caller's connection so it can be part of a larger unit of work,
+/// such as deleting the profile itself.
+pub fn delete_profile_data(conn: &Connection, profile_id: &str) -> Result<usize> {
+    let mut removed = 0;
+    for table in PROFILE_TABLES {
+        removed += conn.execute(&format!("DELETE FROM {} WHERE profile_id = ?1", table), [profile_id])?;
+    }
+    Ok(removed)
+}
+
+/// Tables whose `direct_source` holds a provider stream URL
+const STREAM_URL_TABLES: [&str; 3] = ["xtream_channels", "xtream_movies", "xtream_episodes"];
+
+/// Replace `from` with `to` in a profile's cached stream URLs, returning the rows rewritten
+///
+/// Runs on the caller's connection, so a credential rotation can rewrite the
+/// URLs in the same transaction as the profile itself.
+pub fn rewrite_profile_stream_urls(conn: &Connection, profile_id: &str, from: &str, to: &str) -> Result<usize> {
+    if from.is_empty() || from == to {
+        return Ok(0);
+    }
+    let mut rewritten = 0;
+    for table in STREAM_URL_TABLES {
+        let query = format!(
+            "UPDATE {} SET direct_source = REPLACE(direct_source, ?2, ?3)
+             WHERE profile_id = ?1 AND INSTR(direct_source, ?2) > 0",
+            table
+        );
+        rewritten += conn.execute(&query, rusqlite::params![profile_id, from, to])?;
+    }
+    Ok(rewritten)
+}
+
+/// Move a profile's cached stream URLs from `old_base` to `new_base`, returning the rows rewritten
+///
+/// Only URLs starting with `old_base` followed by `/` or nothing match, so
+/// another port on the same host is left alone. Runs on the caller's connection.
+pub fn rewrite_profile_stream_url_prefix(
+    conn: &Connection,
+    profile_id: &str,
+    old_base: &str,
+    new_base: &str,
+) -> Result<usize> {
+    if old_base.is_empty() || old_base == new_base {
+        return Ok(0);
+    }
+    let mut rewritten = 0;
+    for table in STREAM_URL_TABLES {
+        let query = format!(
+            "UPDATE {} SET direct_source = ?3 || SUBSTR(direct_source, LENGTH(?2) + 1)
+             WHERE profile_id = ?1
+               AND (direct_source = ?2 OR SUBSTR(direct_source, 1, LENGTH(?2) + 1) = ?2 || '/')",
+            table
+        );
+        rewritten += conn.execute(&query, rusqlite::params![profile_id, old_base, new_base])?;
+    }
+    Ok(rewritten)
+}
+
+/// Detect each channel's country from its name and its category's name
+///
+/// Channel categories are synced before channels, so their names are available.
+fn detect_channel_countries(conn: &Connection, profile_id: &str, channels: &mut [XtreamChannel]) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT category_id, category_name FROM xtream_channel_categories WHERE profile_id = ?1",
+    )?;
+    let categories = stmt
+        .query_map([profile_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+    for channel in channels.iter_mut() {
+        let category = channel
+            .category_id
+            .as_ref()
+            .and_then(|id| categories.get(id))
+            .map_or("", String::as_str);
+        channel.country = country::detect_country("", &channel.name, category).map(str::to_string);
+    }
+
+    Ok(())
+}
+
+/// SQL condition leaving out rows of a catalog table tagged hidden
+///
+/// `table` is the table's name or alias in the query, `content_type` the
+/// canonical type of its rows and `id_column` the id tags refer to.
+fn not_hidden(table: &str, content_type: &str, id_column: &str) -> String {
+    tags::not_hidden_clause(
+        table,
+        &format!("'{}'", content_type),
+        &format!("CAST({}.{} AS TEXT)", table, id_column),
+    )
+}
+
+/// Replace provider URLs with the profile's channel URL overrides
+fn apply_url_overrides(conn: &Connection, profile_id: &str, channels: &mut [XtreamChannel]) -> Result<()> {
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT stream_id, url FROM xtream_channel_url_overrides WHERE profile_id = ?1",
+    )?;
+    let overrides = stmt
+        .query_map([profile_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<std::collections::HashMap<_, _>, _>>()?;
+
+    for channel in channels.iter_mut() {
+        if let Some(url) = overrides.get(&channel.stream_id) {
+            channel.direct_source = Some(url.clone());
+            channel.url_overridden = true;
+        }
+    }
+
+    Ok(())
 }
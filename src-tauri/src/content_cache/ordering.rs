@@ -0,0 +1,192 @@
+// Manual channel ordering using fractional positions
+//
+// Moving a channel normally rewrites only its own position, set halfway between
+// its new neighbours. The whole list is renumbered when it has never been
+// ordered before or when neighbouring positions get too close together.
+use crate::error::{Result, XTauriError};
+use crate::xtream::favorites::XtreamFavoritesDb;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Neighbouring positions closer than this are renumbered instead of split again
+pub const MIN_POSITION_GAP: f64 = 1e-9;
+
+/// How a channel listing is ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelSortMode {
+    /// The listing's usual order: by name for channels, newest first for favorites
+    #[default]
+    Default,
+    /// The user's drag-and-drop order, followed by items never moved in the usual order
+    Custom,
+    /// Most watched, favorite and recently added first, from `popularity`
+    Popularity,
+}
+
+/// The list a channel is being reordered within
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelOrderScope {
+    Category { category_id: String },
+    Favorites,
+}
+
+/// Position for an item placed between two neighbours
+///
+/// Returns `None` when the neighbours are too close to fit another position.
+pub fn position_between(before: Option<f64>, after: Option<f64>) -> Option<f64> {
+    match (before, after) {
+        (None, None) => Some(1.0),
+        (Some(before), None) => Some(before + 1.0),
+        (None, Some(after)) => Some(after - 1.0),
+        (Some(before), Some(after)) if after - before > MIN_POSITION_GAP => Some(before + (after - before) / 2.0),
+        _ => None,
+    }
+}
+
+/// Work out the position changes that move `item` to just after `after`
+///
+/// `items` is the list in its current display order with each entry's stored
+/// position; `after` of `None` moves the item to the top.
+pub fn plan_move<T: Clone + PartialEq + Display>(
+    items: &[(T, Option<f64>)],
+    item: &T,
+    after: Option<&T>,
+) -> Result<Vec<(T, f64)>> {
+    if after == Some(item) {
+        return Ok(Vec::new());
+    }
+
+    let mut rest: Vec<&(T, Option<f64>)> = items.iter().filter(|(id, _)| id != item).collect();
+    if rest.len() == items.len() {
+        return Err(XTauriError::internal(format!("Item {} not found in list", item)));
+    }
+
+    let index = match after {
+        None => 0,
+        Some(after) => {
+            rest.iter()
+                .position(|(id, _)| id == after)
+                .ok_or_else(|| XTauriError::internal(format!("Item {} not found in list", after)))?
+                + 1
+        }
+    };
+
+    // Splitting neighbours only works once every item has a stored position
+    if rest.iter().all(|(_, position)| position.is_some()) {
+        let before = index.checked_sub(1).and_then(|i| rest[i].1);
+        let next = rest.get(index).and_then(|(_, position)| *position);
+        if let Some(position) = position_between(before, next) {
+            return Ok(vec![(item.clone(), position)]);
+        }
+    }
+
+    let moved = (item.clone(), None);
+    rest.insert(index, &moved);
+    Ok(rest
+        .into_iter()
+        .enumerate()
+        .map(|(i, (id, _))| (id.clone(), (i + 1) as f64))
+        .collect())
+}
+
+/// Move a cached channel within its category to just after `after_stream_id`
+pub fn move_category_channel(
+    conn: &Connection,
+    profile_id: &str,
+    category_id: &str,
+    stream_id: i64,
+    after_stream_id: Option<i64>,
+) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT c.stream_id, p.position
+         FROM xtream_channels c
+         LEFT JOIN xtream_channel_positions p
+           ON p.profile_id = c.profile_id AND p.category_id = c.category_id AND p.stream_id = c.stream_id
+         WHERE c.profile_id = ?1 AND c.category_id = ?2
+         ORDER BY p.position NULLS LAST, c.name COLLATE NOCASE",
+    )?;
+    let items = stmt
+        .query_map(params![profile_id, category_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<f64>>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let updates = plan_move(&items, &stream_id, after_stream_id.as_ref())?;
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO xtream_channel_positions (profile_id, category_id, stream_id, position)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(profile_id, category_id, stream_id) DO UPDATE SET position = ?4",
+        )?;
+        for (id, position) in updates {
+            stmt.execute(params![profile_id, category_id, id, position])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Move a channel to just after another one in the given list
+pub fn move_channel(
+    conn: &Connection,
+    profile_id: &str,
+    scope: &ChannelOrderScope,
+    stream_id: i64,
+    after_stream_id: Option<i64>,
+) -> Result<()> {
+    match scope {
+        ChannelOrderScope::Category { category_id } => {
+            move_category_channel(conn, profile_id, category_id, stream_id, after_stream_id)
+        }
+        ChannelOrderScope::Favorites => {
+            let after = after_stream_id.map(|id| id.to_string());
+            XtreamFavoritesDb::move_favorite(conn, profile_id, "channel", &stream_id.to_string(), after.as_deref())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_between() {
+        assert_eq!(position_between(None, None), Some(1.0));
+        assert_eq!(position_between(Some(2.0), None), Some(3.0));
+        assert_eq!(position_between(None, Some(2.0)), Some(1.0));
+        assert_eq!(position_between(Some(1.0), Some(2.0)), Some(1.5));
+        assert_eq!(position_between(Some(1.0), Some(1.0 + MIN_POSITION_GAP / 2.0)), None);
+    }
+
+    #[test]
+    fn test_plan_move_splits_neighbours() {
+        let items = [("a", Some(1.0)), ("b", Some(2.0)), ("c", Some(3.0))];
+        assert_eq!(plan_move(&items, &"c", Some(&"a")).unwrap(), vec![("c", 1.5)]);
+        assert_eq!(plan_move(&items, &"b", None).unwrap(), vec![("b", 0.0)]);
+        assert_eq!(plan_move(&items, &"a", Some(&"c")).unwrap(), vec![("a", 4.0)]);
+        assert!(plan_move(&items, &"a", Some(&"a")).unwrap().is_empty());
+        assert!(plan_move(&items, &"x", None).is_err());
+        assert!(plan_move(&items, &"a", Some(&"x")).is_err());
+    }
+
+    #[test]
+    fn test_plan_move_renumbers_unordered_and_crowded_lists() {
+        let unordered = [("a", None), ("b", None), ("c", None)];
+        assert_eq!(
+            plan_move(&unordered, &"c", Some(&"a")).unwrap(),
+            vec![("a", 1.0), ("c", 2.0), ("b", 3.0)]
+        );
+
+        let crowded = [("a", Some(1.0)), ("b", Some(1.0)), ("c", Some(2.0))];
+        assert_eq!(
+            plan_move(&crowded, &"c", Some(&"a")).unwrap(),
+            vec![("a", 1.0), ("c", 2.0), ("b", 3.0)]
+        );
+    }
+}
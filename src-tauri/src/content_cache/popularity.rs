@@ -0,0 +1,290 @@
+// Popularity order for browsing
+//
+// "Popular" means relevant to this profile: every channel, movie and series
+// gets a score blending how often it was watched here, whether it's a
+// favorite and how recently the provider added it. Scores are computed into
+// `xtream_popularity` after each sync and every few hours while the app runs,
+// so a popularity-sorted listing looks them up instead of blending per query.
+// Items with no signal at all have no row and sort after the scored ones.
+use crate::content_cache::ContentCacheState;
+use crate::error::{Result, XTauriError};
+use crate::xtream::history::EPISODE_CONTENT_TYPE;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// How often scores are refreshed while the app runs, for watches and favorites since the last sync
+const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Weight of the log of an item's watch count
+const WATCH_WEIGHT: f64 = 1.0;
+const FAVORITE_WEIGHT: f64 = 2.0;
+/// Weight of a brand new item, halving every `RECENCY_HALF_LIFE_DAYS`
+const RECENCY_WEIGHT: f64 = 1.0;
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Scores below this aren't stored; the item sorts with the unscored ones
+const MIN_SCORE: f64 = 0.01;
+
+/// How a movie or series listing is ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum VodSortMode {
+    /// By name
+    #[default]
+    Default,
+    /// Most watched, favorite and recently added first
+    Popularity,
+}
+
+/// The kinds of content that get a popularity score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopularityKind {
+    Channel,
+    Movie,
+    Series,
+}
+
+impl PopularityKind {
+    pub const ALL: [PopularityKind; 3] = [PopularityKind::Channel, PopularityKind::Movie, PopularityKind::Series];
+
+    /// Content type in the favorites, history and score tables
+    pub fn content_type(self) -> &'static str {
+        match self {
+            PopularityKind::Channel => "channel",
+            PopularityKind::Movie => "movie",
+            PopularityKind::Series => "series",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            PopularityKind::Channel => "xtream_channels",
+            PopularityKind::Movie => "xtream_movies",
+            PopularityKind::Series => "xtream_series",
+        }
+    }
+
+    fn id_column(self) -> &'static str {
+        match self {
+            PopularityKind::Channel | PopularityKind::Movie => "stream_id",
+            PopularityKind::Series => "series_id",
+        }
+    }
+
+    /// Unix time the provider added or last changed the item
+    fn added_column(self) -> &'static str {
+        match self {
+            PopularityKind::Channel | PopularityKind::Movie => "added",
+            PopularityKind::Series => "last_modified",
+        }
+    }
+}
+
+/// `ORDER BY` terms putting the most popular rows of `kind`'s table first, then by name
+pub fn order_clause(kind: PopularityKind) -> String {
+    format!(
+        "(SELECT score FROM xtream_popularity p
+          WHERE p.profile_id = {table}.profile_id AND p.content_type = '{content_type}'
+            AND p.content_id = {table}.{id_column}) DESC NULLS LAST,
+         name COLLATE NOCASE",
+        table = kind.table(),
+        content_type = kind.content_type(),
+        id_column = kind.id_column(),
+    )
+}
+
+/// Blend the signals of one item into its score
+pub fn score(watches: u32, favorite: bool, age_days: Option<f64>) -> f64 {
+    let watched = WATCH_WEIGHT * (1.0 + watches as f64).ln();
+    let favorite = if favorite { FAVORITE_WEIGHT } else { 0.0 };
+    let recency = age_days.map_or(0.0, |days| RECENCY_WEIGHT * 0.5_f64.powf(days.max(0.0) / RECENCY_HALF_LIFE_DAYS));
+    watched + favorite + recency
+}
+
+/// Times each item of `kind` was watched on a profile
+fn watch_counts(conn: &Connection, profile_id: &str, kind: PopularityKind) -> Result<HashMap<i64, u32>> {
+    let sql = match kind {
+        // Channel history keeps one entry per channel; playback stats count every start
+        PopularityKind::Channel => {
+            "SELECT stream_id, start_count FROM xtream_playback_stats WHERE profile_id = ?1
+             UNION ALL
+             SELECT CAST(content_id AS INTEGER), 1 FROM xtream_history WHERE profile_id = ?1 AND content_type = ?2"
+        }
+        PopularityKind::Movie => {
+            "SELECT CAST(content_id AS INTEGER), 1 FROM xtream_history WHERE profile_id = ?1 AND content_type = ?2"
+        }
+        // Each episode watched counts for its series
+        PopularityKind::Series => {
+            "SELECT e.series_id, COUNT(*) FROM xtream_history h
+             JOIN xtream_episodes e ON e.profile_id = h.profile_id AND e.episode_id = h.content_id
+             WHERE h.profile_id = ?1 AND h.content_type = ?3
+             GROUP BY e.series_id
+             UNION ALL
+             SELECT CAST(content_id AS INTEGER), 1 FROM xtream_history WHERE profile_id = ?1 AND content_type = ?2"
+        }
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let bindings: &[&dyn rusqlite::ToSql] = match kind {
+        PopularityKind::Series => &[&profile_id, &kind.content_type(), &EPISODE_CONTENT_TYPE],
+        _ => &[&profile_id, &kind.content_type()],
+    };
+    let rows = stmt.query_map(bindings, |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?)))?;
+
+    let mut counts: HashMap<i64, u32> = HashMap::new();
+    for row in rows {
+        let (id, count) = row?;
+        let total = counts.entry(id).or_default();
+        // Channels appear in both sources; the higher count wins rather than counting twice
+        *total = match kind {
+            PopularityKind::Channel => (*total).max(count),
+            _ => *total + count,
+        };
+    }
+    Ok(counts)
+}
+
+fn favorite_ids(conn: &Connection, profile_id: &str, kind: PopularityKind) -> Result<HashSet<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT CAST(content_id AS INTEGER) FROM xtream_favorites WHERE profile_id = ?1 AND content_type = ?2",
+    )?;
+    let ids = stmt
+        .query_map(params![profile_id, kind.content_type()], |row| row.get(0))?
+        .collect::<std::result::Result<HashSet<i64>, _>>()?;
+    Ok(ids)
+}
+
+/// Recompute the popularity scores of a profile's content, returning how many items have one
+pub fn refresh(conn: &Connection, profile_id: &str, now: i64) -> Result<usize> {
+    let mut scores = Vec::new();
+    for kind in PopularityKind::ALL {
+        let watches = watch_counts(conn, profile_id, kind)?;
+        let favorites = favorite_ids(conn, profile_id, kind)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, {} FROM {} WHERE profile_id = ?1",
+            kind.id_column(),
+            kind.added_column(),
+            kind.table()
+        ))?;
+        let rows = stmt.query_map([profile_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)))?;
+        for row in rows {
+            let (id, added) = row?;
+            let age_days = added
+                .and_then(|added| added.trim().parse::<i64>().ok())
+                .filter(|added| *added > 0)
+                .map(|added| (now - added) as f64 / 86_400.0);
+            let score = score(watches.get(&id).copied().unwrap_or(0), favorites.contains(&id), age_days);
+            if score >= MIN_SCORE {
+                scores.push((kind, id, score));
+            }
+        }
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM xtream_popularity WHERE profile_id = ?1", [profile_id])?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO xtream_popularity (profile_id, content_type, content_id, score) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (kind, id, score) in &scores {
+            insert.execute(params![profile_id, kind.content_type(), id, score])?;
+        }
+    }
+    tx.commit()?;
+    Ok(scores.len())
+}
+
+fn profile_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM xtream_profiles")?;
+    let ids = stmt.query_map([], |row| row.get(0))?.collect::<std::result::Result<Vec<String>, _>>()?;
+    Ok(ids)
+}
+
+/// Keep every profile's scores current for the rest of the run
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    loop {
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+
+        let Some(state) = app_handle.try_state::<ContentCacheState>() else {
+            return;
+        };
+        let cache = std::sync::Arc::clone(&state.cache);
+        let refreshed = tokio::task::spawn_blocking(move || {
+            let profile_ids = {
+                let db = cache.get_db();
+                let conn = db.lock().map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+                profile_ids(&conn)?
+            };
+            for profile_id in &profile_ids {
+                cache.refresh_popularity(profile_id)?;
+            }
+            Ok::<_, XTauriError>(profile_ids.len())
+        })
+        .await;
+        match refreshed {
+            Ok(Ok(profiles)) => println!("[POPULARITY] Refreshed scores of {} profile(s)", profiles),
+            Ok(Err(e)) => eprintln!("[POPULARITY] Failed to refresh scores: {}", e),
+            Err(e) => eprintln!("[POPULARITY] Score refresh stopped: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_rank_watched_favorite_and_new_items_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_channels (profile_id TEXT, stream_id INTEGER, name TEXT, added TEXT);
+             CREATE TABLE xtream_movies (profile_id TEXT, stream_id INTEGER, name TEXT, added TEXT);
+             CREATE TABLE xtream_series (profile_id TEXT, series_id INTEGER, name TEXT, last_modified TEXT);
+             CREATE TABLE xtream_episodes (profile_id TEXT, series_id INTEGER, episode_id TEXT);
+             CREATE TABLE xtream_history (profile_id TEXT, content_type TEXT, content_id TEXT);
+             CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT);
+             CREATE TABLE xtream_playback_stats (profile_id TEXT, stream_id INTEGER, start_count INTEGER);
+             CREATE TABLE xtream_popularity (
+                 profile_id TEXT, content_type TEXT, content_id INTEGER, score REAL,
+                 PRIMARY KEY (profile_id, content_type, content_id)
+             );
+             INSERT INTO xtream_channels VALUES
+                 ('p1', 1, 'Alpha', NULL), ('p1', 2, 'Bravo', NULL), ('p1', 3, 'Charlie', NULL);
+             INSERT INTO xtream_playback_stats VALUES ('p1', 3, 30), ('p1', 2, 1);
+             INSERT INTO xtream_history VALUES ('p1', 'channel', '2');
+             INSERT INTO xtream_favorites VALUES ('p1', 'channel', '2');
+             INSERT INTO xtream_movies VALUES
+                 ('p1', 10, 'Old', '1000000'), ('p1', 11, 'New', '1699913600'), ('p1', 12, 'Unknown', NULL);
+             INSERT INTO xtream_series VALUES ('p1', 20, 'Quiet', NULL), ('p1', 21, 'Binged', NULL);
+             INSERT INTO xtream_episodes VALUES ('p1', 21, 'e1'), ('p1', 21, 'e2'), ('p1', 21, 'e3');
+             INSERT INTO xtream_history VALUES ('p1', 'episode', 'e1'), ('p1', 'episode', 'e2'), ('p1', 'episode', 'e3');",
+        )
+        .unwrap();
+
+        // Scores for channels 2 and 3, movie 11 and series 21
+        let now = 1_700_000_000;
+        assert_eq!(refresh(&conn, "p1", now).unwrap(), 4);
+        let ordered = |kind: PopularityKind| -> Vec<String> {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT name FROM {} WHERE profile_id = 'p1' ORDER BY {}",
+                    kind.table(),
+                    order_clause(kind)
+                ))
+                .unwrap();
+            stmt.query_map([], |row| row.get(0)).unwrap().map(|name| name.unwrap()).collect()
+        };
+        // Thirty plays outweigh one play of a favorite
+        assert_eq!(ordered(PopularityKind::Channel), vec!["Charlie", "Bravo", "Alpha"]);
+        assert_eq!(ordered(PopularityKind::Movie), vec!["New", "Old", "Unknown"]);
+        assert_eq!(ordered(PopularityKind::Series), vec!["Binged", "Quiet"]);
+
+        // A refresh replaces the previous scores
+        conn.execute("DELETE FROM xtream_playback_stats", []).unwrap();
+        assert_eq!(refresh(&conn, "p1", now).unwrap(), 3);
+        assert_eq!(ordered(PopularityKind::Channel), vec!["Bravo", "Alpha", "Charlie"]);
+    }
+}
@@ -0,0 +1,276 @@
+// Profile preview: what a sync would bring in, without writing content rows
+//
+// Each content type's category list is fetched and its item list is streamed
+// only to count items per category, so a provider's catalog can be sized up
+// before committing to a full sync.
+use crate::content_cache::{RetryConfig, SyncHistoryEntry, SyncScheduler, SyncStatus, XtreamCategory};
+use crate::error::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Content types a sync fetches, in sync order
+pub const PREVIEW_CONTENT_TYPES: [&str; 3] = ["channels", "movies", "series"];
+
+/// Items saved per second assumed when there is no sync history to go on
+pub const ASSUMED_SAVE_RATE: u64 = 10_000;
+
+/// A provider category and the number of items a sync would store in it
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct CategoryPreview {
+    pub category_id: String,
+    pub name: String,
+    pub items: usize,
+}
+
+/// What a sync would fetch for one content type
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct ContentTypePreview {
+    pub content_type: String,
+    pub categories: Vec<CategoryPreview>,
+    pub item_count: usize,
+    /// Items without a category, or in one the category list doesn't include
+    pub uncategorized_items: usize,
+    pub download_bytes: u64,
+    /// Why this content type couldn't be previewed; the others are still reported
+    pub error: Option<String>,
+}
+
+/// What the sync time estimate is based on
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateBasis {
+    /// The profile's previous full syncs
+    History,
+    /// The preview's own download time plus an assumed save rate
+    Preview,
+}
+
+/// Summary of a profile's catalog, gathered without saving it
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ProfilePreview {
+    /// Account details from the authentication response
+    pub user_info: Option<serde_json::Value>,
+    pub content: Vec<ContentTypePreview>,
+    pub total_categories: usize,
+    pub total_items: usize,
+    pub download_bytes: u64,
+    pub fetch_ms: u64,
+    pub estimated_sync_secs: u64,
+    pub estimate_basis: EstimateBasis,
+}
+
+/// Combine a category list with per-category item counts
+pub fn content_type_preview(
+    content_type: &str,
+    categories: Vec<XtreamCategory>,
+    counts: HashMap<String, usize>,
+    download_bytes: u64,
+) -> ContentTypePreview {
+    let known: HashSet<&str> = categories.iter().map(|c| c.category_id.as_str()).collect();
+    let uncategorized_items = counts
+        .iter()
+        .filter(|(id, _)| !known.contains(id.as_str()))
+        .map(|(_, count)| count)
+        .sum();
+
+    ContentTypePreview {
+        content_type: content_type.to_string(),
+        item_count: counts.values().sum(),
+        categories: categories
+            .into_iter()
+            .map(|category| CategoryPreview {
+                items: counts.get(&category.category_id).copied().unwrap_or(0),
+                category_id: category.category_id,
+                name: category.category_name,
+            })
+            .collect(),
+        uncategorized_items,
+        download_bytes,
+        error: None,
+    }
+}
+
+/// Estimate how long a full sync of `total_items` would take
+///
+/// Uses the time per item of the profile's completed full syncs when there are
+/// any; otherwise the preview's download time plus [`ASSUMED_SAVE_RATE`].
+pub fn estimate_sync_secs(history: &[SyncHistoryEntry], total_items: usize, fetch_ms: u64) -> (u64, EstimateBasis) {
+    let (history_ms, history_items) = history
+        .iter()
+        .filter(|entry| entry.full_sync && entry.status == SyncStatus::Completed)
+        .map(|entry| {
+            (
+                entry.duration_ms.max(0) as u64,
+                (entry.channels_count + entry.movies_count + entry.series_count) as u64,
+            )
+        })
+        .filter(|(_, items)| *items > 0)
+        .fold((0, 0), |(ms, items), (entry_ms, entry_items)| (ms + entry_ms, items + entry_items));
+
+    if let Some(ms) = (total_items as u64 * history_ms).checked_div(history_items) {
+        return (ms.div_ceil(1000), EstimateBasis::History);
+    }
+
+    let save_ms = total_items as u64 * 1000 / ASSUMED_SAVE_RATE;
+    ((fetch_ms + save_ms).div_ceil(1000), EstimateBasis::Preview)
+}
+
+async fn preview_content_type(
+    client: &reqwest::Client,
+    base_url: &str,
+    username: &str,
+    password: &str,
+    content_type: &str,
+    retry_config: &RetryConfig,
+    cancel_token: &CancellationToken,
+) -> Result<ContentTypePreview> {
+    let categories_data = SyncScheduler::fetch_categories_with_retry(
+        client,
+        base_url,
+        username,
+        password,
+        content_type,
+        retry_config,
+        cancel_token,
+    )
+    .await?;
+    let categories = SyncScheduler::parse_categories(&categories_data)?;
+
+    let (counts, download_bytes) = SyncScheduler::count_content_by_category(
+        client,
+        base_url,
+        username,
+        password,
+        content_type,
+        retry_config,
+        cancel_token,
+    )
+    .await?;
+
+    Ok(content_type_preview(content_type, categories, counts, download_bytes))
+}
+
+/// Fetch category lists and item counts for every content type
+///
+/// A content type that fails is reported with its error rather than failing
+/// the whole preview.
+pub async fn preview_content(
+    base_url: &str,
+    username: &str,
+    password: &str,
+    history: &[SyncHistoryEntry],
+) -> Result<ProfilePreview> {
+    let client = crate::connection::client_builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let retry_config = RetryConfig::default();
+    let cancel_token = CancellationToken::new();
+
+    let started = Instant::now();
+    let mut content = Vec::new();
+    for content_type in PREVIEW_CONTENT_TYPES {
+        let preview = preview_content_type(
+            &client,
+            base_url,
+            username,
+            password,
+            content_type,
+            &retry_config,
+            &cancel_token,
+        )
+        .await
+        .unwrap_or_else(|e| ContentTypePreview {
+            content_type: content_type.to_string(),
+            categories: Vec::new(),
+            item_count: 0,
+            uncategorized_items: 0,
+            download_bytes: 0,
+            error: Some(e.to_string()),
+        });
+        content.push(preview);
+    }
+    let fetch_ms = started.elapsed().as_millis() as u64;
+
+    let total_items = content.iter().map(|c| c.item_count).sum();
+    let (estimated_sync_secs, estimate_basis) = estimate_sync_secs(history, total_items, fetch_ms);
+
+    Ok(ProfilePreview {
+        user_info: None,
+        total_categories: content.iter().map(|c| c.categories.len()).sum(),
+        total_items,
+        download_bytes: content.iter().map(|c| c.download_bytes).sum(),
+        content,
+        fetch_ms,
+        estimated_sync_secs,
+        estimate_basis,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(id: &str, name: &str) -> XtreamCategory {
+        XtreamCategory {
+            category_id: id.to_string(),
+            category_name: name.to_string(),
+            parent_id: None,
+        }
+    }
+
+    fn full_sync(duration_ms: i64, movies_count: usize, status: SyncStatus) -> SyncHistoryEntry {
+        SyncHistoryEntry {
+            id: 0,
+            profile_id: "p1".to_string(),
+            full_sync: true,
+            status,
+            started_at: String::new(),
+            duration_ms,
+            channels_count: 0,
+            movies_count,
+            series_count: 0,
+            channels_delta: 0,
+            movies_delta: 0,
+            series_delta: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_content_type_preview_counts_categories() {
+        let counts = HashMap::from([
+            ("1".to_string(), 3),
+            ("2".to_string(), 5),
+            ("99".to_string(), 1),
+            (String::new(), 2),
+        ]);
+        let preview = content_type_preview(
+            "movies",
+            vec![category("1", "Action"), category("2", "Drama"), category("3", "Empty")],
+            counts,
+            4096,
+        );
+
+        assert_eq!(preview.item_count, 11);
+        assert_eq!(preview.uncategorized_items, 3);
+        assert_eq!(
+            preview.categories.iter().map(|c| (c.name.as_str(), c.items)).collect::<Vec<_>>(),
+            vec![("Action", 3), ("Drama", 5), ("Empty", 0)]
+        );
+    }
+
+    #[test]
+    fn test_estimate_sync_secs() {
+        // 20k items took 10s before, so 80k should take about 40s
+        let history = vec![
+            full_sync(10_000, 20_000, SyncStatus::Completed),
+            full_sync(1_000, 50_000, SyncStatus::Failed),
+        ];
+        assert_eq!(estimate_sync_secs(&history, 80_000, 5_000), (40, EstimateBasis::History));
+
+        // Without history: the preview's download time plus the assumed save rate
+        assert_eq!(estimate_sync_secs(&[], 80_000, 5_000), (13, EstimateBasis::Preview));
+    }
+}
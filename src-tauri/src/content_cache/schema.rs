@@ -1,8 +1,36 @@
-use crate::error::{Result, XTauriError};
+use crate::error::Result;
+use crate::migrations::{self, has_column, Migration, CONTENT_CACHE};
 use rusqlite::Connection;
 
 /// Database schema version
-pub const SCHEMA_VERSION: i32 = 1;
+pub const SCHEMA_VERSION: i32 = 23;
+
+/// Steps from each schema version to the next; fresh installs start at `SCHEMA_VERSION`
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "Initial content cache schema", up: migrate_to_v1 },
+    Migration { version: 2, description: "Sync history", up: create_sync_history_table },
+    Migration { version: 3, description: "Channel URL overrides", up: create_channel_url_overrides_table },
+    Migration { version: 4, description: "Playback failures", up: create_playback_failures_table },
+    Migration { version: 5, description: "EPG programs", up: create_epg_programs_table },
+    Migration { version: 6, description: "Channel positions", up: create_channel_positions_table },
+    Migration { version: 7, description: "Age ratings", up: add_age_rating_columns },
+    Migration { version: 8, description: "Name ordinals", up: add_name_ordinal_columns },
+    Migration { version: 9, description: "Channel countries", up: add_channel_country_column },
+    Migration { version: 10, description: "Playback stats", up: create_playback_stats_table },
+    Migration { version: 11, description: "Favorites-only sync", up: add_favorites_only_sync_column },
+    Migration { version: 12, description: "Synthetic EPG programs", up: add_epg_synthetic_column },
+    Migration { version: 13, description: "Sync limits", up: create_sync_limits_table },
+    Migration { version: 14, description: "Similarity tokens", up: create_similarity_tokens_table },
+    Migration { version: 15, description: "Genre mappings", up: create_genre_mappings_table },
+    Migration { version: 16, description: "Content fingerprints", up: create_content_fingerprints_table },
+    Migration { version: 17, description: "Request limits", up: create_request_limits_table },
+    Migration { version: 18, description: "Popularity scores", up: create_popularity_table },
+    Migration { version: 19, description: "EPG channel mappings", up: create_epg_mappings_table },
+    Migration { version: 20, description: "Followed categories", up: create_followed_categories_tables },
+    Migration { version: 21, description: "Watchlist", up: create_watchlist_table },
+    Migration { version: 22, description: "Content tags", up: crate::xtream::tags::create_table },
+    Migration { version: 23, description: "EPG programme icons", up: add_epg_icon_column },
+];
 
 /// Initialize all content cache tables
 pub fn initialize_content_cache_tables(conn: &Connection) -> Result<()> {
@@ -19,7 +47,7 @@ pub fn initialize_content_cache_tables(conn: &Connection) -> Result<()> {
         set_schema_version(conn, SCHEMA_VERSION)?;
     } else if current_version < SCHEMA_VERSION {
         // Run migrations
-        run_migrations(conn, current_version, SCHEMA_VERSION)?;
+        migrations::run(conn, CONTENT_CACHE, MIGRATIONS)?;
     }
     
     Ok(())
@@ -45,6 +73,7 @@ fn create_all_tables(conn: &Connection) -> Result<()> {
             tv_archive INTEGER DEFAULT 0,
             direct_source TEXT,
             tv_archive_duration INTEGER DEFAULT 0,
+            country TEXT,
             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE,
@@ -97,6 +126,8 @@ fn create_all_tables(conn: &Connection) -> Result<()> {
             director TEXT,
             plot TEXT,
             youtube_trailer TEXT,
+            age_rating INTEGER,
+            name_ordinal INTEGER,
             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE,
@@ -152,6 +183,8 @@ fn create_all_tables(conn: &Connection) -> Result<()> {
             rating_5based REAL,
             episode_run_time TEXT,
             category_id TEXT,
+            age_rating INTEGER,
+            name_ordinal INTEGER,
             created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
             FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE,
@@ -337,69 +370,461 @@ fn create_all_tables(conn: &Connection) -> Result<()> {
         [],
     )?;
     
+    create_sync_history_table(conn)?;
+    create_channel_url_overrides_table(conn)?;
+    create_playback_failures_table(conn)?;
+    create_epg_programs_table(conn)?;
+    create_channel_positions_table(conn)?;
+    add_age_rating_columns(conn)?;
+    add_name_ordinal_columns(conn)?;
+    add_channel_country_column(conn)?;
+    create_playback_stats_table(conn)?;
+    add_favorites_only_sync_column(conn)?;
+    add_epg_synthetic_column(conn)?;
+    create_sync_limits_table(conn)?;
+    create_similarity_tokens_table(conn)?;
+    create_genre_mappings_table(conn)?;
+    create_content_fingerprints_table(conn)?;
+    create_request_limits_table(conn)?;
+    create_popularity_table(conn)?;
+    create_epg_mappings_table(conn)?;
+    create_followed_categories_tables(conn)?;
+    create_watchlist_table(conn)?;
+    crate::xtream::tags::create_table(conn)?;
+    add_epg_icon_column(conn)?;
+    
     Ok(())
 }
 
-/// Get current schema version
-fn get_schema_version(conn: &Connection) -> Result<i32> {
-    // Check if schema_version table exists
-    let table_exists: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='schema_version'",
-            [],
-            |row| row.get::<_, i32>(0),
-        )
-        .map(|count| count > 0)?;
+/// Create the sync history table (added in schema version 2)
+fn create_sync_history_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_sync_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id TEXT NOT NULL,
+            full_sync BOOLEAN NOT NULL,
+            status TEXT NOT NULL,
+            started_at TIMESTAMP NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            channels_count INTEGER DEFAULT 0,
+            movies_count INTEGER DEFAULT 0,
+            series_count INTEGER DEFAULT 0,
+            channels_delta INTEGER DEFAULT 0,
+            movies_delta INTEGER DEFAULT 0,
+            series_delta INTEGER DEFAULT 0,
+            errors TEXT,
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sync_history_profile 
+         ON xtream_sync_history(profile_id, started_at DESC)",
+        [],
+    )?;
     
-    if !table_exists {
-        // Create schema_version table
+    Ok(())
+}
+
+/// Create the channel URL overrides table (added in schema version 3)
+///
+/// Keyed by the provider's stream id rather than the cached row, so overrides
+/// survive channels being deleted and re-inserted by a sync.
+fn create_channel_url_overrides_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_channel_url_overrides (
+            profile_id TEXT NOT NULL,
+            stream_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (profile_id, stream_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the playback failures table (added in schema version 4)
+fn create_playback_failures_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_playback_failures (
+            profile_id TEXT NOT NULL,
+            stream_id INTEGER NOT NULL,
+            failure_count INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            last_failure_at INTEGER NOT NULL,
+            PRIMARY KEY (profile_id, stream_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the EPG programme store (added in schema version 5)
+///
+/// Filled from EPG responses as they are fetched, keyed by the EPG channel id
+/// so channel listings can be joined against it.
+fn create_epg_programs_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_epg_programs (
+            profile_id TEXT NOT NULL,
+            epg_channel_id TEXT NOT NULL,
+            start_timestamp INTEGER NOT NULL,
+            stop_timestamp INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            PRIMARY KEY (profile_id, epg_channel_id, start_timestamp),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_epg_programs_airing 
+         ON xtream_epg_programs(profile_id, stop_timestamp, start_timestamp)",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the manual channel order table (added in schema version 6)
+///
+/// Like URL overrides, positions are keyed by stream id so a sync that
+/// re-inserts the category's channels keeps the user's order.
+fn create_channel_positions_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_channel_positions (
+            profile_id TEXT NOT NULL,
+            category_id TEXT NOT NULL,
+            stream_id INTEGER NOT NULL,
+            position REAL NOT NULL,
+            PRIMARY KEY (profile_id, category_id, stream_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Add the normalized age rating to movies and series (added in schema version 7)
+///
+/// Fresh installs already have the column from `create_all_tables`.
+fn add_age_rating_columns(conn: &Connection) -> Result<()> {
+    for table in ["xtream_movies", "xtream_series"] {
+        if !has_column(conn, table, "age_rating")? {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN age_rating INTEGER", table), [])?;
+        }
+    }
+    
+    Ok(())
+}
+
+/// Add the precomputed name order to movies and series (added in schema version 8)
+///
+/// See `sort_index`; the indexes let a presorted category be paged without sorting.
+fn add_name_ordinal_columns(conn: &Connection) -> Result<()> {
+    for (table, index) in [
+        ("xtream_movies", "idx_movies_name_ordinal"),
+        ("xtream_series", "idx_series_name_ordinal"),
+    ] {
+        if !has_column(conn, table, "name_ordinal")? {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN name_ordinal INTEGER", table), [])?;
+        }
         conn.execute(
-            "CREATE TABLE schema_version (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                version INTEGER NOT NULL,
-                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-            )",
+            &format!(
+                "CREATE INDEX IF NOT EXISTS {} ON {}(profile_id, category_id, name_ordinal)",
+                index, table
+            ),
             [],
         )?;
-        return Ok(0);
     }
     
-    // Get current version
-    let version = conn
-        .query_row("SELECT version FROM schema_version WHERE id = 1", [], |row| {
-            row.get(0)
-        })
-        .unwrap_or(0);
+    Ok(())
+}
+
+/// Add the detected country to channels (added in schema version 9)
+///
+/// Channels cached before then get their country from the stored name and
+/// category. Names whose country tag was stripped by a post-processor only
+/// get one from the category until the next sync.
+fn add_channel_country_column(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "xtream_channels", "country")? {
+        conn.execute("ALTER TABLE xtream_channels ADD COLUMN country TEXT", [])?;
+
+        // Runs inside the migration's transaction
+        let mut select = conn.prepare(
+            "SELECT c.id, c.name, COALESCE(cat.category_name, '')
+             FROM xtream_channels c
+             LEFT JOIN xtream_channel_categories cat
+               ON cat.profile_id = c.profile_id AND cat.category_id = c.category_id",
+        )?;
+        let mut update = conn.prepare("UPDATE xtream_channels SET country = ?1 WHERE id = ?2")?;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(1)?;
+            let category: String = row.get(2)?;
+            if let Some(code) = crate::country::detect_country("", &name, &category) {
+                update.execute(rusqlite::params![code, row.get::<_, i64>(0)?])?;
+            }
+        }
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_channels_country ON xtream_channels(profile_id, country)",
+        [],
+    )?;
     
-    Ok(version)
+    Ok(())
 }
 
-/// Set schema version
-fn set_schema_version(conn: &Connection, version: i32) -> Result<()> {
+/// Create the playback reliability counters (added in schema version 10)
+fn create_playback_stats_table(conn: &Connection) -> Result<()> {
     conn.execute(
-        "INSERT OR REPLACE INTO schema_version (id, version, updated_at) VALUES (1, ?1, CURRENT_TIMESTAMP)",
-        [version],
+        "CREATE TABLE IF NOT EXISTS xtream_playback_stats (
+            profile_id TEXT NOT NULL,
+            stream_id INTEGER NOT NULL,
+            start_count INTEGER NOT NULL DEFAULT 0,
+            buffer_count INTEGER NOT NULL DEFAULT 0,
+            failure_count INTEGER NOT NULL DEFAULT 0,
+            last_error_code TEXT,
+            last_event_at INTEGER NOT NULL,
+            PRIMARY KEY (profile_id, stream_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
     )?;
+    
     Ok(())
 }
 
-/// Run migrations from old version to new version
-fn run_migrations(conn: &Connection, from_version: i32, to_version: i32) -> Result<()> {
-    for version in (from_version + 1)..=to_version {
-        match version {
-            1 => migrate_to_v1(conn)?,
-            _ => {
-                return Err(XTauriError::content_cache(format!(
-                    "Unknown migration version: {}",
-                    version
-                )))
-            }
-        }
-        set_schema_version(conn, version)?;
+/// Add the favorites-only sync mode to sync settings (added in schema version 11)
+fn add_favorites_only_sync_column(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "xtream_sync_settings", "favorites_only")? {
+        conn.execute(
+            "ALTER TABLE xtream_sync_settings ADD COLUMN favorites_only BOOLEAN NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Flag filler programmes that cover gaps in a provider's guide (added in schema version 12)
+fn add_epg_synthetic_column(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "xtream_epg_programs", "synthetic")? {
+        conn.execute(
+            "ALTER TABLE xtream_epg_programs ADD COLUMN synthetic BOOLEAN NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    
+    Ok(())
+}
+
+/// Keep the image the provider lists for each programme (added in schema version 23)
+fn add_epg_icon_column(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "xtream_epg_programs", "icon_url")? {
+        conn.execute("ALTER TABLE xtream_epg_programs ADD COLUMN icon_url TEXT", [])?;
     }
+    
+    Ok(())
+}
+
+/// Create the table of request limits learned from provider health (added in schema version 13)
+fn create_sync_limits_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_sync_limits (
+            profile_id TEXT PRIMARY KEY,
+            max_concurrency INTEGER NOT NULL,
+            request_delay_ms INTEGER NOT NULL,
+            throttle_count INTEGER NOT NULL DEFAULT 0,
+            last_throttled_at INTEGER,
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the table of movie and series tokens used to find similar content (added in schema version 14)
+fn create_similarity_tokens_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_similarity_tokens (
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            content_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            token TEXT NOT NULL,
+            PRIMARY KEY (profile_id, content_type, content_id, kind, token),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_similarity_tokens_token
+         ON xtream_similarity_tokens(profile_id, content_type, kind, token)",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the table of user mappings from provider genre names to canonical genres (added in schema version 15)
+fn create_genre_mappings_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS genre_mappings (
+            provider_genre TEXT PRIMARY KEY,
+            genre_id TEXT NOT NULL
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the table of provider item fingerprints used by delta syncs (added in schema version 16)
+fn create_content_fingerprints_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_content_fingerprints (
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            content_id INTEGER NOT NULL,
+            fingerprint TEXT NOT NULL,
+            PRIMARY KEY (profile_id, content_type, content_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the table of per-profile request limits (added in schema version 17)
+fn create_request_limits_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_request_limits (
+            profile_id TEXT PRIMARY KEY,
+            requests_per_second REAL NOT NULL,
+            burst INTEGER NOT NULL,
+            max_concurrent INTEGER NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the table of popularity scores listings can be sorted by (added in schema version 18)
+fn create_popularity_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_popularity (
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            content_id INTEGER NOT NULL,
+            score REAL NOT NULL,
+            PRIMARY KEY (profile_id, content_type, content_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the table of EPG ids matched to channels by name (added in schema version 19)
+///
+/// `status` is `applied`, `suggested` or `rejected`; a rejected channel keeps
+/// one row with an empty `epg_channel_id`.
+fn create_epg_mappings_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_epg_mappings (
+            profile_id TEXT NOT NULL,
+            stream_id INTEGER NOT NULL,
+            epg_channel_id TEXT NOT NULL,
+            display_name TEXT,
+            confidence REAL,
+            status TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY (profile_id, stream_id, epg_channel_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Create the followed categories and their feed of new items (added in schema version 20)
+fn create_followed_categories_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_followed_categories (
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            category_id TEXT NOT NULL,
+            category_name TEXT NOT NULL,
+            followed_at INTEGER NOT NULL,
+            PRIMARY KEY (profile_id, content_type, category_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_followed_category_updates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            category_id TEXT NOT NULL,
+            category_name TEXT NOT NULL,
+            item_id INTEGER NOT NULL,
+            item_name TEXT NOT NULL,
+            added_at INTEGER NOT NULL,
+            read_at INTEGER,
+            UNIQUE(profile_id, content_type, category_id, item_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
     Ok(())
 }
 
+/// Create the watchlist of movies and series to watch later (added in schema version 21)
+fn create_watchlist_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_watchlist (
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            content_id INTEGER NOT NULL,
+            added_at INTEGER NOT NULL,
+            PRIMARY KEY (profile_id, content_type, content_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+/// Get current schema version
+fn get_schema_version(conn: &Connection) -> Result<i32> {
+    migrations::current_version(conn, CONTENT_CACHE)
+}
+
+/// Set schema version
+fn set_schema_version(conn: &Connection, version: i32) -> Result<()> {
+    migrations::set_version(conn, CONTENT_CACHE, version)
+}
+
 /// Migration to version 1 (initial schema)
 fn migrate_to_v1(conn: &Connection) -> Result<()> {
     // This is the initial schema, so just create all tables
@@ -464,6 +889,7 @@ mod tests {
             "xtream_series_categories",
             "xtream_content_sync",
             "xtream_sync_settings",
+            "xtream_sync_history",
         ];
         
         for table in tables {
@@ -639,6 +1065,7 @@ mod tests {
         // Running again should not error
         let result = initialize_content_cache_tables(&conn);
         assert!(result.is_ok());
+        assert_eq!(migrations::latest_version(MIGRATIONS), SCHEMA_VERSION);
     }
     
     #[test]
@@ -677,4 +1104,297 @@ mod tests {
         assert_eq!(wifi_only, true);
         assert_eq!(notify, false);
     }
+    
+    #[test]
+    fn test_migration_from_v1_adds_sync_history() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE xtream_sync_history", []).unwrap();
+        
+        // Simulate a database created before sync history existed
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 1).unwrap();
+        
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: i32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='xtream_sync_history'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(exists, 1);
+    }    
+    #[test]
+    fn test_migration_from_v2_adds_url_overrides() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE xtream_channel_url_overrides", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 2).unwrap();
+        
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: i32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='xtream_channel_url_overrides'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(exists, 1);
+    }
+
+    #[test]
+    fn test_migration_from_v6_adds_age_rating() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("ALTER TABLE xtream_movies DROP COLUMN age_rating", []).unwrap();
+        conn.execute("ALTER TABLE xtream_series DROP COLUMN age_rating", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 6).unwrap();
+        
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        for table in ["xtream_movies", "xtream_series"] {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = 'age_rating'",
+                    [table],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert!(exists, "{} has no age_rating column", table);
+        }
+    }
+    
+    #[test]
+    fn test_migration_from_v8_detects_channel_countries() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute_batch(
+            "DROP INDEX idx_channels_country;
+             ALTER TABLE xtream_channels DROP COLUMN country;
+             INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('test-profile', 'Test', 'http://example.com', 'user', X'00');
+             INSERT INTO xtream_channel_categories (profile_id, category_id, category_name)
+             VALUES ('test-profile', '1', 'FRANCE FHD');
+             INSERT INTO xtream_channels (profile_id, stream_id, name, category_id)
+             VALUES ('test-profile', 1, 'UK: BBC One', NULL),
+                    ('test-profile', 2, 'TF1', '1'),
+                    ('test-profile', 3, 'Movies 24', NULL);",
+        )
+        .unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 8).unwrap();
+        
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let countries: Vec<Option<String>> = conn
+            .prepare("SELECT country FROM xtream_channels ORDER BY stream_id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(countries, vec![Some("GB".to_string()), Some("FR".to_string()), None]);
+    }
+    
+    #[test]
+    fn test_migration_from_v9_adds_playback_stats() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE xtream_playback_stats", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 9).unwrap();
+        
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: i32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='xtream_playback_stats'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(exists, 1);
+    }
+    
+    #[test]
+    fn test_migration_from_v10_adds_favorites_only() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("ALTER TABLE xtream_sync_settings DROP COLUMN favorites_only", []).unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials) VALUES ('p1', 'P', 'http://x', 'u', X'00')",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO xtream_sync_settings (profile_id) VALUES ('p1')", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 10).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let favorites_only: bool = conn
+            .query_row("SELECT favorites_only FROM xtream_sync_settings WHERE profile_id = 'p1'", [], |row| row.get(0))
+            .unwrap();
+        assert!(!favorites_only);
+    }
+    
+    #[test]
+    fn test_migration_from_v11_adds_epg_synthetic() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("ALTER TABLE xtream_epg_programs DROP COLUMN synthetic", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 11).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('xtream_epg_programs') WHERE name = 'synthetic'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(exists);
+    }
+    
+    #[test]
+    fn test_migration_from_v22_adds_epg_icons() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("ALTER TABLE xtream_epg_programs DROP COLUMN icon_url", []).unwrap();
+        
+        set_schema_version(&conn, 22).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&conn, "xtream_epg_programs", "icon_url").unwrap());
+    }
+    
+    #[test]
+    fn test_migration_from_v12_adds_sync_limits() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE xtream_sync_limits", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 12).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'xtream_sync_limits'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(exists);
+    }
+    
+    #[test]
+    fn test_migration_from_v13_adds_similarity_tokens() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE xtream_similarity_tokens", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 13).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'index' AND name = 'idx_similarity_tokens_token'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(exists);
+    }
+    
+    #[test]
+    fn test_migration_from_v14_adds_genre_mappings() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE genre_mappings", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 14).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.execute(
+            "INSERT INTO genre_mappings (provider_genre, genre_id) VALUES ('médiéval', 'history')",
+            [],
+        )
+        .unwrap();
+    }
+    
+    #[test]
+    fn test_migration_from_v15_adds_content_fingerprints() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute("DROP TABLE xtream_content_fingerprints", []).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 15).unwrap();
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'xtream_content_fingerprints'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(exists);
+    }
+    
+    #[test]
+    fn test_migration_from_v7_adds_name_ordinal() {
+        let conn = create_test_db();
+        create_all_tables(&conn).unwrap();
+        conn.execute_batch(
+            "DROP INDEX idx_movies_name_ordinal;
+             DROP INDEX idx_series_name_ordinal;
+             ALTER TABLE xtream_movies DROP COLUMN name_ordinal;
+             ALTER TABLE xtream_series DROP COLUMN name_ordinal;",
+        )
+        .unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+        set_schema_version(&conn, 7).unwrap();
+        
+        initialize_content_cache_tables(&conn).unwrap();
+        
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        for index in ["idx_movies_name_ordinal", "idx_series_name_ordinal"] {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'index' AND name = ?1",
+                    [index],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert!(exists, "{} is missing", index);
+        }
+    }
 }
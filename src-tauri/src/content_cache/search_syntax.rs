@@ -0,0 +1,232 @@
+// Search query syntax for the content cache
+//
+// Simple mode matches the query text literally. Advanced mode adds `*` and `?`
+// wildcards and quoted phrases, translated into escaped LIKE patterns and FTS5
+// queries so user input can never inject syntax of its own.
+use crate::content_cache::db_utils::sanitize_like_pattern;
+use crate::content_cache::fts::prepare_fts_query;
+use serde::{Deserialize, Serialize};
+
+/// How a search query is interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// The query is plain text; wildcard characters match themselves
+    #[default]
+    Simple,
+    /// `*`, `?` and double quotes have the meanings in [`search_syntax_help`]
+    Advanced,
+}
+
+/// One piece of an advanced query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchTerm {
+    /// Unquoted text, which may contain `*` and `?`
+    Pattern(String),
+    /// Quoted text, matched exactly
+    Phrase(String),
+}
+
+/// Split an advanced query into terms
+///
+/// Unquoted whitespace separates terms. A quote left open runs to the end of the query.
+pub fn parse_terms(query: &str) -> Vec<SearchTerm> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut in_phrase = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                if in_phrase {
+                    let phrase = current.trim();
+                    if !phrase.is_empty() {
+                        terms.push(SearchTerm::Phrase(phrase.to_string()));
+                    }
+                } else if !current.is_empty() {
+                    terms.push(SearchTerm::Pattern(current.clone()));
+                }
+                current.clear();
+                in_phrase = !in_phrase;
+            }
+            c if c.is_whitespace() && !in_phrase => {
+                if !current.is_empty() {
+                    terms.push(SearchTerm::Pattern(current.clone()));
+                    current.clear();
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    let rest = current.trim();
+    if !rest.is_empty() {
+        terms.push(if in_phrase {
+            SearchTerm::Phrase(rest.to_string())
+        } else {
+            SearchTerm::Pattern(rest.to_string())
+        });
+    }
+    terms
+}
+
+/// LIKE pattern for a query, for use with `ESCAPE '\'`
+///
+/// Both modes match anywhere in the field. In advanced mode the terms must
+/// appear in the order given, with anything in between.
+pub fn like_pattern(query: &str, mode: SearchMode) -> String {
+    if mode == SearchMode::Simple {
+        return format!("%{}%", sanitize_like_pattern(query));
+    }
+
+    let terms: Vec<String> = parse_terms(query)
+        .into_iter()
+        .map(|term| match term {
+            SearchTerm::Phrase(phrase) => sanitize_like_pattern(&phrase),
+            SearchTerm::Pattern(pattern) => sanitize_like_pattern(&pattern).replace('*', "%").replace('?', "_"),
+        })
+        .collect();
+    format!("%{}%", terms.join("%"))
+}
+
+fn fts_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+/// FTS5 MATCH query for a query; empty when there is nothing to match
+///
+/// Advanced terms must all match. FTS5 only supports prefix wildcards, so a
+/// term containing `*` or `?` matches words starting with the text before the
+/// first wildcard.
+pub fn fts_query(query: &str, mode: SearchMode) -> String {
+    if mode == SearchMode::Simple {
+        return prepare_fts_query(query);
+    }
+
+    parse_terms(query)
+        .into_iter()
+        .filter_map(|term| match term {
+            SearchTerm::Phrase(phrase) => Some(fts_string(&phrase)),
+            SearchTerm::Pattern(pattern) => match pattern.find(['*', '?']) {
+                Some(0) => None,
+                Some(wildcard) => Some(format!("{}*", fts_string(&pattern[..wildcard]))),
+                None => Some(fts_string(&pattern)),
+            },
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A search mode or syntax element, as described by `get_search_syntax_help`
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct SyntaxEntry {
+    pub syntax: &'static str,
+    pub description: &'static str,
+    pub example: Option<&'static str>,
+}
+
+/// The supported search grammar
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct SearchSyntaxHelp {
+    pub modes: Vec<SyntaxEntry>,
+    /// Syntax understood in advanced mode
+    pub advanced: Vec<SyntaxEntry>,
+    pub notes: Vec<&'static str>,
+}
+
+pub fn search_syntax_help() -> SearchSyntaxHelp {
+    SearchSyntaxHelp {
+        modes: vec![
+            SyntaxEntry {
+                syntax: "simple",
+                description: "The query is matched as typed; no characters are special",
+                example: Some("50% off"),
+            },
+            SyntaxEntry {
+                syntax: "advanced",
+                description: "Wildcards and quoted phrases are recognised",
+                example: Some("\"star wars\" 19??"),
+            },
+        ],
+        advanced: vec![
+            SyntaxEntry {
+                syntax: "*",
+                description: "Any run of characters, including none",
+                example: Some("bat*man"),
+            },
+            SyntaxEntry {
+                syntax: "?",
+                description: "Exactly one character",
+                example: Some("19??"),
+            },
+            SyntaxEntry {
+                syntax: "\"...\"",
+                description: "A phrase matched exactly; * and ? inside it are literal",
+                example: Some("\"what?\""),
+            },
+            SyntaxEntry {
+                syntax: "term term",
+                description: "All terms must match, in the order given",
+                example: Some("news sport"),
+            },
+        ],
+        notes: vec![
+            "Matching is case-insensitive and can start anywhere in a name",
+            "Full-text series search only supports wildcards at the end of a word; text after the first * or ? is ignored",
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_terms() {
+        assert_eq!(
+            parse_terms("bat*  \"the dark knight\" 19?? \"open"),
+            vec![
+                SearchTerm::Pattern("bat*".to_string()),
+                SearchTerm::Phrase("the dark knight".to_string()),
+                SearchTerm::Pattern("19??".to_string()),
+                SearchTerm::Phrase("open".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!(like_pattern("50%_off*", SearchMode::Simple), "%50\\%\\_off*%");
+        assert_eq!(like_pattern("bat*man 19??", SearchMode::Advanced), "%bat%man%19__%");
+        assert_eq!(like_pattern("\"100% what?\"", SearchMode::Advanced), "%100\\% what?%");
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("action movie", SearchMode::Simple), "action* OR movie*");
+        assert_eq!(
+            fts_query("\"star wars\" jed?i * say\"", SearchMode::Advanced),
+            "\"star wars\" \"jed\"* \"say\""
+        );
+    }
+
+    #[test]
+    fn test_like_pattern_matches_in_sqlite() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let matches = |name: &str, query: &str, mode: SearchMode| -> bool {
+            conn.query_row(
+                "SELECT ?1 LIKE ?2 ESCAPE '\\'",
+                [name, &like_pattern(query, mode)],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+
+        assert!(matches("Save 50% Today", "50%", SearchMode::Simple));
+        assert!(!matches("Save 500 Today", "50%", SearchMode::Simple));
+        assert!(matches("Batman Returns (1992)", "bat*ret* 199?", SearchMode::Advanced));
+        assert!(!matches("Batman Returns (1992)", "ret* bat*", SearchMode::Advanced));
+        assert!(matches("What? Really", "\"what?\"", SearchMode::Advanced));
+        assert!(!matches("Whats Really", "\"what?\"", SearchMode::Advanced));
+    }
+}
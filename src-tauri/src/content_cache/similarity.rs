@@ -0,0 +1,323 @@
+// "More like this" from cached metadata
+//
+// Every cached movie and series gets a set of tokens after each sync: its
+// genres, cast members and directors, and the distinctive words of its plot.
+// Similar items are the ones sharing the most tokens, weighted so a shared
+// director counts for more than a shared plot word. Everything is read from the
+// content cache, so it works offline.
+use crate::content_cache::age_rating;
+use crate::error::Result;
+use crate::expiry;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Plot words kept per item; later ones add little but noise
+const MAX_PLOT_KEYWORDS: usize = 24;
+
+/// Plot words shorter than this are rarely distinctive
+const MIN_KEYWORD_LEN: usize = 4;
+
+/// Common plot words that say nothing about the content
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "against", "also", "among", "another", "around", "back", "became", "because",
+    "becomes", "been", "before", "being", "between", "both", "but", "come", "comes", "could", "does", "down",
+    "during", "each", "even", "ever", "every", "find", "finds", "first", "from", "gets", "have", "having", "here",
+    "himself", "herself", "into", "just", "life", "like", "made", "make", "makes", "many", "more", "most", "much",
+    "must", "never", "only", "other", "over", "own", "same", "should", "since", "some", "soon", "still", "such",
+    "take", "takes", "than", "that", "their", "them", "themselves", "then", "there", "these", "they", "this",
+    "those", "through", "together", "under", "until", "upon", "very", "what", "when", "where", "which", "while",
+    "who", "whose", "will", "with", "within", "without", "would", "year", "years", "your",
+];
+
+/// Content that similar items are found for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SimilarContentType {
+    Movie,
+    Series,
+}
+
+impl SimilarContentType {
+    fn as_str(self) -> &'static str {
+        match self {
+            SimilarContentType::Movie => "movie",
+            SimilarContentType::Series => "series",
+        }
+    }
+
+    /// Table, id column and artwork column of the cached items
+    fn columns(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            SimilarContentType::Movie => ("xtream_movies", "stream_id", "stream_icon"),
+            SimilarContentType::Series => ("xtream_series", "series_id", "cover"),
+        }
+    }
+}
+
+/// What a token was taken from, which sets its weight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TokenKind {
+    Genre,
+    Cast,
+    Director,
+    Keyword,
+}
+
+impl TokenKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenKind::Genre => "genre",
+            TokenKind::Cast => "cast",
+            TokenKind::Director => "director",
+            TokenKind::Keyword => "keyword",
+        }
+    }
+}
+
+/// A cached item ranked by how much it has in common with another
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct SimilarItem {
+    pub id: i64,
+    pub name: String,
+    pub icon: Option<String>,
+    pub year: Option<String>,
+    pub score: f64,
+}
+
+/// Names from a comma, slash or pipe separated list, lowercased
+fn list_tokens(list: Option<&str>) -> impl Iterator<Item = String> + '_ {
+    list.unwrap_or_default()
+        .split([',', '/', '|', ';'])
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+}
+
+/// Distinctive words of a plot, in order of first appearance
+fn plot_keywords(plot: Option<&str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    plot.unwrap_or_default()
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| {
+            word.chars().count() >= MIN_KEYWORD_LEN
+                && !word.chars().all(|c| c.is_ascii_digit())
+                && !STOPWORDS.contains(&word.as_str())
+        })
+        .filter(|word| seen.insert(word.clone()))
+        .take(MAX_PLOT_KEYWORDS)
+        .collect()
+}
+
+fn item_tokens(
+    genre: Option<&str>,
+    cast: Option<&str>,
+    director: Option<&str>,
+    plot: Option<&str>,
+) -> HashSet<(TokenKind, String)> {
+    list_tokens(genre)
+        .map(|token| (TokenKind::Genre, token))
+        .chain(list_tokens(cast).map(|token| (TokenKind::Cast, token)))
+        .chain(list_tokens(director).map(|token| (TokenKind::Director, token)))
+        .chain(plot_keywords(plot).into_iter().map(|token| (TokenKind::Keyword, token)))
+        .collect()
+}
+
+/// Recompute the token sets of a profile's movies and series, returning how many items have any
+pub fn rebuild(conn: &Connection, profile_id: &str) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM xtream_similarity_tokens WHERE profile_id = ?1", [profile_id])?;
+
+    let mut indexed = 0;
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR IGNORE INTO xtream_similarity_tokens (profile_id, content_type, content_id, kind, token)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for content_type in [SimilarContentType::Movie, SimilarContentType::Series] {
+            let (table, id_column, _) = content_type.columns();
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {}, genre, \"cast\", director, plot FROM {} WHERE profile_id = ?1",
+                id_column, table
+            ))?;
+            let mut rows = stmt.query([profile_id])?;
+            while let Some(row) = rows.next()? {
+                let id: i64 = row.get(0)?;
+                let tokens = item_tokens(
+                    row.get::<_, Option<String>>(1)?.as_deref(),
+                    row.get::<_, Option<String>>(2)?.as_deref(),
+                    row.get::<_, Option<String>>(3)?.as_deref(),
+                    row.get::<_, Option<String>>(4)?.as_deref(),
+                );
+                if tokens.is_empty() {
+                    continue;
+                }
+                for (kind, token) in &tokens {
+                    insert.execute(params![profile_id, content_type.as_str(), id, kind.as_str(), token])?;
+                }
+                indexed += 1;
+            }
+        }
+    }
+
+    tx.commit()?;
+    Ok(indexed)
+}
+
+/// Items sharing the most tokens with `id`, best first
+///
+/// Rebuilds the profile's token sets first if there are none yet, e.g. right
+/// after upgrading. Items above the parental age limit are left out, and none
+/// are returned while the profile's content is hidden because it expired.
+pub fn similar(
+    conn: &Connection,
+    profile_id: &str,
+    content_type: SimilarContentType,
+    id: i64,
+    limit: usize,
+) -> Result<Vec<SimilarItem>> {
+    if expiry::profile_hidden(profile_id) {
+        return Ok(Vec::new());
+    }
+    let indexed: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM xtream_similarity_tokens WHERE profile_id = ?1)",
+        [profile_id],
+        |row| row.get(0),
+    )?;
+    if !indexed {
+        rebuild(conn, profile_id)?;
+    }
+
+    let (table, id_column, icon_column) = content_type.columns();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT item.{id_column}, item.name, item.{icon_column}, item.year,
+                SUM(CASE other.kind
+                    WHEN 'director' THEN 3.0
+                    WHEN 'genre' THEN 2.0
+                    WHEN 'cast' THEN 1.5
+                    ELSE 0.5
+                END) AS score
+         FROM xtream_similarity_tokens AS source
+         JOIN xtream_similarity_tokens AS other
+           ON other.profile_id = source.profile_id
+          AND other.content_type = source.content_type
+          AND other.kind = source.kind
+          AND other.token = source.token
+          AND other.content_id != source.content_id
+         JOIN {table} AS item
+           ON item.profile_id = other.profile_id AND item.{id_column} = other.content_id
+         WHERE source.profile_id = ?1 AND source.content_type = ?2 AND source.content_id = ?3
+           AND (?4 IS NULL OR (item.age_rating IS NOT NULL AND item.age_rating <= ?4))
+         GROUP BY other.content_id
+         ORDER BY score DESC, item.name
+         LIMIT ?5"
+    ))?;
+    let items = stmt
+        .query_map(
+            params![
+                profile_id,
+                content_type.as_str(),
+                id,
+                age_rating::parental_age_limit(),
+                limit as i64
+            ],
+            |row| {
+                Ok(SimilarItem {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    icon: row.get(2)?,
+                    year: row.get(3)?,
+                    score: row.get(4)?,
+                })
+            },
+        )?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('p1', 'p1', 'http://test.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add_movie(conn: &Connection, id: i64, name: &str, genre: &str, director: &str, plot: &str) {
+        conn.execute(
+            "INSERT INTO xtream_movies (profile_id, stream_id, name, genre, director, plot)
+             VALUES ('p1', ?1, ?2, ?3, ?4, ?5)",
+            params![id, name, genre, director, plot],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_plot_keywords_skip_common_words() {
+        assert_eq!(
+            plot_keywords(Some("A crew travels through a wormhole, and the crew must survive it in 2067.")),
+            vec!["crew", "travels", "wormhole", "survive"]
+        );
+    }
+
+    #[test]
+    fn test_similar_movies_rank_by_shared_tokens() {
+        let conn = create_test_db();
+        add_movie(&conn, 1, "Interstellar", "Sci-Fi, Drama", "Christopher Nolan", "Astronauts travel through a wormhole");
+        add_movie(&conn, 2, "Inception", "Sci-Fi / Thriller", "Christopher Nolan", "A thief enters dreams");
+        add_movie(&conn, 3, "Contact", "Sci-Fi, Drama", "Robert Zemeckis", "A signal from space");
+        add_movie(&conn, 4, "Event Horizon", "Horror", "Paul Anderson", "Astronauts find a lost ship");
+        add_movie(&conn, 5, "Notting Hill", "Romance", "Roger Michell", "A bookshop owner meets a star");
+
+        let items = similar(&conn, "p1", SimilarContentType::Movie, 1, 10).unwrap();
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        // Director and genre (5.0) beat two genres (4.0) beat a plot word (0.5)
+        assert_eq!(names, vec!["Inception", "Contact", "Event Horizon"]);
+        assert_eq!(items[0].score, 5.0);
+
+        assert!(similar(&conn, "p1", SimilarContentType::Series, 1, 10).unwrap().is_empty());
+        assert_eq!(similar(&conn, "p1", SimilarContentType::Movie, 1, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_expired_profile_has_no_similar_items() {
+        let conn = create_test_db();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('expired-similar', 'expired-similar', 'http://test.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+        for (id, name) in [(1, "Interstellar"), (2, "Inception")] {
+            conn.execute(
+                "INSERT INTO xtream_movies (profile_id, stream_id, name, genre, director)
+                 VALUES ('expired-similar', ?1, ?2, 'Sci-Fi', 'Christopher Nolan')",
+                params![id, name],
+            )
+            .unwrap();
+        }
+        assert_eq!(similar(&conn, "expired-similar", SimilarContentType::Movie, 1, 10).unwrap().len(), 1);
+
+        let _guard = expiry::expire_for_test(expiry::ExpirySource::Profile, "expired-similar");
+        assert!(similar(&conn, "expired-similar", SimilarContentType::Movie, 1, 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,240 @@
+// Precomputed name order for jumbo categories
+//
+// Sorting a category of tens of thousands of movies by name on every page is
+// the slowest part of browsing a large catalog. With the setting on, the rows
+// of each large category get a `name_ordinal` after every sync, and paginated
+// name-sorted queries read them in index order instead of sorting. Saving a row
+// clears its ordinal, so a category whose rows changed since the last rebuild
+// falls back to sorting until the next one.
+use crate::error::Result;
+use crate::state::DbState;
+use rusqlite::{params, Connection};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::State;
+
+/// Categories with fewer items sort quickly enough on their own
+pub const MIN_CATEGORY_ITEMS: i64 = 5000;
+
+/// Mirrors the `presorted_categories` setting
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// A content table with a `name_ordinal` column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortedTable {
+    Movies,
+    Series,
+}
+
+impl SortedTable {
+    pub const ALL: [SortedTable; 2] = [SortedTable::Movies, SortedTable::Series];
+
+    fn table(self) -> &'static str {
+        match self {
+            SortedTable::Movies => "xtream_movies",
+            SortedTable::Series => "xtream_series",
+        }
+    }
+
+    fn id_column(self) -> &'static str {
+        match self {
+            SortedTable::Movies => "stream_id",
+            SortedTable::Series => "series_id",
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn load_settings(conn: &Connection) -> Result<bool> {
+    let enabled: bool = conn.query_row(
+        "SELECT presorted_categories FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    ENABLED.store(enabled, Ordering::Relaxed);
+    Ok(enabled)
+}
+
+/// Recompute the name ordinals of a profile's large categories
+///
+/// Returns the number of rows given an ordinal.
+pub fn rebuild(conn: &Connection, profile_id: &str) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut ordered = 0;
+
+    for sorted in SortedTable::ALL {
+        let table = sorted.table();
+        tx.execute(
+            &format!(
+                "UPDATE {} SET name_ordinal = NULL WHERE profile_id = ?1 AND name_ordinal IS NOT NULL",
+                table
+            ),
+            [profile_id],
+        )?;
+        ordered += tx.execute(
+            &format!(
+                "UPDATE {table} SET name_ordinal = ranked.ordinal
+                 FROM (
+                     SELECT id, ROW_NUMBER() OVER (
+                         PARTITION BY category_id ORDER BY name COLLATE NOCASE, {id_column}
+                     ) AS ordinal
+                     FROM {table}
+                     WHERE profile_id = ?1 AND category_id IN (
+                         SELECT category_id FROM {table}
+                         WHERE profile_id = ?1 AND category_id IS NOT NULL
+                         GROUP BY category_id
+                         HAVING COUNT(*) >= ?2
+                     )
+                 ) AS ranked
+                 WHERE {table}.id = ranked.id",
+                table = table,
+                id_column = sorted.id_column()
+            ),
+            params![profile_id, MIN_CATEGORY_ITEMS],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(ordered)
+}
+
+/// Remove every name ordinal, for all profiles
+pub fn clear(conn: &Connection) -> Result<()> {
+    for sorted in SortedTable::ALL {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET name_ordinal = NULL WHERE name_ordinal IS NOT NULL",
+                sorted.table()
+            ),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Whether every row of a category has a name ordinal
+pub fn is_presorted(conn: &Connection, sorted: SortedTable, profile_id: &str, category_id: &str) -> Result<bool> {
+    let table = sorted.table();
+    let presorted = conn.query_row(
+        &format!(
+            "SELECT EXISTS (SELECT 1 FROM {table}
+                            WHERE profile_id = ?1 AND category_id = ?2 AND name_ordinal IS NOT NULL)
+                AND NOT EXISTS (SELECT 1 FROM {table}
+                                WHERE profile_id = ?1 AND category_id = ?2 AND name_ordinal IS NULL)",
+            table = table
+        ),
+        params![profile_id, category_id],
+        |row| row.get(0),
+    )?;
+    Ok(presorted)
+}
+
+/// ORDER BY expression for a name sort within `category_id`
+///
+/// Uses the ordinals while the setting is on and the category is presorted.
+pub fn name_order(
+    conn: &Connection,
+    sorted: SortedTable,
+    profile_id: &str,
+    category_id: Option<&str>,
+) -> Result<&'static str> {
+    match category_id {
+        Some(category_id) if is_enabled() && is_presorted(conn, sorted, profile_id, category_id)? => {
+            Ok("name_ordinal")
+        }
+        _ => Ok("name COLLATE NOCASE"),
+    }
+}
+
+#[tauri::command]
+pub fn get_presorted_categories(state: State<DbState>) -> std::result::Result<bool, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    load_settings(&db).map_err(|e| e.to_string())
+}
+
+/// Turn precomputed name order for large categories on or off
+///
+/// Turning it on orders every cached profile straight away; after that the
+/// order is refreshed at the end of each sync.
+#[tauri::command]
+pub fn set_presorted_categories(state: State<DbState>, enabled: bool) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET presorted_categories = ?1 WHERE id = 1",
+        params![enabled],
+    )
+    .map_err(|e| e.to_string())?;
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    if !enabled {
+        return clear(&db).map_err(|e| e.to_string());
+    }
+
+    let profile_ids: Vec<String> = db
+        .prepare("SELECT DISTINCT profile_id FROM xtream_movies UNION SELECT DISTINCT profile_id FROM xtream_series")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()
+        })
+        .map_err(|e| e.to_string())?;
+    for profile_id in profile_ids {
+        rebuild(&db, &profile_id).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebuild_orders_large_categories() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_movies (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id TEXT NOT NULL,
+                stream_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                category_id TEXT,
+                name_ordinal INTEGER
+            );
+            CREATE TABLE xtream_series (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id TEXT NOT NULL,
+                series_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                category_id TEXT,
+                name_ordinal INTEGER
+            );
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 5000)
+            INSERT INTO xtream_movies (profile_id, stream_id, name, category_id)
+            SELECT 'p1', n, printf('Movie %05d', 5001 - n), 'big' FROM seq;
+            INSERT INTO xtream_movies (profile_id, stream_id, name, category_id) VALUES ('p1', 9001, 'small', 'small');",
+        )
+        .unwrap();
+
+        assert_eq!(rebuild(&conn, "p1").unwrap(), 5000);
+        let first: i64 = conn
+            .query_row(
+                "SELECT stream_id FROM xtream_movies WHERE profile_id = 'p1' AND category_id = 'big' ORDER BY name_ordinal LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(first, 5000);
+
+        assert!(is_presorted(&conn, SortedTable::Movies, "p1", "big").unwrap());
+        assert!(!is_presorted(&conn, SortedTable::Movies, "p1", "small").unwrap());
+
+        // A newly saved row has no ordinal, so the category sorts normally again
+        conn.execute(
+            "INSERT INTO xtream_movies (profile_id, stream_id, name, category_id) VALUES ('p1', 9002, 'new', 'big')",
+            [],
+        )
+        .unwrap();
+        assert!(!is_presorted(&conn, SortedTable::Movies, "p1", "big").unwrap());
+    }
+}
@@ -1,5 +1,9 @@
 // Sync scheduler module for managing content synchronization
+use crate::content_cache::delta::{self, DeltaItem};
+use crate::content_cache::throttle::{self, ProviderThrottle, SyncLimits};
 use crate::error::{Result, XTauriError};
+use crate::profiling::profile_span;
+use crate::xtream::rate_limit::{self, RequestLimiter};
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -8,7 +12,7 @@ use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
 /// Synchronization status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, specta::Type)]
 #[serde(rename_all = "lowercase")]
 pub enum SyncStatus {
     Pending,
@@ -44,7 +48,7 @@ impl SyncStatus {
 }
 
 /// Synchronization progress information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SyncProgress {
     pub status: SyncStatus,
     pub progress: u8, // 0-100
@@ -69,13 +73,81 @@ impl Default for SyncProgress {
     }
 }
 
+/// A profile's sync progress, as sent in the sync progress and completion events
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ProfileSyncProgress {
+    pub profile_id: String,
+    #[serde(flatten)]
+    pub progress: SyncProgress,
+}
+
+/// Number of movies parsed and saved per batch during streaming VOD sync
+pub const MOVIE_BATCH_SIZE: usize = 2000;
+
+/// Category fields some providers use to advertise how many items a category holds
+const ADVERTISED_COUNT_FIELDS: [&str; 5] = ["count", "total", "item_count", "items_count", "stream_count"];
+
+/// Above this share of changed categories, one full VOD list beats fetching them one by one
+const MAX_CHANGED_CATEGORY_SHARE: f64 = 0.25;
+
+/// A response body spooled to a temporary file (still encoded), removed on drop
+struct SpooledResponse {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+    encoding: Option<String>,
+    len: u64,
+}
+
+impl SpooledResponse {
+    fn create(encoding: Option<String>) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("xtauri-sync-{}.json", uuid::Uuid::new_v4()));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        
+        Ok(Self { path, file, encoding, len: 0 })
+    }
+}
+
+impl Drop for SpooledResponse {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Number of sync runs kept per profile in the sync history
+pub const MAX_SYNC_HISTORY: usize = 200;
+
+/// A recorded sync run, with item deltas relative to the cache before the run
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct SyncHistoryEntry {
+    pub id: i64,
+    pub profile_id: String,
+    pub full_sync: bool,
+    pub status: SyncStatus,
+    pub started_at: String,
+    pub duration_ms: i64,
+    pub channels_count: usize,
+    pub movies_count: usize,
+    pub series_count: usize,
+    pub channels_delta: i64,
+    pub movies_delta: i64,
+    pub series_delta: i64,
+    pub errors: Vec<String>,
+}
+
 /// Synchronization settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SyncSettings {
     pub auto_sync_enabled: bool,
     pub sync_interval_hours: u32,
     pub wifi_only: bool,
     pub notify_on_complete: bool,
+    /// Only refresh favorited channels, their EPG and favorited series
+    #[serde(default)]
+    pub favorites_only: bool,
 }
 
 impl Default for SyncSettings {
@@ -85,6 +157,7 @@ impl Default for SyncSettings {
             sync_interval_hours: 24,
             wifi_only: true,
             notify_on_complete: false,
+            favorites_only: false,
         }
     }
 }
@@ -135,6 +208,10 @@ pub struct RetryConfig {
     pub initial_delay_ms: u64,
     pub max_delay_ms: u64,
     pub backoff_multiplier: f64,
+    /// Paces requests to the provider; `None` sends them as they come
+    pub throttle: Option<Arc<ProviderThrottle>>,
+    /// Keeps requests within the profile's request limits, shared with its other requests
+    pub limiter: Option<Arc<RequestLimiter>>,
 }
 
 impl Default for RetryConfig {
@@ -144,6 +221,8 @@ impl Default for RetryConfig {
             initial_delay_ms: 1000,
             max_delay_ms: 30000,
             backoff_multiplier: 2.0,
+            throttle: None,
+            limiter: None,
         }
     }
 }
@@ -235,6 +314,92 @@ impl SyncScheduler {
         Ok(())
     }
     
+    /// Record a finished sync run and prune runs beyond `MAX_SYNC_HISTORY`
+    pub fn record_sync_run(&self, entry: &SyncHistoryEntry) -> Result<i64> {
+        let conn = self.db.lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        
+        let errors = serde_json::to_string(&entry.errors)
+            .map_err(|e| XTauriError::internal(format!("Failed to serialize sync errors: {}", e)))?;
+        
+        conn.execute(
+            "INSERT INTO xtream_sync_history 
+                (profile_id, full_sync, status, started_at, duration_ms,
+                 channels_count, movies_count, series_count,
+                 channels_delta, movies_delta, series_delta, errors)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            rusqlite::params![
+                entry.profile_id,
+                entry.full_sync,
+                entry.status.to_db_string(),
+                entry.started_at,
+                entry.duration_ms,
+                entry.channels_count as i64,
+                entry.movies_count as i64,
+                entry.series_count as i64,
+                entry.channels_delta,
+                entry.movies_delta,
+                entry.series_delta,
+                errors,
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        
+        conn.execute(
+            "DELETE FROM xtream_sync_history 
+             WHERE profile_id = ?1 AND id NOT IN (
+                 SELECT id FROM xtream_sync_history WHERE profile_id = ?1
+                 ORDER BY started_at DESC, id DESC LIMIT ?2
+             )",
+            rusqlite::params![entry.profile_id, MAX_SYNC_HISTORY as i64],
+        )?;
+        
+        Ok(id)
+    }
+    
+    /// Get the most recent sync runs for a profile, newest first
+    pub fn get_sync_history(&self, profile_id: &str, limit: usize) -> Result<Vec<SyncHistoryEntry>> {
+        let conn = self.db.lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, full_sync, status, started_at, duration_ms,
+                    channels_count, movies_count, series_count,
+                    channels_delta, movies_delta, series_delta, errors
+             FROM xtream_sync_history
+             WHERE profile_id = ?1
+             ORDER BY started_at DESC, id DESC
+             LIMIT ?2",
+        )?;
+        
+        let entries = stmt
+            .query_map(rusqlite::params![profile_id, limit as i64], |row| {
+                let status: String = row.get(3)?;
+                let errors: Option<String> = row.get(12)?;
+                
+                Ok(SyncHistoryEntry {
+                    id: row.get(0)?,
+                    profile_id: row.get(1)?,
+                    full_sync: row.get(2)?,
+                    status: SyncStatus::from_db_string(&status),
+                    started_at: row.get(4)?,
+                    duration_ms: row.get(5)?,
+                    channels_count: row.get::<_, i64>(6)? as usize,
+                    movies_count: row.get::<_, i64>(7)? as usize,
+                    series_count: row.get::<_, i64>(8)? as usize,
+                    channels_delta: row.get(9)?,
+                    movies_delta: row.get(10)?,
+                    series_delta: row.get(11)?,
+                    errors: errors
+                        .and_then(|e| serde_json::from_str(&e).ok())
+                        .unwrap_or_default(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        
+        Ok(entries)
+    }
+    
     /// Update last sync timestamp for a specific content type
     pub fn update_last_sync_timestamp(&self, profile_id: &str, content_type: &str) -> Result<()> {
         let conn = self.db.lock()
@@ -260,13 +425,64 @@ impl SyncScheduler {
         Ok(())
     }
     
+    /// Retry settings for a profile's sync, paced by its request limits and the ones learned for its provider
+    pub fn retry_config_for(&self, profile_id: &str) -> RetryConfig {
+        let paced = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))
+            .and_then(|conn| {
+                Ok((ProviderThrottle::load(&conn, profile_id)?, rate_limit::limiter_for(&conn, profile_id)?))
+            });
+        match paced {
+            Ok((throttle, limiter)) => RetryConfig {
+                throttle: Some(throttle),
+                limiter: Some(limiter),
+                ..RetryConfig::default()
+            },
+            Err(e) => {
+                eprintln!("[WARN] Failed to load sync limits for profile {}: {}", profile_id, e);
+                RetryConfig::default()
+            }
+        }
+    }
+    
+    /// Save the request limits a sync ended with
+    pub fn save_sync_limits(&self, retry_config: &RetryConfig) {
+        let Some(throttle) = &retry_config.throttle else {
+            return;
+        };
+        let saved = self
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))
+            .and_then(|conn| throttle.save(&conn));
+        if let Err(e) = saved {
+            eprintln!("[WARN] Failed to save sync limits: {}", e);
+        }
+    }
+    
+    /// Request limits learned for a profile's provider
+    pub fn get_sync_limits(&self, profile_id: &str) -> Result<SyncLimits> {
+        let conn = self.db.lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        throttle::load_limits(&conn, profile_id)
+    }
+    
+    /// Forget the limits learned for a profile, so its next sync runs unthrottled
+    pub fn reset_sync_limits(&self, profile_id: &str) -> Result<()> {
+        let conn = self.db.lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        throttle::reset_limits(&conn, profile_id)
+    }
+    
     /// Get sync settings for a profile
     pub fn get_sync_settings(&self, profile_id: &str) -> Result<SyncSettings> {
         let conn = self.db.lock()
             .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
         
         let result = conn.query_row(
-            "SELECT auto_sync_enabled, sync_interval_hours, wifi_only, notify_on_complete
+            "SELECT auto_sync_enabled, sync_interval_hours, wifi_only, notify_on_complete, favorites_only
              FROM xtream_sync_settings
              WHERE profile_id = ?1",
             [profile_id],
@@ -276,6 +492,7 @@ impl SyncScheduler {
                     sync_interval_hours: row.get::<_, i32>(1)? as u32,
                     wifi_only: row.get(2)?,
                     notify_on_complete: row.get(3)?,
+                    favorites_only: row.get(4)?,
                 })
             },
         );
@@ -313,13 +530,15 @@ impl SyncScheduler {
                  sync_interval_hours = ?2,
                  wifi_only = ?3,
                  notify_on_complete = ?4,
+                 favorites_only = ?5,
                  updated_at = CURRENT_TIMESTAMP
-             WHERE profile_id = ?5",
+             WHERE profile_id = ?6",
             rusqlite::params![
                 settings.auto_sync_enabled,
                 settings.sync_interval_hours as i32,
                 settings.wifi_only,
                 settings.notify_on_complete,
+                settings.favorites_only,
                 profile_id,
             ],
         )?;
@@ -493,12 +712,7 @@ impl SyncScheduler {
         retry_config: &RetryConfig,
         cancel_token: &CancellationToken,
     ) -> Result<serde_json::Value> {
-        let action = match content_type {
-            "channels" => "get_live_streams",
-            "movies" => "get_vod_streams",
-            "series" => "get_series",
-            _ => return Err(XTauriError::internal(format!("Invalid content type: {}", content_type))),
-        };
+        let action = Self::content_list_action(content_type)?;
         
         let mut url = format!(
             "{}/player_api.php?username={}&password={}&action={}",
@@ -512,6 +726,77 @@ impl SyncScheduler {
         Self::fetch_with_retry(client, &url, retry_config, cancel_token).await
     }
     
+    /// API action that lists all items of a content type
+    fn content_list_action(content_type: &str) -> Result<&'static str> {
+        match content_type {
+            "channels" => Ok("get_live_streams"),
+            "movies" => Ok("get_vod_streams"),
+            "series" => Ok("get_series"),
+            _ => Err(XTauriError::internal(format!("Invalid content type: {}", content_type))),
+        }
+    }
+    
+    /// Count the items of a content type per category without keeping them
+    /// 
+    /// The list is spooled and streamed like a movie sync, so previewing a large
+    /// catalog neither holds it in memory nor writes anything to the cache.
+    /// 
+    /// # Returns
+    /// Item counts keyed by category ID (empty for items without one) and the
+    /// number of bytes downloaded
+    pub async fn count_content_by_category(
+        client: &reqwest::Client,
+        base_url: &str,
+        username: &str,
+        password: &str,
+        content_type: &str,
+        retry_config: &RetryConfig,
+        cancel_token: &CancellationToken,
+    ) -> Result<(HashMap<String, usize>, u64)> {
+        let url = format!(
+            "{}/player_api.php?username={}&password={}&action={}",
+            base_url, username, password, Self::content_list_action(content_type)?
+        );
+        
+        let spooled = Self::with_retry(retry_config, cancel_token, || {
+            Self::try_fetch_to_file(client, &url)
+        }).await?;
+        
+        // Parsing a large list takes seconds, so it stays off the async workers
+        let cancel_token = cancel_token.clone();
+        tokio::task::spawn_blocking(move || {
+            let decoder = crate::transfer::decoding_reader(
+                spooled.encoding.as_deref(),
+                std::io::BufReader::new(&spooled.file),
+            )?;
+            
+            let mut counts = HashMap::new();
+            crate::content_cache::json_stream::for_each_array_batch(
+                std::io::BufReader::new(decoder),
+                MOVIE_BATCH_SIZE,
+                |items| {
+                    if cancel_token.is_cancelled() {
+                        return Err(XTauriError::internal("Preview cancelled by user".to_string()));
+                    }
+                    
+                    for item in &items {
+                        let category_id = match item.get("category_id") {
+                            Some(serde_json::Value::String(id)) => id.clone(),
+                            Some(serde_json::Value::Number(id)) => id.to_string(),
+                            _ => String::new(),
+                        };
+                        *counts.entry(category_id).or_insert(0) += 1;
+                    }
+                    Ok(())
+                },
+            )?;
+            
+            Ok((counts, spooled.len))
+        })
+        .await
+        .map_err(|e| XTauriError::internal(format!("Preview task failed: {}", e)))?
+    }
+    
     /// Fetch series details from Xtream API with retry logic
     /// 
     /// # Arguments
@@ -552,12 +837,25 @@ impl SyncScheduler {
     /// 
     /// # Returns
     /// JSON value containing the response
-    async fn fetch_with_retry(
+    pub(crate) async fn fetch_with_retry(
         client: &reqwest::Client,
         url: &str,
         retry_config: &RetryConfig,
         cancel_token: &CancellationToken,
     ) -> Result<serde_json::Value> {
+        Self::with_retry(retry_config, cancel_token, || Self::try_fetch(client, url)).await
+    }
+    
+    /// Run a fetch attempt with retry logic and exponential backoff
+    async fn with_retry<T, F, Fut>(
+        retry_config: &RetryConfig,
+        cancel_token: &CancellationToken,
+        mut attempt_fetch: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
         let mut last_error = None;
         let mut delay_ms = retry_config.initial_delay_ms;
         
@@ -567,7 +865,24 @@ impl SyncScheduler {
                 return Err(XTauriError::internal("Sync cancelled by user".to_string()));
             }
             
-            match Self::try_fetch(client, url).await {
+            let outcome = {
+                // Held for the attempt only, not through the backoff below
+                let _request_permit = match &retry_config.limiter {
+                    Some(limiter) => Some(limiter.acquire().await),
+                    None => None,
+                };
+                match &retry_config.throttle {
+                    Some(throttle) => {
+                        let _permit = throttle.acquire().await;
+                        let outcome = attempt_fetch().await;
+                        throttle.record(&outcome);
+                        outcome
+                    }
+                    None => attempt_fetch().await,
+                }
+            };
+            
+            match outcome {
                 Ok(data) => {
                     #[cfg(debug_assertions)]
                     if attempt > 0 {
@@ -631,8 +946,63 @@ impl SyncScheduler {
         client: &reqwest::Client,
         url: &str,
     ) -> Result<serde_json::Value> {
+        if let Some(data) = crate::xtream::recording::replay(url) {
+            return data;
+        }
+        
+        let response = Self::send_request(client, url).await?;
+        
+        crate::transfer::read_json(response, crate::transfer::SOURCE_CONTENT_SYNC).await
+    }
+    
+    /// Single fetch attempt that spools the response body to a temporary file
+    ///
+    /// Used for responses that can be too large to buffer in memory.
+    async fn try_fetch_to_file(
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<SpooledResponse> {
+        use std::io::{Seek, Write};
+        
+        if let Some(data) = crate::xtream::recording::replay(url) {
+            let mut spooled = SpooledResponse::create(None)?;
+            serde_json::to_writer(&mut spooled.file, &data?)
+                .map_err(|e| XTauriError::internal(format!("Failed to spool recorded response: {}", e)))?;
+            spooled.len = spooled.file.stream_position()?;
+            spooled.file.rewind()?;
+            return Ok(spooled);
+        }
+        
+        let mut response = Self::send_request(client, url).await?;
+        let mut spooled = SpooledResponse::create(crate::transfer::response_encoding(&response))?;
+        let watched = crate::watchdog::watch(crate::transfer::SOURCE_CONTENT_SYNC, response.url());
+        
+        while let Some(chunk) = watched.guard(response.chunk()).await?.map_err(|e| {
+            if e.is_timeout() {
+                XTauriError::timeout("API request")
+            } else {
+                XTauriError::network(e)
+            }
+        })? {
+            watched.progress(chunk.len());
+            spooled.file.write_all(&chunk)?;
+            spooled.len += chunk.len() as u64;
+        }
+        
+        spooled.file.flush()?;
+        spooled.file.rewind()?;
+        
+        Ok(spooled)
+    }
+    
+    /// Send a GET request and map HTTP error statuses
+    async fn send_request(
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<reqwest::Response> {
         let response = client
-            .get(url)
+            .get(crate::xtream::api_language::apply(url))
+            .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
             .send()
             .await
             .map_err(|e| {
@@ -640,7 +1010,7 @@ impl SyncScheduler {
                     XTauriError::timeout("API request")
                 } else {
                     // Network error is a tuple variant wrapping reqwest::Error
-                    XTauriError::Network(e)
+                    XTauriError::network(e)
                 }
             })?;
         
@@ -661,12 +1031,7 @@ impl SyncScheduler {
             });
         }
         
-        let data: serde_json::Value = response
-            .json()
-            .await
-            .map_err(|e| XTauriError::internal(format!("Invalid response format: {}", e)))?;
-        
-        Ok(data)
+        Ok(response)
     }
     
     /// Calculate progress percentage based on completed steps
@@ -727,13 +1092,15 @@ impl SyncScheduler {
     ) -> Result<SyncProgress> {
         use std::time::Duration;
         
+        crate::post_processors::begin_report(&crate::post_processors::xtream_source(profile_id));
+        
         // Create HTTP client with timeout
-        let client = reqwest::Client::builder()
+        let client = crate::connection::client_builder()
             .timeout(Duration::from_secs(30))
             .build()
             .map_err(|e| XTauriError::internal(format!("Failed to create HTTP client: {}", e)))?;
         
-        let retry_config = RetryConfig::default();
+        let retry_config = self.retry_config_for(profile_id);
         
         // Initialize progress
         let mut progress = SyncProgress {
@@ -816,6 +1183,7 @@ impl SyncScheduler {
         self.update_sync_status(profile_id, &progress)?;
         let _ = progress_tx.send(progress.clone()).await;
         
+        let mut advertised_movie_counts = None;
         match Self::sync_categories(
             &client,
             base_url,
@@ -827,7 +1195,8 @@ impl SyncScheduler {
             &retry_config,
             cancel_token,
         ).await {
-            Ok(_) => {
+            Ok(advertised_counts) => {
+                advertised_movie_counts = advertised_counts;
                 current_step += 1;
                 progress.progress = Self::calculate_progress(current_step, total_steps, 0.0);
             }
@@ -843,17 +1212,31 @@ impl SyncScheduler {
         self.update_sync_status(profile_id, &progress)?;
         let _ = progress_tx.send(progress.clone()).await;
         
-        match Self::sync_content(
-            &client,
-            base_url,
-            username,
-            password,
-            "movies",
-            profile_id,
-            content_cache,
-            &retry_config,
-            cancel_token,
-        ).await {
+        let movies_result = match &advertised_movie_counts {
+            Some(advertised_counts) => Self::sync_movies_conditional(
+                &client,
+                base_url,
+                username,
+                password,
+                profile_id,
+                content_cache,
+                advertised_counts,
+                &retry_config,
+                cancel_token,
+            ).await,
+            None => Self::sync_content(
+                &client,
+                base_url,
+                username,
+                password,
+                "movies",
+                profile_id,
+                content_cache,
+                &retry_config,
+                cancel_token,
+            ).await,
+        };
+        match movies_result {
             Ok(count) => {
                 progress.movies_synced = count;
                 current_step += 1;
@@ -935,6 +1318,8 @@ impl SyncScheduler {
             SyncStatus::Failed
         };
         
+        self.save_sync_limits(&retry_config);
+        
         // Update final status
         self.update_sync_status(profile_id, &progress)?;
         let _ = progress_tx.send(progress.clone()).await;
@@ -943,6 +1328,9 @@ impl SyncScheduler {
     }
     
     /// Sync categories for a specific content type
+    ///
+    /// Returns the item counts the provider advertises per category, if it
+    /// advertises one for every category.
     async fn sync_categories(
         client: &reqwest::Client,
         base_url: &str,
@@ -953,7 +1341,7 @@ impl SyncScheduler {
         content_cache: &crate::content_cache::ContentCache,
         retry_config: &RetryConfig,
         cancel_token: &CancellationToken,
-    ) -> Result<usize> {
+    ) -> Result<Option<HashMap<String, usize>>> {
         // Fetch categories from API
         let categories_data = Self::fetch_categories_with_retry(
             client,
@@ -967,6 +1355,7 @@ impl SyncScheduler {
         
         // Parse categories
         let categories = Self::parse_categories(&categories_data)?;
+        let advertised_counts = Self::parse_category_counts(&categories_data);
         
         // Save to cache
         let content_type_enum = match content_type {
@@ -976,9 +1365,9 @@ impl SyncScheduler {
             _ => return Err(XTauriError::internal(format!("Invalid content type: {}", content_type))),
         };
         
-        let count = content_cache.save_categories(profile_id, content_type_enum, categories)?;
+        content_cache.save_categories(profile_id, content_type_enum, categories)?;
         
-        Ok(count)
+        Ok(advertised_counts)
     }
     
     /// Sync content for a specific content type
@@ -993,6 +1382,22 @@ impl SyncScheduler {
         retry_config: &RetryConfig,
         cancel_token: &CancellationToken,
     ) -> Result<usize> {
+        // VOD lists can be hundreds of megabytes, so they are never parsed as a whole
+        if content_type == "movies" {
+            let url = format!(
+                "{}/player_api.php?username={}&password={}&action=get_vod_streams",
+                base_url, username, password
+            );
+            return Self::sync_movies_streaming(
+                client,
+                &url,
+                profile_id,
+                content_cache,
+                retry_config,
+                cancel_token,
+            ).await;
+        }
+        
         // Fetch content from API
         let content_data = Self::fetch_content_with_retry(
             client,
@@ -1025,8 +1430,203 @@ impl SyncScheduler {
         Ok(count)
     }
     
+    /// Sync movies, re-fetching only the categories whose counts changed
+    ///
+    /// The provider's advertised count for each category is compared with the
+    /// cached count. Categories that match are left alone and the others are
+    /// fetched one by one. The whole VOD list is fetched instead when nothing
+    /// is cached yet or too many categories changed.
+    async fn sync_movies_conditional(
+        client: &reqwest::Client,
+        base_url: &str,
+        username: &str,
+        password: &str,
+        profile_id: &str,
+        content_cache: &crate::content_cache::ContentCache,
+        advertised_counts: &HashMap<String, usize>,
+        retry_config: &RetryConfig,
+        cancel_token: &CancellationToken,
+    ) -> Result<usize> {
+        let local_counts = content_cache.get_movie_counts_by_category(profile_id)?;
+        let Some(changed) = Self::plan_movie_refresh(advertised_counts, &local_counts) else {
+            return Self::sync_content(
+                client,
+                base_url,
+                username,
+                password,
+                "movies",
+                profile_id,
+                content_cache,
+                retry_config,
+                cancel_token,
+            ).await;
+        };
+        
+        println!(
+            "[INFO] Movie counts unchanged in {} of {} categories; refreshing {}",
+            advertised_counts.len().saturating_sub(changed.len()),
+            advertised_counts.len(),
+            changed.len()
+        );
+        
+        // Categories are fetched side by side, as many at once as the provider's
+        // learned limits allow, and stored one at a time as they arrive
+        let mut pending = changed.into_iter();
+        let mut fetches = tokio::task::JoinSet::new();
+        let mut saved = 0;
+        loop {
+            let window = retry_config.throttle.as_ref().map_or(1, |throttle| throttle.max_concurrency());
+            while fetches.len() < window {
+                let Some(category_id) = pending.next() else {
+                    break;
+                };
+                if cancel_token.is_cancelled() {
+                    return Err(XTauriError::internal("Sync cancelled by user".to_string()));
+                }
+                
+                let client = client.clone();
+                let (base_url, username, password) = (base_url.to_string(), username.to_string(), password.to_string());
+                let retry_config = retry_config.clone();
+                let cancel_token = cancel_token.clone();
+                fetches.spawn(async move {
+                    let content_data = Self::fetch_content_with_retry(
+                        &client,
+                        &base_url,
+                        &username,
+                        &password,
+                        "movies",
+                        Some(&category_id),
+                        &retry_config,
+                        &cancel_token,
+                    ).await;
+                    (category_id, content_data)
+                });
+            }
+            
+            let Some(fetched) = fetches.join_next().await else {
+                break;
+            };
+            let (category_id, content_data) = fetched
+                .map_err(|e| XTauriError::internal(format!("Movie category fetch failed: {}", e)))?;
+            let movies = Self::parse_movies(&content_data?)?;
+            saved += content_cache.replace_category_movies(profile_id, &category_id, movies)?;
+        }
+        
+        Ok(saved)
+    }
+    
+    /// Item counts a category list advertises, keyed by category ID
+    ///
+    /// `None` unless every category has a count; a partial list can't show
+    /// which categories are unchanged.
+    pub fn parse_category_counts(data: &serde_json::Value) -> Option<HashMap<String, usize>> {
+        let array = data.as_array().filter(|array| !array.is_empty())?;
+        
+        array
+            .iter()
+            .map(|item| {
+                let category_id = match item.get("category_id")? {
+                    serde_json::Value::String(id) => id.clone(),
+                    serde_json::Value::Number(id) => id.to_string(),
+                    _ => return None,
+                };
+                let count = ADVERTISED_COUNT_FIELDS.iter().find_map(|field| match item.get(field)? {
+                    serde_json::Value::Number(count) => count.as_u64(),
+                    serde_json::Value::String(count) => count.trim().parse().ok(),
+                    _ => None,
+                })?;
+                Some((category_id, count as usize))
+            })
+            .collect()
+    }
+    
+    /// Categories to re-fetch, or `None` if the whole list should be fetched
+    ///
+    /// A category is re-fetched when its advertised count differs from the
+    /// cached count, including cached categories the provider no longer lists.
+    pub fn plan_movie_refresh(
+        advertised_counts: &HashMap<String, usize>,
+        local_counts: &HashMap<String, usize>,
+    ) -> Option<Vec<String>> {
+        if local_counts.is_empty() {
+            return None;
+        }
+        
+        let mut changed: Vec<String> = advertised_counts
+            .iter()
+            .filter(|(id, count)| local_counts.get(*id).copied().unwrap_or(0) != **count)
+            .map(|(id, _)| id.clone())
+            .chain(
+                local_counts
+                    .keys()
+                    .filter(|id| !advertised_counts.contains_key(*id))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        
+        let categories = advertised_counts.len().max(1) as f64;
+        (changed.len() as f64 / categories <= MAX_CHANGED_CATEGORY_SHARE).then_some(changed)
+    }
+    
+    /// Sync movies by streaming the VOD list into the cache in batches
+    ///
+    /// The response is spooled to disk and parsed incrementally, so memory use is
+    /// bounded by `MOVIE_BATCH_SIZE` rather than the size of the provider's catalog.
+    async fn sync_movies_streaming(
+        client: &reqwest::Client,
+        url: &str,
+        profile_id: &str,
+        content_cache: &crate::content_cache::ContentCache,
+        retry_config: &RetryConfig,
+        cancel_token: &CancellationToken,
+    ) -> Result<usize> {
+        let spooled = Self::with_retry(retry_config, cancel_token, || {
+            Self::try_fetch_to_file(client, url)
+        }).await?;
+        
+        // Parsing and saving block for as long as the catalog takes, so they run
+        // on a blocking thread rather than an async worker
+        let content_cache = content_cache.clone();
+        let profile_id = profile_id.to_string();
+        let cancel_token = cancel_token.clone();
+        tokio::task::spawn_blocking(move || {
+            let decoder = crate::transfer::decoding_reader(
+                spooled.encoding.as_deref(),
+                std::io::BufReader::new(&spooled.file),
+            )?;
+            let (reader, decoded_bytes) = crate::transfer::CountingReader::new(decoder);
+            
+            let mut saved = 0;
+            crate::content_cache::json_stream::for_each_array_batch(
+                std::io::BufReader::new(reader),
+                MOVIE_BATCH_SIZE,
+                |items| {
+                    if cancel_token.is_cancelled() {
+                        return Err(XTauriError::internal("Sync cancelled by user".to_string()));
+                    }
+                    
+                    let movies = items.iter().map(Self::parse_movie_item).collect();
+                    saved += content_cache.save_movies(&profile_id, movies)?;
+                    Ok(())
+                },
+            )?;
+            
+            crate::transfer::record_transfer(
+                crate::transfer::SOURCE_CONTENT_SYNC,
+                spooled.len,
+                decoded_bytes.load(std::sync::atomic::Ordering::Relaxed),
+            );
+            
+            Ok(saved)
+        })
+        .await
+        .map_err(|e| XTauriError::internal(format!("Movie sync task failed: {}", e)))?
+    }
+    
     /// Parse categories from JSON response
     pub fn parse_categories(data: &serde_json::Value) -> Result<Vec<crate::content_cache::XtreamCategory>> {
+        let _span = profile_span!("parser", "parse_categories");
         let array = data.as_array()
             .ok_or_else(|| XTauriError::internal("Categories response is not an array".to_string()))?;
         
@@ -1058,6 +1658,7 @@ impl SyncScheduler {
     
     /// Parse channels from JSON response
     pub fn parse_channels(data: &serde_json::Value) -> Result<Vec<crate::content_cache::XtreamChannel>> {
+        let _span = profile_span!("parser", "parse_channels");
         let array = data.as_array()
             .ok_or_else(|| XTauriError::internal("Channels response is not an array".to_string()))?;
         
@@ -1087,6 +1688,10 @@ impl SyncScheduler {
                 tv_archive: item.get("tv_archive").and_then(|v| v.as_i64()),
                 direct_source: item.get("direct_source").and_then(|v| v.as_str()).map(String::from),
                 tv_archive_duration: item.get("tv_archive_duration").and_then(|v| v.as_i64()),
+                url_overridden: false,
+                country: None,
+                country_flag: None,
+                reliability: None,
             });
         }
         
@@ -1095,51 +1700,53 @@ impl SyncScheduler {
     
     /// Parse movies from JSON response
     pub fn parse_movies(data: &serde_json::Value) -> Result<Vec<crate::content_cache::XtreamMovie>> {
+        let _span = profile_span!("parser", "parse_movies");
         let array = data.as_array()
             .ok_or_else(|| XTauriError::internal("Movies response is not an array".to_string()))?;
         
-        let mut movies = Vec::new();
-        
-        for item in array {
-            let stream_id = item.get("stream_id")
-                .and_then(|v| v.as_i64())
-                .unwrap_or(0);
-            
-            let name = item.get("name")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown")
-                .to_string();
-            
-            movies.push(crate::content_cache::XtreamMovie {
-                stream_id,
-                num: item.get("num").and_then(|v| v.as_i64()),
-                name,
-                title: item.get("title").and_then(|v| v.as_str()).map(String::from),
-                year: item.get("year").and_then(|v| v.as_str()).map(String::from),
-                stream_type: item.get("stream_type").and_then(|v| v.as_str()).map(String::from),
-                stream_icon: item.get("stream_icon").and_then(|v| v.as_str()).map(String::from),
-                rating: item.get("rating").and_then(|v| v.as_f64()),
-                rating_5based: item.get("rating_5based").and_then(|v| v.as_f64()),
-                genre: item.get("genre").and_then(|v| v.as_str()).map(String::from),
-                added: item.get("added").and_then(|v| v.as_str()).map(String::from),
-                episode_run_time: item.get("episode_run_time").and_then(|v| v.as_i64()),
-                category_id: item.get("category_id").and_then(|v| v.as_str().or_else(|| v.as_i64().map(|i| Box::leak(i.to_string().into_boxed_str()) as &str))).map(String::from),
-                container_extension: item.get("container_extension").and_then(|v| v.as_str()).map(String::from),
-                custom_sid: item.get("custom_sid").and_then(|v| v.as_str()).map(String::from),
-                direct_source: item.get("direct_source").and_then(|v| v.as_str()).map(String::from),
-                release_date: item.get("release_date").and_then(|v| v.as_str()).map(String::from),
-                cast: item.get("cast").and_then(|v| v.as_str()).map(String::from),
-                director: item.get("director").and_then(|v| v.as_str()).map(String::from),
-                plot: item.get("plot").and_then(|v| v.as_str()).map(String::from),
-                youtube_trailer: item.get("youtube_trailer").and_then(|v| v.as_str()).map(String::from),
-            });
+        Ok(array.iter().map(Self::parse_movie_item).collect())
+    }
+    
+    /// Parse a single movie entry from a VOD list response
+    pub fn parse_movie_item(item: &serde_json::Value) -> crate::content_cache::XtreamMovie {
+        let stream_id = item.get("stream_id")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+        
+        let name = item.get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown")
+            .to_string();
+        
+        crate::content_cache::XtreamMovie {
+            stream_id,
+            num: item.get("num").and_then(|v| v.as_i64()),
+            name,
+            title: item.get("title").and_then(|v| v.as_str()).map(String::from),
+            year: item.get("year").and_then(|v| v.as_str()).map(String::from),
+            stream_type: item.get("stream_type").and_then(|v| v.as_str()).map(String::from),
+            stream_icon: item.get("stream_icon").and_then(|v| v.as_str()).map(String::from),
+            rating: item.get("rating").and_then(|v| v.as_f64()),
+            rating_5based: item.get("rating_5based").and_then(|v| v.as_f64()),
+            genre: item.get("genre").and_then(|v| v.as_str()).map(String::from),
+            added: item.get("added").and_then(|v| v.as_str()).map(String::from),
+            episode_run_time: item.get("episode_run_time").and_then(|v| v.as_i64()),
+            category_id: item.get("category_id").and_then(|v| v.as_str().map(String::from).or_else(|| v.as_i64().map(|i| i.to_string()))),
+            container_extension: item.get("container_extension").and_then(|v| v.as_str()).map(String::from),
+            custom_sid: item.get("custom_sid").and_then(|v| v.as_str()).map(String::from),
+            direct_source: item.get("direct_source").and_then(|v| v.as_str()).map(String::from),
+            release_date: item.get("release_date").and_then(|v| v.as_str()).map(String::from),
+            cast: item.get("cast").and_then(|v| v.as_str()).map(String::from),
+            director: item.get("director").and_then(|v| v.as_str()).map(String::from),
+            plot: item.get("plot").and_then(|v| v.as_str()).map(String::from),
+            youtube_trailer: item.get("youtube_trailer").and_then(|v| v.as_str()).map(String::from),
+            age_rating: crate::content_cache::age_rating::extract_age_rating(item),
         }
-        
-        Ok(movies)
     }
     
     /// Parse series from JSON response
     pub fn parse_series(data: &serde_json::Value) -> Result<Vec<crate::content_cache::XtreamSeries>> {
+        let _span = profile_span!("parser", "parse_series");
         let array = data.as_array()
             .ok_or_else(|| XTauriError::internal("Series response is not an array".to_string()))?;
         
@@ -1172,6 +1779,7 @@ impl SyncScheduler {
                 rating_5based: item.get("rating_5based").and_then(|v| v.as_f64()),
                 episode_run_time: item.get("episode_run_time").and_then(|v| v.as_str()).map(String::from),
                 category_id: item.get("category_id").and_then(|v| v.as_str().or_else(|| v.as_i64().map(|i| Box::leak(i.to_string().into_boxed_str()) as &str))).map(String::from),
+                age_rating: crate::content_cache::age_rating::extract_age_rating(item),
             });
         }
         
@@ -1210,13 +1818,15 @@ impl SyncScheduler {
     ) -> Result<SyncProgress> {
         use std::time::Duration;
         
+        crate::post_processors::begin_report(&crate::post_processors::xtream_source(profile_id));
+        
         // Create HTTP client with timeout
-        let client = reqwest::Client::builder()
+        let client = crate::connection::client_builder()
             .timeout(Duration::from_secs(30))
             .build()
             .map_err(|e| XTauriError::internal(format!("Failed to create HTTP client: {}", e)))?;
         
-        let retry_config = RetryConfig::default();
+        let retry_config = self.retry_config_for(profile_id);
         
         // Initialize progress
         let mut progress = SyncProgress {
@@ -1233,9 +1843,6 @@ impl SyncScheduler {
         self.update_sync_status(profile_id, &progress)?;
         let _ = progress_tx.send(progress.clone()).await;
         
-        // Get last sync timestamps
-        let last_sync_times = self.get_last_sync_timestamps(profile_id)?;
-        
         // Total steps: 3 (one for each content type)
         let total_steps = 3;
         let mut current_step = 0;
@@ -1254,7 +1861,6 @@ impl SyncScheduler {
             "channels",
             profile_id,
             content_cache,
-            last_sync_times.channels,
             &retry_config,
             cancel_token,
         ).await {
@@ -1284,7 +1890,6 @@ impl SyncScheduler {
             "movies",
             profile_id,
             content_cache,
-            last_sync_times.movies,
             &retry_config,
             cancel_token,
         ).await {
@@ -1314,7 +1919,6 @@ impl SyncScheduler {
             "series",
             profile_id,
             content_cache,
-            last_sync_times.series,
             &retry_config,
             cancel_token,
         ).await {
@@ -1343,6 +1947,8 @@ impl SyncScheduler {
             SyncStatus::Failed
         };
         
+        self.save_sync_limits(&retry_config);
+        
         // Update final status
         self.update_sync_status(profile_id, &progress)?;
         let _ = progress_tx.send(progress.clone()).await;
@@ -1385,10 +1991,13 @@ impl SyncScheduler {
     
     /// Sync content incrementally for a specific content type
     /// 
-    /// This compares the server content with local cache and:
+    /// This compares the server content with the cached fingerprints and:
     /// 1. Adds new items
-    /// 2. Updates modified items (based on timestamps)
+    /// 2. Replaces items whose provider data changed
     /// 3. Removes deleted items
+    /// 
+    /// Unchanged items are left alone and the search index is updated row by
+    /// row instead of being rebuilt.
     async fn sync_content_incremental(
         client: &reqwest::Client,
         base_url: &str,
@@ -1397,7 +2006,6 @@ impl SyncScheduler {
         content_type: &str,
         profile_id: &str,
         content_cache: &crate::content_cache::ContentCache,
-        last_sync: Option<String>,
         retry_config: &RetryConfig,
         cancel_token: &CancellationToken,
     ) -> Result<usize> {
@@ -1413,74 +2021,36 @@ impl SyncScheduler {
             cancel_token,
         ).await?;
         
-        // Get current content IDs from cache
-        let cached_ids = content_cache.get_content_ids(profile_id, content_type)?;
-        
-        // Parse server content and compare with cache
-        let (new_items, updated_items, server_ids) = match content_type {
-            "channels" => {
-                let channels = Self::parse_channels(&content_data)?;
-                Self::compare_channels(&channels, &cached_ids, last_sync.as_deref())
-            }
-            "movies" => {
-                let movies = Self::parse_movies(&content_data)?;
-                Self::compare_movies(&movies, &cached_ids, last_sync.as_deref())
-            }
-            "series" => {
-                let series = Self::parse_series(&content_data)?;
-                Self::compare_series(&series, &cached_ids, last_sync.as_deref())
-            }
-            _ => return Err(XTauriError::internal(format!("Invalid content type: {}", content_type))),
-        };
-        
-        // Find deleted items (in cache but not on server)
-        let deleted_ids: Vec<i64> = cached_ids
-            .into_iter()
-            .filter(|id| !server_ids.contains(id))
-            .collect();
-        
-        // Apply changes
-        let mut total_changes = 0;
-        
-        // Add/update new and modified items
-        if !new_items.is_empty() || !updated_items.is_empty() {
-            let items_to_save = [new_items, updated_items].concat();
-            
-            let count = match content_type {
-                "channels" => {
-                    let channels: Vec<crate::content_cache::XtreamChannel> = 
-                        serde_json::from_value(serde_json::Value::Array(items_to_save))
-                            .map_err(|e| XTauriError::internal(format!("Failed to deserialize channels: {}", e)))?;
-                    content_cache.save_channels(profile_id, channels)?
-                }
-                "movies" => {
-                    let movies: Vec<crate::content_cache::XtreamMovie> = 
-                        serde_json::from_value(serde_json::Value::Array(items_to_save))
-                            .map_err(|e| XTauriError::internal(format!("Failed to deserialize movies: {}", e)))?;
-                    content_cache.save_movies(profile_id, movies)?
-                }
-                "series" => {
-                    let series: Vec<crate::content_cache::XtreamSeries> = 
-                        serde_json::from_value(serde_json::Value::Array(items_to_save))
-                            .map_err(|e| XTauriError::internal(format!("Failed to deserialize series: {}", e)))?;
-                    content_cache.save_series(profile_id, series)?
-                }
-                _ => 0,
-            };
-            
-            total_changes += count;
+        match content_type {
+            "channels" => Self::apply_delta(content_cache, profile_id, Self::parse_channels(&content_data)?),
+            "movies" => Self::apply_delta(content_cache, profile_id, Self::parse_movies(&content_data)?),
+            "series" => Self::apply_delta(content_cache, profile_id, Self::parse_series(&content_data)?),
+            _ => Err(XTauriError::internal(format!("Invalid content type: {}", content_type))),
         }
+    }
+    
+    /// Diff the provider's items against the cache and write only the changes
+    fn apply_delta<T: DeltaItem>(
+        content_cache: &crate::content_cache::ContentCache,
+        profile_id: &str,
+        server_items: Vec<T>,
+    ) -> Result<usize> {
+        let cached = content_cache.get_content_fingerprints(profile_id, T::CONTENT_TYPE)?;
+        let delta = delta::plan(server_items, &cached);
+        
+        #[cfg(debug_assertions)]
+        println!(
+            "[DEBUG] {} delta: {} new or changed, {} removed, {} unchanged",
+            T::CONTENT_TYPE,
+            delta.upserts.len(),
+            delta.removed.len(),
+            delta.unchanged
+        );
         
-        // Remove deleted items
-        if !deleted_ids.is_empty() {
-            let deleted_count = content_cache.delete_content_by_ids(profile_id, content_type, &deleted_ids)?;
-            total_changes += deleted_count;
-            
-            #[cfg(debug_assertions)]
-            println!("[DEBUG] Removed {} deleted {} items", deleted_count, content_type);
+        if delta.is_empty() {
+            return Ok(0);
         }
-        
-        Ok(total_changes)
+        content_cache.apply_content_delta(profile_id, delta)
     }
     
     /// Compare channels and identify new/updated items
@@ -1666,6 +2236,25 @@ mod tests {
             [],
         ).unwrap();
         
+        conn.execute(
+            "CREATE TABLE xtream_sync_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id TEXT NOT NULL,
+                full_sync BOOLEAN NOT NULL,
+                status TEXT NOT NULL,
+                started_at TIMESTAMP NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                channels_count INTEGER DEFAULT 0,
+                movies_count INTEGER DEFAULT 0,
+                series_count INTEGER DEFAULT 0,
+                channels_delta INTEGER DEFAULT 0,
+                movies_delta INTEGER DEFAULT 0,
+                series_delta INTEGER DEFAULT 0,
+                errors TEXT
+            )",
+            [],
+        ).unwrap();
+        
         conn.execute(
             "CREATE TABLE xtream_sync_settings (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -1674,6 +2263,7 @@ mod tests {
                 sync_interval_hours INTEGER DEFAULT 24,
                 wifi_only BOOLEAN DEFAULT 1,
                 notify_on_complete BOOLEAN DEFAULT 0,
+                favorites_only BOOLEAN NOT NULL DEFAULT 0,
                 created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                 updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                 FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
@@ -1830,6 +2420,7 @@ mod tests {
             sync_interval_hours: 12,
             wifi_only: false,
             notify_on_complete: true,
+            favorites_only: true,
         };
         
         // Update settings
@@ -1841,6 +2432,7 @@ mod tests {
         assert_eq!(retrieved.sync_interval_hours, 12);
         assert_eq!(retrieved.wifi_only, false);
         assert_eq!(retrieved.notify_on_complete, true);
+        assert!(retrieved.favorites_only);
     }
     
     #[test]
@@ -1854,6 +2446,7 @@ mod tests {
             sync_interval_hours: 3, // Too low
             wifi_only: true,
             notify_on_complete: false,
+            favorites_only: false,
         };
         
         // Should fail validation
@@ -1928,6 +2521,7 @@ mod tests {
             sync_interval_hours: 24,
             wifi_only: true,
             notify_on_complete: false,
+            favorites_only: false,
         };
         scheduler.update_sync_settings("test-profile", &settings).unwrap();
         
@@ -1947,6 +2541,7 @@ mod tests {
             sync_interval_hours: 24,
             wifi_only: false,
             notify_on_complete: false,
+            favorites_only: false,
         };
         scheduler.update_sync_settings("test-profile", &settings).unwrap();
         
@@ -1997,6 +2592,8 @@ mod tests {
             initial_delay_ms: 100,
             max_delay_ms: 1000,
             backoff_multiplier: 2.0,
+            throttle: None,
+            limiter: None,
         };
         
         let cancel_token = CancellationToken::new();
@@ -2031,6 +2628,8 @@ mod tests {
             initial_delay_ms: 100,
             max_delay_ms: 1000,
             backoff_multiplier: 2.0,
+            throttle: None,
+            limiter: None,
         };
         
         let cancel_token = CancellationToken::new();
@@ -2154,6 +2753,51 @@ mod tests {
         assert_eq!(series[0].genre, Some("Drama".to_string()));
     }
     
+    #[test]
+    fn test_parse_category_counts() {
+        let data = serde_json::json!([
+            {"category_id": "1", "category_name": "Action", "count": 120},
+            {"category_id": 2, "category_name": "Drama", "total": "45"}
+        ]);
+        let counts = SyncScheduler::parse_category_counts(&data).unwrap();
+        assert_eq!(counts.get("1"), Some(&120));
+        assert_eq!(counts.get("2"), Some(&45));
+        
+        // Without a count for every category there is nothing to compare
+        let data = serde_json::json!([
+            {"category_id": "1", "category_name": "Action", "count": 120},
+            {"category_id": "2", "category_name": "Drama"}
+        ]);
+        assert!(SyncScheduler::parse_category_counts(&data).is_none());
+    }
+    
+    #[test]
+    fn test_plan_movie_refresh() {
+        let counts = |pairs: &[(&str, usize)]| -> HashMap<String, usize> {
+            pairs.iter().map(|(id, count)| (id.to_string(), *count)).collect()
+        };
+        let all = [("1", 10), ("2", 20), ("3", 30), ("4", 40), ("5", 50), ("6", 60), ("7", 70), ("8", 80)];
+        let advertised = counts(&all);
+        
+        // Nothing cached: fetch everything
+        assert_eq!(SyncScheduler::plan_movie_refresh(&advertised, &HashMap::new()), None);
+        
+        assert_eq!(SyncScheduler::plan_movie_refresh(&advertised, &counts(&all)), Some(vec![]));
+        
+        // One changed category and one the provider dropped
+        let mut local = counts(&all);
+        local.insert("2".to_string(), 19);
+        local.insert("9".to_string(), 3);
+        assert_eq!(
+            SyncScheduler::plan_movie_refresh(&advertised, &local),
+            Some(vec!["2".to_string(), "9".to_string()])
+        );
+        
+        // Too many changes: one full list is cheaper
+        local.insert("3".to_string(), 31);
+        assert_eq!(SyncScheduler::plan_movie_refresh(&advertised, &local), None);
+    }
+    
     #[test]
     fn test_parse_empty_arrays() {
         use serde_json::json;
@@ -2177,4 +2821,76 @@ mod tests {
         assert!(SyncScheduler::parse_movies(&invalid_data).is_err());
         assert!(SyncScheduler::parse_series(&invalid_data).is_err());
     }
+    
+    #[test]
+    fn test_record_and_get_sync_history() {
+        let conn = create_test_db();
+        let db = Arc::new(Mutex::new(conn));
+        let scheduler = SyncScheduler::new(db);
+        
+        let mut entry = SyncHistoryEntry {
+            id: 0,
+            profile_id: "test-profile".to_string(),
+            full_sync: true,
+            status: SyncStatus::Completed,
+            started_at: "2024-01-01T00:00:00+00:00".to_string(),
+            duration_ms: 1500,
+            channels_count: 100,
+            movies_count: 50,
+            series_count: 10,
+            channels_delta: 100,
+            movies_delta: 50,
+            series_delta: 10,
+            errors: Vec::new(),
+        };
+        scheduler.record_sync_run(&entry).unwrap();
+        
+        entry.full_sync = false;
+        entry.status = SyncStatus::Partial;
+        entry.started_at = "2024-01-02T00:00:00+00:00".to_string();
+        entry.channels_count = 90;
+        entry.channels_delta = -10;
+        entry.errors = vec!["movies: timeout".to_string()];
+        scheduler.record_sync_run(&entry).unwrap();
+        
+        let history = scheduler.get_sync_history("test-profile", 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].status, SyncStatus::Partial);
+        assert_eq!(history[0].channels_delta, -10);
+        assert_eq!(history[0].errors, vec!["movies: timeout".to_string()]);
+        assert!(history[1].full_sync);
+        
+        let latest = scheduler.get_sync_history("test-profile", 1).unwrap();
+        assert_eq!(latest.len(), 1);
+        assert!(scheduler.get_sync_history("other", 10).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_sync_history_is_pruned() {
+        let conn = create_test_db();
+        let db = Arc::new(Mutex::new(conn));
+        let scheduler = SyncScheduler::new(db);
+        
+        for i in 0..(MAX_SYNC_HISTORY + 5) {
+            let entry = SyncHistoryEntry {
+                id: 0,
+                profile_id: "test-profile".to_string(),
+                full_sync: false,
+                status: SyncStatus::Completed,
+                started_at: format!("2024-01-01T00:00:{:02}.{:03}+00:00", i / 1000, i % 1000),
+                duration_ms: 10,
+                channels_count: 0,
+                movies_count: 0,
+                series_count: 0,
+                channels_delta: 0,
+                movies_delta: 0,
+                series_delta: 0,
+                errors: Vec::new(),
+            };
+            scheduler.record_sync_run(&entry).unwrap();
+        }
+        
+        let history = scheduler.get_sync_history("test-profile", usize::MAX / 2).unwrap();
+        assert_eq!(history.len(), MAX_SYNC_HISTORY);
+    }
 }
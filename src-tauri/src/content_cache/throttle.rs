@@ -0,0 +1,343 @@
+// Adaptive request throttling for content sync
+//
+// Each sync runs its provider requests through a `ProviderThrottle`, which caps
+// how many are in flight and spaces out when they start. Server errors and
+// timeouts halve the concurrency and double the delay; a run of successful
+// requests slowly relaxes them again. The limits a profile ends a sync with are
+// saved in `xtream_sync_limits`, so the next sync starts from what the provider
+// was last known to handle.
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Requests in flight for a provider that hasn't had trouble
+pub const DEFAULT_MAX_CONCURRENCY: u32 = 4;
+
+/// Longest delay between request starts backoff can reach
+const MAX_REQUEST_DELAY_MS: u64 = 5_000;
+
+/// Delay the first backoff starts from
+const MIN_BACKOFF_DELAY_MS: u64 = 250;
+
+/// Successful requests in a row before the limits relax one step
+const RECOVERY_SUCCESSES: u32 = 20;
+
+/// Failures this soon after a backoff are part of the same episode
+///
+/// Requests already in flight when a provider starts failing would otherwise
+/// each halve the limits again.
+const BACKOFF_COOLDOWN: Duration = Duration::from_secs(2);
+
+/// Request limits learned for a profile's provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct SyncLimits {
+    pub profile_id: String,
+    pub max_concurrency: u32,
+    /// Minimum time between the starts of two requests
+    pub request_delay_ms: u64,
+    /// How often the provider's errors have reduced the limits
+    pub throttle_count: u32,
+    pub last_throttled_at: Option<i64>,
+}
+
+impl SyncLimits {
+    pub fn unthrottled(profile_id: &str) -> Self {
+        Self {
+            profile_id: profile_id.to_string(),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            request_delay_ms: 0,
+            throttle_count: 0,
+            last_throttled_at: None,
+        }
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.max_concurrency < DEFAULT_MAX_CONCURRENCY || self.request_delay_ms > 0
+    }
+
+    fn back_off(&mut self, now: i64) {
+        self.max_concurrency = (self.max_concurrency / 2).max(1);
+        self.request_delay_ms = (self.request_delay_ms * 2).clamp(MIN_BACKOFF_DELAY_MS, MAX_REQUEST_DELAY_MS);
+        self.throttle_count += 1;
+        self.last_throttled_at = Some(now);
+    }
+
+    fn relax(&mut self) {
+        self.request_delay_ms = if self.request_delay_ms < MIN_BACKOFF_DELAY_MS {
+            0
+        } else {
+            self.request_delay_ms * 3 / 4
+        };
+        self.max_concurrency = (self.max_concurrency + 1).min(DEFAULT_MAX_CONCURRENCY);
+    }
+}
+
+/// Whether an error says the provider is struggling, as opposed to refusing the request
+pub fn is_provider_strain(error: &XTauriError) -> bool {
+    match error {
+        XTauriError::XtreamApiError { status, .. } => *status >= 500,
+        XTauriError::Timeout { .. } => true,
+        XTauriError::Network(e) => e.is_timeout(),
+        _ => false,
+    }
+}
+
+pub fn load_limits(conn: &Connection, profile_id: &str) -> Result<SyncLimits> {
+    let limits = conn
+        .query_row(
+            "SELECT max_concurrency, request_delay_ms, throttle_count, last_throttled_at
+             FROM xtream_sync_limits WHERE profile_id = ?1",
+            [profile_id],
+            |row| {
+                Ok(SyncLimits {
+                    profile_id: profile_id.to_string(),
+                    max_concurrency: row.get::<_, u32>(0)?.max(1),
+                    request_delay_ms: row.get(1)?,
+                    throttle_count: row.get(2)?,
+                    last_throttled_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(limits.unwrap_or_else(|| SyncLimits::unthrottled(profile_id)))
+}
+
+pub fn save_limits(conn: &Connection, limits: &SyncLimits) -> Result<()> {
+    conn.execute(
+        "INSERT INTO xtream_sync_limits (profile_id, max_concurrency, request_delay_ms, throttle_count, last_throttled_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(profile_id) DO UPDATE SET
+            max_concurrency = excluded.max_concurrency,
+            request_delay_ms = excluded.request_delay_ms,
+            throttle_count = excluded.throttle_count,
+            last_throttled_at = excluded.last_throttled_at",
+        params![
+            limits.profile_id,
+            limits.max_concurrency,
+            limits.request_delay_ms,
+            limits.throttle_count,
+            limits.last_throttled_at,
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn reset_limits(conn: &Connection, profile_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM xtream_sync_limits WHERE profile_id = ?1", [profile_id])?;
+    Ok(())
+}
+
+#[derive(Debug)]
+struct ThrottleState {
+    limits: SyncLimits,
+    in_flight: u32,
+    next_start: Instant,
+    successes: u32,
+    last_backoff: Option<Instant>,
+}
+
+/// Paces one profile's requests during a sync
+#[derive(Debug)]
+pub struct ProviderThrottle {
+    state: Mutex<ThrottleState>,
+    released: Notify,
+}
+
+/// A request slot, given back when dropped
+pub struct ThrottlePermit<'a> {
+    throttle: &'a ProviderThrottle,
+}
+
+impl Drop for ThrottlePermit<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.throttle.state.lock() {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+        self.throttle.released.notify_waiters();
+    }
+}
+
+impl ProviderThrottle {
+    pub fn new(limits: SyncLimits) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(ThrottleState {
+                limits,
+                in_flight: 0,
+                next_start: Instant::now(),
+                successes: 0,
+                last_backoff: None,
+            }),
+            released: Notify::new(),
+        })
+    }
+
+    /// The throttle a profile's last sync left off with
+    pub fn load(conn: &Connection, profile_id: &str) -> Result<Arc<Self>> {
+        Ok(Self::new(load_limits(conn, profile_id)?))
+    }
+
+    pub fn limits(&self) -> Option<SyncLimits> {
+        self.state.lock().ok().map(|state| state.limits.clone())
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.limits().map_or(1, |limits| limits.max_concurrency as usize)
+    }
+
+    /// Wait for a free slot and this request's turn to start
+    pub async fn acquire(&self) -> ThrottlePermit<'_> {
+        loop {
+            let released = self.released.notified();
+            let wait = {
+                let Ok(mut state) = self.state.lock() else {
+                    return ThrottlePermit { throttle: self };
+                };
+                if state.in_flight < state.limits.max_concurrency {
+                    state.in_flight += 1;
+                    let now = Instant::now();
+                    let start = state.next_start.max(now);
+                    state.next_start = start + Duration::from_millis(state.limits.request_delay_ms);
+                    Some(start - now)
+                } else {
+                    None
+                }
+            };
+            if let Some(wait) = wait {
+                // Hold the slot while waiting so it's given back if this is cancelled
+                let permit = ThrottlePermit { throttle: self };
+                if !wait.is_zero() {
+                    tokio::time::sleep(wait).await;
+                }
+                return permit;
+            }
+            released.await;
+        }
+    }
+
+    /// Adjust the limits to how a request went
+    pub fn record<T>(&self, outcome: &Result<T>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        match outcome {
+            Err(e) if is_provider_strain(e) => {
+                state.successes = 0;
+                if state.last_backoff.is_some_and(|at| at.elapsed() < BACKOFF_COOLDOWN) {
+                    return;
+                }
+                state.limits.back_off(chrono::Utc::now().timestamp());
+                state.last_backoff = Some(Instant::now());
+                println!(
+                    "[INFO] Provider for profile {} is struggling; throttling sync to {} request(s) {}ms apart",
+                    state.limits.profile_id, state.limits.max_concurrency, state.limits.request_delay_ms
+                );
+            }
+            Err(_) => {}
+            Ok(_) => {
+                state.successes += 1;
+                if state.successes >= RECOVERY_SUCCESSES && state.limits.is_throttled() {
+                    state.successes = 0;
+                    state.limits.relax();
+                }
+            }
+        }
+    }
+
+    /// Persist the limits this sync ended with
+    pub fn save(&self, conn: &Connection) -> Result<()> {
+        match self.limits() {
+            Some(limits) => save_limits(conn, &limits),
+            None => Err(XTauriError::lock_acquisition("sync throttle")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('p1', 'p1', 'http://test.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_limits_back_off_on_server_errors_and_recover() {
+        let throttle = ProviderThrottle::new(SyncLimits::unthrottled("p1"));
+
+        // Client errors don't say anything about the provider's health
+        throttle.record::<()>(&Err(XTauriError::xtream_api_error(404, "missing".to_string())));
+        assert!(!throttle.limits().unwrap().is_throttled());
+
+        throttle.record::<()>(&Err(XTauriError::xtream_api_error(503, "busy".to_string())));
+        let limits = throttle.limits().unwrap();
+        assert_eq!((limits.max_concurrency, limits.request_delay_ms), (2, MIN_BACKOFF_DELAY_MS));
+        assert_eq!(limits.throttle_count, 1);
+
+        // Failures from requests already in flight count as the same backoff
+        throttle.record::<()>(&Err(XTauriError::timeout("API request")));
+        assert_eq!(throttle.limits().unwrap().throttle_count, 1);
+
+        for _ in 0..RECOVERY_SUCCESSES {
+            throttle.record(&Ok(()));
+        }
+        let limits = throttle.limits().unwrap();
+        assert_eq!((limits.max_concurrency, limits.request_delay_ms), (3, 187));
+    }
+
+    #[test]
+    fn test_limits_persist_per_profile() {
+        let conn = setup_db();
+        assert_eq!(load_limits(&conn, "p1").unwrap(), SyncLimits::unthrottled("p1"));
+
+        let mut limits = SyncLimits::unthrottled("p1");
+        limits.back_off(1_700_000_000);
+        limits.back_off(1_700_000_100);
+        save_limits(&conn, &limits).unwrap();
+
+        let loaded = load_limits(&conn, "p1").unwrap();
+        assert_eq!((loaded.max_concurrency, loaded.request_delay_ms), (1, 500));
+        assert_eq!(loaded.last_throttled_at, Some(1_700_000_100));
+        assert_eq!(load_limits(&conn, "p2").unwrap(), SyncLimits::unthrottled("p2"));
+
+        reset_limits(&conn, "p1").unwrap();
+        assert!(!load_limits(&conn, "p1").unwrap().is_throttled());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_caps_requests_in_flight() {
+        let mut limits = SyncLimits::unthrottled("p1");
+        limits.max_concurrency = 1;
+        limits.request_delay_ms = 20;
+        let throttle = ProviderThrottle::new(limits);
+
+        let started = Instant::now();
+        let first = throttle.acquire().await;
+        let second = tokio::time::timeout(Duration::from_millis(50), throttle.acquire()).await;
+        assert!(second.is_err());
+        drop(first);
+        let _second = throttle.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,157 @@
+// Cached icons for listings
+//
+// Listing and search commands can return the local file of each item's icon
+// when the image cache already holds it, looked up in one batch in the image
+// cache index. The UI renders those straight away and only asks
+// `fetch_images_batch` for the icons that come back as `None`.
+use crate::content_cache::epg::XtreamChannelListing;
+use crate::content_cache::{XtreamChannel, XtreamMovie, XtreamSeries};
+use crate::error::Result;
+use crate::images;
+use crate::pagination::Page;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A cached Xtream movie with the cached file of its icon
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct XtreamMovieListing {
+    #[serde(flatten)]
+    pub movie: XtreamMovie,
+    /// Cached file of `stream_icon`; `None` when it isn't cached or wasn't asked for
+    pub cached_icon: Option<String>,
+}
+
+/// A cached Xtream series with the cached file of its cover
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct XtreamSeriesListing {
+    #[serde(flatten)]
+    pub series: XtreamSeries,
+    /// Cached file of `cover`; `None` when it isn't cached or wasn't asked for
+    pub cached_icon: Option<String>,
+}
+
+/// Items with an icon URL the image cache may hold
+pub trait HasIcon {
+    fn icon_url(&self) -> Option<&str>;
+}
+
+impl HasIcon for XtreamChannel {
+    fn icon_url(&self) -> Option<&str> {
+        self.stream_icon.as_deref()
+    }
+}
+
+impl HasIcon for XtreamChannelListing {
+    fn icon_url(&self) -> Option<&str> {
+        self.channel.icon_url()
+    }
+}
+
+impl HasIcon for XtreamMovie {
+    fn icon_url(&self) -> Option<&str> {
+        self.stream_icon.as_deref()
+    }
+}
+
+impl HasIcon for XtreamSeries {
+    fn icon_url(&self) -> Option<&str> {
+        self.cover.as_deref()
+    }
+}
+
+/// Cached icons of `items` by URL; nothing is looked up without a connection
+fn cached_icons<T: HasIcon>(conn: Option<&Connection>, items: &[T]) -> Result<HashMap<String, String>> {
+    let Some(conn) = conn else {
+        return Ok(HashMap::new());
+    };
+    let urls: Vec<&str> = items
+        .iter()
+        .filter_map(|item| item.icon_url())
+        .filter(|url| !url.is_empty())
+        .collect();
+    if urls.is_empty() {
+        return Ok(HashMap::new());
+    }
+    images::cached_paths(conn, &urls)
+}
+
+fn lookup<T: HasIcon>(icons: &HashMap<String, String>, item: &T) -> Option<String> {
+    item.icon_url().and_then(|url| icons.get(url)).cloned()
+}
+
+/// Fill in `cached_icon` of channel listings; left `None` without a connection
+pub fn channel_listings(
+    conn: Option<&Connection>,
+    page: Page<XtreamChannelListing>,
+) -> Result<Page<XtreamChannelListing>> {
+    let icons = cached_icons(conn, &page.items)?;
+    Ok(page.map(|listing| XtreamChannelListing { cached_icon: lookup(&icons, &listing), ..listing }))
+}
+
+/// Movies with their cached icons; `cached_icon` is `None` throughout without a connection
+pub fn movie_listings(conn: Option<&Connection>, page: Page<XtreamMovie>) -> Result<Page<XtreamMovieListing>> {
+    let icons = cached_icons(conn, &page.items)?;
+    Ok(page.map(|movie| XtreamMovieListing { cached_icon: lookup(&icons, &movie), movie }))
+}
+
+/// Series with their cached covers; `cached_icon` is `None` throughout without a connection
+pub fn series_listings(conn: Option<&Connection>, page: Page<XtreamSeries>) -> Result<Page<XtreamSeriesListing>> {
+    let icons = cached_icons(conn, &page.items)?;
+    Ok(page.map(|series| XtreamSeriesListing { cached_icon: lookup(&icons, &series), series }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn movie(stream_id: i64, icon: Option<&str>) -> XtreamMovie {
+        XtreamMovie {
+            stream_id,
+            num: None,
+            name: format!("Movie {}", stream_id),
+            title: None,
+            year: None,
+            stream_type: None,
+            stream_icon: icon.map(str::to_string),
+            rating: None,
+            rating_5based: None,
+            genre: None,
+            added: None,
+            episode_run_time: None,
+            category_id: None,
+            container_extension: None,
+            custom_sid: None,
+            direct_source: None,
+            release_date: None,
+            cast: None,
+            director: None,
+            plot: None,
+            youtube_trailer: None,
+            age_rating: None,
+        }
+    }
+
+    #[test]
+    fn test_movie_listings_resolve_cached_icons_only() {
+        let conn = Connection::open_in_memory().unwrap();
+        images::create_index_table(&conn).unwrap();
+        images::record_cached(&conn, "http://img/1.jpg", Path::new("/cache/one")).unwrap();
+
+        let page = Page::new(
+            vec![movie(1, Some("http://img/1.jpg")), movie(2, Some("http://img/2.jpg")), movie(3, None)],
+            3,
+            None,
+            0,
+        );
+        let listed = movie_listings(Some(&conn), page.clone()).unwrap();
+        let icons: Vec<Option<&str>> = listed.items.iter().map(|item| item.cached_icon.as_deref()).collect();
+        assert_eq!(icons, vec![Some("/cache/one"), None, None]);
+        assert_eq!(listed.total, 3);
+
+        // Without a lookup every icon is left to the image cache
+        let unresolved = movie_listings(None, page).unwrap();
+        assert!(unresolved.items.iter().all(|item| item.cached_icon.is_none()));
+    }
+}
@@ -0,0 +1,227 @@
+// Watchlist
+//
+// Movies and series a profile means to watch later. Unlike favorites, which
+// keep a snapshot of the content, the watchlist only keeps ids and the date
+// they were added; listing it joins the ids with the cached catalog, so names
+// and artwork follow the provider. Entries whose content left the catalog stay
+// listed as unavailable until they are removed.
+use crate::content_id::{canonical_type, ContentId};
+use crate::error::{Result, XTauriError};
+use crate::expiry;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+/// A watchlist entry with the cached details of its movie or series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct WatchlistItem {
+    /// `movie:<stream id>` or `series:<series id>`
+    pub content: ContentId,
+    /// Unix timestamp
+    pub added_at: i64,
+    /// Whether the content is still in the cached catalog; the details are unset if not
+    pub available: bool,
+    pub name: Option<String>,
+    /// Movie poster or series cover
+    pub image: Option<String>,
+    pub year: Option<String>,
+    pub genre: Option<String>,
+    pub rating_5based: Option<f64>,
+    pub category_id: Option<String>,
+}
+
+const SELECT_ITEMS: &str = "SELECT w.content_type, w.content_id, w.added_at,
+        COALESCE(m.name, s.name), COALESCE(m.stream_icon, s.cover), COALESCE(m.year, s.year),
+        COALESCE(m.genre, s.genre), COALESCE(m.rating_5based, s.rating_5based), COALESCE(m.category_id, s.category_id)
+    FROM xtream_watchlist w
+    LEFT JOIN xtream_movies m
+        ON w.content_type = 'movie' AND m.profile_id = w.profile_id AND m.stream_id = w.content_id
+    LEFT JOIN xtream_series s
+        ON w.content_type = 'series' AND s.profile_id = w.profile_id AND s.series_id = w.content_id";
+
+fn item_from_row(row: &Row) -> rusqlite::Result<WatchlistItem> {
+    let content_type: String = row.get(0)?;
+    let content_id: i64 = row.get(1)?;
+    let content = ContentId::new(&content_type, &content_id.to_string()).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    let name: Option<String> = row.get(3)?;
+    Ok(WatchlistItem {
+        content,
+        added_at: row.get(2)?,
+        available: name.is_some(),
+        name,
+        image: row.get(4)?,
+        year: row.get(5)?,
+        genre: row.get(6)?,
+        rating_5based: row.get(7)?,
+        category_id: row.get(8)?,
+    })
+}
+
+/// Only movies and series go on the watchlist
+fn check_watchable(content: &ContentId) -> Result<()> {
+    match content {
+        ContentId::Movie(_) | ContentId::Series(_) => Ok(()),
+        _ => Err(XTauriError::internal(format!(
+            "Only movies and series can be on the watchlist, not {}",
+            content
+        ))),
+    }
+}
+
+/// Add a cached movie or series to the watchlist; adding it again keeps the first date
+pub fn add(conn: &Connection, profile_id: &str, content: &ContentId, now: i64) -> Result<WatchlistItem> {
+    check_watchable(content)?;
+    let (table, id_column) = content.catalog();
+    let cached = conn
+        .query_row(
+            &format!("SELECT 1 FROM {} WHERE profile_id = ?1 AND {} = ?2", table, id_column),
+            params![profile_id, content.id()],
+            |_| Ok(()),
+        )
+        .optional()?;
+    if cached.is_none() {
+        return Err(XTauriError::internal(format!("{} not found", content)));
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO xtream_watchlist (profile_id, content_type, content_id, added_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![profile_id, content.content_type(), content.id(), now],
+    )?;
+
+    let item = conn.query_row(
+        &format!(
+            "{} WHERE w.profile_id = ?1 AND w.content_type = ?2 AND w.content_id = ?3",
+            SELECT_ITEMS
+        ),
+        params![profile_id, content.content_type(), content.id()],
+        item_from_row,
+    )?;
+    Ok(item)
+}
+
+/// Remove an entry; returns whether it was on the watchlist
+pub fn remove(conn: &Connection, profile_id: &str, content: &ContentId) -> Result<bool> {
+    let removed = conn.execute(
+        "DELETE FROM xtream_watchlist WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3",
+        params![profile_id, content.content_type(), content.id()],
+    )?;
+    Ok(removed > 0)
+}
+
+/// A profile's watchlist, most recently added first, optionally of one content type
+///
+/// The type accepts any spelling `ContentId` does. Empty while the profile's
+/// content is hidden because it expired.
+pub fn items(conn: &Connection, profile_id: &str, content_type: Option<&str>) -> Result<Vec<WatchlistItem>> {
+    let content_type = content_type
+        .map(|kind| {
+            canonical_type(kind).ok_or_else(|| XTauriError::internal(format!("Invalid content type: {}", kind)))
+        })
+        .transpose()?;
+    if expiry::profile_hidden(profile_id) {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE w.profile_id = ?1 AND (?2 IS NULL OR w.content_type = ?2)
+         ORDER BY w.added_at DESC, w.rowid DESC",
+        SELECT_ITEMS
+    ))?;
+    let items = stmt
+        .query_map(params![profile_id, content_type], item_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY);
+             INSERT INTO xtream_profiles VALUES ('p1'), ('p2');",
+        )
+        .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO xtream_movies (profile_id, stream_id, name, stream_icon, year, rating_5based, category_id)
+                 VALUES ('p1', 1, 'Heat', 'http://img/heat.jpg', '1995', 4.5, 'crime'),
+                        ('p1', 2, 'Alien', 'http://img/alien.jpg', '1979', 4.0, 'scifi');
+             INSERT INTO xtream_series (profile_id, series_id, name, cover, year, category_id)
+                 VALUES ('p1', 1, 'The Wire', 'http://img/wire.jpg', '2002', 'drama');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_watchlist_orders_by_added_date_with_cached_details() {
+        let conn = setup();
+        let heat = add(&conn, "p1", &ContentId::Movie(1), 100).unwrap();
+        assert_eq!(heat.name.as_deref(), Some("Heat"));
+        assert_eq!(heat.image.as_deref(), Some("http://img/heat.jpg"));
+        assert_eq!(heat.rating_5based, Some(4.5));
+        assert!(heat.available);
+
+        // Ids are per content type, so movie 1 and series 1 are separate entries
+        let wire = add(&conn, "p1", &ContentId::Series(1), 200).unwrap();
+        assert_eq!(wire.name.as_deref(), Some("The Wire"));
+        add(&conn, "p1", &ContentId::Movie(2), 300).unwrap();
+        // Adding again keeps the first date
+        assert_eq!(
+            add(&conn, "p1", &ContentId::Movie(1), 400).unwrap().added_at,
+            100
+        );
+
+        let names =
+            |items: Vec<WatchlistItem>| -> Vec<String> { items.into_iter().filter_map(|item| item.name).collect() };
+        assert_eq!(
+            names(items(&conn, "p1", None).unwrap()),
+            vec!["Alien", "The Wire", "Heat"]
+        );
+        assert_eq!(
+            names(items(&conn, "p1", Some("movies")).unwrap()),
+            vec!["Alien", "Heat"]
+        );
+        assert!(items(&conn, "p2", None).unwrap().is_empty());
+
+        assert!(remove(&conn, "p1", &ContentId::Movie(2)).unwrap());
+        assert!(!remove(&conn, "p1", &ContentId::Movie(2)).unwrap());
+        assert_eq!(names(items(&conn, "p1", None).unwrap()), vec!["The Wire", "Heat"]);
+    }
+
+    #[test]
+    fn test_watchlist_keeps_content_that_left_the_catalog() {
+        let conn = setup();
+        assert!(add(&conn, "p1", &ContentId::Movie(99), 100).is_err());
+        assert!(add(&conn, "p1", &ContentId::Channel(1), 100).is_err());
+
+        add(&conn, "p1", &ContentId::Movie(1), 100).unwrap();
+        conn.execute("DELETE FROM xtream_movies WHERE stream_id = 1", [])
+            .unwrap();
+
+        let items = items(&conn, "p1", None).unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(!items[0].available);
+        assert_eq!(items[0].name, None);
+        assert_eq!(items[0].content, ContentId::Movie(1));
+    }
+
+    #[test]
+    fn test_expired_profile_lists_no_watchlist() {
+        let conn = setup();
+        conn.execute_batch(
+            "INSERT INTO xtream_profiles VALUES ('expired-watchlist');
+             INSERT INTO xtream_movies (profile_id, stream_id, name) VALUES ('expired-watchlist', 1, 'Heat');",
+        )
+        .unwrap();
+        add(&conn, "expired-watchlist", &ContentId::Movie(1), 100).unwrap();
+        assert_eq!(items(&conn, "expired-watchlist", None).unwrap().len(), 1);
+
+        let _guard = expiry::expire_for_test(expiry::ExpirySource::Profile, "expired-watchlist");
+        assert!(items(&conn, "expired-watchlist", None).unwrap().is_empty());
+    }
+}
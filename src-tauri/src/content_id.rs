@@ -0,0 +1,237 @@
+// Content identity
+//
+// Favorites, history, tags and playback positions all key content by a type
+// and an id, but the type has arrived as "channel", "Channel", "channels" or
+// "xtream-channel" depending on which view sent it, so the same movie could be
+// a favorite under one key and in history under another. `ContentId` is the
+// identity they share: it accepts every spelling, is stored as the canonical
+// type name and id, and serializes to a stable `<type>:<id>` string.
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Tables keyed by `(content_type, content_id)` that are rewritten to canonical types
+const KEYED_TABLES: &[&str] = &["xtream_favorites", "xtream_history", "xtream_content_tags"];
+
+/// A channel, movie, series or episode of a provider
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum ContentId {
+    Channel(i64),
+    Movie(i64),
+    Series(i64),
+    /// Episode ids are provider strings
+    Episode(String),
+}
+
+impl ContentId {
+    /// Every content type name, as stored
+    pub const TYPES: [&'static str; 4] = ["channel", "movie", "series", "episode"];
+
+    /// Identify content from a type in any spelling the app has used and its id
+    pub fn new(content_type: &str, content_id: &str) -> Result<Self> {
+        let canonical = canonical_type(content_type)
+            .ok_or_else(|| XTauriError::internal(format!("Invalid content type: {}", content_type)))?;
+        let id = content_id.trim();
+        let numeric = || {
+            id.parse::<i64>()
+                .map_err(|_| XTauriError::internal(format!("Invalid {} id: {}", canonical, content_id)))
+        };
+        Ok(match canonical {
+            "channel" => ContentId::Channel(numeric()?),
+            "movie" => ContentId::Movie(numeric()?),
+            "series" => ContentId::Series(numeric()?),
+            _ if id.is_empty() => return Err(XTauriError::internal("Episode id cannot be empty".to_string())),
+            _ => ContentId::Episode(id.to_string()),
+        })
+    }
+
+    /// Canonical type name, one of `TYPES`
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ContentId::Channel(_) => "channel",
+            ContentId::Movie(_) => "movie",
+            ContentId::Series(_) => "series",
+            ContentId::Episode(_) => "episode",
+        }
+    }
+
+    /// The id as stored in `content_id` columns
+    pub fn id(&self) -> String {
+        match self {
+            ContentId::Channel(id) | ContentId::Movie(id) | ContentId::Series(id) => id.to_string(),
+            ContentId::Episode(id) => id.clone(),
+        }
+    }
+
+    /// The content cache table listing this type and its id column
+    pub fn catalog(&self) -> (&'static str, &'static str) {
+        catalog_table(self.content_type()).expect("every content type is cached")
+    }
+}
+
+/// Canonical name of a content type, accepting plurals, capitals and the player's `xtream-` prefix
+pub fn canonical_type(content_type: &str) -> Option<&'static str> {
+    let lower = content_type.trim().to_ascii_lowercase();
+    match lower.strip_prefix("xtream-").unwrap_or(&lower) {
+        "channel" | "channels" | "live" => Some("channel"),
+        "movie" | "movies" | "vod" => Some("movie"),
+        "series" => Some("series"),
+        "episode" | "episodes" => Some("episode"),
+        _ => None,
+    }
+}
+
+/// The content cache table listing a content type, in any spelling, and its id column
+pub fn catalog_table(content_type: &str) -> Option<(&'static str, &'static str)> {
+    match canonical_type(content_type)? {
+        "channel" => Some(("xtream_channels", "stream_id")),
+        "movie" => Some(("xtream_movies", "stream_id")),
+        "series" => Some(("xtream_series", "series_id")),
+        _ => Some(("xtream_episodes", "episode_id")),
+    }
+}
+
+impl fmt::Display for ContentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.content_type(), self.id())
+    }
+}
+
+impl FromStr for ContentId {
+    type Err = XTauriError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (content_type, content_id) = s
+            .split_once(':')
+            .ok_or_else(|| XTauriError::internal(format!("Invalid content id: {}", s)))?;
+        Self::new(content_type, content_id)
+    }
+}
+
+impl From<ContentId> for String {
+    fn from(id: ContentId) -> Self {
+        id.to_string()
+    }
+}
+
+impl TryFrom<String> for ContentId {
+    type Error = XTauriError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+// Sent to the frontend as its string encoding
+impl specta::Type for ContentId {
+    fn inline(type_map: &mut specta::TypeCollection, generics: specta::Generics) -> specta::DataType {
+        <String as specta::Type>::inline(type_map, generics)
+    }
+}
+
+/// Rewrite rows stored under other spellings of a content type to the canonical one
+///
+/// Rows that would then duplicate an existing one are dropped. Returns how many
+/// rows were rewritten or dropped. Safe to run on every start.
+pub fn migrate_tables(conn: &Connection) -> Result<usize> {
+    let mut changed = 0;
+    for table in KEYED_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            continue;
+        }
+
+        let types: Vec<String> = conn
+            .prepare(&format!("SELECT DISTINCT content_type FROM {}", table))?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for legacy in types {
+            let Some(canonical) = canonical_type(&legacy).filter(|canonical| *canonical != legacy) else {
+                continue;
+            };
+            changed += conn.execute(
+                &format!(
+                    "UPDATE OR IGNORE {} SET content_type = ?1, content_id = TRIM(content_id) WHERE content_type = ?2",
+                    table
+                ),
+                params![canonical, legacy],
+            )?;
+            // What's left duplicates a row already under the canonical type
+            changed += conn.execute(&format!("DELETE FROM {} WHERE content_type = ?1", table), [&legacy])?;
+        }
+    }
+    if changed > 0 {
+        println!("[CONTENT_ID] Rewrote {} row(s) to unified content ids", changed);
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spellings_share_one_identity() {
+        let movie = ContentId::new("movie", "42").unwrap();
+        for spelling in ["Movie", "movies", "vod", "xtream-movie"] {
+            assert_eq!(ContentId::new(spelling, " 42 ").unwrap(), movie);
+        }
+        assert_eq!(movie.to_string(), "movie:42");
+        assert_eq!("movie:42".parse::<ContentId>().unwrap(), movie);
+        assert_eq!(serde_json::to_string(&movie).unwrap(), "\"movie:42\"");
+        assert_eq!(serde_json::from_str::<ContentId>("\"xtream-channel:7\"").unwrap(), ContentId::Channel(7));
+
+        let episode = ContentId::new("episode", "s1e2-abc").unwrap();
+        assert_eq!(episode.to_string(), "episode:s1e2-abc");
+        assert!(ContentId::new("channel", "abc").is_err());
+        assert!(ContentId::new("podcast", "1").is_err());
+        assert!("movie".parse::<ContentId>().is_err());
+    }
+
+    #[test]
+    fn test_migration_rewrites_legacy_types() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_favorites (
+                id TEXT PRIMARY KEY, profile_id TEXT NOT NULL, content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL, UNIQUE(profile_id, content_type, content_id)
+            );
+            CREATE TABLE xtream_history (
+                id TEXT PRIMARY KEY, profile_id TEXT NOT NULL, content_type TEXT NOT NULL, content_id TEXT NOT NULL
+            );
+            INSERT INTO xtream_favorites VALUES ('f1', 'p1', 'movie', '42'), ('f2', 'p1', 'Movie', '42'), ('f3', 'p1', 'channels', '7');
+            INSERT INTO xtream_history VALUES ('h1', 'p1', 'xtream-series', '9'), ('h2', 'p1', 'movie', '42');",
+        )
+        .unwrap();
+
+        assert_eq!(migrate_tables(&conn).unwrap(), 3);
+        let favorites: Vec<(String, String, String)> = conn
+            .prepare("SELECT id, content_type, content_id FROM xtream_favorites ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            favorites,
+            vec![
+                ("f1".to_string(), "movie".to_string(), "42".to_string()),
+                ("f3".to_string(), "channel".to_string(), "7".to_string()),
+            ]
+        );
+        let series: String = conn
+            .query_row("SELECT content_type FROM xtream_history WHERE id = 'h1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(series, "series");
+
+        // Nothing left to rewrite
+        assert_eq!(migrate_tables(&conn).unwrap(), 0);
+    }
+}
@@ -0,0 +1,252 @@
+// Country detection for channels
+//
+// Playlists and providers rarely say where a channel is from in a structured
+// way. Some M3U entries carry a `tvg-country` attribute, but most only hint at
+// it with a tag in the name ("UK: BBC One", "|FR| TF1") or a group named after
+// the country ("GERMANY FHD"). These hints are resolved to ISO 3166-1 alpha-2
+// codes so channels can be filtered by country and shown with a flag.
+use regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+/// ISO code, English name, and the other tags and names used for a country
+///
+/// Aliases are upper case. Only countries commonly found in IPTV playlists are
+/// listed; anything else is left undetected rather than guessed.
+const COUNTRIES: &[(&str, &str, &[&str])] = &[
+    ("AE", "United Arab Emirates", &["UAE", "EMIRATES"]),
+    ("AF", "Afghanistan", &["AFG"]),
+    ("AL", "Albania", &["ALB", "SHQIPERIA"]),
+    ("AM", "Armenia", &["ARM"]),
+    ("AR", "Argentina", &["ARG"]),
+    ("AT", "Austria", &["AUT", "ÖSTERREICH", "OSTERREICH"]),
+    ("AU", "Australia", &["AUS"]),
+    ("AZ", "Azerbaijan", &["AZE"]),
+    ("BA", "Bosnia and Herzegovina", &["BIH", "BOSNIA"]),
+    ("BD", "Bangladesh", &["BGD"]),
+    ("BE", "Belgium", &["BEL", "BELGIQUE", "BELGIE"]),
+    ("BG", "Bulgaria", &["BUL", "BGR"]),
+    ("BR", "Brazil", &["BRA", "BRASIL"]),
+    ("CA", "Canada", &["CAN"]),
+    ("CH", "Switzerland", &["SUI", "CHE", "SCHWEIZ", "SUISSE"]),
+    ("CL", "Chile", &["CHL"]),
+    ("CN", "China", &["CHN"]),
+    ("CO", "Colombia", &["COL"]),
+    ("CY", "Cyprus", &["CYP"]),
+    ("CZ", "Czechia", &["CZE", "CZECH REPUBLIC"]),
+    ("DE", "Germany", &["GER", "DEU", "DEUTSCHLAND"]),
+    ("DK", "Denmark", &["DEN", "DNK", "DANMARK"]),
+    ("DZ", "Algeria", &["DZA", "ALGERIE"]),
+    ("EG", "Egypt", &["EGY"]),
+    ("ES", "Spain", &["ESP", "ESPAÑA", "ESPANA"]),
+    ("FI", "Finland", &["FIN", "SUOMI"]),
+    ("FR", "France", &["FRA"]),
+    ("GB", "United Kingdom", &["UK", "GBR", "ENGLAND", "BRITAIN"]),
+    ("GR", "Greece", &["GRE", "GRC", "HELLAS"]),
+    ("HR", "Croatia", &["CRO", "HRV", "HRVATSKA"]),
+    ("HU", "Hungary", &["HUN", "MAGYARORSZAG"]),
+    ("ID", "Indonesia", &["IDN"]),
+    ("IE", "Ireland", &["IRL"]),
+    ("IL", "Israel", &["ISR"]),
+    ("IN", "India", &["IND"]),
+    ("IQ", "Iraq", &["IRQ"]),
+    ("IR", "Iran", &["IRN"]),
+    ("IT", "Italy", &["ITA", "ITALIA"]),
+    ("JO", "Jordan", &["JOR"]),
+    ("JP", "Japan", &["JPN"]),
+    ("KR", "South Korea", &["KOR", "KOREA"]),
+    ("KW", "Kuwait", &["KWT"]),
+    ("LB", "Lebanon", &["LBN", "LIBAN"]),
+    ("MA", "Morocco", &["MAR", "MAROC"]),
+    ("MK", "North Macedonia", &["MKD", "MACEDONIA"]),
+    ("MX", "Mexico", &["MEX"]),
+    ("MY", "Malaysia", &["MYS"]),
+    ("NL", "Netherlands", &["NED", "NLD", "NEDERLAND", "HOLLAND"]),
+    ("NO", "Norway", &["NOR", "NORGE"]),
+    ("NZ", "New Zealand", &["NZL"]),
+    ("PE", "Peru", &["PER"]),
+    ("PH", "Philippines", &["PHL"]),
+    ("PK", "Pakistan", &["PAK"]),
+    ("PL", "Poland", &["POL", "POLSKA"]),
+    ("PT", "Portugal", &["POR", "PRT"]),
+    ("QA", "Qatar", &["QAT"]),
+    ("RO", "Romania", &["ROU", "ROM"]),
+    ("RS", "Serbia", &["SRB", "SRBIJA"]),
+    ("RU", "Russia", &["RUS"]),
+    ("SA", "Saudi Arabia", &["KSA", "SAU"]),
+    ("SE", "Sweden", &["SWE", "SVERIGE"]),
+    ("SI", "Slovenia", &["SLO", "SVN"]),
+    ("SK", "Slovakia", &["SVK"]),
+    ("SY", "Syria", &["SYR"]),
+    ("TH", "Thailand", &["THA"]),
+    ("TN", "Tunisia", &["TUN"]),
+    ("TR", "Turkey", &["TUR", "TÜRKIYE", "TURKIYE"]),
+    ("UA", "Ukraine", &["UKR"]),
+    ("US", "United States", &["USA"]),
+    ("VN", "Vietnam", &["VNM"]),
+    ("ZA", "South Africa", &["RSA", "ZAF"]),
+];
+
+/// Tags that look like country codes but mark a language in channel names
+///
+/// "AR:" is an Arabic channel far more often than an Argentinian one.
+const LANGUAGE_TAGS: &[&str] = &["AR"];
+
+/// A country tag at the start of a name: `UK:`, `|US|`, `[FR]` or `DE -`
+static COUNTRY_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:\|\s*([A-Z]{2,3})\s*\||\[\s*([A-Z]{2,3})\s*\]|([A-Z]{2,3})\s*[:|]|([A-Z]{2,3})\s+-)")
+        .unwrap()
+});
+
+/// A country and the number of channels from it
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct CountryCount {
+    pub code: String,
+    pub name: String,
+    pub flag: String,
+    pub channels: usize,
+}
+
+impl CountryCount {
+    pub fn new(code: &str, channels: usize) -> Self {
+        Self {
+            code: code.to_string(),
+            name: country_name(code).unwrap_or(code).to_string(),
+            flag: flag_emoji(code),
+            channels,
+        }
+    }
+}
+
+/// English name of a country by ISO code
+pub fn country_name(code: &str) -> Option<&'static str> {
+    COUNTRIES
+        .iter()
+        .find(|(known, _, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, name, _)| *name)
+}
+
+/// Flag emoji for an ISO code, made of the two regional indicator letters
+pub fn flag_emoji(code: &str) -> String {
+    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return String::new();
+    }
+    code.chars()
+        .filter_map(|c| char::from_u32(0x1F1E6 + (c.to_ascii_uppercase() as u32 - 'A' as u32)))
+        .collect()
+}
+
+/// ISO code for a code, alias or country name, in any case
+pub fn lookup(text: &str) -> Option<&'static str> {
+    let text = text.trim();
+    let upper = text.to_uppercase();
+    COUNTRIES
+        .iter()
+        .find(|(code, name, aliases)| {
+            *code == upper || name.eq_ignore_ascii_case(text) || aliases.contains(&upper.as_str())
+        })
+        .map(|(code, _, _)| *code)
+}
+
+/// The code a country filter matches: a recognized country's ISO code, otherwise the input in upper case
+pub fn filter_code(requested: &str) -> String {
+    lookup(requested).map_or_else(|| requested.trim().to_uppercase(), str::to_string)
+}
+
+/// Country of a leading tag such as `UK:` or `[FR]`
+fn tag_country(text: &str) -> Option<&'static str> {
+    let captures = COUNTRY_TAG.captures(text)?;
+    let tag = captures.iter().skip(1).flatten().next()?.as_str();
+    if LANGUAGE_TAGS.contains(&tag) {
+        return None;
+    }
+    COUNTRIES
+        .iter()
+        .find(|(code, _, aliases)| *code == tag || aliases.contains(&tag))
+        .map(|(code, _, _)| *code)
+}
+
+/// Country whose name or alias starts `text` as a whole word, as in "GERMANY FHD"
+///
+/// Bare two-letter codes are not matched here: "MY CHANNELS" isn't Malaysian.
+fn leading_country(text: &str) -> Option<&'static str> {
+    let upper = text.trim().to_uppercase();
+    let starts_with_word = |word: &str| {
+        upper.get(..word.len()).is_some_and(|start| start.eq_ignore_ascii_case(word))
+            && !upper[word.len()..].starts_with(|c: char| c.is_alphanumeric())
+    };
+    COUNTRIES
+        .iter()
+        .find(|(_, name, aliases)| starts_with_word(name) || aliases.iter().any(|alias| starts_with_word(alias)))
+        .map(|(code, _, _)| *code)
+}
+
+/// Country of a group or category title
+fn group_country(group: &str) -> Option<&'static str> {
+    tag_country(group)
+        .or_else(|| lookup(group))
+        .or_else(|| leading_country(group))
+}
+
+/// Detect a channel's country from its `tvg-country` attribute, name and group title
+///
+/// `tvg-country` may list several codes; the first recognized one wins. Without
+/// it, a tag in the name is preferred over the group. Pass empty strings for
+/// hints a source doesn't have.
+pub fn detect_country(tvg_country: &str, name: &str, group: &str) -> Option<&'static str> {
+    tvg_country
+        .split([';', ',', '|', ' '])
+        .find_map(lookup)
+        .or_else(|| tag_country(name))
+        .or_else(|| (!group.is_empty()).then(|| group_country(group)).flatten())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_tvg_country() {
+        assert_eq!(detect_country("fr", "TF1", ""), Some("FR"));
+        assert_eq!(detect_country("XX;DE", "Das Erste", ""), Some("DE"));
+        // The attribute is explicit, so "AR" is Argentina there
+        assert_eq!(detect_country("AR", "Telefe", "UK | News"), Some("AR"));
+    }
+
+    #[test]
+    fn test_detect_from_name_tag() {
+        assert_eq!(detect_country("", "UK: BBC One", ""), Some("GB"));
+        assert_eq!(detect_country("", "|US| CNN", ""), Some("US"));
+        assert_eq!(detect_country("", "[FR] TF1", ""), Some("FR"));
+        assert_eq!(detect_country("", "DE - Das Erste", ""), Some("DE"));
+        assert_eq!(detect_country("", "USA: ESPN", ""), Some("US"));
+        assert_eq!(detect_country("", "BBC News", ""), None);
+        assert_eq!(detect_country("", "AR: MBC 1", ""), None);
+        assert_eq!(detect_country("", "ABC: News", ""), None);
+    }
+
+    #[test]
+    fn test_detect_from_group() {
+        assert_eq!(detect_country("", "TF1", "FRANCE FHD"), Some("FR"));
+        assert_eq!(detect_country("", "BBC One", "United Kingdom | Entertainment"), Some("GB"));
+        assert_eq!(detect_country("", "Sport 1", "UK Sports"), Some("GB"));
+        assert_eq!(detect_country("", "Das Erste", "DE"), Some("DE"));
+        assert_eq!(detect_country("", "ZDF", "|DE| Nachrichten"), Some("DE"));
+        assert_eq!(detect_country("", "Channel", "MY CHANNELS"), None);
+        assert_eq!(detect_country("", "Channel", "Frances Picks"), None);
+        // A tag in the name wins over the group
+        assert_eq!(detect_country("", "IT: Rai 1", "Europe"), Some("IT"));
+    }
+
+    #[test]
+    fn test_flag_and_lookup() {
+        assert_eq!(flag_emoji("GB"), "🇬🇧");
+        assert_eq!(flag_emoji("us"), "🇺🇸");
+        assert_eq!(flag_emoji("USA"), "");
+        assert_eq!(lookup("uk"), Some("GB"));
+        assert_eq!(lookup("germany"), Some("DE"));
+        assert_eq!(filter_code("usa"), "US");
+        assert_eq!(filter_code("zz"), "ZZ");
+        assert_eq!(CountryCount::new("FR", 3).name, "France");
+    }
+}
@@ -1,18 +1,132 @@
+use crate::db_pool::{self, DbPool};
 use crate::error::{Result, XTauriError};
 use crate::m3u_parser::Channel;
+use crate::migrations::{self, Migration};
 use rusqlite::{Connection, Result as RusqliteResult};
 use std::fs;
-
-pub fn initialize_database() -> Result<Connection> {
-    let data_dir = dirs::data_dir()
+use std::path::PathBuf;
+
+/// Changes to the app tables since `initialize_database` stopped growing ad hoc
+///
+/// Add new columns and tables here rather than as another `ALTER TABLE` above.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Rewrite favorites, history and tags to unified content ids",
+        up: |conn| crate::content_id::migrate_tables(conn).map(|_| ()),
+    },
+    Migration {
+        version: 2,
+        description: "Index the image cache by URL",
+        up: crate::images::create_index_table,
+    },
+    Migration {
+        version: 3,
+        description: "Image format settings and decode failures",
+        up: crate::image_formats::migrate,
+    },
+    Migration {
+        version: 4,
+        description: "Recordings",
+        up: crate::recordings::create_table,
+    },
+    Migration {
+        version: 5,
+        description: "Cache TTLs per content kind",
+        up: crate::cache_ttl::migrate,
+    },
+    Migration {
+        version: 6,
+        description: "Weekly digest",
+        up: crate::digest::migrate,
+    },
+    Migration {
+        version: 7,
+        description: "Image prefetch after sync",
+        up: crate::image_prefetch::migrate,
+    },
+    Migration {
+        version: 8,
+        description: "XMLTV programme icons",
+        up: crate::epg_xmltv::migrate,
+    },
+];
+
+/// Where the database file lives, in the app's data directory
+pub fn database_path() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
         .ok_or_else(|| XTauriError::DataDirectoryAccess)?
-        .join("xtauri");
+        .join("xtauri")
+        .join("database.sqlite"))
+}
+
+/// Keyring service and entry holding the SQLCipher key
+#[cfg(feature = "sqlcipher")]
+const KEYRING_SERVICE: &str = "xtauri-iptv";
+#[cfg(feature = "sqlcipher")]
+const KEYRING_DATABASE_KEY: &str = "database_key";
+
+/// The SQLCipher key as a raw key literal, created and stored in the keyring on first use
+#[cfg(feature = "sqlcipher")]
+fn database_key() -> Result<&'static str> {
+    use rand::RngCore;
+    use std::sync::OnceLock;
+
+    static KEY: OnceLock<String> = OnceLock::new();
+    if let Some(key) = KEY.get() {
+        return Ok(key);
+    }
+
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_DATABASE_KEY)
+        .map_err(|e| XTauriError::credential_encryption(format!("Failed to access keyring: {}", e)))?;
+    let hex = match entry.get_password() {
+        Ok(hex) if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => hex,
+        Ok(_) => {
+            return Err(XTauriError::credential_decryption("Invalid database key".to_string()));
+        }
+        Err(keyring::Error::NoEntry) => {
+            let mut key = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+            entry
+                .set_password(&hex)
+                .map_err(|e| XTauriError::credential_encryption(format!("Failed to store database key: {}", e)))?;
+            hex
+        }
+        Err(e) => {
+            return Err(XTauriError::credential_decryption(format!("Failed to read database key: {}", e)));
+        }
+    };
+    Ok(KEY.get_or_init(|| format!("x'{}'", hex)))
+}
 
-    fs::create_dir_all(&data_dir)
-        .map_err(|_e| XTauriError::directory_creation(data_dir.display().to_string()))?;
+/// Unlock a connection to the database; a no-op unless built with `sqlcipher`
+///
+/// Has to run before any other statement on the connection. With SQLCipher the
+/// file is encrypted with a random 256-bit key kept in the platform keyring.
+pub fn apply_key(conn: &Connection) -> Result<()> {
+    #[cfg(feature = "sqlcipher")]
+    conn.pragma_update(None, "key", database_key()?)?;
+    #[cfg(not(feature = "sqlcipher"))]
+    let _ = conn;
+    Ok(())
+}
+
+/// The pool every state shares, writing through the connection `initialize_database` opened
+pub fn open_pool(writer: Connection) -> Result<DbPool> {
+    Ok(DbPool::open(writer, database_path()?, db_pool::DEFAULT_IDLE_READERS))
+}
+
+pub fn initialize_database() -> Result<Connection> {
+    let db_path = database_path()?;
+    if let Some(data_dir) = db_path.parent() {
+        fs::create_dir_all(data_dir)
+            .map_err(|_e| XTauriError::directory_creation(data_dir.display().to_string()))?;
+    }
 
-    let db_path = data_dir.join("database.sqlite");
     let conn = Connection::open(&db_path)?;
+    apply_key(&conn)?;
+    db_pool::configure_writer(&conn)?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS history (
@@ -101,6 +215,137 @@ pub fn initialize_database() -> Result<Connection> {
     )
     .ok();
 
+    // Add the remote control columns to existing settings table if they don't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN remote_control_enabled BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN remote_control_port INTEGER NOT NULL DEFAULT 8787",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN remote_control_token TEXT",
+        [],
+    )
+    .ok();
+
+    // Add the HTTP compression column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN http_compression_enabled BOOLEAN NOT NULL DEFAULT 1",
+        [],
+    )
+    .ok();
+
+    // Add the connection strategy column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN connection_strategy TEXT NOT NULL DEFAULT 'happy_eyeballs'",
+        [],
+    )
+    .ok();
+
+    // Add the playlist watch folder column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN playlist_watch_dir TEXT",
+        [],
+    )
+    .ok();
+
+    // Add the startup auto-authentication column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN auto_authenticate_on_startup BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+
+    // Add the low-power mode column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN low_power_mode TEXT NOT NULL DEFAULT 'auto'",
+        [],
+    )
+    .ok();
+
+    // Add the search debounce column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN search_debounce_ms INTEGER NOT NULL DEFAULT 150",
+        [],
+    )
+    .ok();
+
+    // Add the update check columns to existing settings table if they don't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN auto_check_updates BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN update_check_endpoint TEXT",
+        [],
+    )
+    .ok();
+
+    // Add the parental age limit column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN parental_max_age_rating INTEGER",
+        [],
+    )
+    .ok();
+
+    // Add the parental PIN and audit retention columns to existing settings table if they don't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN parental_pin_salt TEXT",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN parental_pin_hash TEXT",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN parental_audit_retention_days INTEGER NOT NULL DEFAULT 90",
+        [],
+    )
+    .ok();
+
+    // Add the presorted categories column to existing settings table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE settings ADD COLUMN presorted_categories BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+
+    // Add the maintenance window columns to existing settings table if they don't exist
+    conn.execute("ALTER TABLE settings ADD COLUMN maintenance_window_start TEXT", [])
+        .ok();
+    conn.execute("ALTER TABLE settings ADD COLUMN maintenance_window_end TEXT", [])
+        .ok();
+
+    // Add the command concurrency limit columns to existing settings table if they don't exist
+    for column in [
+        "concurrency_images INTEGER NOT NULL DEFAULT 8",
+        "concurrency_search INTEGER NOT NULL DEFAULT 4",
+        "concurrency_sync INTEGER NOT NULL DEFAULT 2",
+        "concurrency_epg INTEGER NOT NULL DEFAULT 4",
+        "concurrency_max_queued INTEGER NOT NULL DEFAULT 32",
+    ] {
+        conn.execute(&format!("ALTER TABLE settings ADD COLUMN {}", column), [])
+            .ok();
+    }
+
+    // Named copies of the device settings, see settings_profiles.rs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings_profiles (
+            name TEXT PRIMARY KEY,
+            settings TEXT NOT NULL,
+            is_active BOOLEAN NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS channel_lists (
             id INTEGER PRIMARY KEY,
@@ -119,6 +364,43 @@ pub fn initialize_database() -> Result<Connection> {
         [],
     )?;
 
+    // Add the encoding columns to existing channel_lists table if they don't exist
+    conn.execute(
+        "ALTER TABLE channel_lists ADD COLUMN encoding TEXT NOT NULL DEFAULT 'auto'",
+        [],
+    )
+    .ok();
+    conn.execute("ALTER TABLE channel_lists ADD COLUMN detected_encoding TEXT", [])
+        .ok();
+    conn.execute("ALTER TABLE channel_lists ADD COLUMN encoding_warning TEXT", [])
+        .ok();
+
+    // Add the expiry column to existing channel_lists table if it doesn't exist
+    conn.execute("ALTER TABLE channel_lists ADD COLUMN expires_at INTEGER", [])
+        .ok();
+
+    // Add the XMLTV guide columns to existing channel_lists table if they don't exist
+    conn.execute("ALTER TABLE channel_lists ADD COLUMN epg_url TEXT", [])
+        .ok();
+    conn.execute("ALTER TABLE channel_lists ADD COLUMN epg_last_fetched INTEGER", [])
+        .ok();
+
+    // XMLTV programmes for playlist channels, see epg_xmltv.rs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xmltv_programmes (
+            channel_list_id INTEGER NOT NULL,
+            channel_id TEXT NOT NULL,
+            start_timestamp INTEGER NOT NULL,
+            stop_timestamp INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            category TEXT,
+            PRIMARY KEY (channel_list_id, channel_id, start_timestamp),
+            FOREIGN KEY (channel_list_id) REFERENCES channel_lists(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS group_selections (
             channel_list_id INTEGER NOT NULL,
@@ -130,6 +412,25 @@ pub fn initialize_database() -> Result<Connection> {
         [],
     )?;
 
+    // Add the dedupe column to existing group_selections table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE group_selections ADD COLUMN dedupe BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+
+    // Playlist channel URLs that failed to play, for group statistics
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS playlist_link_failures (
+            channel_list_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            failed_at INTEGER NOT NULL,
+            PRIMARY KEY (channel_list_id, url),
+            FOREIGN KEY (channel_list_id) REFERENCES channel_lists(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS saved_filters (
             channel_list_id INTEGER NOT NULL,
@@ -160,6 +461,14 @@ pub fn initialize_database() -> Result<Connection> {
         [],
     )?;
 
+    // Add the API language column to existing profiles table if it doesn't exist
+    conn.execute("ALTER TABLE xtream_profiles ADD COLUMN api_language TEXT", [])
+        .ok();
+
+    // Add the expiry column to existing profiles table if it doesn't exist
+    conn.execute("ALTER TABLE xtream_profiles ADD COLUMN expires_at INTEGER", [])
+        .ok();
+
     conn.execute(
         "CREATE TABLE IF NOT EXISTS xtream_content_cache (
             cache_key TEXT PRIMARY KEY,
@@ -216,6 +525,10 @@ pub fn initialize_database() -> Result<Connection> {
         [],
     )?;
 
+    // Add the position column to existing favorites table if it doesn't exist
+    conn.execute("ALTER TABLE xtream_favorites ADD COLUMN position REAL", [])
+        .ok();
+
     // Create indexes for favorites
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_xtream_favorites_profile_type 
@@ -268,6 +581,28 @@ pub fn initialize_database() -> Result<Connection> {
     conn.execute("ALTER TABLE xtream_history ADD COLUMN duration REAL", [])
         .ok(); // Use ok() to ignore error if column already exists
 
+    conn.execute(
+        "ALTER TABLE xtream_history ADD COLUMN watched BOOLEAN DEFAULT FALSE",
+        [],
+    )
+    .ok(); // Use ok() to ignore error if column already exists
+
+    // Per-series watch progress, kept up to date by the bulk watched operations
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_series_progress (
+            profile_id TEXT NOT NULL,
+            series_id INTEGER NOT NULL,
+            watched_episodes INTEGER NOT NULL DEFAULT 0,
+            total_episodes INTEGER NOT NULL DEFAULT 0,
+            last_watched_episode_id TEXT,
+            next_episode_id TEXT,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (profile_id, series_id),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
     // Search history table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS xtream_search_history (
@@ -312,6 +647,141 @@ pub fn initialize_database() -> Result<Connection> {
     )
     .ok();
 
+    // Content tags table
+    crate::xtream::tags::create_table(&conn)?;
+
+    // Automation scripts table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_automation_scripts (
+            id TEXT PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            event TEXT NOT NULL,
+            source TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            last_run_at DATETIME,
+            last_error TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE,
+            UNIQUE(profile_id, name)
+        )",
+        [],
+    )?;
+
+    // EPG reminders table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_epg_reminders (
+            id TEXT PRIMARY KEY,
+            profile_id TEXT NOT NULL,
+            channel_id TEXT NOT NULL,
+            channel_name TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            start_timestamp INTEGER NOT NULL,
+            stop_timestamp INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE,
+            UNIQUE(profile_id, channel_id, start_timestamp)
+        )",
+        [],
+    )?;
+
+    // Add the notified column to existing xtream_epg_reminders table if it doesn't exist
+    conn.execute(
+        "ALTER TABLE xtream_epg_reminders ADD COLUMN notified BOOLEAN NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+
+    // Account status recorded by the startup authentication check
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_account_status (
+            profile_id TEXT PRIMARY KEY,
+            status TEXT,
+            expires_at INTEGER,
+            expired BOOLEAN NOT NULL DEFAULT 0,
+            clock_skew_secs INTEGER,
+            user_info TEXT,
+            checked_at INTEGER NOT NULL,
+            error TEXT,
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Content post-processors the user has switched on or off
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS content_post_processors (
+            processor_id TEXT PRIMARY KEY,
+            enabled BOOLEAN NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Result of the last check for a newer release
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS update_check (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            endpoint TEXT NOT NULL,
+            result TEXT NOT NULL,
+            checked_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Small JSON values persisted by UI components, such as column widths
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS ui_state (
+            namespace TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY (namespace, key)
+        )",
+        [],
+    )?;
+
+    // In-app notification inbox
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notifications (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT NOT NULL,
+            profile_id TEXT,
+            created_at INTEGER NOT NULL,
+            read_at INTEGER
+        )",
+        [],
+    )?;
+
+    // Notification kinds switched off for a channel; missing routes are on
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_routes (
+            kind TEXT NOT NULL,
+            channel TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL,
+            PRIMARY KEY (kind, channel)
+        )",
+        [],
+    )?;
+
+    // Attempts to get past parental controls with the PIN
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS parental_audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            attempted_at INTEGER NOT NULL,
+            success BOOLEAN NOT NULL,
+            target TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_parental_audit_log_attempted_at ON parental_audit_log(attempted_at)",
+        [],
+    )?;
+
     let list_count: i64 =
         conn.query_row("SELECT COUNT(*) FROM channel_lists", [], |row| row.get(0))?;
     if list_count == 0 {
@@ -334,6 +804,8 @@ pub fn initialize_database() -> Result<Connection> {
     // Initialize content cache tables
     crate::content_cache::initialize_content_cache_tables(&conn)?;
 
+    migrations::run(&conn, migrations::APP, MIGRATIONS)?;
+
     Ok(conn)
 }
 
@@ -383,7 +855,8 @@ pub fn set_group_enabled(
     enabled: bool,
 ) -> RusqliteResult<()> {
     conn.execute(
-        "INSERT OR REPLACE INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, ?3)",
+        "INSERT INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, ?3)
+         ON CONFLICT (channel_list_id, group_name) DO UPDATE SET is_enabled = excluded.is_enabled",
         (channel_list_id, group_name, enabled),
     )?;
     Ok(())
@@ -442,7 +915,10 @@ pub fn enable_all_groups(
     let tx = conn.transaction()?;
 
     {
-        let mut stmt = tx.prepare("INSERT OR REPLACE INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, ?3)")?;
+        let mut stmt = tx.prepare(
+            "INSERT INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, ?3)
+             ON CONFLICT (channel_list_id, group_name) DO UPDATE SET is_enabled = excluded.is_enabled",
+        )?;
         for group in groups {
             stmt.execute((channel_list_id, group, true))?;
         }
@@ -461,7 +937,10 @@ pub fn disable_all_groups(
     let tx = conn.transaction()?;
 
     {
-        let mut stmt = tx.prepare("INSERT OR REPLACE INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, ?3)")?;
+        let mut stmt = tx.prepare(
+            "INSERT INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, ?3)
+             ON CONFLICT (channel_list_id, group_name) DO UPDATE SET is_enabled = excluded.is_enabled",
+        )?;
         for group in groups {
             stmt.execute((channel_list_id, group, false))?;
         }
@@ -471,7 +950,9 @@ pub fn disable_all_groups(
     Ok(())
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
+/// A playlist search saved to a numbered slot
+#[derive(serde::Serialize, serde::Deserialize, specta::Type)]
+#[specta(rename = "PlaylistSavedFilter")]
 pub struct SavedFilter {
     pub slot_number: i32,
     pub search_query: String,
@@ -0,0 +1,216 @@
+// SQLite connection pool
+//
+// The app, the Xtream profiles and the content cache all write through the
+// pool's one read-write connection, so writes are serialized by its mutex
+// rather than by SQLite's busy timeout. The database runs in WAL mode, where
+// readers see the last committed data while the writer works, so listings and
+// searches take a read-only connection from the pool instead and don't wait
+// for a sync holding the writer. Idle readers are kept for reuse up to
+// `max_idle`; a read beyond that opens a connection of its own rather than
+// waiting, so nested reads can't deadlock. An in-memory database can't be
+// opened twice, so a pool over one reads through the writer instead.
+use crate::error::{Result, XTauriError};
+use rusqlite::{Connection, OpenFlags};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Readers kept open, matching the default search concurrency
+pub const DEFAULT_IDLE_READERS: usize = 4;
+
+/// How long a statement waits on a locked database before failing busy
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct DbPool {
+    writer: Arc<Mutex<Connection>>,
+    /// Database file readers are opened on; `None` reads through the writer
+    path: Option<PathBuf>,
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+}
+
+/// A connection to read from; returned to the pool when dropped
+pub enum DbRead<'a> {
+    Pooled {
+        pool: &'a DbPool,
+        conn: Option<Connection>,
+    },
+    Shared(MutexGuard<'a, Connection>),
+}
+
+impl Deref for DbRead<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            DbRead::Pooled { conn, .. } => conn.as_ref().expect("pooled connection is present until drop"),
+            DbRead::Shared(guard) => guard,
+        }
+    }
+}
+
+impl Drop for DbRead<'_> {
+    fn drop(&mut self) {
+        if let DbRead::Pooled { pool, conn } = self {
+            if let Some(conn) = conn.take() {
+                pool.release(conn);
+            }
+        }
+    }
+}
+
+/// Put a read-write connection in WAL mode, which also applies to every other connection to the file
+pub fn configure_writer(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let mode: String = conn.query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") && !mode.eq_ignore_ascii_case("memory") {
+        eprintln!("[DB_POOL] Database stayed in {} journal mode; reads may wait for writes", mode);
+    }
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(())
+}
+
+impl DbPool {
+    /// Pool over a database file, writing through `writer` and reading from read-only connections
+    pub fn open(writer: Connection, path: PathBuf, max_idle: usize) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(writer)),
+            path: Some(path),
+            idle: Mutex::new(Vec::new()),
+            max_idle,
+        }
+    }
+
+    /// Pool that reads through its writer, e.g. over an in-memory database
+    pub fn shared(conn: Arc<Mutex<Connection>>) -> Self {
+        Self {
+            writer: conn,
+            path: None,
+            idle: Mutex::new(Vec::new()),
+            max_idle: 0,
+        }
+    }
+
+    /// The read-write connection, for states that keep it to lock themselves
+    pub fn writer(&self) -> Arc<Mutex<Connection>> {
+        Arc::clone(&self.writer)
+    }
+
+    /// Lock the read-write connection
+    pub fn write(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.writer
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))
+    }
+
+    fn open_reader(path: &Path) -> Result<Connection> {
+        let conn = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+        )?;
+        crate::database::apply_key(&conn)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+
+    fn release(&self, conn: Connection) {
+        if let Ok(mut idle) = self.idle.lock() {
+            if idle.len() < self.max_idle {
+                idle.push(conn);
+            }
+        }
+    }
+
+    /// A connection to read from, reusing an idle one when there is one
+    pub fn read(&self) -> Result<DbRead<'_>> {
+        match &self.path {
+            None => self.write().map(DbRead::Shared),
+            Some(path) => {
+                let reused = self
+                    .idle
+                    .lock()
+                    .map_err(|_| XTauriError::lock_acquisition("database read pool"))?
+                    .pop();
+                let conn = match reused {
+                    Some(conn) => conn,
+                    None => Self::open_reader(path)?,
+                };
+                Ok(DbRead::Pooled { pool: self, conn: Some(conn) })
+            }
+        }
+    }
+
+    /// Number of readers waiting to be reused
+    pub fn idle_readers(&self) -> usize {
+        self.idle.lock().map(|idle| idle.len()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_database(dir: &std::path::Path) -> (PathBuf, Connection) {
+        let path = dir.join("pool.sqlite");
+        let writer = Connection::open(&path).unwrap();
+        configure_writer(&writer).unwrap();
+        writer
+            .execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY); INSERT INTO items VALUES (1), (2);")
+            .unwrap();
+        (path, writer)
+    }
+
+    #[test]
+    fn test_reads_are_not_blocked_by_an_open_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, writer) = file_database(dir.path());
+        let pool = DbPool::open(writer, path, DEFAULT_IDLE_READERS);
+
+        // A sync's transaction holds the write lock until it commits
+        let mut writer = pool.write().unwrap();
+        let tx = writer.transaction().unwrap();
+        tx.execute("INSERT INTO items VALUES (3)", []).unwrap();
+
+        let count = |pool: &DbPool| -> i64 {
+            pool.read().unwrap().query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(count(&pool), 2);
+        tx.commit().unwrap();
+        assert_eq!(count(&pool), 3);
+
+        let reader = pool.read().unwrap();
+        assert!(reader.execute("DELETE FROM items", []).is_err());
+    }
+
+    #[test]
+    fn test_readers_are_reused_up_to_the_idle_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, writer) = file_database(dir.path());
+        let pool = DbPool::open(writer, path, 1);
+
+        {
+            let first = pool.read().unwrap();
+            // Nested reads open their own connection instead of waiting
+            let second = pool.read().unwrap();
+            assert_eq!(pool.idle_readers(), 0);
+            drop(first);
+            drop(second);
+        }
+        assert_eq!(pool.idle_readers(), 1);
+        let _again = pool.read().unwrap();
+        assert_eq!(pool.idle_readers(), 0);
+    }
+
+    #[test]
+    fn test_shared_pool_reads_through_its_connection() {
+        let conn = Arc::new(Mutex::new(Connection::open_in_memory().unwrap()));
+        conn.lock().unwrap().execute_batch("CREATE TABLE items (id INTEGER); INSERT INTO items VALUES (7);").unwrap();
+        let pool = DbPool::shared(Arc::clone(&conn));
+
+        let id: i64 = pool.read().unwrap().query_row("SELECT id FROM items", [], |row| row.get(0)).unwrap();
+        assert_eq!(id, 7);
+        assert_eq!(pool.idle_readers(), 0);
+        assert!(Arc::ptr_eq(&pool.writer(), &conn));
+    }
+}
@@ -0,0 +1,605 @@
+// Weekly digest
+//
+// Once a week, at the day and hour of the digest settings, the week is summed
+// up for every Xtream profile: items that appeared in followed categories,
+// new episodes of favorited series and the reminded programmes coming up in
+// the next week, plus how much the app's storage grew. Digests are kept in the
+// `digests` table, so the latest can be shown again and storage growth is
+// measured against the one before. A notification points to each new digest
+// unless that is switched off.
+use crate::content_cache::followed_categories;
+use crate::error::{Result, XTauriError};
+use crate::notifications::{self, Notification, NotificationKind};
+use crate::state::DbState;
+use crate::xtream::reminders::{XtreamEpgReminder, XtreamRemindersDb};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// How often the schedule is checked
+const DIGEST_POLL: Duration = Duration::from_secs(15 * 60);
+
+const WEEK_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Digests kept, about a year of them
+const DIGEST_HISTORY: i64 = 52;
+
+/// Feed entries of followed categories read into one digest
+const MAX_CATEGORY_UPDATES: i64 = 1000;
+
+/// Item names listed per category; the rest are only counted
+const SAMPLE_ITEMS: usize = 5;
+
+/// When the weekly digest is compiled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    /// Day of the week, 0 for Monday to 6 for Sunday
+    pub weekday: u8,
+    /// Hour of the day in local time, 0 to 23
+    pub hour: u8,
+    /// Send a notification when a digest is ready
+    pub notify: bool,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            weekday: 0,
+            hour: 9,
+            notify: true,
+        }
+    }
+}
+
+impl DigestSettings {
+    fn validate(&self) -> Result<()> {
+        if self.weekday > 6 {
+            return Err(XTauriError::InvalidSetting {
+                key: "digest_weekday".to_string(),
+                value: self.weekday.to_string(),
+            });
+        }
+        if self.hour > 23 {
+            return Err(XTauriError::InvalidSetting {
+                key: "digest_hour".to_string(),
+                value: self.hour.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Items that appeared in one followed category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct CategoryDigest {
+    /// `channel`, `movie` or `series`
+    pub content_type: String,
+    pub category_name: String,
+    pub new_items: usize,
+    /// The first few item names
+    pub sample: Vec<String>,
+}
+
+/// Episodes a favorited series gained
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct SeriesDigest {
+    pub series_id: i64,
+    pub series_name: String,
+    pub new_episodes: i64,
+}
+
+/// The week of one Xtream profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct ProfileDigest {
+    pub profile_id: String,
+    pub profile_name: String,
+    pub followed_categories: Vec<CategoryDigest>,
+    pub new_episodes: Vec<SeriesDigest>,
+    /// Reminded programmes starting within the next week
+    pub upcoming_reminders: Vec<XtreamEpgReminder>,
+}
+
+impl ProfileDigest {
+    fn is_empty(&self) -> bool {
+        self.followed_categories.is_empty() && self.new_episodes.is_empty() && self.upcoming_reminders.is_empty()
+    }
+}
+
+/// Disk space the app uses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct StorageUsage {
+    pub database_bytes: u64,
+    pub image_cache_bytes: u64,
+    pub recordings_bytes: u64,
+    pub total_bytes: u64,
+    /// Change since the previous digest; unset for the first one
+    pub growth_bytes: Option<i64>,
+}
+
+/// A weekly summary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct Digest {
+    /// Unix timestamps of the period covered
+    pub period_start: i64,
+    pub generated_at: i64,
+    /// Profiles with something to report
+    pub profiles: Vec<ProfileDigest>,
+    pub storage: StorageUsage,
+}
+
+impl Digest {
+    /// Notification body counting what the digest holds
+    fn summary(&self) -> String {
+        let new_items: usize = self
+            .profiles
+            .iter()
+            .flat_map(|profile| &profile.followed_categories)
+            .map(|category| category.new_items)
+            .sum();
+        let new_episodes: i64 = self
+            .profiles
+            .iter()
+            .flat_map(|profile| &profile.new_episodes)
+            .map(|series| series.new_episodes)
+            .sum();
+        let reminders: usize = self
+            .profiles
+            .iter()
+            .map(|profile| profile.upcoming_reminders.len())
+            .sum();
+        format!(
+            "{} new in followed categories, {} new episodes, {} upcoming reminders",
+            new_items, new_episodes, reminders
+        )
+    }
+}
+
+/// Add the digest settings and history; the settings start with the digest off
+pub fn migrate(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE settings ADD COLUMN digest_enabled BOOLEAN NOT NULL DEFAULT 0;
+         ALTER TABLE settings ADD COLUMN digest_weekday INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE settings ADD COLUMN digest_hour INTEGER NOT NULL DEFAULT 9;
+         ALTER TABLE settings ADD COLUMN digest_notify BOOLEAN NOT NULL DEFAULT 1;
+         CREATE TABLE IF NOT EXISTS digests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            generated_at INTEGER NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            payload TEXT NOT NULL
+         );",
+    )?;
+    Ok(())
+}
+
+fn read_settings(conn: &Connection) -> Result<DigestSettings> {
+    let settings = conn.query_row(
+        "SELECT digest_enabled, digest_weekday, digest_hour, digest_notify FROM settings WHERE id = 1",
+        [],
+        |row| {
+            Ok(DigestSettings {
+                enabled: row.get(0)?,
+                weekday: row.get::<_, i64>(1)?.clamp(0, 6) as u8,
+                hour: row.get::<_, i64>(2)?.clamp(0, 23) as u8,
+                notify: row.get(3)?,
+            })
+        },
+    )?;
+    Ok(settings)
+}
+
+/// Time of the latest scheduled digest at or before `now`, in local time
+fn last_due(settings: &DigestSettings, now: NaiveDateTime) -> NaiveDateTime {
+    let days_back = (now.weekday().num_days_from_monday() + 7 - u32::from(settings.weekday)) % 7;
+    let at_hour = |date: chrono::NaiveDate| date.and_hms_opt(u32::from(settings.hour), 0, 0).unwrap_or_default();
+    let due = at_hour(now.date() - Days::new(u64::from(days_back)));
+    if due > now {
+        at_hour(due.date() - Days::new(7))
+    } else {
+        due
+    }
+}
+
+/// Whether a digest is due, given when the last one was compiled
+fn is_due(settings: &DigestSettings, last_generated: Option<i64>, now: DateTime<Local>) -> bool {
+    if !settings.enabled {
+        return false;
+    }
+    let Some(last_generated) = last_generated else {
+        return true;
+    };
+    let due = last_due(settings, now.naive_local());
+    let last = DateTime::from_timestamp(last_generated, 0)
+        .map(|last| last.with_timezone(&Local).naive_local())
+        .unwrap_or_default();
+    last < due
+}
+
+/// The latest digest and its storage total
+fn latest(conn: &Connection) -> Result<Option<(i64, u64, Digest)>> {
+    let row = conn
+        .query_row(
+            "SELECT generated_at, total_bytes, payload FROM digests ORDER BY id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)),
+        )
+        .optional()?;
+    match row {
+        Some((generated_at, total_bytes, payload)) => {
+            let digest = serde_json::from_str(&payload)
+                .map_err(|e| XTauriError::internal(format!("Stored digest is unreadable: {}", e)))?;
+            Ok(Some((generated_at, total_bytes.max(0) as u64, digest)))
+        }
+        None => Ok(None),
+    }
+}
+
+fn store(conn: &Connection, digest: &Digest) -> Result<()> {
+    let payload = serde_json::to_string(digest).map_err(|e| XTauriError::internal(e.to_string()))?;
+    conn.execute(
+        "INSERT INTO digests (generated_at, total_bytes, payload) VALUES (?1, ?2, ?3)",
+        params![digest.generated_at, digest.storage.total_bytes as i64, payload],
+    )?;
+    conn.execute(
+        "DELETE FROM digests WHERE id NOT IN (SELECT id FROM digests ORDER BY id DESC LIMIT ?1)",
+        [DIGEST_HISTORY],
+    )?;
+    Ok(())
+}
+
+/// New items of a profile's followed categories since `since`, by category
+fn category_digests(conn: &Connection, profile_id: &str, since: i64) -> Result<Vec<CategoryDigest>> {
+    let mut categories: Vec<CategoryDigest> = Vec::new();
+    let updates = followed_categories::updates(conn, profile_id, false, MAX_CATEGORY_UPDATES)?;
+    for update in updates.into_iter().filter(|update| update.added_at >= since) {
+        let existing = categories.iter_mut().find(|category| {
+            category.content_type == update.content_type && category.category_name == update.category_name
+        });
+        let category = match existing {
+            Some(category) => category,
+            None => {
+                categories.push(CategoryDigest {
+                    content_type: update.content_type.clone(),
+                    category_name: update.category_name.clone(),
+                    new_items: 0,
+                    sample: Vec::new(),
+                });
+                categories.last_mut().expect("category was just added")
+            }
+        };
+        category.new_items += 1;
+        if category.sample.len() < SAMPLE_ITEMS {
+            category.sample.push(update.item_name);
+        }
+    }
+    categories.sort_by(|a, b| {
+        b.new_items
+            .cmp(&a.new_items)
+            .then_with(|| a.category_name.cmp(&b.category_name))
+    });
+    Ok(categories)
+}
+
+/// Favorited series of a profile with episodes the provider added since `since`
+fn series_digests(conn: &Connection, profile_id: &str, since: i64) -> Result<Vec<SeriesDigest>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.series_id, s.name, COUNT(e.episode_id)
+         FROM xtream_series s
+         JOIN xtream_episodes e ON e.profile_id = s.profile_id AND e.series_id = s.series_id
+         WHERE s.profile_id = ?1
+           AND CAST(e.added AS INTEGER) >= ?2
+           AND CAST(s.series_id AS TEXT) IN (
+               SELECT TRIM(content_id) FROM xtream_favorites WHERE profile_id = ?1 AND content_type = 'series'
+           )
+         GROUP BY s.series_id, s.name
+         ORDER BY COUNT(e.episode_id) DESC, s.name",
+    )?;
+    let series = stmt
+        .query_map(params![profile_id, since], |row| {
+            Ok(SeriesDigest {
+                series_id: row.get(0)?,
+                series_name: row.get(1)?,
+                new_episodes: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(series)
+}
+
+/// Sum up the period from `since` to `now` for every profile
+fn profile_digests(conn: &Connection, since: i64, now: i64) -> Result<Vec<ProfileDigest>> {
+    let mut stmt = conn.prepare("SELECT id, name FROM xtream_profiles ORDER BY name COLLATE NOCASE")?;
+    let profiles = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut digests = Vec::new();
+    for (profile_id, profile_name) in profiles {
+        let upcoming_reminders = XtreamRemindersDb::get_reminders(conn, &profile_id, Some(now))?
+            .into_iter()
+            .filter(|reminder| reminder.start_timestamp < now + WEEK_SECS)
+            .collect();
+        let digest = ProfileDigest {
+            followed_categories: category_digests(conn, &profile_id, since)?,
+            new_episodes: series_digests(conn, &profile_id, since)?,
+            upcoming_reminders,
+            profile_id,
+            profile_name,
+        };
+        if !digest.is_empty() {
+            digests.push(digest);
+        }
+    }
+    Ok(digests)
+}
+
+/// Bytes of the files directly in `dir`; nothing if it doesn't exist
+fn dir_bytes(dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+fn file_bytes(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+}
+
+/// Disk space in use now, compared with `previous_total`
+fn storage_usage(previous_total: Option<u64>) -> StorageUsage {
+    let database_bytes = crate::database::database_path()
+        .map(|path| file_bytes(&path) + file_bytes(&path.with_extension("sqlite-wal")))
+        .unwrap_or(0);
+    let image_cache_bytes = crate::images::image_cache_dir().map(|dir| dir_bytes(&dir)).unwrap_or(0);
+    let recordings_bytes = crate::recordings::recordings_dir()
+        .map(|dir| dir_bytes(&dir))
+        .unwrap_or(0);
+    let total_bytes = database_bytes + image_cache_bytes + recordings_bytes;
+    StorageUsage {
+        database_bytes,
+        image_cache_bytes,
+        recordings_bytes,
+        total_bytes,
+        growth_bytes: previous_total.map(|previous| total_bytes as i64 - previous as i64),
+    }
+}
+
+/// Compile a digest of the time since the last one, or of the past week
+fn compile(conn: &Connection, now: i64) -> Result<Digest> {
+    let previous = latest(conn)?;
+    let period_start = previous
+        .as_ref()
+        .map_or(now - WEEK_SECS, |(generated_at, _, _)| *generated_at);
+    Ok(Digest {
+        period_start,
+        generated_at: now,
+        profiles: profile_digests(conn, period_start, now)?,
+        storage: storage_usage(previous.map(|(_, total_bytes, _)| total_bytes)),
+    })
+}
+
+/// Compile and keep a digest, announcing it if the settings ask to
+fn generate(app_handle: &AppHandle, settings: &DigestSettings) -> Result<Digest> {
+    let digest = {
+        let db_state = app_handle.state::<DbState>();
+        let conn = db_state
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let digest = compile(&conn, chrono::Utc::now().timestamp())?;
+        store(&conn, &digest)?;
+        digest
+    };
+
+    if settings.notify {
+        notifications::notify(
+            app_handle,
+            Notification {
+                kind: NotificationKind::Digest,
+                title: "Your weekly digest".to_string(),
+                body: digest.summary(),
+                profile_id: None,
+            },
+        );
+    }
+    Ok(digest)
+}
+
+/// Compile the digest each week at the scheduled time while it is enabled
+///
+/// The settings are re-read on every poll, so changes take effect without a
+/// restart. A digest missed while the app was closed is compiled at the next
+/// start.
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    loop {
+        let state = match app_handle.state::<DbState>().db.lock() {
+            Ok(db) => read_settings(&db).and_then(|settings| {
+                let last_generated = latest(&db)?.map(|(generated_at, _, _)| generated_at);
+                Ok((settings, last_generated))
+            }),
+            Err(_) => return,
+        };
+
+        match state {
+            Ok((settings, last_generated)) if is_due(&settings, last_generated, Local::now()) => {
+                match generate(&app_handle, &settings) {
+                    Ok(digest) => println!("[DIGEST] Compiled a digest of {} profiles", digest.profiles.len()),
+                    Err(e) => eprintln!("[DIGEST] Failed to compile the weekly digest: {}", e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("[DIGEST] Failed to read digest settings: {}", e),
+        }
+
+        tokio::time::sleep(DIGEST_POLL).await;
+    }
+}
+
+#[tauri::command]
+pub fn get_digest_settings(state: State<DbState>) -> std::result::Result<DigestSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    read_settings(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_digest_settings(state: State<DbState>, settings: DigestSettings) -> std::result::Result<(), String> {
+    settings.validate().map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET digest_enabled = ?1, digest_weekday = ?2, digest_hour = ?3, digest_notify = ?4
+         WHERE id = 1",
+        params![settings.enabled, settings.weekday, settings.hour, settings.notify],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The most recent digest, if one was compiled
+#[tauri::command]
+pub fn get_latest_digest(state: State<DbState>) -> std::result::Result<Option<Digest>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(latest(&db).map_err(|e| e.to_string())?.map(|(_, _, digest)| digest))
+}
+
+/// What the next digest would hold so far; nothing is stored or sent
+#[tauri::command]
+pub fn preview_digest(state: State<DbState>) -> std::result::Result<Digest, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    compile(&db, chrono::Utc::now().timestamp()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_last_due_is_the_latest_scheduled_slot() {
+        // 2026-10-14 is a Wednesday
+        let settings = DigestSettings {
+            enabled: true,
+            weekday: 2,
+            hour: 9,
+            notify: true,
+        };
+        assert_eq!(last_due(&settings, at(2026, 10, 14, 10)), at(2026, 10, 14, 9));
+        assert_eq!(last_due(&settings, at(2026, 10, 14, 8)), at(2026, 10, 7, 9));
+        assert_eq!(last_due(&settings, at(2026, 10, 18, 12)), at(2026, 10, 14, 9));
+
+        let monday = DigestSettings { weekday: 0, ..settings };
+        assert_eq!(last_due(&monday, at(2026, 10, 14, 10)), at(2026, 10, 12, 9));
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_schedule() {
+        assert!(DigestSettings::default().validate().is_ok());
+        assert!(DigestSettings {
+            weekday: 7,
+            ..DigestSettings::default()
+        }
+        .validate()
+        .is_err());
+        assert!(DigestSettings {
+            hour: 24,
+            ..DigestSettings::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_compile_sums_up_the_week() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (id INTEGER PRIMARY KEY);
+             INSERT INTO settings (id) VALUES (1);
+             CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, name TEXT NOT NULL, url TEXT NOT NULL,
+                 username TEXT NOT NULL, encrypted_credentials BLOB NOT NULL);
+             INSERT INTO xtream_profiles VALUES ('p1', 'Home', 'http://test.com', 'user', X'00'),
+                 ('p2', 'Quiet', 'http://test.com', 'user', X'00');
+             CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT);
+             INSERT INTO xtream_favorites VALUES ('p1', 'series', '10');
+             CREATE TABLE xtream_epg_reminders (id TEXT PRIMARY KEY, profile_id TEXT, channel_id TEXT,
+                 channel_name TEXT, title TEXT, description TEXT, start_timestamp INTEGER,
+                 stop_timestamp INTEGER, created_at TEXT, notified INTEGER DEFAULT 0);",
+        )
+        .unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        migrate(&conn).unwrap();
+
+        let now = 100 * WEEK_SECS;
+        conn.execute_batch(&format!(
+            "INSERT INTO xtream_series (profile_id, series_id, name) VALUES ('p1', 10, 'Severance'), ('p1', 11, 'Other');
+             INSERT INTO xtream_episodes (profile_id, series_id, episode_id, season_number, episode_num, added) VALUES
+                 ('p1', 10, 'e1', 1, '1', '{old}'), ('p1', 10, 'e2', 2, '1', '{new}'), ('p1', 10, 'e3', 2, '2', '{new}'),
+                 ('p1', 11, 'e4', 1, '1', '{new}');
+             INSERT INTO xtream_followed_category_updates
+                 (profile_id, content_type, category_id, category_name, item_id, item_name, added_at) VALUES
+                 ('p1', 'movie', '7', 'UFC PPV', 1, 'UFC 300', {old}), ('p1', 'movie', '7', 'UFC PPV', 2, 'UFC 301', {new});
+             INSERT INTO xtream_epg_reminders VALUES
+                 ('r1', 'p1', '5', 'Sports', 'Final', NULL, {soon}, {soon_end}, '', 0),
+                 ('r2', 'p1', '5', 'Sports', 'Next season', NULL, {later}, {later_end}, '', 0);",
+            old = now - 2 * WEEK_SECS,
+            new = now - 3600,
+            soon = now + 3600,
+            soon_end = now + 7200,
+            later = now + 2 * WEEK_SECS,
+            later_end = now + 2 * WEEK_SECS + 3600,
+        ))
+        .unwrap();
+
+        let digest = compile(&conn, now).unwrap();
+        assert_eq!(digest.period_start, now - WEEK_SECS);
+        assert_eq!(digest.storage.growth_bytes, None);
+        assert_eq!(digest.profiles.len(), 1, "profiles with nothing to report are left out");
+
+        let profile = &digest.profiles[0];
+        assert_eq!(profile.profile_name, "Home");
+        assert_eq!(
+            profile.followed_categories,
+            vec![CategoryDigest {
+                content_type: "movie".to_string(),
+                category_name: "UFC PPV".to_string(),
+                new_items: 1,
+                sample: vec!["UFC 301".to_string()],
+            }]
+        );
+        assert_eq!(
+            profile.new_episodes,
+            vec![SeriesDigest {
+                series_id: 10,
+                series_name: "Severance".to_string(),
+                new_episodes: 2,
+            }]
+        );
+        let reminders: Vec<&str> = profile.upcoming_reminders.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(reminders, vec!["Final"]);
+        assert_eq!(
+            digest.summary(),
+            "1 new in followed categories, 2 new episodes, 1 upcoming reminders"
+        );
+
+        // The next digest starts where this one ended and measures growth from it
+        store(&conn, &digest).unwrap();
+        let next = compile(&conn, now + WEEK_SECS).unwrap();
+        assert_eq!(next.period_start, now);
+        assert!(next.storage.growth_bytes.is_some());
+        assert_eq!(latest(&conn).unwrap().unwrap().2, digest);
+    }
+}
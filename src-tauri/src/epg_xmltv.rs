@@ -0,0 +1,515 @@
+// XMLTV guide data for M3U playlists
+//
+// Xtream providers serve their guide through the API, but an M3U playlist
+// usually points to an XMLTV file instead, either in its `url-tvg` header or
+// as a URL the user enters. The file is downloaded (plain or gzipped), its
+// programmes are parsed and stored per channel list in `xmltv_programmes`,
+// keyed by the XMLTV channel id that the playlist's `tvg-id` refers to.
+use crate::concurrency::{self, Subsystem};
+use crate::content_cache::epg::EpgProgramme;
+use crate::error::{Result, XTauriError};
+use crate::migrations::has_column;
+use crate::state::DbState;
+use chrono::{FixedOffset, NaiveDateTime, TimeZone, Utc};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::time::Duration;
+use tauri::State;
+
+/// Programmes that ended longer ago than this are not stored
+const KEEP_PAST_SECS: i64 = 24 * 3600;
+
+/// Window `get_channel_epg` returns when no end is given
+const DEFAULT_WINDOW_SECS: i64 = 24 * 3600;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A programme read from an XMLTV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmltvProgramme {
+    /// XMLTV channel id, matched against a playlist channel's `tvg-id`
+    pub channel_id: String,
+    pub start: i64,
+    pub stop: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    /// Image from the programme's `<icon src>`
+    pub icon: Option<String>,
+}
+
+/// A channel listed in an XMLTV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmltvChannel {
+    pub id: String,
+    pub display_names: Vec<String>,
+}
+
+/// Parse an XMLTV time such as `20240101203000 +0100`; times without an offset are UTC
+fn parse_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let local = NaiveDateTime::parse_from_str(value.get(..14)?, "%Y%m%d%H%M%S").ok()?;
+    let offset = match value[14..].trim() {
+        "" => 0,
+        zone => {
+            let sign = match zone.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            let digits = zone.get(1..5)?;
+            let hours: i32 = digits.get(..2)?.parse().ok()?;
+            let minutes: i32 = digits.get(2..)?.parse().ok()?;
+            sign * (hours * 3600 + minutes * 60)
+        }
+    };
+    let zone = FixedOffset::east_opt(offset)?;
+    Some(zone.from_local_datetime(&local).single()?.timestamp())
+}
+
+/// Add the programme image column to `xmltv_programmes`
+pub fn migrate(conn: &Connection) -> Result<()> {
+    if !has_column(conn, "xmltv_programmes", "icon_url")? {
+        conn.execute("ALTER TABLE xmltv_programmes ADD COLUMN icon_url TEXT", [])?;
+    }
+    Ok(())
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|value| value.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// Read every programme with a channel, a valid start and stop and a title
+pub fn parse_xmltv(xml: &[u8]) -> Result<Vec<XmltvProgramme>> {
+    let mut reader = Reader::from_reader(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut programmes = Vec::new();
+    let mut current: Option<XmltvProgramme> = None;
+    // Child element of the current programme whose text is being read
+    let mut field: Option<Vec<u8>> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| XTauriError::internal(format!("Invalid XMLTV data: {}", e)))?;
+        match event {
+            Event::Start(element) if element.name().as_ref() == b"programme" => {
+                let channel_id = attribute(&element, "channel");
+                let start = attribute(&element, "start").and_then(|value| parse_time(&value));
+                let stop = attribute(&element, "stop").and_then(|value| parse_time(&value));
+                current = match (channel_id, start, stop) {
+                    (Some(channel_id), Some(start), Some(stop)) if stop > start => Some(XmltvProgramme {
+                        channel_id,
+                        start,
+                        stop,
+                        title: String::new(),
+                        description: None,
+                        category: None,
+                        icon: None,
+                    }),
+                    _ => None,
+                };
+            }
+            // Usually written as an empty element, so handled before fields
+            Event::Empty(element) | Event::Start(element) if element.name().as_ref() == b"icon" => {
+                let src = attribute(&element, "src").map(|src| src.trim().to_string());
+                if let Some(programme) = current.as_mut().filter(|programme| programme.icon.is_none()) {
+                    programme.icon = src.filter(|src| src.starts_with("http://") || src.starts_with("https://"));
+                }
+            }
+            Event::Start(element) if current.is_some() => {
+                field = Some(element.name().as_ref().to_vec());
+            }
+            Event::Text(_) | Event::CData(_) => {
+                let (Some(programme), Some(name)) = (current.as_mut(), field.as_deref()) else {
+                    buf.clear();
+                    continue;
+                };
+                let text = match &event {
+                    Event::Text(text) => text.unescape().map(|text| text.into_owned()).unwrap_or_default(),
+                    Event::CData(data) => String::from_utf8_lossy(data).into_owned(),
+                    _ => unreachable!(),
+                };
+                // Only the first of each, which XMLTV lists in the preferred language
+                match name {
+                    b"title" if programme.title.is_empty() => programme.title = text,
+                    b"desc" if programme.description.is_none() => programme.description = Some(text),
+                    b"category" if programme.category.is_none() => programme.category = Some(text),
+                    _ => {}
+                }
+            }
+            Event::End(element) if element.name().as_ref() == b"programme" => {
+                if let Some(programme) = current.take().filter(|programme| !programme.title.is_empty()) {
+                    programmes.push(programme);
+                }
+                field = None;
+            }
+            Event::End(_) => field = None,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(programmes)
+}
+
+/// Read every channel with an id and at least one display name
+pub fn parse_xmltv_channels(xml: &[u8]) -> Result<Vec<XmltvChannel>> {
+    let mut reader = Reader::from_reader(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut channels = Vec::new();
+    let mut current: Option<XmltvChannel> = None;
+    let mut in_display_name = false;
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| XTauriError::internal(format!("Invalid XMLTV data: {}", e)))?;
+        match event {
+            Event::Start(element) if element.name().as_ref() == b"channel" => {
+                current = attribute(&element, "id")
+                    .filter(|id| !id.trim().is_empty())
+                    .map(|id| XmltvChannel { id, display_names: Vec::new() });
+            }
+            Event::Start(element) if current.is_some() => {
+                in_display_name = element.name().as_ref() == b"display-name";
+            }
+            Event::Text(text) if in_display_name => {
+                let name = text.unescape().map(|text| text.trim().to_string()).unwrap_or_default();
+                if let Some(channel) = current.as_mut().filter(|_| !name.is_empty()) {
+                    channel.display_names.push(name);
+                }
+            }
+            Event::End(element) if element.name().as_ref() == b"channel" => {
+                if let Some(channel) = current.take().filter(|channel| !channel.display_names.is_empty()) {
+                    channels.push(channel);
+                }
+                in_display_name = false;
+            }
+            Event::End(_) => in_display_name = false,
+            // Programmes follow the channel list
+            Event::Start(element) if element.name().as_ref() == b"programme" => break,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(channels)
+}
+
+/// The guide URL a playlist names in its `#EXTM3U` header, if any
+pub fn header_epg_url(m3u: &str) -> Option<String> {
+    let header = m3u.lines().next()?.trim_start_matches('\u{feff}');
+    if !header.starts_with("#EXTM3U") {
+        return None;
+    }
+    ["url-tvg=\"", "x-tvg-url=\""].iter().find_map(|name| {
+        let start = header.find(name)? + name.len();
+        let value = &header[start..start + header[start..].find('"')?];
+        // Some playlists list several guides; the first is used
+        let url = value.split(',').next()?.trim();
+        (!url.is_empty()).then(|| url.to_string())
+    })
+}
+
+/// Replace a channel list's stored programmes; returns how many were kept
+pub fn store_programmes(conn: &mut Connection, list_id: i32, programmes: &[XmltvProgramme], now: i64) -> Result<usize> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM xmltv_programmes WHERE channel_list_id = ?1", params![list_id])?;
+    let mut stored = 0;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO xmltv_programmes
+                (channel_list_id, channel_id, start_timestamp, stop_timestamp, title, description, category, icon_url)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for programme in programmes.iter().filter(|programme| programme.stop >= now - KEEP_PAST_SECS) {
+            stmt.execute(params![
+                list_id,
+                programme.channel_id,
+                programme.start,
+                programme.stop,
+                programme.title,
+                programme.description,
+                programme.category,
+                programme.icon,
+            ])?;
+            stored += 1;
+        }
+    }
+    tx.execute(
+        "UPDATE channel_lists SET epg_last_fetched = ?1 WHERE id = ?2",
+        params![now, list_id],
+    )?;
+    tx.commit()?;
+    Ok(stored)
+}
+
+/// Programmes on one channel that overlap `start`..`end`, in order
+pub fn channel_programmes(conn: &Connection, list_id: i32, channel_id: &str, start: i64, end: i64) -> Result<Vec<EpgProgramme>> {
+    let mut stmt = conn.prepare(
+        "SELECT title, description, start_timestamp, stop_timestamp, icon_url FROM xmltv_programmes
+         WHERE channel_list_id = ?1 AND channel_id = ?2 AND stop_timestamp > ?3 AND start_timestamp < ?4
+         ORDER BY start_timestamp",
+    )?;
+    let programmes = stmt
+        .query_map(params![list_id, channel_id, start, end], |row| {
+            Ok(EpgProgramme {
+                title: row.get(0)?,
+                description: row.get(1)?,
+                start_timestamp: row.get(2)?,
+                stop_timestamp: row.get(3)?,
+                synthetic: false,
+                icon_url: row.get(4)?,
+                cached_icon: None,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(programmes)
+}
+
+/// The list's guide URL, falling back to the one in its cached playlist header
+fn epg_url(conn: &Connection, list_id: i32) -> Result<Option<String>> {
+    let row: Option<(Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT epg_url, filepath FROM channel_lists WHERE id = ?1",
+            params![list_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let (url, filepath) = row.ok_or_else(|| XTauriError::InvalidPlaylistId { id: list_id.to_string() })?;
+    if url.is_some() {
+        return Ok(url);
+    }
+    let header_url = filepath.and_then(|filepath| {
+        let path = dirs::data_dir()?.join("xtauri/channel_lists").join(filepath);
+        let content = std::fs::read_to_string(path).ok()?;
+        header_epg_url(&content)
+    });
+    Ok(header_url)
+}
+
+async fn download(url: &str) -> Result<Vec<u8>> {
+    let client = crate::connection::client_builder().build()?;
+    let response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    let body = crate::transfer::read_body(response, crate::transfer::SOURCE_EPG).await?;
+    // `.xml.gz` guides are usually served as a gzip file rather than gzip-encoded
+    if body.starts_with(&[0x1f, 0x8b]) {
+        return crate::transfer::decode_body(Some("gzip"), &body);
+    }
+    Ok(body)
+}
+
+/// Set the XMLTV guide URL of a channel list; `None` goes back to the playlist's own
+#[tauri::command]
+pub fn set_playlist_epg_url(state: State<DbState>, id: i32, url: Option<String>) -> std::result::Result<(), String> {
+    let url = url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty());
+    if let Some(url) = &url {
+        let parsed = url::Url::parse(url).map_err(|_| XTauriError::InvalidUrl { url: url.clone() }.to_string())?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(XTauriError::InvalidUrl { url: url.clone() }.to_string());
+        }
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let updated = db
+        .execute("UPDATE channel_lists SET epg_url = ?1 WHERE id = ?2", params![url, id])
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(XTauriError::InvalidPlaylistId { id: id.to_string() }.to_string());
+    }
+    Ok(())
+}
+
+/// Download a channel list's XMLTV guide and store its programmes
+///
+/// Uses the URL set with `set_playlist_epg_url`, or the `url-tvg` in the
+/// playlist header. Returns the number of programmes stored.
+#[tauri::command]
+pub async fn refresh_playlist_epg(state: State<'_, DbState>, id: i32) -> std::result::Result<usize, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
+    let url = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        epg_url(&db, id).map_err(|e| e.to_string())?
+    }
+    .ok_or("This playlist has no XMLTV guide URL")?;
+
+    let body = download(&url).await.map_err(|e| format!("Failed to download guide: {}", e))?;
+    let programmes = tauri::async_runtime::spawn_blocking(move || parse_xmltv(&body))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    store_programmes(&mut db, id, &programmes, Utc::now().timestamp()).map_err(|e| e.to_string())
+}
+
+/// Guide for one playlist channel by its `tvg-id`; the next 24 hours unless a window is given
+#[tauri::command]
+pub async fn get_channel_epg(
+    state: State<'_, DbState>,
+    id: i32,
+    tvg_id: String,
+    start_timestamp: Option<i64>,
+    end_timestamp: Option<i64>,
+) -> std::result::Result<Vec<EpgProgramme>, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
+    let start = start_timestamp.unwrap_or_else(|| Utc::now().timestamp());
+    let end = end_timestamp.unwrap_or(start + DEFAULT_WINDOW_SECS);
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    channel_programmes(&db, id, &tvg_id, start, end)
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XMLTV: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tv generator-info-name="test">
+  <channel id="news.uk"><display-name>News</display-name></channel>
+  <programme start="20240101120000 +0100" stop="20240101130000 +0100" channel="news.uk">
+    <title lang="en">Midday &amp; More</title>
+    <title lang="de">Mittag</title>
+    <desc><![CDATA[Headlines <live>]]></desc>
+    <category>News</category>
+    <icon src="https://img.test/midday.jpg" />
+  </programme>
+  <programme start="20240101130000" stop="20240101140000" channel="news.uk">
+    <title>Weather</title>
+  </programme>
+  <programme start="20240101140000" channel="news.uk"><title>No stop</title></programme>
+  <programme start="20240101150000" stop="20240101160000" channel="news.uk"></programme>
+</tv>"#;
+
+    #[test]
+    fn test_parse_xmltv() {
+        let programmes = parse_xmltv(XMLTV.as_bytes()).unwrap();
+        assert_eq!(programmes.len(), 2);
+
+        let first = &programmes[0];
+        assert_eq!(first.channel_id, "news.uk");
+        // 12:00 at +01:00 is 11:00 UTC
+        assert_eq!(first.start, 1_704_106_800);
+        assert_eq!(first.stop - first.start, 3600);
+        assert_eq!(first.title, "Midday & More");
+        assert_eq!(first.description.as_deref(), Some("Headlines <live>"));
+        assert_eq!(first.category.as_deref(), Some("News"));
+        assert_eq!(first.icon.as_deref(), Some("https://img.test/midday.jpg"));
+
+        assert_eq!(programmes[1].start, 1_704_114_000);
+        assert_eq!(programmes[1].description, None);
+        assert_eq!(programmes[1].icon, None);
+
+        assert_eq!(parse_time("20240101120000 -0230"), Some(1_704_119_400));
+        assert_eq!(parse_time("2024-01-01"), None);
+    }
+
+    #[test]
+    fn test_parse_xmltv_channels() {
+        let xml = r#"<tv>
+  <channel id="bbc1.uk"><display-name lang="en">BBC One</display-name><display-name>BBC 1 HD</display-name></channel>
+  <channel id=""><display-name>No id</display-name></channel>
+  <channel id="blank.uk"><icon src="http://img/blank.png"/></channel>
+  <programme start="20240101120000" stop="20240101130000" channel="bbc1.uk"><title>News</title></programme>
+</tv>"#;
+        let channels = parse_xmltv_channels(xml.as_bytes()).unwrap();
+        assert_eq!(
+            channels,
+            vec![XmltvChannel {
+                id: "bbc1.uk".to_string(),
+                display_names: vec!["BBC One".to_string(), "BBC 1 HD".to_string()],
+            }]
+        );
+        assert_eq!(parse_xmltv_channels(XMLTV.as_bytes()).unwrap()[0].display_names, vec!["News"]);
+    }
+
+    #[test]
+    fn test_header_epg_url() {
+        assert_eq!(
+            header_epg_url("#EXTM3U url-tvg=\"http://guide.test/a.xml.gz,http://guide.test/b.xml\"\n#EXTINF:-1,News\n"),
+            Some("http://guide.test/a.xml.gz".to_string())
+        );
+        assert_eq!(
+            header_epg_url("\u{feff}#EXTM3U x-tvg-url=\"http://guide.test/epg.xml\""),
+            Some("http://guide.test/epg.xml".to_string())
+        );
+        assert_eq!(header_epg_url("#EXTM3U\n#EXTINF:-1 url-tvg=\"http://no\",News"), None);
+    }
+
+    #[test]
+    fn test_store_and_query_programmes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE channel_lists (id INTEGER PRIMARY KEY, epg_last_fetched INTEGER);
+             INSERT INTO channel_lists (id) VALUES (1);
+             CREATE TABLE xmltv_programmes (
+                channel_list_id INTEGER NOT NULL,
+                channel_id TEXT NOT NULL,
+                start_timestamp INTEGER NOT NULL,
+                stop_timestamp INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                category TEXT,
+                PRIMARY KEY (channel_list_id, channel_id, start_timestamp)
+             );",
+        )
+        .unwrap();
+        migrate(&conn).unwrap();
+
+        let programme = |start: i64, title: &str| XmltvProgramme {
+            channel_id: "news.uk".to_string(),
+            start,
+            stop: start + 3600,
+            title: title.to_string(),
+            description: None,
+            category: None,
+            icon: Some(format!("https://img.test/{}.jpg", start)),
+        };
+        let now = 1_000_000;
+        let programmes = [
+            programme(now - KEEP_PAST_SECS - 7200, "Long gone"),
+            programme(now - 1800, "On now"),
+            programme(now + 1800, "Next"),
+            programme(now + 7200, "Later"),
+        ];
+        assert_eq!(store_programmes(&mut conn, 1, &programmes, now).unwrap(), 3);
+
+        let titles: Vec<String> = channel_programmes(&conn, 1, "news.uk", now, now + 3600)
+            .unwrap()
+            .into_iter()
+            .map(|programme| programme.title)
+            .collect();
+        assert_eq!(titles, vec!["On now", "Next"]);
+        let icon = channel_programmes(&conn, 1, "news.uk", now, now + 1).unwrap().remove(0).icon_url;
+        assert_eq!(icon, Some(format!("https://img.test/{}.jpg", now - 1800)));
+        assert!(channel_programmes(&conn, 1, "other", now, now + 3600).unwrap().is_empty());
+
+        // A refresh replaces what was stored before
+        assert_eq!(store_programmes(&mut conn, 1, &programmes[3..], now).unwrap(), 1);
+        assert_eq!(channel_programmes(&conn, 1, "news.uk", 0, i64::MAX).unwrap().len(), 1);
+        let fetched: i64 = conn
+            .query_row("SELECT epg_last_fetched FROM channel_lists WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fetched, now);
+    }
+}
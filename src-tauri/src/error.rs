@@ -13,9 +13,12 @@ pub enum XTauriError {
     #[error("Database migration failed: {reason}")]
     DatabaseMigration { reason: String },
 
+    #[error("Database is busy: {operation} (gave up after {attempts} attempts)")]
+    DatabaseBusy { operation: String, attempts: u32 },
+
     // Network errors
     #[error("Network request failed: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(reqwest::Error),
 
     #[error("Failed to fetch playlist from URL: {url}")]
     PlaylistFetch { url: String },
@@ -83,6 +86,12 @@ pub enum XTauriError {
     #[error("Operation was cancelled: {operation}")]
     Cancelled { operation: String },
 
+    #[error("Operation stalled: {operation} made no progress for {idle_secs}s")]
+    Stalled { operation: String, idle_secs: u64 },
+
+    #[error("Overloaded: too many {subsystem} requests waiting ({queued}), retry later")]
+    Overloaded { subsystem: String, queued: usize },
+
     // Application state errors
     #[error("Application not initialized")]
     NotInitialized,
@@ -126,6 +135,12 @@ pub enum XTauriError {
     Unknown,
 }
 
+impl From<reqwest::Error> for XTauriError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::network(error)
+    }
+}
+
 impl XTauriError {
     /// Create a new database initialization error
     pub fn database_init(reason: impl Into<String>) -> Self {
@@ -134,6 +149,14 @@ impl XTauriError {
         }
     }
 
+    /// Create a new database busy error
+    pub fn database_busy(operation: impl Into<String>, attempts: u32) -> Self {
+        Self::DatabaseBusy {
+            operation: operation.into(),
+            attempts,
+        }
+    }
+
     /// Create a new directory creation error
     pub fn directory_creation(path: impl Into<String>) -> Self {
         Self::DirectoryCreation { path: path.into() }
@@ -170,6 +193,14 @@ impl XTauriError {
         }
     }
 
+    /// Create a new network error, without the request URL
+    ///
+    /// Xtream URLs carry the account's username and password in their query,
+    /// and error messages end up in logs and the sync history.
+    pub fn network(error: reqwest::Error) -> Self {
+        Self::Network(error.without_url())
+    }
+
     /// Create a new timeout error
     pub fn timeout(operation: impl Into<String>) -> Self {
         Self::Timeout {
@@ -177,6 +208,22 @@ impl XTauriError {
         }
     }
 
+    /// Create a new stalled error for a transfer the watchdog aborted
+    pub fn stalled(operation: impl Into<String>, idle_secs: u64) -> Self {
+        Self::Stalled {
+            operation: operation.into(),
+            idle_secs,
+        }
+    }
+
+    /// Create a new overloaded error
+    pub fn overloaded(subsystem: impl Into<String>, queued: usize) -> Self {
+        Self::Overloaded {
+            subsystem: subsystem.into(),
+            queued,
+        }
+    }
+
     /// Create a new internal error
     pub fn internal(reason: impl Into<String>) -> Self {
         Self::Internal {
@@ -243,11 +290,17 @@ impl XTauriError {
             // Timeout and cancellation errors are recoverable
             XTauriError::Timeout { .. } | XTauriError::Cancelled { .. } => true,
 
+            // A stalled transfer usually goes through when retried
+            XTauriError::Stalled { .. } => true,
+
+            // An overloaded subsystem takes new calls again once its queue drains
+            XTauriError::Overloaded { .. } => true,
+
             // Cache errors are usually recoverable
             XTauriError::Cache { .. } | XTauriError::SearchCache { .. } => true,
 
             // Lock acquisition failures might be recoverable
-            XTauriError::LockAcquisition { .. } => true,
+            XTauriError::LockAcquisition { .. } | XTauriError::DatabaseBusy { .. } => true,
 
             // Xtream authentication errors might be recoverable
             XTauriError::XtreamAuthenticationFailed { .. } | XTauriError::XtreamApiError { .. } => {
@@ -269,6 +322,9 @@ impl XTauriError {
             XTauriError::DatabaseInitialization { .. } => {
                 "Failed to initialize database. Please check your permissions.".to_string()
             }
+            XTauriError::DatabaseBusy { .. } => {
+                "The database is busy. Please try again in a moment.".to_string()
+            }
             XTauriError::Network(_) => {
                 "Network connection failed. Please check your internet connection.".to_string()
             }
@@ -288,6 +344,12 @@ impl XTauriError {
                 "Invalid URL format. Please check the URL and try again.".to_string()
             }
             XTauriError::Timeout { .. } => "Operation timed out. Please try again.".to_string(),
+            XTauriError::Stalled { .. } => {
+                "The server stopped sending data. Please try again.".to_string()
+            }
+            XTauriError::Overloaded { .. } => {
+                "Too many requests at once. Please try again in a moment.".to_string()
+            }
             XTauriError::NotFound { .. } => "Requested item not found.".to_string(),
             XTauriError::XtreamAuthenticationFailed { .. } => {
                 "Failed to authenticate with Xtream server. Please check your credentials."
@@ -319,10 +381,12 @@ impl XTauriError {
         match self {
             XTauriError::Database(_)
             | XTauriError::DatabaseInitialization { .. }
-            | XTauriError::DatabaseMigration { .. } => "database",
+            | XTauriError::DatabaseMigration { .. }
+            | XTauriError::DatabaseBusy { .. } => "database",
             XTauriError::Network(_)
             | XTauriError::PlaylistFetch { .. }
-            | XTauriError::FileDownload { .. } => "network",
+            | XTauriError::FileDownload { .. }
+            | XTauriError::Stalled { .. } => "network",
             XTauriError::FileSystem(_)
             | XTauriError::DirectoryCreation { .. }
             | XTauriError::DataDirectoryAccess
@@ -340,7 +404,8 @@ impl XTauriError {
             | XTauriError::InvalidUrl { .. } => "validation",
             XTauriError::LockAcquisition { .. }
             | XTauriError::Timeout { .. }
-            | XTauriError::Cancelled { .. } => "concurrency",
+            | XTauriError::Cancelled { .. }
+            | XTauriError::Overloaded { .. } => "concurrency",
             XTauriError::NotInitialized
             | XTauriError::FeatureNotAvailable { .. }
             | XTauriError::NotFound { .. } => "state",
@@ -392,6 +457,20 @@ impl From<XTauriError> for InvokeError {
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_network_errors_leave_out_the_url() {
+        // Nothing listens on port 1, so the request fails to connect
+        let error = reqwest::Client::new()
+            .get("http://127.0.0.1:1/player_api.php?username=user&password=secret")
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("secret"));
+
+        let message = XTauriError::from(error).to_string();
+        assert!(!message.contains("secret"), "{}", message);
+    }
+
     #[test]
     fn test_error_display() {
         let error = XTauriError::database_init("Connection failed");
@@ -0,0 +1,216 @@
+// Catalog of the events emitted to the frontend
+//
+// Every event is a variant of `AppEvent` carrying its payload, and goes out
+// through `emit`, so an event's name and shape are defined in one place.
+// `src/types/events.ts` declares the same events for the frontend; the test at
+// the bottom fails when the two list different event names.
+use crate::channels::ChannelLoadingStatus;
+use crate::content_cache::ProfileSyncProgress;
+use crate::expiry::ContentExpiry;
+use crate::image_prefetch::ImagePrefetchReport;
+use crate::images::ImageFetched;
+use crate::notifications::InboxNotification;
+use crate::playlists::{PlaylistFetchStatus, WatchImportEvent};
+use crate::power::PowerState;
+#[cfg(feature = "recordings")]
+use crate::recordings::Recording;
+use crate::remote_control::RemotePlayRequest;
+use crate::search::SearchProgress;
+use crate::settings_profiles::SettingChange;
+use crate::updates::UpdateInfo;
+use crate::user_config::EffectiveConfig;
+use crate::xtream::search::ProviderSearchResults;
+use crate::xtream::startup_auth::AccountStatus;
+use crate::xtream::FavoritesChange;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const HISTORY_LOADING: &str = "history_loading";
+pub const SEARCH_PROGRESS: &str = "search_progress";
+pub const GROUPS_LOADING: &str = "groups_loading";
+pub const CHANNEL_LOADING: &str = "channel_loading";
+pub const IMAGE_FETCHED: &str = "image_fetched";
+pub const PLAYLIST_FETCH_STATUS: &str = "playlist_fetch_status";
+pub const PLAYLIST_WATCH_IMPORT: &str = "playlist_watch_import";
+pub const FAVORITES_CHANGED: &str = "favorites_changed";
+pub const XTREAM_STARTUP_AUTH: &str = "xtream_startup_auth";
+pub const XTREAM_ACCOUNT_EXPIRED: &str = "xtream_account_expired";
+pub const REMOTE_CONTROL_PLAY: &str = "remote_control_play";
+pub const UPDATE_AVAILABLE: &str = "update_available";
+pub const PROVIDER_SEARCH_RESULTS: &str = "provider_search_results";
+pub const CONTENT_EXPIRED: &str = "content_expired";
+pub const POWER_STATE_CHANGED: &str = "power_state_changed";
+pub const NOTIFICATION_ADDED: &str = "notification_added";
+pub const SYNC_PROGRESS: &str = "sync://progress";
+pub const SYNC_COMPLETE: &str = "sync://complete";
+pub const SETTING_CHANGED: &str = "setting_changed";
+pub const CONFIG_RELOADED: &str = "config_reloaded";
+// Still declared by the frontend, which doesn't know the build features
+#[cfg_attr(not(feature = "recordings"), allow(dead_code))]
+pub const RECORDING_UPDATED: &str = "recording_updated";
+pub const IMAGE_PREFETCH_COMPLETE: &str = "image_prefetch_complete";
+
+/// An event and its payload
+///
+/// Serializes as the bare payload, which is what listeners receive.
+#[derive(Clone, Serialize)]
+#[serde(untagged)]
+pub enum AppEvent {
+    /// Status text while the playback history loads
+    HistoryLoading(String),
+    SearchProgress(SearchProgress),
+    GroupsLoading(ChannelLoadingStatus),
+    ChannelLoading(ChannelLoadingStatus),
+    /// One image of a preload batch is in the cache, or failed
+    ImageFetched(ImageFetched),
+    PlaylistFetchStatus(PlaylistFetchStatus),
+    /// A file in the playlist watch folder was imported
+    PlaylistWatchImport(WatchImportEvent),
+    FavoritesChanged(FavoritesChange),
+    /// Sent after every startup account check
+    XtreamStartupAuth(AccountStatus),
+    /// Sent when the account expired since the previous run
+    XtreamAccountExpired(AccountStatus),
+    /// A remote asked to start playback; the frontend owns the player
+    RemoteControlPlay(RemotePlayRequest),
+    UpdateAvailable(UpdateInfo),
+    /// Follow-up to a tiered search with the provider's extra matches
+    ProviderSearchResults(ProviderSearchResults),
+    /// A channel list or profile passed its expiry time and its content is hidden
+    ContentExpired(ContentExpiry),
+    /// Low-power mode or the power source changed
+    PowerStateChanged(PowerState),
+    /// A notification was added to the in-app inbox
+    NotificationAdded(InboxNotification),
+    /// A content sync moved on to another step
+    SyncProgress(ProfileSyncProgress),
+    /// A content sync finished, with its final status and counts
+    SyncComplete(ProfileSyncProgress),
+    /// A setting changed because another settings profile was switched to
+    SettingChanged(SettingChange),
+    /// The configuration file changed and was read again
+    ConfigReloaded(EffectiveConfig),
+    /// A recording started or ended
+    #[cfg(feature = "recordings")]
+    RecordingUpdated(Recording),
+    /// Images were prefetched after a sync, with how much of the budget was used
+    ImagePrefetchComplete(ImagePrefetchReport),
+}
+
+impl AppEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppEvent::HistoryLoading(_) => HISTORY_LOADING,
+            AppEvent::SearchProgress(_) => SEARCH_PROGRESS,
+            AppEvent::GroupsLoading(_) => GROUPS_LOADING,
+            AppEvent::ChannelLoading(_) => CHANNEL_LOADING,
+            AppEvent::ImageFetched(_) => IMAGE_FETCHED,
+            AppEvent::PlaylistFetchStatus(_) => PLAYLIST_FETCH_STATUS,
+            AppEvent::PlaylistWatchImport(_) => PLAYLIST_WATCH_IMPORT,
+            AppEvent::FavoritesChanged(_) => FAVORITES_CHANGED,
+            AppEvent::XtreamStartupAuth(_) => XTREAM_STARTUP_AUTH,
+            AppEvent::XtreamAccountExpired(_) => XTREAM_ACCOUNT_EXPIRED,
+            AppEvent::RemoteControlPlay(_) => REMOTE_CONTROL_PLAY,
+            AppEvent::UpdateAvailable(_) => UPDATE_AVAILABLE,
+            AppEvent::ProviderSearchResults(_) => PROVIDER_SEARCH_RESULTS,
+            AppEvent::ContentExpired(_) => CONTENT_EXPIRED,
+            AppEvent::PowerStateChanged(_) => POWER_STATE_CHANGED,
+            AppEvent::NotificationAdded(_) => NOTIFICATION_ADDED,
+            AppEvent::SyncProgress(_) => SYNC_PROGRESS,
+            AppEvent::SyncComplete(_) => SYNC_COMPLETE,
+            AppEvent::SettingChanged(_) => SETTING_CHANGED,
+            AppEvent::ConfigReloaded(_) => CONFIG_RELOADED,
+            #[cfg(feature = "recordings")]
+            AppEvent::RecordingUpdated(_) => RECORDING_UPDATED,
+            AppEvent::ImagePrefetchComplete(_) => IMAGE_PREFETCH_COMPLETE,
+        }
+    }
+}
+
+/// Emit an event to every window
+///
+/// A failure to emit only means no window could be reached, so it is logged
+/// rather than returned.
+pub fn emit(app_handle: &AppHandle, event: AppEvent) {
+    if let Err(e) = app_handle.emit(event.name(), &event) {
+        eprintln!("Failed to emit {} event: {}", event.name(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Name of every event in the catalog; `AppEvent::name` must return one of these
+    const EVENT_NAMES: [&str; 22] = [
+        HISTORY_LOADING,
+        SEARCH_PROGRESS,
+        GROUPS_LOADING,
+        CHANNEL_LOADING,
+        IMAGE_FETCHED,
+        PLAYLIST_FETCH_STATUS,
+        PLAYLIST_WATCH_IMPORT,
+        FAVORITES_CHANGED,
+        XTREAM_STARTUP_AUTH,
+        XTREAM_ACCOUNT_EXPIRED,
+        REMOTE_CONTROL_PLAY,
+        UPDATE_AVAILABLE,
+        PROVIDER_SEARCH_RESULTS,
+        CONTENT_EXPIRED,
+        POWER_STATE_CHANGED,
+        NOTIFICATION_ADDED,
+        SYNC_PROGRESS,
+        SYNC_COMPLETE,
+        SETTING_CHANGED,
+        CONFIG_RELOADED,
+        RECORDING_UPDATED,
+        IMAGE_PREFETCH_COMPLETE,
+    ];
+
+    /// Event names declared in the `AppEventMap` type of the frontend definitions
+    ///
+    /// Names that aren't identifiers, like `sync://progress`, are quoted there.
+    fn frontend_event_names() -> BTreeSet<String> {
+        let definitions = include_str!("../../src/types/events.ts");
+        let map = definitions
+            .split("export type AppEventMap = {")
+            .nth(1)
+            .and_then(|rest| rest.split("};").next())
+            .expect("events.ts has no AppEventMap");
+        map.lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("/**") && !line.starts_with('*'))
+            .filter_map(|line| match line.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').map(|(name, _)| name),
+                None => line.split_once(':').map(|(name, _)| name.trim()),
+            })
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_frontend_definitions_match_catalog() {
+        let backend: BTreeSet<String> = EVENT_NAMES.iter().map(|name| name.to_string()).collect();
+        assert_eq!(backend.len(), EVENT_NAMES.len(), "duplicate event name");
+        assert_eq!(frontend_event_names(), backend);
+    }
+
+    #[test]
+    fn test_event_serializes_as_payload() {
+        let event = AppEvent::HistoryLoading("History loaded!".to_string());
+        assert_eq!(event.name(), HISTORY_LOADING);
+        assert_eq!(serde_json::to_value(&event).unwrap(), serde_json::json!("History loaded!"));
+
+        let event = AppEvent::RemoteControlPlay(RemotePlayRequest {
+            profile_id: "p1".to_string(),
+            content_type: "channel".to_string(),
+            content_id: "42".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"profile_id": "p1", "content_type": "channel", "content_id": "42"})
+        );
+    }
+}
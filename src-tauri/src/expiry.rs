@@ -0,0 +1,317 @@
+// Content expiry for trial channel lists and Xtream profiles
+//
+// A channel list or profile can be given an expiry time, e.g. for a 24 hour
+// trial. Once it passes, the content stays in the database but queries stop
+// returning it, and the frontend is prompted once per run to reactivate or
+// delete the source. Expiry times are unix timestamps in the `expires_at`
+// column of `channel_lists` and `xtream_profiles`, mirrored here so the
+// query paths don't need a database lookup.
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::{ChannelCacheState, DbState};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// How often expiry times are checked for sources that just expired
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What an expiry applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpirySource {
+    ChannelList,
+    Profile,
+}
+
+impl ExpirySource {
+    fn table(self) -> &'static str {
+        match self {
+            ExpirySource::ChannelList => "channel_lists",
+            ExpirySource::Profile => "xtream_profiles",
+        }
+    }
+}
+
+/// A channel list or profile with an expiry time
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ContentExpiry {
+    pub source: ExpirySource,
+    /// Channel list id or profile id
+    pub id: String,
+    pub name: String,
+    pub expires_at: i64,
+    /// Whether its content is hidden
+    pub expired: bool,
+}
+
+#[derive(Default)]
+struct Expiries {
+    lists: HashMap<i32, i64>,
+    profiles: HashMap<String, i64>,
+}
+
+/// Mirrors the `expires_at` columns
+static EXPIRIES: LazyLock<RwLock<Expiries>> = LazyLock::new(|| RwLock::new(Expiries::default()));
+
+/// Expired sources the frontend was prompted about during this run
+static PROMPTED: LazyLock<Mutex<HashSet<(ExpirySource, String)>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Whether an Xtream profile's content is hidden because it expired
+pub fn profile_hidden(profile_id: &str) -> bool {
+    EXPIRIES
+        .read()
+        .ok()
+        .and_then(|expiries| expiries.profiles.get(profile_id).copied())
+        .is_some_and(|expires_at| expires_at <= now())
+}
+
+/// Whether any channel list has expired; lets callers skip [`list_hidden`]
+pub fn has_hidden_lists() -> bool {
+    let now = now();
+    EXPIRIES
+        .read()
+        .map(|expiries| expiries.lists.values().any(|&expires_at| expires_at <= now))
+        .unwrap_or(false)
+}
+
+/// Whether a channel list's content is hidden because it expired; `None` is the default list
+pub fn list_hidden(conn: &Connection, id: Option<i32>) -> bool {
+    let id = match id {
+        Some(id) => Some(id),
+        None => conn
+            .query_row("SELECT id FROM channel_lists WHERE is_default = 1", [], |row| row.get(0))
+            .optional()
+            .ok()
+            .flatten(),
+    };
+    id.and_then(|id| EXPIRIES.read().ok()?.lists.get(&id).copied())
+        .is_some_and(|expires_at| expires_at <= now())
+}
+
+/// Serializes the tests that change the expiry times
+#[cfg(test)]
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// Hide a source's content in a test; other tests changing expiry times wait until the guard drops
+///
+/// Use an id no other test uses, as the source stays expired.
+#[cfg(test)]
+pub(crate) fn expire_for_test(source: ExpirySource, id: &str) -> std::sync::MutexGuard<'static, ()> {
+    let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut expiries = EXPIRIES.write().unwrap();
+    match source {
+        ExpirySource::ChannelList => {
+            expiries.lists.insert(id.parse().unwrap(), 0);
+        }
+        ExpirySource::Profile => {
+            expiries.profiles.insert(id.to_string(), 0);
+        }
+    }
+    guard
+}
+
+/// Load every expiry time; called at startup, after changes and on each periodic check
+pub fn load_settings(conn: &Connection) -> Result<usize> {
+    let mut stmt = conn.prepare("SELECT id, expires_at FROM channel_lists WHERE expires_at IS NOT NULL")?;
+    let lists: HashMap<i32, i64> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|row| row.ok())
+        .collect();
+    let mut stmt = conn.prepare("SELECT id, expires_at FROM xtream_profiles WHERE expires_at IS NOT NULL")?;
+    let profiles: HashMap<String, i64> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|row| row.ok())
+        .collect();
+
+    let count = lists.len() + profiles.len();
+    *EXPIRIES
+        .write()
+        .map_err(|_| XTauriError::lock_acquisition("content expiries"))? = Expiries { lists, profiles };
+    Ok(count)
+}
+
+/// Every channel list and profile with an expiry time, soonest first
+pub fn list_expiries(conn: &Connection) -> Result<Vec<ContentExpiry>> {
+    let now = now();
+    let mut expiries = Vec::new();
+    for source in [ExpirySource::ChannelList, ExpirySource::Profile] {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT CAST(id AS TEXT), name, expires_at FROM {} WHERE expires_at IS NOT NULL",
+            source.table()
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let expires_at: i64 = row.get(2)?;
+            Ok(ContentExpiry {
+                source,
+                id: row.get(0)?,
+                name: row.get(1)?,
+                expires_at,
+                expired: expires_at <= now,
+            })
+        })?;
+        for row in rows {
+            expiries.push(row?);
+        }
+    }
+    expiries.sort_by_key(|expiry| expiry.expires_at);
+    Ok(expiries)
+}
+
+/// Set or clear the expiry time of a channel list or profile
+///
+/// Clearing it, or moving it into the future, makes expired content visible again.
+pub fn set_expiry(conn: &Connection, source: ExpirySource, id: &str, expires_at: Option<i64>) -> Result<()> {
+    let updated = conn.execute(
+        &format!("UPDATE {} SET expires_at = ?1 WHERE CAST(id AS TEXT) = ?2", source.table()),
+        params![expires_at, id],
+    )?;
+    if updated == 0 {
+        return Err(XTauriError::NotFound {
+            resource: format!("{} {}", source.table(), id),
+        });
+    }
+
+    if let Ok(mut prompted) = PROMPTED.lock() {
+        prompted.remove(&(source, id.to_string()));
+    }
+    load_settings(conn)?;
+    Ok(())
+}
+
+/// Sources that expired and haven't been prompted about yet; marks them prompted
+fn take_unprompted(conn: &Connection) -> Result<Vec<ContentExpiry>> {
+    let expired: Vec<ContentExpiry> = list_expiries(conn)?
+        .into_iter()
+        .filter(|expiry| expiry.expired)
+        .collect();
+    let mut prompted = PROMPTED
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("content expiry prompts"))?;
+    Ok(expired
+        .into_iter()
+        .filter(|expiry| prompted.insert((expiry.source, expiry.id.clone())))
+        .collect())
+}
+
+/// Watch for channel lists and profiles passing their expiry time
+///
+/// Each one that expired gets a `content_expired` event once per run. Expired
+/// lists are dropped from the channel caches so their channels stop showing.
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    loop {
+        let expired = match app_handle.state::<DbState>().db.lock() {
+            Ok(db) => load_settings(&db).and_then(|_| take_unprompted(&db)),
+            Err(_) => return,
+        };
+
+        match expired {
+            Ok(expired) => {
+                if expired.iter().any(|expiry| expiry.source == ExpirySource::ChannelList) {
+                    if let Ok(mut cache) = app_handle.state::<ChannelCacheState>().cache.lock() {
+                        cache.clear();
+                    }
+                    crate::search::clear_advanced_cache();
+                }
+                for expiry in expired {
+                    events::emit(&app_handle, AppEvent::ContentExpired(expiry));
+                }
+            }
+            Err(e) => eprintln!("[EXPIRY] Failed to check content expiry: {}", e),
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Get every channel list and profile with an expiry time
+#[tauri::command]
+pub fn get_content_expiries(state: State<DbState>) -> std::result::Result<Vec<ContentExpiry>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    list_expiries(&db).map_err(|e| e.to_string())
+}
+
+/// Set when a channel list's or profile's content expires; `None` never expires
+#[tauri::command]
+pub fn set_content_expiry(
+    state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    source: ExpirySource,
+    id: String,
+    expires_at: Option<i64>,
+) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    set_expiry(&db, source, &id, expires_at).map_err(|e| e.to_string())?;
+    crate::channels::invalidate_channel_cache(cache_state)
+}
+
+/// Make expired content visible again, for `extend_hours` more or for good
+///
+/// Deleting the source instead is done with the usual channel list and profile commands.
+#[tauri::command]
+pub fn reactivate_content(
+    state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    source: ExpirySource,
+    id: String,
+    extend_hours: Option<u32>,
+) -> std::result::Result<(), String> {
+    let expires_at = extend_hours.map(|hours| now() + i64::from(hours) * 3600);
+    set_content_expiry(state, cache_state, source, id, expires_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE channel_lists (id INTEGER PRIMARY KEY, name TEXT NOT NULL, is_default BOOLEAN NOT NULL DEFAULT 0, expires_at INTEGER);
+             CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, name TEXT NOT NULL, expires_at INTEGER);
+             INSERT INTO channel_lists (id, name, is_default) VALUES (1, 'Trial list', 1), (2, 'Other list', 0);
+             INSERT INTO xtream_profiles (id, name) VALUES ('trial-profile', 'Trial'), ('paid-profile', 'Paid');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_expired_content_is_hidden_until_reactivated() {
+        let _guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let conn = create_test_db();
+        set_expiry(&conn, ExpirySource::ChannelList, "1", Some(now() - 10)).unwrap();
+        set_expiry(&conn, ExpirySource::Profile, "trial-profile", Some(now() - 10)).unwrap();
+        set_expiry(&conn, ExpirySource::Profile, "paid-profile", Some(now() + 3600)).unwrap();
+
+        assert!(has_hidden_lists());
+        assert!(list_hidden(&conn, Some(1)));
+        assert!(list_hidden(&conn, None));
+        assert!(!list_hidden(&conn, Some(2)));
+        assert!(profile_hidden("trial-profile"));
+        assert!(!profile_hidden("paid-profile"));
+
+        // Each expired source is prompted about once
+        let expired = take_unprompted(&conn).unwrap();
+        assert_eq!(expired.len(), 2);
+        assert!(take_unprompted(&conn).unwrap().is_empty());
+
+        let expiries = list_expiries(&conn).unwrap();
+        assert_eq!(expiries.len(), 3);
+        assert_eq!(expiries[2].id, "paid-profile");
+        assert!(!expiries[2].expired);
+
+        set_expiry(&conn, ExpirySource::Profile, "trial-profile", None).unwrap();
+        set_expiry(&conn, ExpirySource::ChannelList, "1", Some(now() + 60)).unwrap();
+        assert!(!profile_hidden("trial-profile"));
+        assert!(!list_hidden(&conn, None));
+        assert!(set_expiry(&conn, ExpirySource::Profile, "missing", None).is_err());
+    }
+}
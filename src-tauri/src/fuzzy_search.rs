@@ -1,4 +1,5 @@
 use crate::m3u_parser::Channel;
+use crate::profiling::profile_span;
 
 #[derive(Debug, Clone)]
 pub struct SearchMatch {
@@ -186,6 +187,7 @@ impl FuzzyMatcher {
 
 /// Public function that matches the expected API used in benchmarks
 pub fn fuzzy_search(channels: &[Channel], query: &str, limit: usize) -> Vec<Channel> {
+    let _span = profile_span!("search", "fuzzy_search");
     let matcher = FuzzyMatcher::new();
     let results = matcher.search_channels(channels, query);
     
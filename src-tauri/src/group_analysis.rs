@@ -0,0 +1,337 @@
+// Channel group statistics and cleanup suggestions for playlists
+//
+// Large playlists collect groups that are mostly broken links or the same
+// channels listed several times. `analyze_groups` reports each group's size,
+// how many of its channels failed to play, how many repeat a URL already in
+// the group and when one was last watched, and suggests disabling or
+// deduplicating groups. Failures are reported by the player through
+// `report_playlist_link_status`. Deduplication is a per-group flag in
+// `group_selections`, applied whenever the playlist is loaded.
+use crate::channels::{get_cached_channels, invalidate_channel_cache};
+use crate::error::Result;
+use crate::m3u_parser::Channel;
+use crate::state::{ChannelCacheState, DbState};
+use crate::unit_of_work;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+/// Share of a group's channels that must have failed before disabling it is suggested
+const DEAD_LINK_THRESHOLD: f64 = 0.5;
+
+/// Statistics for one group of a playlist
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct GroupStats {
+    pub group_name: String,
+    pub enabled: bool,
+    /// Whether repeated URLs are already dropped from the group
+    pub deduped: bool,
+    pub channel_count: usize,
+    /// Share of channels whose last playback attempt failed
+    pub dead_link_ratio: f64,
+    /// Share of channels that repeat a URL listed earlier in the group
+    pub duplicate_ratio: f64,
+    /// Channels of the group in the watch history
+    pub watched_channels: usize,
+    /// When a channel of the group was last watched, as stored in the history
+    pub last_watched: Option<String>,
+}
+
+/// A cleanup step for a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupAction {
+    Disable,
+    Dedupe,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct GroupSuggestion {
+    pub group_name: String,
+    pub action: GroupAction,
+    /// Why the step is suggested, for display
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct GroupAnalysis {
+    /// Groups in playlist order
+    pub groups: Vec<GroupStats>,
+    pub suggestions: Vec<GroupSuggestion>,
+}
+
+/// Enabled and dedupe flags by group name
+fn group_flags(conn: &Connection, list_id: i64) -> Result<HashMap<String, (bool, bool)>> {
+    let mut stmt =
+        conn.prepare("SELECT group_name, is_enabled, dedupe FROM group_selections WHERE channel_list_id = ?1")?;
+    let flags = stmt
+        .query_map([list_id], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(flags)
+}
+
+fn failed_urls(conn: &Connection, list_id: i64) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT url FROM playlist_link_failures WHERE channel_list_id = ?1")?;
+    let urls = stmt
+        .query_map([list_id], |row| row.get(0))?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(urls)
+}
+
+/// Last watch time by channel URL
+fn watched_urls(conn: &Connection) -> Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT url, MAX(timestamp) FROM history GROUP BY url")?;
+    let watched = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(watched)
+}
+
+fn analyze(
+    channels: &[Channel],
+    flags: &HashMap<String, (bool, bool)>,
+    failed: &HashSet<String>,
+    watched: &HashMap<String, String>,
+) -> GroupAnalysis {
+    let mut order: Vec<&str> = Vec::new();
+    let mut by_group: HashMap<&str, Vec<&Channel>> = HashMap::new();
+    for channel in channels {
+        let group = by_group.entry(channel.group_title.as_str()).or_default();
+        if group.is_empty() {
+            order.push(&channel.group_title);
+        }
+        group.push(channel);
+    }
+
+    let mut groups = Vec::new();
+    let mut suggestions = Vec::new();
+    for name in order {
+        let members = &by_group[name];
+        let (enabled, deduped) = flags.get(name).copied().unwrap_or((true, false));
+        let mut seen = HashSet::new();
+        let duplicates = members.iter().filter(|channel| !seen.insert(channel.url.as_str())).count();
+        let dead = members.iter().filter(|channel| failed.contains(&channel.url)).count();
+        let watch_times: Vec<&String> = members.iter().filter_map(|channel| watched.get(&channel.url)).collect();
+        let count = members.len();
+
+        let stats = GroupStats {
+            group_name: name.to_string(),
+            enabled,
+            deduped,
+            channel_count: count,
+            dead_link_ratio: dead as f64 / count as f64,
+            duplicate_ratio: duplicates as f64 / count as f64,
+            watched_channels: watch_times.len(),
+            last_watched: watch_times.into_iter().max().cloned(),
+        };
+
+        if enabled && stats.dead_link_ratio >= DEAD_LINK_THRESHOLD {
+            suggestions.push(GroupSuggestion {
+                group_name: stats.group_name.clone(),
+                action: GroupAction::Disable,
+                reason: format!("{} of {} channels failed to play", dead, count),
+            });
+        }
+        if !deduped && duplicates > 0 {
+            suggestions.push(GroupSuggestion {
+                group_name: stats.group_name.clone(),
+                action: GroupAction::Dedupe,
+                reason: format!("{} channels repeat another channel's URL", duplicates),
+            });
+        }
+        groups.push(stats);
+    }
+
+    GroupAnalysis { groups, suggestions }
+}
+
+/// Drop repeated URLs from the playlist's deduplicated groups, keeping the first of each
+pub fn dedupe(conn: &Connection, list_id: i32, channels: Vec<Channel>) -> Vec<Channel> {
+    let deduped: HashSet<String> = match group_flags(conn, list_id as i64) {
+        Ok(flags) => flags
+            .into_iter()
+            .filter(|(_, (_, dedupe))| *dedupe)
+            .map(|(name, _)| name)
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to read deduplicated groups for list {}: {}", list_id, e);
+            return channels;
+        }
+    };
+    if deduped.is_empty() {
+        return channels;
+    }
+
+    let mut seen = HashSet::new();
+    channels
+        .into_iter()
+        .filter(|channel| {
+            !deduped.contains(&channel.group_title) || seen.insert((channel.group_title.clone(), channel.url.clone()))
+        })
+        .collect()
+}
+
+fn apply_suggestion(conn: &Connection, list_id: i64, suggestion: &GroupSuggestion) -> Result<()> {
+    let sql = match suggestion.action {
+        GroupAction::Disable => {
+            "INSERT INTO group_selections (channel_list_id, group_name, is_enabled) VALUES (?1, ?2, 0)
+             ON CONFLICT (channel_list_id, group_name) DO UPDATE SET is_enabled = 0"
+        }
+        GroupAction::Dedupe => {
+            "INSERT INTO group_selections (channel_list_id, group_name, dedupe) VALUES (?1, ?2, 1)
+             ON CONFLICT (channel_list_id, group_name) DO UPDATE SET dedupe = 1"
+        }
+    };
+    conn.execute(sql, params![list_id, suggestion.group_name])?;
+    Ok(())
+}
+
+/// Statistics and cleanup suggestions for each group of a playlist
+#[tauri::command]
+pub fn analyze_groups(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    list_id: i64,
+) -> std::result::Result<GroupAnalysis, String> {
+    let channels = get_cached_channels(db_state.clone(), cache_state, Some(list_id as i32))?;
+    let db = db_state.db.lock().map_err(|e| e.to_string())?;
+    let flags = group_flags(&db, list_id).map_err(|e| e.to_string())?;
+    let failed = failed_urls(&db, list_id).map_err(|e| e.to_string())?;
+    let watched = watched_urls(&db).map_err(|e| e.to_string())?;
+    Ok(analyze(&channels, &flags, &failed, &watched))
+}
+
+/// Apply cleanup suggestions, all or none; returns how many were applied
+#[tauri::command]
+pub fn apply_group_suggestions(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    list_id: i64,
+    suggestions: Vec<GroupSuggestion>,
+) -> std::result::Result<usize, String> {
+    {
+        let db = db_state.db.lock().map_err(|e| e.to_string())?;
+        unit_of_work::run(&db, |unit| {
+            for suggestion in &suggestions {
+                unit.enlist(&format!("group suggestion for {}", suggestion.group_name), |conn| {
+                    apply_suggestion(conn, list_id, suggestion)
+                })?;
+            }
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+    }
+
+    invalidate_channel_cache(cache_state)?;
+    Ok(suggestions.len())
+}
+
+/// Record whether a playlist channel played, for its group's dead-link ratio
+#[tauri::command]
+pub fn report_playlist_link_status(
+    db_state: State<DbState>,
+    list_id: i64,
+    url: String,
+    reachable: bool,
+) -> std::result::Result<(), String> {
+    let db = db_state.db.lock().map_err(|e| e.to_string())?;
+    let result = if reachable {
+        db.execute(
+            "DELETE FROM playlist_link_failures WHERE channel_list_id = ?1 AND url = ?2",
+            params![list_id, url],
+        )
+    } else {
+        db.execute(
+            "INSERT OR REPLACE INTO playlist_link_failures (channel_list_id, url, failed_at) VALUES (?1, ?2, ?3)",
+            params![list_id, url, chrono::Utc::now().timestamp()],
+        )
+    };
+    result.map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(name: &str, group: &str, url: &str) -> Channel {
+        Channel {
+            name: name.to_string(),
+            logo: String::new(),
+            url: url.to_string(),
+            group_title: group.to_string(),
+            tvg_id: String::new(),
+            resolution: String::new(),
+            extra_info: String::new(),
+            country: String::new(),
+            country_flag: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_analyze_and_apply_suggestions() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE group_selections (
+                channel_list_id INTEGER NOT NULL,
+                group_name TEXT NOT NULL,
+                is_enabled BOOLEAN NOT NULL DEFAULT 1,
+                dedupe BOOLEAN NOT NULL DEFAULT 0,
+                PRIMARY KEY (channel_list_id, group_name)
+            );
+            CREATE TABLE playlist_link_failures (
+                channel_list_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                failed_at INTEGER NOT NULL,
+                PRIMARY KEY (channel_list_id, url)
+            );
+            CREATE TABLE history (name TEXT NOT NULL UNIQUE, url TEXT NOT NULL, timestamp DATETIME);
+            INSERT INTO playlist_link_failures VALUES (1, 'http://a/1', 0), (1, 'http://a/2', 0);
+            INSERT INTO history VALUES ('News', 'http://n/1', '2026-01-02 10:00:00'),
+                                       ('News HD', 'http://n/2', '2026-03-04 10:00:00');",
+        )
+        .unwrap();
+        let channels = vec![
+            channel("News", "News", "http://n/1"),
+            channel("News HD", "News", "http://n/2"),
+            channel("News again", "News", "http://n/1"),
+            channel("Adult 1", "Adult", "http://a/1"),
+            channel("Adult 2", "Adult", "http://a/2"),
+            channel("Adult 3", "Adult", "http://a/3"),
+        ];
+
+        let analysis = analyze(
+            &channels,
+            &group_flags(&conn, 1).unwrap(),
+            &failed_urls(&conn, 1).unwrap(),
+            &watched_urls(&conn).unwrap(),
+        );
+        let news = &analysis.groups[0];
+        assert_eq!((news.group_name.as_str(), news.channel_count, news.watched_channels), ("News", 3, 3));
+        assert!((news.duplicate_ratio - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(news.last_watched.as_deref(), Some("2026-03-04 10:00:00"));
+        assert!((analysis.groups[1].dead_link_ratio - 2.0 / 3.0).abs() < 1e-9);
+        let actions: Vec<(&str, GroupAction)> = analysis
+            .suggestions
+            .iter()
+            .map(|s| (s.group_name.as_str(), s.action))
+            .collect();
+        assert_eq!(actions, vec![("News", GroupAction::Dedupe), ("Adult", GroupAction::Disable)]);
+
+        unit_of_work::run(&conn, |unit| {
+            for suggestion in &analysis.suggestions {
+                unit.enlist("apply", |conn| apply_suggestion(conn, 1, suggestion))?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        let flags = group_flags(&conn, 1).unwrap();
+        assert_eq!(flags["News"], (true, true));
+        assert_eq!(flags["Adult"], (false, false));
+
+        let deduped = dedupe(&conn, 1, channels);
+        assert_eq!(deduped.len(), 5);
+        assert!(deduped.iter().all(|channel| channel.name != "News again"));
+    }
+}
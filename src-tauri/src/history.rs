@@ -1,6 +1,9 @@
+use crate::anonymize;
+use crate::country;
+use crate::events::{self, AppEvent};
 use crate::m3u_parser::Channel;
 use crate::state::DbState;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, State};
 
 #[tauri::command]
 pub fn get_history(state: State<DbState>) -> Result<Vec<Channel>, String> {
@@ -8,14 +11,19 @@ pub fn get_history(state: State<DbState>) -> Result<Vec<Channel>, String> {
     let mut stmt = db.prepare("SELECT name, logo, url, group_title, tvg_id, resolution, extra_info FROM history ORDER BY timestamp DESC LIMIT 20").map_err(|e| e.to_string())?;
     let channel_iter = stmt
         .query_map([], |row| {
+            let name: String = row.get(0)?;
+            let group_title: String = row.get(3)?;
+            let country = country::detect_country("", &name, &group_title);
             Ok(Channel {
-                name: row.get(0)?,
+                name,
                 logo: row.get(1)?,
                 url: row.get(2)?,
-                group_title: row.get(3)?,
+                group_title,
                 tvg_id: row.get(4)?,
                 resolution: row.get(5)?,
                 extra_info: row.get(6)?,
+                country: country.unwrap_or_default().to_string(),
+                country_flag: country.map(country::flag_emoji).unwrap_or_default(),
             })
         })
         .map_err(|e| e.to_string())?;
@@ -24,7 +32,7 @@ pub fn get_history(state: State<DbState>) -> Result<Vec<Channel>, String> {
     for channel in channel_iter {
         channels.push(channel.map_err(|e| e.to_string())?);
     }
-    Ok(channels)
+    anonymize::mask_result(channels)
 }
 
 #[tauri::command]
@@ -33,13 +41,13 @@ pub async fn get_history_async(
     state: State<'_, DbState>,
 ) -> Result<Vec<Channel>, String> {
     // Emit start
-    let _ = app_handle.emit("history_loading", "Loading history...");
+    events::emit(&app_handle, AppEvent::HistoryLoading("Loading history...".to_string()));
 
     // Use blocking version for now
     let result = get_history(state);
 
     // Emit completion
-    let _ = app_handle.emit("history_loading", "History loaded!");
+    events::emit(&app_handle, AppEvent::HistoryLoading("History loaded!".to_string()));
 
     result
 }
@@ -0,0 +1,452 @@
+// Image formats the image cache hands to the webview
+//
+// Some providers serve logos as AVIF or HEIC, which not every webview can
+// show. Builds with a decoder for them (the `avif` and `heic` features)
+// transcode such images to PNG or WebP before caching them, unless the format
+// is set to pass through as downloaded, e.g. for a webview that shows it
+// natively. Images that can't be transcoded are cached as downloaded and listed
+// in `image_decode_failures`, so diagnostics can tell why a logo is blank.
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::{LazyLock, RwLock};
+use tauri::State;
+
+/// Decode failures kept for diagnostics; older ones are dropped
+const MAX_DECODE_FAILURES: i64 = 500;
+
+/// Formats of downloaded images, as told by their first bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Avif,
+    Heic,
+}
+
+impl ImageFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Avif => "avif",
+            ImageFormat::Heic => "heic",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [Self::Jpeg, Self::Png, Self::Gif, Self::Webp, Self::Avif, Self::Heic]
+            .into_iter()
+            .find(|format| format.as_str() == value.trim())
+    }
+
+    /// Whether every webview shows it without help
+    pub fn is_web_safe(self) -> bool {
+        !matches!(self, ImageFormat::Avif | ImageFormat::Heic)
+    }
+
+    /// Whether this build can decode it to transcode it
+    pub fn can_decode(self) -> bool {
+        match self {
+            ImageFormat::Avif => cfg!(feature = "avif"),
+            ImageFormat::Heic => cfg!(feature = "heic"),
+            _ => false,
+        }
+    }
+}
+
+/// Tell an image's format from its first bytes
+pub fn sniff(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        return Some(ImageFormat::Jpeg);
+    }
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(ImageFormat::Png);
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some(ImageFormat::Gif);
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some(ImageFormat::Webp);
+    }
+
+    // AVIF and HEIC are ISO media files: a `ftyp` box with a major brand and compatible brands
+    if bytes.len() < 16 || &bytes[4..8] != b"ftyp" {
+        return None;
+    }
+    let box_len = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let brands: Vec<&[u8]> = std::iter::once(&bytes[8..12])
+        .chain(bytes[16..box_len.clamp(16, bytes.len())].chunks_exact(4))
+        .collect();
+    let has_brand = |names: &[&[u8]]| brands.iter().any(|brand| names.contains(brand));
+    if has_brand(&[b"avif", b"avis"]) {
+        Some(ImageFormat::Avif)
+    } else if has_brand(&[b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1"]) {
+        Some(ImageFormat::Heic)
+    } else {
+        None
+    }
+}
+
+/// Format images are transcoded to for the webview
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscodeTarget {
+    #[default]
+    Png,
+    /// Lossless WebP, usually smaller than PNG
+    Webp,
+}
+
+impl TranscodeTarget {
+    fn as_str(self) -> &'static str {
+        match self {
+            TranscodeTarget::Png => "png",
+            TranscodeTarget::Webp => "webp",
+        }
+    }
+}
+
+/// How downloaded images the webview may not show are cached
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct ImageFormatSettings {
+    pub transcode_to: TranscodeTarget,
+    /// Formats cached as downloaded rather than transcoded
+    pub pass_through: Vec<ImageFormat>,
+}
+
+/// Mirrors the image format columns of `settings` so downloads don't need the database
+static SETTINGS: LazyLock<RwLock<ImageFormatSettings>> = LazyLock::new(|| RwLock::new(ImageFormatSettings::default()));
+
+/// Add the settings columns and the decode failure table
+pub fn migrate(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE settings ADD COLUMN image_transcode_target TEXT NOT NULL DEFAULT 'png';
+         ALTER TABLE settings ADD COLUMN image_pass_through TEXT NOT NULL DEFAULT '';
+         CREATE TABLE IF NOT EXISTS image_decode_failures (
+            url TEXT PRIMARY KEY,
+            format TEXT NOT NULL,
+            error TEXT NOT NULL,
+            failures INTEGER NOT NULL DEFAULT 1,
+            last_failed_at INTEGER NOT NULL
+         );",
+    )?;
+    Ok(())
+}
+
+fn read_settings(conn: &Connection) -> Result<ImageFormatSettings> {
+    let (target, pass_through): (String, String) = conn.query_row(
+        "SELECT image_transcode_target, image_pass_through FROM settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(ImageFormatSettings {
+        transcode_to: if target == TranscodeTarget::Webp.as_str() { TranscodeTarget::Webp } else { TranscodeTarget::Png },
+        pass_through: pass_through.split(',').filter_map(ImageFormat::parse).collect(),
+    })
+}
+
+/// Load the image format settings into memory; called at startup
+pub fn load_settings(conn: &Connection) -> Result<ImageFormatSettings> {
+    let settings = read_settings(conn)?;
+    *SETTINGS.write().map_err(|_| XTauriError::lock_acquisition("image format settings"))? = settings.clone();
+    Ok(settings)
+}
+
+pub fn current_settings() -> ImageFormatSettings {
+    SETTINGS.read().map(|settings| settings.clone()).unwrap_or_default()
+}
+
+fn save_settings(conn: &Connection, settings: &ImageFormatSettings) -> Result<()> {
+    let pass_through: Vec<&str> = settings.pass_through.iter().map(|format| format.as_str()).collect();
+    conn.execute(
+        "UPDATE settings SET image_transcode_target = ?1, image_pass_through = ?2 WHERE id = 1",
+        params![settings.transcode_to.as_str(), pass_through.join(",")],
+    )?;
+    load_settings(conn).map(|_| ())
+}
+
+/// Why an image couldn't be made ready for the webview
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeFailure {
+    pub format: ImageFormat,
+    pub error: String,
+}
+
+/// Pixels of a decoded image, 8-bit RGBA row by row
+struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+#[cfg(any(feature = "avif", feature = "heic"))]
+fn decode(bytes: &[u8]) -> Result<RgbaImage> {
+    use libheif_rs::{ColorSpace, HeifContext, HeifError, LibHeif, RgbChroma};
+
+    let heif_error = |e: HeifError| XTauriError::internal(e.to_string());
+    let context = HeifContext::read_from_bytes(bytes).map_err(heif_error)?;
+    let handle = context.primary_image_handle().map_err(heif_error)?;
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)
+        .map_err(heif_error)?;
+    let plane = image
+        .planes()
+        .interleaved
+        .ok_or_else(|| XTauriError::internal("Decoded image has no RGBA plane".to_string()))?;
+
+    // Rows may be padded past the image width
+    let row_len = plane.width as usize * 4;
+    let mut pixels = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+    Ok(RgbaImage { width: plane.width, height: plane.height, pixels })
+}
+
+#[cfg(not(any(feature = "avif", feature = "heic")))]
+fn decode(_bytes: &[u8]) -> Result<RgbaImage> {
+    Err(XTauriError::internal("This build has no image decoder".to_string()))
+}
+
+fn encode(image: &RgbaImage, target: TranscodeTarget) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match target {
+        TranscodeTarget::Png => {
+            let mut encoder = png::Encoder::new(&mut out, image.width, image.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder
+                .write_header()
+                .map_err(|e| XTauriError::internal(format!("Failed to encode PNG: {}", e)))?;
+            writer
+                .write_image_data(&image.pixels)
+                .map_err(|e| XTauriError::internal(format!("Failed to encode PNG: {}", e)))?;
+        }
+        TranscodeTarget::Webp => {
+            image_webp::WebPEncoder::new(&mut out)
+                .encode(&image.pixels, image.width, image.height, image_webp::ColorType::Rgba8)
+                .map_err(|e| XTauriError::internal(format!("Failed to encode WebP: {}", e)))?;
+        }
+    }
+    Ok(out)
+}
+
+/// The bytes to cache for a downloaded image
+///
+/// `Ok(None)` keeps the download as it is: the webview shows its format, or the
+/// format passes through. A format that needs transcoding but can't be
+/// decoded is a failure, and the caller caches the download anyway.
+pub fn for_webview(
+    bytes: &[u8],
+    settings: &ImageFormatSettings,
+) -> std::result::Result<Option<Vec<u8>>, DecodeFailure> {
+    let Some(format) = sniff(bytes).filter(|format| !format.is_web_safe()) else {
+        return Ok(None);
+    };
+    if settings.pass_through.contains(&format) {
+        return Ok(None);
+    }
+    let failure = |error: String| DecodeFailure { format, error };
+    if !format.can_decode() {
+        return Err(failure(format!("This build can't decode {} images", format.as_str())));
+    }
+    let image = decode(bytes).map_err(|e| failure(e.to_string()))?;
+    encode(&image, settings.transcode_to).map(Some).map_err(|e| failure(e.to_string()))
+}
+
+/// An image the cache couldn't transcode for the webview
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ImageDecodeFailure {
+    pub url: String,
+    pub format: ImageFormat,
+    pub error: String,
+    /// Downloads that failed the same way
+    pub failures: u32,
+    pub last_failed_at: i64,
+}
+
+/// Note a failed decode, dropping the oldest entries over the limit
+pub fn record_failure(conn: &Connection, url: &str, failure: &DecodeFailure, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO image_decode_failures (url, format, error, failures, last_failed_at)
+         VALUES (?1, ?2, ?3, 1, ?4)
+         ON CONFLICT(url) DO UPDATE SET
+            format = excluded.format,
+            error = excluded.error,
+            failures = failures + 1,
+            last_failed_at = excluded.last_failed_at",
+        params![url, failure.format.as_str(), failure.error, now],
+    )?;
+    conn.execute(
+        "DELETE FROM image_decode_failures WHERE url NOT IN (
+            SELECT url FROM image_decode_failures ORDER BY last_failed_at DESC LIMIT ?1
+         )",
+        [MAX_DECODE_FAILURES],
+    )?;
+    Ok(())
+}
+
+/// Forget an image's failures once it was transcoded
+pub fn clear_failure(conn: &Connection, url: &str) -> Result<()> {
+    conn.execute("DELETE FROM image_decode_failures WHERE url = ?1", [url])?;
+    Ok(())
+}
+
+/// Recorded decode failures, most recent first
+pub fn decode_failures(conn: &Connection) -> Result<Vec<ImageDecodeFailure>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, format, error, failures, last_failed_at FROM image_decode_failures
+         ORDER BY last_failed_at DESC, url",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, u32>(3)?,
+            row.get::<_, i64>(4)?,
+        ))
+    })?;
+
+    let mut failures = Vec::new();
+    for row in rows {
+        let (url, format, error, count, last_failed_at) = row?;
+        if let Some(format) = ImageFormat::parse(&format) {
+            failures.push(ImageDecodeFailure { url, format, error, failures: count, last_failed_at });
+        }
+    }
+    Ok(failures)
+}
+
+#[tauri::command]
+pub fn get_image_format_settings() -> ImageFormatSettings {
+    current_settings()
+}
+
+/// Set what AVIF and HEIC images are transcoded to and which formats pass through
+///
+/// Applies to images downloaded from now on; cached files are kept as they are.
+#[tauri::command]
+pub fn set_image_format_settings(
+    state: State<DbState>,
+    settings: ImageFormatSettings,
+) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    save_settings(&db, &settings).map_err(|e| e.to_string())
+}
+
+/// Get the images that couldn't be transcoded for the webview, most recent first
+#[tauri::command]
+pub fn get_image_decode_failures(state: State<DbState>) -> std::result::Result<Vec<ImageDecodeFailure>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    decode_failures(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_image_decode_failures(state: State<DbState>) -> std::result::Result<usize, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute("DELETE FROM image_decode_failures", []).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An ISO media file header with a major brand and compatible brands
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let len = 16 + 4 * compatible.len();
+        let mut bytes = (len as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(major);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        for brand in compatible {
+            bytes.extend_from_slice(*brand);
+        }
+        bytes.extend_from_slice(b"\0\0\0\x08meta");
+        bytes
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(&[0xff, 0xd8, 0xff, 0xe0]), Some(ImageFormat::Jpeg));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n...."), Some(ImageFormat::Png));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8L"), Some(ImageFormat::Webp));
+        assert_eq!(sniff(&ftyp(b"avif", &[b"mif1", b"miaf"])), Some(ImageFormat::Avif));
+        assert_eq!(sniff(&ftyp(b"heic", &[b"mif1"])), Some(ImageFormat::Heic));
+        assert_eq!(sniff(&ftyp(b"mif1", &[b"avif"])), Some(ImageFormat::Avif));
+        // The box that follows `ftyp` isn't read as a brand
+        assert_eq!(sniff(&ftyp(b"isom", &[b"mp41"])), None);
+        assert_eq!(sniff(b"<svg"), None);
+    }
+
+    #[test]
+    fn test_for_webview_passes_through_and_reports_failures() {
+        let settings = ImageFormatSettings::default();
+        assert_eq!(for_webview(b"\x89PNG\r\n\x1a\n....", &settings), Ok(None));
+
+        let avif = ftyp(b"avif", &[b"mif1"]);
+        let passed = ImageFormatSettings { pass_through: vec![ImageFormat::Avif], ..settings.clone() };
+        assert_eq!(for_webview(&avif, &passed), Ok(None));
+
+        // Not a real image, so it fails with or without a decoder
+        let failure = for_webview(&avif, &settings).unwrap_err();
+        assert_eq!(failure.format, ImageFormat::Avif);
+        if !ImageFormat::Avif.can_decode() {
+            assert!(failure.error.contains("can't decode avif"));
+        }
+    }
+
+    #[test]
+    fn test_encode_targets() {
+        let image = RgbaImage { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 128] };
+
+        let png = encode(&image, TranscodeTarget::Png).unwrap();
+        assert_eq!(sniff(&png), Some(ImageFormat::Png));
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(pixels, image.pixels);
+
+        let webp = encode(&image, TranscodeTarget::Webp).unwrap();
+        assert_eq!(sniff(&webp), Some(ImageFormat::Webp));
+        let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(webp)).unwrap();
+        let mut pixels = vec![0; decoder.output_buffer_size().unwrap()];
+        decoder.read_image(&mut pixels).unwrap();
+        assert_eq!(pixels, image.pixels);
+    }
+
+    #[test]
+    fn test_settings_and_failures_persist() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE settings (id INTEGER PRIMARY KEY); INSERT INTO settings (id) VALUES (1);")
+            .unwrap();
+        migrate(&conn).unwrap();
+        assert_eq!(read_settings(&conn).unwrap(), ImageFormatSettings::default());
+
+        let settings = ImageFormatSettings {
+            transcode_to: TranscodeTarget::Webp,
+            pass_through: vec![ImageFormat::Heic],
+        };
+        save_settings(&conn, &settings).unwrap();
+        assert_eq!(read_settings(&conn).unwrap(), settings);
+
+        let failure = DecodeFailure { format: ImageFormat::Avif, error: "bad".to_string() };
+        record_failure(&conn, "http://img/a.avif", &failure, 10).unwrap();
+        record_failure(&conn, "http://img/a.avif", &failure, 20).unwrap();
+        record_failure(&conn, "http://img/b.heic", &DecodeFailure { format: ImageFormat::Heic, ..failure }, 15)
+            .unwrap();
+        let failures = decode_failures(&conn).unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!((failures[0].url.as_str(), failures[0].failures), ("http://img/a.avif", 2));
+
+        clear_failure(&conn, "http://img/a.avif").unwrap();
+        assert_eq!(decode_failures(&conn).unwrap()[0].url, "http://img/b.heic");
+    }
+}
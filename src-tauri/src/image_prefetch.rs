@@ -0,0 +1,329 @@
+// Prefetching logos and covers after a sync
+//
+// When enabled, a completed sync is followed by downloading the images of the
+// profile's content into the image cache, up to a budget in megabytes so a
+// metered connection isn't used up on artwork. Images in favorited and
+// followed categories go first, then the most recently added. The downloads
+// go through the image cache's download queue and count as prefetch traffic in
+// the bandwidth tracker; how much of the budget a run used is kept per profile
+// and sent as an event.
+use crate::content_cache::ContentCache;
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::images::{self, ImageGroup};
+use crate::state::DbState;
+use crate::tasks::{self, TaskKind};
+use dashmap::DashMap;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock};
+use tauri::{AppHandle, State};
+use tokio_util::sync::CancellationToken;
+
+/// Largest budget a run can be given
+pub const MAX_BUDGET_MB: u32 = 4096;
+
+/// Images considered per run; a budget rarely reaches this many
+const MAX_CANDIDATES: i64 = 5000;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// The last run of each profile
+static REPORTS: LazyLock<DashMap<String, ImagePrefetchReport>> = LazyLock::new(DashMap::new);
+
+/// Whether images are prefetched after a sync, and how much may be downloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct ImagePrefetchSettings {
+    pub enabled: bool,
+    /// Megabytes downloaded per run at most
+    pub budget_mb: u32,
+}
+
+impl Default for ImagePrefetchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            budget_mb: 50,
+        }
+    }
+}
+
+impl ImagePrefetchSettings {
+    fn validate(&self) -> Result<()> {
+        if !(1..=MAX_BUDGET_MB).contains(&self.budget_mb) {
+            return Err(XTauriError::InvalidSetting {
+                key: "image_prefetch_budget_mb".to_string(),
+                value: self.budget_mb.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn budget_bytes(&self) -> u64 {
+        u64::from(self.budget_mb) * BYTES_PER_MB
+    }
+}
+
+/// How a prefetch run went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+pub struct ImagePrefetchReport {
+    pub profile_id: String,
+    pub budget_bytes: u64,
+    pub used_bytes: u64,
+    /// Images not yet cached when the run started
+    pub candidates: usize,
+    pub fetched: usize,
+    /// Images that failed or didn't fit in what was left of the budget
+    pub skipped: usize,
+    pub finished_at: i64,
+}
+
+/// Add the prefetch settings; prefetching starts off
+pub fn migrate(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE settings ADD COLUMN image_prefetch_enabled BOOLEAN NOT NULL DEFAULT 0;
+         ALTER TABLE settings ADD COLUMN image_prefetch_budget_mb INTEGER NOT NULL DEFAULT 50;",
+    )?;
+    Ok(())
+}
+
+fn read_settings(conn: &Connection) -> Result<ImagePrefetchSettings> {
+    let settings = conn.query_row(
+        "SELECT image_prefetch_enabled, image_prefetch_budget_mb FROM settings WHERE id = 1",
+        [],
+        |row| {
+            Ok(ImagePrefetchSettings {
+                enabled: row.get(0)?,
+                budget_mb: row.get::<_, i64>(1)?.clamp(1, i64::from(MAX_BUDGET_MB)) as u32,
+            })
+        },
+    )?;
+    Ok(settings)
+}
+
+/// Uncached image URLs of a profile's content in the order they are prefetched
+///
+/// Channel logos, movie posters and series covers in categories with a
+/// favorite or that are followed come first; within each of the two groups the
+/// most recently added content goes first.
+fn prefetch_plan(conn: &Connection, profile_id: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "WITH favored (content_type, category_id) AS (
+             SELECT content_type, category_id FROM xtream_followed_categories WHERE profile_id = ?1
+             UNION
+             SELECT 'channel', c.category_id FROM xtream_favorites f
+             JOIN xtream_channels c ON c.profile_id = f.profile_id AND c.stream_id = CAST(TRIM(f.content_id) AS INTEGER)
+             WHERE f.profile_id = ?1 AND f.content_type = 'channel'
+             UNION
+             SELECT 'movie', m.category_id FROM xtream_favorites f
+             JOIN xtream_movies m ON m.profile_id = f.profile_id AND m.stream_id = CAST(TRIM(f.content_id) AS INTEGER)
+             WHERE f.profile_id = ?1 AND f.content_type = 'movie'
+             UNION
+             SELECT 'series', s.category_id FROM xtream_favorites f
+             JOIN xtream_series s ON s.profile_id = f.profile_id AND s.series_id = CAST(TRIM(f.content_id) AS INTEGER)
+             WHERE f.profile_id = ?1 AND f.content_type = 'series'
+         ),
+         images (content_type, category_id, url, added) AS (
+             SELECT 'channel', category_id, TRIM(stream_icon), CAST(added AS INTEGER)
+             FROM xtream_channels WHERE profile_id = ?1
+             UNION ALL
+             SELECT 'movie', category_id, TRIM(stream_icon), CAST(added AS INTEGER)
+             FROM xtream_movies WHERE profile_id = ?1
+             UNION ALL
+             SELECT 'series', category_id, TRIM(cover), CAST(last_modified AS INTEGER)
+             FROM xtream_series WHERE profile_id = ?1
+         )
+         SELECT url, EXISTS (
+             SELECT 1 FROM favored WHERE favored.content_type = images.content_type
+               AND favored.category_id = images.category_id
+         ) AS in_favored
+         FROM images
+         WHERE url LIKE 'http%' AND url NOT IN (SELECT url FROM image_cache_index)
+         ORDER BY in_favored DESC, added DESC
+         LIMIT ?2",
+    )?;
+
+    let mut favored = ImageGroup {
+        priority: 1,
+        urls: Vec::new(),
+    };
+    let mut rest = ImageGroup {
+        priority: 0,
+        urls: Vec::new(),
+    };
+    let rows = stmt.query_map(params![profile_id, MAX_CANDIDATES], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+    })?;
+    for row in rows {
+        let (url, in_favored) = row?;
+        if in_favored {
+            favored.urls.push(url);
+        } else {
+            rest.urls.push(url);
+        }
+    }
+    Ok(images::plan_fetch(vec![favored, rest]))
+}
+
+/// Prefetch a profile's images after a completed sync, if enabled
+///
+/// Skipped in low-power mode. The run is listed as a background task that
+/// can be cancelled.
+pub async fn after_sync(app_handle: AppHandle, cache: Arc<ContentCache>, profile_id: String) {
+    if crate::power::low_power() {
+        return;
+    }
+
+    let plan = {
+        let db = cache.get_db();
+        let Ok(conn) = db.lock() else {
+            return;
+        };
+        read_settings(&conn).and_then(|settings| {
+            if settings.enabled {
+                Ok(Some((settings, prefetch_plan(&conn, &profile_id)?)))
+            } else {
+                Ok(None)
+            }
+        })
+    };
+    let (settings, urls) = match plan {
+        Ok(Some(plan)) => plan,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!(
+                "[IMAGES] Failed to plan image prefetch for profile {}: {}",
+                profile_id, e
+            );
+            return;
+        }
+    };
+
+    let budget_bytes = settings.budget_bytes();
+    let candidates = urls.len();
+    let cancel_token = CancellationToken::new();
+    let task = tasks::register_cancellable(
+        TaskKind::ImagePreload,
+        "Prefetching images",
+        Some(&profile_id),
+        cancel_token.clone(),
+    );
+    let usage = images::prefetch_within_budget(&app_handle, urls, budget_bytes, &cancel_token, |usage| {
+        task.set_progress(
+            (usage.used_bytes * 100 / budget_bytes) as u8,
+            Some(format!(
+                "{:.1} of {} MB",
+                usage.used_bytes as f64 / BYTES_PER_MB as f64,
+                settings.budget_mb
+            )),
+        );
+    })
+    .await;
+    drop(task);
+
+    match usage {
+        Ok(usage) => {
+            let report = ImagePrefetchReport {
+                profile_id: profile_id.clone(),
+                budget_bytes,
+                used_bytes: usage.used_bytes,
+                candidates,
+                fetched: usage.fetched,
+                skipped: usage.skipped,
+                finished_at: chrono::Utc::now().timestamp(),
+            };
+            println!(
+                "[IMAGES] Prefetched {} images for profile {} using {} of {} bytes",
+                report.fetched, profile_id, report.used_bytes, report.budget_bytes
+            );
+            REPORTS.insert(profile_id, report.clone());
+            events::emit(&app_handle, AppEvent::ImagePrefetchComplete(report));
+        }
+        Err(e) => eprintln!("[IMAGES] Image prefetch failed for profile {}: {}", profile_id, e),
+    }
+}
+
+#[tauri::command]
+pub fn get_image_prefetch_settings(state: State<DbState>) -> std::result::Result<ImagePrefetchSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    read_settings(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_image_prefetch_settings(
+    state: State<DbState>,
+    settings: ImagePrefetchSettings,
+) -> std::result::Result<(), String> {
+    settings.validate().map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET image_prefetch_enabled = ?1, image_prefetch_budget_mb = ?2 WHERE id = 1",
+        params![settings.enabled, settings.budget_mb],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// How much of the budget the profile's last prefetch used, if one ran since startup
+#[tauri::command]
+pub fn get_image_prefetch_report(profile_id: String) -> Option<ImagePrefetchReport> {
+    REPORTS.get(&profile_id).map(|report| report.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_out_of_range_budget() {
+        assert!(ImagePrefetchSettings::default().validate().is_ok());
+        for budget_mb in [0, MAX_BUDGET_MB + 1] {
+            let settings = ImagePrefetchSettings {
+                enabled: true,
+                budget_mb,
+            };
+            assert!(settings.validate().is_err());
+        }
+        assert_eq!(ImagePrefetchSettings::default().budget_bytes(), 50 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_prefetch_plan_puts_favored_categories_and_new_content_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        images::create_index_table(&conn).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY);
+             INSERT INTO xtream_profiles VALUES ('p1'), ('p2');
+             CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT);
+             INSERT INTO xtream_favorites VALUES ('p1', 'channel', '1');
+             INSERT INTO xtream_channels (profile_id, stream_id, name, stream_icon, added, category_id) VALUES
+                 ('p1', 1, 'News 1', 'http://img/news1.png', '100', 'news'),
+                 ('p1', 2, 'News 2', 'http://img/news2.png', '50', 'news'),
+                 ('p1', 3, 'Sport', 'http://img/sport.png', '300', 'sport'),
+                 ('p1', 4, 'No logo', '', '400', 'sport'),
+                 ('p2', 5, 'Other profile', 'http://img/other.png', '500', 'news');
+             INSERT INTO xtream_movies (profile_id, stream_id, name, stream_icon, added, category_id) VALUES
+                 ('p1', 10, 'Old', 'http://img/old.jpg', '10', 'films'),
+                 ('p1', 11, 'Cached', 'http://img/cached.jpg', '1000', 'films'),
+                 ('p1', 12, 'Shared poster', 'http://img/sport.png', '20', 'films');
+             INSERT INTO xtream_series (profile_id, series_id, name, cover, last_modified, category_id) VALUES
+                 ('p1', 20, 'Followed', 'http://img/followed.jpg', '5', 'shows');
+             INSERT INTO xtream_followed_categories VALUES ('p1', 'series', 'shows', 'Shows', 0);
+             INSERT INTO image_cache_index (url, path) VALUES ('http://img/cached.jpg', '/cache/x');",
+        )
+        .unwrap();
+
+        assert_eq!(
+            prefetch_plan(&conn, "p1").unwrap(),
+            vec![
+                "http://img/news1.png",
+                "http://img/news2.png",
+                "http://img/followed.jpg",
+                "http://img/sport.png",
+                "http://img/old.jpg",
+            ]
+        );
+    }
+}
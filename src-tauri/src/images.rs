@@ -0,0 +1,544 @@
+// On-disk image cache for posters, season covers and programme icons
+//
+// Detail views ask for many images at once. `fetch_images_batch` queues them
+// by priority group behind a shared download limit, so a burst of covers
+// can't flood the provider, and reports each cached file as an event. Cached
+// files are also recorded in the `image_cache_index` table, so listings can
+// hand out the local file of an icon already cached without a round trip.
+use crate::concurrency::{self, Subsystem};
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::image_formats::{self, DecodeFailure};
+use crate::state::DbState;
+use crate::storage_policy;
+use crate::tasks::{self, TaskKind};
+use dashmap::DashMap;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+/// Images downloaded at the same time, across all batches
+pub const MAX_CONCURRENT_IMAGE_DOWNLOADS: usize = 4;
+
+/// Larger responses are not cached; they are unlikely to be a thumbnail
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+const IMAGE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Prefetching stops once less than this is left of its budget
+const MIN_PREFETCH_BYTES: u64 = 4 * 1024;
+
+static DOWNLOAD_SLOTS: LazyLock<Arc<Semaphore>> =
+    LazyLock::new(|| Arc::new(Semaphore::new(MAX_CONCURRENT_IMAGE_DOWNLOADS)));
+
+/// One lock per URL being fetched, so a URL requested by two batches is downloaded once
+static IN_FLIGHT: LazyLock<DashMap<String, Arc<tokio::sync::Mutex<()>>>> = LazyLock::new(DashMap::new);
+
+/// Delete the least recently written images until the cache fits in `max_bytes`
+///
+/// Returns the number of files removed.
+pub fn trim_image_cache(dir: &Path, max_bytes: u64) -> std::io::Result<usize> {
+    let mut files = Vec::new();
+    let mut total: u64 = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            total += metadata.len();
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+    if total <= max_bytes {
+        return Ok(0);
+    }
+
+    files.sort_by_key(|(modified, _, _)| *modified);
+    let mut removed = 0;
+    for (_, len, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= len;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// URLs that should be fetched together; higher priorities are fetched first
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+pub struct ImageGroup {
+    #[serde(default)]
+    pub priority: i32,
+    pub urls: Vec<String>,
+}
+
+/// What `fetch_images_batch` queued
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ImageBatch {
+    pub batch_id: String,
+    /// Unique URLs queued; one `image_fetched` event follows for each
+    pub queued: usize,
+}
+
+/// Payload of an `image_fetched` event
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ImageFetched {
+    pub batch_id: String,
+    pub url: String,
+    pub path: Option<String>,
+    /// Whether the image was already on disk
+    pub cached: bool,
+    pub error: Option<String>,
+}
+
+/// Directory the images are cached in
+pub fn image_cache_dir() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .ok_or(XTauriError::DataDirectoryAccess)?
+        .join("xtauri")
+        .join("images"))
+}
+
+/// Cache file for a URL, named by its hash so any URL maps to a safe file name
+pub fn cache_path(dir: &Path, url: &str) -> PathBuf {
+    let digest = Sha256::digest(url.as_bytes());
+    let name: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    dir.join(name)
+}
+
+/// Create the index of cached images by URL
+pub fn create_index_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS image_cache_index (
+            url TEXT PRIMARY KEY,
+            path TEXT NOT NULL,
+            cached_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Record that the image at `url` is cached in `path`
+pub fn record_cached(conn: &Connection, url: &str, path: &Path) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO image_cache_index (url, path, cached_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+        params![url, path.to_string_lossy()],
+    )?;
+    Ok(())
+}
+
+/// Cached files of those of `urls` in the index, by URL
+pub fn cached_paths(conn: &Connection, urls: &[&str]) -> Result<HashMap<String, String>> {
+    let mut paths = HashMap::new();
+    // Stay under SQLite's limit on bound parameters
+    for chunk in urls.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut stmt =
+            conn.prepare(&format!("SELECT url, path FROM image_cache_index WHERE url IN ({})", placeholders))?;
+        let rows = stmt.query_map(params_from_iter(chunk), |row| Ok((row.get(0)?, row.get(1)?)))?;
+        for row in rows {
+            let (url, path): (String, String) = row?;
+            paths.insert(url, path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Drop index entries whose file is gone, e.g. after the cache was trimmed
+///
+/// Returns the number of entries removed.
+pub fn prune_index(conn: &Connection) -> Result<usize> {
+    let entries: Vec<(String, String)> = conn
+        .prepare("SELECT url, path FROM image_cache_index")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let tx = conn.unchecked_transaction()?;
+    let mut removed = 0;
+    for (url, path) in entries {
+        if !Path::new(&path).exists() {
+            removed += tx.execute("DELETE FROM image_cache_index WHERE url = ?1", [url])?;
+        }
+    }
+    tx.commit()?;
+    Ok(removed)
+}
+
+fn with_index(app_handle: &AppHandle, f: impl FnOnce(&Connection) -> Result<()>) {
+    let Some(db_state) = app_handle.try_state::<DbState>() else {
+        return;
+    };
+    let result = db_state.db.lock().map_err(|e| e.to_string()).and_then(|db| f(&db).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("[IMAGES] Failed to update the image cache index: {}", e);
+    }
+}
+
+/// Bring the index in line with the cache after files were trimmed
+pub fn forget_trimmed(app_handle: &AppHandle) {
+    with_index(app_handle, |conn| prune_index(conn).map(|_| ()));
+}
+
+/// Keep only the groups of the highest priority; the rest is prefetching
+///
+/// Used in low-power mode, where only what the view shows first is fetched.
+pub fn drop_prefetch(mut groups: Vec<ImageGroup>) -> Vec<ImageGroup> {
+    if let Some(top) = groups.iter().map(|group| group.priority).max() {
+        groups.retain(|group| group.priority == top);
+    }
+    groups
+}
+
+/// Order a batch's URLs by group priority, dropping duplicates and non-HTTP URLs
+///
+/// Groups of equal priority keep their request order, and a URL stays in the
+/// first group it appears in.
+pub fn plan_fetch(mut groups: Vec<ImageGroup>) -> Vec<String> {
+    groups.sort_by_key(|group| std::cmp::Reverse(group.priority));
+
+    let mut seen = HashSet::new();
+    groups
+        .into_iter()
+        .flat_map(|group| group.urls)
+        .map(|url| url.trim().to_string())
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+        .filter(|url| seen.insert(url.clone()))
+        .collect()
+}
+
+/// A freshly downloaded image
+struct Downloaded {
+    /// Size of the body as downloaded
+    bytes: u64,
+    /// Why it couldn't be transcoded for the webview, if it needed to be
+    decode_failure: Option<DecodeFailure>,
+}
+
+fn too_large(bytes: u64, max_bytes: usize) -> XTauriError {
+    XTauriError::internal(format!("Image is {} bytes, over the {} byte limit", bytes, max_bytes))
+}
+
+/// Download an image of at most `max_bytes` into `path`, transcoded when the webview can't show its format
+///
+/// An image that couldn't be transcoded is cached as downloaded. The transfer
+/// counts towards `source` in the bandwidth tracker.
+async fn download(client: &reqwest::Client, url: &str, path: &Path, max_bytes: usize, source: &str) -> Result<Downloaded> {
+    let response = client.get(url).send().await?.error_for_status()?;
+    // Skip images the provider says are too large before reading them
+    if let Some(length) = response.content_length().filter(|length| *length > max_bytes as u64) {
+        return Err(too_large(length, max_bytes));
+    }
+    let body = crate::transfer::read_body(response, source).await?;
+    let bytes = body.len() as u64;
+    if body.len() > max_bytes {
+        return Err(too_large(bytes, max_bytes));
+    }
+
+    let settings = image_formats::current_settings();
+    let (body, failure) = tokio::task::spawn_blocking(move || match image_formats::for_webview(&body, &settings) {
+        Ok(Some(transcoded)) => (transcoded, None),
+        Ok(None) => (body, None),
+        Err(failure) => (body, Some(failure)),
+    })
+    .await
+    .map_err(|e| XTauriError::internal(e.to_string()))?;
+
+    // Write to a temporary file first so a reader never sees a partial image
+    let partial = path.with_extension("part");
+    tokio::fs::write(&partial, &body).await?;
+    tokio::fs::rename(&partial, path).await?;
+    Ok(Downloaded { bytes, decode_failure: failure })
+}
+
+/// An image in the cache
+struct CachedImage {
+    path: PathBuf,
+    /// Whether it was already on disk
+    cached: bool,
+    /// Bytes downloaded; none when it was already on disk
+    bytes: u64,
+    /// Why a new download couldn't be transcoded for the webview
+    decode_failure: Option<DecodeFailure>,
+}
+
+/// Return the cached file for `url`, downloading it if needed
+async fn fetch_image(
+    client: &reqwest::Client,
+    dir: &Path,
+    url: &str,
+    max_bytes: usize,
+    source: &str,
+) -> Result<CachedImage> {
+    let path = cache_path(dir, url);
+    let lock = IN_FLIGHT
+        .entry(url.to_string())
+        .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+        .clone();
+
+    let result = {
+        let _guard = lock.lock().await;
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            Ok(CachedImage { path, cached: true, bytes: 0, decode_failure: None })
+        } else {
+            download(client, url, &path, max_bytes, source).await.map(|downloaded| CachedImage {
+                path,
+                cached: false,
+                bytes: downloaded.bytes,
+                decode_failure: downloaded.decode_failure,
+            })
+        }
+    };
+
+    IN_FLIGHT.remove_if(url, |_, entry| Arc::strong_count(entry) <= 2);
+    result
+}
+
+/// Add a fetched image to the index, along with whether it could be transcoded
+fn record_fetched(app_handle: &AppHandle, url: &str, image: &CachedImage) {
+    with_index(app_handle, |conn| {
+        record_cached(conn, url, &image.path)?;
+        match &image.decode_failure {
+            Some(failure) => image_formats::record_failure(conn, url, failure, chrono::Utc::now().timestamp()),
+            None if !image.cached => image_formats::clear_failure(conn, url),
+            None => Ok(()),
+        }
+    });
+}
+
+/// Make room under the platform's budget before adding to the cache
+async fn make_room(app_handle: &AppHandle, dir: &Path) {
+    let max_bytes = storage_policy::StoragePolicy::current().image_cache_max_bytes;
+    let trim_dir = dir.to_path_buf();
+    match tokio::task::spawn_blocking(move || trim_image_cache(&trim_dir, max_bytes)).await {
+        Ok(Ok(removed)) if removed > 0 => forget_trimmed(app_handle),
+        Ok(Err(e)) => eprintln!("[IMAGES] Failed to trim image cache: {}", e),
+        _ => {}
+    }
+}
+
+async fn run_batch(app_handle: AppHandle, batch_id: String, dir: PathBuf, client: reqwest::Client, urls: Vec<String>) {
+    let cancel_token = CancellationToken::new();
+    let total = urls.len();
+    let task = Arc::new(tasks::register_cancellable(
+        TaskKind::ImagePreload,
+        format!("Fetching {} images", total),
+        None,
+        cancel_token.clone(),
+    ));
+    let done = Arc::new(AtomicUsize::new(0));
+    make_room(&app_handle, &dir).await;
+
+    for url in urls {
+        // Taking slots in plan order keeps higher-priority groups ahead in the queue.
+        // Cancelling stops queueing; downloads already started still finish.
+        let permit = tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            permit = Arc::clone(&DOWNLOAD_SLOTS).acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => return,
+            },
+        };
+        let app_handle = app_handle.clone();
+        let batch_id = batch_id.clone();
+        let dir = dir.clone();
+        let client = client.clone();
+        let task = Arc::clone(&task);
+        let done = Arc::clone(&done);
+
+        tokio::spawn(async move {
+            let result = fetch_image(&client, &dir, &url, MAX_IMAGE_BYTES, crate::transfer::SOURCE_IMAGES).await;
+            drop(permit);
+            if let Ok(image) = &result {
+                record_fetched(&app_handle, &url, image);
+            }
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            task.set_progress((done * 100 / total) as u8, Some(format!("{} of {} images", done, total)));
+
+            let event = match result {
+                Ok(image) => ImageFetched {
+                    batch_id,
+                    url,
+                    path: Some(image.path.to_string_lossy().into_owned()),
+                    cached: image.cached,
+                    error: None,
+                },
+                Err(e) => ImageFetched {
+                    batch_id,
+                    url,
+                    path: None,
+                    cached: false,
+                    error: Some(e.to_string()),
+                },
+            };
+            events::emit(&app_handle, AppEvent::ImageFetched(event));
+        });
+    }
+}
+
+/// Queue groups of image URLs for download into the image cache
+///
+/// Returns straight away; an `image_fetched` event reports each image's cache
+/// path, or its error, as it completes. The batch is listed as a background
+/// task until then.
+///
+/// In low-power mode only the highest-priority groups are queued.
+#[tauri::command]
+pub async fn fetch_images_batch(app_handle: AppHandle, groups: Vec<ImageGroup>) -> std::result::Result<ImageBatch, String> {
+    let _slot = concurrency::acquire(Subsystem::Images).await.map_err(|e| e.to_string())?;
+    let dir = image_cache_dir().map_err(|e| e.to_string())?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create image cache directory: {}", e))?;
+    let client = crate::connection::client_builder()
+        .timeout(IMAGE_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let groups = if crate::power::low_power() { drop_prefetch(groups) } else { groups };
+    let urls = plan_fetch(groups);
+    let batch = ImageBatch {
+        batch_id: uuid::Uuid::new_v4().to_string(),
+        queued: urls.len(),
+    };
+
+    tauri::async_runtime::spawn(run_batch(app_handle, batch.batch_id.clone(), dir, client, urls));
+    Ok(batch)
+}
+
+/// What `prefetch_within_budget` downloaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BudgetUsage {
+    pub fetched: usize,
+    /// Images that failed or were larger than what was left of the budget
+    pub skipped: usize,
+    pub used_bytes: u64,
+}
+
+/// Download `urls` in order into the image cache until `budget_bytes` are used
+///
+/// Each image waits for a slot of the shared download queue, so prefetching
+/// takes turns with the images views ask for. Images already on disk cost
+/// nothing, and one larger than what is left of the budget is skipped.
+/// `on_progress` gets the usage after every image. The transfers count as
+/// prefetch traffic in the bandwidth tracker.
+pub async fn prefetch_within_budget(
+    app_handle: &AppHandle,
+    urls: Vec<String>,
+    budget_bytes: u64,
+    cancel_token: &CancellationToken,
+    on_progress: impl Fn(&BudgetUsage),
+) -> Result<BudgetUsage> {
+    let dir = image_cache_dir()?;
+    tokio::fs::create_dir_all(&dir).await?;
+    let client = crate::connection::client_builder().timeout(IMAGE_TIMEOUT).build()?;
+    make_room(app_handle, &dir).await;
+
+    let mut usage = BudgetUsage::default();
+    for url in urls {
+        let remaining = budget_bytes.saturating_sub(usage.used_bytes);
+        if remaining < MIN_PREFETCH_BYTES {
+            break;
+        }
+        let permit = tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            permit = Arc::clone(&DOWNLOAD_SLOTS).acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => break,
+            },
+        };
+
+        let max_bytes = remaining.min(MAX_IMAGE_BYTES as u64) as usize;
+        let result = fetch_image(&client, &dir, &url, max_bytes, crate::transfer::SOURCE_IMAGE_PREFETCH).await;
+        drop(permit);
+        match result {
+            Ok(image) => {
+                record_fetched(app_handle, &url, &image);
+                usage.used_bytes += image.bytes;
+                if !image.cached {
+                    usage.fetched += 1;
+                }
+            }
+            Err(_) => usage.skipped += 1,
+        }
+        on_progress(&usage);
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(priority: i32, urls: &[&str]) -> ImageGroup {
+        ImageGroup {
+            priority,
+            urls: urls.iter().map(|url| url.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_plan_fetch_orders_and_coalesces() {
+        let plan = plan_fetch(vec![
+            group(0, &["http://img/s1.jpg", "http://img/s2.jpg"]),
+            group(10, &["http://img/poster.jpg", "http://img/s1.jpg"]),
+            group(0, &["", "file:///etc/passwd", "http://img/s2.jpg", "http://img/s3.jpg"]),
+        ]);
+
+        assert_eq!(
+            plan,
+            vec![
+                "http://img/poster.jpg",
+                "http://img/s1.jpg",
+                "http://img/s2.jpg",
+                "http://img/s3.jpg",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_drop_prefetch_keeps_top_priority() {
+        let groups = drop_prefetch(vec![
+            group(0, &["http://img/s1.jpg"]),
+            group(10, &["http://img/poster.jpg"]),
+            group(10, &["http://img/backdrop.jpg"]),
+        ]);
+        assert_eq!(plan_fetch(groups), vec!["http://img/poster.jpg", "http://img/backdrop.jpg"]);
+        assert!(drop_prefetch(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_trim_image_cache_removes_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = std::time::SystemTime::now();
+        for (i, name) in ["old", "middle", "new"].iter().enumerate() {
+            let path = dir.path().join(name);
+            std::fs::write(&path, vec![0u8; 100]).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - Duration::from_secs(300 - i as u64 * 100)).unwrap();
+        }
+
+        assert_eq!(trim_image_cache(dir.path(), 300).unwrap(), 0);
+        assert_eq!(trim_image_cache(dir.path(), 150).unwrap(), 2);
+        assert!(dir.path().join("new").exists());
+        assert!(!dir.path().join("middle").exists());
+    }
+
+    #[test]
+    fn test_cache_path_is_stable() {
+        let dir = Path::new("/cache");
+        assert_eq!(cache_path(dir, "http://img/a.jpg"), cache_path(dir, "http://img/a.jpg"));
+        assert_ne!(cache_path(dir, "http://img/a.jpg"), cache_path(dir, "http://img/b.jpg"));
+        assert_eq!(cache_path(dir, "http://img/a.jpg").file_name().unwrap().len(), 64);
+    }
+}
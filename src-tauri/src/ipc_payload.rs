@@ -0,0 +1,136 @@
+// Binary payloads for large command responses
+//
+// Listing commands return their results through `respond`, which serializes
+// them once, straight into the IPC response body. By default that body is JSON
+// text, exactly what the commands returned before. A frontend that can decode
+// CBOR says so with `negotiate_ipc_payload_encoding`, after which the same
+// commands send compact CBOR bytes, which arrive as an `ArrayBuffer`.
+//
+// Each window negotiates for itself, so a window whose frontend only decodes
+// JSON keeps getting JSON when another window opts into CBOR.
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use tauri::ipc::Response;
+use tauri::{RunEvent, Window, WindowEvent};
+
+/// Negotiated encoding by window label; runtime-only, every frontend load negotiates again
+static ENCODINGS: LazyLock<DashMap<String, PayloadEncoding>> = LazyLock::new(DashMap::new);
+
+/// How listing commands encode their responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadEncoding {
+    #[default]
+    Json,
+    Cbor,
+}
+
+/// The encoding a window negotiated; JSON until it does
+pub fn negotiated(window: &str) -> PayloadEncoding {
+    ENCODINGS.get(window).map(|encoding| *encoding).unwrap_or_default()
+}
+
+/// The best encoding both sides support; JSON is always supported
+fn choose(supported: &[PayloadEncoding]) -> PayloadEncoding {
+    if supported.contains(&PayloadEncoding::Cbor) {
+        PayloadEncoding::Cbor
+    } else {
+        PayloadEncoding::Json
+    }
+}
+
+fn to_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(|e| format!("Failed to encode response: {}", e))?;
+    Ok(bytes)
+}
+
+/// Serialize a command's result in the negotiated encoding
+///
+/// Uses the encoding the calling window negotiated. In demo mode the result is
+/// masked first, like every other listing command.
+pub fn respond<T: Serialize>(window: &Window, value: &T) -> Result<Response, String> {
+    let encoding = negotiated(window.label());
+    if crate::anonymize::is_demo_mode() {
+        let mut json = serde_json::to_value(value).map_err(|e| format!("Failed to encode response: {}", e))?;
+        crate::anonymize::mask_response(&mut json);
+        return encode(encoding, &json);
+    }
+    encode(encoding, value)
+}
+
+fn encode<T: Serialize>(encoding: PayloadEncoding, value: &T) -> Result<Response, String> {
+    match encoding {
+        PayloadEncoding::Json => serde_json::to_string(value)
+            .map(Response::new)
+            .map_err(|e| format!("Failed to encode response: {}", e)),
+        PayloadEncoding::Cbor => to_cbor(value).map(Response::new),
+    }
+}
+
+fn negotiate(window: &str, supported: &[PayloadEncoding]) -> PayloadEncoding {
+    let encoding = choose(supported);
+    ENCODINGS.insert(window.to_string(), encoding);
+    encoding
+}
+
+/// Pick the encoding for the calling window's listing responses from the ones its frontend can decode
+#[tauri::command]
+pub fn negotiate_ipc_payload_encoding(window: Window, supported: Vec<PayloadEncoding>) -> PayloadEncoding {
+    negotiate(window.label(), &supported)
+}
+
+/// Forget a window's encoding when it closes; called for every run event
+pub fn on_run_event(event: &RunEvent) {
+    if let RunEvent::WindowEvent {
+        label,
+        event: WindowEvent::Destroyed,
+        ..
+    } = event
+    {
+        ENCODINGS.remove(label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pagination::Page;
+
+    #[test]
+    fn test_cbor_is_used_only_when_offered() {
+        assert_eq!(choose(&[]), PayloadEncoding::Json);
+        assert_eq!(choose(&[PayloadEncoding::Json]), PayloadEncoding::Json);
+        assert_eq!(choose(&[PayloadEncoding::Json, PayloadEncoding::Cbor]), PayloadEncoding::Cbor);
+    }
+
+    #[test]
+    fn test_each_window_keeps_its_own_encoding() {
+        assert_eq!(
+            negotiate("ipc-test-player", &[PayloadEncoding::Json, PayloadEncoding::Cbor]),
+            PayloadEncoding::Cbor
+        );
+        assert_eq!(
+            negotiate("ipc-test-settings", &[PayloadEncoding::Json]),
+            PayloadEncoding::Json
+        );
+
+        assert_eq!(negotiated("ipc-test-player"), PayloadEncoding::Cbor);
+        assert_eq!(negotiated("ipc-test-settings"), PayloadEncoding::Json);
+        assert_eq!(negotiated("ipc-test-never-negotiated"), PayloadEncoding::Json);
+    }
+
+    #[test]
+    fn test_cbor_payloads_round_trip_and_are_smaller() {
+        let page = Page::from_vec(
+            (0..100).map(|i| format!("Channel {}", i)).collect::<Vec<_>>(),
+            Some(50),
+            Some(0),
+        );
+        let bytes = to_cbor(&page).unwrap();
+        let decoded: serde_json::Value = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, serde_json::to_value(&page).unwrap());
+        assert!(bytes.len() < serde_json::to_string(&page).unwrap().len());
+    }
+}
@@ -1,159 +1,227 @@
+mod anonymize;
+mod backup;
+#[cfg(test)]
+mod bindings;
+mod build_features;
+mod cache_ttl;
 mod channels;
+mod concurrency;
+mod connection;
 pub mod content_cache;
+mod content_id;
+mod country;
+mod digest;
 pub mod database;
+mod db_pool;
+mod epg_xmltv;
 mod error;
+mod events;
+mod expiry;
 mod filters;
 pub mod fuzzy_search;
+mod group_analysis;
 mod groups;
 mod history;
+mod image_formats;
+mod image_prefetch;
+mod images;
+mod ipc_payload;
 pub mod m3u_parser;
 mod m3u_parser_helpers;
+mod memory;
+mod migrations;
+mod notifications;
 mod playlists;
+mod power;
+pub mod pagination;
+mod parental;
+pub mod post_processors;
+mod profiling;
+mod recordings;
+mod remote_control;
 pub mod search;
+mod search_session;
+mod session_state;
 mod settings;
+mod settings_profiles;
+mod startup;
 mod state;
+mod storage_policy;
+mod tasks;
+mod transfer;
+mod ui_state;
+mod unified_search;
+mod unit_of_work;
+mod updates;
+mod user_config;
 mod utils;
+mod watchdog;
 pub mod xtream;
 
 
 
 use content_cache::{
     cancel_content_sync, clear_content_cache, filter_cached_xtream_movies,
-    get_cached_xtream_channels, get_cached_xtream_movies, get_cached_xtream_series,
+    get_cached_xtream_channel_countries, get_cached_xtream_channels, get_cached_xtream_movies, get_cached_xtream_series,
     get_cached_xtream_series_details, get_content_cache_stats, get_sync_progress,
-    get_sync_settings, get_sync_status, search_cached_xtream_channels, search_cached_xtream_movies,
-    search_cached_xtream_series, start_content_sync, update_sync_settings, ContentCacheState,
+    get_playback_candidates, get_xtream_channel_url_overrides, get_xtream_epg_grid, get_cached_xtream_epg, clear_cached_xtream_epg, get_similar, move_xtream_channel, preview_xtream_profile,
+    remove_xtream_channel_url_override, report_playback_event, report_playback_failure, set_xtream_channel_url_override,
+    get_search_syntax_help, get_sync_history, get_sync_settings, get_sync_status, search_cached_xtream_channels, search_cached_xtream_movies,
+    search_cached_xtream_series, start_content_sync, start_profile_sync, update_sync_settings, get_sync_limits, reset_sync_limits,
 };
-use error::{Result, XTauriError};
-use playlists::FetchState;
-use state::{ChannelCacheState, DbState};
-use std::sync::{Arc, Mutex};
-use tauri::Manager;
-use xtream::{ContentCache, CredentialManager, ProfileManager, XtreamState};
+use content_cache::age_rating::{get_parental_age_limit, set_parental_age_limit};
+use content_cache::genres::{get_genre_catalog, get_genre_mappings, get_profile_genres, set_genre_mapping};
+use content_cache::maintenance::{get_maintenance_window, run_content_cache_maintenance, set_maintenance_window};
+use content_cache::sort_index::{get_presorted_categories, set_presorted_categories};
+use playlists::{FetchState, WatchFolderState};
+use remote_control::RemoteControlState;
+use state::ChannelCacheState;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 // Import all the command functions from their respective modules
+use anonymize::*;
+use backup::{export_app_backup, import_app_backup};
+use build_features::get_build_features;
+use cache_ttl::{get_cache_ttls, reset_cache_ttls, set_cache_ttls};
 use channels::*;
+use concurrency::{get_concurrency_limits, get_concurrency_load, set_concurrency_limits};
+use connection::*;
+use digest::{get_digest_settings, get_latest_digest, preview_digest, set_digest_settings};
+use epg_xmltv::{get_channel_epg, refresh_playlist_epg, set_playlist_epg_url};
 use filters::*;
+use group_analysis::{analyze_groups, apply_group_suggestions, report_playlist_link_status};
 use groups::*;
 use history::*;
+use image_formats::{
+    clear_image_decode_failures, get_image_decode_failures, get_image_format_settings, set_image_format_settings,
+};
+use image_prefetch::{get_image_prefetch_report, get_image_prefetch_settings, set_image_prefetch_settings};
+use images::*;
+use ipc_payload::negotiate_ipc_payload_encoding;
+use memory::*;
 use playlists::*;
+use power::{get_power_state, set_low_power_mode};
+use post_processors::{
+    get_content_post_processors, get_post_process_reports, set_content_post_processor_enabled,
+};
+use profiling::dump_profile;
+use recordings::{delete_recording, list_recordings, start_recording, stop_recording};
+use remote_control::*;
 use search::*;
+use search_session::{get_search_debounce_ms, set_search_debounce_ms};
+use session_state::{get_session_state, save_session_state};
 use settings::*;
+use settings_profiles::{
+    delete_settings_profile, get_settings_profiles, save_settings_profile, switch_settings_profile,
+};
+use startup::{get_startup_report, retry_startup, StartupState};
+use storage_policy::{get_storage_policy, set_low_storage};
+use tasks::*;
+use transfer::*;
+use ui_state::*;
+use unified_search::search_all_content;
+use updates::*;
+use user_config::get_effective_config;
+use parental::{
+    get_parental_audit_log, get_parental_audit_retention, is_parental_pin_set, set_parental_audit_retention,
+    set_parental_pin, unlock_parental_controls,
+};
+use notifications::{get_notification_routes, get_notifications, mark_notifications_read, set_notification_route};
+use expiry::{get_content_expiries, reactivate_content, set_content_expiry};
+use xtream::api_language::{get_xtream_api_language, set_xtream_api_language};
 use xtream::commands::*;
-
-fn initialize_application() -> Result<(rusqlite::Connection, Vec<m3u_parser::Channel>)> {
-    let mut db_connection = database::initialize_database().map_err(|e| {
-        XTauriError::database_init(format!("Database initialization failed: {}", e))
-    })?;
-
-    // Run cleanup on startup to remove orphaned channel list files
-    if let Err(e) = utils::cleanup_orphaned_channel_files(&db_connection) {
-        println!("Warning: Channel list cleanup failed: {}", e);
-    }
-
-    let channels = m3u_parser::get_channels(&mut db_connection, None);
-    database::populate_channels(&mut db_connection, &channels)
-        .map_err(|e| XTauriError::database_init(format!("Failed to populate channels: {}", e)))?;
-
-    Ok((db_connection, channels))
-}
-
-fn setup_xtream_state(db_connection: Arc<Mutex<rusqlite::Connection>>) -> Result<XtreamState> {
-    // Create credential manager
-    let credential_manager = Arc::new(CredentialManager::new().map_err(|e| {
-        XTauriError::internal(format!("Failed to initialize credential manager: {}", e))
-    })?);
-
-    // Create content cache using the same database connection
-    let content_cache = Arc::new(ContentCache::new(
-        Arc::clone(&db_connection),
-        std::time::Duration::from_secs(3600),
-    ));
-
-    // Create profile manager using the same database connection
-    let profile_manager = Arc::new(ProfileManager::new(db_connection, credential_manager));
-
-    Ok(XtreamState::new(profile_manager, content_cache))
-}
-
-fn setup_content_cache_state() -> Result<ContentCacheState> {
-    // Create a new database connection for content cache
-    let db_connection = database::initialize_database().map_err(|e| {
-        XTauriError::database_init(format!(
-            "Failed to create content cache DB connection: {}",
-            e
-        ))
-    })?;
-
-    let db_arc = Arc::new(Mutex::new(db_connection));
-    ContentCacheState::new(db_arc)
-}
+use xtream::demo::create_demo_profile;
+use xtream::recording::{get_provider_mode, set_provider_mode};
+use xtream::startup_auth::{
+    get_auto_authenticate_on_startup, get_xtream_account_status, set_auto_authenticate_on_startup,
+};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let (db_connection, _channels) = match initialize_application() {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Fatal error during application initialization: {}", e);
-            eprintln!("Error details: {}", e);
-            std::process::exit(1);
-        }
-    };
-
-    let db_arc = Arc::new(Mutex::new(db_connection));
+    profiling::init();
 
     tauri::Builder::default()
-        .manage(DbState {
-            db: Mutex::new(
-                // Create a new connection for the DbState since we need to share the Arc
-                database::initialize_database()
-                    .map_err(|e| {
-                        XTauriError::database_init(format!(
-                            "Failed to create second DB connection: {}",
-                            e
-                        ))
-                    })
-                    .unwrap(),
-            ),
-        })
         .manage(ChannelCacheState {
-            cache: Mutex::new(None),
+            cache: Mutex::new(HashMap::new()),
         })
         .manage(FetchState::new())
+        .manage(WatchFolderState::new())
+        .manage(RemoteControlState::new())
+        .manage(StartupState::default())
         .setup(|app| {
-            // Initialize Xtream state
-            let xtream_state = match setup_xtream_state(db_arc) {
-                Ok(state) => state,
-                Err(e) => {
-                    eprintln!("Failed to initialize Xtream state: {}", e);
-                    return Err(Box::new(e));
-                }
-            };
-            app.manage(xtream_state);
-
-            // Initialize Content Cache state
-            let content_cache_state = match setup_content_cache_state() {
-                Ok(state) => state,
-                Err(e) => {
-                    eprintln!("Failed to initialize Content Cache state: {}", e);
-                    return Err(Box::new(e));
-                }
-            };
-            app.manage(content_cache_state);
-
+            // A subsystem failing to start leaves the app degraded rather than exiting
+            let report = startup::initialize(app.handle());
+            if !report.ready {
+                eprintln!("Starting degraded; {} subsystem(s) failed to initialize", report.failures.len());
+            }
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(startup::gate_commands(tauri::generate_handler![
             // Channel commands
             get_channels,
+            get_channels_paginated,
+            get_channel_countries,
             get_groups,
             get_history,
             search_channels,
+            search_channels_paginated,
             invalidate_channel_cache,
             invalidate_search_cache,
             get_cache_stats,
             warm_cache_with_common_searches,
+            get_search_debounce_ms,
+            set_search_debounce_ms,
+            // IPC commands
+            negotiate_ipc_payload_encoding,
+            // Memory commands
+            get_memory_report,
+            trim_memory,
+            get_storage_policy,
+            set_low_storage,
+            // Power commands
+            get_power_state,
+            set_low_power_mode,
+            get_concurrency_limits,
+            set_concurrency_limits,
+            get_concurrency_load,
+            // Cache TTL commands
+            get_cache_ttls,
+            set_cache_ttls,
+            reset_cache_ttls,
+            // Weekly digest commands
+            get_digest_settings,
+            set_digest_settings,
+            get_latest_digest,
+            preview_digest,
+            // Configuration file
+            get_effective_config,
+            // Backup commands
+            export_app_backup,
+            import_app_backup,
+            // Profiling commands
+            dump_profile,
+            // Recording commands
+            start_recording,
+            stop_recording,
+            list_recordings,
+            delete_recording,
+            // Build commands
+            get_build_features,
+            // Content post-processor commands
+            get_content_post_processors,
+            set_content_post_processor_enabled,
+            get_post_process_reports,
+            // Update commands
+            check_for_updates,
+            get_update_settings,
+            set_auto_check_updates,
+            set_update_check_endpoint,
+            // Content expiry commands
+            get_content_expiries,
+            set_content_expiry,
+            reactivate_content,
             // Async channel commands
             get_channels_async,
             get_groups_async,
@@ -174,6 +242,39 @@ pub fn run() {
             set_volume,
             get_is_muted,
             set_is_muted,
+            save_settings_profile,
+            get_settings_profiles,
+            delete_settings_profile,
+            switch_settings_profile,
+            // Remote control commands
+            get_remote_control_settings,
+            is_remote_control_running,
+            set_remote_control_enabled,
+            set_remote_control_port,
+            rotate_remote_control_token,
+            // Anonymization commands
+            export_redacted_database,
+            get_demo_mode,
+            set_demo_mode,
+            // Background task commands
+            get_background_tasks,
+            cancel_background_task,
+            // UI state commands
+            get_ui_state,
+            set_ui_state,
+            delete_ui_state,
+            clear_ui_state_namespace,
+            // Session state commands
+            save_session_state,
+            get_session_state,
+            // Transfer commands
+            get_http_compression_enabled,
+            set_http_compression_enabled,
+            get_bandwidth_stats,
+            reset_bandwidth_stats,
+            // Connection commands
+            get_connection_strategy,
+            set_connection_strategy,
             // Playlist commands
             get_channel_lists,
             add_channel_list,
@@ -188,24 +289,49 @@ pub fn run() {
             validate_and_add_channel_list_async,
             get_playlist_fetch_status,
             get_all_playlist_fetch_status,
+            get_playlist_watch_dir,
+            set_playlist_watch_dir,
+            get_supported_playlist_encodings,
+            get_channel_list_encoding,
+            set_channel_list_encoding,
+            export_channel_list_to_m3u,
+            convert_xtream_profile_to_playlist,
+            set_playlist_epg_url,
+            refresh_playlist_epg,
+            get_channel_epg,
             // Group commands
             get_enabled_groups,
             update_group_selection,
             sync_channel_list_groups,
             enable_all_groups,
             disable_all_groups,
+            analyze_groups,
+            apply_group_suggestions,
+            report_playlist_link_status,
             // Filter commands
             save_filter,
             get_saved_filters,
             delete_saved_filter,
             // Xtream commands
             create_xtream_profile,
+            import_xtream_profiles_csv,
             update_xtream_profile,
+            update_xtream_profile_credentials,
+            change_xtream_profile_url,
+            get_xtream_api_language,
+            set_xtream_api_language,
+            get_provider_mode,
+            set_provider_mode,
+            create_demo_profile,
             delete_xtream_profile,
             get_xtream_profiles,
             get_xtream_profile,
             validate_xtream_credentials,
+            test_xtream_connection,
             authenticate_xtream_profile,
+            get_auto_authenticate_on_startup,
+            set_auto_authenticate_on_startup,
+            get_xtream_account_status,
             get_xtream_channel_categories,
             get_xtream_channels,
             get_xtream_channels_paginated,
@@ -229,7 +355,17 @@ pub fn run() {
             get_xtream_current_and_next_epg,
             filter_epg_by_time_range,
             search_epg_programs,
+            get_epg_icon_urls,
+            fetch_images_batch,
+            get_image_format_settings,
+            set_image_format_settings,
+            get_image_decode_failures,
+            clear_image_decode_failures,
+            get_image_prefetch_settings,
+            set_image_prefetch_settings,
+            get_image_prefetch_report,
             generate_xtream_stream_url,
+            resolve_xtream_stream_url,
             filter_xtream_channels,
             sort_xtream_channels,
             search_xtream_channels,
@@ -248,35 +384,83 @@ pub fn run() {
             get_xtream_playback_history,
             add_to_xtream_playback_history,
             update_xtream_playback_position,
+            playback_position_heartbeat,
+            flush_playback_positions,
             // Content cache commands
             get_cached_xtream_channels,
+            get_cached_xtream_channel_countries,
             search_cached_xtream_channels,
+            get_xtream_epg_grid,
+            get_cached_xtream_epg,
+            clear_cached_xtream_epg,
+            get_similar,
+            get_genre_catalog,
+            get_profile_genres,
+            get_genre_mappings,
+            set_genre_mapping,
             get_cached_xtream_movies,
             search_cached_xtream_movies,
             filter_cached_xtream_movies,
             get_cached_xtream_series,
             get_cached_xtream_series_details,
             search_cached_xtream_series,
+            search_all_content,
+            get_search_syntax_help,
+            get_parental_age_limit,
+            set_parental_age_limit,
+            is_parental_pin_set,
+            set_parental_pin,
+            unlock_parental_controls,
+            get_parental_audit_log,
+            get_parental_audit_retention,
+            set_parental_audit_retention,
+            get_presorted_categories,
+            set_presorted_categories,
+            get_maintenance_window,
+            set_maintenance_window,
+            run_content_cache_maintenance,
+            set_xtream_channel_url_override,
+            remove_xtream_channel_url_override,
+            get_xtream_channel_url_overrides,
+            move_xtream_channel,
+            get_playback_candidates,
+            report_playback_failure,
+            report_playback_event,
             // Sync control commands
             start_content_sync,
+            start_profile_sync,
             cancel_content_sync,
             get_sync_progress,
             get_sync_status,
+            get_sync_history,
+            preview_xtream_profile,
             get_sync_settings,
             update_sync_settings,
+            get_sync_limits,
+            reset_sync_limits,
             clear_content_cache,
             get_content_cache_stats,
             // Xtream history commands
             add_xtream_history,
             update_xtream_history_position,
+            set_private_playback,
+            get_private_playback,
             get_xtream_history,
             get_xtream_history_by_type,
             get_xtream_history_item,
+            get_continue_watching,
             remove_xtream_history,
             clear_xtream_history,
             clear_old_xtream_history,
+            mark_season_watched,
+            unmark_season_watched,
+            mark_series_watched,
+            unmark_series_watched,
+            get_xtream_series_progress,
+            get_archive_resume_entries,
             // Search and filter commands
             search_all_xtream_content,
+            search_xtream_content_tiered,
             filter_channels_advanced,
             filter_movies_advanced,
             filter_series_advanced,
@@ -303,11 +487,60 @@ pub fn run() {
             get_xtream_favorites_by_type,
             is_xtream_favorite,
             clear_xtream_favorites,
-        ])
-        .run(tauri::generate_context!())
-        .map_err(|e| {
+            // Content tag commands
+            add_xtream_content_tag,
+            remove_xtream_content_tag,
+            get_xtream_content_tags,
+            get_xtream_content_by_tag,
+            // Automation commands
+            create_xtream_automation_script,
+            update_xtream_automation_script,
+            delete_xtream_automation_script,
+            get_xtream_automation_scripts,
+            run_xtream_automation_script,
+            // EPG reminder commands
+            add_xtream_epg_reminder,
+            remove_xtream_epg_reminder,
+            get_xtream_epg_reminders,
+            export_schedule_as_ics,
+            // Notification commands
+            get_notifications,
+            mark_notifications_read,
+            get_notification_routes,
+            set_notification_route,
+            get_year_in_review,
+            get_backlog_stats,
+            get_request_limits,
+            set_request_limits,
+            auto_map_epg_channels,
+            get_epg_mapping_suggestions,
+            resolve_epg_mapping,
+            follow_category,
+            unfollow_category,
+            get_followed_categories,
+            get_followed_category_updates,
+            mark_followed_category_updates_read,
+            add_to_watchlist,
+            remove_from_watchlist,
+            get_watchlist,
+            export_profile_portables,
+            // Startup commands
+            get_startup_report,
+            retry_startup,
+        ]))
+        .build(tauri::generate_context!())
+        .unwrap_or_else(|e| {
             eprintln!("Failed to run Tauri application: {}", e);
             std::process::exit(1);
         })
-        .unwrap();
+        .run(|app_handle, event| {
+            if !startup::is_ready(app_handle) {
+                return;
+            }
+            storage_policy::on_run_event(app_handle, &event);
+            session_state::on_run_event(app_handle, &event);
+            xtream::heartbeat::on_run_event(app_handle, &event);
+            ipc_payload::on_run_event(&event);
+            search_session::on_run_event(&event);
+        });
 }
@@ -1,13 +1,17 @@
+use crate::country;
+use crate::group_analysis;
+use crate::playlists::{decode_playlist, list_encoding, read_playlist_file, record_decoding};
+use crate::post_processors;
+use crate::profiling::profile_span;
 use chrono::Utc;
 use regex::Regex;
-use reqwest;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, specta::Type)]
 pub struct Channel {
     pub name: String,
     pub logo: String,
@@ -16,9 +20,16 @@ pub struct Channel {
     pub tvg_id: String,
     pub resolution: String,
     pub extra_info: String,
+    /// ISO country code from `tvg-country`, a name tag or the group; empty when unknown
+    #[serde(default)]
+    pub country: String,
+    /// Flag emoji for `country`
+    #[serde(default)]
+    pub country_flag: String,
 }
 
 fn parse_m3u_content(m3u_content: &str) -> Vec<Channel> {
+    let _span = profile_span!("parser", "parse_m3u_content");
     let mut channels = Vec::new();
     let re_resolution = Regex::new(r"(\d+p)").unwrap();
     let re_extra_info = Regex::new(r"\[(.*?)\]").unwrap();
@@ -64,6 +75,14 @@ fn parse_m3u_content(m3u_content: &str) -> Vec<Channel> {
                 .next()
                 .unwrap_or_default()
                 .to_string();
+            let tvg_country = line
+                .split("tvg-country=\"")
+                .nth(1)
+                .unwrap_or_default()
+                .split('"')
+                .next()
+                .unwrap_or_default();
+            let country = country::detect_country(tvg_country, &name, &group_title);
             let resolution = re_resolution
                 .captures(&name)
                 .and_then(|c| c.get(1))
@@ -83,6 +102,8 @@ fn parse_m3u_content(m3u_content: &str) -> Vec<Channel> {
                         tvg_id,
                         resolution,
                         extra_info,
+                        country: country.unwrap_or_default().to_string(),
+                        country_flag: country.map(country::flag_emoji).unwrap_or_default(),
                     });
                     parsed_channels += 1;
                 } else {
@@ -115,6 +136,7 @@ fn parse_m3u_content_with_progress<F>(m3u_content: &str, progress_callback: F) -
 where
     F: Fn(f32, String, usize),
 {
+    let _span = profile_span!("parser", "parse_m3u_content");
     let mut channels = Vec::new();
     let re_resolution = Regex::new(r"(\d+p)").unwrap();
     let re_extra_info = Regex::new(r"\[(.*?)\]").unwrap();
@@ -166,6 +188,14 @@ where
                 .next()
                 .unwrap_or_default()
                 .to_string();
+            let tvg_country = line
+                .split("tvg-country=\"")
+                .nth(1)
+                .unwrap_or_default()
+                .split('"')
+                .next()
+                .unwrap_or_default();
+            let country = country::detect_country(tvg_country, &name, &group_title);
             let resolution = re_resolution
                 .captures(&name)
                 .and_then(|c| c.get(1))
@@ -186,6 +216,8 @@ where
                         tvg_id,
                         resolution,
                         extra_info,
+                        country: country.unwrap_or_default().to_string(),
+                        country_flag: country.map(country::flag_emoji).unwrap_or_default(),
                     });
                     parsed_channels += 1;
                 }
@@ -246,19 +278,20 @@ pub fn get_channels(conn: &mut Connection, id: Option<i32>) -> Vec<Channel> {
                 let data_dir = dirs::data_dir().unwrap().join("xtauri");
                 let channel_lists_dir = data_dir.join("channel_lists");
                 if let Ok(content) = fs::read_to_string(channel_lists_dir.join(fp)) {
-                    return parse_m3u_content(&content);
+                    return post_process(conn, id, parse_m3u_content(&content));
                 }
             }
         }
 
         if source.starts_with("http") {
-            if let Ok(content) = reqwest::blocking::get(&source).and_then(|resp| resp.text()) {
+            if let Ok(body) = crate::transfer::fetch_body_blocking(&source, crate::transfer::SOURCE_PLAYLIST) {
+                let decoded = decode_playlist(&body, list_encoding(conn, id));
                 let data_dir = dirs::data_dir().unwrap().join("xtauri");
                 let channel_lists_dir = data_dir.join("channel_lists");
                 let _ = fs::create_dir_all(&channel_lists_dir);
                 let filename = format!("{}.m3u", Uuid::new_v4());
                 let new_filepath = channel_lists_dir.join(&filename);
-                if fs::write(&new_filepath, &content).is_ok() {
+                if fs::write(&new_filepath, &decoded.text).is_ok() {
                     conn.execute(
                         "UPDATE channel_lists SET filepath = ?1, last_fetched = ?2 WHERE id = ?3",
                         &[
@@ -268,14 +301,15 @@ pub fn get_channels(conn: &mut Connection, id: Option<i32>) -> Vec<Channel> {
                         ],
                     )
                     .unwrap();
-                    return parse_m3u_content(&content);
+                    let _ = record_decoding(conn, id, &decoded);
+                    return post_process(conn, id, parse_m3u_content(&decoded.text));
                 }
             }
         } else {
             let data_dir = dirs::data_dir().unwrap().join("xtauri");
             let channel_lists_dir = data_dir.join("channel_lists");
-            if let Ok(content) = fs::read_to_string(channel_lists_dir.join(&source)) {
-                return parse_m3u_content(&content);
+            if let Ok(decoded) = read_playlist_file(&channel_lists_dir.join(&source), list_encoding(conn, id)) {
+                return post_process(conn, id, parse_m3u_content(&decoded.text));
             }
         }
     }
@@ -283,6 +317,15 @@ pub fn get_channels(conn: &mut Connection, id: Option<i32>) -> Vec<Channel> {
     vec![]
 }
 
+/// Run the enabled content post-processors over a freshly parsed playlist,
+/// then drop repeated channels from groups the user deduplicated
+fn post_process(conn: &Connection, list_id: i32, channels: Vec<Channel>) -> Vec<Channel> {
+    let source = post_processors::playlist_source(list_id);
+    post_processors::begin_report(&source);
+    let channels = post_processors::apply(conn, &source, channels);
+    group_analysis::dedupe(conn, list_id, channels)
+}
+
 // New async version with progress support
 pub fn get_channels_with_progress<F>(
     conn: &mut Connection,
@@ -332,21 +375,23 @@ where
                 let channel_lists_dir = data_dir.join("channel_lists");
                 if let Ok(content) = fs::read_to_string(channel_lists_dir.join(fp)) {
                     progress_callback(0.3, "Parsing cached M3U content...".to_string(), 0);
-                    return parse_m3u_content_with_progress(&content, progress_callback);
+                    return post_process(conn, id, parse_m3u_content_with_progress(&content, progress_callback));
                 }
             }
         }
 
         if source.starts_with("http") {
             progress_callback(0.2, "Downloading playlist...".to_string(), 0);
-            if let Ok(content) = reqwest::blocking::get(&source).and_then(|resp| resp.text()) {
+            if let Ok(body) = crate::transfer::fetch_body_blocking(&source, crate::transfer::SOURCE_PLAYLIST) {
+                let decoded = decode_playlist(&body, list_encoding(conn, id));
+                let content = decoded.text.as_str();
                 progress_callback(0.4, "Saving to cache...".to_string(), 0);
                 let data_dir = dirs::data_dir().unwrap().join("xtauri");
                 let channel_lists_dir = data_dir.join("channel_lists");
                 let _ = fs::create_dir_all(&channel_lists_dir);
                 let filename = format!("{}.m3u", Uuid::new_v4());
                 let new_filepath = channel_lists_dir.join(&filename);
-                if fs::write(&new_filepath, &content).is_ok() {
+                if fs::write(&new_filepath, content).is_ok() {
                     conn.execute(
                         "UPDATE channel_lists SET filepath = ?1, last_fetched = ?2 WHERE id = ?3",
                         &[
@@ -356,17 +401,18 @@ where
                         ],
                     )
                     .unwrap();
+                    let _ = record_decoding(conn, id, &decoded);
                 }
                 progress_callback(0.5, "Parsing M3U content...".to_string(), 0);
-                return parse_m3u_content_with_progress(&content, progress_callback);
+                return post_process(conn, id, parse_m3u_content_with_progress(content, progress_callback));
             }
         } else {
             progress_callback(0.2, "Loading from file...".to_string(), 0);
             let data_dir = dirs::data_dir().unwrap().join("xtauri");
             let channel_lists_dir = data_dir.join("channel_lists");
-            if let Ok(content) = fs::read_to_string(channel_lists_dir.join(&source)) {
+            if let Ok(decoded) = read_playlist_file(&channel_lists_dir.join(&source), list_encoding(conn, id)) {
                 progress_callback(0.3, "Parsing M3U content...".to_string(), 0);
-                return parse_m3u_content_with_progress(&content, progress_callback);
+                return post_process(conn, id, parse_m3u_content_with_progress(&decoded.text, progress_callback));
             }
         }
     }
@@ -1,4 +1,6 @@
+use crate::country;
 use crate::m3u_parser::Channel;
+use crate::playlists::{decode_playlist, list_encoding, read_playlist_file, record_decoding};
 use chrono;
 use dirs;
 use regex;
@@ -50,18 +52,20 @@ pub fn get_m3u_content(conn: &mut rusqlite::Connection, id: Option<i32>) -> Resu
 
         // Fetch from source
         if source.starts_with("http") {
-            let client = reqwest::blocking::Client::builder()
+            let client = crate::connection::blocking_client_builder()
                 .timeout(std::time::Duration::from_secs(120))
                 .build()
                 .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
             
-            let content = client
+            let response = client
                 .get(&source)
                 .header("User-Agent", "Mozilla/5.0")
+                .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
                 .send()
-                .map_err(|e| format!("Failed to fetch playlist: {}", e))?
-                .text()
+                .map_err(|e| format!("Failed to fetch playlist: {}", e))?;
+            let body = crate::transfer::read_body_blocking(response, crate::transfer::SOURCE_PLAYLIST)
                 .map_err(|e| format!("Failed to read response: {}", e))?;
+            let decoded = decode_playlist(&body, list_encoding(conn, id));
 
             // Save to cache
             let data_dir = dirs::data_dir().unwrap().join("xtauri");
@@ -69,7 +73,7 @@ pub fn get_m3u_content(conn: &mut rusqlite::Connection, id: Option<i32>) -> Resu
             let _ = std::fs::create_dir_all(&channel_lists_dir);
             let filename = format!("{}.m3u", uuid::Uuid::new_v4());
             let new_filepath = channel_lists_dir.join(&filename);
-            if std::fs::write(&new_filepath, &content).is_ok() {
+            if std::fs::write(&new_filepath, &decoded.text).is_ok() {
                 let _ = conn.execute(
                     "UPDATE channel_lists SET filepath = ?1, last_fetched = ?2 WHERE id = ?3",
                     &[
@@ -78,14 +82,15 @@ pub fn get_m3u_content(conn: &mut rusqlite::Connection, id: Option<i32>) -> Resu
                         &id as &dyn rusqlite::ToSql,
                     ],
                 );
+                let _ = record_decoding(conn, id, &decoded);
             }
 
-            return Ok(content);
+            return Ok(decoded.text);
         } else {
             let data_dir = dirs::data_dir().unwrap().join("xtauri");
             let channel_lists_dir = data_dir.join("channel_lists");
-            if let Ok(content) = std::fs::read_to_string(channel_lists_dir.join(&source)) {
-                return Ok(content);
+            if let Ok(decoded) = read_playlist_file(&channel_lists_dir.join(&source), list_encoding(conn, id)) {
+                return Ok(decoded.text);
             }
         }
     }
@@ -147,6 +152,14 @@ where
                 .next()
                 .unwrap_or_default()
                 .to_string();
+            let tvg_country = line
+                .split("tvg-country=\"")
+                .nth(1)
+                .unwrap_or_default()
+                .split('"')
+                .next()
+                .unwrap_or_default();
+            let country = country::detect_country(tvg_country, &name, &group_title);
             let resolution = re_resolution
                 .captures(&name)
                 .and_then(|c| c.get(1))
@@ -167,6 +180,8 @@ where
                         tvg_id,
                         resolution,
                         extra_info,
+                        country: country.unwrap_or_default().to_string(),
+                        country_flag: country.map(country::flag_emoji).unwrap_or_default(),
                     });
                     parsed_channels += 1;
                 }
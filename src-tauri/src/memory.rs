@@ -0,0 +1,160 @@
+use crate::content_cache::ContentCacheState;
+use crate::m3u_parser::Channel;
+use crate::search::{advanced_cache_usage, clear_advanced_cache};
+use crate::state::{ChannelCacheState, DbState};
+use crate::xtream::XtreamState;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::State;
+
+/// Size of one in-memory cache
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct CacheMemoryUsage {
+    pub name: String,
+    pub entries: usize,
+    pub approx_bytes: usize,
+}
+
+/// Sizes of the in-memory caches that grow over a session
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct MemoryReport {
+    pub caches: Vec<CacheMemoryUsage>,
+    pub total_bytes: usize,
+}
+
+/// What `trim_memory` released
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct TrimResult {
+    pub entries_cleared: usize,
+    pub approx_bytes_freed: usize,
+    /// Database connections whose page and statement caches were shrunk
+    pub connections_shrunk: usize,
+}
+
+/// Approximate heap and inline size of a channel
+pub fn channel_bytes(channel: &Channel) -> usize {
+    std::mem::size_of::<Channel>()
+        + channel.name.capacity()
+        + channel.logo.capacity()
+        + channel.url.capacity()
+        + channel.group_title.capacity()
+        + channel.tvg_id.capacity()
+        + channel.resolution.capacity()
+        + channel.extra_info.capacity()
+        + channel.country.capacity()
+        + channel.country_flag.capacity()
+}
+
+fn usage(name: &str, (entries, approx_bytes): (usize, usize)) -> CacheMemoryUsage {
+    CacheMemoryUsage {
+        name: name.to_string(),
+        entries,
+        approx_bytes,
+    }
+}
+
+fn channel_list_usage(cache_state: &ChannelCacheState) -> Result<(usize, usize), String> {
+    let cache = cache_state
+        .cache
+        .lock()
+        .map_err(|_| "Failed to acquire lock for channel_cache".to_string())?;
+    Ok(cache.values().fold((0, 0), |(entries, bytes), cached| {
+        (
+            entries + cached.channels.len(),
+            bytes + cached.channels.iter().map(channel_bytes).sum::<usize>(),
+        )
+    }))
+}
+
+/// Release SQLite's page cache and cached statements for a connection
+fn shrink_connection(db: &Mutex<Connection>) -> bool {
+    let Ok(conn) = db.lock() else {
+        return false;
+    };
+    conn.flush_prepared_statement_cache();
+    conn.execute_batch("PRAGMA shrink_memory").is_ok()
+}
+
+fn collect_report(
+    cache_state: &ChannelCacheState,
+    xtream_state: &XtreamState,
+) -> Result<MemoryReport, String> {
+    let caches = vec![
+        usage("channel_list", channel_list_usage(cache_state)?),
+        usage("channel_search", advanced_cache_usage()),
+        usage("xtream_responses", xtream_state.content_cache.memory_usage()),
+    ];
+    let total_bytes = caches.iter().map(|cache| cache.approx_bytes).sum();
+    Ok(MemoryReport { caches, total_bytes })
+}
+
+/// Summarize the in-memory caches with entry counts and approximate sizes
+#[tauri::command]
+pub fn get_memory_report(
+    cache_state: State<ChannelCacheState>,
+    xtream_state: State<XtreamState>,
+) -> Result<MemoryReport, String> {
+    collect_report(&cache_state, &xtream_state)
+}
+
+/// Clear the in-memory caches and shrink SQLite's per-connection memory
+///
+/// Everything cleared here is rebuilt from the database or playlist files on
+/// next use, so trimming only costs a slower first load.
+#[tauri::command]
+pub fn trim_memory(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    xtream_state: State<XtreamState>,
+    content_cache_state: State<ContentCacheState>,
+) -> Result<TrimResult, String> {
+    let before = collect_report(&cache_state, &xtream_state)?;
+
+    cache_state
+        .cache
+        .lock()
+        .map_err(|_| "Failed to acquire lock for channel_cache".to_string())?
+        .clear();
+    clear_advanced_cache();
+    xtream_state.content_cache.clear_memory();
+
+    let connections_shrunk = [
+        &db_state.db,
+        &*xtream_state.profile_manager.get_db_connection(),
+        &*content_cache_state.cache.get_db(),
+    ]
+    .into_iter()
+    .filter(|db| shrink_connection(db))
+    .count();
+
+    Ok(TrimResult {
+        entries_cleared: before.caches.iter().map(|cache| cache.entries).sum(),
+        approx_bytes_freed: before.total_bytes,
+        connections_shrunk,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_bytes_counts_strings() {
+        let channel = Channel {
+            name: "BBC One".to_string(),
+            logo: String::new(),
+            url: "http://example.com/bbc1".to_string(),
+            group_title: "UK".to_string(),
+            tvg_id: String::new(),
+            resolution: String::new(),
+            extra_info: String::new(),
+            country: String::new(),
+            country_flag: String::new(),
+        };
+        assert_eq!(
+            channel_bytes(&channel),
+            std::mem::size_of::<Channel>() + 7 + 23 + 2
+        );
+    }
+}
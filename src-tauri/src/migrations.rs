@@ -0,0 +1,222 @@
+// Schema migrations
+//
+// Each part of the database with a schema of its own (the app tables, the
+// content cache) is a component with an ordered list of migrations. The
+// `schema_version` table records how far each component has been migrated,
+// and `run` applies the ones a database hasn't seen yet, each in its own
+// transaction with its version bump, so a failed step is retried on the next
+// start instead of leaving a half-migrated schema. A new column is a new
+// migration at the end of its component's list, never an edit of an old one.
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Component of the app's own tables, set up by `database::initialize_database`
+pub const APP: &str = "app";
+
+/// Component of the content cache tables
+pub const CONTENT_CACHE: &str = "content_cache";
+
+/// One step of a component's schema
+pub struct Migration {
+    /// Version the schema is at once this has run; counts up from 1
+    pub version: i32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> Result<()>,
+}
+
+/// Create the version table, converting the content cache's single-row table of older releases
+fn ensure_version_table(conn: &Connection) -> Result<()> {
+    let legacy: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('schema_version') WHERE name = 'id'",
+        [],
+        |row| row.get(0),
+    )?;
+    if legacy {
+        let tx = conn.unchecked_transaction()?;
+        let version: Option<i32> = tx
+            .query_row("SELECT version FROM schema_version WHERE id = 1", [], |row| row.get(0))
+            .optional()?;
+        tx.execute("DROP TABLE schema_version", [])?;
+        create_version_table(&tx)?;
+        if let Some(version) = version {
+            set_version(&tx, CONTENT_CACHE, version)?;
+        }
+        tx.commit()?;
+        return Ok(());
+    }
+
+    create_version_table(conn)
+}
+
+fn create_version_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            component TEXT PRIMARY KEY,
+            version INTEGER NOT NULL,
+            updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Version a component's schema is at; 0 when it has never been set up
+pub fn current_version(conn: &Connection, component: &str) -> Result<i32> {
+    ensure_version_table(conn)?;
+    let version = conn
+        .query_row(
+            "SELECT version FROM schema_version WHERE component = ?1",
+            [component],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Record the version a component's schema is at
+pub fn set_version(conn: &Connection, component: &str, version: i32) -> Result<()> {
+    ensure_version_table(conn)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO schema_version (component, version, updated_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+        params![component, version],
+    )?;
+    Ok(())
+}
+
+/// Version a component is at after all of its migrations
+pub fn latest_version(migrations: &[Migration]) -> i32 {
+    migrations.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+/// Apply the migrations a component hasn't run yet, in order
+///
+/// Returns the number applied. A database already past the newest migration,
+/// e.g. one an older release opens after a downgrade, is left alone.
+pub fn run(conn: &Connection, component: &str, migrations: &[Migration]) -> Result<usize> {
+    for (expected, migration) in (1..).zip(migrations) {
+        if migration.version != expected {
+            return Err(XTauriError::internal(format!(
+                "{} migration {} is out of order, expected version {}",
+                component, migration.version, expected
+            )));
+        }
+    }
+
+    let current = current_version(conn, component)?;
+    if current > latest_version(migrations) {
+        eprintln!(
+            "[MIGRATIONS] {} schema is at version {}, newer than this release knows ({})",
+            component,
+            current,
+            latest_version(migrations)
+        );
+    }
+    let mut applied = 0;
+    for migration in migrations.iter().filter(|migration| migration.version > current) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx).map_err(|e| {
+            XTauriError::internal(format!(
+                "{} migration {} ({}) failed: {}",
+                component, migration.version, migration.description, e
+            ))
+        })?;
+        set_version(&tx, component, migration.version)?;
+        tx.commit()?;
+        println!(
+            "[MIGRATIONS] Migrated {} to version {}: {}",
+            component, migration.version, migration.description
+        );
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Whether a table already has a column, for migrations that may meet a column added before they existed
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let exists = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEPS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "Create items",
+            up: |conn| {
+                conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY)", [])?;
+                Ok(())
+            },
+        },
+        Migration {
+            version: 2,
+            description: "Add item names",
+            up: |conn| {
+                conn.execute("ALTER TABLE items ADD COLUMN name TEXT", [])?;
+                Ok(())
+            },
+        },
+    ];
+
+    #[test]
+    fn test_pending_migrations_run_once() {
+        let conn = Connection::open_in_memory().unwrap();
+
+        assert_eq!(run(&conn, APP, &STEPS[..1]).unwrap(), 1);
+        assert_eq!(current_version(&conn, APP).unwrap(), 1);
+        // A later release adds a step; only that one runs
+        assert_eq!(run(&conn, APP, STEPS).unwrap(), 1);
+        assert_eq!(run(&conn, APP, STEPS).unwrap(), 0);
+        assert_eq!(current_version(&conn, APP).unwrap(), latest_version(STEPS));
+        assert!(has_column(&conn, "items", "name").unwrap());
+        assert_eq!(current_version(&conn, CONTENT_CACHE).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        let failing = [
+            Migration { ..STEPS[0] },
+            Migration {
+                version: 2,
+                description: "Broken",
+                up: |conn| {
+                    conn.execute("ALTER TABLE items ADD COLUMN name TEXT", [])?;
+                    conn.execute("ALTER TABLE missing ADD COLUMN name TEXT", [])?;
+                    Ok(())
+                },
+            },
+        ];
+
+        assert!(run(&conn, APP, &failing).is_err());
+        assert_eq!(current_version(&conn, APP).unwrap(), 1);
+        assert!(!has_column(&conn, "items", "name").unwrap());
+
+        let out_of_order = [Migration { version: 3, ..STEPS[1] }];
+        assert!(run(&conn, APP, &out_of_order).is_err());
+    }
+
+    #[test]
+    fn test_single_row_version_table_is_converted() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE schema_version (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                version INTEGER NOT NULL,
+                updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO schema_version (id, version) VALUES (1, 12);",
+        )
+        .unwrap();
+
+        assert_eq!(current_version(&conn, CONTENT_CACHE).unwrap(), 12);
+        assert_eq!(current_version(&conn, APP).unwrap(), 0);
+        assert!(!has_column(&conn, "schema_version", "id").unwrap());
+    }
+}
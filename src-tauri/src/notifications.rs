@@ -0,0 +1,440 @@
+// Notification pipeline
+//
+// Reminders, sync results and alerts about new episodes or new items in
+// followed categories are all sent with `notify`, which hands them to every
+// `NotificationChannel` the user hasn't switched off for that kind of
+// notification. Two channels are built in: the
+// operating system's notifications and the in-app inbox, kept in the
+// `notifications` table with its read state. Other channels, such as email,
+// are added by implementing the trait and listing it in `CHANNELS`.
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use tauri::{AppHandle, Manager, State};
+
+/// Inbox entries beyond this many are dropped, oldest first
+const INBOX_LIMIT: i64 = 500;
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// An EPG programme with a reminder is about to start
+    Reminder,
+    /// A content sync finished or failed
+    SyncResult,
+    /// A favorited series has new episodes
+    NewEpisode,
+    /// A followed category has new items
+    FollowedCategory,
+    /// The weekly digest is ready
+    Digest,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 5] = [
+        NotificationKind::Reminder,
+        NotificationKind::SyncResult,
+        NotificationKind::NewEpisode,
+        NotificationKind::FollowedCategory,
+        NotificationKind::Digest,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::Reminder => "reminder",
+            NotificationKind::SyncResult => "sync_result",
+            NotificationKind::NewEpisode => "new_episode",
+            NotificationKind::FollowedCategory => "followed_category",
+            NotificationKind::Digest => "digest",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// A notification on its way to the channels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub profile_id: Option<String>,
+}
+
+/// A notification in the in-app inbox
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct InboxNotification {
+    pub id: i64,
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+    pub profile_id: Option<String>,
+    /// Unix timestamp
+    pub created_at: i64,
+    pub read: bool,
+}
+
+/// Whether a channel delivers one kind of notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct NotificationRoute {
+    pub kind: NotificationKind,
+    pub channel: String,
+    pub enabled: bool,
+}
+
+/// A way of getting a notification to the user
+pub trait NotificationChannel: Send + Sync {
+    /// Stable identifier, used for routes
+    fn id(&self) -> &'static str;
+
+    fn deliver(&self, app_handle: &AppHandle, notification: &Notification) -> Result<()>;
+}
+
+/// The operating system's notification center
+///
+/// The platform tool is run on a blocking thread so callers on the async
+/// runtime never wait on it; its failures are logged from there.
+struct SystemChannel;
+
+impl NotificationChannel for SystemChannel {
+    fn id(&self) -> &'static str {
+        "system"
+    }
+
+    fn deliver(&self, _app_handle: &AppHandle, notification: &Notification) -> Result<()> {
+        let title = notification.title.clone();
+        let body = notification.body.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            if let Err(e) = show_system_notification(&title, &body) {
+                eprintln!("[NOTIFICATIONS] system failed to deliver \"{}\": {}", title, e);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn show_system_notification(title: &str, body: &str) -> Result<()> {
+    let status = std::process::Command::new("notify-send")
+        .args(["--app-name=xTauri", title, body])
+        .status()?;
+    if !status.success() {
+        return Err(XTauriError::internal(format!("notify-send exited with {}", status)));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn show_system_notification(title: &str, body: &str) -> Result<()> {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let script = format!("display notification {} with title {}", quote(body), quote(title));
+    let status = std::process::Command::new("osascript").args(["-e", &script]).status()?;
+    if !status.success() {
+        return Err(XTauriError::internal(format!("osascript exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Shows a toast through the WinRT notification API under the bundle identifier the
+/// installer registers; title and body are passed
+/// as environment variables so they never need escaping into the script
+#[cfg(target_os = "windows")]
+fn show_system_notification(title: &str, body: &str) -> Result<()> {
+    const SCRIPT: &str = r#"
+[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null
+$template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $template.GetElementsByTagName('text')
+$text.Item(0).AppendChild($template.CreateTextNode($env:XTAURI_TOAST_TITLE)) | Out-Null
+$text.Item(1).AppendChild($template.CreateTextNode($env:XTAURI_TOAST_BODY)) | Out-Null
+$toast = [Windows.UI.Notifications.ToastNotification]::new($template)
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('com.xtauri.app').Show($toast)
+"#;
+    let status = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-WindowStyle", "Hidden", "-Command", SCRIPT])
+        .env("XTAURI_TOAST_TITLE", title)
+        .env("XTAURI_TOAST_BODY", body)
+        .status()?;
+    if !status.success() {
+        return Err(XTauriError::internal(format!("powershell exited with {}", status)));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn show_system_notification(_title: &str, _body: &str) -> Result<()> {
+    Err(XTauriError::internal(
+        "System notifications aren't supported on this platform".to_string(),
+    ))
+}
+
+/// The in-app inbox; new entries are announced with `notification_added`
+struct InboxChannel;
+
+impl NotificationChannel for InboxChannel {
+    fn id(&self) -> &'static str {
+        "inbox"
+    }
+
+    fn deliver(&self, app_handle: &AppHandle, notification: &Notification) -> Result<()> {
+        let entry = {
+            let db = app_handle.state::<DbState>();
+            let db = db
+                .db
+                .lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            add_to_inbox(&db, notification, chrono::Utc::now().timestamp())?
+        };
+        events::emit(app_handle, AppEvent::NotificationAdded(entry));
+        Ok(())
+    }
+}
+
+/// Channels notifications are delivered through, in order
+static CHANNELS: LazyLock<Vec<Box<dyn NotificationChannel>>> =
+    LazyLock::new(|| vec![Box::new(SystemChannel), Box::new(InboxChannel)]);
+
+fn channel_ids() -> Vec<&'static str> {
+    CHANNELS.iter().map(|channel| channel.id()).collect()
+}
+
+/// `(kind, channel)` pairs the user switched off; every other route is on
+fn disabled_routes(conn: &Connection) -> Result<HashSet<(NotificationKind, String)>> {
+    let mut stmt = conn.prepare("SELECT kind, channel FROM notification_routes WHERE enabled = 0")?;
+    let routes = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|row| row.ok())
+        .filter_map(|(kind, channel)| Some((NotificationKind::parse(&kind)?, channel)))
+        .collect();
+    Ok(routes)
+}
+
+fn list_routes(conn: &Connection, channels: &[&str]) -> Result<Vec<NotificationRoute>> {
+    let disabled = disabled_routes(conn)?;
+    Ok(NotificationKind::ALL
+        .into_iter()
+        .flat_map(|kind| {
+            let disabled = &disabled;
+            channels.iter().map(move |channel| NotificationRoute {
+                kind,
+                channel: channel.to_string(),
+                enabled: !disabled.contains(&(kind, channel.to_string())),
+            })
+        })
+        .collect())
+}
+
+fn set_route(conn: &Connection, kind: NotificationKind, channel: &str, enabled: bool) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO notification_routes (kind, channel, enabled) VALUES (?1, ?2, ?3)",
+        params![kind.as_str(), channel, enabled],
+    )?;
+    Ok(())
+}
+
+/// Store a notification in the inbox, trimming it to `INBOX_LIMIT` entries
+pub fn add_to_inbox(conn: &Connection, notification: &Notification, now: i64) -> Result<InboxNotification> {
+    conn.execute(
+        "INSERT INTO notifications (kind, title, body, profile_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            notification.kind.as_str(),
+            notification.title,
+            notification.body,
+            notification.profile_id,
+            now
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM notifications WHERE id <= ?1 - ?2",
+        params![id, INBOX_LIMIT],
+    )?;
+
+    Ok(InboxNotification {
+        id,
+        kind: notification.kind,
+        title: notification.title.clone(),
+        body: notification.body.clone(),
+        profile_id: notification.profile_id.clone(),
+        created_at: now,
+        read: false,
+    })
+}
+
+/// Inbox entries, newest first
+pub fn inbox(conn: &Connection, unread_only: bool, limit: i64) -> Result<Vec<InboxNotification>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, title, body, profile_id, created_at, read_at IS NOT NULL
+         FROM notifications
+         WHERE ?1 = 0 OR read_at IS NULL
+         ORDER BY id DESC
+         LIMIT ?2",
+    )?;
+    let entries = stmt
+        .query_map(params![unread_only, limit], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, bool>(6)?,
+            ))
+        })?
+        .filter_map(|row| row.ok())
+        .filter_map(|(id, kind, title, body, profile_id, created_at, read)| {
+            Some(InboxNotification {
+                id,
+                kind: NotificationKind::parse(&kind)?,
+                title,
+                body,
+                profile_id,
+                created_at,
+                read,
+            })
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Mark inbox entries read; all of them when `ids` is `None`
+pub fn mark_read(conn: &Connection, ids: Option<&[i64]>, now: i64) -> Result<usize> {
+    let Some(ids) = ids else {
+        return Ok(conn.execute("UPDATE notifications SET read_at = ?1 WHERE read_at IS NULL", [now])?);
+    };
+    let mut stmt = conn.prepare("UPDATE notifications SET read_at = ?1 WHERE id = ?2 AND read_at IS NULL")?;
+    let mut marked = 0;
+    for id in ids {
+        marked += stmt.execute(params![now, id])?;
+    }
+    Ok(marked)
+}
+
+/// Send a notification through every channel routed for its kind
+///
+/// Delivery failures are logged; one channel failing doesn't stop the others.
+pub fn notify(app_handle: &AppHandle, notification: Notification) {
+    let disabled = match app_handle.state::<DbState>().db.lock() {
+        Ok(db) => disabled_routes(&db).unwrap_or_default(),
+        Err(_) => HashSet::new(),
+    };
+    for channel in CHANNELS.iter() {
+        if disabled.contains(&(notification.kind, channel.id().to_string())) {
+            continue;
+        }
+        if let Err(e) = channel.deliver(app_handle, &notification) {
+            eprintln!("[NOTIFICATIONS] {} failed to deliver \"{}\": {}", channel.id(), notification.title, e);
+        }
+    }
+}
+
+/// Inbox entries, newest first
+#[tauri::command]
+pub fn get_notifications(
+    state: State<DbState>,
+    unread_only: Option<bool>,
+    limit: Option<i64>,
+) -> std::result::Result<Vec<InboxNotification>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    inbox(&db, unread_only.unwrap_or(false), limit.unwrap_or(100)).map_err(|e| e.to_string())
+}
+
+/// Mark inbox entries read, or all of them without `ids`; returns how many changed
+#[tauri::command]
+pub fn mark_notifications_read(
+    state: State<DbState>,
+    ids: Option<Vec<i64>>,
+) -> std::result::Result<usize, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    mark_read(&db, ids.as_deref(), chrono::Utc::now().timestamp()).map_err(|e| e.to_string())
+}
+
+/// Every kind of notification with every channel, and whether it is delivered there
+#[tauri::command]
+pub fn get_notification_routes(state: State<DbState>) -> std::result::Result<Vec<NotificationRoute>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    list_routes(&db, &channel_ids()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_notification_route(
+    state: State<DbState>,
+    kind: NotificationKind,
+    channel: String,
+    enabled: bool,
+) -> std::result::Result<(), String> {
+    if !channel_ids().contains(&channel.as_str()) {
+        return Err(format!("Unknown notification channel: {}", channel));
+    }
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    set_route(&db, kind, &channel, enabled).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(kind: NotificationKind, title: &str) -> Notification {
+        Notification {
+            kind,
+            title: title.to_string(),
+            body: String::new(),
+            profile_id: Some("p1".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_inbox_and_routes() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notifications (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                kind TEXT NOT NULL,
+                title TEXT NOT NULL,
+                body TEXT NOT NULL,
+                profile_id TEXT,
+                created_at INTEGER NOT NULL,
+                read_at INTEGER
+            );
+            CREATE TABLE notification_routes (
+                kind TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                enabled BOOLEAN NOT NULL,
+                PRIMARY KEY (kind, channel)
+            );",
+        )
+        .unwrap();
+
+        let first = add_to_inbox(&conn, &notification(NotificationKind::SyncResult, "Synced"), 100).unwrap();
+        add_to_inbox(&conn, &notification(NotificationKind::NewEpisode, "New episodes"), 200).unwrap();
+        let entries = inbox(&conn, false, 10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, NotificationKind::NewEpisode);
+
+        assert_eq!(mark_read(&conn, Some(&[first.id]), 300).unwrap(), 1);
+        let unread = inbox(&conn, true, 10).unwrap();
+        assert_eq!(unread.len(), 1);
+        assert_eq!(unread[0].title, "New episodes");
+        assert_eq!(mark_read(&conn, None, 300).unwrap(), 1);
+        assert!(inbox(&conn, true, 10).unwrap().is_empty());
+
+        for i in 0..INBOX_LIMIT {
+            add_to_inbox(&conn, &notification(NotificationKind::Reminder, &i.to_string()), 400).unwrap();
+        }
+        assert_eq!(inbox(&conn, false, 1000).unwrap().len() as i64, INBOX_LIMIT);
+
+        set_route(&conn, NotificationKind::Reminder, "system", false).unwrap();
+        let routes = list_routes(&conn, &["system", "inbox"]).unwrap();
+        assert_eq!(routes.len(), NotificationKind::ALL.len() * 2);
+        let off: Vec<_> = routes.iter().filter(|route| !route.enabled).collect();
+        assert_eq!(off.len(), 1);
+        assert_eq!((off[0].kind, off[0].channel.as_str()), (NotificationKind::Reminder, "system"));
+    }
+}
@@ -0,0 +1,151 @@
+// Paginated list responses
+//
+// Listing and search commands return a `Page` so the UI gets the total along
+// with the items instead of making a separate count call. Database-backed
+// pages take the total from a `COUNT(*) OVER ()` column on the same query.
+use crate::error::Result;
+use rusqlite::{Connection, Row, ToSql};
+use serde::{Deserialize, Serialize};
+
+/// One page of a list, with the size of the whole list
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Items in the whole list, not just this page
+    pub total: usize,
+    /// Page size; `None` when the rest of the list was requested
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: usize, limit: Option<usize>, offset: usize) -> Self {
+        let has_more = offset + items.len() < total;
+        Self {
+            items,
+            total,
+            limit,
+            offset,
+            has_more,
+        }
+    }
+
+    /// Cut a page out of a list that is already in memory
+    pub fn from_vec(all: Vec<T>, limit: Option<usize>, offset: Option<usize>) -> Self {
+        let total = all.len();
+        let offset = offset.unwrap_or(0);
+        let items = all
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        Self::new(items, total, limit, offset)
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            limit: self.limit,
+            offset: self.offset,
+            has_more: self.has_more,
+        }
+    }
+}
+
+/// An empty page
+impl<T> Default for Page<T> {
+    fn default() -> Self {
+        Self::new(Vec::new(), 0, None, 0)
+    }
+}
+
+/// Run a list query one page at a time
+///
+/// `sql` must select `COUNT(*) OVER ()` as its last column and must not have a
+/// LIMIT or OFFSET; they are appended here. SQLite evaluates the window before
+/// the LIMIT, so every row carries the unpaged total. A page past the end has
+/// no rows to read it from, so the total is then counted separately.
+pub fn query_page<T>(
+    conn: &Connection,
+    sql: &str,
+    params: &[&dyn ToSql],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    mut map_row: impl FnMut(&Row<'_>) -> rusqlite::Result<T>,
+) -> Result<Page<T>> {
+    let offset = offset.unwrap_or(0);
+    let mut paged = sql.to_string();
+    if let Some(limit) = limit {
+        paged.push_str(&format!(" LIMIT {}", limit));
+    } else if offset > 0 {
+        paged.push_str(" LIMIT -1");
+    }
+    if offset > 0 {
+        paged.push_str(&format!(" OFFSET {}", offset));
+    }
+
+    let mut stmt = conn.prepare(&paged)?;
+    let total_column = stmt.column_count() - 1;
+    let mut total = 0;
+    let items = stmt
+        .query_map(params, |row| {
+            total = row.get::<_, i64>(total_column)? as usize;
+            map_row(row)
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    if items.is_empty() && offset > 0 {
+        let count_sql = format!("SELECT COUNT(*) FROM ({})", sql);
+        total = conn.query_row(&count_sql, params, |row| row.get::<_, i64>(0))? as usize;
+    }
+
+    Ok(Page::new(items, total, limit, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE numbers (n INTEGER NOT NULL);
+             WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 25)
+             INSERT INTO numbers SELECT n FROM seq;",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_query_page_totals() {
+        let conn = numbers_db();
+        let sql = "SELECT n, COUNT(*) OVER () FROM numbers WHERE n > ?1 ORDER BY n";
+        let params: [&dyn ToSql; 1] = [&5];
+
+        let page = query_page(&conn, sql, &params, Some(10), Some(10), |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(page.items, (16..=25).collect::<Vec<_>>());
+        assert_eq!((page.total, page.offset, page.has_more), (20, 10, false));
+
+        let page = query_page(&conn, sql, &params, Some(10), None, |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!((page.items.len(), page.total, page.has_more), (10, 20, true));
+
+        // Past the end the total still comes back
+        let page = query_page(&conn, sql, &params, Some(10), Some(40), |row| row.get::<_, i64>(0)).unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!((page.total, page.has_more), (20, false));
+    }
+
+    #[test]
+    fn test_page_from_vec() {
+        let page = Page::from_vec((1..=7).collect(), Some(3), Some(3));
+        assert_eq!(page.items, vec![4, 5, 6]);
+        assert_eq!((page.total, page.has_more), (7, true));
+
+        let page = Page::from_vec((1..=7).collect::<Vec<i32>>(), None, Some(5)).map(|n| n * 10);
+        assert_eq!(page.items, vec![60, 70]);
+        assert!(!page.has_more);
+    }
+}
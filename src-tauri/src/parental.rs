@@ -0,0 +1,298 @@
+// Parental PIN and its audit log
+//
+// Parental controls are active once a PIN is set. Every attempt to get past
+// them with the PIN - changing the age limit or the PIN, unlocking content,
+// reading the log itself - is recorded in `parental_audit_log` with whether the
+// PIN was right, so parents can see when it was being guessed at. The log can
+// only be read with the PIN, and entries older than the retention period are
+// dropped as new ones are written.
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use base64::{engine::general_purpose, Engine as _};
+use pbkdf2::pbkdf2_hmac;
+use rand::{thread_rng, RngCore};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::State;
+
+/// Days audit entries are kept unless changed
+pub const DEFAULT_AUDIT_RETENTION_DAYS: i64 = 90;
+
+/// Longest retention that can be set
+const MAX_AUDIT_RETENTION_DAYS: i64 = 3650;
+
+const PIN_HASH_ROUNDS: u32 = 100_000;
+
+/// Audit targets for the actions this module guards itself
+pub const TARGET_AGE_LIMIT: &str = "age_limit";
+const TARGET_PIN: &str = "pin";
+const TARGET_AUDIT_LOG: &str = "audit_log";
+const TARGET_RETENTION: &str = "audit_retention";
+
+/// One attempt to unlock parental controls
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ParentalAuditEntry {
+    pub id: i64,
+    pub attempted_at: i64,
+    pub success: bool,
+    /// What the PIN was entered for, e.g. `age_limit` or a content id
+    pub target: String,
+}
+
+fn hash_pin(pin: &str, salt: &[u8]) -> String {
+    let mut hash = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, PIN_HASH_ROUNDS, &mut hash);
+    general_purpose::STANDARD.encode(hash)
+}
+
+/// Compare hashes without short-circuiting on the first differing byte
+fn hashes_match(expected: &str, provided: &str) -> bool {
+    expected.len() == provided.len()
+        && expected
+            .bytes()
+            .zip(provided.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// The stored salt and hash, if a PIN is set
+fn stored_pin(conn: &Connection) -> Result<Option<(Vec<u8>, String)>> {
+    let stored: Option<(Option<String>, Option<String>)> = conn
+        .query_row(
+            "SELECT parental_pin_salt, parental_pin_hash FROM settings WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((Some(salt), Some(hash))) = stored else {
+        return Ok(None);
+    };
+    let salt = general_purpose::STANDARD
+        .decode(salt)
+        .map_err(|e| XTauriError::internal(format!("Invalid parental PIN salt: {}", e)))?;
+    Ok(Some((salt, hash)))
+}
+
+pub fn is_pin_set(conn: &Connection) -> Result<bool> {
+    Ok(stored_pin(conn)?.is_some())
+}
+
+fn validate_pin(pin: &str) -> Result<()> {
+    if !(4..=8).contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(XTauriError::internal("The PIN must be 4 to 8 digits".to_string()));
+    }
+    Ok(())
+}
+
+fn save_pin(conn: &Connection, pin: Option<&str>) -> Result<()> {
+    let (salt, hash) = match pin {
+        Some(pin) => {
+            let mut salt = [0u8; 16];
+            thread_rng().fill_bytes(&mut salt);
+            (Some(general_purpose::STANDARD.encode(salt)), Some(hash_pin(pin, &salt)))
+        }
+        None => (None, None),
+    };
+    conn.execute(
+        "UPDATE settings SET parental_pin_salt = ?1, parental_pin_hash = ?2 WHERE id = 1",
+        params![salt, hash],
+    )?;
+    Ok(())
+}
+
+pub fn audit_retention_days(conn: &Connection) -> Result<i64> {
+    let days: Option<i64> = conn
+        .query_row(
+            "SELECT parental_audit_retention_days FROM settings WHERE id = 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(days.unwrap_or(DEFAULT_AUDIT_RETENTION_DAYS))
+}
+
+fn record_attempt(conn: &Connection, success: bool, target: &str, now: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO parental_audit_log (attempted_at, success, target) VALUES (?1, ?2, ?3)",
+        params![now, success, target],
+    )?;
+    let cutoff = now - audit_retention_days(conn)? * 86_400;
+    conn.execute("DELETE FROM parental_audit_log WHERE attempted_at < ?1", [cutoff])?;
+    Ok(())
+}
+
+/// Check a PIN entered to unlock `target`, recording the attempt
+///
+/// Always passes without a record while no PIN is set, since parental controls
+/// are off then.
+pub fn verify_and_record(conn: &Connection, pin: Option<&str>, target: &str, now: i64) -> Result<bool> {
+    let Some((salt, hash)) = stored_pin(conn)? else {
+        return Ok(true);
+    };
+    let success = pin.is_some_and(|pin| hashes_match(&hash, &hash_pin(pin, &salt)));
+    record_attempt(conn, success, target, now)?;
+    Ok(success)
+}
+
+/// Like `verify_and_record`, but a wrong PIN is an error
+pub fn require_pin(conn: &Connection, pin: Option<&str>, target: &str, now: i64) -> Result<()> {
+    if verify_and_record(conn, pin, target, now)? {
+        Ok(())
+    } else {
+        Err(XTauriError::internal("Incorrect parental PIN".to_string()))
+    }
+}
+
+pub fn audit_log(conn: &Connection, limit: i64) -> Result<Vec<ParentalAuditEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, attempted_at, success, target FROM parental_audit_log
+         ORDER BY attempted_at DESC, id DESC LIMIT ?1",
+    )?;
+    let entries = stmt
+        .query_map([limit], |row| {
+            Ok(ParentalAuditEntry {
+                id: row.get(0)?,
+                attempted_at: row.get(1)?,
+                success: row.get(2)?,
+                target: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn is_parental_pin_set(state: State<DbState>) -> std::result::Result<bool, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    is_pin_set(&db).map_err(|e| e.to_string())
+}
+
+/// Set, change or remove (`new_pin` of `None`) the parental PIN
+///
+/// The current PIN is required once one is set.
+#[tauri::command]
+pub fn set_parental_pin(
+    state: State<DbState>,
+    current_pin: Option<String>,
+    new_pin: Option<String>,
+) -> std::result::Result<(), String> {
+    if let Some(pin) = &new_pin {
+        validate_pin(pin).map_err(|e| e.to_string())?;
+    }
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().timestamp();
+    require_pin(&db, current_pin.as_deref(), TARGET_PIN, now).map_err(|e| e.to_string())?;
+    save_pin(&db, new_pin.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Check the PIN before showing something parental controls hide
+///
+/// `target` names what is being unlocked and is kept in the audit log.
+#[tauri::command]
+pub fn unlock_parental_controls(
+    state: State<DbState>,
+    pin: String,
+    target: String,
+) -> std::result::Result<bool, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    verify_and_record(&db, Some(&pin), &target, chrono::Utc::now().timestamp()).map_err(|e| e.to_string())
+}
+
+/// Most recent unlock attempts, newest first; requires the PIN
+#[tauri::command]
+pub fn get_parental_audit_log(
+    state: State<DbState>,
+    pin: String,
+    limit: Option<i64>,
+) -> std::result::Result<Vec<ParentalAuditEntry>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    require_pin(&db, Some(&pin), TARGET_AUDIT_LOG, chrono::Utc::now().timestamp()).map_err(|e| e.to_string())?;
+    audit_log(&db, limit.unwrap_or(200)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_parental_audit_retention(state: State<DbState>) -> std::result::Result<i64, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    audit_retention_days(&db).map_err(|e| e.to_string())
+}
+
+/// Keep audit entries for `days`; requires the PIN
+#[tauri::command]
+pub fn set_parental_audit_retention(
+    state: State<DbState>,
+    pin: String,
+    days: i64,
+) -> std::result::Result<(), String> {
+    if !(1..=MAX_AUDIT_RETENTION_DAYS).contains(&days) {
+        return Err(format!("Retention must be between 1 and {} days", MAX_AUDIT_RETENTION_DAYS));
+    }
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    require_pin(&db, Some(&pin), TARGET_RETENTION, chrono::Utc::now().timestamp()).map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET parental_audit_retention_days = ?1 WHERE id = 1",
+        [days],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (
+                id INTEGER PRIMARY KEY,
+                parental_pin_salt TEXT,
+                parental_pin_hash TEXT,
+                parental_audit_retention_days INTEGER NOT NULL DEFAULT 90
+            );
+            INSERT INTO settings (id) VALUES (1);
+            CREATE TABLE parental_audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                attempted_at INTEGER NOT NULL,
+                success BOOLEAN NOT NULL,
+                target TEXT NOT NULL
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_attempts_are_recorded_once_a_pin_is_set() {
+        let conn = setup_db();
+        let day = 86_400;
+
+        // No PIN, no parental controls and nothing to record
+        assert!(verify_and_record(&conn, None, "movie:1", 0).unwrap());
+        assert!(audit_log(&conn, 10).unwrap().is_empty());
+
+        save_pin(&conn, Some("1234")).unwrap();
+        assert!(is_pin_set(&conn).unwrap());
+        assert!(!verify_and_record(&conn, Some("0000"), "movie:1", day).unwrap());
+        assert!(!verify_and_record(&conn, None, TARGET_AGE_LIMIT, 2 * day).unwrap());
+        assert!(verify_and_record(&conn, Some("1234"), "movie:1", 3 * day).unwrap());
+        assert!(require_pin(&conn, Some("1111"), TARGET_AUDIT_LOG, 3 * day).is_err());
+
+        let log = audit_log(&conn, 10).unwrap();
+        let attempts: Vec<(bool, &str)> = log.iter().map(|entry| (entry.success, entry.target.as_str())).collect();
+        assert_eq!(
+            attempts,
+            vec![(false, TARGET_AUDIT_LOG), (true, "movie:1"), (false, TARGET_AGE_LIMIT), (false, "movie:1")]
+        );
+
+        // Entries past the retention period go as new ones are written
+        conn.execute("UPDATE settings SET parental_audit_retention_days = 30", []).unwrap();
+        assert!(verify_and_record(&conn, Some("1234"), "series:2", 32 * day).unwrap());
+        let targets: Vec<String> = audit_log(&conn, 10).unwrap().into_iter().map(|entry| entry.target).collect();
+        assert_eq!(targets, vec!["series:2", TARGET_AUDIT_LOG, "movie:1", TARGET_AGE_LIMIT]);
+
+        save_pin(&conn, None).unwrap();
+        assert!(!is_pin_set(&conn).unwrap());
+    }
+}
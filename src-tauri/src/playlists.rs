@@ -1,10 +1,18 @@
 // Module declarations
 mod crud;
+mod encoding;
+mod export;
 mod fetch;
 
 mod types;
+mod watch;
+mod xtream_bridge;
 
 // Re-export all public items from the sub-modules
 pub use crud::*;
+pub use encoding::*;
+pub use export::*;
 pub use fetch::*;
 pub use types::*;
+pub use watch::*;
+pub use xtream_bridge::*;
@@ -1,4 +1,5 @@
 use crate::channels::invalidate_channel_cache;
+use crate::playlists::encoding::{list_encoding, read_playlist_file};
 use crate::playlists::fetch::refresh_channel_list_async;
 use crate::playlists::types::FetchState;
 use crate::state::{ChannelCacheState, ChannelList, DbState};
@@ -8,7 +9,11 @@ use tauri::{AppHandle, State};
 pub fn get_channel_lists(state: State<DbState>) -> Result<Vec<ChannelList>, String> {
     let db = state.db.lock().unwrap();
     let mut stmt = db
-        .prepare("SELECT id, name, source, is_default, filepath, last_fetched FROM channel_lists")
+        .prepare(
+            "SELECT id, name, source, is_default, filepath, last_fetched, encoding,
+                    detected_encoding, encoding_warning
+             FROM channel_lists",
+        )
         .map_err(|e| e.to_string())?;
     let list_iter = stmt
         .query_map([], |row| {
@@ -19,6 +24,9 @@ pub fn get_channel_lists(state: State<DbState>) -> Result<Vec<ChannelList>, Stri
                 is_default: row.get(3)?,
                 filepath: row.get(4)?,
                 last_fetched: row.get(5)?,
+                encoding: row.get(6)?,
+                detected_encoding: row.get(7)?,
+                encoding_warning: row.get(8)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -27,7 +35,7 @@ pub fn get_channel_lists(state: State<DbState>) -> Result<Vec<ChannelList>, Stri
     for list in list_iter {
         lists.push(list.map_err(|e| e.to_string())?);
     }
-    Ok(lists)
+    crate::anonymize::mask_result(lists)
 }
 
 #[tauri::command]
@@ -133,8 +141,9 @@ pub async fn start_channel_list_selection_async(
             }
             
             // Read and validate the file
-            match std::fs::read_to_string(&source) {
-                Ok(content) => {
+            match read_playlist_file(std::path::Path::new(&source), list_encoding(&db, id)) {
+                Ok(decoded) => {
+                    let content = decoded.text;
                     if content.trim().is_empty() || !content.trim_start().starts_with("#EXTM3U") {
                         return Err("Invalid M3U playlist file".to_string());
                     }
@@ -0,0 +1,496 @@
+// Character encoding of M3U playlists
+//
+// Playlists from some providers are saved in a legacy code page rather than
+// UTF-8. The raw bytes are decoded once when a list is fetched or read, using
+// the list's configured encoding or auto-detection, and the cached copy is
+// always stored as UTF-8.
+use crate::channels::invalidate_channel_cache;
+use crate::profiling::profile_span;
+use crate::state::{ChannelCacheState, DbState};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+/// Setting value that lets the list's encoding be detected from its content
+pub const AUTO_ENCODING: &str = "auto";
+
+/// Letters that almost only appear when Turkish text is read as Windows-1252
+const RARE_LATIN_LETTERS: [char; 6] = ['Ð', 'Ý', 'Þ', 'ð', 'ý', 'þ'];
+
+/// Lead characters of UTF-8 sequences when they are read as Windows-1252
+const MOJIBAKE_LEADS: [char; 8] = ['Ã', 'Â', 'Ä', 'Å', 'Ð', 'Ñ', 'Ø', 'Ù'];
+
+/// A character encoding a playlist can be decoded with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistEncoding {
+    Utf8,
+    Windows1251,
+    Windows1252,
+    Windows1254,
+    Windows1256,
+    Iso8859_1,
+    Iso8859_9,
+}
+
+/// Single-byte encodings tried by auto-detection, in order of preference on a tie
+///
+/// ISO-8859-1 and ISO-8859-9 only differ from Windows-1252 and Windows-1254 in
+/// control characters, so the Windows code pages stand in for them.
+const AUTO_CANDIDATES: [PlaylistEncoding; 4] = [
+    PlaylistEncoding::Windows1252,
+    PlaylistEncoding::Windows1254,
+    PlaylistEncoding::Windows1251,
+    PlaylistEncoding::Windows1256,
+];
+
+impl PlaylistEncoding {
+    pub const ALL: [PlaylistEncoding; 7] = [
+        PlaylistEncoding::Utf8,
+        PlaylistEncoding::Windows1251,
+        PlaylistEncoding::Windows1252,
+        PlaylistEncoding::Windows1254,
+        PlaylistEncoding::Windows1256,
+        PlaylistEncoding::Iso8859_1,
+        PlaylistEncoding::Iso8859_9,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlaylistEncoding::Utf8 => "utf-8",
+            PlaylistEncoding::Windows1251 => "windows-1251",
+            PlaylistEncoding::Windows1252 => "windows-1252",
+            PlaylistEncoding::Windows1254 => "windows-1254",
+            PlaylistEncoding::Windows1256 => "windows-1256",
+            PlaylistEncoding::Iso8859_1 => "iso-8859-1",
+            PlaylistEncoding::Iso8859_9 => "iso-8859-9",
+        }
+    }
+
+    /// Parse an encoding label, accepting common aliases such as `cp1256` or `latin1`
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_ascii_lowercase();
+        let encoding = match label.as_str() {
+            "utf-8" | "utf8" => PlaylistEncoding::Utf8,
+            "windows-1251" | "cp1251" => PlaylistEncoding::Windows1251,
+            "windows-1252" | "cp1252" => PlaylistEncoding::Windows1252,
+            "windows-1254" | "cp1254" => PlaylistEncoding::Windows1254,
+            "windows-1256" | "cp1256" => PlaylistEncoding::Windows1256,
+            "iso-8859-1" | "latin1" => PlaylistEncoding::Iso8859_1,
+            "iso-8859-9" | "latin5" => PlaylistEncoding::Iso8859_9,
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    /// Characters for bytes 0x80 to 0xFF, for the single-byte encodings with a table
+    fn high_table(self) -> Option<&'static [char; 128]> {
+        match self {
+            PlaylistEncoding::Windows1251 => Some(&WINDOWS_1251),
+            PlaylistEncoding::Windows1252 => Some(&WINDOWS_1252),
+            PlaylistEncoding::Windows1254 => Some(&WINDOWS_1254),
+            PlaylistEncoding::Windows1256 => Some(&WINDOWS_1256),
+            PlaylistEncoding::Iso8859_9 => Some(&ISO_8859_9),
+            PlaylistEncoding::Utf8 | PlaylistEncoding::Iso8859_1 => None,
+        }
+    }
+
+    /// Decode bytes, replacing anything invalid with U+FFFD
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match (self, self.high_table()) {
+            (PlaylistEncoding::Utf8, _) => String::from_utf8_lossy(bytes).into_owned(),
+            (_, Some(table)) => bytes
+                .iter()
+                .map(|&b| if b < 0x80 { b as char } else { table[(b - 0x80) as usize] })
+                .collect(),
+            (_, None) => bytes.iter().map(|&b| b as char).collect(),
+        }
+    }
+}
+
+/// Playlist text with the encoding it was decoded from
+#[derive(Debug, Clone)]
+pub struct DecodedPlaylist {
+    pub text: String,
+    pub encoding: PlaylistEncoding,
+    /// Why the decoded text may be garbled, if it looks that way
+    pub warning: Option<String>,
+}
+
+/// Encoding setting and the outcome of the last decode for a channel list
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct EncodingReport {
+    pub encoding: String,
+    pub detected_encoding: Option<String>,
+    pub encoding_warning: Option<String>,
+}
+
+/// Decode playlist bytes with a fixed encoding, or detect it when `encoding` is `None`
+pub fn decode_playlist(bytes: &[u8], encoding: Option<PlaylistEncoding>) -> DecodedPlaylist {
+    let _span = profile_span!("parser", "decode_playlist");
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+
+    let (encoding, low_confidence) = match encoding {
+        Some(encoding) => (encoding, false),
+        None => detect_encoding(bytes),
+    };
+    let text = encoding.decode(bytes);
+
+    let warning = suspicious_decoding(&text).or_else(|| {
+        low_confidence.then(|| {
+            format!(
+                "Encoding could not be detected reliably; decoded as {}",
+                encoding.label()
+            )
+        })
+    });
+
+    DecodedPlaylist { text, encoding, warning }
+}
+
+/// Pick the most plausible encoding, and whether the choice is a guess
+fn detect_encoding(bytes: &[u8]) -> (PlaylistEncoding, bool) {
+    if std::str::from_utf8(bytes).is_ok() {
+        return (PlaylistEncoding::Utf8, false);
+    }
+
+    let mut best = (PlaylistEncoding::Windows1252, i64::MIN);
+    for encoding in AUTO_CANDIDATES {
+        let score = plausibility(&encoding.decode(bytes));
+        if score > best.1 {
+            best = (encoding, score);
+        }
+    }
+    (best.0, best.1 <= 0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Arabic,
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match c as u32 {
+        0x0000..=0x024F => Script::Latin,
+        0x0370..=0x03FF => Script::Greek,
+        0x0400..=0x052F => Script::Cyrillic,
+        0x0600..=0x06FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+        _ => Script::Other,
+    }
+}
+
+fn is_undecodable(c: char) -> bool {
+    c == '\u{FFFD}' || ('\u{80}'..='\u{9F}').contains(&c)
+}
+
+/// Score how much decoded text looks like real words
+///
+/// Non-ASCII letters count for a decoding when they sit in words of a single
+/// script, and against it when a word mixes scripts, changes case mid-word, or
+/// is made only of accented Latin letters, which is how text in another code
+/// page usually comes out.
+fn plausibility(text: &str) -> i64 {
+    let undecodable = text.chars().filter(|c| is_undecodable(*c)).count() as i64;
+    text.split(|c: char| !c.is_alphabetic())
+        .map(word_plausibility)
+        .sum::<i64>()
+        - 5 * undecodable
+}
+
+fn word_plausibility(word: &str) -> i64 {
+    let non_ascii = word.chars().filter(|c| !c.is_ascii()).count() as i64;
+    if non_ascii == 0 {
+        return 0;
+    }
+
+    let has_ascii = word.chars().any(|c| c.is_ascii());
+    let mut scripts = word.chars().map(script);
+    let first = scripts.next().unwrap_or(Script::Other);
+    if scripts.any(|s| s != first) {
+        return -2 * non_ascii;
+    }
+
+    let mut score = match first {
+        Script::Latin if has_ascii => non_ascii,
+        Script::Latin if word.chars().count() == 1 => 0,
+        Script::Latin | Script::Other => -non_ascii,
+        Script::Greek | Script::Cyrillic | Script::Arabic => non_ascii,
+    };
+
+    let case_changes = word
+        .chars()
+        .zip(word.chars().skip(1))
+        .filter(|(a, b)| a.is_lowercase() && b.is_uppercase())
+        .count() as i64;
+    score -= 2 * case_changes;
+    score -= word.chars().filter(|c| RARE_LATIN_LETTERS.contains(c)).count() as i64;
+    score
+}
+
+/// Describe signs that decoded playlist text is garbled
+pub fn suspicious_decoding(text: &str) -> Option<String> {
+    let undecodable = text.chars().filter(|c| is_undecodable(*c)).count();
+    if undecodable > 0 {
+        return Some(format!("{} characters could not be decoded", undecodable));
+    }
+
+    let mojibake = text
+        .chars()
+        .zip(text.chars().skip(1))
+        .filter(|(lead, next)| {
+            MOJIBAKE_LEADS.contains(lead)
+                && (('\u{A0}'..='\u{BF}').contains(next) || WINDOWS_1252[..32].contains(next))
+        })
+        .count();
+    if mojibake > 0 {
+        return Some(format!(
+            "{} sequences look like UTF-8 text saved in a single-byte encoding",
+            mojibake
+        ));
+    }
+
+    None
+}
+
+/// Configured encoding for a channel list; `None` means auto-detect
+pub fn list_encoding(conn: &Connection, list_id: i32) -> Option<PlaylistEncoding> {
+    conn.query_row(
+        "SELECT encoding FROM channel_lists WHERE id = ?1",
+        [list_id],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+    .and_then(|label| PlaylistEncoding::from_label(&label))
+}
+
+/// Remember which encoding a list was decoded with and any warning about it
+pub fn record_decoding(conn: &Connection, list_id: i32, decoded: &DecodedPlaylist) -> Result<(), String> {
+    conn.execute(
+        "UPDATE channel_lists SET detected_encoding = ?1, encoding_warning = ?2 WHERE id = ?3",
+        params![decoded.encoding.label(), decoded.warning, list_id],
+    )
+    .map_err(|e| format!("Failed to update: {}", e))?;
+    Ok(())
+}
+
+/// Read and decode a playlist file
+pub fn read_playlist_file(path: &Path, encoding: Option<PlaylistEncoding>) -> std::io::Result<DecodedPlaylist> {
+    Ok(decode_playlist(&std::fs::read(path)?, encoding))
+}
+
+#[tauri::command]
+pub fn get_supported_playlist_encodings() -> Vec<&'static str> {
+    std::iter::once(AUTO_ENCODING)
+        .chain(PlaylistEncoding::ALL.iter().map(|encoding| encoding.label()))
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_channel_list_encoding(state: State<DbState>, id: i32) -> Result<EncodingReport, String> {
+    let db = state.db.lock().unwrap();
+    db.query_row(
+        "SELECT encoding, detected_encoding, encoding_warning FROM channel_lists WHERE id = ?1",
+        [id],
+        |row| {
+            Ok(EncodingReport {
+                encoding: row.get(0)?,
+                detected_encoding: row.get(1)?,
+                encoding_warning: row.get(2)?,
+            })
+        },
+    )
+    .map_err(|_| "Channel list not found".to_string())
+}
+
+/// Set a channel list's encoding, `auto` or a label from `get_supported_playlist_encodings`
+///
+/// The cached copy was decoded with the old setting, so it is marked stale and
+/// the list is decoded again from its source on next load or refresh.
+#[tauri::command]
+pub fn set_channel_list_encoding(
+    state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    id: i32,
+    encoding: String,
+) -> Result<(), String> {
+    let label = if encoding.trim().eq_ignore_ascii_case(AUTO_ENCODING) {
+        AUTO_ENCODING
+    } else {
+        PlaylistEncoding::from_label(&encoding)
+            .ok_or_else(|| format!("Unsupported encoding: {}", encoding))?
+            .label()
+    };
+
+    {
+        let db = state.db.lock().unwrap();
+        let updated = db
+            .execute(
+                "UPDATE channel_lists SET encoding = ?1, last_fetched = NULL WHERE id = ?2",
+                params![label, id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("Channel list not found".to_string());
+        }
+    }
+
+    invalidate_channel_cache(cache_state)
+}
+
+const WINDOWS_1251: [char; 128] = [
+    '\u{0402}', '\u{0403}', '\u{201A}', '\u{0453}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{20AC}', '\u{2030}', '\u{0409}', '\u{2039}', '\u{040A}', '\u{040C}', '\u{040B}', '\u{040F}',
+    '\u{0452}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{FFFD}', '\u{2122}', '\u{0459}', '\u{203A}', '\u{045A}', '\u{045C}', '\u{045B}', '\u{045F}',
+    '\u{00A0}', '\u{040E}', '\u{045E}', '\u{0408}', '\u{00A4}', '\u{0490}', '\u{00A6}', '\u{00A7}',
+    '\u{0401}', '\u{00A9}', '\u{0404}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{0407}',
+    '\u{00B0}', '\u{00B1}', '\u{0406}', '\u{0456}', '\u{0491}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{0451}', '\u{2116}', '\u{0454}', '\u{00BB}', '\u{0458}', '\u{0405}', '\u{0455}', '\u{0457}',
+    '\u{0410}', '\u{0411}', '\u{0412}', '\u{0413}', '\u{0414}', '\u{0415}', '\u{0416}', '\u{0417}',
+    '\u{0418}', '\u{0419}', '\u{041A}', '\u{041B}', '\u{041C}', '\u{041D}', '\u{041E}', '\u{041F}',
+    '\u{0420}', '\u{0421}', '\u{0422}', '\u{0423}', '\u{0424}', '\u{0425}', '\u{0426}', '\u{0427}',
+    '\u{0428}', '\u{0429}', '\u{042A}', '\u{042B}', '\u{042C}', '\u{042D}', '\u{042E}', '\u{042F}',
+    '\u{0430}', '\u{0431}', '\u{0432}', '\u{0433}', '\u{0434}', '\u{0435}', '\u{0436}', '\u{0437}',
+    '\u{0438}', '\u{0439}', '\u{043A}', '\u{043B}', '\u{043C}', '\u{043D}', '\u{043E}', '\u{043F}',
+    '\u{0440}', '\u{0441}', '\u{0442}', '\u{0443}', '\u{0444}', '\u{0445}', '\u{0446}', '\u{0447}',
+    '\u{0448}', '\u{0449}', '\u{044A}', '\u{044B}', '\u{044C}', '\u{044D}', '\u{044E}', '\u{044F}',
+];
+
+const WINDOWS_1252: [char; 128] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+    '\u{00A0}', '\u{00A1}', '\u{00A2}', '\u{00A3}', '\u{00A4}', '\u{00A5}', '\u{00A6}', '\u{00A7}',
+    '\u{00A8}', '\u{00A9}', '\u{00AA}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{00AF}',
+    '\u{00B0}', '\u{00B1}', '\u{00B2}', '\u{00B3}', '\u{00B4}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{00B8}', '\u{00B9}', '\u{00BA}', '\u{00BB}', '\u{00BC}', '\u{00BD}', '\u{00BE}', '\u{00BF}',
+    '\u{00C0}', '\u{00C1}', '\u{00C2}', '\u{00C3}', '\u{00C4}', '\u{00C5}', '\u{00C6}', '\u{00C7}',
+    '\u{00C8}', '\u{00C9}', '\u{00CA}', '\u{00CB}', '\u{00CC}', '\u{00CD}', '\u{00CE}', '\u{00CF}',
+    '\u{00D0}', '\u{00D1}', '\u{00D2}', '\u{00D3}', '\u{00D4}', '\u{00D5}', '\u{00D6}', '\u{00D7}',
+    '\u{00D8}', '\u{00D9}', '\u{00DA}', '\u{00DB}', '\u{00DC}', '\u{00DD}', '\u{00DE}', '\u{00DF}',
+    '\u{00E0}', '\u{00E1}', '\u{00E2}', '\u{00E3}', '\u{00E4}', '\u{00E5}', '\u{00E6}', '\u{00E7}',
+    '\u{00E8}', '\u{00E9}', '\u{00EA}', '\u{00EB}', '\u{00EC}', '\u{00ED}', '\u{00EE}', '\u{00EF}',
+    '\u{00F0}', '\u{00F1}', '\u{00F2}', '\u{00F3}', '\u{00F4}', '\u{00F5}', '\u{00F6}', '\u{00F7}',
+    '\u{00F8}', '\u{00F9}', '\u{00FA}', '\u{00FB}', '\u{00FC}', '\u{00FD}', '\u{00FE}', '\u{00FF}',
+];
+
+const WINDOWS_1254: [char; 128] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{FFFD}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{FFFD}', '\u{0178}',
+    '\u{00A0}', '\u{00A1}', '\u{00A2}', '\u{00A3}', '\u{00A4}', '\u{00A5}', '\u{00A6}', '\u{00A7}',
+    '\u{00A8}', '\u{00A9}', '\u{00AA}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{00AF}',
+    '\u{00B0}', '\u{00B1}', '\u{00B2}', '\u{00B3}', '\u{00B4}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{00B8}', '\u{00B9}', '\u{00BA}', '\u{00BB}', '\u{00BC}', '\u{00BD}', '\u{00BE}', '\u{00BF}',
+    '\u{00C0}', '\u{00C1}', '\u{00C2}', '\u{00C3}', '\u{00C4}', '\u{00C5}', '\u{00C6}', '\u{00C7}',
+    '\u{00C8}', '\u{00C9}', '\u{00CA}', '\u{00CB}', '\u{00CC}', '\u{00CD}', '\u{00CE}', '\u{00CF}',
+    '\u{011E}', '\u{00D1}', '\u{00D2}', '\u{00D3}', '\u{00D4}', '\u{00D5}', '\u{00D6}', '\u{00D7}',
+    '\u{00D8}', '\u{00D9}', '\u{00DA}', '\u{00DB}', '\u{00DC}', '\u{0130}', '\u{015E}', '\u{00DF}',
+    '\u{00E0}', '\u{00E1}', '\u{00E2}', '\u{00E3}', '\u{00E4}', '\u{00E5}', '\u{00E6}', '\u{00E7}',
+    '\u{00E8}', '\u{00E9}', '\u{00EA}', '\u{00EB}', '\u{00EC}', '\u{00ED}', '\u{00EE}', '\u{00EF}',
+    '\u{011F}', '\u{00F1}', '\u{00F2}', '\u{00F3}', '\u{00F4}', '\u{00F5}', '\u{00F6}', '\u{00F7}',
+    '\u{00F8}', '\u{00F9}', '\u{00FA}', '\u{00FB}', '\u{00FC}', '\u{0131}', '\u{015F}', '\u{00FF}',
+];
+
+const WINDOWS_1256: [char; 128] = [
+    '\u{20AC}', '\u{067E}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0679}', '\u{2039}', '\u{0152}', '\u{0686}', '\u{0698}', '\u{0688}',
+    '\u{06AF}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{06A9}', '\u{2122}', '\u{0691}', '\u{203A}', '\u{0153}', '\u{200C}', '\u{200D}', '\u{06BA}',
+    '\u{00A0}', '\u{060C}', '\u{00A2}', '\u{00A3}', '\u{00A4}', '\u{00A5}', '\u{00A6}', '\u{00A7}',
+    '\u{00A8}', '\u{00A9}', '\u{06BE}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{00AF}',
+    '\u{00B0}', '\u{00B1}', '\u{00B2}', '\u{00B3}', '\u{00B4}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{00B8}', '\u{00B9}', '\u{061B}', '\u{00BB}', '\u{00BC}', '\u{00BD}', '\u{00BE}', '\u{061F}',
+    '\u{06C1}', '\u{0621}', '\u{0622}', '\u{0623}', '\u{0624}', '\u{0625}', '\u{0626}', '\u{0627}',
+    '\u{0628}', '\u{0629}', '\u{062A}', '\u{062B}', '\u{062C}', '\u{062D}', '\u{062E}', '\u{062F}',
+    '\u{0630}', '\u{0631}', '\u{0632}', '\u{0633}', '\u{0634}', '\u{0635}', '\u{0636}', '\u{00D7}',
+    '\u{0637}', '\u{0638}', '\u{0639}', '\u{063A}', '\u{0640}', '\u{0641}', '\u{0642}', '\u{0643}',
+    '\u{00E0}', '\u{0644}', '\u{00E2}', '\u{0645}', '\u{0646}', '\u{0647}', '\u{0648}', '\u{00E7}',
+    '\u{00E8}', '\u{00E9}', '\u{00EA}', '\u{00EB}', '\u{0649}', '\u{064A}', '\u{00EE}', '\u{00EF}',
+    '\u{064B}', '\u{064C}', '\u{064D}', '\u{064E}', '\u{00F4}', '\u{064F}', '\u{0650}', '\u{00F7}',
+    '\u{0651}', '\u{00F9}', '\u{0652}', '\u{00FB}', '\u{00FC}', '\u{200E}', '\u{200F}', '\u{06D2}',
+];
+
+const ISO_8859_9: [char; 128] = [
+    '\u{0080}', '\u{0081}', '\u{0082}', '\u{0083}', '\u{0084}', '\u{0085}', '\u{0086}', '\u{0087}',
+    '\u{0088}', '\u{0089}', '\u{008A}', '\u{008B}', '\u{008C}', '\u{008D}', '\u{008E}', '\u{008F}',
+    '\u{0090}', '\u{0091}', '\u{0092}', '\u{0093}', '\u{0094}', '\u{0095}', '\u{0096}', '\u{0097}',
+    '\u{0098}', '\u{0099}', '\u{009A}', '\u{009B}', '\u{009C}', '\u{009D}', '\u{009E}', '\u{009F}',
+    '\u{00A0}', '\u{00A1}', '\u{00A2}', '\u{00A3}', '\u{00A4}', '\u{00A5}', '\u{00A6}', '\u{00A7}',
+    '\u{00A8}', '\u{00A9}', '\u{00AA}', '\u{00AB}', '\u{00AC}', '\u{00AD}', '\u{00AE}', '\u{00AF}',
+    '\u{00B0}', '\u{00B1}', '\u{00B2}', '\u{00B3}', '\u{00B4}', '\u{00B5}', '\u{00B6}', '\u{00B7}',
+    '\u{00B8}', '\u{00B9}', '\u{00BA}', '\u{00BB}', '\u{00BC}', '\u{00BD}', '\u{00BE}', '\u{00BF}',
+    '\u{00C0}', '\u{00C1}', '\u{00C2}', '\u{00C3}', '\u{00C4}', '\u{00C5}', '\u{00C6}', '\u{00C7}',
+    '\u{00C8}', '\u{00C9}', '\u{00CA}', '\u{00CB}', '\u{00CC}', '\u{00CD}', '\u{00CE}', '\u{00CF}',
+    '\u{011E}', '\u{00D1}', '\u{00D2}', '\u{00D3}', '\u{00D4}', '\u{00D5}', '\u{00D6}', '\u{00D7}',
+    '\u{00D8}', '\u{00D9}', '\u{00DA}', '\u{00DB}', '\u{00DC}', '\u{0130}', '\u{015E}', '\u{00DF}',
+    '\u{00E0}', '\u{00E1}', '\u{00E2}', '\u{00E3}', '\u{00E4}', '\u{00E5}', '\u{00E6}', '\u{00E7}',
+    '\u{00E8}', '\u{00E9}', '\u{00EA}', '\u{00EB}', '\u{00EC}', '\u{00ED}', '\u{00EE}', '\u{00EF}',
+    '\u{011F}', '\u{00F1}', '\u{00F2}', '\u{00F3}', '\u{00F4}', '\u{00F5}', '\u{00F6}', '\u{00F7}',
+    '\u{00F8}', '\u{00F9}', '\u{00FA}', '\u{00FB}', '\u{00FC}', '\u{0131}', '\u{015F}', '\u{00FF}',
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_legacy_code_pages() {
+        let arabic = b"#EXTINF:-1 group-title=\"\xc3\xce\xc8\xc7\xd1\",\xde\xe4\xc7\xc9 \xc7\xe1\xcc\xd2\xed\xd1\xc9";
+        let decoded = decode_playlist(arabic, None);
+        assert_eq!(decoded.encoding, PlaylistEncoding::Windows1256);
+        assert!(decoded.text.ends_with("قناة الجزيرة"));
+        assert_eq!(decoded.warning, None);
+
+        let turkish = b"#EXTINF:-1 group-title=\"Ulusal\",TRT Haber \xdeark\xfdlar D\xfcnyas\xfd";
+        let decoded = decode_playlist(turkish, None);
+        assert_eq!(decoded.encoding, PlaylistEncoding::Windows1254);
+        assert!(decoded.text.ends_with("Şarkılar Dünyası"));
+
+        let russian = b"#EXTINF:-1,\xcf\xe5\xf0\xe2\xfb\xe9 \xea\xe0\xed\xe0\xeb";
+        let decoded = decode_playlist(russian, None);
+        assert_eq!(decoded.encoding, PlaylistEncoding::Windows1251);
+        assert!(decoded.text.ends_with("Первый канал"));
+
+        let french = b"#EXTINF:-1,T\xe9l\xe9 Matin";
+        let decoded = decode_playlist(french, None);
+        assert_eq!(decoded.encoding, PlaylistEncoding::Windows1252);
+        assert!(decoded.text.ends_with("Télé Matin"));
+    }
+
+    #[test]
+    fn test_utf8_and_manual_override() {
+        let decoded = decode_playlist("\u{FEFF}#EXTM3U\n#EXTINF:-1,Télé".as_bytes(), None);
+        assert_eq!(decoded.encoding, PlaylistEncoding::Utf8);
+        assert_eq!(decoded.text, "#EXTM3U\n#EXTINF:-1,Télé");
+
+        let turkish = b"#EXTINF:-1,\xdeark\xfdlar";
+        let decoded = decode_playlist(turkish, Some(PlaylistEncoding::Iso8859_9));
+        assert_eq!(decoded.text, "#EXTINF:-1,Şarkılar");
+        let decoded = decode_playlist(turkish, Some(PlaylistEncoding::Iso8859_1));
+        assert_eq!(decoded.text, "#EXTINF:-1,Þarkýlar");
+
+        assert_eq!(PlaylistEncoding::from_label(" CP1256 "), Some(PlaylistEncoding::Windows1256));
+        assert_eq!(PlaylistEncoding::from_label("auto"), None);
+    }
+
+    #[test]
+    fn test_flags_suspicious_decoding() {
+        // UTF-8 "Télé" that was saved again after being read as Windows-1252
+        let decoded = decode_playlist("#EXTINF:-1,TÃ©lÃ©".as_bytes(), None);
+        assert!(decoded.warning.unwrap().contains("2 sequences"));
+
+        let decoded = decode_playlist(b"#EXTINF:-1,\x81\x8d", Some(PlaylistEncoding::Windows1252));
+        assert_eq!(decoded.warning, Some("2 characters could not be decoded".to_string()));
+
+        assert_eq!(suspicious_decoding("#EXTINF:-1,قناة الجزيرة"), None);
+    }
+}
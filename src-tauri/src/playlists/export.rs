@@ -0,0 +1,206 @@
+use crate::channels::{filter_by_country, get_cached_channels};
+use crate::m3u_parser::Channel;
+use crate::state::{ChannelCacheState, DbState};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::State;
+
+/// Which channels of a list to export; everything when left empty
+#[derive(Debug, Clone, Default, Serialize, Deserialize, specta::Type)]
+pub struct M3uExportFilter {
+    /// Only channels in these groups
+    #[serde(default)]
+    pub groups: Option<Vec<String>>,
+    /// Only channels in the list's enabled groups
+    #[serde(default)]
+    pub enabled_groups_only: bool,
+    /// Only channels from this country; an ISO code, a tag like "UK" or a name
+    #[serde(default)]
+    pub country: Option<String>,
+    /// Only channels this Xtream profile has in its favorites
+    #[serde(default)]
+    pub favorites_profile_id: Option<String>,
+}
+
+/// Attribute values can't contain quotes or line breaks
+fn attribute(value: &str) -> String {
+    value.replace('"', "'").replace(['\r', '\n'], " ")
+}
+
+/// Serialize channels to an extended M3U playlist
+pub fn channels_to_m3u(channels: &[Channel]) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for channel in channels {
+        m3u.push_str("#EXTINF:-1");
+        for (name, value) in [
+            ("tvg-id", &channel.tvg_id),
+            ("tvg-logo", &channel.logo),
+            ("tvg-country", &channel.country),
+            ("group-title", &channel.group_title),
+        ] {
+            if !value.is_empty() {
+                m3u.push_str(&format!(" {}=\"{}\"", name, attribute(value)));
+            }
+        }
+        m3u.push(',');
+        m3u.push_str(&channel.name.replace(['\r', '\n'], " "));
+        m3u.push('\n');
+        m3u.push_str(channel.url.trim());
+        m3u.push('\n');
+    }
+    m3u
+}
+
+/// Xtream stream id of a channel, from a converted channel's marker or its stream URL
+fn stream_id(channel: &Channel) -> Option<&str> {
+    if let Some(id) = channel.extra_info.strip_prefix("stream_id:") {
+        return Some(id.trim());
+    }
+    let last = channel.url.trim_end_matches('/').rsplit('/').next()?;
+    let id = last.split('.').next()?;
+    (!id.is_empty() && id.bytes().all(|b| b.is_ascii_digit())).then_some(id)
+}
+
+fn favorite_channel_ids(conn: &Connection, profile_id: &str) -> rusqlite::Result<HashSet<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT content_id FROM xtream_favorites WHERE profile_id = ?1 AND content_type = 'channel'",
+    )?;
+    let ids = stmt
+        .query_map(params![profile_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(ids)
+}
+
+fn enabled_groups(conn: &Connection, id: Option<i32>) -> rusqlite::Result<HashSet<String>> {
+    let list_id = match id {
+        Some(id) => Some(id as i64),
+        None => conn
+            .query_row("SELECT id FROM channel_lists WHERE is_default = 1", [], |row| row.get(0))
+            .optional()?,
+    };
+    match list_id {
+        Some(list_id) => Ok(crate::database::get_enabled_groups(conn, list_id)?.into_iter().collect()),
+        None => Ok(HashSet::new()),
+    }
+}
+
+/// Keep the channels a filter selects
+fn select_channels(conn: &Connection, id: Option<i32>, channels: Vec<Channel>, filter: &M3uExportFilter) -> rusqlite::Result<Vec<Channel>> {
+    let mut channels = filter_by_country(channels, filter.country.as_deref());
+
+    if let Some(groups) = &filter.groups {
+        channels.retain(|channel| groups.contains(&channel.group_title));
+    }
+    if filter.enabled_groups_only {
+        let enabled = enabled_groups(conn, id)?;
+        channels.retain(|channel| enabled.contains(&channel.group_title));
+    }
+    if let Some(profile_id) = &filter.favorites_profile_id {
+        let favorites = favorite_channel_ids(conn, profile_id)?;
+        channels.retain(|channel| stream_id(channel).is_some_and(|id| favorites.contains(id)));
+    }
+
+    Ok(channels)
+}
+
+/// Write a channel list, or the part a filter selects, to an M3U file
+///
+/// Group, tvg-id, logo and country are written as attributes so the file can
+/// be imported again here or in another player. Returns the number of channels written.
+#[tauri::command]
+pub fn export_channel_list_to_m3u(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    id: Option<i32>,
+    path: String,
+    filter: Option<M3uExportFilter>,
+) -> Result<usize, String> {
+    let channels = get_cached_channels(db_state.clone(), cache_state, id)?;
+    let channels = {
+        let db = db_state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        select_channels(&db, id, channels, &filter.unwrap_or_default()).map_err(|e| e.to_string())?
+    };
+
+    std::fs::write(&path, channels_to_m3u(&channels))
+        .map_err(|e| format!("Failed to write playlist file: {}", e))?;
+
+    Ok(channels.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(name: &str, group: &str, url: &str) -> Channel {
+        Channel {
+            name: name.to_string(),
+            logo: format!("http://logos.example.com/{}.png", name),
+            url: url.to_string(),
+            group_title: group.to_string(),
+            tvg_id: format!("{}.uk", name.to_lowercase()),
+            resolution: String::new(),
+            extra_info: String::new(),
+            country: "GB".to_string(),
+            country_flag: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_export_round_trips_through_the_parser() {
+        let mut channels = vec![
+            channel("News", "UK \"Main\"", "http://example.com/live/u/p/101.ts"),
+            channel("Sports", "Sports", "http://example.com/live/u/p/102.ts"),
+        ];
+        channels[1].logo.clear();
+
+        let m3u = channels_to_m3u(&channels);
+        assert!(m3u.starts_with("#EXTM3U\n"));
+        assert!(m3u.contains(
+            "#EXTINF:-1 tvg-id=\"news.uk\" tvg-logo=\"http://logos.example.com/News.png\" tvg-country=\"GB\" group-title=\"UK 'Main'\",News\n"
+        ));
+        assert!(!m3u.contains("tvg-logo=\"\""));
+
+        let parsed = crate::m3u_parser_helpers::parse_m3u_with_progress(&m3u, |_, _, _| {});
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].group_title, "UK 'Main'");
+        assert_eq!(parsed[0].tvg_id, "news.uk");
+        assert_eq!(parsed[1].url, "http://example.com/live/u/p/102.ts");
+        assert_eq!(parsed[1].country, "GB");
+    }
+
+    #[test]
+    fn test_filter_selects_groups_and_favorites() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT);
+             INSERT INTO xtream_favorites VALUES ('p1', 'channel', '102'), ('p1', 'channel', '7'), ('p1', 'movie', '101');",
+        )
+        .unwrap();
+        let mut converted = channel("Music", "Music", "http://example.com/play?token=x");
+        converted.extra_info = "stream_id:7".to_string();
+        let channels = vec![
+            channel("News", "News", "http://example.com/live/u/p/101.ts"),
+            channel("Sports", "Sports", "http://example.com/u/p/102"),
+            converted,
+        ];
+
+        let filter = M3uExportFilter {
+            groups: Some(vec!["News".to_string(), "Sports".to_string()]),
+            ..Default::default()
+        };
+        let selected = select_channels(&conn, Some(1), channels.clone(), &filter).unwrap();
+        assert_eq!(selected.len(), 2);
+
+        let filter = M3uExportFilter {
+            favorites_profile_id: Some("p1".to_string()),
+            ..Default::default()
+        };
+        let names: Vec<String> = select_channels(&conn, Some(1), channels, &filter)
+            .unwrap()
+            .into_iter()
+            .map(|channel| channel.name)
+            .collect();
+        assert_eq!(names, vec!["Sports", "Music"]);
+    }
+}
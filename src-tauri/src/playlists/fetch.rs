@@ -1,4 +1,7 @@
 use crate::channels::invalidate_channel_cache;
+use crate::playlists::encoding::{
+    decode_playlist, list_encoding, read_playlist_file, record_decoding, PlaylistEncoding,
+};
 use crate::playlists::types::{emit_progress, FetchState, PlaylistFetchStatus};
 use crate::state::{ChannelCacheState, DbState};
 use chrono::Utc;
@@ -17,15 +20,17 @@ pub async fn refresh_channel_list_async(
     fetch_state: State<'_, FetchState>,
     id: i32,
 ) -> Result<(), String> {
-    // Get the source URL from database
-    let source = {
+    // Get the source URL and encoding from database
+    let (source, encoding) = {
         let db = db_state.db.lock().unwrap();
-        db.query_row(
-            "SELECT source FROM channel_lists WHERE id = ?1",
-            &[&id],
-            |row| row.get::<_, String>(0),
-        )
-        .map_err(|_| "Channel list not found".to_string())?
+        let source = db
+            .query_row(
+                "SELECT source FROM channel_lists WHERE id = ?1",
+                &[&id],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|_| "Channel list not found".to_string())?;
+        (source, list_encoding(&db, id))
     };
 
     // Handle both HTTP and file sources
@@ -33,7 +38,8 @@ pub async fn refresh_channel_list_async(
         // HTTP source - download and cache
     } else {
         // File source - read from local filesystem
-        return refresh_file_playlist(app_handle, db_state, cache_state, fetch_state, id, source).await;
+        return refresh_file_playlist(app_handle, db_state, cache_state, fetch_state, id, source, encoding)
+            .await;
     }
 
     // Emit starting status
@@ -67,10 +73,13 @@ pub async fn refresh_channel_list_async(
     .await;
 
     // Fetch the playlist
-    let client = reqwest::Client::new();
+    let client = crate::connection::client_builder()
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
     let response = client
         .get(&source)
         .header("User-Agent", "Mozilla/5.0")
+        .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
         .timeout(std::time::Duration::from_secs(120))
         .send()
         .await
@@ -91,10 +100,11 @@ pub async fn refresh_channel_list_async(
     )
     .await;
 
-    let content = response
-        .text()
+    let body = crate::transfer::read_body(response, crate::transfer::SOURCE_PLAYLIST)
         .await
         .map_err(|e| format!("Failed to read: {}", e))?;
+    let decoded = decode_playlist(&body, encoding);
+    let content = decoded.text.as_str();
 
     if content.trim().is_empty() || !content.trim_start().starts_with("#EXTM3U") {
         let error_msg = "Invalid M3U playlist".to_string();
@@ -141,7 +151,7 @@ pub async fn refresh_channel_list_async(
     let filename = format!("{}.m3u", Uuid::new_v4());
     let filepath = data_dir.join(&filename);
 
-    fs::write(&filepath, &content).map_err(|e| format!("Failed to save: {}", e))?;
+    fs::write(&filepath, content).map_err(|e| format!("Failed to save: {}", e))?;
 
     // Update database
     let now = Utc::now().timestamp();
@@ -156,6 +166,7 @@ pub async fn refresh_channel_list_async(
             ],
         )
         .map_err(|e| format!("Failed to update: {}", e))?;
+        record_decoding(&db, id, &decoded)?;
     }
 
     // Invalidate cache
@@ -264,10 +275,13 @@ pub async fn validate_and_add_channel_list_async(
         .await;
 
         // Fetch the playlist
-        let client = reqwest::Client::new();
+        let client = crate::connection::client_builder()
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
         let response = client
             .get(clean_source)
             .header("User-Agent", "Mozilla/5.0")
+            .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
             .timeout(std::time::Duration::from_secs(120))
             .send()
             .await
@@ -288,10 +302,11 @@ pub async fn validate_and_add_channel_list_async(
         )
         .await;
 
-        let content = response
-            .text()
+        let body = crate::transfer::read_body(response, crate::transfer::SOURCE_PLAYLIST)
             .await
             .map_err(|e| format!("Failed to read: {}", e))?;
+        let decoded = decode_playlist(&body, None);
+        let content = decoded.text.as_str();
 
         if content.trim().is_empty() || !content.trim_start().starts_with("#EXTM3U") {
             let error_msg = "Invalid M3U playlist".to_string();
@@ -355,7 +370,7 @@ pub async fn validate_and_add_channel_list_async(
         let filename = format!("{}.m3u", Uuid::new_v4());
         let filepath = data_dir.join(&filename);
 
-        fs::write(&filepath, &content).map_err(|e| format!("Failed to save: {}", e))?;
+        fs::write(&filepath, content).map_err(|e| format!("Failed to save: {}", e))?;
 
         // Update database with file info
         let now = Utc::now().timestamp();
@@ -370,6 +385,7 @@ pub async fn validate_and_add_channel_list_async(
                 ],
             )
             .map_err(|e| format!("Failed to update: {}", e))?;
+            record_decoding(&db, list_id, &decoded)?;
         }
 
         // Invalidate cache
@@ -399,13 +415,14 @@ pub async fn validate_and_add_channel_list_async(
         }
 
         // Read and validate the file
-        let content = fs::read_to_string(clean_source)
+        let decoded = read_playlist_file(std::path::Path::new(clean_source), None)
             .map_err(|e| {
                 // Delete the playlist entry since we can't read the file
                 let db = db_state.db.lock().unwrap();
                 let _ = db.execute("DELETE FROM channel_lists WHERE id = ?1", [list_id]);
                 format!("Failed to read file '{}': {}", clean_source, e)
             })?;
+        let content = decoded.text.as_str();
 
         if content.trim().is_empty() || !content.trim_start().starts_with("#EXTM3U") {
             // Delete the playlist entry since the file is invalid
@@ -432,7 +449,7 @@ pub async fn validate_and_add_channel_list_async(
         let filename = format!("{}.m3u", Uuid::new_v4());
         let filepath = data_dir.join(&filename);
 
-        fs::write(&filepath, &content).map_err(|e| format!("Failed to save: {}", e))?;
+        fs::write(&filepath, content).map_err(|e| format!("Failed to save: {}", e))?;
 
         // Update database with file info
         let now = Utc::now().timestamp();
@@ -447,6 +464,7 @@ pub async fn validate_and_add_channel_list_async(
                 ],
             )
             .map_err(|e| format!("Failed to update: {}", e))?;
+            record_decoding(&db, list_id, &decoded)?;
         }
 
         // Invalidate cache
@@ -480,6 +498,7 @@ async fn refresh_file_playlist(
     fetch_state: State<'_, FetchState>,
     id: i32,
     source: String,
+    encoding: Option<PlaylistEncoding>,
 ) -> Result<(), String> {
     // Emit starting status
     emit_progress(
@@ -512,8 +531,9 @@ async fn refresh_file_playlist(
     .await;
 
     // Read the file content
-    let content = fs::read_to_string(&source)
+    let decoded = read_playlist_file(std::path::Path::new(&source), encoding)
         .map_err(|e| format!("Failed to read file '{}': {}", source, e))?;
+    let content = decoded.text.as_str();
 
     if content.trim().is_empty() || !content.trim_start().starts_with("#EXTM3U") {
         let error_msg = "Invalid M3U playlist file".to_string();
@@ -560,7 +580,7 @@ async fn refresh_file_playlist(
     let filename = format!("{}.m3u", Uuid::new_v4());
     let filepath = data_dir.join(&filename);
 
-    fs::write(&filepath, &content).map_err(|e| format!("Failed to save: {}", e))?;
+    fs::write(&filepath, content).map_err(|e| format!("Failed to save: {}", e))?;
 
     // Update database
     let now = Utc::now().timestamp();
@@ -575,6 +595,7 @@ async fn refresh_file_playlist(
             ],
         )
         .map_err(|e| format!("Failed to update: {}", e))?;
+        record_decoding(&db, id, &decoded)?;
     }
 
     // Invalidate cache
@@ -1,10 +1,11 @@
+use crate::events::{self, AppEvent};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, State};
 use tokio::sync::Mutex as AsyncMutex;
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, specta::Type)]
 pub struct PlaylistFetchStatus {
     pub id: i32,
     pub status: String, // "starting", "fetching", "processing", "saving", "completed", "error"
@@ -38,7 +39,5 @@ pub async fn emit_progress(
     drop(operations);
 
     // Emit event to frontend
-    if let Err(e) = app_handle.emit("playlist_fetch_status", &status) {
-        eprintln!("Failed to emit playlist_fetch_status event: {}", e);
-    }
+    events::emit(app_handle, AppEvent::PlaylistFetchStatus(status));
 }
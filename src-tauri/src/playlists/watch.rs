@@ -0,0 +1,481 @@
+use crate::channels::invalidate_channel_cache;
+use crate::events::{self, AppEvent};
+use crate::playlists::encoding::{list_encoding, read_playlist_file, record_decoding};
+use crate::state::{ChannelCacheState, DbState};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+/// How often the watch directory is scanned
+const SCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Outcome of importing one file from the watch directory
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct WatchImportEvent {
+    pub file: String,
+    pub name: String,
+    pub list_id: Option<i32>,
+    pub status: String, // "added", "updated", "error"
+    pub channel_count: Option<usize>,
+    pub error: Option<String>,
+}
+
+/// Managed state tracking the running watch folder task
+pub struct WatchFolderState {
+    task: Mutex<Option<CancellationToken>>,
+}
+
+impl WatchFolderState {
+    pub fn new() -> Self {
+        Self {
+            task: Mutex::new(None),
+        }
+    }
+
+    fn stop(&self) {
+        if let Some(cancel_token) = self.task.lock().unwrap().take() {
+            cancel_token.cancel();
+        }
+    }
+
+    /// Start watching `dir`, replacing any running watcher
+    fn start(&self, app_handle: AppHandle, dir: PathBuf) {
+        self.stop();
+        let cancel_token = CancellationToken::new();
+        tauri::async_runtime::spawn(run_watcher(app_handle, dir, cancel_token.clone()));
+        *self.task.lock().unwrap() = Some(cancel_token);
+    }
+}
+
+impl Default for WatchFolderState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_playlist_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"))
+            .unwrap_or(false)
+}
+
+fn modified_timestamp(path: &Path) -> i64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| {
+            modified
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64
+        })
+        .unwrap_or(0)
+}
+
+/// Check whether a watched file is new or has changed since its list was last fetched
+pub fn needs_import(conn: &Connection, path: &Path) -> Result<bool, String> {
+    let name = list_name(path);
+    let existing: Option<(String, Option<i64>)> = conn
+        .query_row(
+            "SELECT source, last_fetched FROM channel_lists WHERE name = ?1",
+            [&name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(match existing {
+        None => true,
+        Some((source, _)) if source != path.to_string_lossy() => true,
+        Some((_, last_fetched)) => modified_timestamp(path) > last_fetched.unwrap_or(0),
+    })
+}
+
+/// Channel list name for a watched file: its file name without the extension
+fn list_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Validate a playlist file and add it as a channel list, or update the list with the same name
+///
+/// A copy of the playlist is stored in `channel_lists_dir`, as for manually added lists.
+pub fn import_playlist_file(
+    conn: &Connection,
+    path: &Path,
+    channel_lists_dir: &Path,
+) -> Result<WatchImportEvent, String> {
+    let name = list_name(path);
+    if name.is_empty() {
+        return Err("Playlist file name is empty".to_string());
+    }
+
+    let existing: Option<(i32, Option<String>)> = conn
+        .query_row(
+            "SELECT id, filepath FROM channel_lists WHERE name = ?1",
+            [&name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let encoding = existing.as_ref().and_then(|(id, _)| list_encoding(conn, *id));
+    let decoded = read_playlist_file(path, encoding)
+        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+    let content = decoded.text.as_str();
+    if content.trim().is_empty() || !content.trim_start().starts_with("#EXTM3U") {
+        return Err("Invalid M3U playlist file".to_string());
+    }
+
+    let channel_count = content
+        .lines()
+        .filter(|line| line.starts_with("#EXTINF:"))
+        .count();
+    if channel_count == 0 {
+        return Err("No channels found in playlist file".to_string());
+    }
+
+    fs::create_dir_all(channel_lists_dir)
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+    let filename = format!("{}.m3u", Uuid::new_v4());
+    fs::write(channel_lists_dir.join(&filename), content)
+        .map_err(|e| format!("Failed to save: {}", e))?;
+
+    let source = path.to_string_lossy().to_string();
+    let now = Utc::now().timestamp();
+    let (list_id, status) = match existing {
+        Some((id, previous)) => {
+            conn.execute(
+                "UPDATE channel_lists SET source = ?1, filepath = ?2, last_fetched = ?3 WHERE id = ?4",
+                params![source, filename, now, id],
+            )
+            .map_err(|e| format!("Failed to update: {}", e))?;
+            // The list now points at the new copy; the old one is unreferenced
+            if let Some(previous) = previous.filter(|previous| *previous != filename) {
+                let previous = channel_lists_dir.join(previous);
+                if let Err(e) = fs::remove_file(&previous) {
+                    eprintln!("Failed to remove replaced playlist '{}': {}", previous.display(), e);
+                }
+            }
+            (id, "updated")
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO channel_lists (name, source, filepath, last_fetched) VALUES (?1, ?2, ?3, ?4)",
+                params![name, source, filename, now],
+            )
+            .map_err(|e| e.to_string())?;
+            (conn.last_insert_rowid() as i32, "added")
+        }
+    };
+    record_decoding(conn, list_id, &decoded)?;
+
+    Ok(WatchImportEvent {
+        file: source,
+        name,
+        list_id: Some(list_id),
+        status: status.to_string(),
+        channel_count: Some(channel_count),
+        error: None,
+    })
+}
+
+fn emit_import(app_handle: &AppHandle, event: WatchImportEvent) {
+    events::emit(app_handle, AppEvent::PlaylistWatchImport(event));
+}
+
+/// Import the stable, changed playlist files in `dir`
+///
+/// A file is only imported once its size is unchanged since the previous scan, so
+/// files still being copied are not picked up half-written. Files that failed are
+/// not retried until they are modified again.
+fn scan_once(
+    app_handle: &AppHandle,
+    dir: &Path,
+    sizes: &mut HashMap<PathBuf, u64>,
+    failed: &mut HashSet<(PathBuf, i64)>,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Failed to read watch directory '{}': {}", dir.display(), e);
+            return;
+        }
+    };
+
+    let mut current_sizes = HashMap::new();
+    let mut imported = false;
+
+    for path in entries.flatten().map(|entry| entry.path()).filter(|p| is_playlist_file(p)) {
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let stable = sizes.get(&path) == Some(&size);
+        current_sizes.insert(path.clone(), size);
+
+        let modified = modified_timestamp(&path);
+        if !stable || failed.contains(&(path.clone(), modified)) {
+            continue;
+        }
+
+        let db_state = app_handle.state::<DbState>();
+        let db = db_state.db.lock().unwrap();
+        match needs_import(&db, &path) {
+            Ok(false) => continue,
+            Ok(true) => {}
+            Err(e) => {
+                eprintln!("Failed to check watched playlist '{}': {}", path.display(), e);
+                continue;
+            }
+        }
+
+        let channel_lists_dir = dirs::data_dir().unwrap().join("xtauri/channel_lists");
+        let event = match import_playlist_file(&db, &path, &channel_lists_dir) {
+            Ok(event) => {
+                imported = true;
+                event
+            }
+            Err(error) => {
+                failed.insert((path.clone(), modified));
+                WatchImportEvent {
+                    file: path.to_string_lossy().to_string(),
+                    name: list_name(&path),
+                    list_id: None,
+                    status: "error".to_string(),
+                    channel_count: None,
+                    error: Some(error),
+                }
+            }
+        };
+        drop(db);
+
+        emit_import(app_handle, event);
+    }
+
+    *sizes = current_sizes;
+
+    if imported {
+        let _ = invalidate_channel_cache(app_handle.state::<ChannelCacheState>());
+    }
+}
+
+async fn run_watcher(app_handle: AppHandle, dir: PathBuf, cancel_token: CancellationToken) {
+    let _task = crate::tasks::register_cancellable(
+        crate::tasks::TaskKind::PlaylistWatch,
+        format!("Watching {}", dir.display()),
+        None,
+        cancel_token.clone(),
+    );
+    let mut sizes = HashMap::new();
+    let mut failed = HashSet::new();
+    let mut interval = tokio::time::interval(SCAN_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            _ = interval.tick() => scan_once(&app_handle, &dir, &mut sizes, &mut failed),
+        }
+    }
+}
+
+fn load_watch_dir(conn: &Connection) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT playlist_watch_dir FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Start the watcher on launch if a watch directory is configured
+pub fn restore_on_startup(app_handle: &AppHandle) {
+    let dir = {
+        let db_state = app_handle.state::<DbState>();
+        let db = db_state.db.lock().unwrap();
+        match load_watch_dir(&db) {
+            Ok(dir) => dir,
+            Err(e) => {
+                eprintln!("Failed to load playlist watch directory: {}", e);
+                return;
+            }
+        }
+    };
+
+    if let Some(dir) = dir.filter(|dir| Path::new(dir).is_dir()) {
+        app_handle
+            .state::<WatchFolderState>()
+            .start(app_handle.clone(), PathBuf::from(dir));
+    }
+}
+
+/// Follow a watch directory changed outside `set_playlist_watch_dir`, such as by a settings profile
+pub fn reload_watch_dir(app_handle: &AppHandle) {
+    app_handle.state::<WatchFolderState>().stop();
+    restore_on_startup(app_handle);
+}
+
+#[tauri::command]
+pub fn get_playlist_watch_dir(state: State<DbState>) -> Result<Option<String>, String> {
+    let db = state.db.lock().unwrap();
+    load_watch_dir(&db)
+}
+
+/// Set or clear (with `None`) the directory watched for new playlist files
+#[tauri::command]
+pub fn set_playlist_watch_dir(
+    app_handle: AppHandle,
+    db_state: State<DbState>,
+    watch_state: State<WatchFolderState>,
+    path: Option<String>,
+) -> Result<(), String> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(dir) = &path {
+        if !Path::new(dir).is_dir() {
+            return Err(format!("Directory '{}' does not exist", dir));
+        }
+    }
+
+    {
+        let db = db_state.db.lock().unwrap();
+        db.execute(
+            "UPDATE settings SET playlist_watch_dir = ?1 WHERE id = 1",
+            [&path],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    match path {
+        Some(dir) => watch_state.start(app_handle, PathBuf::from(dir)),
+        None => watch_state.stop(),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = "#EXTM3U\n#EXTINF:-1,News\nhttp://example.com/news.m3u8\n#EXTINF:-1,Sports\nhttp://example.com/sports.m3u8\n";
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE channel_lists (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                source TEXT NOT NULL,
+                filepath TEXT,
+                last_fetched INTEGER,
+                is_default BOOLEAN NOT NULL DEFAULT 0,
+                encoding TEXT NOT NULL DEFAULT 'auto',
+                detected_encoding TEXT,
+                encoding_warning TEXT
+            )",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_import_adds_then_updates_by_name() {
+        let conn = create_test_db();
+        let watch_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let file = watch_dir.path().join("Family.m3u8");
+        fs::write(&file, PLAYLIST).unwrap();
+
+        assert!(needs_import(&conn, &file).unwrap());
+        let added = import_playlist_file(&conn, &file, cache_dir.path()).unwrap();
+        assert_eq!(added.name, "Family");
+        assert_eq!(added.status, "added");
+        assert_eq!(added.channel_count, Some(2));
+        assert!(!needs_import(&conn, &file).unwrap());
+
+        let updated = import_playlist_file(&conn, &file, cache_dir.path()).unwrap();
+        assert_eq!(updated.status, "updated");
+        assert_eq!(updated.list_id, added.list_id);
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM channel_lists", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        // The copy saved by the first import was replaced, not kept alongside
+        assert_eq!(fs::read_dir(cache_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_files() {
+        let conn = create_test_db();
+        let watch_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+
+        let not_m3u = watch_dir.path().join("notes.m3u");
+        fs::write(&not_m3u, "just some text").unwrap();
+        assert!(import_playlist_file(&conn, &not_m3u, cache_dir.path()).is_err());
+
+        let empty = watch_dir.path().join("empty.m3u");
+        fs::write(&empty, "#EXTM3U\n").unwrap();
+        assert!(import_playlist_file(&conn, &empty, cache_dir.path()).is_err());
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM channel_lists", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_import_decodes_with_list_encoding() {
+        let conn = create_test_db();
+        let watch_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let file = watch_dir.path().join("Turkish.m3u");
+        fs::write(&file, b"#EXTM3U\n#EXTINF:-1,\xdeark\xfdlar\nhttp://example.com/1.ts\n").unwrap();
+
+        let added = import_playlist_file(&conn, &file, cache_dir.path()).unwrap();
+        let list_id = added.list_id.unwrap();
+        let (filepath, detected): (String, String) = conn
+            .query_row(
+                "SELECT filepath, detected_encoding FROM channel_lists WHERE id = ?1",
+                [list_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(detected, "windows-1254");
+        let cached = fs::read_to_string(cache_dir.path().join(filepath)).unwrap();
+        assert!(cached.contains("Şarkılar"));
+
+        // A manual override is used when the file is imported again
+        conn.execute("UPDATE channel_lists SET encoding = 'iso-8859-1' WHERE id = ?1", [list_id])
+            .unwrap();
+        import_playlist_file(&conn, &file, cache_dir.path()).unwrap();
+        let detected: String = conn
+            .query_row("SELECT detected_encoding FROM channel_lists WHERE id = ?1", [list_id], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(detected, "iso-8859-1");
+    }
+
+    #[test]
+    fn test_is_playlist_file() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.m3u", "b.M3U8", "c.txt"] {
+            fs::write(dir.path().join(name), PLAYLIST).unwrap();
+        }
+        assert!(is_playlist_file(&dir.path().join("a.m3u")));
+        assert!(is_playlist_file(&dir.path().join("b.M3U8")));
+        assert!(!is_playlist_file(&dir.path().join("c.txt")));
+        assert!(!is_playlist_file(dir.path()));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::channels::invalidate_channel_cache;
+use crate::content_cache::{ContentCacheState, ContentType as CacheContentType, XtreamChannel};
+use crate::m3u_parser::Channel;
+use crate::playlists::export::channels_to_m3u;
+use crate::state::{ChannelCacheState, DbState};
+use crate::xtream::{ContentType, StreamURLRequest, XtreamClient, XtreamState};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::State;
+
+/// Group for channels whose category isn't cached
+const UNCATEGORIZED: &str = "Uncategorized";
+
+/// Turn cached Xtream channels into playlist channels, grouped by category name
+fn to_playlist_channels<F>(
+    channels: &[XtreamChannel],
+    category_names: &HashMap<String, String>,
+    stream_url: F,
+) -> Vec<Channel>
+where
+    F: Fn(&XtreamChannel) -> Option<String>,
+{
+    channels
+        .iter()
+        .filter_map(|channel| {
+            let url = match &channel.direct_source {
+                Some(url) if channel.url_overridden => url.clone(),
+                _ => stream_url(channel)?,
+            };
+            let group_title = channel
+                .category_id
+                .as_ref()
+                .and_then(|id| category_names.get(id))
+                .cloned()
+                .unwrap_or_else(|| UNCATEGORIZED.to_string());
+            Some(Channel {
+                name: channel.name.clone(),
+                logo: channel.stream_icon.clone().unwrap_or_default(),
+                url,
+                group_title,
+                tvg_id: channel.epg_channel_id.clone().unwrap_or_default(),
+                resolution: String::new(),
+                extra_info: String::new(),
+                country: channel.country.clone().unwrap_or_default(),
+                country_flag: channel.country_flag.clone().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Add the channel list for a converted profile, or point the existing one at the new file
+fn upsert_channel_list(conn: &Connection, name: &str, source: &str, filename: &str) -> rusqlite::Result<i32> {
+    let now = Utc::now().timestamp();
+    let existing: Option<i32> = conn
+        .query_row("SELECT id FROM channel_lists WHERE source = ?1", params![source], |row| row.get(0))
+        .optional()?;
+    match existing {
+        Some(id) => {
+            conn.execute(
+                "UPDATE channel_lists SET filepath = ?1, last_fetched = ?2 WHERE id = ?3",
+                params![filename, now, id],
+            )?;
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO channel_lists (name, source, filepath, last_fetched) VALUES (?1, ?2, ?3, ?4)",
+                params![name, source, filename, now],
+            )?;
+            Ok(conn.last_insert_rowid() as i32)
+        }
+    }
+}
+
+/// Materialize an Xtream profile's cached live channels as a local M3U channel list
+///
+/// Categories become groups, so the list works with the playlist groups and
+/// filters. Converting the same profile again refreshes its list from the
+/// current cache instead of adding another. The stream URLs carry the
+/// profile's credentials, like a provider's own M3U link. Returns the list id.
+#[tauri::command]
+pub async fn convert_xtream_profile_to_playlist(
+    xtream_state: State<'_, XtreamState>,
+    content_cache_state: State<'_, ContentCacheState>,
+    db_state: State<'_, DbState>,
+    cache_state: State<'_, ChannelCacheState>,
+    profile_id: String,
+    name: Option<String>,
+) -> Result<i32, String> {
+    let profile = xtream_state
+        .profile_manager
+        .get_profile(&profile_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Profile not found: {}", profile_id))?;
+
+    let cache = &content_cache_state.cache;
+    let channels = cache.get_channels(&profile_id, None).map_err(|e| e.to_string())?;
+    if channels.is_empty() {
+        return Err("No live channels are cached for this profile; sync it first".to_string());
+    }
+    let category_names: HashMap<String, String> = cache
+        .get_categories(&profile_id, CacheContentType::Channels, None)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|category| (category.category_id, category.category_name))
+        .collect();
+
+    let credentials = xtream_state
+        .profile_manager
+        .get_profile_credentials_async_wrapper(&profile_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let client = XtreamClient::new(credentials, xtream_state.content_cache.clone()).map_err(|e| e.to_string())?;
+    let playlist = to_playlist_channels(&channels, &category_names, |channel| {
+        client
+            .generate_stream_url(&StreamURLRequest {
+                content_type: ContentType::Channel,
+                content_id: channel.stream_id.to_string(),
+                extension: None,
+            })
+            .ok()
+    });
+
+    let dir = dirs::data_dir()
+        .ok_or("Failed to find the data directory")?
+        .join("xtauri/channel_lists");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let filename = format!("xtream-{}.m3u", profile_id);
+    let path = dir.join(&filename);
+    std::fs::write(&path, channels_to_m3u(&playlist)).map_err(|e| format!("Failed to save: {}", e))?;
+
+    let id = {
+        let db = db_state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let name = name.unwrap_or(profile.name);
+        upsert_channel_list(&db, &name, &path.to_string_lossy(), &filename)
+            .map_err(|e| format!("Failed to save channel list: {}", e))?
+    };
+    invalidate_channel_cache(cache_state)?;
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xtream_channel(stream_id: i64, name: &str, category_id: Option<&str>) -> XtreamChannel {
+        XtreamChannel {
+            stream_id,
+            num: None,
+            name: name.to_string(),
+            stream_type: Some("live".to_string()),
+            stream_icon: Some(format!("http://logos.example.com/{}.png", stream_id)),
+            thumbnail: None,
+            epg_channel_id: Some(format!("{}.uk", name.to_lowercase())),
+            added: None,
+            category_id: category_id.map(str::to_string),
+            custom_sid: None,
+            tv_archive: None,
+            direct_source: None,
+            tv_archive_duration: None,
+            url_overridden: false,
+            country: Some("GB".to_string()),
+            country_flag: None,
+            reliability: None,
+        }
+    }
+
+    #[test]
+    fn test_channels_are_grouped_by_category_name() {
+        let mut overridden = xtream_channel(3, "Music", Some("9"));
+        overridden.direct_source = Some("http://mirror.example.com/music.m3u8".to_string());
+        overridden.url_overridden = true;
+        let channels = vec![
+            xtream_channel(1, "News", Some("7")),
+            xtream_channel(2, "Sports", None),
+            overridden,
+        ];
+        let category_names = HashMap::from([("7".to_string(), "UK News".to_string())]);
+
+        let playlist = to_playlist_channels(&channels, &category_names, |channel| {
+            Some(format!("http://provider.test/live/u/p/{}.m3u8", channel.stream_id))
+        });
+        assert_eq!(playlist.len(), 3);
+        assert_eq!(playlist[0].group_title, "UK News");
+        assert_eq!(playlist[0].tvg_id, "news.uk");
+        assert_eq!(playlist[0].url, "http://provider.test/live/u/p/1.m3u8");
+        assert_eq!(playlist[1].group_title, UNCATEGORIZED);
+        assert_eq!(playlist[2].url, "http://mirror.example.com/music.m3u8");
+
+        let m3u = channels_to_m3u(&playlist);
+        let parsed = crate::m3u_parser_helpers::parse_m3u_with_progress(&m3u, |_, _, _| {});
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].group_title, "UK News");
+        assert_eq!(parsed[0].logo, "http://logos.example.com/1.png");
+    }
+
+    #[test]
+    fn test_converting_again_updates_the_same_list() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE channel_lists (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                source TEXT NOT NULL,
+                filepath TEXT,
+                last_fetched INTEGER,
+                is_default BOOLEAN NOT NULL DEFAULT 0
+            )",
+        )
+        .unwrap();
+
+        let id = upsert_channel_list(&conn, "Provider", "/data/xtream-p1.m3u", "xtream-p1.m3u").unwrap();
+        let again = upsert_channel_list(&conn, "Renamed", "/data/xtream-p1.m3u", "xtream-p1.m3u").unwrap();
+        assert_eq!(id, again);
+
+        let (count, name): (i64, String) = conn
+            .query_row("SELECT COUNT(*), MAX(name) FROM channel_lists", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((count, name.as_str()), (1, "Provider"));
+    }
+}
@@ -0,0 +1,612 @@
+// Content post-processors applied to parsed playlists and synced Xtream content
+//
+// A post-processor looks at one item at a time and may rename it, move it to
+// another category or drop it. Processors run in registration order, each one
+// seeing the previous one's output, and only those enabled in the
+// `content_post_processors` table take part.
+use crate::content_cache::{XtreamCategory, XtreamChannel, XtreamMovie, XtreamSeries};
+use crate::error::{Result, XTauriError};
+use crate::m3u_parser::Channel;
+use crate::state::{ChannelCacheState, DbState};
+use chrono::Utc;
+use dashmap::DashMap;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::{LazyLock, RwLock};
+use tauri::State;
+
+/// Kind of item passed through the post-processors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    /// A channel from an M3U playlist
+    PlaylistChannel,
+    Channel,
+    Movie,
+    Series,
+    Category,
+}
+
+/// The parts of a parsed item a post-processor can see
+#[derive(Debug, Clone, Copy)]
+pub struct ContentItem<'a> {
+    pub kind: ContentKind,
+    pub name: &'a str,
+    /// Group title for playlist channels, category id for Xtream content
+    pub category: Option<&'a str>,
+}
+
+/// What a post-processor wants done with an item
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostProcessAction {
+    Keep,
+    Rename(String),
+    Recategorize(String),
+    Drop,
+}
+
+/// A step that rewrites or filters content as it is parsed or synced
+pub trait ContentPostProcessor: Send + Sync {
+    /// Stable identifier, used for the enabled setting and in reports
+    fn id(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn process(&self, item: &ContentItem<'_>) -> PostProcessAction;
+}
+
+/// Content types the post-processors can run over
+pub trait PostProcessable {
+    const KIND: ContentKind;
+
+    fn name(&self) -> &str;
+
+    fn category(&self) -> Option<&str>;
+
+    fn set_name(&mut self, name: String);
+
+    /// Returns `false` for items that have no category to change
+    fn set_category(&mut self, category: String) -> bool;
+}
+
+impl PostProcessable for Channel {
+    const KIND: ContentKind = ContentKind::PlaylistChannel;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn category(&self) -> Option<&str> {
+        Some(&self.group_title)
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    fn set_category(&mut self, category: String) -> bool {
+        self.group_title = category;
+        true
+    }
+}
+
+macro_rules! xtream_post_processable {
+    ($type:ty, $kind:expr) => {
+        impl PostProcessable for $type {
+            const KIND: ContentKind = $kind;
+
+            fn name(&self) -> &str {
+                &self.name
+            }
+
+            fn category(&self) -> Option<&str> {
+                self.category_id.as_deref()
+            }
+
+            fn set_name(&mut self, name: String) {
+                self.name = name;
+            }
+
+            fn set_category(&mut self, category: String) -> bool {
+                self.category_id = Some(category);
+                true
+            }
+        }
+    };
+}
+
+xtream_post_processable!(XtreamChannel, ContentKind::Channel);
+xtream_post_processable!(XtreamMovie, ContentKind::Movie);
+xtream_post_processable!(XtreamSeries, ContentKind::Series);
+
+impl PostProcessable for XtreamCategory {
+    const KIND: ContentKind = ContentKind::Category;
+
+    fn name(&self) -> &str {
+        &self.category_name
+    }
+
+    fn category(&self) -> Option<&str> {
+        None
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.category_name = name;
+    }
+
+    fn set_category(&mut self, _category: String) -> bool {
+        false
+    }
+}
+
+static COUNTRY_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:\|\s*(?:[A-Z]{2}|USA|UAE)\s*\||\[\s*(?:[A-Z]{2}|USA|UAE)\s*\]|(?:[A-Z]{2}|USA|UAE)\s*(?::|\|)|(?:[A-Z]{2}|USA|UAE)\s+-)\s*")
+        .unwrap()
+});
+
+static TRAILING_YEAR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\s*(?:[\(\[]\s*(?:19|20)\d{2}\s*[\)\]]|-\s*(?:19|20)\d{2})\s*$").unwrap()
+});
+
+/// Removes country tags such as `UK:`, `|US|`, `[FR]` or `DE -` from the start of names
+pub struct StripCountryPrefix;
+
+impl ContentPostProcessor for StripCountryPrefix {
+    fn id(&self) -> &'static str {
+        "strip_country_prefix"
+    }
+
+    fn description(&self) -> &'static str {
+        "Remove country tags like \"UK:\" or \"|US|\" from the start of names"
+    }
+
+    fn process(&self, item: &ContentItem<'_>) -> PostProcessAction {
+        let stripped = COUNTRY_PREFIX.replace(item.name, "");
+        if stripped.len() == item.name.len() || stripped.trim().is_empty() {
+            return PostProcessAction::Keep;
+        }
+        PostProcessAction::Rename(stripped.into_owned())
+    }
+}
+
+/// Words kept as written when title-casing
+const TITLE_CASE_KEEP: &[&str] = &["HD", "FHD", "UHD", "SD", "HDR", "TV", "4K", "8K"];
+
+/// Title-cases names written entirely in upper or lower case
+///
+/// Mixed-case names are left alone, since they usually carry deliberate
+/// capitalisation such as "BBC One".
+pub struct TitleCaseNames;
+
+fn title_case_word(word: &str) -> String {
+    if TITLE_CASE_KEEP.contains(&word.to_uppercase().as_str()) {
+        return word.to_uppercase();
+    }
+    if word.chars().any(|c| c.is_ascii_digit()) {
+        return word.to_string();
+    }
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+impl ContentPostProcessor for TitleCaseNames {
+    fn id(&self) -> &'static str {
+        "title_case_names"
+    }
+
+    fn description(&self) -> &'static str {
+        "Title-case names written in all capitals or all lower case"
+    }
+
+    fn process(&self, item: &ContentItem<'_>) -> PostProcessAction {
+        let has_upper = item.name.chars().any(char::is_uppercase);
+        let has_lower = item.name.chars().any(char::is_lowercase);
+        if has_upper && has_lower {
+            return PostProcessAction::Keep;
+        }
+
+        let renamed = item
+            .name
+            .split(' ')
+            .map(title_case_word)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if renamed == item.name {
+            PostProcessAction::Keep
+        } else {
+            PostProcessAction::Rename(renamed)
+        }
+    }
+}
+
+/// Removes a trailing release year such as `(1999)` or `- 2004` from movie and series names
+pub struct StripYear;
+
+impl ContentPostProcessor for StripYear {
+    fn id(&self) -> &'static str {
+        "strip_year"
+    }
+
+    fn description(&self) -> &'static str {
+        "Remove a trailing release year like \"(1999)\" from movie and series names"
+    }
+
+    fn process(&self, item: &ContentItem<'_>) -> PostProcessAction {
+        if !matches!(item.kind, ContentKind::Movie | ContentKind::Series) {
+            return PostProcessAction::Keep;
+        }
+        let stripped = TRAILING_YEAR.replace(item.name, "");
+        if stripped.len() == item.name.len() || stripped.trim().is_empty() {
+            return PostProcessAction::Keep;
+        }
+        PostProcessAction::Rename(stripped.into_owned())
+    }
+}
+
+/// Changes made by one post-processor
+#[derive(Debug, Clone, Default, Serialize, PartialEq, specta::Type)]
+pub struct ProcessorChangeCounts {
+    pub processor_id: String,
+    pub renamed: usize,
+    pub recategorized: usize,
+    pub dropped: usize,
+}
+
+/// Changes the post-processors made to one playlist or Xtream profile
+#[derive(Debug, Clone, Default, Serialize, specta::Type)]
+pub struct PostProcessReport {
+    /// `playlist:<id>` or `xtream:<profile_id>`
+    pub source: String,
+    pub items_processed: usize,
+    pub processors: Vec<ProcessorChangeCounts>,
+    pub updated_at: i64,
+}
+
+impl PostProcessReport {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn counts_mut(&mut self, processor_id: &str) -> &mut ProcessorChangeCounts {
+        let index = match self
+            .processors
+            .iter()
+            .position(|counts| counts.processor_id == processor_id)
+        {
+            Some(index) => index,
+            None => {
+                self.processors.push(ProcessorChangeCounts {
+                    processor_id: processor_id.to_string(),
+                    ..Default::default()
+                });
+                self.processors.len() - 1
+            }
+        };
+        &mut self.processors[index]
+    }
+}
+
+/// The available post-processors, in the order they run
+pub struct PostProcessorRegistry {
+    processors: Vec<Box<dyn ContentPostProcessor>>,
+}
+
+impl Default for PostProcessorRegistry {
+    fn default() -> Self {
+        let mut registry = Self { processors: Vec::new() };
+        registry.register(Box::new(StripCountryPrefix));
+        registry.register(Box::new(StripYear));
+        registry.register(Box::new(TitleCaseNames));
+        registry
+    }
+}
+
+impl PostProcessorRegistry {
+    /// Add a processor after the existing ones, replacing any with the same id
+    pub fn register(&mut self, processor: Box<dyn ContentPostProcessor>) {
+        self.processors.retain(|existing| existing.id() != processor.id());
+        self.processors.push(processor);
+    }
+
+    pub fn processors(&self) -> &[Box<dyn ContentPostProcessor>] {
+        &self.processors
+    }
+
+    /// Run the enabled processors over `items`, counting their changes in `report`
+    pub fn apply<T: PostProcessable>(
+        &self,
+        enabled: &HashSet<String>,
+        items: Vec<T>,
+        report: &mut PostProcessReport,
+    ) -> Vec<T> {
+        let active: Vec<&dyn ContentPostProcessor> = self
+            .processors
+            .iter()
+            .map(|processor| processor.as_ref())
+            .filter(|processor| enabled.contains(processor.id()))
+            .collect();
+        if active.is_empty() {
+            return items;
+        }
+
+        report.items_processed += items.len();
+        report.updated_at = Utc::now().timestamp();
+        items
+            .into_iter()
+            .filter_map(|mut item| {
+                for processor in &active {
+                    let action = processor.process(&ContentItem {
+                        kind: T::KIND,
+                        name: item.name(),
+                        category: item.category(),
+                    });
+                    match action {
+                        PostProcessAction::Keep => {}
+                        PostProcessAction::Rename(name) => {
+                            if name != item.name() {
+                                item.set_name(name);
+                                report.counts_mut(processor.id()).renamed += 1;
+                            }
+                        }
+                        PostProcessAction::Recategorize(category) => {
+                            if item.category() != Some(category.as_str()) && item.set_category(category) {
+                                report.counts_mut(processor.id()).recategorized += 1;
+                            }
+                        }
+                        PostProcessAction::Drop => {
+                            report.counts_mut(processor.id()).dropped += 1;
+                            return None;
+                        }
+                    }
+                }
+                Some(item)
+            })
+            .collect()
+    }
+}
+
+static REGISTRY: LazyLock<RwLock<PostProcessorRegistry>> =
+    LazyLock::new(|| RwLock::new(PostProcessorRegistry::default()));
+
+/// Latest report per source
+static REPORTS: LazyLock<DashMap<String, PostProcessReport>> = LazyLock::new(DashMap::new);
+
+/// Make a post-processor available to be enabled in settings
+pub fn register(processor: Box<dyn ContentPostProcessor>) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.register(processor);
+    }
+}
+
+pub fn playlist_source(list_id: i32) -> String {
+    format!("playlist:{}", list_id)
+}
+
+pub fn xtream_source(profile_id: &str) -> String {
+    format!("xtream:{}", profile_id)
+}
+
+/// Start a fresh report for a source, e.g. at the beginning of a sync run
+pub fn begin_report(source: &str) {
+    REPORTS.insert(source.to_string(), PostProcessReport::new(source));
+}
+
+/// Run the enabled post-processors over items parsed or synced from `source`
+///
+/// Changes are added to the source's current report. Items pass through
+/// untouched if no processor is enabled or the settings can't be read.
+pub fn apply<T: PostProcessable>(conn: &Connection, source: &str, items: Vec<T>) -> Vec<T> {
+    let enabled = match PostProcessorDb::enabled(conn) {
+        Ok(enabled) if !enabled.is_empty() => enabled,
+        _ => return items,
+    };
+    let Ok(registry) = REGISTRY.read() else {
+        return items;
+    };
+
+    let mut report = REPORTS
+        .entry(source.to_string())
+        .or_insert_with(|| PostProcessReport::new(source));
+    registry.apply(&enabled, items, &mut report)
+}
+
+/// Database operations for the post-processor toggles
+pub struct PostProcessorDb;
+
+impl PostProcessorDb {
+    pub fn enabled(conn: &Connection) -> Result<HashSet<String>> {
+        let mut stmt =
+            conn.prepare_cached("SELECT processor_id FROM content_post_processors WHERE enabled = 1")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<HashSet<String>, _>>()?;
+        Ok(ids)
+    }
+
+    pub fn set_enabled(conn: &Connection, processor_id: &str, enabled: bool) -> Result<()> {
+        conn.execute(
+            "INSERT INTO content_post_processors (processor_id, enabled) VALUES (?1, ?2)
+             ON CONFLICT(processor_id) DO UPDATE SET enabled = ?2",
+            params![processor_id, enabled],
+        )?;
+        Ok(())
+    }
+}
+
+/// A registered post-processor and whether it is enabled
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct PostProcessorInfo {
+    pub id: String,
+    pub description: String,
+    pub enabled: bool,
+}
+
+#[tauri::command]
+pub fn get_content_post_processors(
+    state: State<DbState>,
+) -> std::result::Result<Vec<PostProcessorInfo>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let enabled = PostProcessorDb::enabled(&db).map_err(|e| e.to_string())?;
+    let registry = REGISTRY.read().map_err(|e| e.to_string())?;
+    Ok(registry
+        .processors()
+        .iter()
+        .map(|processor| PostProcessorInfo {
+            id: processor.id().to_string(),
+            description: processor.description().to_string(),
+            enabled: enabled.contains(processor.id()),
+        })
+        .collect())
+}
+
+/// Turn a post-processor on or off
+///
+/// Playlists pick the change up the next time they are loaded; Xtream content
+/// on the next sync.
+#[tauri::command]
+pub fn set_content_post_processor_enabled(
+    state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    processor_id: String,
+    enabled: bool,
+) -> std::result::Result<(), String> {
+    let known = REGISTRY
+        .read()
+        .map_err(|e| e.to_string())?
+        .processors()
+        .iter()
+        .any(|processor| processor.id() == processor_id);
+    if !known {
+        return Err(XTauriError::internal(format!("Unknown post-processor: {}", processor_id)).to_string());
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    PostProcessorDb::set_enabled(&db, &processor_id, enabled).map_err(|e| e.to_string())?;
+    drop(db);
+
+    crate::channels::invalidate_channel_cache(cache_state)
+}
+
+/// Changes the post-processors made on the latest load or sync of each source
+#[tauri::command]
+pub fn get_post_process_reports() -> Vec<PostProcessReport> {
+    let mut reports: Vec<PostProcessReport> = REPORTS.iter().map(|report| report.clone()).collect();
+    reports.sort_by(|a, b| a.source.cmp(&b.source));
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename(processor: &dyn ContentPostProcessor, kind: ContentKind, name: &str) -> Option<String> {
+        match processor.process(&ContentItem { kind, name, category: None }) {
+            PostProcessAction::Rename(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_builtin_processors() {
+        let kind = ContentKind::Channel;
+        assert_eq!(rename(&StripCountryPrefix, kind, "UK: BBC One").as_deref(), Some("BBC One"));
+        assert_eq!(rename(&StripCountryPrefix, kind, "|US| CNN").as_deref(), Some("CNN"));
+        assert_eq!(rename(&StripCountryPrefix, kind, "[FR] TF1").as_deref(), Some("TF1"));
+        assert_eq!(rename(&StripCountryPrefix, kind, "DE - Das Erste").as_deref(), Some("Das Erste"));
+        assert_eq!(rename(&StripCountryPrefix, kind, "BBC News"), None);
+        assert_eq!(rename(&StripCountryPrefix, kind, "UK:"), None);
+
+        assert_eq!(rename(&TitleCaseNames, kind, "SKY SPORTS NEWS HD").as_deref(), Some("Sky Sports News HD"));
+        assert_eq!(rename(&TitleCaseNames, kind, "discovery channel").as_deref(), Some("Discovery Channel"));
+        assert_eq!(rename(&TitleCaseNames, kind, "BBC One"), None);
+
+        let movie = ContentKind::Movie;
+        assert_eq!(rename(&StripYear, movie, "The Matrix (1999)").as_deref(), Some("The Matrix"));
+        assert_eq!(rename(&StripYear, movie, "Heat - 1995").as_deref(), Some("Heat"));
+        assert_eq!(rename(&StripYear, movie, "Blade Runner 2049"), None);
+        assert_eq!(rename(&StripYear, movie, "(2019)"), None);
+        assert_eq!(rename(&StripYear, kind, "Channel (2020)"), None);
+    }
+
+    struct DropAdult;
+
+    impl ContentPostProcessor for DropAdult {
+        fn id(&self) -> &'static str {
+            "drop_adult"
+        }
+
+        fn description(&self) -> &'static str {
+            "Drop adult channels"
+        }
+
+        fn process(&self, item: &ContentItem<'_>) -> PostProcessAction {
+            match item.category {
+                Some("Adult") => PostProcessAction::Drop,
+                Some("Sport") => PostProcessAction::Recategorize("Sports".to_string()),
+                _ => PostProcessAction::Keep,
+            }
+        }
+    }
+
+    fn channel(name: &str, group: &str) -> Channel {
+        Channel {
+            name: name.to_string(),
+            logo: String::new(),
+            url: String::new(),
+            group_title: group.to_string(),
+            tvg_id: String::new(),
+            resolution: String::new(),
+            extra_info: String::new(),
+            country: String::new(),
+            country_flag: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_registry_applies_enabled_processors_and_counts_changes() {
+        let mut registry = PostProcessorRegistry::default();
+        registry.register(Box::new(DropAdult));
+        let enabled: HashSet<String> = ["strip_country_prefix", "title_case_names", "drop_adult"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+
+        let mut report = PostProcessReport::new("playlist:1");
+        let channels = registry.apply(
+            &enabled,
+            vec![
+                channel("UK: SKY NEWS", "News"),
+                channel("UK: Late Night", "Adult"),
+                channel("Eurosport 1", "Sport"),
+            ],
+            &mut report,
+        );
+
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].name, "Sky News");
+        assert_eq!(channels[1].group_title, "Sports");
+        assert_eq!(report.items_processed, 3);
+        assert_eq!(
+            report.processors,
+            vec![
+                ProcessorChangeCounts { processor_id: "strip_country_prefix".to_string(), renamed: 2, ..Default::default() },
+                ProcessorChangeCounts { processor_id: "title_case_names".to_string(), renamed: 1, ..Default::default() },
+                ProcessorChangeCounts { processor_id: "drop_adult".to_string(), recategorized: 1, dropped: 1, ..Default::default() },
+            ]
+        );
+
+        // Nothing enabled leaves items and the report untouched
+        let mut empty = PostProcessReport::new("playlist:2");
+        let untouched = registry.apply(&HashSet::new(), vec![channel("UK: SKY NEWS", "News")], &mut empty);
+        assert_eq!(untouched[0].name, "UK: SKY NEWS");
+        assert_eq!(empty.items_processed, 0);
+    }
+}
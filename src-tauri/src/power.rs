@@ -0,0 +1,267 @@
+// Low-power mode for laptops
+//
+// On battery, work the user didn't ask for is put off: image batches only
+// fetch their top-priority group instead of prefetching the rest, and EPG data
+// is cached four times as long before it is fetched again. The mode follows the power source by default and can be
+// forced on or off; it is kept in the `low_power_mode` setting. The power
+// source is read from sysfs on Linux and `pmset` on macOS; elsewhere it is
+// unknown and only the manual setting applies.
+use crate::error::Result;
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+/// How often the power source is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Refresh intervals are multiplied by this in low-power mode
+const INTERVAL_FACTOR: u32 = 4;
+
+/// Whether low-power mode is on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum LowPowerMode {
+    /// On while running on battery
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+impl LowPowerMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LowPowerMode::Auto => "auto",
+            LowPowerMode::On => "on",
+            LowPowerMode::Off => "off",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "on" => LowPowerMode::On,
+            "off" => LowPowerMode::Off,
+            _ => LowPowerMode::Auto,
+        }
+    }
+}
+
+/// Payload of a `power_state_changed` event
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct PowerState {
+    pub mode: LowPowerMode,
+    /// `None` when the power source can't be detected on this platform
+    pub on_battery: Option<bool>,
+    /// Whether background work is cut back right now
+    pub low_power: bool,
+}
+
+/// Mirrors the `low_power_mode` setting
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Last detected power source: 0 unknown, 1 mains, 2 battery
+static SOURCE: AtomicU8 = AtomicU8::new(0);
+
+/// State in the last `power_state_changed` event
+static LAST_SENT: Mutex<Option<PowerState>> = Mutex::new(None);
+
+/// Mode in effect: the configuration file's, else the saved setting
+fn mode() -> LowPowerMode {
+    if let Some(mode) = crate::user_config::low_power_mode() {
+        return mode;
+    }
+    match MODE.load(Ordering::Relaxed) {
+        1 => LowPowerMode::On,
+        2 => LowPowerMode::Off,
+        _ => LowPowerMode::Auto,
+    }
+}
+
+fn store_mode(mode: LowPowerMode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+fn on_battery() -> Option<bool> {
+    match SOURCE.load(Ordering::Relaxed) {
+        1 => Some(false),
+        2 => Some(true),
+        _ => None,
+    }
+}
+
+fn store_on_battery(on_battery: Option<bool>) {
+    SOURCE.store(on_battery.map_or(0, |battery| if battery { 2 } else { 1 }), Ordering::Relaxed);
+}
+
+impl PowerState {
+    fn new(mode: LowPowerMode, on_battery: Option<bool>) -> Self {
+        let low_power = match mode {
+            LowPowerMode::Auto => on_battery == Some(true),
+            LowPowerMode::On => true,
+            LowPowerMode::Off => false,
+        };
+        Self {
+            mode,
+            on_battery,
+            low_power,
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::new(mode(), on_battery())
+    }
+}
+
+/// Whether background work should be cut back
+pub fn low_power() -> bool {
+    PowerState::current().low_power
+}
+
+/// A refresh interval, lengthened in low-power mode
+pub fn scale_interval(interval: Duration) -> Duration {
+    if low_power() {
+        interval * INTERVAL_FACTOR
+    } else {
+        interval
+    }
+}
+
+/// Power supplies as `(type, online, status)` read from sysfs
+///
+/// On battery when a mains supply is offline, or, without a mains supply,
+/// when a battery is discharging.
+fn on_battery_from_supplies(supplies: &[(String, Option<String>, Option<String>)]) -> Option<bool> {
+    let mut mains = supplies.iter().filter(|(kind, _, _)| kind == "Mains").peekable();
+    if mains.peek().is_some() {
+        return Some(!mains.any(|(_, online, _)| online.as_deref() == Some("1")));
+    }
+    let mut batteries = supplies.iter().filter(|(kind, _, _)| kind == "Battery").peekable();
+    batteries.peek()?;
+    Some(batteries.any(|(_, _, status)| status.as_deref() == Some("Discharging")))
+}
+
+/// Whether the machine runs on battery; `None` if it can't be told
+#[cfg(target_os = "linux")]
+fn detect_on_battery() -> Option<bool> {
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).ok().map(|value| value.trim().to_string());
+    let supplies: Vec<_> = std::fs::read_dir("/sys/class/power_supply")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let dir = entry.path();
+            Some((read(dir.join("type"))?, read(dir.join("online")), read(dir.join("status"))))
+        })
+        .collect();
+    on_battery_from_supplies(&supplies)
+}
+
+#[cfg(target_os = "macos")]
+fn detect_on_battery() -> Option<bool> {
+    let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let first_line = String::from_utf8_lossy(&output.stdout).lines().next()?.to_string();
+    if first_line.contains("'Battery Power'") {
+        Some(true)
+    } else if first_line.contains("'AC Power'") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn detect_on_battery() -> Option<bool> {
+    None
+}
+
+pub fn load_settings(conn: &Connection) -> Result<LowPowerMode> {
+    let value: String = conn.query_row("SELECT low_power_mode FROM settings WHERE id = 1", [], |row| row.get(0))?;
+    let mode = LowPowerMode::parse(&value);
+    store_mode(mode);
+    Ok(mode)
+}
+
+fn save_mode(conn: &Connection, mode: LowPowerMode) -> Result<()> {
+    conn.execute("UPDATE settings SET low_power_mode = ?1 WHERE id = 1", [mode.as_str()])?;
+    store_mode(mode);
+    Ok(())
+}
+
+/// Send `power_state_changed` if the state differs from the last one sent
+pub fn notify_if_changed(app_handle: &AppHandle) {
+    let state = PowerState::current();
+    let Ok(mut last) = LAST_SENT.lock() else {
+        return;
+    };
+    if last.as_ref() != Some(&state) {
+        // The first state is only recorded; the frontend asks for it on load
+        if last.is_some() {
+            events::emit(app_handle, AppEvent::PowerStateChanged(state.clone()));
+        }
+        *last = Some(state);
+    }
+}
+
+/// Follow the power source, sending `power_state_changed` whenever the state changes
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    loop {
+        let detected = tauri::async_runtime::spawn_blocking(detect_on_battery)
+            .await
+            .ok()
+            .flatten();
+        store_on_battery(detected);
+        notify_if_changed(&app_handle);
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[tauri::command]
+pub fn get_power_state() -> PowerState {
+    PowerState::current()
+}
+
+/// Follow the power source, or force low-power mode on or off
+#[tauri::command]
+pub fn set_low_power_mode(
+    app_handle: AppHandle,
+    state: State<DbState>,
+    mode: LowPowerMode,
+) -> std::result::Result<PowerState, String> {
+    {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        save_mode(&db, mode).map_err(|e| e.to_string())?;
+    }
+    notify_if_changed(&app_handle);
+    Ok(PowerState::current())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(kind: &str, online: Option<&str>, status: Option<&str>) -> (String, Option<String>, Option<String>) {
+        (kind.to_string(), online.map(String::from), status.map(String::from))
+    }
+
+    #[test]
+    fn test_power_source_and_mode() {
+        let laptop_unplugged = [supply("Mains", Some("0"), None), supply("Battery", None, Some("Discharging"))];
+        let laptop_plugged = [supply("Mains", Some("1"), None), supply("Battery", None, Some("Charging"))];
+        let no_mains = [supply("Battery", None, Some("Discharging"))];
+        assert_eq!(on_battery_from_supplies(&laptop_unplugged), Some(true));
+        assert_eq!(on_battery_from_supplies(&laptop_plugged), Some(false));
+        assert_eq!(on_battery_from_supplies(&no_mains), Some(true));
+        assert_eq!(on_battery_from_supplies(&[supply("USB", Some("1"), None)]), None);
+
+        assert!(PowerState::new(LowPowerMode::Auto, Some(true)).low_power);
+        assert!(!PowerState::new(LowPowerMode::Auto, None).low_power);
+        assert!(PowerState::new(LowPowerMode::On, Some(false)).low_power);
+        assert!(!PowerState::new(LowPowerMode::Off, Some(true)).low_power);
+        assert_eq!(LowPowerMode::parse(LowPowerMode::Off.as_str()), LowPowerMode::Off);
+    }
+}
@@ -0,0 +1,342 @@
+// Opt-in profiling of the parser, sync and search subsystems
+//
+// Built with `--features profiling`, the `profile_span!` and `profile_future!`
+// macros open tracing spans whose timings are kept in memory for the session.
+// `dump_profile` writes them out as Chrome trace JSON, which chrome://tracing,
+// Perfetto and speedscope show as a flamegraph. Without the feature the macros
+// compile to nothing.
+use serde::Serialize;
+
+/// Time a block; the span lasts until the returned guard is dropped
+#[cfg(feature = "profiling")]
+macro_rules! profile_span {
+    ($category:literal, $name:literal) => {
+        tracing::info_span!(target: concat!("xtauri_profile::", $category), $name).entered()
+    };
+}
+
+#[cfg(not(feature = "profiling"))]
+macro_rules! profile_span {
+    ($category:literal, $name:literal) => {
+        $crate::profiling::DisabledSpan
+    };
+}
+
+/// Time a future across all of its polls
+#[cfg(feature = "profiling")]
+macro_rules! profile_future {
+    ($category:literal, $name:literal, $future:expr) => {
+        tracing::Instrument::instrument(
+            $future,
+            tracing::info_span!(target: concat!("xtauri_profile::", $category), $name),
+        )
+    };
+}
+
+#[cfg(not(feature = "profiling"))]
+macro_rules! profile_future {
+    ($category:literal, $name:literal, $future:expr) => {
+        $future
+    };
+}
+
+pub(crate) use profile_future;
+pub(crate) use profile_span;
+
+/// Stand-in for a span guard in builds without profiling
+#[cfg(not(feature = "profiling"))]
+pub struct DisabledSpan;
+
+/// One timed span in Chrome's "complete event" format
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TraceEvent {
+    pub name: &'static str,
+    pub cat: &'static str,
+    pub ph: &'static str,
+    /// Start, in microseconds since the app started
+    pub ts: u64,
+    /// Duration in microseconds
+    pub dur: u64,
+    pub pid: u32,
+    pub tid: u64,
+}
+
+/// A Chrome trace file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChromeTrace {
+    pub trace_events: Vec<TraceEvent>,
+    pub display_time_unit: &'static str,
+}
+
+/// What `dump_profile` wrote
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ProfileDump {
+    pub path: String,
+    pub events: usize,
+    /// Spans not recorded because the session hit the recording limit
+    pub dropped_events: usize,
+}
+
+#[cfg(feature = "profiling")]
+mod recorder {
+    use super::TraceEvent;
+    use dashmap::DashMap;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::{LazyLock, Mutex};
+    use std::time::Instant;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::subscriber::Interest;
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Target prefix of the spans opened by the profiling macros
+    pub const PROFILE_TARGET_PREFIX: &str = "xtauri_profile::";
+
+    /// Spans recorded beyond this are dropped and counted, so a long session can't
+    /// grow without bound
+    pub const MAX_PROFILE_EVENTS: usize = 200_000;
+
+    static EPOCH: LazyLock<Instant> = LazyLock::new(Instant::now);
+    static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+    static EVENTS: Mutex<Vec<TraceEvent>> = Mutex::new(Vec::new());
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static THREAD_ID: u64 = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+        /// Spans entered on this thread with the time they were entered
+        static ENTERED: RefCell<Vec<(u64, Instant)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct SpanInfo {
+        name: &'static str,
+        category: &'static str,
+        refs: usize,
+    }
+
+    /// Subscriber that records each entry into a profiling span as a trace event
+    ///
+    /// Spans from other targets, including dependencies, are ignored.
+    pub struct Recorder {
+        spans: DashMap<u64, SpanInfo>,
+        next_id: AtomicU64,
+    }
+
+    impl Recorder {
+        pub fn new() -> Self {
+            LazyLock::force(&EPOCH);
+            Self {
+                spans: DashMap::new(),
+                next_id: AtomicU64::new(1),
+            }
+        }
+    }
+
+    fn is_profiled(metadata: &Metadata<'_>) -> bool {
+        metadata.is_span() && metadata.target().starts_with(PROFILE_TARGET_PREFIX)
+    }
+
+    pub fn record(event: TraceEvent) {
+        let Ok(mut events) = EVENTS.lock() else {
+            return;
+        };
+        if events.len() < MAX_PROFILE_EVENTS {
+            events.push(event);
+        } else {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Events recorded so far and the number dropped, optionally starting a new session
+    pub fn snapshot(reset: bool) -> (Vec<TraceEvent>, usize) {
+        let mut events = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+        if reset {
+            (std::mem::take(&mut *events), DROPPED.swap(0, Ordering::Relaxed))
+        } else {
+            (events.clone(), DROPPED.load(Ordering::Relaxed))
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+            if is_profiled(metadata) {
+                Interest::always()
+            } else {
+                Interest::never()
+            }
+        }
+
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            is_profiled(metadata)
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let metadata = span.metadata();
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            self.spans.insert(
+                id,
+                SpanInfo {
+                    name: metadata.name(),
+                    category: metadata
+                        .target()
+                        .strip_prefix(PROFILE_TARGET_PREFIX)
+                        .unwrap_or(metadata.target()),
+                    refs: 1,
+                },
+            );
+            Id::from_u64(id)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, _event: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            ENTERED.with(|entered| entered.borrow_mut().push((span.into_u64(), Instant::now())));
+        }
+
+        fn exit(&self, span: &Id) {
+            let id = span.into_u64();
+            let entered_at = ENTERED.with(|entered| {
+                let mut entered = entered.borrow_mut();
+                let index = entered.iter().rposition(|(entered_id, _)| *entered_id == id)?;
+                Some(entered.remove(index).1)
+            });
+            let (Some(entered_at), Some(info)) = (entered_at, self.spans.get(&id)) else {
+                return;
+            };
+
+            record(TraceEvent {
+                name: info.name,
+                cat: info.category,
+                ph: "X",
+                ts: entered_at.saturating_duration_since(*EPOCH).as_micros() as u64,
+                dur: entered_at.elapsed().as_micros() as u64,
+                pid: std::process::id(),
+                tid: THREAD_ID.with(|tid| *tid),
+            });
+        }
+
+        fn clone_span(&self, span: &Id) -> Id {
+            if let Some(mut info) = self.spans.get_mut(&span.into_u64()) {
+                info.refs += 1;
+            }
+            span.clone()
+        }
+
+        fn try_close(&self, span: Id) -> bool {
+            let id = span.into_u64();
+            let closed = match self.spans.get_mut(&id) {
+                Some(mut info) => {
+                    info.refs -= 1;
+                    info.refs == 0
+                }
+                None => false,
+            };
+            if closed {
+                self.spans.remove(&id);
+            }
+            closed
+        }
+    }
+}
+
+/// Whether this build records profiling spans
+pub fn is_enabled() -> bool {
+    cfg!(feature = "profiling")
+}
+
+/// Start recording profiling spans; called once at startup
+pub fn init() {
+    #[cfg(feature = "profiling")]
+    if let Err(e) = tracing::subscriber::set_global_default(recorder::Recorder::new()) {
+        eprintln!("Failed to install profiling recorder: {}", e);
+    }
+}
+
+/// The spans recorded this session as a Chrome trace, plus the number dropped
+///
+/// With `reset` the recorded spans are cleared so the next dump covers a new session.
+pub fn take_trace(reset: bool) -> (ChromeTrace, usize) {
+    #[cfg(feature = "profiling")]
+    let (trace_events, dropped) = recorder::snapshot(reset);
+    #[cfg(not(feature = "profiling"))]
+    let (trace_events, dropped) = {
+        let _ = reset;
+        (Vec::new(), 0)
+    };
+
+    let trace = ChromeTrace {
+        trace_events,
+        display_time_unit: "ms",
+    };
+    (trace, dropped)
+}
+
+/// Write the session's profiling spans to `path` as Chrome trace JSON
+///
+/// Only available in builds with the `profiling` feature.
+#[tauri::command]
+pub fn dump_profile(path: String, reset: Option<bool>) -> Result<ProfileDump, String> {
+    if !is_enabled() {
+        return Err("Profiling is not enabled in this build; rebuild with --features profiling".to_string());
+    }
+
+    let (trace, dropped_events) = take_trace(reset.unwrap_or(false));
+    let json = serde_json::to_vec(&trace).map_err(|e| format!("Failed to serialize profile: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write profile: {}", e))?;
+
+    Ok(ProfileDump {
+        path,
+        events: trace.trace_events.len(),
+        dropped_events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chrome_trace_format() {
+        let trace = ChromeTrace {
+            trace_events: vec![TraceEvent {
+                name: "parse_m3u_content",
+                cat: "parser",
+                ph: "X",
+                ts: 10,
+                dur: 250,
+                pid: 1,
+                tid: 2,
+            }],
+            display_time_unit: "ms",
+        };
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["displayTimeUnit"], "ms");
+        assert_eq!(json["traceEvents"][0]["name"], "parse_m3u_content");
+        assert_eq!(json["traceEvents"][0]["cat"], "parser");
+        assert_eq!(json["traceEvents"][0]["ph"], "X");
+        assert_eq!(json["traceEvents"][0]["dur"], 250);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_spans_are_recorded() {
+        let _ = tracing::subscriber::set_global_default(recorder::Recorder::new());
+        {
+            let _span = profile_span!("test", "test_spans_are_recorded");
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let (trace, _) = take_trace(false);
+        let event = trace
+            .trace_events
+            .iter()
+            .find(|event| event.name == "test_spans_are_recorded")
+            .expect("span was recorded");
+        assert_eq!(event.cat, "test");
+        assert!(event.dur >= 2000);
+    }
+}
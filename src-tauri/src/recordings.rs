@@ -0,0 +1,346 @@
+// Recording of live streams to disk
+//
+// `start_recording` writes a live stream to a file under the data directory
+// until it is stopped, its duration runs out or the stream ends. Plain
+// MPEG-TS streams are copied as they arrive; HLS playlists are followed
+// segment by segment. Players can open the file while it still grows, which
+// is how timeshift works on live channels. Recordings are kept in the
+// `recordings` table, and each one in progress is a cancellable background
+// task.
+//
+// Builds without the `recordings` feature keep the table and the listing
+// commands, but leave out the capture code in `capture` and can't start a
+// recording.
+#![cfg_attr(not(feature = "recordings"), allow(dead_code))]
+
+#[cfg(feature = "recordings")]
+mod capture;
+
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use chrono::Utc;
+use dashmap::DashMap;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use tauri::{AppHandle, State};
+use tokio_util::sync::CancellationToken;
+
+/// Recordings in progress, by recording id
+static ACTIVE: LazyLock<DashMap<String, ActiveRecording>> = LazyLock::new(DashMap::new);
+
+#[derive(Clone)]
+struct ActiveRecording {
+    cancel_token: CancellationToken,
+    /// Bytes written to the file so far
+    written: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingStatus {
+    Recording,
+    /// The duration ran out or the stream ended
+    Completed,
+    /// Stopped with `stop_recording`
+    Stopped,
+    Failed,
+}
+
+impl RecordingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordingStatus::Recording => "recording",
+            RecordingStatus::Completed => "completed",
+            RecordingStatus::Stopped => "stopped",
+            RecordingStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "recording" => RecordingStatus::Recording,
+            "completed" => RecordingStatus::Completed,
+            "stopped" => RecordingStatus::Stopped,
+            _ => RecordingStatus::Failed,
+        }
+    }
+}
+
+/// A recording as listed by `list_recordings`
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct Recording {
+    pub id: String,
+    pub name: String,
+    pub stream_url: String,
+    pub profile_id: Option<String>,
+    pub channel_id: Option<String>,
+    /// Can be played while the recording is still running
+    pub file_path: String,
+    pub status: RecordingStatus,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    /// Bytes written so far
+    pub bytes: u64,
+    pub error: Option<String>,
+}
+
+/// What `start_recording` should record
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+pub struct RecordingRequest {
+    pub stream_url: String,
+    /// Shown in the list and used for the file name; usually the channel name
+    pub name: String,
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    /// Stop after this many minutes; without it the recording runs until stopped
+    #[serde(default)]
+    pub duration_minutes: Option<u32>,
+}
+
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recordings (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            stream_url TEXT NOT NULL,
+            profile_id TEXT,
+            channel_id TEXT,
+            file_path TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER,
+            bytes INTEGER NOT NULL DEFAULT 0,
+            error TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+const RECORDING_COLUMNS: &str =
+    "id, name, stream_url, profile_id, channel_id, file_path, status, started_at, ended_at, bytes, error";
+
+fn from_row(row: &Row) -> rusqlite::Result<Recording> {
+    Ok(Recording {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        stream_url: row.get(2)?,
+        profile_id: row.get(3)?,
+        channel_id: row.get(4)?,
+        file_path: row.get(5)?,
+        status: RecordingStatus::parse(&row.get::<_, String>(6)?),
+        started_at: row.get(7)?,
+        ended_at: row.get(8)?,
+        bytes: row.get::<_, i64>(9)?.max(0) as u64,
+        error: row.get(10)?,
+    })
+}
+
+fn insert(conn: &Connection, recording: &Recording) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO recordings ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            RECORDING_COLUMNS
+        ),
+        params![
+            recording.id,
+            recording.name,
+            recording.stream_url,
+            recording.profile_id,
+            recording.channel_id,
+            recording.file_path,
+            recording.status.as_str(),
+            recording.started_at,
+            recording.ended_at,
+            recording.bytes as i64,
+            recording.error,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Store how a recording ended
+fn finish(conn: &Connection, recording: &Recording) -> Result<()> {
+    conn.execute(
+        "UPDATE recordings SET status = ?2, ended_at = ?3, bytes = ?4, error = ?5 WHERE id = ?1",
+        params![
+            recording.id,
+            recording.status.as_str(),
+            recording.ended_at,
+            recording.bytes as i64,
+            recording.error,
+        ],
+    )?;
+    Ok(())
+}
+
+fn get(conn: &Connection, recording_id: &str) -> Result<Option<Recording>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {} FROM recordings WHERE id = ?1", RECORDING_COLUMNS),
+            params![recording_id],
+            from_row,
+        )
+        .optional()?)
+}
+
+/// Every recording, newest first, with the bytes written so far for those still running
+pub fn list(conn: &Connection) -> Result<Vec<Recording>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM recordings ORDER BY started_at DESC, id",
+        RECORDING_COLUMNS
+    ))?;
+    let mut recordings = stmt.query_map([], from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    for recording in &mut recordings {
+        if let Some(active) = ACTIVE.get(&recording.id) {
+            recording.bytes = active.written.load(Ordering::Relaxed);
+        }
+    }
+    Ok(recordings)
+}
+
+/// Mark recordings still listed as running as failed; called at startup
+///
+/// They were cut off when the app last exited. Returns how many there were.
+pub fn mark_interrupted(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute(
+        "UPDATE recordings SET status = ?1, ended_at = COALESCE(ended_at, ?2),
+                error = 'Interrupted when the app closed'
+         WHERE status = ?3",
+        params![
+            RecordingStatus::Failed.as_str(),
+            Utc::now().timestamp(),
+            RecordingStatus::Recording.as_str()
+        ],
+    )?)
+}
+
+/// Directory recordings are written to
+pub fn recordings_dir() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .ok_or(XTauriError::DataDirectoryAccess)?
+        .join("xtauri")
+        .join("recordings"))
+}
+
+/// Start recording a live stream to disk
+///
+/// Returns once the file is created; a `recording_updated` event follows when
+/// the recording ends.
+#[tauri::command]
+pub async fn start_recording(
+    app_handle: AppHandle,
+    state: State<'_, DbState>,
+    request: RecordingRequest,
+) -> std::result::Result<Recording, String> {
+    #[cfg(feature = "recordings")]
+    return capture::start(app_handle, &state, request)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result);
+    #[cfg(not(feature = "recordings"))]
+    {
+        let _ = (app_handle, state, request);
+        Err("Recordings are not included in this build".to_string())
+    }
+}
+
+/// Stop a running recording; the file recorded so far is kept
+#[tauri::command]
+pub fn stop_recording(recording_id: String) -> std::result::Result<(), String> {
+    let active = ACTIVE
+        .get(&recording_id)
+        .ok_or_else(|| format!("Recording {} is not running", recording_id))?;
+    active.cancel_token.cancel();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_recordings(state: State<DbState>) -> std::result::Result<Vec<Recording>, String> {
+    let conn = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    list(&conn).map_err(|e| e.to_string()).and_then(crate::anonymize::mask_result)
+}
+
+/// Remove a finished recording from the list, and its file with `delete_file`
+#[tauri::command]
+pub fn delete_recording(
+    state: State<DbState>,
+    recording_id: String,
+    delete_file: bool,
+) -> std::result::Result<(), String> {
+    if ACTIVE.contains_key(&recording_id) {
+        return Err("Stop the recording before deleting it".to_string());
+    }
+    let conn = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let recording = get(&conn, &recording_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Recording {} not found", recording_id))?;
+
+    if delete_file {
+        match std::fs::remove_file(&recording.file_path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete {}: {}", recording.file_path, e)),
+        }
+    }
+    conn.execute("DELETE FROM recordings WHERE id = ?1", params![recording_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording(id: &str, started_at: i64) -> Recording {
+        Recording {
+            id: id.to_string(),
+            name: "News 24".to_string(),
+            stream_url: "http://provider/live/user/pass/42.ts".to_string(),
+            profile_id: Some("p1".to_string()),
+            channel_id: Some("42".to_string()),
+            file_path: format!("/tmp/{}.ts", id),
+            status: RecordingStatus::Recording,
+            started_at,
+            ended_at: None,
+            bytes: 0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_recordings_table() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_table(&conn).unwrap();
+        insert(&conn, &recording("a", 100)).unwrap();
+        insert(&conn, &recording("b", 200)).unwrap();
+
+        let ids: Vec<String> = list(&conn).unwrap().into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["b", "a"]);
+
+        let done = Recording {
+            status: RecordingStatus::Stopped,
+            ended_at: Some(160),
+            bytes: 4096,
+            ..recording("a", 100)
+        };
+        finish(&conn, &done).unwrap();
+        let stored = get(&conn, "a").unwrap().unwrap();
+        assert_eq!(stored.status, RecordingStatus::Stopped);
+        assert_eq!(stored.ended_at, Some(160));
+        assert_eq!(stored.bytes, 4096);
+
+        // Only the recording still marked as running was cut off
+        assert_eq!(mark_interrupted(&conn).unwrap(), 1);
+        let interrupted = get(&conn, "b").unwrap().unwrap();
+        assert_eq!(interrupted.status, RecordingStatus::Failed);
+        assert!(interrupted.ended_at.is_some());
+        assert!(interrupted.error.is_some());
+        assert_eq!(get(&conn, "a").unwrap().unwrap().status, RecordingStatus::Stopped);
+    }
+}
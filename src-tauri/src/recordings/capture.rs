@@ -0,0 +1,398 @@
+// Capturing live streams into recording files
+//
+// Only compiled with the `recordings` feature. Plain MPEG-TS streams are
+// copied as they arrive, reconnecting when they drop; HLS playlists are
+// polled and each new segment is appended to the file.
+use super::{finish, insert, recordings_dir, ActiveRecording, Recording, RecordingRequest, RecordingStatus, ACTIVE};
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use crate::tasks::{self, TaskKind};
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Reconnects in a row that receive nothing before a recording gives up
+const RECONNECT_ATTEMPTS: u32 = 3;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// A stream that sends nothing for this long is treated as dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Segment URLs remembered so the window of a live playlist isn't downloaded twice
+const HLS_SEEN_SEGMENTS: usize = 256;
+
+/// File name for a recording: its name made safe for any file system, the start time and a short id
+fn file_name(name: &str, started_at: i64, recording_id: &str) -> String {
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(80)
+        .collect();
+    let safe = safe.trim();
+    let time = chrono::DateTime::from_timestamp(started_at, 0)
+        .unwrap_or_default()
+        .format("%Y%m%d-%H%M%S");
+    let short_id: String = recording_id.chars().take(8).collect();
+    format!(
+        "{}_{}_{}.ts",
+        if safe.is_empty() { "recording" } else { safe },
+        time,
+        short_id
+    )
+}
+
+/// What a fetched HLS playlist holds
+#[derive(Debug, Default, PartialEq)]
+struct HlsPlaylist {
+    /// Highest-bandwidth variant, when this is a master playlist
+    variant: Option<String>,
+    segments: Vec<String>,
+    target_duration: Option<u64>,
+    /// `#EXT-X-ENDLIST`: no more segments will be added
+    ended: bool,
+}
+
+fn parse_playlist(text: &str) -> HlsPlaylist {
+    let mut playlist = HlsPlaylist::default();
+    let mut best_bandwidth = None;
+    // Bandwidth of the `#EXT-X-STREAM-INF` line whose URI comes next
+    let mut pending_variant: Option<u64> = None;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            pending_variant = Some(
+                attributes
+                    .split(',')
+                    .find_map(|attribute| attribute.strip_prefix("BANDWIDTH="))
+                    .and_then(|bandwidth| bandwidth.parse().ok())
+                    .unwrap_or(0),
+            );
+        } else if let Some(duration) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            playlist.target_duration = duration.trim().parse().ok();
+        } else if line == "#EXT-X-ENDLIST" {
+            playlist.ended = true;
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some(bandwidth) = pending_variant.take() {
+            if best_bandwidth.is_none_or(|best| bandwidth > best) {
+                best_bandwidth = Some(bandwidth);
+                playlist.variant = Some(line.to_string());
+            }
+        } else {
+            playlist.segments.push(line.to_string());
+        }
+    }
+    playlist
+}
+
+fn is_hls(url: &Url) -> bool {
+    url.path().to_ascii_lowercase().ends_with(".m3u8")
+}
+
+async fn fetch(client: &reqwest::Client, url: &Url) -> Result<reqwest::Response> {
+    Ok(client.get(url.clone()).send().await?.error_for_status()?)
+}
+
+/// Copy a response body into `file` until the server closes it
+async fn copy_stream(client: &reqwest::Client, url: &Url, file: &mut File, written: &AtomicU64) -> Result<()> {
+    let mut response = fetch(client, url).await?;
+    loop {
+        let chunk = tokio::time::timeout(READ_TIMEOUT, response.chunk())
+            .await
+            .map_err(|_| XTauriError::internal("The stream stopped sending data"))??;
+        let Some(chunk) = chunk else {
+            return Ok(());
+        };
+        file.write_all(&chunk).await?;
+        written.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Record a continuous stream, reconnecting when it drops
+///
+/// Live streams are expected to run until stopped, so a stream the server
+/// closes is reconnected too. The recording ends once the reconnects in a row
+/// stop bringing in data.
+async fn record_direct(client: &reqwest::Client, url: &Url, file: &mut File, written: &AtomicU64) -> Result<()> {
+    let mut attempts = 0;
+    loop {
+        let before = written.load(Ordering::Relaxed);
+        let result = copy_stream(client, url, file, written).await;
+        let total = written.load(Ordering::Relaxed);
+        if total > before {
+            attempts = 0;
+        }
+        attempts += 1;
+
+        if attempts > RECONNECT_ATTEMPTS {
+            return match result {
+                Ok(()) if total == 0 => Err(XTauriError::internal("The stream sent no data")),
+                result => result,
+            };
+        }
+        if let Err(e) = result {
+            eprintln!("[RECORDING] Stream dropped, reconnecting: {}", e);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Record an HLS stream by appending each new segment of its playlist
+async fn record_hls(client: &reqwest::Client, url: Url, file: &mut File, written: &AtomicU64) -> Result<()> {
+    let mut playlist_url = url;
+    let mut followed_variant = false;
+    let mut seen: VecDeque<Url> = VecDeque::new();
+    let mut failures = 0;
+
+    loop {
+        let playlist = match fetch(client, &playlist_url).await {
+            Ok(response) => parse_playlist(&response.text().await?),
+            Err(e) => {
+                failures += 1;
+                if failures > RECONNECT_ATTEMPTS {
+                    return Err(e);
+                }
+                eprintln!("[RECORDING] Failed to fetch HLS playlist, retrying: {}", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+
+        if let Some(variant) = &playlist.variant {
+            if followed_variant {
+                return Err(XTauriError::internal(
+                    "The HLS variant playlist is a master playlist too",
+                ));
+            }
+            playlist_url = playlist_url
+                .join(variant)
+                .map_err(|e| XTauriError::internal(format!("Invalid HLS variant URL: {}", e)))?;
+            followed_variant = true;
+            continue;
+        }
+
+        for segment in &playlist.segments {
+            let segment_url = playlist_url
+                .join(segment)
+                .map_err(|e| XTauriError::internal(format!("Invalid HLS segment URL: {}", e)))?;
+            if seen.contains(&segment_url) {
+                continue;
+            }
+            match copy_stream(client, &segment_url, file, written).await {
+                Ok(()) => failures = 0,
+                Err(e) => {
+                    failures += 1;
+                    if failures > RECONNECT_ATTEMPTS {
+                        return Err(e);
+                    }
+                    eprintln!("[RECORDING] Skipped HLS segment: {}", e);
+                }
+            }
+            seen.push_back(segment_url);
+            if seen.len() > HLS_SEEN_SEGMENTS {
+                seen.pop_front();
+            }
+        }
+
+        if playlist.ended {
+            return Ok(());
+        }
+        // Poll at half the target duration, as the HLS spec asks of clients when a playlist didn't change
+        let target = playlist.target_duration.unwrap_or(4).max(2);
+        tokio::time::sleep(Duration::from_secs(target) / 2).await;
+    }
+}
+
+/// Resolves after `duration`, or never without one
+async fn until(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn run(
+    app_handle: AppHandle,
+    client: reqwest::Client,
+    mut file: File,
+    recording: Recording,
+    url: Url,
+    duration: Option<Duration>,
+    active: ActiveRecording,
+) {
+    let _task = tasks::register_cancellable(
+        TaskKind::Recording,
+        format!("Recording {}", recording.name),
+        recording.profile_id.as_deref(),
+        active.cancel_token.clone(),
+    );
+
+    let recorded = async {
+        if is_hls(&url) {
+            record_hls(&client, url.clone(), &mut file, &active.written).await
+        } else {
+            record_direct(&client, &url, &mut file, &active.written).await
+        }
+    };
+    let result = tokio::select! {
+        result = recorded => result.map(|_| RecordingStatus::Completed),
+        _ = active.cancel_token.cancelled() => Ok(RecordingStatus::Stopped),
+        _ = until(duration) => Ok(RecordingStatus::Completed),
+    };
+    let result = match file.flush().await {
+        Ok(()) => result,
+        Err(e) => result.and(Err(e.into())),
+    };
+
+    ACTIVE.remove(&recording.id);
+    let (status, error) = match result {
+        Ok(status) => (status, None),
+        Err(e) => (RecordingStatus::Failed, Some(e.to_string())),
+    };
+    let recording = Recording {
+        status,
+        ended_at: Some(Utc::now().timestamp()),
+        bytes: active.written.load(Ordering::Relaxed),
+        error,
+        ..recording
+    };
+    println!(
+        "[RECORDING] {} ended as {} after {} bytes",
+        recording.name,
+        recording.status.as_str(),
+        recording.bytes
+    );
+
+    if let Some(db_state) = app_handle.try_state::<DbState>() {
+        let result = db_state
+            .db
+            .lock()
+            .map_err(|e| e.to_string())
+            .and_then(|db| finish(&db, &recording).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!(
+                "[RECORDING] Failed to store the end of recording {}: {}",
+                recording.id, e
+            );
+        }
+    }
+    events::emit(&app_handle, AppEvent::RecordingUpdated(recording));
+}
+
+pub(super) async fn start(app_handle: AppHandle, state: &DbState, request: RecordingRequest) -> Result<Recording> {
+    let url = Url::parse(request.stream_url.trim())
+        .map_err(|e| XTauriError::internal(format!("Invalid stream URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(XTauriError::internal("Only HTTP streams can be recorded"));
+    }
+    let name = match request.name.trim() {
+        "" => "Recording",
+        name => name,
+    };
+
+    let dir = recordings_dir()?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| XTauriError::internal(format!("Failed to create recordings directory: {}", e)))?;
+    let client = crate::connection::client_builder().build()?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let started_at = Utc::now().timestamp();
+    let path = dir.join(file_name(name, started_at, &id));
+    let file = File::create(&path).await?;
+    let recording = Recording {
+        id,
+        name: name.to_string(),
+        stream_url: url.to_string(),
+        profile_id: request.profile_id,
+        channel_id: request.channel_id,
+        file_path: path.to_string_lossy().into_owned(),
+        status: RecordingStatus::Recording,
+        started_at,
+        ended_at: None,
+        bytes: 0,
+        error: None,
+    };
+
+    let stored = state
+        .db
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("database connection"))
+        .and_then(|conn| insert(&conn, &recording));
+    if let Err(e) = stored {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+
+    let active = ActiveRecording {
+        cancel_token: CancellationToken::new(),
+        written: Arc::new(AtomicU64::new(0)),
+    };
+    ACTIVE.insert(recording.id.clone(), active.clone());
+    let duration = request
+        .duration_minutes
+        .map(|minutes| Duration::from_secs(u64::from(minutes) * 60));
+    println!("[RECORDING] Started {} into {}", recording.name, recording.file_path);
+
+    events::emit(&app_handle, AppEvent::RecordingUpdated(recording.clone()));
+    tauri::async_runtime::spawn(run(app_handle, client, file, recording.clone(), url, duration, active));
+    Ok(recording)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_media_playlist() {
+        let playlist = parse_playlist(
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:120\n\
+             #EXTINF:6.0,\nseg120.ts\n#EXTINF:6.0,\n/live/seg121.ts\n#EXT-X-ENDLIST\n",
+        );
+        assert_eq!(
+            playlist,
+            HlsPlaylist {
+                variant: None,
+                segments: vec!["seg120.ts".to_string(), "/live/seg121.ts".to_string()],
+                target_duration: Some(6),
+                ended: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_master_playlist_picks_highest_bandwidth() {
+        let playlist = parse_playlist(
+            "#EXTM3U\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,CODECS=\"avc1.4d401e,mp4a.40.2\"\nlow.m3u8\n\
+             #EXT-X-STREAM-INF:AVERAGE-BANDWIDTH=2000000,BANDWIDTH=2500000\nhigh.m3u8\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=1400000\nmid.m3u8\n",
+        );
+        assert_eq!(playlist.variant.as_deref(), Some("high.m3u8"));
+        assert!(playlist.segments.is_empty());
+        assert!(!playlist.ended);
+    }
+
+    #[test]
+    fn test_file_name_is_safe() {
+        let name = file_name("Sport: HD / 1080p", 0, "0123456789abcdef");
+        assert_eq!(name, "Sport_ HD _ 1080p_19700101-000000_01234567.ts");
+        assert_eq!(file_name("../", 0, "ab"), "____19700101-000000_ab.ts");
+        assert_eq!(file_name("  ", 0, "ab"), "recording_19700101-000000_ab.ts");
+    }
+}
@@ -0,0 +1,822 @@
+// Local remote control API for phone remotes and Stream Deck style plugins
+use crate::content_cache::{
+    ChannelFilter, ContentCache, ContentCacheState, MovieFilter, SeriesFilter,
+};
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use crate::xtream::{XtreamFavoritesDb, XtreamState};
+use base64::{engine::general_purpose, Engine as _};
+use rand::{thread_rng, RngCore};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Default port the remote control server listens on
+pub const DEFAULT_REMOTE_CONTROL_PORT: u16 = 8787;
+
+/// Upper bound for a single request (headers + body)
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+/// Maximum number of results returned per content type by the search endpoint
+const MAX_SEARCH_RESULTS: usize = 25;
+
+/// Persisted remote control configuration
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct RemoteControlSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub pairing_token: String,
+}
+
+/// Request sent by a remote to start playback of a content item
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct RemotePlayRequest {
+    pub profile_id: String,
+    pub content_type: String,
+    pub content_id: String,
+}
+
+/// Managed state tracking the running remote control server
+pub struct RemoteControlState {
+    server: Mutex<Option<CancellationToken>>,
+}
+
+impl RemoteControlState {
+    pub fn new() -> Self {
+        Self {
+            server: Mutex::new(None),
+        }
+    }
+
+    /// Check whether the server is currently running
+    pub fn is_running(&self) -> bool {
+        self.server
+            .lock()
+            .map(|server| server.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Stop the server if it is running
+    pub fn stop(&self) -> Result<()> {
+        let mut server = self
+            .server
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("remote control server"))?;
+
+        if let Some(cancel_token) = server.take() {
+            cancel_token.cancel();
+        }
+
+        Ok(())
+    }
+
+    /// Start the server with the given settings, replacing any running instance
+    pub async fn start(
+        &self,
+        settings: &RemoteControlSettings,
+        context: RemoteControlContext,
+    ) -> Result<()> {
+        self.stop()?;
+
+        // Only bind to the loopback interface; the API is never exposed on the network
+        let listener = TcpListener::bind(("127.0.0.1", settings.port))
+            .await
+            .map_err(|e| {
+                XTauriError::internal(format!(
+                    "Failed to bind remote control server on port {}: {}",
+                    settings.port, e
+                ))
+            })?;
+
+        let cancel_token = CancellationToken::new();
+        tokio::spawn(run_server(listener, Arc::new(context), cancel_token.clone()));
+
+        let mut server = self
+            .server
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("remote control server"))?;
+        *server = Some(cancel_token);
+
+        Ok(())
+    }
+}
+
+impl Default for RemoteControlState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything the request handlers need to answer remote requests
+pub struct RemoteControlContext {
+    pairing_token: String,
+    favorites_db: Arc<Mutex<Connection>>,
+    content_cache: Arc<ContentCache>,
+    play_tx: mpsc::UnboundedSender<RemotePlayRequest>,
+}
+
+impl RemoteControlContext {
+    pub fn new(
+        pairing_token: String,
+        favorites_db: Arc<Mutex<Connection>>,
+        content_cache: Arc<ContentCache>,
+        play_tx: mpsc::UnboundedSender<RemotePlayRequest>,
+    ) -> Self {
+        Self {
+            pairing_token,
+            favorites_db,
+            content_cache,
+            play_tx,
+        }
+    }
+}
+
+/// Parsed HTTP request
+#[derive(Debug, Default)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// HTTP response with a JSON body
+#[derive(Debug)]
+struct HttpResponse {
+    status: u16,
+    body: Value,
+}
+
+impl HttpResponse {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let body = self.body.to_string();
+        let reason = match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            _ => "Internal Server Error",
+        };
+
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+}
+
+/// Generate a new random pairing token
+pub fn generate_pairing_token() -> String {
+    let mut bytes = [0u8; 24];
+    thread_rng().fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Load remote control settings, creating a pairing token on first use
+pub fn load_settings(conn: &Connection) -> Result<RemoteControlSettings> {
+    let (enabled, port, token): (bool, i64, Option<String>) = conn.query_row(
+        "SELECT remote_control_enabled, remote_control_port, remote_control_token FROM settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    let pairing_token = match token {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            let token = generate_pairing_token();
+            conn.execute(
+                "UPDATE settings SET remote_control_token = ?1 WHERE id = 1",
+                [&token],
+            )?;
+            token
+        }
+    };
+
+    Ok(RemoteControlSettings {
+        enabled,
+        port: u16::try_from(port).unwrap_or(DEFAULT_REMOTE_CONTROL_PORT),
+        pairing_token,
+    })
+}
+
+/// Persist remote control settings
+pub fn save_settings(conn: &Connection, settings: &RemoteControlSettings) -> Result<()> {
+    conn.execute(
+        "UPDATE settings SET remote_control_enabled = ?1, remote_control_port = ?2, remote_control_token = ?3 WHERE id = 1",
+        rusqlite::params![settings.enabled, settings.port, settings.pairing_token],
+    )?;
+    Ok(())
+}
+
+/// Compare tokens without short-circuiting on the first differing byte
+fn tokens_match(expected: &str, provided: &str) -> bool {
+    let expected = expected.as_bytes();
+    let provided = provided.as_bytes();
+
+    if expected.len() != provided.len() {
+        return false;
+    }
+
+    expected
+        .iter()
+        .zip(provided.iter())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// Check the pairing token sent as a bearer token or `X-Pairing-Token` header
+fn is_authorized(request: &HttpRequest, pairing_token: &str) -> bool {
+    let provided = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| request.header("x-pairing-token"));
+
+    match provided {
+        Some(token) => tokens_match(pairing_token, token.trim()),
+        None => false,
+    }
+}
+
+/// Parse the request line and headers of a raw HTTP request
+fn parse_request_head(head: &str) -> Option<HttpRequest> {
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_uppercase();
+    let target = parts.next()?;
+
+    let (path, query_string) = match target.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (target, ""),
+    };
+
+    let query = url::form_urlencoded::parse(query_string.as_bytes())
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Some(HttpRequest {
+        method,
+        path: path.to_string(),
+        query,
+        headers,
+        body: Vec::new(),
+    })
+}
+
+/// Read a full HTTP request from the stream
+async fn read_request(stream: &mut TcpStream) -> std::result::Result<HttpRequest, HttpResponse> {
+    let mut buffer = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|_| HttpResponse::error(400, "Failed to read request"))?;
+        if read == 0 {
+            return Err(HttpResponse::error(400, "Incomplete request"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        if let Some(pos) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos;
+        }
+        if buffer.len() > MAX_REQUEST_SIZE {
+            return Err(HttpResponse::error(413, "Request too large"));
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut request =
+        parse_request_head(&head).ok_or_else(|| HttpResponse::error(400, "Malformed request"))?;
+
+    let content_length = request
+        .header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    if header_end + 4 + content_length > MAX_REQUEST_SIZE {
+        return Err(HttpResponse::error(413, "Request too large"));
+    }
+
+    let mut body = buffer[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|_| HttpResponse::error(400, "Failed to read request body"))?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+
+    Ok(request)
+}
+
+/// Route an authenticated request to its handler
+fn handle_request(context: &RemoteControlContext, request: &HttpRequest) -> HttpResponse {
+    if !is_authorized(request, &context.pairing_token) {
+        return HttpResponse::error(401, "Missing or invalid pairing token");
+    }
+
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/status") => Ok(HttpResponse::ok(json!({ "status": "ok" }))),
+        ("GET", "/api/favorites") => handle_favorites(context, request),
+        ("GET", "/api/search") => handle_search(context, request),
+        ("POST", "/api/play") => handle_play(context, request),
+        (_, "/api/status") | (_, "/api/favorites") | (_, "/api/search") | (_, "/api/play") => {
+            Ok(HttpResponse::error(405, "Method not allowed"))
+        }
+        _ => Ok(HttpResponse::error(404, "Unknown endpoint")),
+    };
+
+    result.unwrap_or_else(|e| HttpResponse::error(500, e.user_message()))
+}
+
+fn handle_favorites(context: &RemoteControlContext, request: &HttpRequest) -> Result<HttpResponse> {
+    let profile_id = match request.query_param("profile_id") {
+        Some(profile_id) if !profile_id.is_empty() => profile_id,
+        _ => return Ok(HttpResponse::error(400, "profile_id is required")),
+    };
+
+    let conn = context
+        .favorites_db
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+    let favorites = match request.query_param("content_type") {
+        Some(content_type) => {
+            XtreamFavoritesDb::get_favorites_by_type(&conn, profile_id, content_type)?
+        }
+        None => XtreamFavoritesDb::get_favorites(&conn, profile_id)?,
+    };
+
+    Ok(HttpResponse::ok(json!({ "favorites": favorites })))
+}
+
+fn handle_search(context: &RemoteControlContext, request: &HttpRequest) -> Result<HttpResponse> {
+    let profile_id = match request.query_param("profile_id") {
+        Some(profile_id) if !profile_id.is_empty() => profile_id,
+        _ => return Ok(HttpResponse::error(400, "profile_id is required")),
+    };
+    let query = match request.query_param("q") {
+        Some(query) if !query.trim().is_empty() => query.trim(),
+        _ => return Ok(HttpResponse::error(400, "q is required")),
+    };
+
+    let channels = context.content_cache.search_channels(
+        profile_id,
+        query,
+        Some(ChannelFilter {
+            limit: Some(MAX_SEARCH_RESULTS),
+            ..Default::default()
+        }),
+    )?;
+    let movies = context.content_cache.search_movies(
+        profile_id,
+        query,
+        Some(MovieFilter {
+            limit: Some(MAX_SEARCH_RESULTS),
+            ..Default::default()
+        }),
+        None,
+        None,
+    )?;
+    let series = context.content_cache.fts_search_series(
+        profile_id,
+        query,
+        Some(SeriesFilter {
+            limit: Some(MAX_SEARCH_RESULTS),
+            ..Default::default()
+        }),
+    )?;
+
+    Ok(HttpResponse::ok(json!({
+        "channels": channels,
+        "movies": movies,
+        "series": series,
+    })))
+}
+
+fn handle_play(context: &RemoteControlContext, request: &HttpRequest) -> Result<HttpResponse> {
+    let play_request: RemotePlayRequest = match serde_json::from_slice(&request.body) {
+        Ok(play_request) => play_request,
+        Err(e) => return Ok(HttpResponse::error(400, format!("Invalid play request: {}", e))),
+    };
+
+    if play_request.profile_id.is_empty() || play_request.content_id.is_empty() {
+        return Ok(HttpResponse::error(400, "profile_id and content_id are required"));
+    }
+    if !matches!(play_request.content_type.as_str(), "channel" | "movie" | "series") {
+        return Ok(HttpResponse::error(400, "content_type must be channel, movie or series"));
+    }
+
+    context
+        .play_tx
+        .send(play_request)
+        .map_err(|_| XTauriError::internal("Remote control event channel closed"))?;
+
+    Ok(HttpResponse {
+        status: 202,
+        body: json!({ "status": "queued" }),
+    })
+}
+
+async fn handle_connection(mut stream: TcpStream, context: &RemoteControlContext) {
+    let response = match read_request(&mut stream).await {
+        Ok(request) => handle_request(context, &request),
+        Err(response) => response,
+    };
+
+    if let Err(e) = stream.write_all(&response.to_bytes()).await {
+        eprintln!("[REMOTE CONTROL] Failed to write response: {}", e);
+    }
+    let _ = stream.shutdown().await;
+}
+
+async fn run_server(
+    listener: TcpListener,
+    context: Arc<RemoteControlContext>,
+    cancel_token: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let context = Arc::clone(&context);
+                    tokio::spawn(async move {
+                        handle_connection(stream, &context).await;
+                    });
+                }
+                Err(e) => eprintln!("[REMOTE CONTROL] Failed to accept connection: {}", e),
+            }
+        }
+    }
+}
+
+/// Start or stop the server so it matches the given settings
+async fn apply_settings(
+    app_handle: &AppHandle,
+    remote_state: &RemoteControlState,
+    xtream_state: &XtreamState,
+    cache_state: &ContentCacheState,
+    settings: &RemoteControlSettings,
+) -> Result<()> {
+    if !settings.enabled {
+        return remote_state.stop();
+    }
+
+    // Forward play requests to the frontend, which owns the player
+    let (play_tx, mut play_rx) = mpsc::unbounded_channel::<RemotePlayRequest>();
+    let handle = app_handle.clone();
+    tokio::spawn(async move {
+        while let Some(play_request) = play_rx.recv().await {
+            events::emit(&handle, AppEvent::RemoteControlPlay(play_request));
+        }
+    });
+
+    let context = RemoteControlContext::new(
+        settings.pairing_token.clone(),
+        xtream_state.profile_manager.get_db_connection(),
+        Arc::clone(&cache_state.cache),
+        play_tx,
+    );
+
+    remote_state.start(settings, context).await
+}
+
+/// Start the remote control server on launch if it was left enabled
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    let settings = {
+        let db_state = app_handle.state::<DbState>();
+        let db = match db_state.db.lock() {
+            Ok(db) => db,
+            Err(_) => return,
+        };
+        match load_settings(&db) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("[REMOTE CONTROL] Failed to load settings: {}", e);
+                return;
+            }
+        }
+    };
+
+    if !settings.enabled {
+        return;
+    }
+
+    let remote_state = app_handle.state::<RemoteControlState>();
+    let xtream_state = app_handle.state::<XtreamState>();
+    let cache_state = app_handle.state::<ContentCacheState>();
+    if let Err(e) = apply_settings(
+        &app_handle,
+        &remote_state,
+        &xtream_state,
+        &cache_state,
+        &settings,
+    )
+    .await
+    {
+        eprintln!("[REMOTE CONTROL] Failed to start server: {}", e);
+    }
+}
+
+fn update_settings(
+    db_state: &DbState,
+    update: impl FnOnce(&mut RemoteControlSettings),
+) -> std::result::Result<RemoteControlSettings, String> {
+    let db = db_state.db.lock().map_err(|e| e.to_string())?;
+    let mut settings = load_settings(&db).map_err(|e| e.to_string())?;
+    update(&mut settings);
+    save_settings(&db, &settings).map_err(|e| e.to_string())?;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn get_remote_control_settings(
+    db_state: State<DbState>,
+) -> std::result::Result<RemoteControlSettings, String> {
+    let db = db_state.db.lock().map_err(|e| e.to_string())?;
+    load_settings(&db).map_err(|e| e.to_string()).and_then(crate::anonymize::mask_result)
+}
+
+#[tauri::command]
+pub fn is_remote_control_running(remote_state: State<RemoteControlState>) -> bool {
+    remote_state.is_running()
+}
+
+#[tauri::command]
+pub async fn set_remote_control_enabled(
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+    remote_state: State<'_, RemoteControlState>,
+    xtream_state: State<'_, XtreamState>,
+    cache_state: State<'_, ContentCacheState>,
+    enabled: bool,
+) -> std::result::Result<RemoteControlSettings, String> {
+    let settings = update_settings(&db_state, |settings| settings.enabled = enabled)?;
+    apply_settings(&app_handle, &remote_state, &xtream_state, &cache_state, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(settings)
+}
+
+#[tauri::command]
+pub async fn set_remote_control_port(
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+    remote_state: State<'_, RemoteControlState>,
+    xtream_state: State<'_, XtreamState>,
+    cache_state: State<'_, ContentCacheState>,
+    port: u16,
+) -> std::result::Result<RemoteControlSettings, String> {
+    if port < 1024 {
+        return Err("Port must be 1024 or higher".to_string());
+    }
+
+    let settings = update_settings(&db_state, |settings| settings.port = port)?;
+    apply_settings(&app_handle, &remote_state, &xtream_state, &cache_state, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(settings)
+}
+
+/// Issue a new pairing token; previously paired remotes must pair again
+#[tauri::command]
+pub async fn rotate_remote_control_token(
+    app_handle: AppHandle,
+    db_state: State<'_, DbState>,
+    remote_state: State<'_, RemoteControlState>,
+    xtream_state: State<'_, XtreamState>,
+    cache_state: State<'_, ContentCacheState>,
+) -> std::result::Result<RemoteControlSettings, String> {
+    let settings = update_settings(&db_state, |settings| {
+        settings.pairing_token = generate_pairing_token()
+    })?;
+    apply_settings(&app_handle, &remote_state, &xtream_state, &cache_state, &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_context() -> (RemoteControlContext, mpsc::UnboundedReceiver<RemotePlayRequest>) {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_used DATETIME,
+                is_active BOOLEAN DEFAULT FALSE
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_favorites (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                content_data BLOB NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(profile_id, content_type, content_id)
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('profile-1', 'Test', 'http://example.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+
+        let db = Arc::new(Mutex::new(conn));
+        let cache = Arc::new(ContentCache::new(Arc::clone(&db)).unwrap());
+        let (play_tx, play_rx) = mpsc::unbounded_channel();
+
+        (
+            RemoteControlContext::new("secret-token".to_string(), db, cache, play_tx),
+            play_rx,
+        )
+    }
+
+    fn request(method: &str, target: &str, token: Option<&str>, body: &str) -> HttpRequest {
+        let mut head = format!("{} {} HTTP/1.1\r\nHost: localhost", method, target);
+        if let Some(token) = token {
+            head.push_str(&format!("\r\nAuthorization: Bearer {}", token));
+        }
+        let mut request = parse_request_head(&head).unwrap();
+        request.body = body.as_bytes().to_vec();
+        request
+    }
+
+    #[test]
+    fn test_parse_request_head() {
+        let request = parse_request_head(
+            "GET /api/search?profile_id=abc&q=news%20hd HTTP/1.1\r\nX-Pairing-Token: t0k",
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/search");
+        assert_eq!(request.query_param("q"), Some("news hd"));
+        assert_eq!(request.header("x-pairing-token"), Some("t0k"));
+    }
+
+    #[test]
+    fn test_requests_without_valid_token_are_rejected() {
+        let (context, _rx) = create_test_context();
+
+        let response = handle_request(&context, &request("GET", "/api/status", None, ""));
+        assert_eq!(response.status, 401);
+
+        let response =
+            handle_request(&context, &request("GET", "/api/status", Some("wrong-token!"), ""));
+        assert_eq!(response.status, 401);
+
+        let response =
+            handle_request(&context, &request("GET", "/api/status", Some("secret-token"), ""));
+        assert_eq!(response.status, 200);
+    }
+
+    #[test]
+    fn test_favorites_endpoint() {
+        let (context, _rx) = create_test_context();
+        {
+            let conn = context.favorites_db.lock().unwrap();
+            XtreamFavoritesDb::add_favorite(
+                &conn,
+                &crate::xtream::AddFavoriteRequest {
+                    profile_id: "profile-1".to_string(),
+                    content_type: "channel".to_string(),
+                    content_id: "42".to_string(),
+                    content_data: json!({ "name": "News" }),
+                },
+            )
+            .unwrap();
+        }
+
+        let response = handle_request(
+            &context,
+            &request("GET", "/api/favorites?profile_id=profile-1", Some("secret-token"), ""),
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["favorites"].as_array().unwrap().len(), 1);
+
+        let response = handle_request(
+            &context,
+            &request("GET", "/api/favorites", Some("secret-token"), ""),
+        );
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_play_endpoint_forwards_request() {
+        let (context, mut rx) = create_test_context();
+
+        let response = handle_request(
+            &context,
+            &request(
+                "POST",
+                "/api/play",
+                Some("secret-token"),
+                r#"{"profile_id":"profile-1","content_type":"channel","content_id":"42"}"#,
+            ),
+        );
+        assert_eq!(response.status, 202);
+
+        let forwarded = rx.try_recv().unwrap();
+        assert_eq!(forwarded.content_id, "42");
+
+        let response = handle_request(
+            &context,
+            &request("POST", "/api/play", Some("secret-token"), r#"{"bad":true}"#),
+        );
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_unknown_routes_and_methods() {
+        let (context, _rx) = create_test_context();
+
+        let response =
+            handle_request(&context, &request("GET", "/api/unknown", Some("secret-token"), ""));
+        assert_eq!(response.status, 404);
+
+        let response =
+            handle_request(&context, &request("DELETE", "/api/play", Some("secret-token"), ""));
+        assert_eq!(response.status, 405);
+    }
+
+    #[test]
+    fn test_generated_tokens_are_unique() {
+        let first = generate_pairing_token();
+        let second = generate_pairing_token();
+
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, second);
+        assert!(tokens_match(&first, &first));
+        assert!(!tokens_match(&first, &second));
+    }
+}
@@ -1,15 +1,23 @@
+use crate::anonymize;
+use crate::events::{self, AppEvent};
 use crate::m3u_parser::Channel;
 use crate::state::{ChannelCacheState, DbState};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{LazyLock, Mutex};
+use std::sync::LazyLock;
 use std::time::{Duration, SystemTime};
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, State, Window};
 
-use crate::channels::{get_cached_channels, ChannelLoadingStatus};
+use crate::channels::{filter_by_country, get_cached_channels, ChannelListing, ChannelLoadingStatus};
+use crate::concurrency::{self, Subsystem};
+use crate::content_cache::epg;
 use crate::fuzzy_search::FuzzyMatcher;
+use crate::memory::channel_bytes;
+use crate::pagination::Page;
+use crate::profiling::profile_span;
+use crate::search_session::{self, SearchTicket};
 
 #[cfg(test)]
 mod tests {
@@ -26,6 +34,8 @@ mod tests {
                 tvg_id: "bbc1".to_string(),
                 resolution: "1080p".to_string(),
                 extra_info: "HD".to_string(),
+                country: String::new(),
+                country_flag: String::new(),
             },
             Channel {
                 name: "CNN International".to_string(),
@@ -35,6 +45,8 @@ mod tests {
                 tvg_id: "cnn1".to_string(),
                 resolution: "720p".to_string(),
                 extra_info: "".to_string(),
+                country: String::new(),
+                country_flag: String::new(),
             },
             Channel {
                 name: "ESPN Sports".to_string(),
@@ -44,6 +56,8 @@ mod tests {
                 tvg_id: "espn1".to_string(),
                 resolution: "1080p".to_string(),
                 extra_info: "HD".to_string(),
+                country: String::new(),
+                country_flag: String::new(),
             },
         ]
     }
@@ -309,7 +323,9 @@ mod tests {
 
 // Search cancellation system
 static SEARCH_COUNTER: AtomicU64 = AtomicU64::new(0);
-static ACTIVE_SEARCH_ID: LazyLock<Mutex<Option<u64>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Cache warming runs in its own search session so it doesn't cancel the user's search
+const WARM_CACHE_SESSION: &str = "warm_cache";
 
 // Phase 2: Advanced LRU Cache System
 #[derive(Clone, Debug)]
@@ -357,7 +373,7 @@ const MAX_CACHE_ENTRIES: usize = 100;
 const MAX_TOTAL_RESULTS: usize = 50_000;
 const CACHE_TTL_SECONDS: u64 = 300; // 5 minutes
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, specta::Type)]
 pub struct SearchProgress {
     pub progress: f32,
     pub message: String,
@@ -365,13 +381,24 @@ pub struct SearchProgress {
     pub is_complete: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct CacheStats {
     pub hits: u64,
     pub misses: u64,
     pub entries: usize,
     pub total_results: usize,
     pub memory_usage_estimate: usize,
+    /// Channel list cache lookups, by key class
+    pub channel_cache: Vec<CacheKeyClassStats>,
+}
+
+/// Lookups of one class of channel cache keys
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct CacheKeyClassStats {
+    /// "list" for full lists, "view" for lists narrowed by groups or filters
+    pub class: String,
+    pub hits: u64,
+    pub misses: u64,
 }
 
 // Cache statistics tracking
@@ -477,19 +504,88 @@ fn get_search_space(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn search_channels(
+    window: Window,
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    query: String,
+    id: Option<i32>,
+    session_id: Option<String>,
+    country: Option<String>,
+    include_now_playing: Option<bool>,
+) -> Result<Vec<ChannelListing>, String> {
+    // Make this the session's active search (cancels its previous search)
+    let ticket = search_session::begin(window.label(), session_id.as_deref());
+    let channels = run_channel_search(db_state.clone(), cache_state, query, id, &ticket)?;
+    let channels = filter_by_country(channels, country.as_deref());
+
+    // Guide data comes from the Xtream EPG store, matched on the channel's tvg-id
+    let airing = if include_now_playing.unwrap_or(false) {
+        let tvg_ids: Vec<&str> = channels
+            .iter()
+            .map(|channel| channel.tvg_id.as_str())
+            .filter(|id| !id.is_empty())
+            .collect();
+        let db = db_state.pool.read().map_err(|e| e.to_string())?;
+        epg::now_playing_for_channels(&db, None, &tvg_ids, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?
+    } else {
+        HashMap::new()
+    };
+
+    anonymize::mask_result(
+        channels
+            .into_iter()
+            .map(|channel| ChannelListing {
+                now_playing: airing.get(&channel.tvg_id).cloned(),
+                channel,
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Search a playlist's channels, returning one page along with the number of matches
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn search_channels_paginated(
+    window: Window,
     db_state: State<DbState>,
     cache_state: State<ChannelCacheState>,
     query: String,
     id: Option<i32>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    session_id: Option<String>,
+    country: Option<String>,
+    include_now_playing: Option<bool>,
+) -> Result<Page<ChannelListing>, String> {
+    let channels = search_channels(
+        window,
+        db_state,
+        cache_state,
+        query,
+        id,
+        session_id,
+        country,
+        include_now_playing,
+    )?;
+    Ok(Page::from_vec(channels, limit, offset))
+}
+
+pub(crate) fn run_channel_search(
+    db_state: State<DbState>,
+    cache_state: State<ChannelCacheState>,
+    query: String,
+    id: Option<i32>,
+    ticket: &SearchTicket,
 ) -> Result<Vec<Channel>, String> {
-    // Generate unique search ID for cancellation
-    let search_id = SEARCH_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let _span = profile_span!("search", "search_playlist_channels");
+    SEARCH_COUNTER.fetch_add(1, Ordering::SeqCst);
 
-    // Set this as the active search (cancels previous searches)
-    {
-        let mut active_id = ACTIVE_SEARCH_ID.lock().unwrap();
-        *active_id = Some(search_id);
+    // Cached results may predate the list expiring
+    if crate::channels::list_expired(&db_state, id)? {
+        return Ok(Vec::new());
     }
 
     // If query is empty, clear cache and return all channels
@@ -507,24 +603,14 @@ pub fn search_channels(
     let channels_to_search = get_search_space(&query, id, &db_state, &cache_state)?;
 
     // Check if we're still the active search
-    {
-        let active_id = ACTIVE_SEARCH_ID.lock().unwrap();
-        if *active_id != Some(search_id) {
-            return Err("Search cancelled".to_string());
-        }
-    }
+    ticket.check()?;
 
     // Use fuzzy matcher for intelligent search
     let matcher = FuzzyMatcher::new();
     let filtered_channels = matcher.search_channels(&channels_to_search, &query);
 
     // Final check if we're still the active search
-    {
-        let active_id = ACTIVE_SEARCH_ID.lock().unwrap();
-        if *active_id != Some(search_id) {
-            return Err("Search cancelled".to_string());
-        }
-    }
+    ticket.check()?;
 
     // Update advanced cache with new results
     let cache_key = make_cache_key(&query, id);
@@ -539,6 +625,20 @@ pub fn clear_advanced_cache() {
     ADVANCED_CACHE.clear();
 }
 
+/// Entry count and approximate size in bytes of the search result cache
+pub fn advanced_cache_usage() -> (usize, usize) {
+    let bytes = ADVANCED_CACHE
+        .iter()
+        .map(|entry| {
+            entry.key().len()
+                + entry.query.len()
+                + std::mem::size_of::<AdvancedSearchCacheEntry>()
+                + entry.results.iter().map(channel_bytes).sum::<usize>()
+        })
+        .sum();
+    (ADVANCED_CACHE.len(), bytes)
+}
+
 #[tauri::command]
 pub fn invalidate_search_cache() -> Result<(), String> {
     clear_advanced_cache();
@@ -558,11 +658,13 @@ pub fn get_cache_stats() -> Result<CacheStats, String> {
         entries,
         total_results,
         memory_usage_estimate: memory_estimate,
+        channel_cache: crate::channels::channel_cache_stats(),
     })
 }
 
 #[tauri::command]
 pub fn warm_cache_with_common_searches(
+    window: Window,
     db_state: State<DbState>,
     cache_state: State<ChannelCacheState>,
     id: Option<i32>,
@@ -571,10 +673,14 @@ pub fn warm_cache_with_common_searches(
 
     for search_term in common_searches {
         let _ = search_channels(
+            window.clone(),
             db_state.clone(),
             cache_state.clone(),
             search_term.to_string(),
             id,
+            Some(WARM_CACHE_SESSION.to_string()),
+            None,
+            None,
         );
     }
 
@@ -601,40 +707,47 @@ pub fn get_groups(
 
 #[tauri::command]
 pub async fn search_channels_async(
+    window: Window,
     app_handle: AppHandle,
     db_state: State<'_, DbState>,
     cache_state: State<'_, ChannelCacheState>,
     query: String,
     id: Option<i32>,
+    session_id: Option<String>,
 ) -> Result<Vec<Channel>, String> {
     let query_clone = query.clone();
 
+    // Let rapid keystrokes replace this search before any work is done
+    let ticket = search_session::begin_debounced(window.label(), session_id.as_deref()).await?;
+
+    let _slot = concurrency::acquire(Subsystem::Search).await.map_err(|e| e.to_string())?;
+
     // Emit search start
-    let _ = app_handle.emit(
-        "search_progress",
-        SearchProgress {
+    events::emit(
+        &app_handle,
+        AppEvent::SearchProgress(SearchProgress {
             progress: 0.0,
             message: format!("Searching for '{}'...", query),
             current_results: 0,
             is_complete: false,
-        },
+        }),
     );
 
     // Use the main search function (now with advanced caching and cancellation)
-    let channels = search_channels(db_state, cache_state, query_clone, id)?;
+    let channels = run_channel_search(db_state, cache_state, query_clone, id, &ticket)?;
 
     // Emit completion
-    let _ = app_handle.emit(
-        "search_progress",
-        SearchProgress {
+    events::emit(
+        &app_handle,
+        AppEvent::SearchProgress(SearchProgress {
             progress: 1.0,
             message: format!("Search complete! Found {} channels.", channels.len()),
             current_results: channels.len(),
             is_complete: true,
-        },
+        }),
     );
 
-    Ok(channels)
+    anonymize::mask_result(channels)
 }
 
 #[tauri::command]
@@ -645,28 +758,28 @@ pub async fn get_groups_async(
     id: Option<i32>,
 ) -> Result<Vec<String>, String> {
     // Emit loading start
-    let _ = app_handle.emit(
-        "groups_loading",
-        ChannelLoadingStatus {
+    events::emit(
+        &app_handle,
+        AppEvent::GroupsLoading(ChannelLoadingStatus {
             progress: 0.0,
             message: "Loading channel groups...".to_string(),
             channel_count: None,
             is_complete: false,
-        },
+        }),
     );
 
     // For now, use the blocking version directly to avoid lifetime issues
     let groups = get_groups(db_state, cache_state, id)?;
 
     // Emit completion
-    let _ = app_handle.emit(
-        "groups_loading",
-        ChannelLoadingStatus {
+    events::emit(
+        &app_handle,
+        AppEvent::GroupsLoading(ChannelLoadingStatus {
             progress: 1.0,
             message: format!("Loaded {} groups successfully!", groups.len()),
             channel_count: Some(groups.len()),
             is_complete: true,
-        },
+        }),
     );
 
     Ok(groups)
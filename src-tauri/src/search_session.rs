@@ -0,0 +1,262 @@
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use dashmap::DashMap;
+use rusqlite::{Connection, InterruptHandle};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tauri::{RunEvent, State, WindowEvent};
+
+/// Error returned by a search that a newer search from the same session replaced
+pub const SEARCH_CANCELLED: &str = "Search cancelled";
+
+/// Session used by callers that don't pass one; each window has its own
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Upper bound for the debounce setting, so a typo can't make search unusable
+pub const MAX_DEBOUNCE_MS: u64 = 2000;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// Mirrors the `search_debounce_ms` setting
+static DEBOUNCE_MS: AtomicU64 = AtomicU64::new(150);
+
+/// Latest search per (window label, session), plus the handle of the query it is running
+///
+/// Sessions are scoped to the window that started them, so two windows searching
+/// at once, even both in the default session, never cancel each other.
+static SESSIONS: LazyLock<DashMap<(String, String), ActiveSearch>> = LazyLock::new(DashMap::new);
+
+struct ActiveSearch {
+    token: u64,
+    interrupt: Option<InterruptHandle>,
+}
+
+/// A search in progress; it stays valid until the same session starts another one
+pub struct SearchTicket {
+    session: (String, String),
+    token: u64,
+}
+
+/// Start a search in a window, superseding and interrupting the session's previous one
+pub fn begin(window: &str, session: Option<&str>) -> SearchTicket {
+    let session = (window.to_string(), session.unwrap_or(DEFAULT_SESSION).to_string());
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::SeqCst);
+
+    // Interrupt under the entry lock so the previous search can't deregister its
+    // handle and move on to other work in between
+    let mut search = SESSIONS
+        .entry(session.clone())
+        .or_insert(ActiveSearch { token, interrupt: None });
+    if let Some(interrupt) = search.interrupt.take() {
+        interrupt.interrupt();
+    }
+    search.token = token;
+    drop(search);
+
+    SearchTicket { session, token }
+}
+
+/// Start a search and wait out the debounce delay
+///
+/// Fails with [`SEARCH_CANCELLED`] if a newer search from the same session
+/// arrives first.
+pub async fn begin_debounced(window: &str, session: Option<&str>) -> std::result::Result<SearchTicket, String> {
+    let ticket = begin(window, session);
+    ticket.debounce().await?;
+    Ok(ticket)
+}
+
+impl SearchTicket {
+    /// Whether no newer search has started in this session
+    pub fn is_current(&self) -> bool {
+        SESSIONS
+            .get(&self.session)
+            .is_some_and(|search| search.token == self.token)
+    }
+
+    /// Fail with [`SEARCH_CANCELLED`] if the search has been superseded
+    pub fn check(&self) -> std::result::Result<(), String> {
+        if self.is_current() {
+            Ok(())
+        } else {
+            Err(SEARCH_CANCELLED.to_string())
+        }
+    }
+
+    /// Wait out the debounce delay, then check the search is still wanted
+    ///
+    /// Keystrokes that arrive within the delay replace this search before it
+    /// touches the database.
+    pub async fn debounce(&self) -> std::result::Result<(), String> {
+        let delay = debounce_ms();
+        if delay > 0 {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+        self.check()
+    }
+
+    /// Run a query on `conn` that a newer search in this session may interrupt
+    ///
+    /// `conn` must already be held by the caller, so the registered handle can
+    /// only interrupt this query, never another command waiting for the same
+    /// connection. The handle is only registered while `query` runs, so later
+    /// statements on the connection aren't affected. Results that arrive after
+    /// the search was superseded are dropped.
+    pub fn run_interruptible<T>(
+        &self,
+        conn: &Connection,
+        query: impl FnOnce(&Connection) -> Result<T>,
+    ) -> std::result::Result<T, String> {
+        match SESSIONS.get_mut(&self.session) {
+            Some(mut search) if search.token == self.token => search.interrupt = Some(conn.get_interrupt_handle()),
+            _ => return Err(SEARCH_CANCELLED.to_string()),
+        }
+
+        let result = query(conn);
+
+        if let Some(mut search) = SESSIONS.get_mut(&self.session) {
+            if search.token == self.token {
+                search.interrupt = None;
+            }
+        }
+
+        self.check()?;
+        result.map_err(|e| e.to_string())
+    }
+}
+
+/// Drop a window's sessions when it closes; called for every run event
+pub fn on_run_event(event: &RunEvent) {
+    if let RunEvent::WindowEvent {
+        label,
+        event: WindowEvent::Destroyed,
+        ..
+    } = event
+    {
+        forget_window(label);
+    }
+}
+
+fn forget_window(label: &str) {
+    SESSIONS.retain(|(window, _), _| window != label);
+}
+
+pub fn debounce_ms() -> u64 {
+    DEBOUNCE_MS.load(Ordering::Relaxed)
+}
+
+/// Load the debounce delay into memory; called once at startup
+pub fn load_settings(conn: &Connection) -> Result<u64> {
+    let value: i64 = conn.query_row(
+        "SELECT search_debounce_ms FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    let delay = value.clamp(0, MAX_DEBOUNCE_MS as i64) as u64;
+    DEBOUNCE_MS.store(delay, Ordering::Relaxed);
+    Ok(delay)
+}
+
+#[tauri::command]
+pub fn get_search_debounce_ms() -> u64 {
+    debounce_ms()
+}
+
+#[tauri::command]
+pub fn set_search_debounce_ms(state: State<DbState>, delay_ms: u64) -> std::result::Result<(), String> {
+    if delay_ms > MAX_DEBOUNCE_MS {
+        return Err(XTauriError::internal(format!(
+            "Search debounce cannot exceed {} ms",
+            MAX_DEBOUNCE_MS
+        ))
+        .to_string());
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET search_debounce_ms = ?1 WHERE id = 1",
+        [delay_ms as i64],
+    )
+    .map_err(|e| e.to_string())?;
+    DEBOUNCE_MS.store(delay_ms, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_search_supersedes_previous_in_session() {
+        let first = begin("main", Some("test-supersede"));
+        let other_session = begin("main", Some("test-supersede-other"));
+        assert!(first.is_current());
+
+        let second = begin("main", Some("test-supersede"));
+        assert!(!first.is_current());
+        assert_eq!(first.check(), Err(SEARCH_CANCELLED.to_string()));
+        assert!(second.is_current());
+        assert!(other_session.is_current());
+    }
+
+    #[test]
+    fn test_new_search_interrupts_running_query() {
+        let conn = Connection::open_in_memory().unwrap();
+        let first = begin("main", Some("test-interrupt"));
+
+        // A newer search arrives while this one is still running
+        let newer = std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(50));
+            begin("main", Some("test-interrupt")).is_current()
+        });
+
+        let result = first.run_interruptible(&conn, |conn| {
+            let count: i64 = conn.query_row(
+                "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 1000000000)
+                 SELECT COUNT(*) FROM n",
+                [],
+                |row| row.get(0),
+            )?;
+            Ok(count)
+        });
+        assert_eq!(result, Err(SEARCH_CANCELLED.to_string()));
+        assert!(newer.join().unwrap());
+
+        // The connection is usable again once the interrupted statement is gone
+        let one: i64 = conn.query_row("SELECT 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(one, 1);
+    }
+
+    #[test]
+    fn test_run_interruptible_returns_current_results() {
+        let conn = Connection::open_in_memory().unwrap();
+        let ticket = begin("main", Some("test-current"));
+        let result = ticket.run_interruptible(&conn, |conn| {
+            Ok(conn.query_row("SELECT 42", [], |row| row.get::<_, i64>(0))?)
+        });
+        assert_eq!(result, Ok(42));
+    }
+
+    #[test]
+    fn test_windows_have_separate_sessions() {
+        let player = begin("test-window-player", None);
+        let settings = begin("test-window-settings", None);
+        assert!(player.is_current());
+        assert!(settings.is_current());
+
+        let player_again = begin("test-window-player", None);
+        assert!(!player.is_current());
+        assert!(player_again.is_current());
+        assert!(settings.is_current());
+    }
+
+    #[test]
+    fn test_closed_window_sessions_are_dropped() {
+        let ticket = begin("test-window-closed", Some("search"));
+        assert!(ticket.is_current());
+
+        forget_window("test-window-closed");
+        assert!(!ticket.is_current());
+    }
+}
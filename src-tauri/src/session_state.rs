@@ -0,0 +1,184 @@
+// Navigation session for resume-on-launch
+//
+// The frontend reports where the user is (profile, view, category, the item
+// the list is scrolled to and the active filters) every time it changes.
+// Saves are coalesced in memory and written to the `ui_state` table once they
+// settle for a moment, so scrolling doesn't hit the database on every frame
+// while a crash loses at most the last fraction of a second. Pending state is
+// also written when the app exits or goes to the background.
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use crate::ui_state;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, RunEvent, State, WindowEvent};
+
+/// `ui_state` namespace and key the session is stored under
+const NAMESPACE: &str = "session";
+const KEY: &str = "navigation";
+
+/// How long saves must settle before they are written
+const WRITE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Where the user was in the app
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct SessionState {
+    /// Active Xtream profile
+    pub profile_id: Option<String>,
+    /// Active tab or screen
+    pub view: Option<String>,
+    pub category_id: Option<String>,
+    /// Content id of the item the list was scrolled to
+    pub scroll_anchor_id: Option<String>,
+    /// Active filters, as the view keeps them
+    #[serde(default)]
+    pub filters: serde_json::Value,
+    /// When the state was saved, as a unix timestamp; set by the backend
+    #[serde(default)]
+    pub saved_at: i64,
+}
+
+/// The latest save that hasn't been written yet
+static PENDING: LazyLock<Mutex<Option<SessionState>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Bumped on every save; a delayed write only goes ahead if no save came after it
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// Hold a save for the next write; returns its revision
+fn queue(mut session: SessionState) -> Result<u64> {
+    session.saved_at = chrono::Utc::now().timestamp();
+    let value = serde_json::to_value(&session)
+        .map_err(|e| XTauriError::internal(format!("Failed to serialize session state: {}", e)))?;
+    ui_state::serialize_value(NAMESPACE, KEY, &value)?;
+
+    let mut pending = PENDING
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("pending session state"))?;
+    *pending = Some(session);
+    Ok(REVISION.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+/// Take the pending save; with a revision, only if no later save replaced it
+fn take_pending(revision: Option<u64>) -> Option<SessionState> {
+    if revision.is_some_and(|revision| revision != REVISION.load(Ordering::SeqCst)) {
+        return None;
+    }
+    PENDING.lock().ok()?.take()
+}
+
+fn peek_pending() -> Option<SessionState> {
+    PENDING.lock().ok()?.clone()
+}
+
+pub fn store(conn: &Connection, session: &SessionState) -> Result<()> {
+    let value = serde_json::to_value(session)
+        .map_err(|e| XTauriError::internal(format!("Failed to serialize session state: {}", e)))?;
+    ui_state::set_value(conn, NAMESPACE, KEY, &value)
+}
+
+/// The last written session; `None` when there is none or it can't be read
+pub fn load(conn: &Connection) -> Result<Option<SessionState>> {
+    Ok(ui_state::get_value(conn, NAMESPACE, KEY)?.and_then(|value| serde_json::from_value(value).ok()))
+}
+
+fn write_pending(app_handle: &AppHandle, revision: Option<u64>) {
+    let Some(session) = take_pending(revision) else {
+        return;
+    };
+    let written = match app_handle.state::<DbState>().db.lock() {
+        Ok(db) => store(&db, &session),
+        Err(_) => Err(XTauriError::lock_acquisition("database connection")),
+    };
+    if let Err(e) = written {
+        eprintln!("[SESSION] Failed to save session state: {}", e);
+    }
+}
+
+/// Write pending state before the app exits or is backgrounded; called for every run event
+pub fn on_run_event(app_handle: &AppHandle, event: &RunEvent) {
+    match event {
+        RunEvent::Exit
+        | RunEvent::WindowEvent {
+            event: WindowEvent::Focused(false),
+            ..
+        } => write_pending(app_handle, None),
+        _ => {}
+    }
+}
+
+/// Save where the user is; written after saves settle for 500ms
+#[tauri::command]
+pub fn save_session_state(app_handle: AppHandle, session: SessionState) -> std::result::Result<(), String> {
+    let revision = queue(session).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(WRITE_DEBOUNCE).await;
+        write_pending(&app_handle, Some(revision));
+    });
+    Ok(())
+}
+
+/// Get where the user was, including a save that hasn't been written yet
+#[tauri::command]
+pub fn get_session_state(state: State<DbState>) -> std::result::Result<Option<SessionState>, String> {
+    if let Some(session) = peek_pending() {
+        return Ok(Some(session));
+    }
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    load(&db).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_only_the_latest_save_is_written() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ui_state (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (namespace, key)
+            )",
+        )
+        .unwrap();
+        assert_eq!(load(&conn).unwrap(), None);
+
+        let first = queue(SessionState {
+            profile_id: Some("profile-1".to_string()),
+            category_id: Some("12".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let second = queue(SessionState {
+            profile_id: Some("profile-1".to_string()),
+            view: Some("movies".to_string()),
+            category_id: Some("12".to_string()),
+            scroll_anchor_id: Some("4051".to_string()),
+            filters: json!({ "genre": "Drama" }),
+            saved_at: 0,
+        })
+        .unwrap();
+
+        // The first save's delayed write finds it was superseded
+        assert!(take_pending(Some(first)).is_none());
+        let session = take_pending(Some(second)).unwrap();
+        assert!(session.saved_at > 0);
+        assert!(take_pending(None).is_none());
+
+        store(&conn, &session).unwrap();
+        assert_eq!(load(&conn).unwrap(), Some(session));
+
+        let oversized = SessionState {
+            filters: json!("x".repeat(ui_state::MAX_VALUE_BYTES)),
+            ..Default::default()
+        };
+        assert!(queue(oversized).is_err());
+    }
+}
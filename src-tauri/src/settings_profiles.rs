@@ -0,0 +1,377 @@
+// Named settings profiles for the machines one set of app data is used on
+//
+// The settings live in a single row. A profile ("HTPC", "Laptop") keeps a copy
+// of the row's device-specific columns in the main database, so an exported
+// or copied database carries every profile. Switching saves the current values
+// into the active profile, writes the chosen profile's values into the row and
+// announces each key that changed.
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use chrono::Utc;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use tauri::{AppHandle, State};
+
+/// Settings that belong to a machine rather than to the user
+///
+/// Parental controls and the remote control token are left out, so switching
+/// profiles can't lift a restriction or swap a credential.
+pub const DEVICE_SETTING_KEYS: &[&str] = &[
+    "player_command",
+    "cache_duration_hours",
+    "enable_preview",
+    "mute_on_start",
+    "show_controls",
+    "autoplay",
+    "volume",
+    "is_muted",
+    "http_compression_enabled",
+    "connection_strategy",
+    "playlist_watch_dir",
+    "auto_authenticate_on_startup",
+    "low_power_mode",
+    "search_debounce_ms",
+    "auto_check_updates",
+    "presorted_categories",
+    "maintenance_window_start",
+    "maintenance_window_end",
+    "concurrency_images",
+    "concurrency_search",
+    "concurrency_sync",
+    "concurrency_epg",
+    "concurrency_max_queued",
+    "cache_ttl_categories",
+    "cache_ttl_channels",
+    "cache_ttl_movies",
+    "cache_ttl_series",
+    "cache_ttl_epg_short",
+    "cache_ttl_epg_full",
+    "cache_ttl_details",
+    "image_prefetch_enabled",
+    "image_prefetch_budget_mb",
+    "image_transcode_target",
+    "image_pass_through",
+    "digest_enabled",
+    "digest_weekday",
+    "digest_hour",
+    "digest_notify",
+];
+
+/// A named set of device settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct SettingsProfile {
+    pub name: String,
+    /// Setting values by key
+    pub settings: Value,
+    /// Whether the settings in use were last switched to this profile
+    pub active: bool,
+    pub updated_at: String,
+}
+
+/// A setting that changed when a profile was switched to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct SettingChange {
+    pub key: String,
+    pub value: Value,
+    /// Profile that was switched to
+    pub profile: String,
+}
+
+/// Columns of the settings table declared as booleans, which SQLite stores as integers
+fn boolean_columns(conn: &Connection) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT name, type FROM pragma_table_info('settings')")?;
+    let columns = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|column| match column {
+            Ok((name, kind)) if kind.eq_ignore_ascii_case("BOOLEAN") => Some(Ok(name)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(columns)
+}
+
+/// Device settings currently in use, by key
+fn current_settings(conn: &Connection) -> Result<Map<String, Value>> {
+    let booleans = boolean_columns(conn)?;
+    let mut stmt = conn.prepare("SELECT * FROM settings WHERE id = 1")?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let mut rows = stmt.query([])?;
+    let mut settings = Map::new();
+
+    if let Some(row) = rows.next()? {
+        for (index, column) in columns.iter().enumerate() {
+            if !DEVICE_SETTING_KEYS.contains(&column.as_str()) {
+                continue;
+            }
+            let value = match row.get_ref(index)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(n) if booleans.contains(column) => Value::Bool(n != 0),
+                ValueRef::Integer(n) => Value::from(n),
+                ValueRef::Real(n) => Value::from(n),
+                ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).into_owned()),
+                ValueRef::Blob(_) => continue,
+            };
+            settings.insert(column.clone(), value);
+        }
+    }
+
+    Ok(settings)
+}
+
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Bool(b) => SqlValue::Integer(*b as i64),
+        Value::Number(n) => n
+            .as_i64()
+            .map(SqlValue::Integer)
+            .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or_default())),
+        Value::String(s) => SqlValue::Text(s.clone()),
+        _ => SqlValue::Null,
+    }
+}
+
+fn load_profile(conn: &Connection, name: &str) -> Result<Option<Map<String, Value>>> {
+    let settings: Option<String> = conn
+        .query_row(
+            "SELECT settings FROM settings_profiles WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(settings.and_then(|settings| serde_json::from_str(&settings).ok()))
+}
+
+fn store_profile(conn: &Connection, name: &str, settings: &Map<String, Value>) -> Result<()> {
+    let settings = serde_json::to_string(settings)
+        .map_err(|e| XTauriError::internal(format!("Failed to serialize settings: {}", e)))?;
+    conn.execute(
+        "INSERT INTO settings_profiles (name, settings, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE SET settings = excluded.settings, updated_at = excluded.updated_at",
+        params![name, settings, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Save the settings in use as a profile, replacing one of the same name
+pub fn save_profile(conn: &Connection, name: &str) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(XTauriError::internal("Settings profile name cannot be empty"));
+    }
+    store_profile(conn, name, &current_settings(conn)?)
+}
+
+/// Every settings profile, by name
+pub fn list_profiles(conn: &Connection) -> Result<Vec<SettingsProfile>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, settings, is_active, updated_at FROM settings_profiles ORDER BY name COLLATE NOCASE",
+    )?;
+    let profiles = stmt
+        .query_map([], |row| {
+            let settings: String = row.get(1)?;
+            Ok(SettingsProfile {
+                name: row.get(0)?,
+                settings: serde_json::from_str(&settings).unwrap_or(Value::Null),
+                active: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(profiles)
+}
+
+/// Delete a settings profile; the settings in use are left as they are
+pub fn delete_profile(conn: &Connection, name: &str) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM settings_profiles WHERE name = ?1", [name])? > 0)
+}
+
+/// Switch to a profile, returning the settings that changed
+///
+/// The settings in use are first saved into the active profile, so changes
+/// made since switching to it are kept.
+pub fn switch_profile(conn: &Connection, name: &str) -> Result<Vec<SettingChange>> {
+    let tx = conn.unchecked_transaction()?;
+    let target = load_profile(&tx, name)?.ok_or_else(|| XTauriError::NotFound {
+        resource: format!("settings profile '{}'", name),
+    })?;
+    let current = current_settings(&tx)?;
+
+    let active: Option<String> = tx
+        .query_row("SELECT name FROM settings_profiles WHERE is_active = 1", [], |row| row.get(0))
+        .optional()?;
+    if let Some(active) = active.filter(|active| active != name) {
+        store_profile(&tx, &active, &current)?;
+    }
+
+    let mut changes = Vec::new();
+    for (key, value) in &target {
+        // Keys the settings table doesn't have, say from a newer version, are skipped
+        if !current.contains_key(key) || current.get(key) == Some(value) {
+            continue;
+        }
+        tx.execute(&format!("UPDATE settings SET {} = ?1 WHERE id = 1", key), [sql_value(value)])?;
+        changes.push(SettingChange {
+            key: key.clone(),
+            value: value.clone(),
+            profile: name.to_string(),
+        });
+    }
+
+    tx.execute(
+        "UPDATE settings_profiles SET is_active = (name = ?1)",
+        [name],
+    )?;
+    tx.commit()?;
+    Ok(changes)
+}
+
+#[tauri::command]
+pub fn save_settings_profile(state: State<DbState>, name: String) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    save_profile(&db, &name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_settings_profiles(state: State<DbState>) -> std::result::Result<Vec<SettingsProfile>, String> {
+    let db = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    list_profiles(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_settings_profile(state: State<DbState>, name: String) -> std::result::Result<bool, String> {
+    let db = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    delete_profile(&db, &name).map_err(|e| e.to_string())
+}
+
+/// Swap the settings in use for a profile's, emitting a change event per changed key
+#[tauri::command]
+pub fn switch_settings_profile(
+    app_handle: AppHandle,
+    state: State<DbState>,
+    name: String,
+) -> std::result::Result<Vec<SettingChange>, String> {
+    let changes = {
+        let db = state.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let changes = switch_profile(&db, &name).map_err(|e| e.to_string())?;
+        crate::startup::load_memory_settings(&db);
+        changes
+    };
+
+    if changes.iter().any(|change| change.key == "playlist_watch_dir") {
+        crate::playlists::reload_watch_dir(&app_handle);
+    }
+    for change in &changes {
+        events::emit(&app_handle, AppEvent::SettingChanged(change.clone()));
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (
+                id INTEGER PRIMARY KEY,
+                player_command TEXT NOT NULL,
+                autoplay BOOLEAN NOT NULL DEFAULT 0,
+                volume REAL NOT NULL DEFAULT 1.0,
+                playlist_watch_dir TEXT,
+                parental_max_age_rating INTEGER
+            );
+            CREATE TABLE settings_profiles (
+                name TEXT PRIMARY KEY,
+                settings TEXT NOT NULL,
+                is_active BOOLEAN NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL
+            );
+            INSERT INTO settings (id, player_command, autoplay, volume, parental_max_age_rating)
+                VALUES (1, 'mpv', 0, 1.0, 12);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_profile_keeps_device_settings_only() {
+        let conn = create_test_db();
+        save_profile(&conn, " Laptop ").unwrap();
+        assert!(save_profile(&conn, "  ").is_err());
+
+        let profiles = list_profiles(&conn).unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "Laptop");
+        assert_eq!(
+            profiles[0].settings,
+            serde_json::json!({"player_command": "mpv", "autoplay": false, "volume": 1.0, "playlist_watch_dir": null})
+        );
+    }
+
+    #[test]
+    fn test_digest_and_image_format_settings_belong_to_profiles() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE settings (id INTEGER PRIMARY KEY)", []).unwrap();
+        crate::image_formats::migrate(&conn).unwrap();
+        crate::digest::migrate(&conn).unwrap();
+
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('settings') WHERE name <> 'id'")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(columns.len(), 6);
+        for column in columns {
+            assert!(DEVICE_SETTING_KEYS.contains(&column.as_str()), "{} is not a device setting", column);
+        }
+    }
+
+    #[test]
+    fn test_switch_applies_profile_and_keeps_active_changes() {
+        let conn = create_test_db();
+        save_profile(&conn, "Laptop").unwrap();
+        conn.execute("UPDATE settings SET player_command = 'vlc', autoplay = 1 WHERE id = 1", []).unwrap();
+        save_profile(&conn, "HTPC").unwrap();
+        switch_profile(&conn, "HTPC").unwrap();
+
+        // A change made while on HTPC is saved into it on switching away
+        conn.execute("UPDATE settings SET volume = 0.5, parental_max_age_rating = 18 WHERE id = 1", []).unwrap();
+        let changes = switch_profile(&conn, "Laptop").unwrap();
+        let mut changed: Vec<(&str, &Value)> = changes.iter().map(|c| (c.key.as_str(), &c.value)).collect();
+        changed.sort_by_key(|(key, _)| *key);
+        assert_eq!(
+            changed,
+            vec![
+                ("autoplay", &Value::Bool(false)),
+                ("player_command", &Value::from("mpv")),
+                ("volume", &Value::from(1.0)),
+            ]
+        );
+        assert!(changes.iter().all(|change| change.profile == "Laptop"));
+
+        let (player, autoplay, rating): (String, bool, i64) = conn
+            .query_row("SELECT player_command, autoplay, parental_max_age_rating FROM settings", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((player.as_str(), autoplay, rating), ("mpv", false, 18));
+
+        let profiles = list_profiles(&conn).unwrap();
+        let htpc = profiles.iter().find(|p| p.name == "HTPC").unwrap();
+        assert_eq!(htpc.settings["volume"], Value::from(0.5));
+        assert!(profiles.iter().find(|p| p.name == "Laptop").unwrap().active);
+        assert!(!htpc.active);
+
+        assert!(switch_profile(&conn, "Laptop").unwrap().is_empty());
+        assert!(switch_profile(&conn, "Missing").is_err());
+    }
+}
@@ -0,0 +1,432 @@
+// Startup health gate
+//
+// The subsystems the app needs are brought up in `initialize`. A failing one no
+// longer exits the process: the window opens anyway, the failures are kept in a
+// `StartupReport` with suggested fixes, and `retry_startup` tries the failed
+// subsystems again without relaunching. Until the database-backed subsystems
+// are up, commands other than the ones in `AVAILABLE_WHILE_DEGRADED` are
+// rejected with the reason, rather than failing on state that was never managed.
+use crate::content_cache::{self, ContentCacheState};
+use crate::db_pool::DbPool;
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use crate::xtream::{self, ContentCache, CredentialManager, ProfileManager, XtreamState};
+use crate::{
+    cache_ttl, concurrency, connection, database, digest, expiry, image_formats, images, m3u_parser, playlists,
+    power, recordings, remote_control, search_session, transfer, updates, user_config, utils,
+};
+use rusqlite::{Connection, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Manager, State, Wry};
+
+/// Commands that work before the database is up
+const AVAILABLE_WHILE_DEGRADED: &[&str] = &[
+    "get_startup_report",
+    "retry_startup",
+    "get_build_features",
+    "get_storage_policy",
+    "dump_profile",
+];
+
+/// A part of the app brought up at startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupSubsystem {
+    Database,
+    Profiles,
+    ContentCache,
+    ImageCache,
+}
+
+impl StartupSubsystem {
+    fn label(self) -> &'static str {
+        match self {
+            StartupSubsystem::Database => "database",
+            StartupSubsystem::Profiles => "Xtream profiles",
+            StartupSubsystem::ContentCache => "content cache",
+            StartupSubsystem::ImageCache => "image cache",
+        }
+    }
+}
+
+/// Why a subsystem didn't come up and what might fix it
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct SubsystemFailure {
+    pub subsystem: StartupSubsystem,
+    pub error: String,
+    pub message: String,
+    pub suggestions: Vec<String>,
+}
+
+/// Outcome of the latest startup attempt
+#[derive(Debug, Clone, Default, Serialize, Deserialize, specta::Type)]
+pub struct StartupReport {
+    /// Whether everything commands depend on is up; the image cache is optional
+    pub ready: bool,
+    pub failures: Vec<SubsystemFailure>,
+    pub attempts: u32,
+    pub checked_at: i64,
+}
+
+#[derive(Default)]
+pub struct StartupState {
+    report: Mutex<StartupReport>,
+    ready: AtomicBool,
+    /// Serializes retries, so two can't both manage the same state
+    initializing: Mutex<()>,
+}
+
+impl StartupState {
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+}
+
+fn failure(subsystem: StartupSubsystem, error: &XTauriError) -> SubsystemFailure {
+    SubsystemFailure {
+        subsystem,
+        error: error.to_string(),
+        message: error.user_message(),
+        suggestions: suggestions(subsystem, error),
+    }
+}
+
+fn suggestions(subsystem: StartupSubsystem, error: &XTauriError) -> Vec<String> {
+    let database = database::database_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| "database.sqlite".to_string());
+    let sqlite_code = match error {
+        XTauriError::Database(rusqlite::Error::SqliteFailure(e, _)) => Some(e.code),
+        _ => None,
+    };
+
+    let mut suggestions = match (subsystem, sqlite_code) {
+        (_, Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)) => vec![
+            format!("The database file is damaged. Restore a backup of {}", database),
+            "Or move the file aside to start with an empty database".to_string(),
+        ],
+        (_, Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)) => {
+            vec!["Close any other running copy of the app".to_string()]
+        }
+        (_, Some(ErrorCode::DiskFull)) => vec!["Free up disk space".to_string()],
+        (_, Some(ErrorCode::ReadOnly | ErrorCode::CannotOpen | ErrorCode::PermissionDenied)) => {
+            vec![format!("Check that {} can be written to", database)]
+        }
+        (StartupSubsystem::ImageCache, _) => vec![
+            "Check that the cache directory can be written to".to_string(),
+            "Images are downloaded without caching until then".to_string(),
+        ],
+        (StartupSubsystem::Profiles, _) => {
+            vec!["Unlock or set up the system keyring, which stores the credentials key".to_string()]
+        }
+        (_, _) if matches!(error, XTauriError::DataDirectoryAccess | XTauriError::DirectoryCreation { .. }) => {
+            vec!["Check that the app's data directory exists and can be written to".to_string()]
+        }
+        (_, _) => Vec::new(),
+    };
+    suggestions.push("Retry once the problem is fixed".to_string());
+    suggestions
+}
+
+/// Load the settings that are mirrored in memory, after startup or a settings profile switch
+pub fn load_memory_settings(conn: &Connection) {
+    if let Err(e) = transfer::load_settings(conn) {
+        eprintln!("Failed to load HTTP compression setting: {}", e);
+    }
+    if let Err(e) = image_formats::load_settings(conn) {
+        eprintln!("Failed to load image format settings: {}", e);
+    }
+    if let Err(e) = connection::load_settings(conn) {
+        eprintln!("Failed to load connection strategy setting: {}", e);
+    }
+    if let Err(e) = search_session::load_settings(conn) {
+        eprintln!("Failed to load search debounce setting: {}", e);
+    }
+    if let Err(e) = content_cache::age_rating::load_settings(conn) {
+        eprintln!("Failed to load parental age limit: {}", e);
+    }
+    if let Err(e) = content_cache::sort_index::load_settings(conn) {
+        eprintln!("Failed to load presorted categories setting: {}", e);
+    }
+    if let Err(e) = content_cache::maintenance::load_settings(conn) {
+        eprintln!("Failed to load maintenance window: {}", e);
+    }
+    if let Err(e) = xtream::api_language::load_settings(conn) {
+        eprintln!("Failed to load provider API languages: {}", e);
+    }
+    if let Err(e) = expiry::load_settings(conn) {
+        eprintln!("Failed to load content expiry times: {}", e);
+    }
+    if let Err(e) = power::load_settings(conn) {
+        eprintln!("Failed to load low-power mode setting: {}", e);
+    }
+    if let Err(e) = concurrency::load_settings(conn) {
+        eprintln!("Failed to load command concurrency limits: {}", e);
+    }
+    if let Err(e) = cache_ttl::load_settings(conn) {
+        eprintln!("Failed to load cache TTLs: {}", e);
+    }
+}
+
+/// Open the main database, refresh the default channel list and load the settings kept in memory
+fn open_database() -> Result<Connection> {
+    let mut db_connection = database::initialize_database()?;
+
+    // Run cleanup on startup to remove orphaned channel list files
+    if let Err(e) = utils::cleanup_orphaned_channel_files(&db_connection) {
+        println!("Warning: Channel list cleanup failed: {}", e);
+    }
+    if let Err(e) = recordings::mark_interrupted(&db_connection) {
+        println!("Warning: Failed to close interrupted recordings: {}", e);
+    }
+
+    let channels = m3u_parser::get_channels(&mut db_connection, None);
+    database::populate_channels(&mut db_connection, &channels)
+        .map_err(|e| XTauriError::database_init(format!("Failed to populate channels: {}", e)))?;
+
+    load_memory_settings(&db_connection);
+
+    Ok(db_connection)
+}
+
+fn setup_xtream_state(pool: &Arc<DbPool>) -> Result<XtreamState> {
+    // Create credential manager
+    let credential_manager = Arc::new(CredentialManager::new()?);
+
+    // Create content cache using the shared writer
+    let content_cache = Arc::new(ContentCache::new(pool.writer(), std::time::Duration::from_secs(3600)));
+
+    // Create profile manager writing through the shared writer and reading from the pool
+    let profile_manager =
+        Arc::new(ProfileManager::new(pool.writer(), credential_manager).with_read_pool(Arc::clone(pool)));
+
+    Ok(XtreamState::new(profile_manager, content_cache))
+}
+
+fn ensure_image_cache() -> Result<()> {
+    let dir = images::image_cache_dir()?;
+    std::fs::create_dir_all(&dir).map_err(|_| XTauriError::directory_creation(dir.display().to_string()))
+}
+
+/// Start the background work that needs every database-backed state
+fn start_services(app: &AppHandle) {
+    // Apply the configuration file and follow its changes
+    user_config::restore_on_startup(app);
+
+    // Abort network transfers that stop receiving data
+    crate::watchdog::start();
+
+    // Record playback positions a crash kept from being written
+    if let Some(xtream_state) = app.try_state::<XtreamState>() {
+        let profile_manager = Arc::clone(&xtream_state.profile_manager);
+        tauri::async_runtime::spawn(async move { xtream::heartbeat::recover(&profile_manager).await });
+    }
+
+    // Bring the remote control API back up if it was left enabled
+    tauri::async_runtime::spawn(remote_control::restore_on_startup(app.clone()));
+
+    // Resume watching the playlist import folder if one is configured
+    playlists::restore_on_startup(app);
+
+    // Check the active Xtream profile's account in the background if enabled
+    tauri::async_runtime::spawn(xtream::startup_auth::restore_on_startup(app.clone()));
+
+    // Check for a newer release once a day if enabled
+    tauri::async_runtime::spawn(updates::restore_on_startup(app.clone()));
+
+    // Prompt about trial lists and profiles as they expire
+    tauri::async_runtime::spawn(expiry::restore_on_startup(app.clone()));
+
+    // Follow the power source for low-power mode
+    tauri::async_runtime::spawn(power::restore_on_startup(app.clone()));
+
+    // Send reminder notifications as programmes are about to start
+    tauri::async_runtime::spawn(xtream::reminders::restore_on_startup(app.clone()));
+
+    // Keep the popularity order fresh as watches and favorites pile up
+    tauri::async_runtime::spawn(content_cache::popularity::restore_on_startup(app.clone()));
+
+    // Compile the weekly digest on its schedule if enabled
+    tauri::async_runtime::spawn(digest::restore_on_startup(app.clone()));
+}
+
+/// Bring up whatever isn't up yet and record how it went
+///
+/// Subsystems already up are left alone, so this is also how a failed startup
+/// is retried.
+pub fn initialize(app: &AppHandle) -> StartupReport {
+    let startup = app.state::<StartupState>();
+    let _initializing = startup.initializing.lock().unwrap_or_else(|e| e.into_inner());
+    let mut failures = Vec::new();
+
+    // Every state shares the pool, and so the one writer, the settings were loaded from
+    if app.try_state::<DbState>().is_none() {
+        match open_database().and_then(database::open_pool) {
+            Ok(pool) => {
+                let pool = Arc::new(pool);
+                app.manage(DbState { db: pool.writer(), pool });
+            }
+            Err(e) => failures.push(failure(StartupSubsystem::Database, &e)),
+        }
+    }
+
+    if let (None, Some(db_state)) = (app.try_state::<XtreamState>(), app.try_state::<DbState>()) {
+        match setup_xtream_state(&db_state.pool) {
+            Ok(state) => {
+                app.manage(state);
+            }
+            Err(e) => failures.push(failure(StartupSubsystem::Profiles, &e)),
+        }
+    }
+
+    if let (None, Some(db_state)) = (app.try_state::<ContentCacheState>(), app.try_state::<DbState>()) {
+        match ContentCacheState::new(Arc::clone(&db_state.pool)) {
+            Ok(state) => {
+                app.manage(state);
+            }
+            Err(e) => failures.push(failure(StartupSubsystem::ContentCache, &e)),
+        }
+    }
+
+    // Initialize automation engine on the Xtream database, searching the content cache
+    if app.try_state::<xtream::AutomationState>().is_none() {
+        if let (Some(xtream_state), Some(cache_state)) =
+            (app.try_state::<XtreamState>(), app.try_state::<ContentCacheState>())
+        {
+            let automation_state = xtream::AutomationState::new(
+                xtream::AutomationEngine::new(
+                    xtream_state.profile_manager.get_db_connection(),
+                    Arc::clone(&cache_state.cache),
+                )
+                .with_app_handle(app.clone()),
+            );
+            app.manage(automation_state);
+        }
+    }
+
+    if let Err(e) = ensure_image_cache() {
+        failures.push(failure(StartupSubsystem::ImageCache, &e));
+    }
+
+    let ready = app.try_state::<xtream::AutomationState>().is_some();
+    if ready && !startup.ready.swap(true, Ordering::AcqRel) {
+        start_services(app);
+    }
+
+    for failure in &failures {
+        eprintln!("[STARTUP] {} failed to initialize: {}", failure.subsystem.label(), failure.error);
+    }
+
+    let mut report = startup.report.lock().unwrap_or_else(|e| e.into_inner());
+    *report = StartupReport {
+        ready,
+        failures,
+        attempts: report.attempts + 1,
+        checked_at: chrono::Utc::now().timestamp(),
+    };
+    report.clone()
+}
+
+/// Why `command` can't run yet, if it can't
+fn gate_reason(report: &StartupReport, command: &str) -> Option<String> {
+    if report.ready || AVAILABLE_WHILE_DEGRADED.contains(&command) {
+        return None;
+    }
+    let failed: Vec<&str> = report
+        .failures
+        .iter()
+        .filter(|failure| failure.subsystem != StartupSubsystem::ImageCache)
+        .map(|failure| failure.subsystem.label())
+        .collect();
+    if failed.is_empty() {
+        return Some(format!("{} is unavailable until startup finishes", command));
+    }
+    Some(format!(
+        "{} is unavailable because the {} failed to start; see the startup report",
+        command,
+        failed.join(", ")
+    ))
+}
+
+/// Wrap the command handler so commands are rejected while startup is degraded
+pub fn gate_commands<F>(handler: F) -> impl Fn(Invoke<Wry>) -> bool + Send + Sync + 'static
+where
+    F: Fn(Invoke<Wry>) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let app = invoke.message.webview().app_handle().clone();
+        let startup = app.state::<StartupState>();
+        if !startup.is_ready() {
+            let report = startup.report.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(reason) = gate_reason(&report, invoke.message.command()) {
+                drop(report);
+                invoke.resolver.reject(reason);
+                return true;
+            }
+        }
+        handler(invoke)
+    }
+}
+
+/// Whether the app started fully; lifecycle hooks that use the database check this first
+pub fn is_ready(app: &AppHandle) -> bool {
+    app.try_state::<StartupState>().is_some_and(|startup| startup.is_ready())
+}
+
+#[tauri::command]
+pub fn get_startup_report(state: State<StartupState>) -> StartupReport {
+    state.report.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Try the subsystems that failed to start again
+#[tauri::command]
+pub async fn retry_startup(app_handle: AppHandle) -> StartupReport {
+    initialize(&app_handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corrupt_database() -> XTauriError {
+        XTauriError::Database(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT),
+            None,
+        ))
+    }
+
+    #[test]
+    fn test_suggestions_follow_the_failure() {
+        let damaged = suggestions(StartupSubsystem::Database, &corrupt_database());
+        assert!(damaged[0].starts_with("The database file is damaged. Restore a backup of"));
+        assert_eq!(damaged.last().unwrap(), "Retry once the problem is fixed");
+
+        let keyring = suggestions(
+            StartupSubsystem::Profiles,
+            &XTauriError::credential_encryption("Failed to access keyring"),
+        );
+        assert!(keyring[0].contains("keyring"));
+    }
+
+    #[test]
+    fn test_commands_are_gated_until_ready() {
+        let mut report = StartupReport {
+            failures: vec![
+                failure(StartupSubsystem::Database, &corrupt_database()),
+                failure(StartupSubsystem::ImageCache, &XTauriError::DataDirectoryAccess),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            gate_reason(&report, "get_channels").unwrap(),
+            "get_channels is unavailable because the database failed to start; see the startup report"
+        );
+        assert!(gate_reason(&report, "retry_startup").is_none());
+
+        report.ready = true;
+        assert!(gate_reason(&report, "get_channels").is_none());
+    }
+}
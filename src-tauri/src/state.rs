@@ -1,25 +1,60 @@
+use crate::db_pool::DbPool;
 use crate::m3u_parser::Channel;
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 pub struct DbState {
-    pub db: Mutex<Connection>,
+    /// The pool's writer, which the Xtream profiles and the content cache write through too
+    pub db: Arc<Mutex<Connection>>,
+    /// Read-only connections shared with the content cache and the profiles
+    pub pool: Arc<DbPool>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ChannelCache {
-    pub channel_list_id: Option<i32>,
     pub channels: Vec<Channel>,
     pub last_updated: SystemTime,
 }
 
+/// Identifies a cached channel listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelCacheKey {
+    /// `None` is the default list
+    pub channel_list_id: Option<i32>,
+    /// Fingerprint of the enabled groups, for listings limited to them
+    pub groups: Option<u64>,
+    /// Hash of the filters applied on top, 0 when unfiltered
+    pub filter: u64,
+}
+
+impl ChannelCacheKey {
+    /// Key of a list's full, unfiltered channels
+    pub fn list(channel_list_id: Option<i32>) -> Self {
+        Self {
+            channel_list_id,
+            groups: None,
+            filter: 0,
+        }
+    }
+
+    /// "list" for a full list, "view" for one narrowed by groups or filters
+    pub fn class(&self) -> &'static str {
+        if self.groups.is_none() && self.filter == 0 {
+            "list"
+        } else {
+            "view"
+        }
+    }
+}
+
 pub struct ChannelCacheState {
-    pub cache: Mutex<Option<ChannelCache>>,
+    pub cache: Mutex<HashMap<ChannelCacheKey, ChannelCache>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, specta::Type)]
 pub struct ChannelList {
     pub id: i32,
     pub name: String,
@@ -27,4 +62,9 @@ pub struct ChannelList {
     pub is_default: bool,
     pub filepath: Option<String>,
     pub last_fetched: Option<i64>,
+    /// `auto` or the encoding label the list is always decoded with
+    pub encoding: String,
+    pub detected_encoding: Option<String>,
+    /// Set when the last decode looked garbled
+    pub encoding_warning: Option<String>,
 }
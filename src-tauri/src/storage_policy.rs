@@ -0,0 +1,167 @@
+// Platform-aware cache budgets
+//
+// Phones have far less storage than desktops, so mobile builds start with much
+// lower cache ceilings: the on-disk image cache gets a smaller byte budget and
+// the Xtream response cache keeps a fraction of its per-type entry limits.
+// Both shrink further while the OS reports low storage, and the caches are
+// trimmed when the app goes to the background on mobile.
+use crate::content_cache::ContentCacheState;
+use crate::images;
+use crate::state::{ChannelCacheState, DbState};
+use crate::xtream::XtreamState;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager, RunEvent, WindowEvent};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Image cache ceiling on desktop
+const DESKTOP_IMAGE_CACHE_BYTES: u64 = 512 * MIB;
+
+/// Image cache ceiling on Android and iOS
+const MOBILE_IMAGE_CACHE_BYTES: u64 = 64 * MIB;
+
+/// Share of the response cache entry limits kept on mobile
+const MOBILE_ENTRY_PERCENT: u8 = 25;
+
+/// Budgets are divided by this while storage is low
+const LOW_STORAGE_DIVISOR: u64 = 4;
+
+/// Set while the OS reports low storage
+static LOW_STORAGE: AtomicBool = AtomicBool::new(false);
+
+/// Set while the mobile app is in the background
+static BACKGROUNDED: AtomicBool = AtomicBool::new(false);
+
+/// The cache budgets in effect
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct StoragePolicy {
+    pub mobile: bool,
+    pub low_storage: bool,
+    pub backgrounded: bool,
+    pub image_cache_max_bytes: u64,
+    /// Share of each response cache entry limit in use
+    pub response_cache_percent: u8,
+}
+
+impl StoragePolicy {
+    fn new(mobile: bool, low_storage: bool) -> Self {
+        let (image_bytes, percent) = if mobile {
+            (MOBILE_IMAGE_CACHE_BYTES, MOBILE_ENTRY_PERCENT)
+        } else {
+            (DESKTOP_IMAGE_CACHE_BYTES, 100)
+        };
+        let divisor = if low_storage { LOW_STORAGE_DIVISOR } else { 1 };
+        Self {
+            mobile,
+            low_storage,
+            backgrounded: BACKGROUNDED.load(Ordering::Relaxed),
+            image_cache_max_bytes: image_bytes / divisor,
+            response_cache_percent: (u64::from(percent) / divisor).max(1) as u8,
+        }
+    }
+
+    /// The policy for this platform and the current storage state
+    pub fn current() -> Self {
+        Self::new(is_mobile(), LOW_STORAGE.load(Ordering::Relaxed))
+    }
+
+    /// An entry limit scaled to this policy, never below one entry
+    pub fn scale_entries(&self, max_entries: usize) -> usize {
+        (max_entries * usize::from(self.response_cache_percent) / 100).max(1)
+    }
+}
+
+/// Whether this is an Android or iOS build
+pub fn is_mobile() -> bool {
+    cfg!(any(target_os = "android", target_os = "ios"))
+}
+
+/// Release cached data beyond the current budgets
+///
+/// The in-memory caches are dropped as by `trim_memory`, and the image cache
+/// is cut down to its byte budget off the async runtime.
+pub fn trim_caches(app_handle: &AppHandle) {
+    if let Err(e) = crate::memory::trim_memory(
+        app_handle.state::<DbState>(),
+        app_handle.state::<ChannelCacheState>(),
+        app_handle.state::<XtreamState>(),
+        app_handle.state::<ContentCacheState>(),
+    ) {
+        eprintln!("[STORAGE] Failed to trim memory caches: {}", e);
+    }
+
+    let max_bytes = StoragePolicy::current().image_cache_max_bytes;
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let trimmed = images::image_cache_dir()
+            .map_err(|e| e.to_string())
+            .and_then(|dir| images::trim_image_cache(&dir, max_bytes).map_err(|e| e.to_string()));
+        match trimmed {
+            Ok(removed) if removed > 0 => images::forget_trimmed(&app_handle),
+            Ok(_) => {}
+            Err(e) => eprintln!("[STORAGE] Failed to trim image cache: {}", e),
+        }
+    });
+}
+
+/// Follow the mobile app lifecycle; called for every run event
+///
+/// Losing focus on mobile means the app went to the background, where the OS
+/// may kill it for memory, so the caches are trimmed right away.
+pub fn on_run_event(app_handle: &AppHandle, event: &RunEvent) {
+    if !is_mobile() {
+        return;
+    }
+    match event {
+        RunEvent::WindowEvent {
+            event: WindowEvent::Focused(false),
+            ..
+        } if !BACKGROUNDED.swap(true, Ordering::Relaxed) => trim_caches(app_handle),
+        RunEvent::WindowEvent {
+            event: WindowEvent::Focused(true),
+            ..
+        }
+        | RunEvent::Resumed => BACKGROUNDED.store(false, Ordering::Relaxed),
+        _ => {}
+    }
+}
+
+/// Get the cache budgets in effect
+#[tauri::command]
+pub fn get_storage_policy() -> StoragePolicy {
+    StoragePolicy::current()
+}
+
+/// Report the OS low-storage state; the mobile shell calls this when the OS signals it
+///
+/// Entering low storage trims the caches to the reduced budgets straight away.
+#[tauri::command]
+pub fn set_low_storage(app_handle: AppHandle, low_storage: bool) -> StoragePolicy {
+    if !LOW_STORAGE.swap(low_storage, Ordering::Relaxed) && low_storage {
+        trim_caches(&app_handle);
+    }
+    StoragePolicy::current()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budgets_by_platform_and_storage() {
+        let desktop = StoragePolicy::new(false, false);
+        assert_eq!(desktop.image_cache_max_bytes, 512 * MIB);
+        assert_eq!(desktop.scale_entries(1000), 1000);
+
+        let mobile = StoragePolicy::new(true, false);
+        assert_eq!(mobile.image_cache_max_bytes, 64 * MIB);
+        assert_eq!(mobile.scale_entries(1000), 250);
+
+        let low = StoragePolicy::new(true, true);
+        assert_eq!(low.image_cache_max_bytes, 16 * MIB);
+        assert_eq!(low.response_cache_percent, 6);
+        assert_eq!(low.scale_entries(1000), 60);
+        assert_eq!(low.scale_entries(5), 1);
+    }
+}
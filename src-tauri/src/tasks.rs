@@ -0,0 +1,178 @@
+// Registry of background tasks
+//
+// Syncs, image batches, the playlist watcher and other jobs run detached from
+// the command that started them. Each registers here while it runs, so the UI
+// can list what is going on in the background and cancel what supports it.
+use crate::error::{Result, XTauriError};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::LazyLock;
+use tokio_util::sync::CancellationToken;
+
+static TASKS: LazyLock<DashMap<String, RegisteredTask>> = LazyLock::new(DashMap::new);
+
+/// What kind of job a background task is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Sync,
+    ImagePreload,
+    PlaylistWatch,
+    UpdateCheck,
+    AccountCheck,
+    Automation,
+    Maintenance,
+    #[cfg(feature = "recordings")]
+    Recording,
+}
+
+/// A background task as reported by `get_background_tasks`
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct BackgroundTask {
+    pub id: String,
+    pub kind: TaskKind,
+    pub name: String,
+    pub profile_id: Option<String>,
+    /// Percent complete, for tasks that can tell
+    pub progress: Option<u8>,
+    /// What the task is doing right now
+    pub detail: Option<String>,
+    pub cancellable: bool,
+    pub started_at: String,
+}
+
+struct RegisteredTask {
+    task: BackgroundTask,
+    cancel_token: Option<CancellationToken>,
+}
+
+/// Registration of a running task; the task leaves the registry when this is dropped
+pub struct TaskHandle {
+    id: String,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn set_progress(&self, progress: u8, detail: Option<String>) {
+        set_progress(&self.id, progress, detail);
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        TASKS.remove(&self.id);
+    }
+}
+
+fn insert(
+    kind: TaskKind,
+    name: String,
+    profile_id: Option<&str>,
+    cancel_token: Option<CancellationToken>,
+) -> TaskHandle {
+    let id = uuid::Uuid::new_v4().to_string();
+    let task = BackgroundTask {
+        id: id.clone(),
+        kind,
+        name,
+        profile_id: profile_id.map(str::to_string),
+        progress: None,
+        detail: None,
+        cancellable: cancel_token.is_some(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+    };
+    TASKS.insert(id.clone(), RegisteredTask { task, cancel_token });
+    TaskHandle { id }
+}
+
+/// Register a task that runs to completion once started
+pub fn register(kind: TaskKind, name: impl Into<String>, profile_id: Option<&str>) -> TaskHandle {
+    insert(kind, name.into(), profile_id, None)
+}
+
+/// Register a task that stops when `cancel_token` is cancelled
+pub fn register_cancellable(
+    kind: TaskKind,
+    name: impl Into<String>,
+    profile_id: Option<&str>,
+    cancel_token: CancellationToken,
+) -> TaskHandle {
+    insert(kind, name.into(), profile_id, Some(cancel_token))
+}
+
+/// Update a running task's progress; does nothing once the task has finished
+pub fn set_progress(id: &str, progress: u8, detail: Option<String>) {
+    if let Some(mut entry) = TASKS.get_mut(id) {
+        entry.task.progress = Some(progress.min(100));
+        if detail.is_some() {
+            entry.task.detail = detail;
+        }
+    }
+}
+
+/// Running tasks, oldest first
+pub fn list() -> Vec<BackgroundTask> {
+    let mut tasks: Vec<BackgroundTask> = TASKS.iter().map(|entry| entry.task.clone()).collect();
+    tasks.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.id.cmp(&b.id)));
+    tasks
+}
+
+/// Ask a task to stop
+///
+/// Cancellation is cooperative: the task leaves the registry once it has wound down.
+pub fn cancel(id: &str) -> Result<()> {
+    let entry = TASKS.get(id).ok_or_else(|| XTauriError::NotFound {
+        resource: format!("background task {}", id),
+    })?;
+    match &entry.cancel_token {
+        Some(cancel_token) => {
+            cancel_token.cancel();
+            Ok(())
+        }
+        None => Err(XTauriError::internal(format!(
+            "Background task '{}' can't be cancelled",
+            entry.task.name
+        ))),
+    }
+}
+
+#[tauri::command]
+pub fn get_background_tasks() -> Vec<BackgroundTask> {
+    list()
+}
+
+#[tauri::command]
+pub fn cancel_background_task(task_id: String) -> std::result::Result<(), String> {
+    cancel(&task_id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_progress_and_cancel() {
+        let cancel_token = CancellationToken::new();
+        let sync = register_cancellable(TaskKind::Sync, "Full sync", Some("p1"), cancel_token.clone());
+        let check = register(TaskKind::UpdateCheck, "Checking for updates", None);
+
+        sync.set_progress(140, Some("Syncing movies".to_string()));
+        let task = list().into_iter().find(|task| task.id == sync.id()).unwrap();
+        assert_eq!(task.progress, Some(100));
+        assert_eq!(task.detail.as_deref(), Some("Syncing movies"));
+        assert_eq!(task.profile_id.as_deref(), Some("p1"));
+        assert!(task.cancellable);
+
+        assert!(cancel(check.id()).is_err());
+        cancel(sync.id()).unwrap();
+        assert!(cancel_token.is_cancelled());
+
+        let id = sync.id().to_string();
+        drop(sync);
+        assert!(list().iter().all(|task| task.id != id));
+        assert!(cancel(&id).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use dashmap::DashMap;
+use flate2::read::{GzDecoder, ZlibDecoder, DeflateDecoder};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use tauri::State;
+
+/// Traffic source labels used by the bandwidth tracker
+pub const SOURCE_XTREAM_API: &str = "xtream_api";
+pub const SOURCE_CONTENT_SYNC: &str = "content_sync";
+pub const SOURCE_PLAYLIST: &str = "playlist";
+pub const SOURCE_IMAGES: &str = "images";
+pub const SOURCE_IMAGE_PREFETCH: &str = "image_prefetch";
+pub const SOURCE_EPG: &str = "epg";
+
+/// Mirrors the `http_compression_enabled` setting so request code doesn't need the database
+static COMPRESSION_ENABLED: AtomicBool = AtomicBool::new(true);
+
+static BANDWIDTH: LazyLock<DashMap<String, TransferStats>> = LazyLock::new(DashMap::new);
+
+/// Byte counts for one traffic source
+///
+/// `compressed_bytes` is what crossed the wire; `uncompressed_bytes` is the decoded
+/// body size. They are equal for responses that were not compressed.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, specta::Type)]
+pub struct TransferStats {
+    pub requests: u64,
+    pub compressed_bytes: u64,
+    pub uncompressed_bytes: u64,
+}
+
+pub fn is_compression_enabled() -> bool {
+    crate::user_config::http_compression().unwrap_or_else(|| COMPRESSION_ENABLED.load(Ordering::Relaxed))
+}
+
+/// Value for the `Accept-Encoding` request header
+pub fn accept_encoding() -> &'static str {
+    if is_compression_enabled() {
+        "gzip, deflate"
+    } else {
+        "identity"
+    }
+}
+
+/// Add one response to the bandwidth tracker
+pub fn record_transfer(source: &str, compressed_bytes: u64, uncompressed_bytes: u64) {
+    let mut stats = BANDWIDTH.entry(source.to_string()).or_default();
+    stats.requests += 1;
+    stats.compressed_bytes += compressed_bytes;
+    stats.uncompressed_bytes += uncompressed_bytes;
+}
+
+pub fn bandwidth_stats() -> Vec<(String, TransferStats)> {
+    let mut stats: Vec<_> = BANDWIDTH
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect();
+    stats.sort_by(|a, b| a.0.cmp(&b.0));
+    stats
+}
+
+fn content_encoding(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    headers
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_ascii_lowercase())
+}
+
+/// Wrap a reader with the decoder matching a `Content-Encoding` header
+pub fn decoding_reader<'a, R: Read + 'a>(encoding: Option<&str>, reader: R) -> Result<Box<dyn Read + 'a>> {
+    match encoding {
+        None | Some("") | Some("identity") => Ok(Box::new(reader)),
+        Some("gzip") | Some("x-gzip") => Ok(Box::new(GzDecoder::new(reader))),
+        Some("deflate") => Ok(Box::new(DeflateAutoDecoder::new(reader))),
+        Some(other) => Err(XTauriError::internal(format!(
+            "Unsupported content encoding: {}",
+            other
+        ))),
+    }
+}
+
+/// Decode a complete response body
+pub fn decode_body(encoding: Option<&str>, body: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(body.len());
+    decoding_reader(encoding, body)?
+        .read_to_end(&mut decoded)
+        .map_err(|e| XTauriError::internal(format!("Failed to decompress response: {}", e)))?;
+    Ok(decoded)
+}
+
+/// Read and decode a response body, recording its transfer size
+pub async fn read_body(mut response: reqwest::Response, source: &str) -> Result<Vec<u8>> {
+    let encoding = content_encoding(response.headers());
+    let watched = crate::watchdog::watch(source, response.url());
+    let mut body = Vec::new();
+    while let Some(chunk) = watched.guard(response.chunk()).await?.map_err(|e| {
+        if e.is_timeout() {
+            XTauriError::timeout("API request")
+        } else {
+            XTauriError::network(e)
+        }
+    })? {
+        watched.progress(chunk.len());
+        body.extend_from_slice(&chunk);
+    }
+
+    let decoded = decode_body(encoding.as_deref(), &body)?;
+    record_transfer(source, body.len() as u64, decoded.len() as u64);
+    Ok(decoded)
+}
+
+/// Read and decode a response body as JSON, recording its transfer size
+pub async fn read_json(response: reqwest::Response, source: &str) -> Result<serde_json::Value> {
+    let decoded = read_body(response, source).await?;
+    serde_json::from_slice(&decoded)
+        .map_err(|e| XTauriError::internal(format!("Invalid response format: {}", e)))
+}
+
+/// Blocking variant of `read_body` for the synchronous playlist loaders
+///
+/// Playlists are returned as bytes so they can be decoded with the list's
+/// character encoding.
+pub fn read_body_blocking(response: reqwest::blocking::Response, source: &str) -> Result<Vec<u8>> {
+    let encoding = content_encoding(response.headers());
+    let body = response.bytes().map_err(XTauriError::network)?;
+
+    let decoded = decode_body(encoding.as_deref(), &body)?;
+    record_transfer(source, body.len() as u64, decoded.len() as u64);
+    Ok(decoded)
+}
+
+/// Fetch a URL with the blocking client, negotiating compression
+pub fn fetch_body_blocking(url: &str, source: &str) -> Result<Vec<u8>> {
+    let response = crate::connection::blocking_client_builder()
+        .build()
+        .map_err(XTauriError::network)?
+        .get(url)
+        .header(reqwest::header::ACCEPT_ENCODING, accept_encoding())
+        .send()
+        .map_err(XTauriError::network)?;
+    read_body_blocking(response, source)
+}
+
+/// Encoding of a response, for callers that stream the body themselves
+pub fn response_encoding(response: &reqwest::Response) -> Option<String> {
+    content_encoding(response.headers())
+}
+
+/// Reader that counts the bytes passing through it
+pub struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> (Self, Arc<AtomicU64>) {
+        let count = Arc::new(AtomicU64::new(0));
+        (
+            Self {
+                inner,
+                count: Arc::clone(&count),
+            },
+            count,
+        )
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// "deflate" is specified as zlib-wrapped, but some servers send raw deflate streams
+struct DeflateAutoDecoder<R: Read> {
+    state: DeflateState<R>,
+}
+
+enum DeflateState<R: Read> {
+    Pending(Option<R>),
+    Zlib(ZlibDecoder<std::io::Chain<std::io::Cursor<Vec<u8>>, R>>),
+    Raw(DeflateDecoder<std::io::Chain<std::io::Cursor<Vec<u8>>, R>>),
+}
+
+impl<R: Read> DeflateAutoDecoder<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            state: DeflateState::Pending(Some(reader)),
+        }
+    }
+}
+
+impl<R: Read> Read for DeflateAutoDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if let DeflateState::Pending(reader) = &mut self.state {
+            let mut reader = reader.take().expect("pending deflate reader");
+            let mut header = vec![0u8; 2];
+            let mut filled = 0;
+            while filled < 2 {
+                let n = reader.read(&mut header[filled..])?;
+                if n == 0 {
+                    break;
+                }
+                filled += n;
+            }
+            header.truncate(filled);
+
+            // A zlib header's first two bytes, read big-endian, are a multiple of 31
+            let is_zlib = filled == 2
+                && header[0] & 0x0f == 8
+                && (u16::from(header[0]) << 8 | u16::from(header[1])) % 31 == 0;
+
+            let chained = std::io::Cursor::new(header).chain(reader);
+            self.state = if is_zlib {
+                DeflateState::Zlib(ZlibDecoder::new(chained))
+            } else {
+                DeflateState::Raw(DeflateDecoder::new(chained))
+            };
+        }
+
+        match &mut self.state {
+            DeflateState::Zlib(decoder) => decoder.read(buf),
+            DeflateState::Raw(decoder) => decoder.read(buf),
+            DeflateState::Pending(_) => unreachable!(),
+        }
+    }
+}
+
+/// Load the compression setting into memory; called once at startup
+pub fn load_settings(conn: &Connection) -> Result<bool> {
+    let enabled: bool = conn.query_row(
+        "SELECT http_compression_enabled FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    COMPRESSION_ENABLED.store(enabled, Ordering::Relaxed);
+    Ok(enabled)
+}
+
+#[tauri::command]
+pub fn get_http_compression_enabled() -> bool {
+    is_compression_enabled()
+}
+
+#[tauri::command]
+pub fn set_http_compression_enabled(state: State<DbState>, enabled: bool) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET http_compression_enabled = ?1 WHERE id = 1",
+        [enabled],
+    )
+    .map_err(|e| e.to_string())?;
+    COMPRESSION_ENABLED.store(enabled, Ordering::Relaxed);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct BandwidthSourceStats {
+    pub source: String,
+    #[serde(flatten)]
+    pub stats: TransferStats,
+}
+
+#[tauri::command]
+pub fn get_bandwidth_stats() -> Vec<BandwidthSourceStats> {
+    bandwidth_stats()
+        .into_iter()
+        .map(|(source, stats)| BandwidthSourceStats { source, stats })
+        .collect()
+}
+
+#[tauri::command]
+pub fn reset_bandwidth_stats() {
+    BANDWIDTH.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    const BODY: &[u8] = b"[{\"stream_id\": 1, \"name\": \"News\"}, {\"stream_id\": 2, \"name\": \"News\"}]";
+
+    #[test]
+    fn test_decode_gzip_and_deflate() {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(BODY).unwrap();
+        assert_eq!(decode_body(Some("gzip"), &gz.finish().unwrap()).unwrap(), BODY);
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(BODY).unwrap();
+        assert_eq!(decode_body(Some("deflate"), &zlib.finish().unwrap()).unwrap(), BODY);
+
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(BODY).unwrap();
+        assert_eq!(decode_body(Some("deflate"), &raw.finish().unwrap()).unwrap(), BODY);
+
+        assert_eq!(decode_body(None, BODY).unwrap(), BODY);
+        assert!(decode_body(Some("br"), BODY).is_err());
+    }
+
+    #[test]
+    fn test_record_transfer() {
+        let source = "test_record_transfer";
+        record_transfer(source, 100, 400);
+        record_transfer(source, 50, 50);
+
+        let stats = bandwidth_stats()
+            .into_iter()
+            .find(|(s, _)| s == source)
+            .map(|(_, stats)| stats)
+            .unwrap();
+        assert_eq!(
+            stats,
+            TransferStats {
+                requests: 2,
+                compressed_bytes: 150,
+                uncompressed_bytes: 450,
+            }
+        );
+    }
+
+    #[test]
+    fn test_counting_reader() {
+        let (mut reader, count) = CountingReader::new(BODY);
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(count.load(Ordering::Relaxed), BODY.len() as u64);
+    }
+}
@@ -0,0 +1,164 @@
+// Key-value store for UI component state
+//
+// Components keep small JSON values such as column widths or collapsed
+// sections here instead of localStorage, so they live in the database with
+// everything else and travel with database exports.
+use crate::error::{Result, XTauriError};
+use crate::state::DbState;
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::State;
+
+/// Largest value that can be stored, measured as serialized JSON
+pub const MAX_VALUE_BYTES: usize = 4096;
+
+/// Longest namespace or key
+pub const MAX_NAME_LENGTH: usize = 128;
+
+fn validate_name(kind: &str, name: &str) -> Result<()> {
+    if name.trim().is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(XTauriError::InvalidSetting {
+            key: kind.to_string(),
+            value: format!("must be 1 to {} bytes, got {:?}", MAX_NAME_LENGTH, name),
+        });
+    }
+    Ok(())
+}
+
+pub fn get_value(conn: &Connection, namespace: &str, key: &str) -> Result<Option<serde_json::Value>> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM ui_state WHERE namespace = ?1 AND key = ?2",
+            params![namespace, key],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    value
+        .map(|value| {
+            serde_json::from_str(&value)
+                .map_err(|e| XTauriError::internal(format!("Invalid UI state for {}/{}: {}", namespace, key, e)))
+        })
+        .transpose()
+}
+
+/// Check a value against the name and size limits; returns it serialized
+pub fn serialize_value(namespace: &str, key: &str, value: &serde_json::Value) -> Result<String> {
+    validate_name("namespace", namespace)?;
+    validate_name("key", key)?;
+
+    let json = serde_json::to_string(value)
+        .map_err(|e| XTauriError::internal(format!("Failed to serialize UI state: {}", e)))?;
+    if json.len() > MAX_VALUE_BYTES {
+        return Err(XTauriError::InvalidSetting {
+            key: format!("{}/{}", namespace, key),
+            value: format!("{} bytes, over the {} byte limit", json.len(), MAX_VALUE_BYTES),
+        });
+    }
+    Ok(json)
+}
+
+/// Store a value, replacing any previous value for the key
+pub fn set_value(conn: &Connection, namespace: &str, key: &str, value: &serde_json::Value) -> Result<()> {
+    let json = serialize_value(namespace, key, value)?;
+    conn.execute(
+        "INSERT INTO ui_state (namespace, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(namespace, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![namespace, key, json, chrono::Utc::now().timestamp()],
+    )?;
+    Ok(())
+}
+
+/// Remove a value; returns whether there was one
+pub fn delete_value(conn: &Connection, namespace: &str, key: &str) -> Result<bool> {
+    let rows_affected = conn.execute(
+        "DELETE FROM ui_state WHERE namespace = ?1 AND key = ?2",
+        params![namespace, key],
+    )?;
+    Ok(rows_affected > 0)
+}
+
+/// Remove every value in a namespace; returns how many were removed
+pub fn clear_namespace(conn: &Connection, namespace: &str) -> Result<usize> {
+    Ok(conn.execute("DELETE FROM ui_state WHERE namespace = ?1", [namespace])?)
+}
+
+#[tauri::command]
+pub fn get_ui_state(
+    state: State<DbState>,
+    namespace: String,
+    key: String,
+) -> std::result::Result<Option<serde_json::Value>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    get_value(&db, &namespace, &key).map_err(|e| e.to_string())
+}
+
+/// Store a JSON value of at most 4KB for a UI component
+#[tauri::command]
+pub fn set_ui_state(
+    state: State<DbState>,
+    namespace: String,
+    key: String,
+    value: serde_json::Value,
+) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    set_value(&db, &namespace, &key, &value).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_ui_state(state: State<DbState>, namespace: String, key: String) -> std::result::Result<bool, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    delete_value(&db, &namespace, &key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clear_ui_state_namespace(state: State<DbState>, namespace: String) -> std::result::Result<usize, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    clear_namespace(&db, &namespace).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ui_state_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ui_state (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (namespace, key)
+            )",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_set_get_delete() {
+        let conn = ui_state_db();
+        set_value(&conn, "channel_table", "widths", &json!([120, 300])).unwrap();
+        set_value(&conn, "channel_table", "widths", &json!([150, 280])).unwrap();
+        set_value(&conn, "sidebar", "collapsed", &json!(true)).unwrap();
+
+        assert_eq!(get_value(&conn, "channel_table", "widths").unwrap(), Some(json!([150, 280])));
+        assert!(delete_value(&conn, "sidebar", "collapsed").unwrap());
+        assert!(!delete_value(&conn, "sidebar", "collapsed").unwrap());
+        assert_eq!(get_value(&conn, "sidebar", "collapsed").unwrap(), None);
+
+        assert_eq!(clear_namespace(&conn, "channel_table").unwrap(), 1);
+        assert_eq!(get_value(&conn, "channel_table", "widths").unwrap(), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        let conn = ui_state_db();
+        let big = json!("x".repeat(MAX_VALUE_BYTES));
+        assert!(set_value(&conn, "ns", "big", &big).is_err());
+        assert!(set_value(&conn, "", "key", &json!(1)).is_err());
+        assert!(set_value(&conn, "ns", &"k".repeat(MAX_NAME_LENGTH + 1), &json!(1)).is_err());
+        assert_eq!(get_value(&conn, "ns", "big").unwrap(), None);
+    }
+}
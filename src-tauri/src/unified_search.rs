@@ -0,0 +1,357 @@
+// Search across every source in one call
+//
+// Playlist channels are matched by the playlist search, and each Xtream
+// profile's channels, movies and series by the content cache's FTS search. All
+// hits are then scored with the same relevance function, so results from
+// different sources can be compared, and grouped by source and content type.
+use crate::content_cache::{
+    age_rating, fts, ChannelFilter, ContentCacheState, MovieFilter, SeriesFilter, XtreamChannel, XtreamMovie,
+    XtreamSeries,
+};
+use crate::expiry;
+use crate::m3u_parser::Channel;
+use crate::search::run_channel_search;
+use crate::search_session;
+use crate::state::{ChannelCacheState, DbState};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{State, Window};
+
+/// Hits returned per group unless asked otherwise
+const DEFAULT_GROUP_LIMIT: usize = 20;
+
+/// Matches taken from each FTS search before scoring
+const MAX_CANDIDATES: usize = 200;
+
+/// Where a search hit comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSource {
+    Playlist,
+    Xtream,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchContentType {
+    Channel,
+    Movie,
+    Series,
+}
+
+/// A matched item, as the source's own search would return it
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum SearchItem {
+    PlaylistChannel(Channel),
+    XtreamChannel(XtreamChannel),
+    Movie(XtreamMovie),
+    Series(XtreamSeries),
+}
+
+impl SearchItem {
+    /// Relevance on the scale the content cache uses; anything matched scores at least 1
+    fn relevance(&self, query: &str) -> f64 {
+        let (name, title, plot) = match self {
+            SearchItem::PlaylistChannel(channel) => (&channel.name, &None, &None),
+            SearchItem::XtreamChannel(channel) => (&channel.name, &None, &None),
+            SearchItem::Movie(movie) => (&movie.name, &movie.title, &movie.plot),
+            SearchItem::Series(series) => (&series.name, &series.title, &series.plot),
+        };
+        1.0 + fts::calculate_relevance_score(query, &Some(name.clone()), title, plot)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct SearchHit {
+    pub relevance_score: f64,
+    pub item: SearchItem,
+}
+
+/// One source's hits of one content type, best first
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct SearchGroup {
+    pub source: SearchSource,
+    /// Channel list id for playlists, profile id for Xtream
+    pub source_id: String,
+    pub source_name: String,
+    pub content_type: SearchContentType,
+    /// Matches found, of which the best are in `hits`
+    pub total: usize,
+    pub hits: Vec<SearchHit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct UnifiedSearchResults {
+    pub query: String,
+    /// Groups with any hits, the group with the best hit first
+    pub groups: Vec<SearchGroup>,
+}
+
+/// Score items and keep the best `limit`, returning how many there were
+///
+/// The sort is stable, so equally scored items keep the order their source
+/// ranked them in.
+fn rank(query: &str, items: Vec<SearchItem>, limit: usize) -> (usize, Vec<SearchHit>) {
+    let total = items.len();
+    let mut hits: Vec<SearchHit> = items
+        .into_iter()
+        .map(|item| SearchHit {
+            relevance_score: item.relevance(query),
+            item,
+        })
+        .collect();
+    hits.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+    hits.truncate(limit);
+    (total, hits)
+}
+
+fn best_score(group: &SearchGroup) -> f64 {
+    group.hits.first().map_or(0.0, |hit| hit.relevance_score)
+}
+
+/// Order groups by their best hit and drop the empty ones
+fn finish(query: String, mut groups: Vec<SearchGroup>) -> UnifiedSearchResults {
+    groups.retain(|group| !group.hits.is_empty());
+    groups.sort_by(|a, b| best_score(b).total_cmp(&best_score(a)));
+    UnifiedSearchResults { query, groups }
+}
+
+/// Ids and names of the profiles to search, all of them when `profile_id` is `None`
+///
+/// Profiles whose content is hidden because it expired are left out.
+fn searchable_profiles(conn: &Connection, profile_id: Option<&str>) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT id, name FROM xtream_profiles WHERE ?1 IS NULL OR id = ?1 ORDER BY name")?;
+    let profiles = stmt
+        .query_map([profile_id], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+        .filter(|row| row.as_ref().map_or(true, |(id, _)| !expiry::profile_hidden(id)))
+        .collect();
+    profiles
+}
+
+/// Search playlist channels and every Xtream profile's channels, movies and series at once
+///
+/// `channel_list_id` picks the playlist (the default one when omitted) and
+/// `profile_id` limits Xtream results to one profile. Movies and series above
+/// the parental age limit are left out, as in the per-type searches, and so is
+/// content of expired playlists and profiles.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn search_all_content(
+    window: Window,
+    db_state: State<'_, DbState>,
+    cache_state: State<'_, ChannelCacheState>,
+    content_state: State<'_, ContentCacheState>,
+    query: String,
+    channel_list_id: Option<i32>,
+    profile_id: Option<String>,
+    limit: Option<usize>,
+    session_id: Option<String>,
+) -> std::result::Result<UnifiedSearchResults, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(finish(query, Vec::new()));
+    }
+    let limit = limit.unwrap_or(DEFAULT_GROUP_LIMIT);
+    let ticket = search_session::begin(window.label(), session_id.as_deref());
+    let mut groups = Vec::new();
+
+    let list_name = {
+        let db = db_state.pool.read().map_err(|e| e.to_string())?;
+        db.query_row(
+            "SELECT id, name FROM channel_lists WHERE id = ?1 OR (?1 IS NULL AND is_default = 1)",
+            [channel_list_id],
+            |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+    };
+    let channels = run_channel_search(db_state.clone(), cache_state, query.clone(), channel_list_id, &ticket)?;
+    let (total, hits) = rank(&query, channels.into_iter().map(SearchItem::PlaylistChannel).collect(), limit);
+    let (list_id, list_name) = list_name.unzip();
+    groups.push(SearchGroup {
+        source: SearchSource::Playlist,
+        source_id: list_id.or(channel_list_id).map(|id| id.to_string()).unwrap_or_default(),
+        source_name: list_name.unwrap_or_default(),
+        content_type: SearchContentType::Channel,
+        total,
+        hits,
+    });
+
+    let profiles = {
+        let conn = content_state.cache.reader().map_err(|e| e.to_string())?;
+        searchable_profiles(&conn, profile_id.as_deref()).map_err(|e| e.to_string())?
+    };
+
+    let max_age_rating = age_rating::effective_max_age(None);
+    for (profile_id, profile_name) in profiles {
+        ticket.check()?;
+        let cache = &content_state.cache;
+        let channels = cache
+            .fts_search_channels(
+                &profile_id,
+                &query,
+                Some(ChannelFilter {
+                    limit: Some(MAX_CANDIDATES),
+                    ..Default::default()
+                }),
+            )
+            .map_err(|e| e.to_string())?;
+        let movies = cache
+            .fts_search_movies(
+                &profile_id,
+                &query,
+                Some(MovieFilter {
+                    max_age_rating,
+                    limit: Some(MAX_CANDIDATES),
+                    ..Default::default()
+                }),
+            )
+            .map_err(|e| e.to_string())?;
+        let series = cache
+            .fts_search_series(
+                &profile_id,
+                &query,
+                Some(SeriesFilter {
+                    max_age_rating,
+                    limit: Some(MAX_CANDIDATES),
+                    ..Default::default()
+                }),
+            )
+            .map_err(|e| e.to_string())?;
+
+        for (content_type, items) in [
+            (SearchContentType::Channel, channels.into_iter().map(SearchItem::XtreamChannel).collect()),
+            (SearchContentType::Movie, movies.into_iter().map(SearchItem::Movie).collect()),
+            (SearchContentType::Series, series.into_iter().map(SearchItem::Series).collect::<Vec<_>>()),
+        ] {
+            let (total, hits) = rank(&query, items, limit);
+            groups.push(SearchGroup {
+                source: SearchSource::Xtream,
+                source_id: profile_id.clone(),
+                source_name: profile_name.clone(),
+                content_type,
+                total,
+                hits,
+            });
+        }
+    }
+
+    ticket.check()?;
+    crate::anonymize::mask_result(finish(query, groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movie(name: &str, plot: Option<&str>) -> SearchItem {
+        SearchItem::Movie(XtreamMovie {
+            stream_id: 1,
+            num: None,
+            name: name.to_string(),
+            title: None,
+            year: None,
+            stream_type: None,
+            stream_icon: None,
+            rating: None,
+            rating_5based: None,
+            genre: None,
+            added: None,
+            episode_run_time: None,
+            category_id: None,
+            container_extension: None,
+            custom_sid: None,
+            direct_source: None,
+            release_date: None,
+            cast: None,
+            director: None,
+            plot: plot.map(str::to_string),
+            youtube_trailer: None,
+            age_rating: None,
+        })
+    }
+
+    fn playlist_channel(name: &str) -> SearchItem {
+        SearchItem::PlaylistChannel(Channel {
+            name: name.to_string(),
+            logo: String::new(),
+            url: String::new(),
+            group_title: String::new(),
+            tvg_id: String::new(),
+            resolution: String::new(),
+            extra_info: String::new(),
+            country: String::new(),
+            country_flag: String::new(),
+        })
+    }
+
+    fn names(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter()
+            .map(|hit| match &hit.item {
+                SearchItem::PlaylistChannel(channel) => channel.name.as_str(),
+                SearchItem::XtreamChannel(channel) => channel.name.as_str(),
+                SearchItem::Movie(movie) => movie.name.as_str(),
+                SearchItem::Series(series) => series.name.as_str(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hits_are_ranked_on_one_scale() {
+        let items = vec![
+            movie("The Alien Planet", Some("Explorers find an alien")),
+            movie("Aliens", None),
+            movie("Alien", None),
+            movie("Prometheus", Some("A prequel to alien")),
+        ];
+        let (total, hits) = rank("alien", items, 3);
+        assert_eq!(total, 4);
+        assert_eq!(names(&hits), vec!["Alien", "Aliens", "The Alien Planet"]);
+        assert_eq!(hits[0].relevance_score, 101.0);
+        assert_eq!(hits[2].relevance_score, 36.0);
+    }
+
+    #[test]
+    fn test_groups_are_ordered_by_their_best_hit() {
+        let group = |source_id: &str, items: Vec<SearchItem>| {
+            let (total, hits) = rank("news", items, 10);
+            SearchGroup {
+                source: SearchSource::Playlist,
+                source_id: source_id.to_string(),
+                source_name: String::new(),
+                content_type: SearchContentType::Channel,
+                total,
+                hits,
+            }
+        };
+        let results = finish(
+            "news".to_string(),
+            vec![
+                group("partial", vec![playlist_channel("BBC News")]),
+                group("empty", Vec::new()),
+                group("exact", vec![playlist_channel("Sky Sports"), playlist_channel("News")]),
+            ],
+        );
+        let order: Vec<&str> = results.groups.iter().map(|group| group.source_id.as_str()).collect();
+        assert_eq!(order, vec!["exact", "partial"]);
+        assert_eq!(names(&results.groups[0].hits), vec!["News", "Sky Sports"]);
+    }
+
+    #[test]
+    fn test_expired_profiles_are_not_searched() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, name TEXT NOT NULL);
+             INSERT INTO xtream_profiles VALUES ('search-paid', 'Paid'), ('search-trial', 'Trial');",
+        )
+        .unwrap();
+        let _guard = expiry::expire_for_test(expiry::ExpirySource::Profile, "search-trial");
+
+        let ids = |profile_id| -> Vec<String> {
+            searchable_profiles(&conn, profile_id).unwrap().into_iter().map(|(id, _)| id).collect()
+        };
+        assert_eq!(ids(None), vec!["search-paid"]);
+        assert!(ids(Some("search-trial")).is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+// Transactions that span modules
+//
+// Deleting a profile touches favorites, history, the content cache and more,
+// each owned by a different module. A `UnitOfWork` wraps one transaction that
+// every module's step runs in, so either all of the changes land or none do.
+// Side effects outside the database, such as dropping cached credentials, are
+// queued with `after_commit` and only happen once the transaction committed.
+use crate::error::Result;
+use rusqlite::{Connection, Transaction};
+
+pub struct UnitOfWork<'conn> {
+    tx: Transaction<'conn>,
+    after_commit: Vec<Box<dyn FnOnce() + 'conn>>,
+}
+
+impl<'conn> UnitOfWork<'conn> {
+    /// Start a unit of work on a connection the caller has locked
+    pub fn begin(conn: &'conn Connection) -> Result<Self> {
+        Ok(Self {
+            tx: conn.unchecked_transaction()?,
+            after_commit: Vec::new(),
+        })
+    }
+
+    /// Run one module's part of the work inside the transaction
+    ///
+    /// An error is returned as is; the caller gives up and the transaction
+    /// rolls back when the unit of work is dropped.
+    pub fn enlist<T>(&mut self, step: &str, work: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+        work(&self.tx).inspect_err(|e| eprintln!("[UNIT OF WORK] {} failed, rolling back: {}", step, e))
+    }
+
+    /// Queue a side effect to run once the transaction has committed
+    pub fn after_commit(&mut self, effect: impl FnOnce() + 'conn) {
+        self.after_commit.push(Box::new(effect));
+    }
+
+    pub fn commit(self) -> Result<()> {
+        self.tx.commit()?;
+        for effect in self.after_commit {
+            effect();
+        }
+        Ok(())
+    }
+}
+
+/// Run `work` as one unit of work: committed if it succeeds, rolled back if it fails
+pub fn run<'conn, T>(conn: &'conn Connection, work: impl FnOnce(&mut UnitOfWork<'conn>) -> Result<T>) -> Result<T> {
+    let mut unit = UnitOfWork::begin(conn)?;
+    let value = work(&mut unit)?;
+    unit.commit()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::XTauriError;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_failed_step_rolls_back_every_step() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE a (id INTEGER);
+             CREATE TABLE b (id INTEGER);
+             INSERT INTO a VALUES (1);
+             INSERT INTO b VALUES (1);",
+        )
+        .unwrap();
+        let count = |table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+        };
+        let effects = Cell::new(0);
+
+        let result: Result<()> = run(&conn, |unit| {
+            unit.enlist("first", |conn| Ok(conn.execute("DELETE FROM a", [])?))?;
+            unit.after_commit(|| effects.set(effects.get() + 1));
+            unit.enlist("second", |_| Err(XTauriError::internal("boom".to_string())))?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(count("a"), 1);
+        assert_eq!(effects.get(), 0);
+
+        run(&conn, |unit| {
+            unit.enlist("first", |conn| Ok(conn.execute("DELETE FROM a", [])?))?;
+            unit.enlist("second", |conn| Ok(conn.execute("DELETE FROM b", [])?))?;
+            unit.after_commit(|| effects.set(effects.get() + 1));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count("a") + count("b"), 0);
+        assert_eq!(effects.get(), 1);
+    }
+}
@@ -0,0 +1,417 @@
+use crate::connection;
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// Releases queried when no custom endpoint is configured
+pub const DEFAULT_RELEASES_ENDPOINT: &str = "https://api.github.com/repos/thiiz/xtauri/releases";
+
+/// A manual check within this long of the last one reuses its result
+pub const CHECK_CACHE_TTL_SECS: i64 = 60 * 60;
+
+/// Interval between automatic checks
+pub const AUTO_CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// How often the background task looks at whether an automatic check is due
+const AUTO_CHECK_POLL: Duration = Duration::from_secs(60 * 60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Version of the running build
+pub fn current_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// A downloadable file attached to a release
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub url: String,
+    pub size: u64,
+    pub content_type: Option<String>,
+}
+
+/// Result of comparing the newest release against the running version
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    /// Newest published release, if the endpoint listed any with a version tag
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    pub release_name: Option<String>,
+    /// Release notes as written on the release (usually Markdown)
+    pub notes: Option<String>,
+    pub release_url: Option<String>,
+    pub published_at: Option<String>,
+    pub assets: Vec<ReleaseAsset>,
+    pub checked_at: i64,
+    /// Whether this result was served from the last check instead of fetched
+    pub from_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct UpdateSettings {
+    pub auto_check: bool,
+    /// Endpoint in use, custom or default
+    pub endpoint: String,
+    pub default_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubAsset {
+    name: String,
+    browser_download_url: String,
+    #[serde(default)]
+    size: u64,
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    name: Option<String>,
+    body: Option<String>,
+    html_url: Option<String>,
+    published_at: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<GithubAsset>,
+}
+
+/// The releases list endpoint returns an array, `/releases/latest` a single release
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ReleasesResponse {
+    List(Vec<GithubRelease>),
+    Single(GithubRelease),
+}
+
+/// Parse a release tag such as `v1.2.3` as a semantic version
+pub fn parse_version(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix(['v', 'V']).unwrap_or(tag);
+    Version::parse(tag).ok()
+}
+
+/// Build the update report from a releases API response
+///
+/// Drafts, prereleases and tags that aren't semantic versions are ignored;
+/// the highest remaining version is compared against `current`.
+pub fn update_info_from_response(body: &str, current: &str, checked_at: i64) -> Result<UpdateInfo> {
+    let releases = match serde_json::from_str(body)
+        .map_err(|e| XTauriError::internal(format!("Unexpected releases response: {}", e)))?
+    {
+        ReleasesResponse::List(releases) => releases,
+        ReleasesResponse::Single(release) => vec![release],
+    };
+
+    let latest = releases
+        .into_iter()
+        .filter(|release| !release.draft && !release.prerelease)
+        .filter_map(|release| parse_version(&release.tag_name).map(|version| (version, release)))
+        .max_by(|(a, _), (b, _)| a.cmp(b));
+
+    let current_version = parse_version(current);
+    let mut info = UpdateInfo {
+        current_version: current.to_string(),
+        latest_version: None,
+        update_available: false,
+        release_name: None,
+        notes: None,
+        release_url: None,
+        published_at: None,
+        assets: Vec::new(),
+        checked_at,
+        from_cache: false,
+    };
+
+    if let Some((version, release)) = latest {
+        info.update_available = current_version.is_some_and(|current| version > current);
+        info.latest_version = Some(version.to_string());
+        info.release_name = release.name.filter(|name| !name.is_empty());
+        info.notes = release.body.filter(|body| !body.is_empty());
+        info.release_url = release.html_url;
+        info.published_at = release.published_at;
+        info.assets = release
+            .assets
+            .into_iter()
+            .map(|asset| ReleaseAsset {
+                name: asset.name,
+                url: asset.browser_download_url,
+                size: asset.size,
+                content_type: asset.content_type,
+            })
+            .collect();
+    }
+
+    Ok(info)
+}
+
+fn custom_endpoint(conn: &Connection) -> Result<Option<String>> {
+    let endpoint = conn.query_row(
+        "SELECT update_check_endpoint FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(endpoint)
+}
+
+fn endpoint(conn: &Connection) -> Result<String> {
+    Ok(custom_endpoint(conn)?.unwrap_or_else(|| DEFAULT_RELEASES_ENDPOINT.to_string()))
+}
+
+fn auto_check_enabled(conn: &Connection) -> Result<bool> {
+    let enabled = conn.query_row(
+        "SELECT auto_check_updates FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(enabled)
+}
+
+/// Database operations for the last update check
+pub struct UpdateCheckDb;
+
+impl UpdateCheckDb {
+    pub fn save(conn: &Connection, endpoint: &str, info: &UpdateInfo) -> Result<()> {
+        let result = serde_json::to_string(info)
+            .map_err(|e| XTauriError::internal(format!("Failed to serialize update check: {}", e)))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO update_check (id, endpoint, result, checked_at)
+             VALUES (1, ?1, ?2, ?3)",
+            params![endpoint, result, info.checked_at],
+        )?;
+        Ok(())
+    }
+
+    /// The last check against `endpoint` made by this version of the app
+    pub fn get(conn: &Connection, endpoint: &str) -> Result<Option<UpdateInfo>> {
+        let result: Option<String> = conn
+            .query_row(
+                "SELECT result FROM update_check WHERE id = 1 AND endpoint = ?1",
+                params![endpoint],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(result
+            .and_then(|result| serde_json::from_str::<UpdateInfo>(&result).ok())
+            .filter(|info| info.current_version == current_version()))
+    }
+
+    pub fn clear(conn: &Connection) -> Result<()> {
+        conn.execute("DELETE FROM update_check", [])?;
+        Ok(())
+    }
+}
+
+async fn fetch_update_info(endpoint: &str) -> Result<UpdateInfo> {
+    let client = connection::client_builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(concat!("xtauri/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let response = client
+        .get(endpoint)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(XTauriError::internal(format!(
+            "Release check failed with HTTP {}",
+            status.as_u16()
+        )));
+    }
+
+    let body = response.text().await?;
+    update_info_from_response(&body, current_version(), Utc::now().timestamp())
+}
+
+/// Check for a newer release, reusing the last result if it is younger than `max_age_secs`
+async fn check(db_state: &DbState, max_age_secs: i64) -> Result<UpdateInfo> {
+    let (endpoint, cached) = {
+        let db = db_state
+            .db
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let endpoint = endpoint(&db)?;
+        let cached = UpdateCheckDb::get(&db, &endpoint)?;
+        (endpoint, cached)
+    };
+
+    if let Some(mut info) = cached {
+        if Utc::now().timestamp() - info.checked_at < max_age_secs {
+            info.from_cache = true;
+            return Ok(info);
+        }
+    }
+
+    let info = fetch_update_info(&endpoint).await?;
+    let db = db_state
+        .db
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+    UpdateCheckDb::save(&db, &endpoint, &info)?;
+    Ok(info)
+}
+
+/// Check for updates once a day in the background while automatic checks are enabled
+///
+/// The setting is re-read on every poll, so turning it on or off takes effect
+/// without a restart.
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    loop {
+        let enabled = match app_handle.state::<DbState>().db.lock() {
+            Ok(db) => auto_check_enabled(&db).unwrap_or(false),
+            Err(_) => return,
+        };
+
+        if enabled {
+            let _task = crate::tasks::register(crate::tasks::TaskKind::UpdateCheck, "Checking for updates", None);
+            match check(&app_handle.state::<DbState>(), AUTO_CHECK_INTERVAL_SECS).await {
+                Ok(info) if info.update_available && !info.from_cache => {
+                    events::emit(&app_handle, AppEvent::UpdateAvailable(info));
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("[UPDATES] Automatic update check failed: {}", e),
+            }
+        }
+
+        tokio::time::sleep(AUTO_CHECK_POLL).await;
+    }
+}
+
+/// Compare the newest published release against the running version
+///
+/// Results are cached for an hour; pass `force` to query the endpoint regardless.
+#[tauri::command]
+pub async fn check_for_updates(
+    state: State<'_, DbState>,
+    force: Option<bool>,
+) -> std::result::Result<UpdateInfo, String> {
+    let max_age = if force.unwrap_or(false) { 0 } else { CHECK_CACHE_TTL_SECS };
+    check(&state, max_age).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_update_settings(state: State<DbState>) -> std::result::Result<UpdateSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(UpdateSettings {
+        auto_check: auto_check_enabled(&db).map_err(|e| e.to_string())?,
+        endpoint: endpoint(&db).map_err(|e| e.to_string())?,
+        default_endpoint: DEFAULT_RELEASES_ENDPOINT.to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn set_auto_check_updates(state: State<DbState>, enabled: bool) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET auto_check_updates = ?1 WHERE id = 1",
+        params![enabled],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Point update checks at another releases endpoint; `None` restores the default
+#[tauri::command]
+pub fn set_update_check_endpoint(
+    state: State<DbState>,
+    endpoint: Option<String>,
+) -> std::result::Result<(), String> {
+    let endpoint = endpoint
+        .map(|endpoint| endpoint.trim().to_string())
+        .filter(|endpoint| !endpoint.is_empty());
+    if let Some(endpoint) = &endpoint {
+        let valid = url::Url::parse(endpoint).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !valid {
+            return Err(XTauriError::InvalidUrl { url: endpoint.clone() }.to_string());
+        }
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET update_check_endpoint = ?1 WHERE id = 1",
+        params![endpoint],
+    )
+    .map_err(|e| e.to_string())?;
+    UpdateCheckDb::clear(&db).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELEASES: &str = r#"[
+        {"tag_name": "v0.3.0-beta.1", "name": "Beta", "prerelease": true, "draft": false, "assets": []},
+        {"tag_name": "v0.2.0", "name": "0.2.0", "body": "- Faster search", "html_url": "https://example.com/r/0.2.0",
+         "published_at": "2026-01-02T00:00:00Z", "draft": false, "prerelease": false,
+         "assets": [{"name": "xtauri.msi", "browser_download_url": "https://example.com/xtauri.msi", "size": 1024, "content_type": "application/x-msi"}]},
+        {"tag_name": "nightly", "draft": false, "prerelease": false, "assets": []},
+        {"tag_name": "v0.1.9", "draft": false, "prerelease": false, "assets": []}
+    ]"#;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("v1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse_version(" 0.1.8 "), Some(Version::new(0, 1, 8)));
+        assert!(parse_version("v1.2.3-rc.1").unwrap() < Version::new(1, 2, 3));
+        assert_eq!(parse_version("nightly"), None);
+    }
+
+    #[test]
+    fn test_update_info_picks_newest_stable_release() {
+        let info = update_info_from_response(RELEASES, "0.1.8", 1_700_000_000).unwrap();
+        assert!(info.update_available);
+        assert_eq!(info.latest_version.as_deref(), Some("0.2.0"));
+        assert_eq!(info.notes.as_deref(), Some("- Faster search"));
+        assert_eq!(info.assets.len(), 1);
+        assert_eq!(info.assets[0].url, "https://example.com/xtauri.msi");
+
+        let up_to_date = update_info_from_response(RELEASES, "0.2.0", 1_700_000_000).unwrap();
+        assert!(!up_to_date.update_available);
+
+        let single = r#"{"tag_name": "0.1.8", "assets": []}"#;
+        let same = update_info_from_response(single, "0.1.8", 1_700_000_000).unwrap();
+        assert_eq!(same.latest_version.as_deref(), Some("0.1.8"));
+        assert!(!same.update_available);
+
+        assert!(update_info_from_response(r#"{"message": "Not Found"}"#, "0.1.8", 0).is_err());
+    }
+
+    #[test]
+    fn test_cached_check_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE update_check (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                endpoint TEXT NOT NULL,
+                result TEXT NOT NULL,
+                checked_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+
+        let info = update_info_from_response(RELEASES, current_version(), 1_700_000_000).unwrap();
+        UpdateCheckDb::save(&conn, DEFAULT_RELEASES_ENDPOINT, &info).unwrap();
+        assert_eq!(UpdateCheckDb::get(&conn, DEFAULT_RELEASES_ENDPOINT).unwrap(), Some(info));
+        assert_eq!(UpdateCheckDb::get(&conn, "https://example.com/releases").unwrap(), None);
+
+        // A check made by an older build doesn't apply after upgrading
+        let old = update_info_from_response(RELEASES, "0.0.1", 1_700_000_000).unwrap();
+        UpdateCheckDb::save(&conn, DEFAULT_RELEASES_ENDPOINT, &old).unwrap();
+        assert_eq!(UpdateCheckDb::get(&conn, DEFAULT_RELEASES_ENDPOINT).unwrap(), None);
+    }
+}
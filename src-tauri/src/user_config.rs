@@ -0,0 +1,335 @@
+// User configuration file
+//
+// Power users can keep an optional `config.toml` next to the database for what
+// the settings screen doesn't offer (an HTTP proxy, connect and API timeouts)
+// and to pin settings over the ones saved in the database (connection
+// strategy, HTTP compression, low-power mode). The file is checked for changes
+// every few seconds. Pinned settings are read on every use and apply at once;
+// the proxy and timeouts apply to HTTP clients created after the change. A
+// file that fails to parse is reported and the last good one stays in effect.
+use crate::connection::ConnectionStrategy;
+use crate::database;
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::power::{self, LowPowerMode};
+use crate::state::DbState;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, State};
+
+const FILE_NAME: &str = "config.toml";
+
+/// How often the file is checked for changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Timeout of Xtream API requests when the file doesn't set one
+pub const DEFAULT_API_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Contents of `config.toml`; every key is optional
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserConfig {
+    pub network: NetworkConfig,
+    pub features: FeatureConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Proxy for every request, e.g. `http://host:3128` or `socks5://host:1080`
+    pub proxy: Option<String>,
+    pub connect_timeout_secs: Option<u64>,
+    pub api_timeout_secs: Option<u64>,
+    pub connection_strategy: Option<ConnectionStrategy>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureConfig {
+    pub http_compression: Option<bool>,
+    pub low_power_mode: Option<LowPowerMode>,
+}
+
+impl UserConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: UserConfig =
+            toml::from_str(text).map_err(|e| XTauriError::internal(format!("Invalid {}: {}", FILE_NAME, e)))?;
+        if let Some(proxy) = &config.network.proxy {
+            reqwest::Proxy::all(proxy)
+                .map_err(|e| XTauriError::internal(format!("Invalid proxy {}: {}", proxy, e)))?;
+        }
+        for (key, secs) in [
+            ("connect_timeout_secs", config.network.connect_timeout_secs),
+            ("api_timeout_secs", config.network.api_timeout_secs),
+        ] {
+            if secs == Some(0) {
+                return Err(XTauriError::internal(format!("{} must be at least 1", key)));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Where a value in effect comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    File,
+    Database,
+    Default,
+}
+
+/// One value in effect, by its key in the file
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct ConfigValue {
+    pub key: String,
+    /// `None` when nothing is set and the HTTP client's own behavior applies
+    pub value: Option<String>,
+    pub source: ConfigSource,
+}
+
+/// The configuration in effect, merged from the file, the settings and defaults
+#[derive(Debug, Clone, PartialEq, Serialize, specta::Type)]
+pub struct EffectiveConfig {
+    pub path: String,
+    /// Whether the file exists
+    pub file_present: bool,
+    /// Why the file was last rejected; the previous contents stay in effect
+    pub error: Option<String>,
+    pub values: Vec<ConfigValue>,
+}
+
+#[derive(Default)]
+struct Loaded {
+    config: UserConfig,
+    modified: Option<SystemTime>,
+    present: bool,
+    error: Option<String>,
+}
+
+static LOADED: LazyLock<RwLock<Loaded>> = LazyLock::new(|| RwLock::new(Loaded::default()));
+
+pub fn config_path() -> Result<PathBuf> {
+    let db_path = database::database_path()?;
+    Ok(db_path.parent().map(|dir| dir.join(FILE_NAME)).unwrap_or_else(|| PathBuf::from(FILE_NAME)))
+}
+
+fn read<T>(f: impl FnOnce(&UserConfig) -> T) -> T {
+    match LOADED.read() {
+        Ok(loaded) => f(&loaded.config),
+        Err(poisoned) => f(&poisoned.into_inner().config),
+    }
+}
+
+/// Proxy set in the file
+pub fn proxy() -> Option<reqwest::Proxy> {
+    read(|config| config.network.proxy.as_deref().and_then(|proxy| reqwest::Proxy::all(proxy).ok()))
+}
+
+pub fn connect_timeout() -> Option<Duration> {
+    read(|config| config.network.connect_timeout_secs.map(Duration::from_secs))
+}
+
+pub fn api_timeout() -> Duration {
+    read(|config| config.network.api_timeout_secs.map(Duration::from_secs)).unwrap_or(DEFAULT_API_TIMEOUT)
+}
+
+pub fn connection_strategy() -> Option<ConnectionStrategy> {
+    read(|config| config.network.connection_strategy)
+}
+
+pub fn http_compression() -> Option<bool> {
+    read(|config| config.features.http_compression)
+}
+
+pub fn low_power_mode() -> Option<LowPowerMode> {
+    read(|config| config.features.low_power_mode)
+}
+
+/// Reload the file if it changed since it was last read; returns whether anything changed
+fn reload_from(path: &Path) -> bool {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let present = path.is_file();
+    let mut loaded = LOADED.write().unwrap_or_else(|e| e.into_inner());
+    if present == loaded.present && modified == loaded.modified {
+        return false;
+    }
+    loaded.present = present;
+    loaded.modified = modified;
+
+    let parsed = if present {
+        fs::read_to_string(path)
+            .map_err(|e| XTauriError::internal(format!("Failed to read {}: {}", path.display(), e)))
+            .and_then(|text| UserConfig::parse(&text))
+    } else {
+        Ok(UserConfig::default())
+    };
+    match parsed {
+        Ok(config) => {
+            if present {
+                println!("[CONFIG] Loaded {}", path.display());
+            }
+            loaded.config = config;
+            loaded.error = None;
+        }
+        Err(e) => {
+            eprintln!("[CONFIG] {}; keeping the previous configuration", e);
+            loaded.error = Some(e.to_string());
+        }
+    }
+    true
+}
+
+/// Merge the file with the settings saved in the database
+pub fn effective(conn: &Connection) -> Result<EffectiveConfig> {
+    let (strategy, compression, low_power): (String, bool, String) = conn.query_row(
+        "SELECT connection_strategy, http_compression_enabled, low_power_mode FROM settings WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let loaded = LOADED.read().unwrap_or_else(|e| e.into_inner());
+    let network = &loaded.config.network;
+    let features = &loaded.config.features;
+
+    let from_file = |key: &str, value: Option<String>, fallback: Option<String>, source: ConfigSource| match value {
+        Some(value) => ConfigValue { key: key.to_string(), value: Some(value), source: ConfigSource::File },
+        None => ConfigValue { key: key.to_string(), value: fallback, source },
+    };
+    let values = vec![
+        from_file("network.proxy", network.proxy.clone(), None, ConfigSource::Default),
+        from_file(
+            "network.connect_timeout_secs",
+            network.connect_timeout_secs.map(|secs| secs.to_string()),
+            None,
+            ConfigSource::Default,
+        ),
+        from_file(
+            "network.api_timeout_secs",
+            network.api_timeout_secs.map(|secs| secs.to_string()),
+            Some(DEFAULT_API_TIMEOUT.as_secs().to_string()),
+            ConfigSource::Default,
+        ),
+        from_file(
+            "network.connection_strategy",
+            network.connection_strategy.map(|strategy| strategy.as_str().to_string()),
+            Some(strategy),
+            ConfigSource::Database,
+        ),
+        from_file(
+            "features.http_compression",
+            features.http_compression.map(|enabled| enabled.to_string()),
+            Some(compression.to_string()),
+            ConfigSource::Database,
+        ),
+        from_file(
+            "features.low_power_mode",
+            features.low_power_mode.map(|mode| mode.as_str().to_string()),
+            Some(low_power),
+            ConfigSource::Database,
+        ),
+    ];
+
+    Ok(EffectiveConfig {
+        path: config_path()?.display().to_string(),
+        file_present: loaded.present,
+        error: loaded.error.clone(),
+        values,
+    })
+}
+
+fn on_reloaded(app_handle: &AppHandle) {
+    power::notify_if_changed(app_handle);
+    let Some(db_state) = app_handle.try_state::<DbState>() else {
+        return;
+    };
+    let config = db_state.db.lock().map_err(|e| e.to_string()).and_then(|db| effective(&db).map_err(|e| e.to_string()));
+    match config {
+        Ok(config) => events::emit(app_handle, AppEvent::ConfigReloaded(config)),
+        Err(e) => eprintln!("[CONFIG] Failed to report the reloaded configuration: {}", e),
+    }
+}
+
+/// Load the file and keep following it for the rest of the run
+pub fn restore_on_startup(app_handle: &AppHandle) {
+    let path = match config_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("[CONFIG] No configuration file location: {}", e);
+            return;
+        }
+    };
+    reload_from(&path);
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if reload_from(&path) {
+                on_reloaded(&app_handle);
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_effective_config(state: State<DbState>) -> std::result::Result<EffectiveConfig, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    effective(&db).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = UserConfig::parse(
+            r#"
+            [network]
+            proxy = "http://127.0.0.1:3128"
+            api_timeout_secs = 60
+            connection_strategy = "prefer_ipv4"
+
+            [features]
+            low_power_mode = "on"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.network.api_timeout_secs, Some(60));
+        assert_eq!(config.network.connection_strategy, Some(ConnectionStrategy::PreferIpv4));
+        assert_eq!(config.network.connect_timeout_secs, None);
+        assert_eq!(config.features.low_power_mode, Some(LowPowerMode::On));
+        assert_eq!(UserConfig::parse("").unwrap(), UserConfig::default());
+
+        // Typos and unusable values are reported instead of ignored
+        assert!(UserConfig::parse("[network]\nproxi = \"http://host\"").is_err());
+        assert!(UserConfig::parse("[network]\nconnect_timeout_secs = 0").is_err());
+        assert!(UserConfig::parse("[network]\nproxy = \"not a url\"").is_err());
+    }
+
+    #[test]
+    fn test_effective_config_falls_back_to_settings() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE settings (
+                id INTEGER PRIMARY KEY, connection_strategy TEXT, http_compression_enabled BOOLEAN, low_power_mode TEXT
+            );
+            INSERT INTO settings VALUES (1, 'prefer_ipv6', 0, 'auto');",
+        )
+        .unwrap();
+
+        let config = effective(&conn).unwrap();
+        let value = |key: &str| config.values.iter().find(|value| value.key == key).unwrap().clone();
+        assert_eq!(value("network.connection_strategy").value.as_deref(), Some("prefer_ipv6"));
+        assert_eq!(value("network.connection_strategy").source, ConfigSource::Database);
+        assert_eq!(value("features.http_compression").value.as_deref(), Some("false"));
+        assert_eq!(value("network.api_timeout_secs").value.as_deref(), Some("30"));
+        assert_eq!(value("network.proxy").source, ConfigSource::Default);
+    }
+}
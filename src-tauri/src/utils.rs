@@ -1,5 +1,6 @@
 use crate::error::{Result as XTauriResult, XTauriError};
 use std::fs;
+use std::path::Path;
 use rusqlite::Connection;
 use dirs;
 
@@ -8,16 +9,19 @@ pub fn cleanup_orphaned_channel_files(db_connection: &Connection) -> XTauriResul
     let data_dir = dirs::data_dir()
         .ok_or_else(|| XTauriError::DataDirectoryAccess)?
         .join("xtauri");
-    let channel_lists_dir = data_dir.join("channel_lists");
-    
+    cleanup_orphaned_files_in(db_connection, &data_dir.join("channel_lists"))
+}
+
+/// Delete the `.m3u` files in `channel_lists_dir` that no channel list's `filepath` names
+pub fn cleanup_orphaned_files_in(db_connection: &Connection, channel_lists_dir: &Path) -> XTauriResult<()> {
     // Create channel_lists directory if it doesn't exist
-    if let Err(e) = fs::create_dir_all(&channel_lists_dir) {
+    if let Err(e) = fs::create_dir_all(channel_lists_dir) {
         println!("Warning: Failed to create channel_lists directory: {}", e);
         return Ok(()); // Don't fail startup if we can't create the directory
     }
     
     // Get all .m3u files in the channel_lists directory
-    let disk_files = match fs::read_dir(&channel_lists_dir) {
+    let disk_files = match fs::read_dir(channel_lists_dir) {
         Ok(entries) => {
             entries
                 .filter_map(|entry| entry.ok())
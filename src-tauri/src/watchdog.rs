@@ -0,0 +1,177 @@
+// Watchdog for network transfers that stop making progress
+//
+// Some providers keep a connection open and trickle the body a few bytes at a
+// time, or stop sending without closing, so a read can outlive the client
+// timeout. Transfers register here and report the bytes they receive; a
+// background sweep aborts any transfer that has gone STALL_TIMEOUT without
+// receiving anything, which then fails with `XTauriError::Stalled`.
+use crate::error::{Result, XTauriError};
+use dashmap::DashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Once};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// How long a transfer may go without receiving data before it is aborted
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often the watchdog checks registered transfers
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Transfers in progress, by registration id
+static OPERATIONS: LazyLock<DashMap<u64, Arc<Operation>>> = LazyLock::new(DashMap::new);
+
+static STARTED: Once = Once::new();
+
+struct Operation {
+    label: String,
+    /// Origin and path of the request; the query is left out as it carries credentials
+    target: String,
+    started: Instant,
+    bytes: AtomicU64,
+    /// Milliseconds after `started` when data last arrived
+    last_progress_ms: AtomicU64,
+    cancel: CancellationToken,
+}
+
+impl Operation {
+    fn idle(&self, now: Instant) -> Duration {
+        let last = self.started + Duration::from_millis(self.last_progress_ms.load(Ordering::Relaxed));
+        now.saturating_duration_since(last)
+    }
+}
+
+/// A registered transfer; it is unregistered when dropped
+pub struct WatchedOperation {
+    id: u64,
+    operation: Arc<Operation>,
+}
+
+/// Register a transfer with the watchdog
+pub fn watch(label: &str, url: &reqwest::Url) -> WatchedOperation {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let operation = Arc::new(Operation {
+        label: label.to_string(),
+        target: format!("{}{}", url.origin().ascii_serialization(), url.path()),
+        started: Instant::now(),
+        bytes: AtomicU64::new(0),
+        last_progress_ms: AtomicU64::new(0),
+        cancel: CancellationToken::new(),
+    });
+    OPERATIONS.insert(id, operation.clone());
+    WatchedOperation { id, operation }
+}
+
+impl WatchedOperation {
+    /// Record that `bytes` more bytes arrived
+    pub fn progress(&self, bytes: usize) {
+        let operation = &self.operation;
+        operation.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        operation
+            .last_progress_ms
+            .store(operation.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Run one step of the transfer, failing if the watchdog aborts it meanwhile
+    pub async fn guard<F: Future>(&self, step: F) -> Result<F::Output> {
+        tokio::select! {
+            biased;
+            output = step => Ok(output),
+            _ = self.operation.cancel.cancelled() => Err(self.stalled_error()),
+        }
+    }
+
+    fn stalled_error(&self) -> XTauriError {
+        XTauriError::stalled(
+            self.operation.label.clone(),
+            self.operation.idle(Instant::now()).as_secs(),
+        )
+    }
+}
+
+impl Drop for WatchedOperation {
+    fn drop(&mut self) {
+        OPERATIONS.remove(&self.id);
+    }
+}
+
+/// Abort a transfer if it has been idle longer than `STALL_TIMEOUT`
+fn abort_if_stalled(operation: &Operation, now: Instant) -> bool {
+    let idle = operation.idle(now);
+    if idle < STALL_TIMEOUT || operation.cancel.is_cancelled() {
+        return false;
+    }
+    eprintln!(
+        "Watchdog: aborting stalled {} transfer of {} after {}s without data ({} bytes received, {}s elapsed)",
+        operation.label,
+        operation.target,
+        idle.as_secs(),
+        operation.bytes.load(Ordering::Relaxed),
+        now.saturating_duration_since(operation.started).as_secs(),
+    );
+    operation.cancel.cancel();
+    true
+}
+
+/// Start the background sweep; later calls do nothing
+pub fn start() {
+    STARTED.call_once(|| {
+        tauri::async_runtime::spawn(async {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = Instant::now();
+                for entry in OPERATIONS.iter() {
+                    abort_if_stalled(entry.value(), now);
+                }
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url() -> reqwest::Url {
+        reqwest::Url::parse("http://provider.test/player_api.php?username=u&password=p").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sweep_aborts_idle_transfer() {
+        let watched = watch("test", &url());
+        assert_eq!(watched.operation.target, "http://provider.test/player_api.php");
+        watched.progress(512);
+
+        assert!(!abort_if_stalled(&watched.operation, Instant::now()));
+        let later = Instant::now() + STALL_TIMEOUT + Duration::from_secs(1);
+        assert!(abort_if_stalled(&watched.operation, later));
+        assert!(watched.operation.cancel.is_cancelled());
+        assert!(!abort_if_stalled(&watched.operation, later));
+
+        let result = watched.guard(std::future::pending::<()>()).await;
+        match result {
+            Err(XTauriError::Stalled { operation, .. }) => assert_eq!(operation, "test"),
+            other => panic!("expected a stalled error, got {:?}", other),
+        }
+
+        let id = watched.id;
+        drop(watched);
+        assert!(!OPERATIONS.contains_key(&id));
+    }
+
+    #[tokio::test]
+    async fn test_progress_keeps_transfer_alive() {
+        let watched = watch("test", &url());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        watched.progress(1);
+
+        let idle = watched.operation.idle(Instant::now());
+        assert!(idle < Duration::from_millis(20));
+        assert_eq!(watched.guard(async { 7 }).await.unwrap(), 7);
+        assert!(!watched.operation.cancel.is_cancelled());
+    }
+}
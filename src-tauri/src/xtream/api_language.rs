@@ -0,0 +1,223 @@
+// Content language for provider API requests
+//
+// Some panels translate category and VOD names when `player_api.php` requests
+// carry a `lang` parameter. Each profile can set one. API URLs are built in
+// many places from the server URL and credentials alone, so the language is
+// looked up per account (server and username) where requests are sent rather
+// than passed through every call.
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use tauri::State;
+use url::Url;
+
+/// Query parameter that carries the language
+const LANGUAGE_PARAM: &str = "lang";
+
+/// Language by account key; mirrors the `api_language` profile column
+static LANGUAGES: LazyLock<RwLock<HashMap<String, String>>> = LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Key shared by a profile and the API URLs built for it
+fn account_key(url: &Url, username: &str) -> Option<String> {
+    Some(format!(
+        "{}:{}|{}",
+        url.host_str()?.to_lowercase(),
+        url.port_or_known_default()?,
+        username
+    ))
+}
+
+/// Check and normalize a language tag such as `en`, `pt-BR` or `fra`
+pub fn normalize(language: &str) -> Result<String> {
+    let language = language.trim();
+    let mut parts = language.split(['-', '_']);
+    let primary = parts.next().unwrap_or_default();
+    let valid = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| (2..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !valid {
+        return Err(XTauriError::InvalidSetting {
+            key: "api_language".to_string(),
+            value: language.to_string(),
+        });
+    }
+
+    let mut parts = language.split(['-', '_']);
+    let mut normalized = parts.next().unwrap_or_default().to_lowercase();
+    for part in parts {
+        normalized.push('-');
+        normalized.push_str(&part.to_uppercase());
+    }
+    Ok(normalized)
+}
+
+/// Add the account's language to a `player_api.php` URL that doesn't have one
+pub fn apply(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if !parsed.path().ends_with("player_api.php") || parsed.query_pairs().any(|(key, _)| key == LANGUAGE_PARAM) {
+        return url.to_string();
+    }
+    let Some(username) = parsed.query_pairs().find(|(key, _)| key == "username").map(|(_, value)| value) else {
+        return url.to_string();
+    };
+
+    let language = account_key(&parsed, &username).and_then(|key| {
+        LANGUAGES
+            .read()
+            .ok()
+            .and_then(|languages| languages.get(&key).cloned())
+    });
+    match language {
+        // Normalized tags need no escaping
+        Some(language) => format!("{}&{}={}", url, LANGUAGE_PARAM, language),
+        None => url.to_string(),
+    }
+}
+
+/// Load every profile's language; call again after a profile's URL or username changes
+pub fn load_settings(conn: &Connection) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "SELECT url, username, api_language FROM xtream_profiles WHERE api_language IS NOT NULL",
+    )?;
+    let languages: HashMap<String, String> = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?
+        .filter_map(|row| row.ok())
+        .filter_map(|(url, username, language)| {
+            let key = account_key(&Url::parse(&url).ok()?, &username)?;
+            Some((key, language))
+        })
+        .collect();
+
+    let count = languages.len();
+    *LANGUAGES
+        .write()
+        .map_err(|_| XTauriError::lock_acquisition("API languages"))? = languages;
+    Ok(count)
+}
+
+/// Get the content language a profile requests from its provider
+#[tauri::command]
+pub async fn get_xtream_api_language(
+    state: State<'_, crate::xtream::XtreamState>,
+    profile_id: String,
+) -> std::result::Result<Option<String>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    conn_guard
+        .query_row(
+            "SELECT api_language FROM xtream_profiles WHERE id = ?1",
+            [&profile_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Set the content language a profile requests from its provider; `None` uses the panel default
+///
+/// Returns true when the language changed and the profile has cached content,
+/// whose names stay in the old language until it is synced again.
+#[tauri::command]
+pub async fn set_xtream_api_language(
+    state: State<'_, crate::xtream::XtreamState>,
+    content_cache_state: State<'_, crate::content_cache::ContentCacheState>,
+    profile_id: String,
+    language: Option<String>,
+) -> std::result::Result<bool, String> {
+    let language = language
+        .filter(|language| !language.trim().is_empty())
+        .map(|language| normalize(&language))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    let changed = {
+        let conn = state.profile_manager.get_db_connection();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+        let previous: Option<String> = conn_guard
+            .query_row(
+                "SELECT api_language FROM xtream_profiles WHERE id = ?1",
+                [&profile_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        conn_guard
+            .execute(
+                "UPDATE xtream_profiles SET api_language = ?1 WHERE id = ?2",
+                params![language, profile_id],
+            )
+            .map_err(|e| e.to_string())?;
+        load_settings(&conn_guard).map_err(|e| e.to_string())?;
+        previous != language
+    };
+    if !changed {
+        return Ok(false);
+    }
+
+    // Cached API responses are in the old language
+    state.content_cache.clear_profile_cache(&profile_id).map_err(|e| e.to_string())?;
+    let username = state
+        .profile_manager
+        .get_profile_credentials(&profile_id)
+        .map_err(|e| e.to_string())?
+        .username;
+    state.content_cache.invalidate(&username).map_err(|e| e.to_string())?;
+
+    let (channels, movies, series) = content_cache_state
+        .cache
+        .get_content_counts(&profile_id)
+        .map_err(|e| e.to_string())?;
+    Ok(channels + movies + series > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("EN").unwrap(), "en");
+        assert_eq!(normalize(" pt_br ").unwrap(), "pt-BR");
+        assert_eq!(normalize("fra").unwrap(), "fra");
+        assert!(normalize("english").is_err());
+        assert!(normalize("e").is_err());
+        assert!(normalize("en&x=1").is_err());
+    }
+
+    #[test]
+    fn test_apply_language_to_account_urls() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, url TEXT NOT NULL, username TEXT NOT NULL, api_language TEXT)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles VALUES ('p1', 'http://Panel.example.com/', 'alice', 'de')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles VALUES ('p2', 'http://panel.example.com', 'bob', NULL)",
+            [],
+        )
+        .unwrap();
+        assert_eq!(load_settings(&conn).unwrap(), 1);
+
+        let url = "http://panel.example.com:80/player_api.php?username=alice&password=x&action=get_vod_streams";
+        assert_eq!(apply(url), format!("{}&lang=de", url));
+
+        // Other accounts, other endpoints and URLs that already set a language are left alone
+        let other = "http://panel.example.com/player_api.php?username=bob&password=x&action=get_vod_streams";
+        assert_eq!(apply(other), other);
+        let stream = "http://panel.example.com/movie/alice/x/1.mp4";
+        assert_eq!(apply(stream), stream);
+        let explicit = "http://panel.example.com/player_api.php?username=alice&password=x&lang=fr";
+        assert_eq!(apply(explicit), explicit);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::Result;
+use chrono::DateTime;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+/// Programmes watched for less than this aren't worth resuming
+const MIN_WATCHED_SECS: i64 = 120;
+
+/// Programmes left with less than this to go count as finished
+const MIN_REMAINING_SECS: i64 = 300;
+
+/// A programme on a catch-up channel that was left part way through
+///
+/// Found by matching when a channel was last watched against the programme
+/// airing on it at the time, as long as the provider's archive still has it.
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct ArchiveResume {
+    pub stream_id: i64,
+    pub channel_name: String,
+    pub programme_title: String,
+    pub programme_start: i64,
+    pub programme_stop: i64,
+    /// Seconds into the programme playback stopped at
+    pub position_secs: i64,
+    pub watched_at: i64,
+    /// When the programme drops out of the provider's archive
+    pub available_until: i64,
+    /// Timeshift stream starting at the programme's start; filled in with the profile's credentials
+    pub timeshift_url: Option<String>,
+}
+
+/// Programmes on archive channels that history shows were left unfinished, most recent first
+///
+/// History records when a channel was last playing; the programme airing on it
+/// then is the one to resume. Synthetic guide entries are skipped, since there
+/// is nothing in the archive to resume for them.
+pub fn resume_candidates(conn: &Connection, profile_id: &str, now: i64, limit: usize) -> Result<Vec<ArchiveResume>> {
+    let mut channels = conn.prepare(
+        "SELECT c.stream_id, c.name, c.epg_channel_id, c.tv_archive_duration, h.watched_at
+         FROM xtream_history h
+         JOIN xtream_channels c
+           ON c.profile_id = h.profile_id AND CAST(c.stream_id AS TEXT) = h.content_id
+         WHERE h.profile_id = ?1 AND h.content_type = 'channel'
+           AND c.tv_archive = 1 AND c.epg_channel_id IS NOT NULL
+         ORDER BY h.watched_at DESC",
+    )?;
+    let mut programme = conn.prepare(
+        "SELECT title, start_timestamp, stop_timestamp FROM xtream_epg_programs
+         WHERE profile_id = ?1 AND epg_channel_id = ?2 AND synthetic = 0
+           AND start_timestamp <= ?3 AND stop_timestamp > ?3",
+    )?;
+
+    let rows = channels
+        .query_map([profile_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut entries = Vec::new();
+    for (stream_id, channel_name, epg_channel_id, archive_days, watched_at) in rows {
+        if entries.len() >= limit {
+            break;
+        }
+        let Ok(watched_at) = DateTime::parse_from_rfc3339(&watched_at).map(|at| at.timestamp()) else {
+            continue;
+        };
+        let airing = programme
+            .query_row(params![profile_id, epg_channel_id, watched_at], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?))
+            })
+            .optional()?;
+        let Some((programme_title, programme_start, programme_stop)) = airing else {
+            continue;
+        };
+
+        let position_secs = watched_at - programme_start;
+        let available_until = programme_start + archive_days.unwrap_or(0).max(0) * 86_400;
+        if position_secs < MIN_WATCHED_SECS
+            || programme_stop - watched_at < MIN_REMAINING_SECS
+            || available_until <= now
+        {
+            continue;
+        }
+
+        entries.push(ArchiveResume {
+            stream_id,
+            channel_name,
+            programme_title,
+            programme_start,
+            programme_stop,
+            position_secs,
+            watched_at,
+            available_until,
+            timeshift_url: None,
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_history (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                content_data BLOB NOT NULL,
+                watched_at DATETIME,
+                position REAL,
+                duration REAL
+            );
+            CREATE TABLE xtream_channels (
+                profile_id TEXT NOT NULL,
+                stream_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                epg_channel_id TEXT,
+                tv_archive INTEGER,
+                tv_archive_duration INTEGER
+            );
+            CREATE TABLE xtream_epg_programs (
+                profile_id TEXT NOT NULL,
+                epg_channel_id TEXT NOT NULL,
+                start_timestamp INTEGER NOT NULL,
+                stop_timestamp INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                synthetic BOOLEAN NOT NULL DEFAULT 0
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn watch(conn: &Connection, stream_id: i64, watched_at: i64) {
+        let watched_at = DateTime::from_timestamp(watched_at, 0).unwrap().to_rfc3339();
+        conn.execute(
+            "INSERT INTO xtream_history (id, profile_id, content_type, content_id, content_data, watched_at)
+             VALUES (?1, 'p1', 'channel', ?2, X'7B7D', ?3)",
+            params![format!("h{}", stream_id), stream_id.to_string(), watched_at],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_unfinished_archive_programmes_are_resumable() {
+        let conn = create_test_db();
+        let hour = 3600;
+        let now = 1_700_000_000;
+        conn.execute_batch(
+            "INSERT INTO xtream_channels VALUES ('p1', 1, 'News', 'news', 1, 3);
+             INSERT INTO xtream_channels VALUES ('p1', 2, 'Movies', 'movies', 0, 0);
+             INSERT INTO xtream_channels VALUES ('p1', 3, 'Sport', 'sport', 1, 1);",
+        )
+        .unwrap();
+        for (channel, title, start, synthetic) in [
+            ("news", "Evening News", now - 5 * hour, 0),
+            ("movies", "Feature", now - 5 * hour, 0),
+            ("sport", "Match", now - 30 * hour, 0),
+            ("news", "No information", now - 10 * hour, 1),
+        ] {
+            conn.execute(
+                "INSERT INTO xtream_epg_programs VALUES ('p1', ?1, ?2, ?3, ?4, NULL, ?5)",
+                params![channel, start, start + 2 * hour, title, synthetic],
+            )
+            .unwrap();
+        }
+
+        // Half an hour into the news; the movie channel has no archive and the
+        // match has already dropped out of its one-day archive
+        watch(&conn, 1, now - 5 * hour + 1800);
+        watch(&conn, 2, now - 5 * hour + 1800);
+        watch(&conn, 3, now - 30 * hour + 1800);
+
+        let entries = resume_candidates(&conn, "p1", now, 10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].programme_title, "Evening News");
+        assert_eq!(entries[0].position_secs, 1800);
+        assert_eq!(entries[0].available_until, now - 5 * hour + 3 * 86_400);
+
+        // Left in the last minutes, or during a gap in the guide: nothing to resume
+        conn.execute("DELETE FROM xtream_history", []).unwrap();
+        watch(&conn, 1, now - 3 * hour - 60);
+        assert!(resume_candidates(&conn, "p1", now, 10).unwrap().is_empty());
+        conn.execute("DELETE FROM xtream_history", []).unwrap();
+        watch(&conn, 1, now - 9 * hour);
+        assert!(resume_candidates(&conn, "p1", now, 10).unwrap().is_empty());
+    }
+}
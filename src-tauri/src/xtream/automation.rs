@@ -0,0 +1,820 @@
+use crate::content_cache::ContentCache as ContentStore;
+use crate::error::{Result, XTauriError};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::AppHandle;
+use uuid::Uuid;
+#[cfg(feature = "scripting")]
+use {
+    crate::content_cache::ChannelFilter,
+    crate::xtream::{AddFavoriteRequest, FavoriteIdentity, FavoritesChange, XtreamFavoritesDb, XtreamTagsDb},
+    rhai::{Array, Dynamic, Engine, EvalAltResult, Scope},
+    std::time::Instant,
+};
+
+/// Maximum number of operations a single script run may perform
+#[cfg(feature = "scripting")]
+const MAX_SCRIPT_OPERATIONS: u64 = 5_000_000;
+
+/// Maximum size of the source of a single script
+const MAX_SCRIPT_SOURCE_LEN: usize = 64 * 1024;
+
+/// Events that automation scripts can be attached to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationEvent {
+    AfterSync,
+    PlaybackStart,
+    Manual,
+}
+
+impl AutomationEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutomationEvent::AfterSync => "after_sync",
+            AutomationEvent::PlaybackStart => "playback_start",
+            AutomationEvent::Manual => "manual",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "after_sync" => Ok(AutomationEvent::AfterSync),
+            "playback_start" => Ok(AutomationEvent::PlaybackStart),
+            "manual" => Ok(AutomationEvent::Manual),
+            _ => Err(XTauriError::internal(format!(
+                "Unknown automation event: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// A user automation script stored in the database
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct AutomationScript {
+    pub id: String,
+    pub profile_id: String,
+    pub name: String,
+    pub event: AutomationEvent,
+    pub source: String,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Request to create an automation script
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct CreateAutomationScriptRequest {
+    pub profile_id: String,
+    pub name: String,
+    pub event: AutomationEvent,
+    pub source: String,
+    pub enabled: Option<bool>,
+}
+
+/// Request to update an automation script
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct UpdateAutomationScriptRequest {
+    pub name: Option<String>,
+    pub event: Option<AutomationEvent>,
+    pub source: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+/// Outcome of running a single script
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ScriptRunResult {
+    pub script_id: String,
+    pub success: bool,
+    pub output: Vec<String>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Database operations for automation scripts
+pub struct AutomationScriptsDb;
+
+impl AutomationScriptsDb {
+    pub fn create_script(
+        conn: &Connection,
+        request: &CreateAutomationScriptRequest,
+    ) -> Result<String> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "INSERT INTO xtream_automation_scripts
+             (id, profile_id, name, event, source, enabled, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+            params![
+                id,
+                request.profile_id,
+                request.name,
+                request.event.as_str(),
+                request.source,
+                request.enabled.unwrap_or(true),
+                now,
+            ],
+        )
+        .map_err(|e| {
+            if e.to_string().contains("UNIQUE constraint failed") {
+                XTauriError::internal(format!(
+                    "A script named '{}' already exists",
+                    request.name
+                ))
+            } else {
+                XTauriError::Database(e)
+            }
+        })?;
+
+        Ok(id)
+    }
+
+    pub fn update_script(
+        conn: &Connection,
+        id: &str,
+        request: &UpdateAutomationScriptRequest,
+    ) -> Result<()> {
+        let existing = Self::get_script(conn, id)?
+            .ok_or_else(|| XTauriError::internal("Automation script not found".to_string()))?;
+
+        conn.execute(
+            "UPDATE xtream_automation_scripts
+             SET name = ?1, event = ?2, source = ?3, enabled = ?4, updated_at = ?5
+             WHERE id = ?6",
+            params![
+                request.name.as_ref().unwrap_or(&existing.name),
+                request.event.unwrap_or(existing.event).as_str(),
+                request.source.as_ref().unwrap_or(&existing.source),
+                request.enabled.unwrap_or(existing.enabled),
+                Utc::now().to_rfc3339(),
+                id,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn delete_script(conn: &Connection, id: &str) -> Result<()> {
+        let rows_affected = conn.execute(
+            "DELETE FROM xtream_automation_scripts WHERE id = ?1",
+            params![id],
+        )?;
+
+        if rows_affected == 0 {
+            return Err(XTauriError::internal("Automation script not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub fn get_script(conn: &Connection, id: &str) -> Result<Option<AutomationScript>> {
+        let script = conn
+            .query_row(
+                "SELECT id, profile_id, name, event, source, enabled, last_run_at, last_error, created_at, updated_at
+                 FROM xtream_automation_scripts WHERE id = ?1",
+                params![id],
+                Self::map_row,
+            )
+            .optional()?;
+
+        script.transpose()
+    }
+
+    pub fn get_scripts(conn: &Connection, profile_id: &str) -> Result<Vec<AutomationScript>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, name, event, source, enabled, last_run_at, last_error, created_at, updated_at
+             FROM xtream_automation_scripts
+             WHERE profile_id = ?1
+             ORDER BY name",
+        )?;
+
+        let rows = stmt
+            .query_map(params![profile_id], Self::map_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        rows.into_iter().collect()
+    }
+
+    /// Get enabled scripts attached to an event for a profile
+    pub fn get_scripts_for_event(
+        conn: &Connection,
+        profile_id: &str,
+        event: AutomationEvent,
+    ) -> Result<Vec<AutomationScript>> {
+        Ok(Self::get_scripts(conn, profile_id)?
+            .into_iter()
+            .filter(|script| script.enabled && script.event == event)
+            .collect())
+    }
+
+    pub fn record_run(conn: &Connection, id: &str, error: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE xtream_automation_scripts SET last_run_at = ?1, last_error = ?2 WHERE id = ?3",
+            params![Utc::now().to_rfc3339(), error, id],
+        )?;
+        Ok(())
+    }
+
+    fn map_row(row: &rusqlite::Row) -> rusqlite::Result<Result<AutomationScript>> {
+        let event: String = row.get(3)?;
+        let script = AutomationScript {
+            id: row.get(0)?,
+            profile_id: row.get(1)?,
+            name: row.get(2)?,
+            event: AutomationEvent::Manual,
+            source: row.get(4)?,
+            enabled: row.get(5)?,
+            last_run_at: row.get(6)?,
+            last_error: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+        };
+
+        Ok(AutomationEvent::parse(&event).map(|event| AutomationScript { event, ..script }))
+    }
+}
+
+/// State wrapper for the automation engine
+pub struct AutomationState {
+    pub engine: Arc<AutomationEngine>,
+}
+
+impl AutomationState {
+    pub fn new(engine: AutomationEngine) -> Self {
+        Self {
+            engine: Arc::new(engine),
+        }
+    }
+
+    /// Run hooks for an event in the background, logging failures
+    pub fn spawn_hooks(&self, profile_id: String, event: AutomationEvent, event_data: serde_json::Value) {
+        spawn_hooks(Arc::clone(&self.engine), profile_id, event, event_data);
+    }
+}
+
+/// Run hooks for an event on the blocking pool so scripts never stall the async runtime
+pub fn spawn_hooks(
+    engine: Arc<AutomationEngine>,
+    profile_id: String,
+    event: AutomationEvent,
+    event_data: serde_json::Value,
+) {
+    let task = crate::tasks::register(
+        crate::tasks::TaskKind::Automation,
+        format!("Running {} hooks", event.as_str()),
+        Some(&profile_id),
+    );
+    tokio::task::spawn_blocking(move || {
+        let _task = task;
+        match engine.run_hooks(&profile_id, event, event_data) {
+            Ok(results) => {
+                for result in results.iter().filter(|result| !result.success) {
+                    eprintln!(
+                        "[AUTOMATION] Script {} failed on {}: {}",
+                        result.script_id,
+                        event.as_str(),
+                        result.error.as_deref().unwrap_or("unknown error")
+                    );
+                }
+            }
+            Err(e) => eprintln!("[AUTOMATION] Failed to run {} hooks: {}", event.as_str(), e),
+        }
+    });
+}
+
+/// Runs user scripts in a sandboxed Rhai engine
+///
+/// Scripts only see the functions registered here: content search, favorites and tags for
+/// the profile the script belongs to. There is no file system, network or process access,
+/// and every run is bounded by an operation budget.
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+pub struct AutomationEngine {
+    db: Arc<Mutex<Connection>>,
+    content: Arc<ContentStore>,
+    /// Used to announce favorites that scripts change; unset in tests
+    app_handle: Option<AppHandle>,
+}
+
+impl AutomationEngine {
+    pub fn new(db: Arc<Mutex<Connection>>, content: Arc<ContentStore>) -> Self {
+        Self {
+            db,
+            content,
+            app_handle: None,
+        }
+    }
+
+    /// Emit `favorites_changed` events for favorites that scripts add or remove
+    pub fn with_app_handle(mut self, app_handle: AppHandle) -> Self {
+        self.app_handle = Some(app_handle);
+        self
+    }
+
+    pub fn get_db(&self) -> Arc<Mutex<Connection>> {
+        Arc::clone(&self.db)
+    }
+
+    /// Check that a script compiles without running it
+    pub fn validate_source(source: &str) -> Result<()> {
+        if source.len() > MAX_SCRIPT_SOURCE_LEN {
+            return Err(XTauriError::internal(format!(
+                "Script is too large (max {} bytes)",
+                MAX_SCRIPT_SOURCE_LEN
+            )));
+        }
+
+        #[cfg(feature = "scripting")]
+        return Self::sandboxed_engine()
+            .compile(source)
+            .map(|_| ())
+            .map_err(|e| XTauriError::internal(format!("Script does not compile: {}", e)));
+        #[cfg(not(feature = "scripting"))]
+        Err(scripting_unavailable())
+    }
+
+    /// Run every enabled script attached to `event` for a profile
+    pub fn run_hooks(
+        &self,
+        profile_id: &str,
+        event: AutomationEvent,
+        event_data: serde_json::Value,
+    ) -> Result<Vec<ScriptRunResult>> {
+        let scripts = {
+            let conn = self
+                .db
+                .lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            AutomationScriptsDb::get_scripts_for_event(&conn, profile_id, event)?
+        };
+
+        let mut results = Vec::with_capacity(scripts.len());
+        for script in scripts {
+            results.push(self.run_script(&script, event, event_data.clone())?);
+        }
+
+        Ok(results)
+    }
+
+    /// Scripts can't run in a build without the `scripting` feature
+    #[cfg(not(feature = "scripting"))]
+    pub fn run_script(
+        &self,
+        _script: &AutomationScript,
+        _event: AutomationEvent,
+        _event_data: serde_json::Value,
+    ) -> Result<ScriptRunResult> {
+        Err(scripting_unavailable())
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+fn scripting_unavailable() -> XTauriError {
+    XTauriError::internal("Automation scripts are not included in this build")
+}
+
+#[cfg(feature = "scripting")]
+impl AutomationEngine {
+    /// Run a single script and record the outcome
+    pub fn run_script(
+        &self,
+        script: &AutomationScript,
+        event: AutomationEvent,
+        event_data: serde_json::Value,
+    ) -> Result<ScriptRunResult> {
+        let start = Instant::now();
+        let output = Arc::new(Mutex::new(Vec::new()));
+
+        let mut engine = Self::sandboxed_engine();
+        self.register_api(&mut engine, &script.profile_id, Arc::clone(&output));
+
+        let mut scope = Scope::new();
+        scope.push_constant("profile_id", script.profile_id.clone());
+        scope.push_constant("event_name", event.as_str().to_string());
+        let event_data = rhai::serde::to_dynamic(&event_data).unwrap_or(Dynamic::UNIT);
+        scope.push_constant("event", event_data);
+
+        let error = engine
+            .run_with_scope(&mut scope, &script.source)
+            .err()
+            .map(|e| e.to_string());
+
+        {
+            let conn = self
+                .db
+                .lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            AutomationScriptsDb::record_run(&conn, &script.id, error.as_deref())?;
+        }
+
+        let output = output.lock().map(|lines| lines.clone()).unwrap_or_default();
+
+        Ok(ScriptRunResult {
+            script_id: script.id.clone(),
+            success: error.is_none(),
+            output,
+            error,
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    fn sandboxed_engine() -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(64 * 1024);
+        engine.set_max_array_size(100_000);
+        engine.set_max_map_size(10_000);
+        engine.disable_symbol("eval");
+        engine
+    }
+
+    fn register_api(&self, engine: &mut Engine, profile_id: &str, output: Arc<Mutex<Vec<String>>>) {
+        let log = Arc::clone(&output);
+        engine.on_print(move |message| {
+            if let Ok(mut lines) = log.lock() {
+                lines.push(message.to_string());
+            }
+        });
+        let log = Arc::clone(&output);
+        engine.on_debug(move |message, _, _| {
+            if let Ok(mut lines) = log.lock() {
+                lines.push(message.to_string());
+            }
+        });
+
+        let content = Arc::clone(&self.content);
+        let profile = profile_id.to_string();
+        engine.register_fn(
+            "search_channels",
+            move |query: &str| -> std::result::Result<Array, Box<EvalAltResult>> {
+                let channels = content
+                    .search_channels(&profile, query, Some(ChannelFilter::default()))
+                    .map_err(|e| e.to_string())?;
+                to_array(&channels)
+            },
+        );
+
+        let content = Arc::clone(&self.content);
+        let profile = profile_id.to_string();
+        engine.register_fn(
+            "search_movies",
+            move |query: &str| -> std::result::Result<Array, Box<EvalAltResult>> {
+                let movies = content
+                    .search_movies(&profile, query, None, None, None)
+                    .map_err(|e| e.to_string())?;
+                to_array(&movies)
+            },
+        );
+
+        let content = Arc::clone(&self.content);
+        let profile = profile_id.to_string();
+        engine.register_fn(
+            "search_series",
+            move |query: &str| -> std::result::Result<Array, Box<EvalAltResult>> {
+                let series = content
+                    .fts_search_series(&profile, query, None)
+                    .map_err(|e| e.to_string())?;
+                to_array(&series)
+            },
+        );
+
+        let db = Arc::clone(&self.db);
+        let profile = profile_id.to_string();
+        let app_handle = self.app_handle.clone();
+        engine.register_fn(
+            "add_favorite",
+            move |content_type: &str,
+                  content_id: &str,
+                  data: Dynamic|
+                  -> std::result::Result<bool, Box<EvalAltResult>> {
+                let content_data: serde_json::Value =
+                    rhai::serde::from_dynamic(&data).unwrap_or(serde_json::Value::Null);
+                let conn = db.lock().map_err(|_| "Failed to lock database")?;
+                if XtreamFavoritesDb::is_favorite(&conn, &profile, content_type, content_id)
+                    .map_err(|e| e.to_string())?
+                {
+                    return Ok(false);
+                }
+                XtreamFavoritesDb::add_favorite(
+                    &conn,
+                    &AddFavoriteRequest {
+                        profile_id: profile.clone(),
+                        content_type: content_type.to_string(),
+                        content_id: content_id.to_string(),
+                        content_data,
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+                if let Some(app_handle) = &app_handle {
+                    FavoritesChange::added(&profile, FavoriteIdentity::new(content_type, content_id))
+                        .emit(app_handle);
+                }
+                Ok(true)
+            },
+        );
+
+        let db = Arc::clone(&self.db);
+        let profile = profile_id.to_string();
+        let app_handle = self.app_handle.clone();
+        engine.register_fn(
+            "remove_favorite",
+            move |content_type: &str,
+                  content_id: &str|
+                  -> std::result::Result<bool, Box<EvalAltResult>> {
+                let conn = db.lock().map_err(|_| "Failed to lock database")?;
+                let removed =
+                    XtreamFavoritesDb::remove_favorite_by_content(&conn, &profile, content_type, content_id)
+                        .is_ok();
+                if let (true, Some(app_handle)) = (removed, &app_handle) {
+                    FavoritesChange::removed(&profile, vec![FavoriteIdentity::new(content_type, content_id)])
+                        .emit(app_handle);
+                }
+                Ok(removed)
+            },
+        );
+
+        let db = Arc::clone(&self.db);
+        let profile = profile_id.to_string();
+        engine.register_fn(
+            "set_tag",
+            move |content_type: &str,
+                  content_id: &str,
+                  tag: &str|
+                  -> std::result::Result<(), Box<EvalAltResult>> {
+                let conn = db.lock().map_err(|_| "Failed to lock database")?;
+                XtreamTagsDb::add_tag(&conn, &profile, content_type, content_id, tag)
+                    .map_err(|e| e.to_string().into())
+            },
+        );
+
+        let db = Arc::clone(&self.db);
+        let profile = profile_id.to_string();
+        engine.register_fn(
+            "remove_tag",
+            move |content_type: &str,
+                  content_id: &str,
+                  tag: &str|
+                  -> std::result::Result<(), Box<EvalAltResult>> {
+                let conn = db.lock().map_err(|_| "Failed to lock database")?;
+                XtreamTagsDb::remove_tag(&conn, &profile, content_type, content_id, tag)
+                    .map_err(|e| e.to_string().into())
+            },
+        );
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn to_array<T: Serialize>(items: &[T]) -> std::result::Result<Array, Box<EvalAltResult>> {
+    items.iter().map(rhai::serde::to_dynamic).collect()
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+    use crate::content_cache::XtreamChannel;
+    use crate::xtream::tags::HIDDEN_TAG;
+
+    fn create_test_engine() -> AutomationEngine {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_used DATETIME,
+                is_active BOOLEAN DEFAULT FALSE
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_favorites (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                content_data BLOB NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(profile_id, content_type, content_id)
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_content_tags (
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (profile_id, content_type, content_id, tag)
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_automation_scripts (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                event TEXT NOT NULL,
+                source TEXT NOT NULL,
+                enabled BOOLEAN NOT NULL DEFAULT 1,
+                last_run_at DATETIME,
+                last_error TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                UNIQUE(profile_id, name)
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('profile-1', 'Test', 'http://example.com', 'user', X'00')",
+            [],
+        )
+        .unwrap();
+
+        let db = Arc::new(Mutex::new(conn));
+        let content = Arc::new(ContentStore::new(Arc::clone(&db)).unwrap());
+        content
+            .save_channels(
+                "profile-1",
+                vec![test_channel(1, "Shopping Channel"), test_channel(2, "News 24")],
+            )
+            .unwrap();
+
+        AutomationEngine::new(db, content)
+    }
+
+    fn test_channel(stream_id: i64, name: &str) -> XtreamChannel {
+        XtreamChannel {
+            stream_id,
+            num: Some(stream_id),
+            name: name.to_string(),
+            stream_type: Some("live".to_string()),
+            stream_icon: None,
+            thumbnail: None,
+            epg_channel_id: None,
+            added: None,
+            category_id: Some("1".to_string()),
+            custom_sid: None,
+            tv_archive: None,
+            direct_source: None,
+            tv_archive_duration: None,
+            url_overridden: false,
+            country: None,
+            country_flag: None,
+            reliability: None,
+        }
+    }
+
+    fn create_script(engine: &AutomationEngine, event: AutomationEvent, source: &str) -> String {
+        let conn = engine.db.lock().unwrap();
+        AutomationScriptsDb::create_script(
+            &conn,
+            &CreateAutomationScriptRequest {
+                profile_id: "profile-1".to_string(),
+                name: format!("script-{}", Uuid::new_v4()),
+                event,
+                source: source.to_string(),
+                enabled: None,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_after_sync_hook_hides_matching_channels() {
+        let engine = create_test_engine();
+        create_script(
+            &engine,
+            AutomationEvent::AfterSync,
+            r#"
+                for ch in search_channels("shopping") {
+                    set_tag("channel", ch.stream_id.to_string(), "hidden");
+                    print("hid " + ch.name);
+                }
+            "#,
+        );
+
+        let results = engine
+            .run_hooks("profile-1", AutomationEvent::AfterSync, serde_json::json!({}))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success, "{:?}", results[0].error);
+        assert_eq!(results[0].output, vec!["hid Shopping Channel".to_string()]);
+
+        let conn = engine.db.lock().unwrap();
+        let hidden = XtreamTagsDb::get_content_by_tag(&conn, "profile-1", HIDDEN_TAG, None).unwrap();
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].content_id, "1");
+    }
+
+    #[test]
+    fn test_hooks_only_run_for_matching_event() {
+        let engine = create_test_engine();
+        create_script(&engine, AutomationEvent::PlaybackStart, "print(event.content_id);");
+
+        let results = engine
+            .run_hooks("profile-1", AutomationEvent::AfterSync, serde_json::json!({}))
+            .unwrap();
+        assert!(results.is_empty());
+
+        let results = engine
+            .run_hooks(
+                "profile-1",
+                AutomationEvent::PlaybackStart,
+                serde_json::json!({ "content_id": "42" }),
+            )
+            .unwrap();
+        assert_eq!(results[0].output, vec!["42".to_string()]);
+    }
+
+    #[test]
+    fn test_script_can_add_favorites() {
+        let engine = create_test_engine();
+        create_script(
+            &engine,
+            AutomationEvent::Manual,
+            r#"
+                for ch in search_channels("news") {
+                    add_favorite("channel", ch.stream_id.to_string(), ch);
+                }
+            "#,
+        );
+
+        engine
+            .run_hooks("profile-1", AutomationEvent::Manual, serde_json::json!({}))
+            .unwrap();
+
+        let conn = engine.db.lock().unwrap();
+        assert!(XtreamFavoritesDb::is_favorite(&conn, "profile-1", "channel", "2").unwrap());
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped_and_recorded() {
+        let engine = create_test_engine();
+        let id = create_script(&engine, AutomationEvent::Manual, "loop { }");
+
+        let results = engine
+            .run_hooks("profile-1", AutomationEvent::Manual, serde_json::json!({}))
+            .unwrap();
+        assert!(!results[0].success);
+
+        let conn = engine.db.lock().unwrap();
+        let script = AutomationScriptsDb::get_script(&conn, &id).unwrap().unwrap();
+        assert!(script.last_error.is_some());
+        assert!(script.last_run_at.is_some());
+    }
+
+    #[test]
+    fn test_validate_source() {
+        assert!(AutomationEngine::validate_source("let x = 1 + 2;").is_ok());
+        assert!(AutomationEngine::validate_source("let x = ;").is_err());
+        assert!(AutomationEngine::validate_source("eval(\"1\")").is_err());
+    }
+
+    #[test]
+    fn test_update_and_delete_script() {
+        let engine = create_test_engine();
+        let id = create_script(&engine, AutomationEvent::Manual, "print(1);");
+
+        let conn = engine.db.lock().unwrap();
+        AutomationScriptsDb::update_script(
+            &conn,
+            &id,
+            &UpdateAutomationScriptRequest {
+                name: None,
+                event: Some(AutomationEvent::AfterSync),
+                source: None,
+                enabled: Some(false),
+            },
+        )
+        .unwrap();
+
+        let script = AutomationScriptsDb::get_script(&conn, &id).unwrap().unwrap();
+        assert_eq!(script.event, AutomationEvent::AfterSync);
+        assert!(!script.enabled);
+        assert!(
+            AutomationScriptsDb::get_scripts_for_event(&conn, "profile-1", AutomationEvent::AfterSync)
+                .unwrap()
+                .is_empty()
+        );
+
+        AutomationScriptsDb::delete_script(&conn, &id).unwrap();
+        assert!(AutomationScriptsDb::get_script(&conn, &id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::Result;
+use crate::xtream::history::{CONTINUE_WATCHING_MAX_PROGRESS, EPISODE_CONTENT_TYPE};
+use crate::xtream::year_review::{hours, split_genres};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Unwatched titles of one genre
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct BacklogGenre {
+    pub name: String,
+    pub movies: usize,
+    pub episodes: usize,
+    pub hours: f64,
+}
+
+/// What a profile still has to watch among its favorite movies and followed series
+///
+/// A title counts as watched once it was marked watched or played past
+/// `CONTINUE_WATCHING_MAX_PROGRESS`; one left part way through counts with the
+/// time that is left.
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct BacklogStats {
+    pub profile_id: String,
+    pub movies: usize,
+    pub episodes: usize,
+    /// Followed series with unwatched episodes
+    pub series: usize,
+    /// Time left in the unwatched titles, from their runtimes
+    pub hours: f64,
+    /// Unwatched titles with no known runtime, left out of `hours`
+    pub without_runtime: usize,
+    /// Followed series whose episodes aren't cached yet, so they can't be counted
+    pub series_without_episodes: usize,
+    /// Most hours first
+    pub genres: Vec<BacklogGenre>,
+}
+
+/// An unwatched movie or episode
+#[derive(Debug, Clone)]
+struct BacklogEntry {
+    /// Series of an episode, `None` for a movie
+    series_id: Option<i64>,
+    genres: Vec<String>,
+    /// Time left, when the runtime is known
+    seconds: Option<f64>,
+}
+
+/// Playback state of a title from its history entry
+struct Playback {
+    watched: bool,
+    position: Option<f64>,
+    duration: Option<f64>,
+}
+
+impl Playback {
+    fn is_watched(&self) -> bool {
+        self.watched
+            || matches!((self.position, self.duration), (Some(position), Some(duration))
+                if duration > 0.0 && position / duration >= CONTINUE_WATCHING_MAX_PROGRESS)
+    }
+
+    /// Time left of a title with a runtime from the catalog, falling back to the one seen in playback
+    fn remaining(&self, runtime: Option<f64>) -> Option<f64> {
+        let runtime = runtime.filter(|secs| *secs > 0.0).or(self.duration.filter(|secs| *secs > 0.0))?;
+        Some((runtime - self.position.unwrap_or(0.0)).max(0.0))
+    }
+}
+
+/// Genres of a favorite, from the catalog or else from the data saved with it
+fn entry_genres(catalog: Option<String>, content_data: &[u8]) -> Vec<String> {
+    catalog.filter(|genre| !genre.trim().is_empty()).map(|genre| split_genres(&genre)).unwrap_or_else(|| {
+        serde_json::from_slice::<serde_json::Value>(content_data)
+            .ok()
+            .and_then(|data| data.get("genre")?.as_str().map(split_genres))
+            .unwrap_or_default()
+    })
+}
+
+fn unwatched_movies(conn: &Connection, profile_id: &str) -> Result<Vec<BacklogEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.content_data, m.genre, m.episode_run_time * 60,
+                COALESCE(h.watched, FALSE), h.position, h.duration
+         FROM xtream_favorites f
+         LEFT JOIN xtream_movies m
+           ON m.profile_id = f.profile_id AND m.stream_id = CAST(f.content_id AS INTEGER)
+         LEFT JOIN xtream_history h
+           ON h.profile_id = f.profile_id AND h.content_type = 'movie' AND h.content_id = f.content_id
+         WHERE f.profile_id = ?1 AND f.content_type = 'movie'",
+    )?;
+
+    let rows = stmt.query_map(params![profile_id], |row| {
+        let content_data: Vec<u8> = row.get(0)?;
+        let playback = Playback { watched: row.get(3)?, position: row.get(4)?, duration: row.get(5)? };
+        Ok((entry_genres(row.get(1)?, &content_data), row.get::<_, Option<f64>>(2)?, playback))
+    })?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let (genres, runtime, playback) = row?;
+        if !playback.is_watched() {
+            entries.push(BacklogEntry { series_id: None, genres, seconds: playback.remaining(runtime) });
+        }
+    }
+    Ok(entries)
+}
+
+/// Unwatched episodes of followed series, and the number of those series with no cached episodes
+fn unwatched_episodes(conn: &Connection, profile_id: &str) -> Result<(Vec<BacklogEntry>, usize)> {
+    let mut stmt = conn.prepare(
+        "SELECT CAST(f.content_id AS INTEGER), f.content_data, s.genre, s.episode_run_time, e.episode_id,
+                json_extract(e.info_json, '$.duration_secs'),
+                COALESCE(h.watched, FALSE), h.position, h.duration
+         FROM xtream_favorites f
+         LEFT JOIN xtream_series s
+           ON s.profile_id = f.profile_id AND s.series_id = CAST(f.content_id AS INTEGER)
+         LEFT JOIN xtream_episodes e
+           ON e.profile_id = f.profile_id AND e.series_id = CAST(f.content_id AS INTEGER)
+         LEFT JOIN xtream_history h
+           ON h.profile_id = f.profile_id AND h.content_type = ?2 AND h.content_id = e.episode_id
+         WHERE f.profile_id = ?1 AND f.content_type = 'series'",
+    )?;
+
+    let rows = stmt.query_map(params![profile_id, EPISODE_CONTENT_TYPE], |row| {
+        let content_data: Vec<u8> = row.get(1)?;
+        // The series' runtime is a free-form text field in minutes
+        let series_runtime = row
+            .get::<_, Option<String>>(3)?
+            .and_then(|minutes| minutes.trim().parse::<f64>().ok())
+            .map(|minutes| minutes * 60.0);
+        let episode_runtime: Option<f64> = row.get(5)?;
+        let playback = Playback { watched: row.get(6)?, position: row.get(7)?, duration: row.get(8)? };
+        Ok((
+            row.get::<_, i64>(0)?,
+            entry_genres(row.get(2)?, &content_data),
+            row.get::<_, Option<String>>(4)?,
+            episode_runtime.or(series_runtime),
+            playback,
+        ))
+    })?;
+
+    let mut entries = Vec::new();
+    let mut without_episodes = 0;
+    for row in rows {
+        let (series_id, genres, episode_id, runtime, playback) = row?;
+        if episode_id.is_none() {
+            without_episodes += 1;
+        } else if !playback.is_watched() {
+            entries.push(BacklogEntry { series_id: Some(series_id), genres, seconds: playback.remaining(runtime) });
+        }
+    }
+    Ok((entries, without_episodes))
+}
+
+fn summarize(profile_id: &str, entries: &[BacklogEntry], series_without_episodes: usize) -> BacklogStats {
+    let mut genres: HashMap<&str, (usize, usize, f64)> = HashMap::new();
+    for entry in entries {
+        for genre in &entry.genres {
+            let total = genres.entry(genre).or_default();
+            match entry.series_id {
+                Some(_) => total.1 += 1,
+                None => total.0 += 1,
+            }
+            total.2 += entry.seconds.unwrap_or(0.0);
+        }
+    }
+    let mut genres: Vec<(&str, (usize, usize, f64))> = genres.into_iter().collect();
+    genres.sort_by(|a, b| b.1 .2.total_cmp(&a.1 .2).then_with(|| a.0.cmp(b.0)));
+
+    BacklogStats {
+        profile_id: profile_id.to_string(),
+        movies: entries.iter().filter(|entry| entry.series_id.is_none()).count(),
+        episodes: entries.iter().filter(|entry| entry.series_id.is_some()).count(),
+        series: entries.iter().filter_map(|entry| entry.series_id).collect::<HashSet<_>>().len(),
+        hours: hours(entries.iter().filter_map(|entry| entry.seconds).sum()),
+        without_runtime: entries.iter().filter(|entry| entry.seconds.is_none()).count(),
+        series_without_episodes,
+        genres: genres
+            .into_iter()
+            .map(|(name, (movies, episodes, seconds))| BacklogGenre {
+                name: name.to_string(),
+                movies,
+                episodes,
+                hours: hours(seconds),
+            })
+            .collect(),
+    }
+}
+
+/// Count what a profile still has to watch among its favorites, from local tables only
+pub fn backlog_stats(conn: &Connection, profile_id: &str) -> Result<BacklogStats> {
+    let mut entries = unwatched_movies(conn, profile_id)?;
+    let (episodes, series_without_episodes) = unwatched_episodes(conn, profile_id)?;
+    entries.extend(episodes);
+    Ok(summarize(profile_id, &entries, series_without_episodes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backlog_counts_unwatched_favorites() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_favorites (profile_id TEXT, content_type TEXT, content_id TEXT, content_data BLOB);
+             CREATE TABLE xtream_movies (profile_id TEXT, stream_id INTEGER, genre TEXT, episode_run_time INTEGER);
+             CREATE TABLE xtream_series (profile_id TEXT, series_id INTEGER, genre TEXT, episode_run_time TEXT);
+             CREATE TABLE xtream_episodes (profile_id TEXT, series_id INTEGER, episode_id TEXT, info_json TEXT);
+             CREATE TABLE xtream_history (
+                 profile_id TEXT, content_type TEXT, content_id TEXT, watched BOOLEAN, position REAL, duration REAL
+             );
+             INSERT INTO xtream_favorites VALUES
+                 ('p1', 'movie', '1', x''), ('p1', 'movie', '2', x''), ('p1', 'movie', '3', x''),
+                 ('p1', 'movie', '4', CAST('{\"genre\": \"Comedy\"}' AS BLOB)),
+                 ('p1', 'series', '9', x''), ('p1', 'series', '10', x''), ('p2', 'movie', '5', x'');
+             INSERT INTO xtream_movies VALUES
+                 ('p1', 1, 'Drama, Thriller', 120), ('p1', 2, 'Drama', 90), ('p1', 3, 'Drama', 100), ('p2', 5, 'Drama', 90);
+             INSERT INTO xtream_series VALUES ('p1', 9, 'Drama', '45');
+             INSERT INTO xtream_episodes VALUES
+                 ('p1', 9, '901', '{\"duration_secs\": 1800}'), ('p1', 9, '902', NULL), ('p1', 9, '903', NULL);
+             INSERT INTO xtream_history VALUES
+                 ('p1', 'movie', '2', FALSE, 5300, 5400),
+                 ('p1', 'movie', '3', FALSE, 3000, 6000),
+                 ('p1', 'episode', '903', TRUE, NULL, NULL);",
+        )
+        .unwrap();
+
+        let stats = backlog_stats(&conn, "p1").unwrap();
+        // Movie 2 is played to the end and episode 903 marked watched
+        assert_eq!((stats.movies, stats.episodes, stats.series), (3, 2, 1));
+        // 2h of movie 1, the 50 minutes left of movie 3, 30 and 45 minute episodes
+        assert_eq!(stats.hours, 4.1);
+        // Movie 4 isn't in the catalog and series 10 has no episodes yet
+        assert_eq!(stats.without_runtime, 1);
+        assert_eq!(stats.series_without_episodes, 1);
+
+        assert_eq!(
+            stats.genres[0],
+            BacklogGenre { name: "Drama".to_string(), movies: 2, episodes: 2, hours: 4.1 }
+        );
+        assert_eq!(stats.genres[1].name, "Thriller");
+        assert_eq!(stats.genres[2], BacklogGenre { name: "Comedy".to_string(), movies: 1, episodes: 0, hours: 0.0 });
+    }
+}
@@ -1,12 +1,21 @@
+use crate::concurrency::{self, Subsystem};
+use crate::content_cache::epg;
+use crate::content_cache::db_utils::with_busy_retry;
 use crate::error::XTauriError;
+use crate::pagination::Page;
 use crate::xtream::{
     ProfileManager, XtreamClient, ContentCache, ProfileCredentials, 
     CreateProfileRequest, UpdateProfileRequest, StreamURLRequest,
-    XtreamProfile, AuthenticationResult, AuthenticationErrorType
+    XtreamProfile, AuthenticationResult, AuthenticationErrorType, ConnectionTestResult,
+    ResolvedStreamUrl, EpgBatchResult
 };
+use crate::xtream::archive_resume::{resume_candidates, ArchiveResume};
+use crate::xtream::heartbeat;
+use crate::xtream::profile_import::{self, ProfileImportReport};
+use crate::xtream::xtream_client::{DEFAULT_EPG_CHUNK_CONCURRENCY, DEFAULT_EPG_CHUNK_SIZE};
 use serde_json::Value;
 use std::sync::Arc;
-use tauri::State;
+use tauri::{AppHandle, State};
 
 /// State for managing Xtream profiles and clients
 pub struct XtreamState {
@@ -36,6 +45,25 @@ pub async fn create_xtream_profile(
         .map_err(|e| e.to_string())
 }
 
+/// Create Xtream profiles from a CSV file of name,url,username,password rows
+///
+/// With `authenticate` set each row's credentials are checked against its server first.
+/// Returns what happened to every row.
+#[tauri::command]
+pub async fn import_xtream_profiles_csv(
+    state: State<'_, XtreamState>,
+    path: String,
+    authenticate: Option<bool>,
+) -> Result<ProfileImportReport, String> {
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+
+    profile_import::import_csv(&state.profile_manager, &content, authenticate.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// Update an existing Xtream profile
 #[tauri::command]
 pub async fn update_xtream_profile(
@@ -50,6 +78,63 @@ pub async fn update_xtream_profile(
         .map_err(|e| e.to_string())
 }
 
+/// Rotate the credentials of an Xtream profile
+///
+/// The new credentials are re-authenticated before being saved. The profile and
+/// every stream URL derived from the old credentials, in the content cache,
+/// favorites and history, are updated in one transaction; cached API responses
+/// are then invalidated.
+#[tauri::command]
+pub async fn update_xtream_profile_credentials(
+    state: State<'_, XtreamState>,
+    id: String,
+    username: Option<String>,
+    password: String,
+) -> Result<(), String> {
+    state
+        .profile_manager
+        .update_profile_credentials(&id, username, password)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    // Cached API responses were fetched with the old credentials
+    state.content_cache.clear_profile_cache(&id).map_err(|e| e.to_string())?;
+    reload_api_languages(&state)?;
+    
+    Ok(())
+}
+
+/// Languages are looked up by server and username, so reload them when either changes
+fn reload_api_languages(state: &State<'_, XtreamState>) -> Result<(), String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    crate::xtream::api_language::load_settings(&conn_guard).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Move an Xtream profile to a new provider base URL
+///
+/// Authenticates against the new URL, then rewrites the URLs stored in the content
+/// cache, favorites and history, in the same transaction as the profile, so they
+/// keep working without a full resync.
+#[tauri::command]
+pub async fn change_xtream_profile_url(
+    state: State<'_, XtreamState>,
+    id: String,
+    url: String,
+) -> Result<(), String> {
+    state
+        .profile_manager
+        .change_profile_url(&id, &url)
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    state.content_cache.clear_profile_cache(&id).map_err(|e| e.to_string())?;
+    reload_api_languages(&state)?;
+    
+    Ok(())
+}
+
 /// Delete an Xtream profile
 #[tauri::command]
 pub async fn delete_xtream_profile(
@@ -68,11 +153,17 @@ pub async fn delete_xtream_profile(
 pub async fn get_xtream_profiles(
     state: State<'_, XtreamState>,
 ) -> Result<Vec<XtreamProfile>, String> {
-    state
+    let profiles = state
         .profile_manager
         .get_profiles_async_wrapper()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    
+    if crate::anonymize::is_demo_mode() {
+        return Ok(profiles.into_iter().map(crate::anonymize::mask_profile).collect());
+    }
+    
+    Ok(profiles)
 }
 
 /// Get a specific Xtream profile by ID
@@ -81,11 +172,17 @@ pub async fn get_xtream_profile(
     state: State<'_, XtreamState>,
     id: String,
 ) -> Result<Option<XtreamProfile>, String> {
-    state
+    let profile = state
         .profile_manager
         .get_profile_async_wrapper(&id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    
+    if crate::anonymize::is_demo_mode() {
+        return Ok(profile.map(crate::anonymize::mask_profile));
+    }
+    
+    Ok(profile)
 }
 
 /// Validate Xtream profile credentials
@@ -108,11 +205,16 @@ pub async fn validate_xtream_credentials(
     };
 
     match client.authenticate().await {
-        Ok(profile_data) => Ok(AuthenticationResult {
+        Ok(mut profile_data) => Ok(AuthenticationResult {
             success: true,
             error_message: None,
             error_type: AuthenticationErrorType::None,
-            server_info: Some(profile_data),
+            server_info: Some({
+                if crate::anonymize::is_demo_mode() {
+                    crate::anonymize::mask_json(&mut profile_data);
+                }
+                profile_data
+            }),
         }),
         Err(e) => {
             let error_type = match &e {
@@ -140,6 +242,32 @@ pub async fn validate_xtream_credentials(
     }
 }
 
+/// Test connectivity to an Xtream server
+///
+/// Reports how the host resolves and which of its addresses accept connections
+/// before attempting authentication, so broken DNS records can be told apart from
+/// bad credentials. DNS diagnostics are omitted when the URL can't be parsed.
+#[tauri::command]
+pub async fn test_xtream_connection(
+    state: State<'_, XtreamState>,
+    credentials: ProfileCredentials,
+) -> Result<ConnectionTestResult, String> {
+    let dns = crate::connection::diagnose(&credentials.url)
+        .await
+        .ok()
+        .map(|dns| {
+            if crate::anonymize::is_demo_mode() {
+                dns.redacted()
+            } else {
+                dns
+            }
+        });
+
+    let authentication = validate_xtream_credentials(state, credentials).await?;
+
+    Ok(ConnectionTestResult { dns, authentication })
+}
+
 /// Authenticate with Xtream server and get profile information
 #[tauri::command]
 pub async fn authenticate_xtream_profile(
@@ -163,9 +291,10 @@ pub async fn authenticate_xtream_profile(
 
     // Create client and authenticate
     let client = XtreamClient::new(credentials, state.content_cache.clone())
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())?
+        .for_profile(&profile_id);
 
-    let profile_data = client.authenticate().await.map_err(|e| e.to_string())?;
+    let mut profile_data = client.authenticate().await.map_err(|e| e.to_string())?;
 
     // Update last used timestamp
     state
@@ -174,6 +303,10 @@ pub async fn authenticate_xtream_profile(
         .await
         .map_err(|e| e.to_string())?;
 
+    if crate::anonymize::is_demo_mode() {
+        crate::anonymize::mask_json(&mut profile_data);
+    }
+
     Ok(profile_data)
 }
 
@@ -193,15 +326,37 @@ pub async fn get_xtream_channels(
     state: State<'_, XtreamState>,
     profile_id: String,
     category_id: Option<String>,
+    include_now_playing: Option<bool>,
 ) -> Result<Value, String> {
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client
+    let mut channels = client
         .get_channels(category_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    if include_now_playing.unwrap_or(false) {
+        let conn = state.profile_manager.get_db_connection();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        epg::attach_now_playing(&conn_guard, &profile_id, &mut channels, chrono::Utc::now().timestamp())
+            .map_err(|e| e.to_string())?;
+    }
+
+    crate::anonymize::mask_result(channels)
+}
+
+/// Cut a page out of a provider list response; anything but an array is an empty list
+fn page_from_list(list: Value, limit: Option<u32>, offset: Option<u32>) -> Page<Value> {
+    let items = match list {
+        Value::Array(items) => items,
+        _ => Vec::new(),
+    };
+    Page::from_vec(items, limit.map(|l| l as usize), offset.map(|o| o as usize))
 }
 
 /// Get live channels with pagination
+///
+/// Providers don't reliably honour paging parameters, so the (cached) full list
+/// is fetched and the page cut from it, which also gives the total.
 #[tauri::command]
 pub async fn get_xtream_channels_paginated(
     state: State<'_, XtreamState>,
@@ -209,12 +364,13 @@ pub async fn get_xtream_channels_paginated(
     category_id: Option<String>,
     limit: Option<u32>,
     offset: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Page<Value>, String> {
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client
-        .get_channels_with_pagination(category_id.as_deref(), limit, offset)
+    let list = client
+        .get_channels(category_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(page_from_list(list, limit, offset))
 }
 
 /// Get VOD (movie) categories
@@ -239,9 +395,13 @@ pub async fn get_xtream_movies(
         .get_movies(category_id.as_deref())
         .await
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Get VOD (movies) with pagination
+///
+/// Providers don't reliably honour paging parameters, so the (cached) full list
+/// is fetched and the page cut from it, which also gives the total.
 #[tauri::command]
 pub async fn get_xtream_movies_paginated(
     state: State<'_, XtreamState>,
@@ -249,12 +409,13 @@ pub async fn get_xtream_movies_paginated(
     category_id: Option<String>,
     limit: Option<u32>,
     offset: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Page<Value>, String> {
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client
-        .get_movies_with_pagination(category_id.as_deref(), limit, offset)
+    let list = client
+        .get_movies(category_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(page_from_list(list, limit, offset))
 }
 
 /// Get movie information with enhanced metadata
@@ -265,7 +426,11 @@ pub async fn get_xtream_movie_info(
     movie_id: String,
 ) -> Result<Value, String> {
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client.get_movie_info(&movie_id).await.map_err(|e| e.to_string())
+    client
+        .get_movie_info(&movie_id)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Get TV series categories
@@ -290,9 +455,13 @@ pub async fn get_xtream_series(
         .get_series(category_id.as_deref())
         .await
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Get TV series with pagination
+///
+/// Providers don't reliably honour paging parameters, so the (cached) full list
+/// is fetched and the page cut from it, which also gives the total.
 #[tauri::command]
 pub async fn get_xtream_series_paginated(
     state: State<'_, XtreamState>,
@@ -300,12 +469,13 @@ pub async fn get_xtream_series_paginated(
     category_id: Option<String>,
     limit: Option<u32>,
     offset: Option<u32>,
-) -> Result<Value, String> {
+) -> Result<Page<Value>, String> {
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client
-        .get_series_with_pagination(category_id.as_deref(), limit, offset)
+    let list = client
+        .get_series(category_id.as_deref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(page_from_list(list, limit, offset))
 }
 
 /// Get series information with enhanced metadata
@@ -316,7 +486,11 @@ pub async fn get_xtream_series_info(
     series_id: String,
 ) -> Result<Value, String> {
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client.get_series_info(&series_id).await.map_err(|e| e.to_string())
+    client
+        .get_series_info(&series_id)
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Generate episode streaming URL
@@ -332,6 +506,23 @@ pub async fn generate_xtream_episode_stream_url(
     client
         .generate_episode_stream_url(&series_id, &episode_id, extension.as_deref())
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Keep fetched programmes in the local EPG store so channel listings can show
+/// what's on now; a failure here shouldn't fail the EPG request itself
+fn remember_epg_programs(state: &XtreamState, profile_id: &str, stream_id: Option<&str>, epg_data: &Value) {
+    let conn = state.profile_manager.get_db_connection();
+    let stored = conn
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("database connection"))
+        .and_then(|conn| {
+            epg::store_epg_programs(&conn, profile_id, stream_id, epg_data, chrono::Utc::now().timestamp())
+        });
+
+    if let Err(e) = stored {
+        eprintln!("Failed to store EPG programmes for profile {}: {}", profile_id, e);
+    }
 }
 
 /// Get short EPG for a channel
@@ -341,8 +532,11 @@ pub async fn get_xtream_short_epg(
     profile_id: String,
     channel_id: String,
 ) -> Result<Value, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client.get_short_epg(&channel_id).await.map_err(|e| e.to_string())
+    let epg_data = client.get_short_epg(&channel_id).await.map_err(|e| e.to_string())?;
+    remember_epg_programs(&state, &profile_id, Some(&channel_id), &epg_data);
+    Ok(epg_data)
 }
 
 /// Get full EPG for a channel with optional date range
@@ -354,26 +548,48 @@ pub async fn get_xtream_full_epg(
     start_date: Option<String>,
     end_date: Option<String>,
 ) -> Result<Value, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client
+    let epg_data = client
         .get_full_epg(&channel_id, start_date.as_deref(), end_date.as_deref())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    remember_epg_programs(&state, &profile_id, Some(&channel_id), &epg_data);
+    Ok(epg_data)
 }
 
 /// Get EPG for multiple channels
+///
+/// Ids are fetched in chunks of `chunk_size` with up to `max_concurrency`
+/// requests at once; listings come back keyed by channel, and chunks that
+/// failed are listed rather than failing the whole request.
 #[tauri::command]
 pub async fn get_xtream_epg_for_channels(
     state: State<'_, XtreamState>,
     profile_id: String,
     channel_ids: Vec<String>,
-) -> Result<Value, String> {
+    chunk_size: Option<usize>,
+    max_concurrency: Option<usize>,
+) -> Result<EpgBatchResult, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
     let client = create_authenticated_client(&state, &profile_id).await?;
-    let channel_refs: Vec<&str> = channel_ids.iter().map(|s| s.as_str()).collect();
-    client
-        .get_epg_for_channels(&channel_refs)
+    let batch = client
+        .get_epg_for_channels_chunked(
+            &channel_ids,
+            chunk_size.unwrap_or(DEFAULT_EPG_CHUNK_SIZE),
+            max_concurrency.unwrap_or(DEFAULT_EPG_CHUNK_CONCURRENCY),
+        )
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    if batch.chunks_total > 0 && batch.failed_chunks.len() == batch.chunks_total {
+        let error = &batch.failed_chunks[0].error;
+        return Err(format!("All EPG requests failed: {}", error));
+    }
+
+    let listings = Value::Array(batch.epg.values().flatten().cloned().collect());
+    remember_epg_programs(&state, &profile_id, None, &listings);
+    Ok(batch)
 }
 
 /// Get EPG for a specific date range using timestamps
@@ -385,11 +601,14 @@ pub async fn get_xtream_epg_by_date_range(
     start_timestamp: u64,
     end_timestamp: u64,
 ) -> Result<Value, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client
+    let epg_data = client
         .get_epg_by_date_range(&channel_id, start_timestamp, end_timestamp)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    remember_epg_programs(&state, &profile_id, Some(&channel_id), &epg_data);
+    Ok(epg_data)
 }
 
 /// Format EPG timestamp for display
@@ -429,6 +648,7 @@ pub async fn get_xtream_current_and_next_epg(
     profile_id: String,
     channel_id: String,
 ) -> Result<Value, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
     let client = create_authenticated_client(&state, &profile_id).await?;
     client.get_current_and_next_epg(&channel_id).await.map_err(|e| e.to_string())
 }
@@ -449,24 +669,64 @@ pub fn search_epg_programs(epg_data: Value, search_query: String) -> Result<Valu
     XtreamClient::search_epg_programs(&epg_data, &search_query).map_err(|e| e.to_string())
 }
 
+/// Get the programme icon URLs for the visible guide window, for image prefetching
+#[tauri::command]
+pub fn get_epg_icon_urls(
+    epg_data: Value,
+    start_timestamp: Option<i64>,
+    end_timestamp: Option<i64>,
+) -> Result<Vec<String>, String> {
+    XtreamClient::collect_epg_icon_urls(&epg_data, start_timestamp, end_timestamp)
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
 /// Generate streaming URL for content
+///
+/// A channel's custom URL override, when set, is returned instead of the provider URL.
 #[tauri::command]
 pub async fn generate_xtream_stream_url(
     state: State<'_, XtreamState>,
+    content_cache_state: State<'_, crate::content_cache::ContentCacheState>,
     profile_id: String,
     content_type: String,
     content_id: String,
     extension: Option<String>,
 ) -> Result<String, String> {
+    resolve_xtream_stream_url(state, content_cache_state, profile_id, content_type, content_id, extension)
+        .await
+        .map(|resolved| resolved.url)
+}
+
+/// Resolve the playback URL for content, flagging channel URL overrides
+#[tauri::command]
+pub async fn resolve_xtream_stream_url(
+    state: State<'_, XtreamState>,
+    content_cache_state: State<'_, crate::content_cache::ContentCacheState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+    extension: Option<String>,
+) -> Result<ResolvedStreamUrl, String> {
     use crate::xtream::ContentType;
     
-    let content_type_enum = match content_type.as_str() {
-        "Channel" => ContentType::Channel,
-        "Movie" => ContentType::Movie,
-        "Series" => ContentType::Series,
+    let content_type_enum = match crate::content_id::canonical_type(&content_type) {
+        Some("channel") => ContentType::Channel,
+        Some("movie") => ContentType::Movie,
+        Some("series") => ContentType::Series,
         _ => return Err(format!("Invalid content type: {}", content_type)),
     };
     
+    if let (ContentType::Channel, Ok(stream_id)) = (&content_type_enum, content_id.parse::<i64>()) {
+        let url_override = content_cache_state
+            .cache
+            .get_channel_url_override(&profile_id, stream_id)
+            .map_err(|e| e.to_string())?;
+        if let Some(url) = url_override {
+            return crate::anonymize::mask_result(ResolvedStreamUrl { url, overridden: true });
+        }
+    }
+    
     let request = StreamURLRequest {
         content_type: content_type_enum,
         content_id,
@@ -474,7 +734,8 @@ pub async fn generate_xtream_stream_url(
     };
     
     let client = create_authenticated_client(&state, &profile_id).await?;
-    client.generate_stream_url(&request).map_err(|e| e.to_string())
+    let url = client.generate_stream_url(&request).map_err(|e| e.to_string())?;
+    crate::anonymize::mask_result(ResolvedStreamUrl { url, overridden: false })
 }
 
 /// Filter channels by various criteria
@@ -652,6 +913,7 @@ pub async fn get_xtream_playback_history(
         .get_playback_history(&profile_id)
         .await
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Add content to playback history
@@ -682,6 +944,8 @@ pub async fn update_xtream_playback_position(
     position: f64,
     duration: Option<f64>,
 ) -> Result<(), String> {
+    // A buffered heartbeat for the item is older than this position
+    heartbeat::discard(&profile_id, &content_type, &content_id);
     state
         .profile_manager
         .update_playback_position(&profile_id, &content_type, &content_id, position, duration)
@@ -689,6 +953,87 @@ pub async fn update_xtream_playback_position(
         .map_err(|e| e.to_string())
 }
 
+/// Report the position of what's playing; call every few seconds while playing
+///
+/// Positions are buffered and written to history every 15 seconds at most, and
+/// straight away when `stopped` is true. A position buffered when the app
+/// crashes is recorded on the next start. Nothing is recorded for a `private`
+/// playback.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn playback_position_heartbeat(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+    position: f64,
+    duration: Option<f64>,
+    stopped: Option<bool>,
+    private: Option<bool>,
+) -> Result<(), String> {
+    let due = heartbeat::record(
+        heartbeat::PendingPosition {
+            profile_id,
+            content_type,
+            content_id,
+            position,
+            duration,
+        },
+        private.unwrap_or(false),
+    )
+    .map_err(|e| e.to_string())?;
+    if due || stopped.unwrap_or(false) {
+        heartbeat::flush(&state.profile_manager).await.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Write buffered playback positions to history now; returns how many were written
+#[tauri::command]
+pub async fn flush_playback_positions(state: State<'_, XtreamState>) -> Result<usize, String> {
+    heartbeat::flush(&state.profile_manager).await.map_err(|e| e.to_string())
+}
+
+/// Catch-up programmes to offer in continue watching
+///
+/// Each is a programme on an archive channel that was left part way through,
+/// with a timeshift URL starting at the programme's start; seek to
+/// `position_secs` to resume. `server_utc_offset_minutes` is the provider's
+/// clock offset from UTC, if known.
+#[tauri::command]
+pub async fn get_archive_resume_entries(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    limit: Option<usize>,
+    server_utc_offset_minutes: Option<i32>,
+) -> Result<Vec<ArchiveResume>, String> {
+    let mut entries = {
+        let conn = state.profile_manager.get_db_connection();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        resume_candidates(&conn_guard, &profile_id, chrono::Utc::now().timestamp(), limit.unwrap_or(10))
+            .map_err(|e| e.to_string())?
+    };
+    if entries.is_empty() {
+        return Ok(entries);
+    }
+
+    let client = create_authenticated_client(&state, &profile_id).await?;
+    for entry in &mut entries {
+        let duration_minutes = (entry.programme_stop - entry.programme_start + 59) / 60;
+        entry.timeshift_url = Some(
+            client
+                .generate_timeshift_url(
+                    entry.stream_id,
+                    entry.programme_start,
+                    duration_minutes,
+                    server_utc_offset_minutes.unwrap_or(0),
+                )
+                .map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(entries)
+}
+
 /// Helper function to create an authenticated client for a profile
 async fn create_authenticated_client(
     state: &State<'_, XtreamState>,
@@ -702,7 +1047,9 @@ async fn create_authenticated_client(
         .map_err(|e| e.to_string())?;
 
     // Create and return client
-    XtreamClient::new(credentials, state.content_cache.clone()).map_err(|e| e.to_string())
+    XtreamClient::new(credentials, state.content_cache.clone())
+        .map(|client| client.for_profile(profile_id))
+        .map_err(|e| e.to_string())
 }
 
 #[cfg(test)]
@@ -1024,37 +1371,59 @@ mod tests {
 }
 
 // Favorites commands
-use crate::xtream::{XtreamFavoritesDb, AddFavoriteRequest, XtreamFavorite};
+use crate::xtream::{XtreamFavoritesDb, AddFavoriteRequest, FavoriteIdentity, FavoritesChange, XtreamFavorite};
+use crate::content_cache::ordering::ChannelSortMode;
 
 /// Add a favorite for a profile
 #[tauri::command]
 pub async fn add_xtream_favorite(
+    app_handle: AppHandle,
     state: State<'_, XtreamState>,
     request: AddFavoriteRequest,
 ) -> Result<String, String> {
     let conn = state.profile_manager.get_db_connection();
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
-    XtreamFavoritesDb::add_favorite(&conn_guard, &request)
-        .map_err(|e| e.to_string())
+    let favorite_id = XtreamFavoritesDb::add_favorite(&conn_guard, &request)
+        .map_err(|e| e.to_string())?;
+    
+    FavoritesChange::added(
+        &request.profile_id,
+        FavoriteIdentity::new(&request.content_type, &request.content_id),
+    )
+    .emit(&app_handle);
+    Ok(favorite_id)
 }
 
 /// Remove a favorite by ID
 #[tauri::command]
 pub async fn remove_xtream_favorite(
+    app_handle: AppHandle,
     state: State<'_, XtreamState>,
     favorite_id: String,
 ) -> Result<(), String> {
     let conn = state.profile_manager.get_db_connection();
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
+    let favorite = XtreamFavoritesDb::get_favorite(&conn_guard, &favorite_id)
+        .map_err(|e| e.to_string())?;
     XtreamFavoritesDb::remove_favorite(&conn_guard, &favorite_id)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    
+    if let Some(favorite) = favorite {
+        FavoritesChange::removed(
+            &favorite.profile_id,
+            vec![FavoriteIdentity::new(&favorite.content_type, &favorite.content_id)],
+        )
+        .emit(&app_handle);
+    }
+    Ok(())
 }
 
 /// Remove a favorite by content
 #[tauri::command]
 pub async fn remove_xtream_favorite_by_content(
+    app_handle: AppHandle,
     state: State<'_, XtreamState>,
     profile_id: String,
     content_type: String,
@@ -1064,34 +1433,47 @@ pub async fn remove_xtream_favorite_by_content(
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
     XtreamFavoritesDb::remove_favorite_by_content(&conn_guard, &profile_id, &content_type, &content_id)
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    
+    FavoritesChange::removed(&profile_id, vec![FavoriteIdentity::new(&content_type, &content_id)])
+        .emit(&app_handle);
+    Ok(())
 }
 
-/// Get all favorites for a profile
+/// Get all favorites for a profile, newest first or in the `custom` order
 #[tauri::command]
 pub async fn get_xtream_favorites(
     state: State<'_, XtreamState>,
     profile_id: String,
+    sort_mode: Option<ChannelSortMode>,
 ) -> Result<Vec<XtreamFavorite>, String> {
     let conn = state.profile_manager.get_db_connection();
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
-    XtreamFavoritesDb::get_favorites(&conn_guard, &profile_id)
+    XtreamFavoritesDb::get_favorites_sorted(&conn_guard, &profile_id, None, sort_mode.unwrap_or_default())
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
-/// Get favorites by content type for a profile
+/// Get favorites by content type for a profile, newest first or in the `custom` order
 #[tauri::command]
 pub async fn get_xtream_favorites_by_type(
     state: State<'_, XtreamState>,
     profile_id: String,
     content_type: String,
+    sort_mode: Option<ChannelSortMode>,
 ) -> Result<Vec<XtreamFavorite>, String> {
     let conn = state.profile_manager.get_db_connection();
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
-    XtreamFavoritesDb::get_favorites_by_type(&conn_guard, &profile_id, &content_type)
-        .map_err(|e| e.to_string())
+    XtreamFavoritesDb::get_favorites_sorted(
+        &conn_guard,
+        &profile_id,
+        Some(&content_type),
+        sort_mode.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+    .and_then(crate::anonymize::mask_result)
 }
 
 /// Check if an item is favorited
@@ -1112,30 +1494,55 @@ pub async fn is_xtream_favorite(
 /// Clear all favorites for a profile
 #[tauri::command]
 pub async fn clear_xtream_favorites(
+    app_handle: AppHandle,
     state: State<'_, XtreamState>,
     profile_id: String,
 ) -> Result<(), String> {
     let conn = state.profile_manager.get_db_connection();
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
-    XtreamFavoritesDb::clear_favorites(&conn_guard, &profile_id)
-        .map_err(|e| e.to_string())
+    let removed = XtreamFavoritesDb::clear_favorites(&conn_guard, &profile_id)
+        .map_err(|e| e.to_string())?;
+    
+    FavoritesChange::removed(&profile_id, removed).emit(&app_handle);
+    Ok(())
 }
 
 // History commands
-use crate::xtream::{XtreamHistoryDb, AddHistoryRequest, UpdatePositionRequest, XtreamHistory};
+use crate::xtream::{XtreamHistoryDb, AddHistoryRequest, UpdatePositionRequest, XtreamHistory, SeriesProgress, ContinueWatchingItem};
 
 /// Add or update a history item for a profile
 #[tauri::command]
 pub async fn add_xtream_history(
     state: State<'_, XtreamState>,
+    automation_state: State<'_, AutomationState>,
     request: AddHistoryRequest,
-) -> Result<String, String> {
-    let conn = state.profile_manager.get_db_connection();
-    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
-    
-    XtreamHistoryDb::add_history(&conn_guard, &request)
-        .map_err(|e| e.to_string())
+) -> Result<Option<String>, String> {
+    let history_id = {
+        let conn = state.profile_manager.get_db_connection();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+        with_busy_retry("add_xtream_history", || XtreamHistoryDb::add_history(&conn_guard, &request))
+            .map_err(|e| e.to_string())?
+    };
+
+    // Private playback is kept from scripts as well as from history
+    if history_id.is_none() {
+        return Ok(None);
+    }
+
+    // Playback starts are recorded through history, so this is where playback hooks fire
+    automation_state.spawn_hooks(
+        request.profile_id.clone(),
+        AutomationEvent::PlaybackStart,
+        serde_json::json!({
+            "content_type": request.content_type,
+            "content_id": request.content_id,
+            "content_data": request.content_data,
+        }),
+    );
+
+    Ok(history_id)
 }
 
 /// Update playback position for a history item
@@ -1147,8 +1554,23 @@ pub async fn update_xtream_history_position(
     let conn = state.profile_manager.get_db_connection();
     let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
     
-    XtreamHistoryDb::update_position(&conn_guard, &request)
-        .map_err(|e| e.to_string())
+    with_busy_retry("update_xtream_history_position", || {
+        XtreamHistoryDb::update_position(&conn_guard, &request)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Turn private playback on or off for the rest of the session
+#[tauri::command]
+pub async fn set_private_playback(enabled: bool) -> Result<(), String> {
+    crate::xtream::history::set_private_session(enabled);
+    Ok(())
+}
+
+/// Whether private playback is on for the session
+#[tauri::command]
+pub async fn get_private_playback() -> Result<bool, String> {
+    Ok(crate::xtream::history::is_private_session())
 }
 
 /// Get history for a profile
@@ -1163,6 +1585,7 @@ pub async fn get_xtream_history(
     
     XtreamHistoryDb::get_history(&conn_guard, &profile_id, limit)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Get history by content type for a profile
@@ -1178,6 +1601,7 @@ pub async fn get_xtream_history_by_type(
     
     XtreamHistoryDb::get_history_by_type(&conn_guard, &profile_id, &content_type, limit)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Get a specific history item
@@ -1193,6 +1617,24 @@ pub async fn get_xtream_history_item(
     
     XtreamHistoryDb::get_history_item(&conn_guard, &profile_id, &content_type, &content_id)
         .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+/// Get movies and episodes left part way through, with their cached titles and covers
+///
+/// Covers every profile when no profile is given.
+#[tauri::command]
+pub async fn get_continue_watching(
+    state: State<'_, XtreamState>,
+    profile_id: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<ContinueWatchingItem>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    
+    XtreamHistoryDb::get_continue_watching(&conn_guard, profile_id.as_deref(), limit)
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
 }
 
 /// Remove a history item
@@ -1235,11 +1677,83 @@ pub async fn clear_old_xtream_history(
         .map_err(|e| e.to_string())
 }
 
+fn set_episodes_watched(
+    state: &XtreamState,
+    profile_id: &str,
+    series_id: i64,
+    season_number: Option<i64>,
+    watched: bool,
+) -> Result<SeriesProgress, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamHistoryDb::set_episodes_watched(&conn_guard, profile_id, series_id, season_number, watched)
+        .map_err(|e| e.to_string())
+}
+
+/// Mark every cached episode of a season as watched
+#[tauri::command]
+pub async fn mark_season_watched(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    series_id: i64,
+    season_number: i64,
+) -> Result<SeriesProgress, String> {
+    set_episodes_watched(&state, &profile_id, series_id, Some(season_number), true)
+}
+
+/// Mark every cached episode of a season as unwatched
+#[tauri::command]
+pub async fn unmark_season_watched(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    series_id: i64,
+    season_number: i64,
+) -> Result<SeriesProgress, String> {
+    set_episodes_watched(&state, &profile_id, series_id, Some(season_number), false)
+}
+
+/// Mark every cached episode of a series as watched
+#[tauri::command]
+pub async fn mark_series_watched(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    series_id: i64,
+) -> Result<SeriesProgress, String> {
+    set_episodes_watched(&state, &profile_id, series_id, None, true)
+}
+
+/// Mark every cached episode of a series as unwatched
+#[tauri::command]
+pub async fn unmark_series_watched(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    series_id: i64,
+) -> Result<SeriesProgress, String> {
+    set_episodes_watched(&state, &profile_id, series_id, None, false)
+}
+
+/// Get the watch progress of a series, including the next episode to play
+#[tauri::command]
+pub async fn get_xtream_series_progress(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    series_id: i64,
+) -> Result<Option<SeriesProgress>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamHistoryDb::get_series_progress(&conn_guard, &profile_id, series_id)
+        .map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Search and Filter Commands
 // ============================================================================
 
-use crate::xtream::search::{SearchOptions, SearchResult, search_all_content};
+use crate::xtream::search::{
+    search_all_content, without_local_matches, ProviderSearchResults, SearchOptions, SearchResult, TieredSearchStart,
+};
 use crate::xtream::filter::{ChannelFilter, MovieFilter, SeriesFilter, filter_channels, filter_movies, filter_series};
 use crate::content_cache::{XtreamChannel, XtreamMovie, XtreamSeries};
 
@@ -1250,9 +1764,10 @@ pub async fn search_all_xtream_content(
     profile_id: String,
     options: SearchOptions,
 ) -> Result<SearchResult, String> {
+    let _slot = concurrency::acquire(Subsystem::Search).await.map_err(|e| e.to_string())?;
     // Fetch all content types as JSON and deserialize
     let channels: Vec<XtreamChannel> = if options.search_channels {
-        let channels_json = get_xtream_channels(state.clone(), profile_id.clone(), None).await?;
+        let channels_json = get_xtream_channels(state.clone(), profile_id.clone(), None, None).await?;
         serde_json::from_value(channels_json).map_err(|e| e.to_string())?
     } else {
         Vec::new()
@@ -1276,23 +1791,120 @@ pub async fn search_all_xtream_content(
     Ok(search_all_content(&channels, &movies, &series, &options))
 }
 
-/// Filter channels with advanced criteria
+/// Search the local cache and the provider, returning local matches straight away
+///
+/// The provider may list content that hasn't been synced yet. Its lists are
+/// searched in the background, and matches missing from the local results are
+/// sent in a `provider_search_results` event with the returned `search_id`.
 #[tauri::command]
-pub async fn filter_channels_advanced(
+pub async fn search_xtream_content_tiered(
+    app_handle: AppHandle,
     state: State<'_, XtreamState>,
+    cache_state: State<'_, crate::content_cache::ContentCacheState>,
     profile_id: String,
-    filter: ChannelFilter,
-) -> Result<Vec<XtreamChannel>, String> {
-    // Fetch channels as JSON and deserialize
-    let channels_json = get_xtream_channels(state, profile_id, filter.category_id.clone()).await?;
-    let channels: Vec<XtreamChannel> = serde_json::from_value(channels_json).map_err(|e| e.to_string())?;
-    
-    // Apply filter
-    Ok(filter_channels(&channels, &filter))
-}
-
-/// Filter movies with advanced criteria
-#[tauri::command]
+    options: SearchOptions,
+) -> Result<TieredSearchStart, String> {
+    let _slot = concurrency::acquire(Subsystem::Search).await.map_err(|e| e.to_string())?;
+    let limit = options.max_results_per_type;
+    let cache = &cache_state.cache;
+    let mut local = SearchResult::empty();
+    if !options.query.is_empty() {
+        if options.search_channels {
+            local.channels = cache
+                .fts_search_channels(&profile_id, &options.query, Some(crate::content_cache::ChannelFilter { limit, ..Default::default() }))
+                .map_err(|e| e.to_string())?;
+        }
+        if options.search_movies {
+            local.movies = cache
+                .fts_search_movies(&profile_id, &options.query, Some(crate::content_cache::MovieFilter { limit, ..Default::default() }))
+                .map_err(|e| e.to_string())?;
+        }
+        if options.search_series {
+            local.series = cache
+                .fts_search_series(&profile_id, &options.query, Some(crate::content_cache::SeriesFilter { limit, ..Default::default() }))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    local.total_results = local.channels.len() + local.movies.len() + local.series.len();
+
+    let search_id = uuid::Uuid::new_v4().to_string();
+    if !options.query.is_empty() {
+        let profile_manager = Arc::clone(&state.profile_manager);
+        let content_cache = Arc::clone(&state.content_cache);
+        let local = local.clone();
+        let search_id = search_id.clone();
+        tokio::spawn(async move {
+            let (results, error) = match search_provider(&profile_manager, content_cache, &profile_id, &options).await {
+                Ok(results) => (without_local_matches(results, &local), None),
+                Err(e) => (SearchResult::empty(), Some(e)),
+            };
+            crate::events::emit(
+                &app_handle,
+                crate::events::AppEvent::ProviderSearchResults(ProviderSearchResults {
+                    search_id,
+                    profile_id,
+                    results,
+                    error,
+                }),
+            );
+        });
+    }
+
+    Ok(TieredSearchStart { search_id, local })
+}
+
+/// Search the provider's full lists, which its client caches between calls
+async fn search_provider(
+    profile_manager: &ProfileManager,
+    content_cache: Arc<ContentCache>,
+    profile_id: &str,
+    options: &SearchOptions,
+) -> Result<SearchResult, String> {
+    let credentials = profile_manager
+        .get_profile_credentials_async_wrapper(profile_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let client = XtreamClient::new(credentials, content_cache).map_err(|e| e.to_string())?.for_profile(profile_id);
+
+    let channels: Vec<XtreamChannel> = if options.search_channels {
+        let channels_json = client.get_channels(None).await.map_err(|e| e.to_string())?;
+        serde_json::from_value(channels_json).map_err(|e| e.to_string())?
+    } else {
+        Vec::new()
+    };
+    let movies: Vec<XtreamMovie> = if options.search_movies {
+        let movies_json = client.get_movies(None).await.map_err(|e| e.to_string())?;
+        serde_json::from_value(movies_json).map_err(|e| e.to_string())?
+    } else {
+        Vec::new()
+    };
+    let series: Vec<XtreamSeries> = if options.search_series {
+        let series_json = client.get_series(None).await.map_err(|e| e.to_string())?;
+        serde_json::from_value(series_json).map_err(|e| e.to_string())?
+    } else {
+        Vec::new()
+    };
+
+    Ok(search_all_content(&channels, &movies, &series, options))
+}
+
+/// Filter channels with advanced criteria
+#[tauri::command]
+pub async fn filter_channels_advanced(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    filter: ChannelFilter,
+) -> Result<Vec<XtreamChannel>, String> {
+    // Fetch channels as JSON and deserialize
+    let channels_json = get_xtream_channels(state, profile_id, filter.category_id.clone(), None).await?;
+    let channels: Vec<XtreamChannel> = serde_json::from_value(channels_json).map_err(|e| e.to_string())?;
+    
+    // Apply filter
+    Ok(filter_channels(&channels, &filter))
+}
+
+/// Filter movies with advanced criteria
+#[tauri::command]
 pub async fn filter_movies_advanced(
     state: State<'_, XtreamState>,
     profile_id: String,
@@ -1507,3 +2119,551 @@ pub async fn clear_xtream_saved_filters(
         .map_err(|e| e.to_string())
 }
 
+
+// ============================================================================
+// Content Tags Commands
+// ============================================================================
+
+use crate::xtream::tags::{XtreamContentTag, XtreamTagsDb};
+
+/// Attach a tag to a content item
+#[tauri::command]
+pub async fn add_xtream_content_tag(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+    tag: String,
+) -> Result<(), String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamTagsDb::add_tag(&conn_guard, &profile_id, &content_type, &content_id, &tag)
+        .map_err(|e| e.to_string())
+}
+
+/// Remove a tag from a content item
+#[tauri::command]
+pub async fn remove_xtream_content_tag(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+    tag: String,
+) -> Result<(), String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamTagsDb::remove_tag(&conn_guard, &profile_id, &content_type, &content_id, &tag)
+        .map_err(|e| e.to_string())
+}
+
+/// Get the tags attached to a content item
+#[tauri::command]
+pub async fn get_xtream_content_tags(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+) -> Result<Vec<String>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamTagsDb::get_tags_for_content(&conn_guard, &profile_id, &content_type, &content_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Get all content items carrying a tag (e.g. "hidden")
+#[tauri::command]
+pub async fn get_xtream_content_by_tag(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    tag: String,
+    content_type: Option<String>,
+) -> Result<Vec<XtreamContentTag>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamTagsDb::get_content_by_tag(&conn_guard, &profile_id, &tag, content_type.as_deref())
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+// ============================================================================
+// Automation Commands
+// ============================================================================
+
+use crate::xtream::automation::{
+    AutomationEngine, AutomationEvent, AutomationScript, AutomationScriptsDb, AutomationState,
+    CreateAutomationScriptRequest, ScriptRunResult, UpdateAutomationScriptRequest,
+};
+
+/// Create an automation script after checking that it compiles
+#[tauri::command]
+pub async fn create_xtream_automation_script(
+    state: State<'_, AutomationState>,
+    request: CreateAutomationScriptRequest,
+) -> Result<String, String> {
+    AutomationEngine::validate_source(&request.source).map_err(|e| e.to_string())?;
+
+    let conn = state.engine.get_db();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    AutomationScriptsDb::create_script(&conn_guard, &request)
+        .map_err(|e| e.to_string())
+}
+
+/// Update an automation script
+#[tauri::command]
+pub async fn update_xtream_automation_script(
+    state: State<'_, AutomationState>,
+    id: String,
+    request: UpdateAutomationScriptRequest,
+) -> Result<(), String> {
+    if let Some(source) = &request.source {
+        AutomationEngine::validate_source(source).map_err(|e| e.to_string())?;
+    }
+
+    let conn = state.engine.get_db();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    AutomationScriptsDb::update_script(&conn_guard, &id, &request)
+        .map_err(|e| e.to_string())
+}
+
+/// Delete an automation script
+#[tauri::command]
+pub async fn delete_xtream_automation_script(
+    state: State<'_, AutomationState>,
+    id: String,
+) -> Result<(), String> {
+    let conn = state.engine.get_db();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    AutomationScriptsDb::delete_script(&conn_guard, &id)
+        .map_err(|e| e.to_string())
+}
+
+/// Get all automation scripts for a profile
+#[tauri::command]
+pub async fn get_xtream_automation_scripts(
+    state: State<'_, AutomationState>,
+    profile_id: String,
+) -> Result<Vec<AutomationScript>, String> {
+    let conn = state.engine.get_db();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    AutomationScriptsDb::get_scripts(&conn_guard, &profile_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Run an automation script immediately and return its output
+#[tauri::command]
+pub async fn run_xtream_automation_script(
+    state: State<'_, AutomationState>,
+    id: String,
+) -> Result<ScriptRunResult, String> {
+    let script = {
+        let conn = state.engine.get_db();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        AutomationScriptsDb::get_script(&conn_guard, &id)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Automation script not found".to_string())?
+    };
+
+    let engine = Arc::clone(&state.engine);
+    tokio::task::spawn_blocking(move || {
+        engine.run_script(&script, AutomationEvent::Manual, serde_json::json!({}))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+    .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// EPG Reminder Commands
+// ============================================================================
+
+use crate::xtream::reminders::{
+    reminders_to_ics, CreateEpgReminderRequest, XtreamEpgReminder, XtreamRemindersDb,
+};
+
+/// Set a reminder for an upcoming EPG programme
+#[tauri::command]
+pub async fn add_xtream_epg_reminder(
+    state: State<'_, XtreamState>,
+    request: CreateEpgReminderRequest,
+) -> Result<String, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamRemindersDb::add_reminder(&conn_guard, &request)
+        .map_err(|e| e.to_string())
+}
+
+/// Remove an EPG reminder
+#[tauri::command]
+pub async fn remove_xtream_epg_reminder(
+    state: State<'_, XtreamState>,
+    id: String,
+) -> Result<(), String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    XtreamRemindersDb::remove_reminder(&conn_guard, &id)
+        .map_err(|e| e.to_string())
+}
+
+/// Get a profile's EPG reminders, by default only those that haven't ended yet
+#[tauri::command]
+pub async fn get_xtream_epg_reminders(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    include_past: Option<bool>,
+) -> Result<Vec<XtreamEpgReminder>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    let after = if include_past.unwrap_or(false) {
+        None
+    } else {
+        Some(chrono::Utc::now().timestamp())
+    };
+
+    XtreamRemindersDb::get_reminders(&conn_guard, &profile_id, after)
+        .map_err(|e| e.to_string())
+}
+
+/// Write a profile's upcoming reminders to an iCalendar (.ics) file
+///
+/// Recordings are left out: `start_recording` records from the moment it is
+/// called and nothing stores a future start, so no recording is ever scheduled
+/// ahead of time. Returns the number of events written.
+#[tauri::command]
+pub async fn export_schedule_as_ics(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    path: String,
+) -> Result<usize, String> {
+    let reminders = {
+        let conn = state.profile_manager.get_db_connection();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        XtreamRemindersDb::get_reminders(&conn_guard, &profile_id, Some(chrono::Utc::now().timestamp()))
+            .map_err(|e| e.to_string())?
+    };
+
+    let ics = reminders_to_ics(&reminders).map_err(|e| e.to_string())?;
+    std::fs::write(&path, ics).map_err(|e| format!("Failed to write calendar file: {}", e))?;
+
+    Ok(reminders.len())
+}
+
+// ============================================================================
+// Year in Review Commands
+// ============================================================================
+
+use crate::xtream::year_review::{year_in_review, YearInReview};
+
+/// Summarize a profile's watching in a year: hours, top channels and genres, busiest day
+///
+/// With `render_chart`, the review includes a PNG bar chart of hours per month.
+#[tauri::command]
+pub async fn get_year_in_review(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    year: i32,
+    render_chart: Option<bool>,
+) -> Result<YearInReview, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    year_in_review(&conn_guard, &profile_id, year, render_chart.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Backlog Commands
+// ============================================================================
+
+use crate::xtream::backlog::{backlog_stats, BacklogStats};
+
+/// Count the favorite movies and followed series episodes a profile hasn't watched yet
+///
+/// Totals are split by genre and include the estimated hours left, from cached runtimes.
+#[tauri::command]
+pub async fn get_backlog_stats(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+) -> Result<BacklogStats, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    backlog_stats(&conn_guard, &profile_id).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Request Limit Commands
+// ============================================================================
+
+use crate::xtream::rate_limit::{self, RequestLimits};
+
+/// Get the request rate and concurrency a profile's provider is sent
+#[tauri::command]
+pub async fn get_request_limits(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+) -> Result<RequestLimits, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    rate_limit::load_limits(&conn_guard, &profile_id).map_err(|e| e.to_string())
+}
+
+/// Set the request rate and concurrency for a profile's provider
+///
+/// Applies to the profile's next request, from any client or sync.
+#[tauri::command]
+pub async fn set_request_limits(
+    state: State<'_, XtreamState>,
+    limits: RequestLimits,
+) -> Result<(), String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    rate_limit::save_limits(&conn_guard, &limits).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// EPG Mapping Commands
+// ============================================================================
+
+use crate::content_cache::epg_mapping::{self, EpgMappingReport, EpgMappingSuggestion};
+
+/// Match channels without an EPG id to the provider's XMLTV channels by name
+///
+/// Downloads the provider's guide; confident matches are applied and the rest
+/// are left for review in `get_epg_mapping_suggestions`.
+#[tauri::command]
+pub async fn auto_map_epg_channels(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+) -> Result<EpgMappingReport, String> {
+    let _slot = concurrency::acquire(Subsystem::Epg).await.map_err(|e| e.to_string())?;
+    let client = create_authenticated_client(&state, &profile_id).await?;
+    let body = client
+        .get_xmltv()
+        .await
+        .map_err(|e| format!("Failed to download guide: {}", e))?;
+    let guide = tauri::async_runtime::spawn_blocking(move || crate::epg_xmltv::parse_xmltv_channels(&body))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+    let report = epg_mapping::auto_map(&conn_guard, &profile_id, &guide, chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())?;
+    println!(
+        "[EPG] Mapped {} channels for profile {}, {} to review, {} unmatched",
+        report.applied, profile_id, report.suggested, report.unmatched
+    );
+    Ok(report)
+}
+
+/// Get the channels whose EPG matches need review, with their candidates
+#[tauri::command]
+pub async fn get_epg_mapping_suggestions(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+) -> Result<Vec<EpgMappingSuggestion>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    epg_mapping::suggestions(&conn_guard, &profile_id).map_err(|e| e.to_string())
+}
+
+/// Apply an EPG id to a channel, or reject its mapping with `None`
+#[tauri::command]
+pub async fn resolve_epg_mapping(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    stream_id: i64,
+    epg_channel_id: Option<String>,
+) -> Result<(), String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    epg_mapping::resolve(
+        &conn_guard,
+        &profile_id,
+        stream_id,
+        epg_channel_id.as_deref(),
+        chrono::Utc::now().timestamp(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Followed Category Commands
+// ============================================================================
+
+use crate::content_cache::followed_categories::{self, FollowedCategory, FollowedCategoryUpdate};
+
+/// Follow a category; syncs then report its new items in a notification and
+/// in `get_followed_category_updates`
+#[tauri::command]
+pub async fn follow_category(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    category_id: String,
+) -> Result<FollowedCategory, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    followed_categories::follow(
+        &conn_guard,
+        &profile_id,
+        &content_type,
+        &category_id,
+        chrono::Utc::now().timestamp(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Stop following a category and drop its feed entries
+#[tauri::command]
+pub async fn unfollow_category(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    category_id: String,
+) -> Result<bool, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    followed_categories::unfollow(&conn_guard, &profile_id, &content_type, &category_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_followed_categories(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+) -> Result<Vec<FollowedCategory>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    followed_categories::followed(&conn_guard, &profile_id).map_err(|e| e.to_string())
+}
+
+/// Items that appeared in followed categories, newest first
+#[tauri::command]
+pub async fn get_followed_category_updates(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    unread_only: Option<bool>,
+    limit: Option<i64>,
+) -> Result<Vec<FollowedCategoryUpdate>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    followed_categories::updates(&conn_guard, &profile_id, unread_only.unwrap_or(false), limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
+
+/// Mark followed category updates read, or all of the profile's without `ids`; returns how many changed
+#[tauri::command]
+pub async fn mark_followed_category_updates_read(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    ids: Option<Vec<i64>>,
+) -> Result<usize, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    followed_categories::mark_read(&conn_guard, &profile_id, ids.as_deref(), chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// Watchlist Commands
+// ============================================================================
+
+use crate::content_cache::watchlist::{self, WatchlistItem};
+use crate::content_id::ContentId;
+
+/// Add a movie or series to watch later; it must be in the cached catalog
+#[tauri::command]
+pub async fn add_to_watchlist(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+) -> Result<WatchlistItem, String> {
+    let content = ContentId::new(&content_type, &content_id).map_err(|e| e.to_string())?;
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    watchlist::add(&conn_guard, &profile_id, &content, chrono::Utc::now().timestamp())
+        .map_err(|e| e.to_string())
+    .and_then(crate::anonymize::mask_result)
+}
+
+/// Remove a movie or series from the watchlist; returns whether it was on it
+#[tauri::command]
+pub async fn remove_from_watchlist(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: String,
+    content_id: String,
+) -> Result<bool, String> {
+    let content = ContentId::new(&content_type, &content_id).map_err(|e| e.to_string())?;
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    watchlist::remove(&conn_guard, &profile_id, &content).map_err(|e| e.to_string())
+}
+
+/// The watchlist with cached details, most recently added first
+#[tauri::command]
+pub async fn get_watchlist(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    content_type: Option<String>,
+) -> Result<Vec<WatchlistItem>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    watchlist::items(&conn_guard, &profile_id, content_type.as_deref())
+        .map_err(|e| e.to_string())
+        .and_then(crate::anonymize::mask_result)
+}
+
+// ============================================================================
+// Portable Export Commands
+// ============================================================================
+
+use crate::xtream::portable::export_portables;
+
+/// Write a profile's favorites and tag groups to a provider-agnostic JSON file
+///
+/// Items are keyed by normalized name and tvg-id rather than stream ids, so a
+/// profile on another provider can re-link them. Returns the number of items written.
+#[tauri::command]
+pub async fn export_profile_portables(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+    path: String,
+) -> Result<usize, String> {
+    let portables = {
+        let conn = state.profile_manager.get_db_connection();
+        let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        export_portables(&conn_guard, &profile_id).map_err(|e| e.to_string())?
+    };
+
+    let json = serde_json::to_string_pretty(&portables)
+        .map_err(|e| format!("Failed to serialize export: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write export file: {}", e))?;
+
+    Ok(portables.item_count())
+}
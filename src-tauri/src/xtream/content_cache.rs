@@ -162,6 +162,11 @@ impl ContentCache {
         }
     }
     
+    /// Get the database connection the cache is stored in
+    pub fn get_db(&self) -> Arc<Mutex<Connection>> {
+        Arc::clone(&self.db)
+    }
+    
     /// Get cached content by key
     pub fn get<T>(&self, key: &str) -> Result<Option<T>>
     where
@@ -347,6 +352,29 @@ impl ContentCache {
         Ok(())
     }
     
+    /// Entry count and approximate size in bytes of the in-memory layer
+    pub fn memory_usage(&self) -> (usize, usize) {
+        let bytes = self
+            .memory_cache
+            .iter()
+            .map(|entry| {
+                entry.key().len()
+                    + entry.value().data.len()
+                    + entry.value().content_type.len()
+                    + std::mem::size_of::<CachedContent>()
+            })
+            .sum();
+        (self.memory_cache.len(), bytes)
+    }
+    
+    /// Drop the in-memory layer; entries are reloaded from the database on demand
+    pub fn clear_memory(&self) -> usize {
+        let entries = self.memory_cache.len();
+        self.memory_cache.clear();
+        self.memory_cache.shrink_to_fit();
+        entries
+    }
+    
     /// Clear expired entries from both memory and database
     pub fn cleanup_expired(&self) -> Result<()> {
         let now = Utc::now();
@@ -616,7 +644,7 @@ impl ContentCache {
             "channels" => self.cache_policies.channels.ttl,
             "movies" => self.cache_policies.movies.ttl,
             "series" => self.cache_policies.series.ttl,
-            "epg" => self.cache_policies.epg.ttl,
+            "epg" => crate::power::scale_interval(self.cache_policies.epg.ttl),
             "categories" | "channel_categories" | "movie_categories" | "series_categories" => {
                 self.cache_policies.categories.ttl
             }
@@ -641,6 +669,7 @@ impl ContentCache {
     /// Enforce cache policy for a content type (evict if over limit)
     fn enforce_cache_policy(&self, content_type: &str) -> Result<()> {
         let policy = self.get_policy_for_content_type(content_type);
+        let max_entries = crate::storage_policy::StoragePolicy::current().scale_entries(policy.max_entries);
         
         // Count entries of this content type in memory cache
         let entries: Vec<_> = self.memory_cache
@@ -649,13 +678,13 @@ impl ContentCache {
             .map(|entry| (entry.key().clone(), entry.value().expires_at))
             .collect();
         
-        if entries.len() > policy.max_entries {
+        if entries.len() > max_entries {
             // Sort by expiration time (oldest first) and priority
             let mut sorted_entries = entries;
             sorted_entries.sort_by(|a, b| a.1.cmp(&b.1));
             
             // Remove oldest entries
-            let to_remove = sorted_entries.len() - policy.max_entries;
+            let to_remove = sorted_entries.len() - max_entries;
             for (key, _) in sorted_entries.iter().take(to_remove) {
                 self.memory_cache.remove(key);
                 
@@ -676,8 +705,8 @@ impl ContentCache {
             |row| row.get(0),
         )?;
         
-        if count as usize > policy.max_entries {
-            let to_remove = count as usize - policy.max_entries;
+        if count as usize > max_entries {
+            let to_remove = count as usize - max_entries;
             db.execute(
                 "DELETE FROM xtream_content_cache WHERE cache_key IN (
                     SELECT cache_key FROM xtream_content_cache 
@@ -826,6 +855,24 @@ mod tests {
         assert!(has_high_priority);
     }
     
+    #[test]
+    fn test_clear_memory_keeps_database_copy() {
+        let db = Arc::new(Mutex::new(create_test_db()));
+        let cache = ContentCache::new(db, Duration::from_secs(3600));
+        let data = TestData { id: 1, name: "Test".to_string() };
+        
+        cache.set("test-profile:channels", &data, None).unwrap();
+        let (entries, bytes) = cache.memory_usage();
+        assert_eq!(entries, 1);
+        assert!(bytes > "test-profile:channels".len());
+        
+        assert_eq!(cache.clear_memory(), 1);
+        assert_eq!(cache.memory_usage(), (0, 0));
+        
+        let reloaded: Option<TestData> = cache.get("test-profile:channels").unwrap();
+        assert_eq!(reloaded, Some(data));
+    }
+    
     #[test]
     fn test_content_type_specific_ttl() {
         let db = Arc::new(Mutex::new(create_test_db()));
@@ -0,0 +1,472 @@
+// Built-in demo provider
+//
+// `create_demo_profile` writes a synthetic catalog as replay fixtures in the
+// demo fixture directory and adds a profile on `DEMO_HOST`, which always
+// replays them. New users can browse channels, movies, series and the guide,
+// sync, favorite and search before entering real credentials, and tests get a
+// rich provider without a network. Artwork is inline SVG, so nothing is
+// downloaded; the streams themselves don't exist.
+use crate::error::{Result, XTauriError};
+use crate::xtream::recording::{self, DEMO_HOST};
+use crate::xtream::{CreateProfileRequest, XtreamState};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::State;
+use url::Url;
+
+const DEMO_PROFILE_NAME: &str = "Demo";
+const DEMO_CREDENTIAL: &str = "demo";
+
+/// Guide hours generated before and after the current hour
+const EPG_HOURS_BEFORE: i64 = 6;
+const EPG_HOURS_AFTER: i64 = 18;
+
+/// Category id, name and the titles in it
+type Group = (&'static str, &'static str, &'static [&'static str]);
+
+const LIVE_CATEGORIES: &[Group] = &[
+    ("1", "News", &["World News 24", "Business Today", "Weather Now"]),
+    ("2", "Sports", &["Sports Arena", "Goal Line", "Racing Live"]),
+    ("3", "Music", &["Hit Radio TV", "Classic Rock", "Jazz Lounge"]),
+    ("4", "Kids", &["Cartoon Corner", "Science Kids", "Story Time"]),
+];
+
+const MOVIE_CATEGORIES: &[Group] = &[
+    ("11", "Drama", &["The Long Harbor", "Quiet Streets", "Paper Lanterns", "Winter Orchard"]),
+    ("12", "Comedy", &["Lost in the Office", "Two Left Feet", "The Wrong Wedding", "Uncle Max"]),
+    ("13", "Documentary", &["Deep Blue Planet", "City of Bridges", "The Bee Keepers", "Northern Lights"]),
+];
+
+const SERIES_CATEGORIES: &[Group] = &[
+    ("21", "Sci-Fi", &["Orbit Station", "The Signal", "Red Dust"]),
+    ("22", "Crime", &["Harbor Precinct", "Cold Files", "The Fixer"]),
+];
+
+const SEASONS_PER_SERIES: i64 = 2;
+const EPISODES_PER_SEASON: i64 = 4;
+
+const PROGRAMME_TITLES: &[&str] = &[
+    "Morning Edition",
+    "Highlights",
+    "Live Coverage",
+    "Feature Hour",
+    "In Depth",
+    "The Late Show",
+];
+
+const PALETTE: &[&str] = &["#3b82f6", "#ef4444", "#10b981", "#f59e0b", "#8b5cf6", "#ec4899", "#14b8a6"];
+
+/// Placeholder artwork: the title's initials on a colored card, as a data URI
+fn placeholder_image(title: &str, width: u32, height: u32) -> String {
+    let initials: String = title
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .filter(|c| c.is_alphanumeric())
+        .take(2)
+        .collect();
+    let color = PALETTE[title.bytes().map(usize::from).sum::<usize>() % PALETTE.len()];
+    let svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"{color}\"/>\
+         <text x=\"50%\" y=\"50%\" dominant-baseline=\"middle\" text-anchor=\"middle\" \
+         font-family=\"sans-serif\" font-size=\"{size}\" fill=\"#ffffff\">{initials}</text></svg>",
+        w = width,
+        h = height,
+        color = color,
+        size = width.min(height) / 3,
+        initials = initials,
+    );
+    format!("data:image/svg+xml;base64,{}", general_purpose::STANDARD.encode(svg))
+}
+
+fn categories(groups: &[Group]) -> Value {
+    groups
+        .iter()
+        .map(|(id, name, _)| json!({ "category_id": id, "category_name": name, "parent_id": 0 }))
+        .collect()
+}
+
+/// Every item of a content type with its category id, numbered from `first_id`
+fn items(groups: &[Group], first_id: i64) -> Vec<(i64, &'static str, &'static str)> {
+    groups
+        .iter()
+        .flat_map(|(category_id, _, titles)| titles.iter().map(move |title| (*category_id, *title)))
+        .enumerate()
+        .map(|(index, (category_id, title))| (first_id + index as i64, category_id, title))
+        .collect()
+}
+
+fn epg_channel_id(name: &str) -> String {
+    format!("{}.demo", name.to_lowercase().replace(' ', ""))
+}
+
+fn live_streams(added: i64) -> Vec<Value> {
+    items(LIVE_CATEGORIES, 1001)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (stream_id, category_id, name))| {
+            json!({
+                "num": index + 1,
+                "name": name,
+                "stream_type": "live",
+                "stream_id": stream_id,
+                "stream_icon": placeholder_image(name, 256, 256),
+                "epg_channel_id": epg_channel_id(name),
+                "added": added.to_string(),
+                "category_id": category_id,
+                "custom_sid": "",
+                "tv_archive": 1,
+                "direct_source": "",
+                "tv_archive_duration": 3,
+            })
+        })
+        .collect()
+}
+
+fn movie_streams(added: i64) -> Vec<Value> {
+    items(MOVIE_CATEGORIES, 2001)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (stream_id, category_id, name))| {
+            let rating = 5.0 + (index % 5) as f64;
+            json!({
+                "num": index + 1,
+                "name": name,
+                "stream_type": "movie",
+                "stream_id": stream_id,
+                "stream_icon": placeholder_image(name, 300, 450),
+                "rating": format!("{:.1}", rating),
+                "rating_5based": rating / 2.0,
+                "added": (added - index as i64 * 86_400).to_string(),
+                "category_id": category_id,
+                "container_extension": "mp4",
+                "custom_sid": "",
+                "direct_source": "",
+            })
+        })
+        .collect()
+}
+
+fn movie_info(stream: &Value) -> Value {
+    let name = stream["name"].as_str().unwrap_or_default();
+    json!({
+        "info": {
+            "name": name,
+            "movie_image": stream["stream_icon"],
+            "backdrop_path": [placeholder_image(name, 1280, 720)],
+            "plot": format!("{} is a demo film. Its details, artwork and rating are generated by xTauri.", name),
+            "cast": "Alex Morgan, Sam Rivera, Jordan Lee",
+            "director": "Casey Quinn",
+            "genre": "Demo",
+            "releasedate": "2020-01-01",
+            "duration_secs": 5400,
+            "duration": "01:30:00",
+            "rating": stream["rating"],
+        },
+        "movie_data": {
+            "stream_id": stream["stream_id"],
+            "name": name,
+            "added": stream["added"],
+            "category_id": stream["category_id"],
+            "container_extension": stream["container_extension"],
+            "custom_sid": "",
+            "direct_source": "",
+        },
+    })
+}
+
+fn series_list(added: i64) -> Vec<Value> {
+    items(SERIES_CATEGORIES, 3001)
+        .into_iter()
+        .enumerate()
+        .map(|(index, (series_id, category_id, name))| {
+            let rating = 6.0 + (index % 4) as f64;
+            json!({
+                "num": index + 1,
+                "name": name,
+                "series_id": series_id,
+                "cover": placeholder_image(name, 300, 450),
+                "plot": format!("{} is a demo series with {} seasons.", name, SEASONS_PER_SERIES),
+                "cast": "Robin Hayes, Taylor Brooks",
+                "director": "Morgan Ellis",
+                "genre": "Demo",
+                "releaseDate": "2021-09-01",
+                "last_modified": (added - index as i64 * 3_600).to_string(),
+                "rating": format!("{:.1}", rating),
+                "rating_5based": rating / 2.0,
+                "backdrop_path": [placeholder_image(name, 1280, 720)],
+                "youtube_trailer": "",
+                "episode_run_time": "45",
+                "category_id": category_id,
+            })
+        })
+        .collect()
+}
+
+fn series_info(series: &Value, added: i64) -> Value {
+    let name = series["name"].as_str().unwrap_or_default();
+    let series_id = series["series_id"].as_i64().unwrap_or_default();
+    let seasons: Vec<Value> = (1..=SEASONS_PER_SERIES)
+        .map(|season| {
+            json!({
+                "season_number": season,
+                "name": format!("Season {}", season),
+                "episode_count": EPISODES_PER_SEASON.to_string(),
+                "overview": format!("Season {} of {}.", season, name),
+                "air_date": format!("{}-09-01", 2020 + season),
+                "cover": placeholder_image(&format!("{} S{}", name, season), 300, 450),
+                "vote_average": series["rating"],
+            })
+        })
+        .collect();
+    let episodes: serde_json::Map<String, Value> = (1..=SEASONS_PER_SERIES)
+        .map(|season| {
+            let list: Vec<Value> = (1..=EPISODES_PER_SEASON)
+                .map(|episode| {
+                    let title = format!("Episode {}", episode);
+                    json!({
+                        "id": (series_id * 100 + season * 10 + episode).to_string(),
+                        "episode_num": episode,
+                        "title": format!("{} - S{:02}E{:02} - {}", name, season, episode, title),
+                        "container_extension": "mkv",
+                        "info": {
+                            "plot": format!("{} of season {}.", title, season),
+                            "duration_secs": 2700,
+                            "duration": "00:45:00",
+                            "movie_image": placeholder_image(&format!("{} {}", season, episode), 640, 360),
+                        },
+                        "custom_sid": "",
+                        "added": added.to_string(),
+                        "season": season,
+                        "direct_source": "",
+                    })
+                })
+                .collect();
+            (season.to_string(), Value::Array(list))
+        })
+        .collect();
+    json!({
+        "seasons": seasons,
+        "info": {
+            "name": name,
+            "cover": series["cover"],
+            "plot": series["plot"],
+            "cast": series["cast"],
+            "director": series["director"],
+            "genre": series["genre"],
+            "releaseDate": series["releaseDate"],
+            "rating": series["rating"],
+            "backdrop_path": series["backdrop_path"],
+            "episode_run_time": series["episode_run_time"],
+            "category_id": series["category_id"],
+        },
+        "episodes": episodes,
+    })
+}
+
+/// Hourly programmes around `now` for a channel
+fn epg_listings(channel: &Value, now: DateTime<Utc>) -> Value {
+    let name = channel["name"].as_str().unwrap_or_default();
+    let stream_id = channel["stream_id"].as_i64().unwrap_or_default();
+    let epg_id = epg_channel_id(name);
+    let hour = now.duration_trunc(Duration::hours(1)).unwrap_or(now);
+    let listings: Vec<Value> = (-EPG_HOURS_BEFORE..EPG_HOURS_AFTER)
+        .map(|offset| {
+            let start = hour + Duration::hours(offset);
+            let end = start + Duration::hours(1);
+            let title = PROGRAMME_TITLES[(stream_id + offset).rem_euclid(PROGRAMME_TITLES.len() as i64) as usize];
+            json!({
+                "id": format!("{}{}", stream_id, start.timestamp()),
+                "epg_id": stream_id.to_string(),
+                "title": title,
+                "lang": "en",
+                "start": start.format("%Y-%m-%d %H:%M:%S").to_string(),
+                "end": end.format("%Y-%m-%d %H:%M:%S").to_string(),
+                "description": format!("{} on {}.", title, name),
+                "channel_id": epg_id,
+                "start_timestamp": start.timestamp().to_string(),
+                "stop_timestamp": end.timestamp().to_string(),
+                "has_archive": i32::from(offset < 0),
+            })
+        })
+        .collect();
+    json!({ "epg_listings": listings })
+}
+
+fn auth_response(now: DateTime<Utc>) -> Value {
+    json!({
+        "user_info": {
+            "username": DEMO_CREDENTIAL,
+            "password": DEMO_CREDENTIAL,
+            "message": "Demo account",
+            "auth": 1,
+            "status": "Active",
+            "exp_date": (now + Duration::days(365)).timestamp().to_string(),
+            "is_trial": "1",
+            "active_cons": "0",
+            "created_at": now.timestamp().to_string(),
+            "max_connections": "1",
+            "allowed_output_formats": ["m3u8", "ts"],
+        },
+        "server_info": {
+            "url": DEMO_HOST,
+            "port": "80",
+            "https_port": "443",
+            "server_protocol": "http",
+            "timezone": "UTC",
+            "timestamp_now": now.timestamp(),
+            "time_now": now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+    })
+}
+
+fn write(dir: &Path, query: &str, response: &Value) -> Result<()> {
+    let url = Url::parse(&format!(
+        "http://{}/player_api.php?username={}&password={}{}",
+        DEMO_HOST, DEMO_CREDENTIAL, DEMO_CREDENTIAL, query
+    ))
+    .map_err(|e| XTauriError::internal(format!("Invalid demo request: {}", e)))?;
+    recording::write_fixture(dir, &url, response)
+}
+
+/// Write the demo catalog to `dir` as fixtures, with the guide and dates around `now`
+///
+/// Returns how many fixtures were written.
+pub fn generate_fixtures(dir: &Path, now: DateTime<Utc>) -> Result<usize> {
+    std::fs::create_dir_all(dir).map_err(|_| XTauriError::directory_creation(dir.display().to_string()))?;
+    let added = now.timestamp() - 7 * 86_400;
+    let mut written = 0;
+    let mut put = |query: String, response: Value| -> Result<()> {
+        write(dir, &query, &response)?;
+        written += 1;
+        Ok(())
+    };
+
+    put(String::new(), auth_response(now))?;
+
+    let content: [(&str, &str, &[Group], Vec<Value>); 3] = [
+        ("get_live_categories", "get_live_streams", LIVE_CATEGORIES, live_streams(added)),
+        ("get_vod_categories", "get_vod_streams", MOVIE_CATEGORIES, movie_streams(added)),
+        ("get_series_categories", "get_series", SERIES_CATEGORIES, series_list(added)),
+    ];
+    for (categories_action, list_action, groups, list) in &content {
+        put(format!("&action={}", categories_action), categories(groups))?;
+        put(format!("&action={}", list_action), Value::Array(list.clone()))?;
+        for (category_id, _, _) in groups.iter() {
+            let in_category: Vec<Value> = list.iter().filter(|item| item["category_id"] == *category_id).cloned().collect();
+            put(format!("&action={}&category_id={}", list_action, category_id), Value::Array(in_category))?;
+        }
+    }
+
+    let [(_, _, _, channels), (_, _, _, movies), (_, _, _, series)] = content;
+    for channel in &channels {
+        let listings = epg_listings(channel, now);
+        put(format!("&action=get_short_epg&stream_id={}", channel["stream_id"]), listings.clone())?;
+        put(format!("&action=get_simple_data_table&stream_id={}", channel["stream_id"]), listings)?;
+    }
+    for movie in &movies {
+        put(format!("&action=get_vod_info&vod_id={}", movie["stream_id"]), movie_info(movie))?;
+    }
+    for show in &series {
+        put(format!("&action=get_series_info&series_id={}", show["series_id"]), series_info(show, added))?;
+    }
+
+    Ok(written)
+}
+
+/// Create the demo profile, or refresh the existing one; returns its id
+///
+/// The demo catalog is regenerated each time so its guide covers the current day.
+#[tauri::command]
+pub async fn create_demo_profile(state: State<'_, XtreamState>) -> std::result::Result<String, String> {
+    let dir = recording::demo_fixture_dir().ok_or("Failed to find the data directory")?;
+    generate_fixtures(&dir, Utc::now()).map_err(|e| e.to_string())?;
+
+    let profiles = state
+        .profile_manager
+        .get_profiles_async_wrapper()
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some(profile) = profiles
+        .iter()
+        .find(|profile| Url::parse(&profile.url).ok().and_then(|url| url.host_str().map(str::to_string)).as_deref() == Some(DEMO_HOST))
+    {
+        return Ok(profile.id.clone());
+    }
+
+    // Don't collide with a user's own profile called "Demo"
+    let name = (1..)
+        .map(|n| if n == 1 { DEMO_PROFILE_NAME.to_string() } else { format!("{} {}", DEMO_PROFILE_NAME, n) })
+        .find(|name| !profiles.iter().any(|profile| &profile.name == name))
+        .unwrap_or_else(|| DEMO_PROFILE_NAME.to_string());
+    state
+        .profile_manager
+        .create_profile_async_wrapper(CreateProfileRequest {
+            name,
+            url: format!("http://{}", DEMO_HOST),
+            username: DEMO_CREDENTIAL.to_string(),
+            password: DEMO_CREDENTIAL.to_string(),
+        })
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content_cache::favorites_sync::parse_series_details;
+    use crate::content_cache::sync_scheduler::SyncScheduler;
+
+    fn fixture(dir: &Path, query: &str) -> Value {
+        let url = Url::parse(&format!("http://{}/player_api.php?username=x&password=y{}", DEMO_HOST, query)).unwrap();
+        let name = recording::fixture_name(&url);
+        let contents: Value = serde_json::from_str(&std::fs::read_to_string(dir.join(name)).unwrap()).unwrap();
+        contents["response"].clone()
+    }
+
+    #[test]
+    fn test_demo_catalog_is_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-03-14T15:20:00Z").unwrap().with_timezone(&Utc);
+        let written = generate_fixtures(dir.path(), now).unwrap();
+        assert_eq!(written, std::fs::read_dir(dir.path()).unwrap().count());
+
+        assert_eq!(fixture(dir.path(), "")["user_info"]["auth"], 1);
+        assert_eq!(fixture(dir.path(), "&action=get_live_categories").as_array().unwrap().len(), 4);
+        let channels = fixture(dir.path(), "&action=get_live_streams");
+        assert_eq!(channels.as_array().unwrap().len(), 12);
+        assert!(channels[0]["stream_icon"].as_str().unwrap().starts_with("data:image/svg+xml;base64,"));
+        assert_eq!(fixture(dir.path(), "&action=get_vod_streams&category_id=12").as_array().unwrap().len(), 4);
+
+        let guide = fixture(dir.path(), "&action=get_short_epg&stream_id=1001");
+        let listings = guide["epg_listings"].as_array().unwrap();
+        assert_eq!(listings.len() as i64, EPG_HOURS_BEFORE + EPG_HOURS_AFTER);
+        let now_ts = now.timestamp().to_string();
+        assert!(listings.iter().any(|programme| {
+            programme["start_timestamp"].as_str().unwrap() <= now_ts.as_str()
+                && programme["stop_timestamp"].as_str().unwrap() > now_ts.as_str()
+        }));
+
+        let movie = fixture(dir.path(), "&action=get_vod_info&vod_id=2001");
+        assert_eq!(movie["movie_data"]["name"], "The Long Harbor");
+
+        let series = SyncScheduler::parse_series(&fixture(dir.path(), "&action=get_series")).unwrap();
+        assert_eq!(series.len(), 6);
+        let details = parse_series_details(series[0].clone(), &fixture(dir.path(), "&action=get_series_info&series_id=3001"));
+        assert_eq!(details.seasons.len() as i64, SEASONS_PER_SERIES);
+        assert_eq!(details.episodes.len() as i64, SEASONS_PER_SERIES * EPISODES_PER_SEASON);
+    }
+
+    #[test]
+    fn test_placeholder_images_differ_by_title() {
+        let news = placeholder_image("World News 24", 256, 256);
+        let sport = placeholder_image("Sports Arena", 256, 256);
+        assert_ne!(news, sport);
+        let svg = general_purpose::STANDARD
+            .decode(news.trim_start_matches("data:image/svg+xml;base64,"))
+            .unwrap();
+        assert!(String::from_utf8(svg).unwrap().contains(">WN<"));
+    }
+}
@@ -1,11 +1,17 @@
+use crate::content_cache::ordering::{plan_move, ChannelSortMode};
+use crate::content_id::{canonical_type, ContentId};
 use crate::error::{Result, XTauriError};
-use rusqlite::{Connection, params};
+use crate::events::{self, AppEvent};
+use crate::expiry;
+use crate::xtream::tags;
+use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
 use uuid::Uuid;
 use chrono::Utc;
 
 /// Favorite item for Xtream content
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamFavorite {
     pub id: String,
     pub profile_id: String,
@@ -16,14 +22,84 @@ pub struct XtreamFavorite {
 }
 
 /// Request to add a favorite
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct AddFavoriteRequest {
     pub profile_id: String,
+    /// One of `ContentId::TYPES`; other spellings are stored as that type
     pub content_type: String,
     pub content_id: String,
     pub content_data: serde_json::Value,
 }
 
+/// Identifies a favorite by what it points at, which is unique per profile
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct FavoriteIdentity {
+    pub content_type: String,
+    pub content_id: String,
+}
+
+impl FavoriteIdentity {
+    /// Identity as stored, whatever spelling of the content type it was given
+    pub fn new(content_type: &str, content_id: &str) -> Self {
+        Self {
+            content_type: canonical_type(content_type).unwrap_or(content_type).to_string(),
+            content_id: content_id.trim().to_string(),
+        }
+    }
+}
+
+/// Favorites that changed in one operation, so open views can patch their copy
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct FavoritesChange {
+    pub profile_id: String,
+    pub added: Vec<FavoriteIdentity>,
+    pub removed: Vec<FavoriteIdentity>,
+    /// Favorites whose position in the custom order changed
+    pub moved: Vec<FavoriteIdentity>,
+}
+
+impl FavoritesChange {
+    pub fn new(profile_id: &str) -> Self {
+        Self {
+            profile_id: profile_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn added(profile_id: &str, identity: FavoriteIdentity) -> Self {
+        Self {
+            added: vec![identity],
+            ..Self::new(profile_id)
+        }
+    }
+
+    pub fn removed(profile_id: &str, identities: Vec<FavoriteIdentity>) -> Self {
+        Self {
+            removed: identities,
+            ..Self::new(profile_id)
+        }
+    }
+
+    pub fn moved(profile_id: &str, identity: FavoriteIdentity) -> Self {
+        Self {
+            moved: vec![identity],
+            ..Self::new(profile_id)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+
+    /// Emit the `favorites_changed` event to every window, unless nothing changed
+    pub fn emit(self, app_handle: &AppHandle) {
+        if self.is_empty() {
+            return;
+        }
+        events::emit(app_handle, AppEvent::FavoritesChanged(self));
+    }
+}
+
 /// Database operations for Xtream favorites
 pub struct XtreamFavoritesDb;
 
@@ -33,6 +109,8 @@ impl XtreamFavoritesDb {
         conn: &Connection,
         request: &AddFavoriteRequest,
     ) -> Result<String> {
+        let key = ContentId::new(&request.content_type, &request.content_id)?;
+        
         let favorite_id = Uuid::new_v4().to_string();
         let now = Utc::now();
         
@@ -46,8 +124,8 @@ impl XtreamFavoritesDb {
             params![
                 favorite_id,
                 request.profile_id,
-                request.content_type,
-                request.content_id,
+                key.content_type(),
+                key.id(),
                 content_data_bytes,
                 now.to_rfc3339(),
             ],
@@ -62,6 +140,35 @@ impl XtreamFavoritesDb {
         Ok(favorite_id)
     }
     
+    /// Get a favorite by ID
+    pub fn get_favorite(
+        conn: &Connection,
+        favorite_id: &str,
+    ) -> Result<Option<XtreamFavorite>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, content_type, content_id, content_data, created_at 
+             FROM xtream_favorites 
+             WHERE id = ?1"
+        )?;
+        
+        let favorite = stmt.query_row(params![favorite_id], |row| {
+            let content_data_bytes: Vec<u8> = row.get(4)?;
+            let content_data: serde_json::Value = serde_json::from_slice(&content_data_bytes)
+                .map_err(|_| rusqlite::Error::InvalidColumnType(4, "content_data".to_string(), rusqlite::types::Type::Blob))?;
+            
+            Ok(XtreamFavorite {
+                id: row.get(0)?,
+                profile_id: row.get(1)?,
+                content_type: row.get(2)?,
+                content_id: row.get(3)?,
+                content_data,
+                created_at: row.get(5)?,
+            })
+        }).optional()?;
+        
+        Ok(favorite)
+    }
+    
     /// Remove a favorite by ID
     pub fn remove_favorite(
         conn: &Connection,
@@ -86,9 +193,10 @@ impl XtreamFavoritesDb {
         content_type: &str,
         content_id: &str,
     ) -> Result<()> {
+        let key = ContentId::new(content_type, content_id)?;
         let rows_affected = conn.execute(
             "DELETE FROM xtream_favorites WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3",
-            params![profile_id, content_type, content_id],
+            params![profile_id, key.content_type(), key.id()],
         )?;
         
         if rows_affected == 0 {
@@ -103,14 +211,53 @@ impl XtreamFavoritesDb {
         conn: &Connection,
         profile_id: &str,
     ) -> Result<Vec<XtreamFavorite>> {
-        let mut stmt = conn.prepare(
+        Self::get_favorites_sorted(conn, profile_id, None, ChannelSortMode::Default)
+    }
+    
+    /// Get favorites by content type for a profile
+    pub fn get_favorites_by_type(
+        conn: &Connection,
+        profile_id: &str,
+        content_type: &str,
+    ) -> Result<Vec<XtreamFavorite>> {
+        Self::get_favorites_sorted(conn, profile_id, Some(content_type), ChannelSortMode::Default)
+    }
+    
+    /// Get favorites, optionally of one content type, in the requested order
+    ///
+    /// The default order is newest first; `Custom` follows the positions set by
+    /// `move_favorite`, with favorites that were never moved after them, and
+    /// `Popularity` puts the highest scored in `xtream_popularity` first.
+    pub fn get_favorites_sorted(
+        conn: &Connection,
+        profile_id: &str,
+        content_type: Option<&str>,
+        sort_mode: ChannelSortMode,
+    ) -> Result<Vec<XtreamFavorite>> {
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Vec::new());
+        }
+        let content_type = content_type.map(|t| canonical_type(t).unwrap_or(t));
+        let order_by = match sort_mode {
+            ChannelSortMode::Default => "created_at DESC",
+            ChannelSortMode::Custom => "position NULLS LAST, created_at DESC",
+            ChannelSortMode::Popularity => {
+                "(SELECT score FROM xtream_popularity p
+                  WHERE p.profile_id = xtream_favorites.profile_id AND p.content_type = xtream_favorites.content_type
+                    AND p.content_id = CAST(xtream_favorites.content_id AS INTEGER)) DESC NULLS LAST,
+                 created_at DESC"
+            }
+        };
+        let mut stmt = conn.prepare(&format!(
             "SELECT id, profile_id, content_type, content_id, content_data, created_at 
              FROM xtream_favorites 
-             WHERE profile_id = ?1 
-             ORDER BY created_at DESC"
-        )?;
+             WHERE profile_id = ?1 AND (?2 IS NULL OR content_type = ?2){} 
+             ORDER BY {}",
+            tags::not_hidden_clause("xtream_favorites", "xtream_favorites.content_type", "xtream_favorites.content_id"),
+            order_by
+        ))?;
         
-        let favorite_iter = stmt.query_map(params![profile_id], |row| {
+        let favorite_iter = stmt.query_map(params![profile_id, content_type], |row| {
             let content_data_bytes: Vec<u8> = row.get(4)?;
             let content_data: serde_json::Value = serde_json::from_slice(&content_data_bytes)
                 .map_err(|e| rusqlite::Error::InvalidColumnType(4, "content_data".to_string(), rusqlite::types::Type::Blob))?;
@@ -133,40 +280,42 @@ impl XtreamFavoritesDb {
         Ok(favorites)
     }
     
-    /// Get favorites by content type for a profile
-    pub fn get_favorites_by_type(
+    /// Move a favorite to just after another favorite of the same type
+    ///
+    /// `after_content_id` of `None` moves it to the top of the custom order.
+    pub fn move_favorite(
         conn: &Connection,
         profile_id: &str,
         content_type: &str,
-    ) -> Result<Vec<XtreamFavorite>> {
+        content_id: &str,
+        after_content_id: Option<&str>,
+    ) -> Result<()> {
         let mut stmt = conn.prepare(
-            "SELECT id, profile_id, content_type, content_id, content_data, created_at 
-             FROM xtream_favorites 
+            "SELECT content_id, position FROM xtream_favorites 
              WHERE profile_id = ?1 AND content_type = ?2 
-             ORDER BY created_at DESC"
+             ORDER BY position NULLS LAST, created_at DESC"
         )?;
-        
-        let favorite_iter = stmt.query_map(params![profile_id, content_type], |row| {
-            let content_data_bytes: Vec<u8> = row.get(4)?;
-            let content_data: serde_json::Value = serde_json::from_slice(&content_data_bytes)
-                .map_err(|e| rusqlite::Error::InvalidColumnType(4, "content_data".to_string(), rusqlite::types::Type::Blob))?;
-            
-            Ok(XtreamFavorite {
-                id: row.get(0)?,
-                profile_id: row.get(1)?,
-                content_type: row.get(2)?,
-                content_id: row.get(3)?,
-                content_data,
-                created_at: row.get(5)?,
-            })
-        })?;
-        
-        let mut favorites = Vec::new();
-        for favorite in favorite_iter {
-            favorites.push(favorite?);
+        let items = stmt
+            .query_map(params![profile_id, content_type], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<f64>>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        
+        let after = after_content_id.map(str::to_string);
+        let updates = plan_move(&items, &content_id.to_string(), after.as_ref())
+            .map_err(|_| XTauriError::internal("Favorite not found".to_string()))?;
+        
+        let tx = conn.unchecked_transaction()?;
+        for (id, position) in updates {
+            tx.execute(
+                "UPDATE xtream_favorites SET position = ?1 
+                 WHERE profile_id = ?2 AND content_type = ?3 AND content_id = ?4",
+                params![position, profile_id, content_type, id],
+            )?;
         }
+        tx.commit()?;
         
-        Ok(favorites)
+        Ok(())
     }
     
     /// Check if an item is favorited
@@ -176,9 +325,10 @@ impl XtreamFavoritesDb {
         content_type: &str,
         content_id: &str,
     ) -> Result<bool> {
+        let key = ContentId::new(content_type, content_id)?;
         let count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM xtream_favorites WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3",
-            params![profile_id, content_type, content_id],
+            params![profile_id, key.content_type(), key.id()],
             |row| row.get(0),
         )?;
         
@@ -186,16 +336,26 @@ impl XtreamFavoritesDb {
     }
     
     /// Clear all favorites for a profile
+    ///
+    /// # Returns
+    /// The favorites that were removed
     pub fn clear_favorites(
         conn: &Connection,
         profile_id: &str,
-    ) -> Result<()> {
-        conn.execute(
-            "DELETE FROM xtream_favorites WHERE profile_id = ?1",
-            params![profile_id],
+    ) -> Result<Vec<FavoriteIdentity>> {
+        let mut stmt = conn.prepare(
+            "DELETE FROM xtream_favorites WHERE profile_id = ?1 RETURNING content_type, content_id"
         )?;
-        
-        Ok(())
+        let removed = stmt
+            .query_map(params![profile_id], |row| {
+                Ok(FavoriteIdentity {
+                    content_type: row.get(0)?,
+                    content_id: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        
+        Ok(removed)
     }
 }
 
@@ -232,11 +392,13 @@ mod tests {
                 content_id TEXT NOT NULL,
                 content_data BLOB NOT NULL,
                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                position REAL,
                 FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE,
                 UNIQUE(profile_id, content_type, content_id)
             )",
             [],
         ).unwrap();
+        tags::create_table(&conn).unwrap();
         
         // Insert a test profile
         conn.execute(
@@ -276,6 +438,68 @@ mod tests {
         assert_eq!(favorites[0].content_id, "123");
     }
     
+    #[test]
+    fn test_add_vod_and_episode_favorites() {
+        let conn = create_test_db();
+        for (content_type, content_id) in [("movie", "7"), ("series", "8"), ("episode", "9001")] {
+            let request = AddFavoriteRequest {
+                content_type: content_type.to_string(),
+                content_id: content_id.to_string(),
+                ..create_test_favorite_request()
+            };
+            XtreamFavoritesDb::add_favorite(&conn, &request).unwrap();
+        }
+        
+        let episodes = XtreamFavoritesDb::get_favorites_by_type(&conn, "test-profile-1", "episode").unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].content_id, "9001");
+        
+        let request = AddFavoriteRequest {
+            content_type: "playlist".to_string(),
+            ..create_test_favorite_request()
+        };
+        assert!(XtreamFavoritesDb::add_favorite(&conn, &request).is_err());
+    }
+    
+    #[test]
+    fn test_hidden_favorites_are_left_out() {
+        let conn = create_test_db();
+        for content_id in ["7", "8"] {
+            let request = AddFavoriteRequest {
+                content_type: "movie".to_string(),
+                content_id: content_id.to_string(),
+                ..create_test_favorite_request()
+            };
+            XtreamFavoritesDb::add_favorite(&conn, &request).unwrap();
+        }
+        tags::XtreamTagsDb::add_tag(&conn, "test-profile-1", "movies", "8", tags::HIDDEN_TAG).unwrap();
+        
+        let movies = XtreamFavoritesDb::get_favorites_by_type(&conn, "test-profile-1", "movie").unwrap();
+        assert_eq!(movies.iter().map(|f| f.content_id.as_str()).collect::<Vec<_>>(), vec!["7"]);
+        
+        tags::XtreamTagsDb::remove_tag(&conn, "test-profile-1", "movie", "8", tags::HIDDEN_TAG).unwrap();
+        assert_eq!(XtreamFavoritesDb::get_favorites(&conn, "test-profile-1").unwrap().len(), 2);
+    }
+    
+    #[test]
+    fn test_favorite_type_spellings_share_one_key() {
+        let conn = create_test_db();
+        let request = AddFavoriteRequest {
+            content_type: "xtream-movie".to_string(),
+            content_id: "42".to_string(),
+            ..create_test_favorite_request()
+        };
+        XtreamFavoritesDb::add_favorite(&conn, &request).unwrap();
+        
+        assert!(XtreamFavoritesDb::is_favorite(&conn, "test-profile-1", "movie", "42").unwrap());
+        assert!(XtreamFavoritesDb::is_favorite(&conn, "test-profile-1", "Movies", "42").unwrap());
+        let duplicate = AddFavoriteRequest { content_type: "vod".to_string(), ..request };
+        assert!(XtreamFavoritesDb::add_favorite(&conn, &duplicate).is_err());
+        
+        XtreamFavoritesDb::remove_favorite_by_content(&conn, "test-profile-1", "movie", "42").unwrap();
+        assert!(XtreamFavoritesDb::get_favorites(&conn, "test-profile-1").unwrap().is_empty());
+    }
+    
     #[test]
     fn test_add_duplicate_favorite() {
         let conn = create_test_db();
@@ -297,8 +521,12 @@ mod tests {
         
         let favorite_id = XtreamFavoritesDb::add_favorite(&conn, &request).unwrap();
         
+        let favorite = XtreamFavoritesDb::get_favorite(&conn, &favorite_id).unwrap().unwrap();
+        assert_eq!(favorite.content_id, "123");
+        
         // Remove favorite
         XtreamFavoritesDb::remove_favorite(&conn, &favorite_id).unwrap();
+        assert!(XtreamFavoritesDb::get_favorite(&conn, &favorite_id).unwrap().is_none());
         
         // Verify it's gone
         let favorites = XtreamFavoritesDb::get_favorites(&conn, "test-profile-1").unwrap();
@@ -393,10 +621,63 @@ mod tests {
         assert_eq!(favorites.len(), 5);
         
         // Clear all
-        XtreamFavoritesDb::clear_favorites(&conn, "test-profile-1").unwrap();
+        let removed = XtreamFavoritesDb::clear_favorites(&conn, "test-profile-1").unwrap();
+        assert_eq!(removed.len(), 5);
+        assert!(removed.contains(&FavoriteIdentity::new("channel", "3")));
         
         // Verify they're gone
         let favorites = XtreamFavoritesDb::get_favorites(&conn, "test-profile-1").unwrap();
         assert_eq!(favorites.len(), 0);
     }
+    
+    #[test]
+    fn test_move_favorite_custom_order() {
+        let conn = create_test_db();
+        
+        for (i, created_at) in ["2024-01-01", "2024-01-02", "2024-01-03"].iter().enumerate() {
+            conn.execute(
+                "INSERT INTO xtream_favorites (id, profile_id, content_type, content_id, content_data, created_at) 
+                 VALUES (?1, 'test-profile-1', 'channel', ?1, X'7B7D', ?2)",
+                params![i.to_string(), created_at],
+            ).unwrap();
+        }
+        let ids = |sort_mode| {
+            XtreamFavoritesDb::get_favorites_sorted(&conn, "test-profile-1", Some("channel"), sort_mode)
+                .unwrap()
+                .into_iter()
+                .map(|favorite| favorite.content_id)
+                .collect::<Vec<_>>()
+        };
+        
+        // Custom order starts out as newest first
+        assert_eq!(ids(ChannelSortMode::Custom), vec!["2", "1", "0"]);
+        
+        XtreamFavoritesDb::move_favorite(&conn, "test-profile-1", "channel", "0", None).unwrap();
+        assert_eq!(ids(ChannelSortMode::Custom), vec!["0", "2", "1"]);
+        
+        XtreamFavoritesDb::move_favorite(&conn, "test-profile-1", "channel", "1", Some("0")).unwrap();
+        assert_eq!(ids(ChannelSortMode::Custom), vec!["0", "1", "2"]);
+        
+        // The default order ignores positions
+        assert_eq!(ids(ChannelSortMode::Default), vec!["2", "1", "0"]);
+        assert!(XtreamFavoritesDb::move_favorite(&conn, "test-profile-1", "channel", "9", None).is_err());
+    }
+    
+    #[test]
+    fn test_expired_profile_lists_no_favorites() {
+        let conn = create_test_db();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('expired-favorites', 'Trial', 'http://example.com', 'testuser', X'00')",
+            [],
+        ).unwrap();
+        let mut request = create_test_favorite_request();
+        request.profile_id = "expired-favorites".to_string();
+        XtreamFavoritesDb::add_favorite(&conn, &request).unwrap();
+        assert_eq!(XtreamFavoritesDb::get_favorites(&conn, "expired-favorites").unwrap().len(), 1);
+        
+        let _guard = expiry::expire_for_test(expiry::ExpirySource::Profile, "expired-favorites");
+        assert!(XtreamFavoritesDb::get_favorites(&conn, "expired-favorites").unwrap().is_empty());
+        assert!(XtreamFavoritesDb::get_favorites_by_type(&conn, "expired-favorites", "channel").unwrap().is_empty());
+    }
 }
@@ -1,14 +1,18 @@
 use serde::{Deserialize, Serialize};
 use crate::content_cache::{XtreamChannel, XtreamMovie, XtreamSeries};
+use crate::country;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct ChannelFilter {
     pub name: Option<String>,
     pub category_id: Option<String>,
     pub group: Option<String>,
+    /// ISO code, tag or name of the channels' country
+    #[serde(default)]
+    pub country: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct MovieFilter {
     pub name: Option<String>,
     pub category_id: Option<String>,
@@ -17,7 +21,7 @@ pub struct MovieFilter {
     pub min_rating: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SeriesFilter {
     pub name: Option<String>,
     pub category_id: Option<String>,
@@ -63,6 +67,13 @@ pub fn filter_channels(
                 }
             }
 
+            // Country filter
+            if let Some(country) = &filter.country {
+                if channel.country.as_deref() != Some(country::filter_code(country).as_str()) {
+                    return false;
+                }
+            }
+
             true
         })
         .cloned()
@@ -207,6 +218,10 @@ mod tests {
             tv_archive: None,
             direct_source: None,
             tv_archive_duration: None,
+            url_overridden: false,
+            country: None,
+            country_flag: None,
+            reliability: None,
         }
     }
 
@@ -239,6 +254,7 @@ mod tests {
             director: None,
             plot: None,
             youtube_trailer: None,
+            age_rating: None,
         }
     }
 
@@ -267,6 +283,7 @@ mod tests {
             rating_5based: rating,
             episode_run_time: None,
             category_id: category_id.map(|s| s.to_string()),
+            age_rating: None,
         }
     }
 
@@ -282,6 +299,7 @@ mod tests {
             name: Some("sport".to_string()),
             category_id: None,
             group: None,
+            country: None,
         };
 
         let results = filter_channels(&channels, &filter);
@@ -301,6 +319,7 @@ mod tests {
             name: None,
             category_id: Some("1".to_string()),
             group: None,
+            country: None,
         };
 
         let results = filter_channels(&channels, &filter);
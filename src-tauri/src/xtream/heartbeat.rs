@@ -0,0 +1,309 @@
+// Playback position heartbeat
+//
+// The player reports its position every few seconds while something plays.
+// Heartbeats are held in memory and written to history at most once every
+// FLUSH_INTERVAL, and straight away when playback stops or the app exits, so
+// a long film doesn't update SQLite hundreds of times. Buffered positions are
+// also written to a small journal file next to the database, at most once every
+// JOURNAL_INTERVAL; if the app crashes before a flush, the positions in the
+// journal are recorded on the next start. Private playback is never buffered.
+use crate::content_id::canonical_type;
+use crate::error::{Result, XTauriError};
+use crate::xtream::{ProfileManager, XtreamState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, RunEvent};
+
+/// How often buffered positions are written to history while playing
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often the journal is rewritten while playing; a crash loses at most this much progress
+const JOURNAL_INTERVAL: Duration = Duration::from_secs(5);
+
+const JOURNAL_FILE: &str = "playback_heartbeat.json";
+
+/// A position that hasn't been written to history yet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingPosition {
+    pub profile_id: String,
+    pub content_type: String,
+    pub content_id: String,
+    pub position: f64,
+    pub duration: Option<f64>,
+}
+
+/// Buffer key of an item; content type aliases share one entry
+fn key(profile_id: &str, content_type: &str, content_id: &str) -> (String, String, String) {
+    let content_type = canonical_type(content_type).unwrap_or(content_type);
+    (profile_id.to_string(), content_type.to_string(), content_id.to_string())
+}
+
+impl PendingPosition {
+    fn key(&self) -> (String, String, String) {
+        key(&self.profile_id, &self.content_type, &self.content_id)
+    }
+}
+
+/// What a heartbeat calls for
+#[derive(Debug, PartialEq)]
+struct Due {
+    flush: bool,
+    journal: bool,
+}
+
+#[derive(Default)]
+struct Buffer {
+    positions: HashMap<(String, String, String), PendingPosition>,
+    flushed_at: Option<Instant>,
+    journaled_at: Option<Instant>,
+}
+
+impl Buffer {
+    /// Hold the latest position for its item
+    ///
+    /// A flush is due every FLUSH_INTERVAL. The journal is due every
+    /// JOURNAL_INTERVAL, and straight away for an item it doesn't hold yet.
+    fn record(&mut self, position: PendingPosition, now: Instant) -> Due {
+        let new_item = self.positions.insert(position.key(), position).is_none();
+        let flushed_at = *self.flushed_at.get_or_insert(now);
+        let journal = new_item
+            || self
+                .journaled_at
+                .is_none_or(|at| now.saturating_duration_since(at) >= JOURNAL_INTERVAL);
+        if journal {
+            self.journaled_at = Some(now);
+        }
+        Due {
+            flush: now.saturating_duration_since(flushed_at) >= FLUSH_INTERVAL,
+            journal,
+        }
+    }
+
+    fn take(&mut self, now: Instant) -> Vec<PendingPosition> {
+        self.flushed_at = Some(now);
+        self.positions.drain().map(|(_, position)| position).collect()
+    }
+
+    fn snapshot(&self) -> Vec<&PendingPosition> {
+        self.positions.values().collect()
+    }
+}
+
+static BUFFER: LazyLock<Mutex<Buffer>> = LazyLock::new(|| Mutex::new(Buffer::default()));
+
+fn lock_buffer() -> Result<std::sync::MutexGuard<'static, Buffer>> {
+    BUFFER
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("playback heartbeat buffer"))
+}
+
+fn journal_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("xtauri").join(JOURNAL_FILE))
+}
+
+/// Replace the journal with the buffered positions, or remove it when there are none
+fn write_journal(path: &Path, positions: &[&PendingPosition]) -> Result<()> {
+    if positions.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        };
+    }
+    let json = serde_json::to_vec(positions)
+        .map_err(|e| XTauriError::internal(format!("Failed to serialize playback positions: {}", e)))?;
+    // Write beside the journal and rename, so a crash mid-write leaves the previous one intact
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn read_journal(path: &Path) -> Vec<PendingPosition> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn sync_journal(buffer: &Buffer) {
+    let Some(path) = journal_path() else {
+        return;
+    };
+    if let Err(e) = write_journal(&path, &buffer.snapshot()) {
+        eprintln!("[HEARTBEAT] Failed to write playback journal: {}", e);
+    }
+}
+
+/// Buffer a position; returns whether buffered positions should be flushed now
+///
+/// A private playback is not buffered at all.
+pub fn record(position: PendingPosition, private: bool) -> Result<bool> {
+    if crate::xtream::history::playback_recording_suppressed(private) {
+        return Ok(false);
+    }
+    let mut buffer = lock_buffer()?;
+    let due = buffer.record(position, Instant::now());
+    // A flush rewrites the journal anyway
+    if due.journal && !due.flush {
+        sync_journal(&buffer);
+    }
+    Ok(due.flush)
+}
+
+/// Drop the buffered position of an item that is being written directly
+pub fn discard(profile_id: &str, content_type: &str, content_id: &str) {
+    if let Ok(mut buffer) = lock_buffer() {
+        if buffer.positions.remove(&key(profile_id, content_type, content_id)).is_some() {
+            sync_journal(&buffer);
+        }
+    }
+}
+
+async fn write_positions(manager: &ProfileManager, positions: Vec<PendingPosition>) -> usize {
+    let mut written = 0;
+    for pending in positions {
+        match manager
+            .update_playback_position(
+                &pending.profile_id,
+                &pending.content_type,
+                &pending.content_id,
+                pending.position,
+                pending.duration,
+            )
+            .await
+        {
+            Ok(()) => written += 1,
+            Err(e) => eprintln!(
+                "[HEARTBEAT] Failed to save position of {} {}: {}",
+                pending.content_type, pending.content_id, e
+            ),
+        }
+    }
+    written
+}
+
+/// Write every buffered position to history; returns how many were written
+pub async fn flush(manager: &ProfileManager) -> Result<usize> {
+    let positions = {
+        let mut buffer = lock_buffer()?;
+        let positions = buffer.take(Instant::now());
+        sync_journal(&buffer);
+        positions
+    };
+    Ok(write_positions(manager, positions).await)
+}
+
+/// Record positions left in the journal by a session that didn't flush them
+pub async fn recover(manager: &ProfileManager) {
+    let Some(path) = journal_path() else {
+        return;
+    };
+    let positions = read_journal(&path);
+    if !positions.is_empty() {
+        let written = write_positions(manager, positions).await;
+        println!("[HEARTBEAT] Recovered {} playback position(s) from the last session", written);
+    }
+    if let Err(e) = write_journal(&path, &[]) {
+        eprintln!("[HEARTBEAT] Failed to remove playback journal: {}", e);
+    }
+}
+
+/// Write buffered positions before the app exits; called for every run event
+pub fn on_run_event(app_handle: &AppHandle, event: &RunEvent) {
+    if !matches!(event, RunEvent::Exit) {
+        return;
+    }
+    if let Some(state) = app_handle.try_state::<XtreamState>() {
+        if let Err(e) = tauri::async_runtime::block_on(flush(&state.profile_manager)) {
+            eprintln!("[HEARTBEAT] Failed to save playback positions on exit: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(content_id: &str, position: f64) -> PendingPosition {
+        PendingPosition {
+            profile_id: "profile-1".to_string(),
+            content_type: "movie".to_string(),
+            content_id: content_id.to_string(),
+            position,
+            duration: Some(5400.0),
+        }
+    }
+
+    #[test]
+    fn test_heartbeats_coalesce_until_the_interval() {
+        let mut buffer = Buffer::default();
+        let start = Instant::now();
+
+        assert!(!buffer.record(position("101", 10.0), start).flush);
+        assert!(!buffer.record(position("101", 15.0), start + Duration::from_secs(5)).flush);
+        assert!(!buffer.record(position("202", 3.0), start + Duration::from_secs(10)).flush);
+        assert!(buffer.record(position("101", 25.0), start + FLUSH_INTERVAL).flush);
+
+        let mut flushed = buffer.take(start + FLUSH_INTERVAL);
+        flushed.sort_by(|a, b| a.content_id.cmp(&b.content_id));
+        assert_eq!(flushed, vec![position("101", 25.0), position("202", 3.0)]);
+        assert!(buffer.snapshot().is_empty());
+
+        // The interval restarts at the flush
+        assert!(
+            !buffer
+                .record(position("101", 30.0), start + FLUSH_INTERVAL + Duration::from_secs(5))
+                .flush
+        );
+    }
+
+    #[test]
+    fn test_journal_rewrites_are_debounced() {
+        let mut buffer = Buffer::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(buffer.record(position("101", 10.0), at(0)).journal);
+        assert!(!buffer.record(position("101", 12.0), at(2)).journal);
+        assert!(!buffer.record(position("101", 14.0), at(4)).journal);
+        assert!(buffer.record(position("101", 15.0), start + JOURNAL_INTERVAL).journal);
+
+        // A new item is journaled straight away
+        assert!(buffer.record(position("202", 1.0), start + JOURNAL_INTERVAL + Duration::from_secs(1)).journal);
+    }
+
+    #[test]
+    fn test_aliased_content_types_share_one_entry() {
+        let mut buffer = Buffer::default();
+        let start = Instant::now();
+        buffer.record(position("101", 10.0), start);
+        let mut aliased = position("101", 20.0);
+        aliased.content_type = "movies".to_string();
+        buffer.record(aliased, start);
+        assert_eq!(buffer.snapshot().len(), 1);
+
+        assert!(buffer.positions.remove(&key("profile-1", "movies", "101")).is_some());
+        assert!(buffer.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_journal_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        assert!(read_journal(&path).is_empty());
+
+        let pending = [position("101", 42.5), position("202", 7.0)];
+        write_journal(&path, &pending.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(read_journal(&path), pending.to_vec());
+
+        write_journal(&path, &[]).unwrap();
+        assert!(!path.exists());
+        write_journal(&path, &[]).unwrap();
+
+        std::fs::write(&path, b"{ truncated").unwrap();
+        assert!(read_journal(&path).is_empty());
+    }
+}
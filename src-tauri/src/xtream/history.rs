@@ -1,11 +1,36 @@
+use crate::content_id::{canonical_type, ContentId};
 use crate::error::{Result, XTauriError};
+use crate::expiry;
 use rusqlite::{Connection, params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Private playback for the rest of the session; reset on restart
+static PRIVATE_SESSION: AtomicBool = AtomicBool::new(false);
+
+/// Turn private playback on or off for the session
+pub fn set_private_session(enabled: bool) {
+    PRIVATE_SESSION.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether private playback is on for the session
+pub fn is_private_session() -> bool {
+    PRIVATE_SESSION.load(Ordering::Relaxed)
+}
+
+/// Whether playback should be kept out of history and continue watching
+///
+/// Every playback history write checks this, so a private playback or a
+/// private session leaves no trace whichever command records it. Marking
+/// episodes as watched is an explicit user action and is not affected.
+pub fn playback_recording_suppressed(private: bool) -> bool {
+    private || is_private_session()
+}
 
 /// History item for Xtream content
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamHistory {
     pub id: String,
     pub profile_id: String,
@@ -15,10 +40,13 @@ pub struct XtreamHistory {
     pub watched_at: String,
     pub position: Option<f64>,
     pub duration: Option<f64>,
+    /// Set when the item was marked as watched, rather than from playback position
+    #[serde(default)]
+    pub watched: bool,
 }
 
 /// Request to add a history item
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct AddHistoryRequest {
     pub profile_id: String,
     pub content_type: String,
@@ -26,16 +54,96 @@ pub struct AddHistoryRequest {
     pub content_data: serde_json::Value,
     pub position: Option<f64>,
     pub duration: Option<f64>,
+    /// Play without recording history, regardless of the session setting
+    #[serde(default)]
+    pub private: bool,
 }
 
 /// Request to update playback position
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct UpdatePositionRequest {
     pub profile_id: String,
     pub content_type: String,
     pub content_id: String,
     pub position: f64,
     pub duration: Option<f64>,
+    /// Play without recording history, regardless of the session setting
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// Content type of history items for series episodes
+pub const EPISODE_CONTENT_TYPE: &str = "episode";
+
+/// Watch progress through a series, used for continue watching and the next episode
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct SeriesProgress {
+    pub profile_id: String,
+    pub series_id: i64,
+    pub watched_episodes: i64,
+    pub total_episodes: i64,
+    /// The episode watched or played most recently
+    pub last_watched_episode_id: Option<String>,
+    /// The episode to play next; `None` once every episode is watched
+    pub next_episode_id: Option<String>,
+    pub updated_at: String,
+}
+
+/// Share of an item's duration after which it drops out of continue watching
+pub const CONTINUE_WATCHING_MAX_PROGRESS: f64 = 0.95;
+
+/// A movie or episode that was left part way through, with its cached details
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct ContinueWatchingItem {
+    pub profile_id: String,
+    pub profile_name: String,
+    /// "movie" or "episode"
+    pub content_type: String,
+    pub content_id: String,
+    pub title: String,
+    pub cover: Option<String>,
+    /// Saved position in seconds
+    pub position: f64,
+    /// Duration in seconds, from playback or else from the cached metadata
+    pub duration: Option<f64>,
+    /// Share of the duration watched, when the duration is known
+    pub progress: Option<f64>,
+    /// Series an episode belongs to
+    pub series_id: Option<i64>,
+    pub season_number: Option<i64>,
+    pub episode_num: Option<String>,
+    pub watched_at: String,
+}
+
+/// An episode of a series in episode order, with its watch state
+struct EpisodeState {
+    episode_id: String,
+    watched: bool,
+    watched_at: Option<String>,
+}
+
+/// Pick the last watched and next episode from episodes in order
+///
+/// The next episode is the first unwatched one after the last watched, or the
+/// first unwatched one overall if there is none after it.
+fn last_and_next(episodes: &[EpisodeState]) -> (Option<String>, Option<String>) {
+    let last = episodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, episode)| episode.watched_at.as_ref().map(|at| (at, index)))
+        .max()
+        .map(|(_, index)| index);
+
+    let after_last = last.map(|index| index + 1).unwrap_or(0);
+    let next = episodes[after_last..]
+        .iter()
+        .chain(&episodes[..after_last])
+        .find(|episode| !episode.watched);
+
+    (
+        last.map(|index| episodes[index].episode_id.clone()),
+        next.map(|episode| episode.episode_id.clone()),
+    )
 }
 
 /// Database operations for Xtream history
@@ -43,14 +151,23 @@ pub struct XtreamHistoryDb;
 
 impl XtreamHistoryDb {
     /// Add or update a history item for a profile
+    ///
+    /// Returns the item's ID, or `None` when the playback is private and
+    /// nothing was written.
     pub fn add_history(
         conn: &Connection,
         request: &AddHistoryRequest,
-    ) -> Result<String> {
+    ) -> Result<Option<String>> {
+        if playback_recording_suppressed(request.private) {
+            return Ok(None);
+        }
+
+        let key = ContentId::new(&request.content_type, &request.content_id)?;
+
         // Check if history item already exists
         let existing_id: Option<String> = conn.query_row(
             "SELECT id FROM xtream_history WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3",
-            params![request.profile_id, request.content_type, request.content_id],
+            params![request.profile_id, key.content_type(), key.id()],
             |row| row.get(0),
         ).optional()?;
         
@@ -72,7 +189,7 @@ impl XtreamHistoryDb {
                     id,
                 ],
             )?;
-            Ok(id)
+            Ok(Some(id))
         } else {
             // Insert new history item
             let history_id = Uuid::new_v4().to_string();
@@ -83,8 +200,8 @@ impl XtreamHistoryDb {
                 params![
                     history_id,
                     request.profile_id,
-                    request.content_type,
-                    request.content_id,
+                    key.content_type(),
+                    key.id(),
                     content_data_bytes,
                     now.to_rfc3339(),
                     request.position,
@@ -92,15 +209,22 @@ impl XtreamHistoryDb {
                 ],
             )?;
             
-            Ok(history_id)
+            Ok(Some(history_id))
         }
     }
     
     /// Update playback position for a history item
+    ///
+    /// Does nothing when the playback is private.
     pub fn update_position(
         conn: &Connection,
         request: &UpdatePositionRequest,
     ) -> Result<()> {
+        if playback_recording_suppressed(request.private) {
+            return Ok(());
+        }
+
+        let key = ContentId::new(&request.content_type, &request.content_id)?;
         let now = Utc::now();
         
         let rows_affected = conn.execute(
@@ -112,8 +236,8 @@ impl XtreamHistoryDb {
                 request.duration,
                 now.to_rfc3339(),
                 request.profile_id,
-                request.content_type,
-                request.content_id,
+                key.content_type(),
+                key.id(),
             ],
         )?;
         
@@ -130,10 +254,13 @@ impl XtreamHistoryDb {
         profile_id: &str,
         limit: Option<i64>,
     ) -> Result<Vec<XtreamHistory>> {
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Vec::new());
+        }
         let limit_value = limit.unwrap_or(50);
         
         let mut stmt = conn.prepare(
-            "SELECT id, profile_id, content_type, content_id, content_data, watched_at, position, duration 
+            "SELECT id, profile_id, content_type, content_id, content_data, watched_at, position, duration, watched 
              FROM xtream_history 
              WHERE profile_id = ?1 
              ORDER BY watched_at DESC 
@@ -154,6 +281,7 @@ impl XtreamHistoryDb {
                 watched_at: row.get(5)?,
                 position: row.get(6)?,
                 duration: row.get(7)?,
+                watched: row.get(8)?,
             })
         })?;
         
@@ -172,10 +300,14 @@ impl XtreamHistoryDb {
         content_type: &str,
         limit: Option<i64>,
     ) -> Result<Vec<XtreamHistory>> {
+        if expiry::profile_hidden(profile_id) {
+            return Ok(Vec::new());
+        }
         let limit_value = limit.unwrap_or(50);
+        let content_type = canonical_type(content_type).unwrap_or(content_type);
         
         let mut stmt = conn.prepare(
-            "SELECT id, profile_id, content_type, content_id, content_data, watched_at, position, duration 
+            "SELECT id, profile_id, content_type, content_id, content_data, watched_at, position, duration, watched 
              FROM xtream_history 
              WHERE profile_id = ?1 AND content_type = ?2 
              ORDER BY watched_at DESC 
@@ -196,6 +328,7 @@ impl XtreamHistoryDb {
                 watched_at: row.get(5)?,
                 position: row.get(6)?,
                 duration: row.get(7)?,
+                watched: row.get(8)?,
             })
         })?;
         
@@ -214,13 +347,14 @@ impl XtreamHistoryDb {
         content_type: &str,
         content_id: &str,
     ) -> Result<Option<XtreamHistory>> {
+        let key = ContentId::new(content_type, content_id)?;
         let mut stmt = conn.prepare(
-            "SELECT id, profile_id, content_type, content_id, content_data, watched_at, position, duration 
+            "SELECT id, profile_id, content_type, content_id, content_data, watched_at, position, duration, watched 
              FROM xtream_history 
              WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3"
         )?;
         
-        let mut history_iter = stmt.query_map(params![profile_id, content_type, content_id], |row| {
+        let mut history_iter = stmt.query_map(params![profile_id, key.content_type(), key.id()], |row| {
             let content_data_bytes: Vec<u8> = row.get(4)?;
             let content_data: serde_json::Value = serde_json::from_slice(&content_data_bytes)
                 .map_err(|e| rusqlite::Error::InvalidColumnType(4, "content_data".to_string(), rusqlite::types::Type::Blob))?;
@@ -234,6 +368,7 @@ impl XtreamHistoryDb {
                 watched_at: row.get(5)?,
                 position: row.get(6)?,
                 duration: row.get(7)?,
+                watched: row.get(8)?,
             })
         })?;
         
@@ -288,6 +423,258 @@ impl XtreamHistoryDb {
         
         Ok(rows_affected)
     }
+
+    /// Set the watched state of every cached episode of a series, or of one season
+    ///
+    /// All episodes and the series progress are updated in one transaction.
+    /// Unmarking also clears saved positions so the episodes play from the start.
+    pub fn set_episodes_watched(
+        conn: &Connection,
+        profile_id: &str,
+        series_id: i64,
+        season_number: Option<i64>,
+        watched: bool,
+    ) -> Result<SeriesProgress> {
+        let tx = conn.unchecked_transaction()?;
+
+        let episodes: Vec<(String, i64, String, Option<String>)> = {
+            let mut stmt = tx.prepare(
+                "SELECT episode_id, season_number, episode_num, title
+                 FROM xtream_episodes
+                 WHERE profile_id = ?1 AND series_id = ?2 AND (?3 IS NULL OR season_number = ?3)",
+            )?;
+            let rows = stmt
+                .query_map(params![profile_id, series_id, season_number], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+
+        if episodes.is_empty() {
+            return Err(XTauriError::internal(match season_number {
+                Some(season) => format!("No cached episodes for season {} of series {}", season, series_id),
+                None => format!("No cached episodes for series {}", series_id),
+            }));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        for (episode_id, season, episode_num, title) in &episodes {
+            if !watched {
+                tx.execute(
+                    "UPDATE xtream_history SET watched = FALSE, position = NULL
+                     WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3",
+                    params![profile_id, EPISODE_CONTENT_TYPE, episode_id],
+                )?;
+                continue;
+            }
+
+            let rows_affected = tx.execute(
+                "UPDATE xtream_history SET watched = TRUE, watched_at = ?1
+                 WHERE profile_id = ?2 AND content_type = ?3 AND content_id = ?4",
+                params![now, profile_id, EPISODE_CONTENT_TYPE, episode_id],
+            )?;
+            if rows_affected == 0 {
+                let content_data = serde_json::to_vec(&serde_json::json!({
+                    "series_id": series_id,
+                    "season_number": season,
+                    "episode_num": episode_num,
+                    "title": title,
+                }))
+                .map_err(|e| XTauriError::internal(format!("Failed to serialize content data: {}", e)))?;
+
+                tx.execute(
+                    "INSERT INTO xtream_history (id, profile_id, content_type, content_id, content_data, watched_at, watched)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, TRUE)",
+                    params![
+                        Uuid::new_v4().to_string(),
+                        profile_id,
+                        EPISODE_CONTENT_TYPE,
+                        episode_id,
+                        content_data,
+                        now,
+                    ],
+                )?;
+            }
+        }
+
+        let progress = Self::refresh_series_progress(&tx, profile_id, series_id)?;
+        tx.commit()?;
+        Ok(progress)
+    }
+
+    /// Recompute the stored progress of a series from its cached episodes and history
+    pub fn refresh_series_progress(
+        conn: &Connection,
+        profile_id: &str,
+        series_id: i64,
+    ) -> Result<SeriesProgress> {
+        // Unmarked episodes without a saved position don't count as watched recently
+        let mut stmt = conn.prepare(
+            "SELECT e.episode_id, COALESCE(h.watched, FALSE),
+                    CASE WHEN h.watched OR h.position IS NOT NULL THEN h.watched_at END
+             FROM xtream_episodes e
+             LEFT JOIN xtream_history h
+               ON h.profile_id = e.profile_id AND h.content_type = ?3 AND h.content_id = e.episode_id
+             WHERE e.profile_id = ?1 AND e.series_id = ?2
+             ORDER BY e.season_number, CAST(e.episode_num AS INTEGER)",
+        )?;
+        let episodes = stmt
+            .query_map(params![profile_id, series_id, EPISODE_CONTENT_TYPE], |row| {
+                Ok(EpisodeState {
+                    episode_id: row.get(0)?,
+                    watched: row.get(1)?,
+                    watched_at: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let (last_watched_episode_id, next_episode_id) = last_and_next(&episodes);
+        let progress = SeriesProgress {
+            profile_id: profile_id.to_string(),
+            series_id,
+            watched_episodes: episodes.iter().filter(|episode| episode.watched).count() as i64,
+            total_episodes: episodes.len() as i64,
+            last_watched_episode_id,
+            next_episode_id,
+            updated_at: Utc::now().to_rfc3339(),
+        };
+
+        conn.execute(
+            "INSERT INTO xtream_series_progress
+                 (profile_id, series_id, watched_episodes, total_episodes, last_watched_episode_id, next_episode_id, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(profile_id, series_id) DO UPDATE SET
+                 watched_episodes = excluded.watched_episodes,
+                 total_episodes = excluded.total_episodes,
+                 last_watched_episode_id = excluded.last_watched_episode_id,
+                 next_episode_id = excluded.next_episode_id,
+                 updated_at = excluded.updated_at",
+            params![
+                progress.profile_id,
+                progress.series_id,
+                progress.watched_episodes,
+                progress.total_episodes,
+                progress.last_watched_episode_id,
+                progress.next_episode_id,
+                progress.updated_at,
+            ],
+        )?;
+
+        Ok(progress)
+    }
+
+    /// Get the stored progress of a series, if any has been recorded
+    pub fn get_series_progress(
+        conn: &Connection,
+        profile_id: &str,
+        series_id: i64,
+    ) -> Result<Option<SeriesProgress>> {
+        let progress = conn
+            .query_row(
+                "SELECT profile_id, series_id, watched_episodes, total_episodes,
+                        last_watched_episode_id, next_episode_id, updated_at
+                 FROM xtream_series_progress
+                 WHERE profile_id = ?1 AND series_id = ?2",
+                params![profile_id, series_id],
+                |row| {
+                    Ok(SeriesProgress {
+                        profile_id: row.get(0)?,
+                        series_id: row.get(1)?,
+                        watched_episodes: row.get(2)?,
+                        total_episodes: row.get(3)?,
+                        last_watched_episode_id: row.get(4)?,
+                        next_episode_id: row.get(5)?,
+                        updated_at: row.get(6)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(progress)
+    }
+
+    /// Get movies and episodes left part way through, most recently watched first
+    ///
+    /// Covers every profile when `profile_id` is `None`. Items marked as watched
+    /// or played past `CONTINUE_WATCHING_MAX_PROGRESS` of their duration, and
+    /// items of expired profiles, are left out. Titles and covers come from the content cache, falling back to the
+    /// data saved with the history item.
+    pub fn get_continue_watching(
+        conn: &Connection,
+        profile_id: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ContinueWatchingItem>> {
+        let limit_value = limit.unwrap_or(20).max(0) as usize;
+
+        let mut stmt = conn.prepare(
+            "SELECT h.profile_id, p.name, h.content_type, h.content_id, h.content_data,
+                    COALESCE(m.name, e.title),
+                    COALESCE(NULLIF(m.stream_icon, ''), NULLIF(json_extract(e.info_json, '$.movie_image'), ''), NULLIF(s.cover, '')),
+                    h.position,
+                    COALESCE(NULLIF(h.duration, 0), m.episode_run_time * 60, NULLIF(json_extract(e.info_json, '$.duration_secs'), 0)),
+                    e.series_id, e.season_number, e.episode_num, h.watched_at
+             FROM xtream_history h
+             JOIN xtream_profiles p ON p.id = h.profile_id
+             LEFT JOIN xtream_movies m
+               ON h.content_type = 'movie' AND m.profile_id = h.profile_id AND m.stream_id = CAST(h.content_id AS INTEGER)
+             LEFT JOIN xtream_episodes e
+               ON h.content_type = ?2 AND e.profile_id = h.profile_id AND e.episode_id = h.content_id
+             LEFT JOIN xtream_series s
+               ON s.profile_id = e.profile_id AND s.series_id = e.series_id
+             WHERE (?1 IS NULL OR h.profile_id = ?1)
+               AND h.content_type IN ('movie', ?2)
+               AND h.position > 0
+               AND NOT COALESCE(h.watched, FALSE)
+             ORDER BY h.watched_at DESC",
+        )?;
+
+        let rows = stmt.query_map(params![profile_id, EPISODE_CONTENT_TYPE], |row| {
+            let content_data: Vec<u8> = row.get(4)?;
+            let cached_title: Option<String> = row.get(5)?;
+            let position: f64 = row.get(7)?;
+            let duration: Option<f64> = row.get(8)?;
+
+            let title = cached_title.filter(|title| !title.trim().is_empty()).or_else(|| {
+                let data: serde_json::Value = serde_json::from_slice(&content_data).ok()?;
+                ["name", "title"]
+                    .iter()
+                    .find_map(|key| data.get(key)?.as_str().map(str::to_string))
+            });
+
+            Ok(ContinueWatchingItem {
+                profile_id: row.get(0)?,
+                profile_name: row.get(1)?,
+                content_type: row.get(2)?,
+                content_id: row.get(3)?,
+                title: title.unwrap_or_default(),
+                cover: row.get(6)?,
+                position,
+                duration,
+                progress: duration.filter(|d| *d > 0.0).map(|d| (position / d).min(1.0)),
+                series_id: row.get(9)?,
+                season_number: row.get(10)?,
+                episode_num: row.get(11)?,
+                watched_at: row.get(12)?,
+            })
+        })?;
+
+        let mut items = Vec::new();
+        for item in rows {
+            let item = item?;
+            if item.progress.is_some_and(|progress| progress >= CONTINUE_WATCHING_MAX_PROGRESS)
+                || expiry::profile_hidden(&item.profile_id)
+            {
+                continue;
+            }
+            items.push(item);
+            if items.len() == limit_value {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
 }
 
 #[cfg(test)]
@@ -325,11 +712,49 @@ mod tests {
                 watched_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                 position REAL,
                 duration REAL,
+                watched BOOLEAN DEFAULT FALSE,
                 FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
             )",
             [],
         ).unwrap();
         
+        // Create the episode cache and series progress tables
+        conn.execute_batch(
+            "CREATE TABLE xtream_episodes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id TEXT NOT NULL,
+                series_id INTEGER NOT NULL,
+                episode_id TEXT NOT NULL,
+                season_number INTEGER NOT NULL,
+                episode_num TEXT NOT NULL,
+                title TEXT,
+                info_json TEXT
+            );
+            CREATE TABLE xtream_movies (
+                profile_id TEXT NOT NULL,
+                stream_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                stream_icon TEXT,
+                episode_run_time INTEGER
+            );
+            CREATE TABLE xtream_series (
+                profile_id TEXT NOT NULL,
+                series_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                cover TEXT
+            );
+            CREATE TABLE xtream_series_progress (
+                profile_id TEXT NOT NULL,
+                series_id INTEGER NOT NULL,
+                watched_episodes INTEGER NOT NULL DEFAULT 0,
+                total_episodes INTEGER NOT NULL DEFAULT 0,
+                last_watched_episode_id TEXT,
+                next_episode_id TEXT,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (profile_id, series_id)
+            );",
+        ).unwrap();
+        
         // Insert a test profile
         conn.execute(
             "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials) 
@@ -351,6 +776,7 @@ mod tests {
             }),
             position: Some(120.5),
             duration: Some(7200.0),
+            private: false,
         }
     }
     
@@ -359,7 +785,7 @@ mod tests {
         let conn = create_test_db();
         let request = create_test_history_request();
         
-        let history_id = XtreamHistoryDb::add_history(&conn, &request).unwrap();
+        let history_id = XtreamHistoryDb::add_history(&conn, &request).unwrap().unwrap();
         
         assert!(!history_id.is_empty());
         
@@ -377,12 +803,12 @@ mod tests {
         let request = create_test_history_request();
         
         // Add first time
-        let id1 = XtreamHistoryDb::add_history(&conn, &request).unwrap();
+        let id1 = XtreamHistoryDb::add_history(&conn, &request).unwrap().unwrap();
         
         // Add again with different position
         let mut request2 = request.clone();
         request2.position = Some(240.0);
-        let id2 = XtreamHistoryDb::add_history(&conn, &request2).unwrap();
+        let id2 = XtreamHistoryDb::add_history(&conn, &request2).unwrap().unwrap();
         
         // Should be the same ID (updated, not inserted)
         assert_eq!(id1, id2);
@@ -407,6 +833,7 @@ mod tests {
             content_id: "123".to_string(),
             position: 500.0,
             duration: Some(7200.0),
+            private: false,
         };
         
         XtreamHistoryDb::update_position(&conn, &update_request).unwrap();
@@ -415,7 +842,39 @@ mod tests {
         let history = XtreamHistoryDb::get_history(&conn, "test-profile-1", None).unwrap();
         assert_eq!(history[0].position, Some(500.0));
     }
-    
+
+    #[test]
+    fn test_private_playback_is_not_recorded() {
+        let conn = create_test_db();
+        let mut request = create_test_history_request();
+        request.private = true;
+
+        assert_eq!(XtreamHistoryDb::add_history(&conn, &request).unwrap(), None);
+        assert!(XtreamHistoryDb::get_history(&conn, "test-profile-1", None).unwrap().is_empty());
+
+        // A private position update neither fails for the missing item nor touches a recorded one
+        let mut update_request = UpdatePositionRequest {
+            profile_id: "test-profile-1".to_string(),
+            content_type: "movie".to_string(),
+            content_id: "123".to_string(),
+            position: 500.0,
+            duration: Some(7200.0),
+            private: true,
+        };
+        XtreamHistoryDb::update_position(&conn, &update_request).unwrap();
+
+        request.private = false;
+        XtreamHistoryDb::add_history(&conn, &request).unwrap();
+        XtreamHistoryDb::update_position(&conn, &update_request).unwrap();
+        let history = XtreamHistoryDb::get_history(&conn, "test-profile-1", None).unwrap();
+        assert_eq!(history[0].position, Some(120.5));
+
+        update_request.private = false;
+        XtreamHistoryDb::update_position(&conn, &update_request).unwrap();
+        let history = XtreamHistoryDb::get_history(&conn, "test-profile-1", None).unwrap();
+        assert_eq!(history[0].position, Some(500.0));
+    }
+
     #[test]
     fn test_get_history_by_type() {
         let conn = create_test_db();
@@ -428,6 +887,7 @@ mod tests {
             content_data: serde_json::json!({"name": "Movie"}),
             position: None,
             duration: None,
+            private: false,
         };
         
         let channel_request = AddHistoryRequest {
@@ -437,6 +897,7 @@ mod tests {
             content_data: serde_json::json!({"name": "Channel"}),
             position: None,
             duration: None,
+            private: false,
         };
         
         XtreamHistoryDb::add_history(&conn, &movie_request).unwrap();
@@ -475,7 +936,7 @@ mod tests {
         let conn = create_test_db();
         let request = create_test_history_request();
         
-        let history_id = XtreamHistoryDb::add_history(&conn, &request).unwrap();
+        let history_id = XtreamHistoryDb::add_history(&conn, &request).unwrap().unwrap();
         
         // Remove history
         XtreamHistoryDb::remove_history(&conn, &history_id).unwrap();
@@ -498,6 +959,7 @@ mod tests {
                 content_data: serde_json::json!({"name": format!("Movie {}", i)}),
                 position: None,
                 duration: None,
+                private: false,
             };
             XtreamHistoryDb::add_history(&conn, &request).unwrap();
         }
@@ -527,6 +989,7 @@ mod tests {
                 content_data: serde_json::json!({"name": format!("Movie {}", i)}),
                 position: None,
                 duration: None,
+                private: false,
             };
             XtreamHistoryDb::add_history(&conn, &request).unwrap();
         }
@@ -535,4 +998,124 @@ mod tests {
         let history = XtreamHistoryDb::get_history(&conn, "test-profile-1", Some(5)).unwrap();
         assert_eq!(history.len(), 5);
     }
+    
+    #[test]
+    fn test_season_and_series_watched() {
+        let conn = create_test_db();
+        for (episode_id, season, num) in [("101", 1, "1"), ("102", 1, "2"), ("201", 2, "1"), ("202", 2, "2")] {
+            conn.execute(
+                "INSERT INTO xtream_episodes (profile_id, series_id, episode_id, season_number, episode_num)
+                 VALUES ('test-profile-1', 7, ?1, ?2, ?3)",
+                params![episode_id, season, num],
+            ).unwrap();
+        }
+        
+        let progress = XtreamHistoryDb::set_episodes_watched(&conn, "test-profile-1", 7, Some(1), true).unwrap();
+        assert_eq!((progress.watched_episodes, progress.total_episodes), (2, 4));
+        assert_eq!(progress.next_episode_id.as_deref(), Some("201"));
+        
+        let item = XtreamHistoryDb::get_history_item(&conn, "test-profile-1", EPISODE_CONTENT_TYPE, "102").unwrap().unwrap();
+        assert!(item.watched);
+        
+        let progress = XtreamHistoryDb::set_episodes_watched(&conn, "test-profile-1", 7, None, true).unwrap();
+        assert_eq!(progress.watched_episodes, 4);
+        assert_eq!(progress.next_episode_id, None);
+        
+        XtreamHistoryDb::set_episodes_watched(&conn, "test-profile-1", 7, Some(2), false).unwrap();
+        let stored = XtreamHistoryDb::get_series_progress(&conn, "test-profile-1", 7).unwrap().unwrap();
+        assert_eq!(stored.watched_episodes, 2);
+        assert_eq!(stored.next_episode_id.as_deref(), Some("201"));
+        
+        // A season with nothing cached is an error and changes nothing
+        assert!(XtreamHistoryDb::set_episodes_watched(&conn, "test-profile-1", 7, Some(3), true).is_err());
+    }
+
+    #[test]
+    fn test_continue_watching_joins_cached_details() {
+        let conn = create_test_db();
+        conn.execute_batch(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+                 VALUES ('test-profile-2', 'Second Profile', 'http://example.org', 'other', X'00');
+             INSERT INTO xtream_movies VALUES ('test-profile-1', 1, 'Dune', 'http://img/dune.jpg', 60);
+             INSERT INTO xtream_movies VALUES ('test-profile-1', 2, 'Heat', '', 170);
+             INSERT INTO xtream_series VALUES ('test-profile-2', 7, 'The Expanse', 'http://img/expanse.jpg');
+             INSERT INTO xtream_episodes (profile_id, series_id, episode_id, season_number, episode_num, title, info_json)
+                 VALUES ('test-profile-2', 7, '701', 1, '3', 'Remember the Cant', '{\"duration_secs\": 2700}');",
+        ).unwrap();
+
+        let play = |profile_id: &str, content_type: &str, content_id: &str, position: Option<f64>, duration: Option<f64>, at: &str| {
+            XtreamHistoryDb::add_history(&conn, &AddHistoryRequest {
+                profile_id: profile_id.to_string(),
+                content_type: content_type.to_string(),
+                content_id: content_id.to_string(),
+                content_data: serde_json::json!({"name": format!("Saved {}", content_id)}),
+                position,
+                duration,
+                private: false,
+            }).unwrap();
+            conn.execute(
+                "UPDATE xtream_history SET watched_at = ?1 WHERE profile_id = ?2 AND content_id = ?3",
+                params![at, profile_id, content_id],
+            ).unwrap();
+        };
+        play("test-profile-1", "movie", "1", Some(1200.0), None, "2024-01-01T10:00:00Z");
+        play("test-profile-1", "movie", "2", Some(3500.0), Some(3600.0), "2024-01-01T11:00:00Z");
+        play("test-profile-1", "movie", "3", Some(60.0), None, "2024-01-01T12:00:00Z");
+        play("test-profile-1", "movie", "4", Some(0.0), Some(3600.0), "2024-01-01T13:00:00Z");
+        play("test-profile-1", "channel", "5", Some(30.0), None, "2024-01-01T14:00:00Z");
+        play("test-profile-2", EPISODE_CONTENT_TYPE, "701", Some(900.0), None, "2024-01-01T15:00:00Z");
+
+        let items = XtreamHistoryDb::get_continue_watching(&conn, None, None).unwrap();
+        let ids: Vec<&str> = items.iter().map(|item| item.content_id.as_str()).collect();
+        assert_eq!(ids, vec!["701", "3", "1"]);
+
+        let episode = &items[0];
+        assert_eq!(episode.profile_name, "Second Profile");
+        assert_eq!(episode.title, "Remember the Cant");
+        assert_eq!(episode.cover.as_deref(), Some("http://img/expanse.jpg"));
+        assert_eq!(episode.duration, Some(2700.0));
+        assert_eq!((episode.series_id, episode.season_number), (Some(7), Some(1)));
+
+        // Not in the cache, so the saved title is used and progress is unknown
+        assert_eq!(items[1].title, "Saved 3");
+        assert_eq!(items[1].progress, None);
+
+        let movie = &items[2];
+        assert_eq!(movie.title, "Dune");
+        assert_eq!(movie.cover.as_deref(), Some("http://img/dune.jpg"));
+        assert_eq!(movie.duration, Some(3600.0));
+        assert_eq!(movie.progress, Some(1200.0 / 3600.0));
+
+        let items = XtreamHistoryDb::get_continue_watching(&conn, Some("test-profile-1"), Some(1)).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].content_id, "3");
+    }
+
+    #[test]
+    fn test_expired_profile_lists_no_history() {
+        let conn = create_test_db();
+        conn.execute(
+            "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials)
+             VALUES ('expired-history', 'Trial', 'http://example.com', 'testuser', X'00')",
+            [],
+        ).unwrap();
+        XtreamHistoryDb::add_history(&conn, &AddHistoryRequest {
+            profile_id: "expired-history".to_string(),
+            content_type: "movie".to_string(),
+            content_id: "1".to_string(),
+            content_data: serde_json::json!({"name": "Dune"}),
+            position: Some(600.0),
+            duration: None,
+            private: false,
+        }).unwrap();
+        assert_eq!(XtreamHistoryDb::get_continue_watching(&conn, Some("expired-history"), None).unwrap().len(), 1);
+
+        let _guard = expiry::expire_for_test(expiry::ExpirySource::Profile, "expired-history");
+        assert!(XtreamHistoryDb::get_history(&conn, "expired-history", None).unwrap().is_empty());
+        assert!(XtreamHistoryDb::get_history_by_type(&conn, "expired-history", "movie", None).unwrap().is_empty());
+        assert!(XtreamHistoryDb::get_continue_watching(&conn, None, None)
+            .unwrap()
+            .iter()
+            .all(|item| item.profile_id != "expired-history"));
+    }
 }
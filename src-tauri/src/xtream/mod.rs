@@ -1,24 +1,39 @@
+pub mod api_language;
+pub mod archive_resume;
+pub mod automation;
+pub mod backlog;
 pub mod commands;
 pub mod content_cache;
 pub mod credential_manager;
 pub mod database;
+pub mod demo;
 pub mod favorites;
 pub mod filter;
 pub mod graceful_degradation;
+pub mod heartbeat;
 pub mod history;
 pub mod performance_monitor;
 pub mod prefetch;
+pub mod profile_import;
+pub mod portable;
 pub mod profile_manager;
+pub mod rate_limit;
+pub mod recording;
+pub mod reminders;
 pub mod retry;
 pub mod saved_filters;
 pub mod search;
 pub mod search_history;
 pub mod session_manager;
+pub mod startup_auth;
+pub mod tags;
 pub mod types;
 pub mod xtream_client;
+pub mod year_review;
 
 
 
+pub use automation::*;
 pub use commands::XtreamState;
 pub use content_cache::ContentCache;
 pub use credential_manager::CredentialManager;
@@ -30,10 +45,12 @@ pub use history::*;
 pub use performance_monitor::*;
 pub use prefetch::*;
 pub use profile_manager::ProfileManager;
+pub use reminders::*;
 pub use retry::*;
 pub use saved_filters::*;
 pub use search::*;
 pub use search_history::*;
 pub use session_manager::*;
+pub use tags::*;
 pub use types::*;
 pub use xtream_client::XtreamClient;
@@ -0,0 +1,267 @@
+// Provider-agnostic export of a profile's favorites and tag groups
+//
+// Stream ids only mean something to the provider that issued them, so items are
+// written by name, the normalized name the failover matcher uses, and the EPG
+// id (tvg-id) where there is one. Importing into a profile on another provider
+// can then re-link most items by matching those against its cached content.
+use crate::content_cache::failover::channel_identity_key;
+use crate::error::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Version of the portable file layout
+pub const PORTABLE_FORMAT_VERSION: u32 = 1;
+
+/// A favorited or tagged item, described without provider ids
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct PortableItem {
+    /// "channel", "movie" or "series"
+    pub content_type: String,
+    pub name: String,
+    /// Normalized name shared by copies of the item on different providers
+    pub match_key: String,
+    /// EPG channel id of a channel
+    pub tvg_id: Option<String>,
+    /// Release year of a movie or series, to tell remakes apart
+    pub year: Option<String>,
+}
+
+/// Items carrying one user tag
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct PortableGroup {
+    pub name: String,
+    pub items: Vec<PortableItem>,
+}
+
+/// Everything a profile's setup needs to be rebuilt on another provider
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct ProfilePortables {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub profile_name: String,
+    /// Favorites in the profile's custom order
+    pub favorites: Vec<PortableItem>,
+    pub groups: Vec<PortableGroup>,
+    /// Items that could not be described, because they are neither cached nor named
+    pub skipped: usize,
+}
+
+impl ProfilePortables {
+    /// Number of items written, counting an item once per list it is in
+    pub fn item_count(&self) -> usize {
+        self.favorites.len() + self.groups.iter().map(|group| group.items.len()).sum::<usize>()
+    }
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Describe an item from the content cache, or from the data saved with a favorite
+fn describe(
+    conn: &Connection,
+    profile_id: &str,
+    content_type: &str,
+    content_id: &str,
+    content_data: Option<&Value>,
+) -> Result<Option<PortableItem>> {
+    let query = match content_type {
+        "channel" => "SELECT name, epg_channel_id FROM xtream_channels WHERE profile_id = ?1 AND stream_id = ?2",
+        "movie" => "SELECT name, year FROM xtream_movies WHERE profile_id = ?1 AND stream_id = ?2",
+        "series" => "SELECT name, year FROM xtream_series WHERE profile_id = ?1 AND series_id = ?2",
+        _ => return Ok(None),
+    };
+    let cached: Option<(String, Option<String>)> = match content_id.trim().parse::<i64>() {
+        Ok(id) => conn
+            .query_row(query, params![profile_id, id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?,
+        Err(_) => None,
+    };
+
+    let (name, detail) = match cached {
+        Some(cached) => cached,
+        None => {
+            let field = |key: &str| text(content_data.and_then(|data| data.get(key)));
+            let Some(name) = field("name").or_else(|| field("title")) else {
+                return Ok(None);
+            };
+            let detail = if content_type == "channel" { field("epg_channel_id") } else { field("year") };
+            (name, detail)
+        }
+    };
+    let detail = detail.filter(|value| !value.trim().is_empty());
+
+    Ok(Some(PortableItem {
+        content_type: content_type.to_string(),
+        match_key: channel_identity_key(&name),
+        name,
+        tvg_id: if content_type == "channel" { detail.clone() } else { None },
+        year: if content_type == "channel" { None } else { detail },
+    }))
+}
+
+/// Collect a profile's favorites and tag groups in the portable format
+pub fn export_portables(conn: &Connection, profile_id: &str) -> Result<ProfilePortables> {
+    let profile_name: String = conn.query_row(
+        "SELECT name FROM xtream_profiles WHERE id = ?1",
+        [profile_id],
+        |row| row.get(0),
+    )?;
+    let mut skipped = 0;
+
+    let mut favorites = Vec::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT content_type, content_id, content_data FROM xtream_favorites
+             WHERE profile_id = ?1
+             ORDER BY position NULLS LAST, created_at DESC",
+        )?;
+        let rows = stmt.query_map([profile_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Vec<u8>>(2)?))
+        })?;
+        for row in rows {
+            let (content_type, content_id, content_data) = row?;
+            let content_data: Option<Value> = serde_json::from_slice(&content_data).ok();
+            match describe(conn, profile_id, &content_type, &content_id, content_data.as_ref())? {
+                Some(item) => favorites.push(item),
+                None => skipped += 1,
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<String, Vec<PortableItem>> = BTreeMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT tag, content_type, content_id FROM xtream_content_tags
+             WHERE profile_id = ?1
+             ORDER BY tag, created_at",
+        )?;
+        let rows = stmt.query_map([profile_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })?;
+        for row in rows {
+            let (tag, content_type, content_id) = row?;
+            match describe(conn, profile_id, &content_type, &content_id, None)? {
+                Some(item) => groups.entry(tag).or_default().push(item),
+                None => skipped += 1,
+            }
+        }
+    }
+
+    Ok(ProfilePortables {
+        format_version: PORTABLE_FORMAT_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        profile_name,
+        favorites,
+        groups: groups
+            .into_iter()
+            .map(|(name, items)| PortableGroup { name, items })
+            .collect(),
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_profiles (id TEXT PRIMARY KEY, name TEXT NOT NULL);
+            CREATE TABLE xtream_favorites (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                content_data BLOB NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                position REAL
+            );
+            CREATE TABLE xtream_content_tags (
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE xtream_channels (
+                profile_id TEXT NOT NULL,
+                stream_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                epg_channel_id TEXT
+            );
+            CREATE TABLE xtream_movies (profile_id TEXT NOT NULL, stream_id INTEGER NOT NULL, name TEXT NOT NULL, year TEXT);
+            CREATE TABLE xtream_series (profile_id TEXT NOT NULL, series_id INTEGER NOT NULL, name TEXT NOT NULL, year TEXT);
+            INSERT INTO xtream_profiles VALUES ('p1', 'Home');
+            INSERT INTO xtream_channels VALUES ('p1', 10, 'UK: BBC One FHD', 'bbc1.uk');
+            INSERT INTO xtream_movies VALUES ('p1', 20, 'Dune', '2021');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn favorite(conn: &Connection, content_type: &str, content_id: &str, data: Value, position: f64) {
+        conn.execute(
+            "INSERT INTO xtream_favorites (id, profile_id, content_type, content_id, content_data, position)
+             VALUES (?1, 'p1', ?2, ?3, ?4, ?5)",
+            params![
+                format!("{}-{}", content_type, content_id),
+                content_type,
+                content_id,
+                serde_json::to_vec(&data).unwrap(),
+                position
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_export_describes_items_without_provider_ids() {
+        let conn = create_test_db();
+        favorite(&conn, "movie", "20", json!({}), 2.0);
+        favorite(&conn, "channel", "10", json!({}), 1.0);
+        favorite(&conn, "series", "30", json!({"name": "The Expanse", "year": "2015"}), 3.0);
+        favorite(&conn, "channel", "99", json!({}), 4.0);
+        conn.execute_batch(
+            "INSERT INTO xtream_content_tags (profile_id, content_type, content_id, tag) VALUES
+                ('p1', 'channel', '10', 'Morning'),
+                ('p1', 'movie', '20', 'Weekend'),
+                ('p1', 'movie', '404', 'Weekend');",
+        )
+        .unwrap();
+
+        let portables = export_portables(&conn, "p1").unwrap();
+        assert_eq!(portables.profile_name, "Home");
+        assert_eq!(
+            portables.favorites[0],
+            PortableItem {
+                content_type: "channel".to_string(),
+                name: "UK: BBC One FHD".to_string(),
+                match_key: "bbc one".to_string(),
+                tvg_id: Some("bbc1.uk".to_string()),
+                year: None,
+            }
+        );
+        assert_eq!(portables.favorites[1].year.as_deref(), Some("2021"));
+        assert_eq!(portables.favorites[2].name, "The Expanse");
+        assert_eq!(portables.favorites[2].year.as_deref(), Some("2015"));
+        assert_eq!(portables.favorites.len(), 3);
+
+        let groups: Vec<(&str, usize)> =
+            portables.groups.iter().map(|g| (g.name.as_str(), g.items.len())).collect();
+        assert_eq!(groups, vec![("Morning", 1), ("Weekend", 1)]);
+        assert_eq!(portables.skipped, 2);
+        assert_eq!(portables.item_count(), 5);
+
+        let written = serde_json::to_string(&portables).unwrap();
+        assert!(!written.contains("\"10\"") && !written.contains("stream_id"));
+    }
+}
@@ -38,6 +38,12 @@ impl PrefetchManager {
     /// Start the prefetch worker
     pub async fn start_prefetch_worker(&self, client: Arc<XtreamClient>) -> Result<()> {
         loop {
+            // Prefetching is background work, held back while saving power
+            if crate::power::low_power() {
+                sleep(self.prefetch_interval).await;
+                continue;
+            }
+
             // Get next items to prefetch
             let mut items_to_prefetch = Vec::new();
             for _ in 0..self.max_concurrent_prefetch {
@@ -0,0 +1,209 @@
+// Bulk profile import from CSV
+//
+// Resellers keep their lines in spreadsheets. A CSV export with the columns
+// name,url,username,password (the header row is optional) is turned into one
+// profile per row, and every row gets a line in the report saying whether it
+// was created or why not.
+use crate::error::Result;
+use crate::xtream::profile_manager::ProfileManager;
+use crate::xtream::types::CreateProfileRequest;
+use serde::{Deserialize, Serialize};
+
+/// Columns every row must have, in order
+const COLUMNS: [&str; 4] = ["name", "url", "username", "password"];
+
+/// Outcome of one CSV row
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ProfileImportRow {
+    /// Line the row starts on, counting the header
+    pub row: usize,
+    pub name: String,
+    /// Id of the created profile; `None` when the row was rejected
+    pub profile_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ProfileImportReport {
+    pub created: usize,
+    pub failed: usize,
+    pub rows: Vec<ProfileImportRow>,
+}
+
+/// Split CSV content into records of fields, with the line each record starts on
+///
+/// Handles quoted fields with embedded commas, newlines and `""` escapes.
+/// Blank lines are skipped.
+fn parse_csv(content: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                if fields.iter().any(|f| !f.trim().is_empty()) {
+                    records.push((record_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                record_line = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    fields.push(field);
+    if fields.iter().any(|f| !f.trim().is_empty()) {
+        records.push((record_line, fields));
+    }
+    records
+}
+
+/// Turn a CSV record into a profile request
+fn parse_row(fields: &[String]) -> std::result::Result<CreateProfileRequest, String> {
+    if fields.len() != COLUMNS.len() {
+        return Err(format!(
+            "Expected {} columns ({}), found {}",
+            COLUMNS.len(),
+            COLUMNS.join(","),
+            fields.len()
+        ));
+    }
+    Ok(CreateProfileRequest {
+        name: fields[0].trim().to_string(),
+        url: fields[1].trim().to_string(),
+        username: fields[2].trim().to_string(),
+        password: fields[3].trim().to_string(),
+    })
+}
+
+/// Create a profile for each row of a CSV file and report on every row
+///
+/// Rows that parse are handed to [`ProfileManager::import_profiles`], which
+/// creates the valid ones in a single transaction.
+pub async fn import_csv(
+    manager: &ProfileManager,
+    content: &str,
+    authenticate: bool,
+) -> Result<ProfileImportReport> {
+    let mut records = parse_csv(content);
+    let is_header = records.first().is_some_and(|(_, fields)| {
+        fields.len() == COLUMNS.len()
+            && fields.iter().zip(COLUMNS).all(|(field, column)| field.trim().eq_ignore_ascii_case(column))
+    });
+    if is_header {
+        records.remove(0);
+    }
+
+    let mut rows = Vec::with_capacity(records.len());
+    let mut requests = Vec::new();
+    for (line, fields) in &records {
+        let name = fields.first().map(|name| name.trim().to_string()).unwrap_or_default();
+        match parse_row(fields) {
+            Ok(request) => {
+                requests.push(request);
+                rows.push(ProfileImportRow { row: *line, name, profile_id: None, error: None });
+            }
+            Err(e) => rows.push(ProfileImportRow { row: *line, name, profile_id: None, error: Some(e) }),
+        }
+    }
+
+    let mut outcomes = manager.import_profiles(requests, authenticate).await?.into_iter();
+    for row in rows.iter_mut().filter(|row| row.error.is_none()) {
+        match outcomes.next() {
+            Some(Ok(profile_id)) => row.profile_id = Some(profile_id),
+            Some(Err(e)) => row.error = Some(e.to_string()),
+            None => {}
+        }
+    }
+
+    let created = rows.iter().filter(|row| row.profile_id.is_some()).count();
+    Ok(ProfileImportReport {
+        created,
+        failed: rows.len() - created,
+        rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xtream::credential_manager::CredentialManager;
+    use rusqlite::Connection;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_parse_csv() {
+        let content = "name,url,username,password\r\n\r\n\"Line, One\",http://a.example.com,u1,\"p\"\"1\"\n\"Multi\nline\",http://b.example.com,u2,p2";
+        let records = parse_csv(content);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], (3, vec!["Line, One".to_string(), "http://a.example.com".to_string(), "u1".to_string(), "p\"1".to_string()]));
+        assert_eq!(records[2].0, 4);
+        assert_eq!(records[2].1[0], "Multi\nline");
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_reports_each_row() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_profiles (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                url TEXT NOT NULL,
+                username TEXT NOT NULL,
+                encrypted_credentials BLOB NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                last_used DATETIME,
+                is_active BOOLEAN DEFAULT FALSE
+            )",
+            [],
+        )
+        .unwrap();
+        let manager = ProfileManager::new(Arc::new(Mutex::new(conn)), Arc::new(CredentialManager::with_key([7u8; 32])));
+
+        let content = "\
+Name,URL,Username,Password
+Line 1,http://panel.example.com:8080,alice,secret
+Line 2,not a url,bob,secret
+Line 1,http://panel.example.com:8080,carol,secret
+Line 3,http://panel.example.com:8080,dave
+Line 4,http://other.example.com,erin,secret
+";
+        let report = import_csv(&manager, content, false).await.unwrap();
+        assert_eq!(report.created, 2);
+        assert_eq!(report.failed, 3);
+
+        let rows = &report.rows;
+        assert_eq!(rows.iter().map(|row| row.row).collect::<Vec<_>>(), vec![2, 3, 4, 5, 6]);
+        assert!(rows[0].profile_id.is_some());
+        assert!(rows[1].error.as_deref().unwrap().contains("Invalid URL"));
+        assert!(rows[2].error.as_deref().unwrap().contains("more than once"));
+        assert!(rows[3].error.as_deref().unwrap().contains("Expected 4 columns"));
+        let credentials = manager.get_profile_credentials(rows[4].profile_id.as_deref().unwrap()).unwrap();
+        assert_eq!(credentials.username, "erin");
+        assert_eq!(manager.get_profiles().unwrap().len(), 2);
+
+        // Names from an earlier import are taken
+        let report = import_csv(&manager, "Line 4,http://other.example.com,frank,secret", false).await.unwrap();
+        assert_eq!(report.created, 0);
+        assert!(report.rows[0].error.as_deref().unwrap().contains("already exists"));
+    }
+}
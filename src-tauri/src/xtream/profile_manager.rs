@@ -1,14 +1,34 @@
+use crate::content_id::ContentId;
+use crate::db_pool::DbPool;
 use crate::error::{Result, XTauriError};
 use crate::xtream::types::{XtreamProfile, CreateProfileRequest, UpdateProfileRequest, ProfileCredentials, AuthenticationResult, AuthenticationErrorType};
+use crate::unit_of_work;
 use crate::xtream::credential_manager::CredentialManager;
 use rusqlite::Connection;
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+/// Tables outside the content cache holding rows that belong to a profile
+const PROFILE_TABLES: [&str; 10] = [
+    "xtream_favorites",
+    "xtream_content_tags",
+    "xtream_history",
+    "xtream_series_progress",
+    "xtream_search_history",
+    "xtream_saved_filters",
+    "xtream_automation_scripts",
+    "xtream_epg_reminders",
+    "xtream_account_status",
+    "xtream_content_cache",
+];
+
 /// Manages Xtream profiles including CRUD operations and credential handling
 pub struct ProfileManager {
     db: Arc<Mutex<Connection>>,
+    /// Where profile lookups read from, so they don't wait for a write holding `db`
+    reads: Arc<DbPool>,
     credential_manager: Arc<CredentialManager>,
 }
 
@@ -16,11 +36,18 @@ impl ProfileManager {
     /// Create a new profile manager
     pub fn new(db: Arc<Mutex<Connection>>, credential_manager: Arc<CredentialManager>) -> Self {
         Self {
+            reads: Arc::new(DbPool::shared(Arc::clone(&db))),
             db,
             credential_manager,
         }
     }
     
+    /// Read profiles from a pool of connections to the database file
+    pub fn with_read_pool(mut self, reads: Arc<DbPool>) -> Self {
+        self.reads = reads;
+        self
+    }
+    
     /// Create a new profile (synchronous version for backward compatibility)
     pub fn create_profile(&self, request: CreateProfileRequest) -> Result<String> {
         // Use tokio runtime to run async validation
@@ -141,7 +168,105 @@ impl ProfileManager {
         
         Ok(profile_id)
     }
-    
+
+    /// Create profiles in bulk, skipping the requests that fail validation
+    ///
+    /// Every request is checked before anything is written: its format, names that are
+    /// taken or repeated in the batch and, when `authenticate` is set, the credentials
+    /// against the server. The valid ones are inserted in a single transaction.
+    /// Returns the new profile id or the error for each request, in order.
+    pub async fn import_profiles(
+        &self,
+        requests: Vec<CreateProfileRequest>,
+        authenticate: bool,
+    ) -> Result<Vec<Result<String>>> {
+        let mut outcomes = Vec::with_capacity(requests.len());
+        let mut batch_names = HashSet::new();
+        for request in requests {
+            let checked = self.check_import_request(&request, &batch_names, authenticate).await;
+            outcomes.push(checked.and_then(|_| {
+                let credentials = ProfileCredentials {
+                    url: request.url.clone(),
+                    username: request.username.clone(),
+                    password: request.password,
+                };
+                let encrypted = self.credential_manager.encrypt_credentials(&credentials)?;
+                batch_names.insert(request.name.clone());
+                Ok((request.name, credentials, self.credential_manager.encode_for_storage(&encrypted)))
+            }));
+        }
+
+        let now_str = Utc::now().to_rfc3339();
+        let mut created = Vec::new();
+        {
+            let mut db = self.db.lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+
+            let tx = db.transaction()?;
+            for (name, credentials, encoded_credentials) in outcomes.iter().flatten() {
+                let profile_id = Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO xtream_profiles (id, name, url, username, encrypted_credentials, created_at, updated_at, is_active)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    (
+                        &profile_id,
+                        name,
+                        &credentials.url,
+                        &credentials.username,
+                        encoded_credentials,
+                        &now_str,
+                        &now_str,
+                        false,
+                    ),
+                )?;
+                created.push(profile_id);
+            }
+            tx.commit()?;
+        }
+
+        // Ids were assigned to the valid requests in order
+        let mut created = created.into_iter();
+        Ok(outcomes
+            .into_iter()
+            .map(|outcome| {
+                let (_, credentials, _) = outcome?;
+                let profile_id = created.next().unwrap_or_default();
+                self.credential_manager.cache_credentials(&profile_id, &credentials)?;
+                Ok(profile_id)
+            })
+            .collect())
+    }
+
+    /// Check one request of a bulk import against the stored profiles and the rest of the batch
+    async fn check_import_request(
+        &self,
+        request: &CreateProfileRequest,
+        batch_names: &HashSet<String>,
+        authenticate: bool,
+    ) -> Result<()> {
+        self.validate_create_request(request)?;
+
+        if self.profile_name_exists(&request.name)? {
+            return Err(XTauriError::profile_validation(format!("Profile name '{}' already exists", request.name)));
+        }
+        if batch_names.contains(&request.name) {
+            return Err(XTauriError::profile_validation(format!("Profile name '{}' appears more than once", request.name)));
+        }
+
+        if authenticate {
+            let credentials = ProfileCredentials {
+                url: request.url.clone(),
+                username: request.username.clone(),
+                password: request.password.clone(),
+            };
+            if !self.validate_credentials(&credentials).await? {
+                return Err(XTauriError::XtreamInvalidCredentials);
+            }
+        }
+
+        Ok(())
+    }
+
     /// Update an existing profile
     pub fn update_profile(&self, id: &str, request: UpdateProfileRequest) -> Result<()> {
         // Check if profile exists
@@ -229,27 +354,12 @@ impl ProfileManager {
     
     /// Delete a profile
     pub fn delete_profile(&self, id: &str) -> Result<()> {
-        // Check if profile exists
-        if self.get_profile(id)?.is_none() {
-            return Err(XTauriError::xtream_profile_not_found(id.to_string()));
-        }
-        
-        // Clear cached credentials
-        self.credential_manager.clear_cached_credentials(id)?;
-        
-        // Delete from database (cascade will handle related data)
-        let db = self.db.lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
-        
-        db.execute("DELETE FROM xtream_profiles WHERE id = ?", [id])?;
-        
-        Ok(())
+        Self::delete_profile_sync_static(&self.db, &self.credential_manager, id)
     }
     
     /// Get all profiles
     pub fn get_profiles(&self) -> Result<Vec<XtreamProfile>> {
-        let db = self.db.lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let db = self.reads.read()?;
             
         let mut stmt = db.prepare(
             "SELECT id, name, url, username, created_at, updated_at, last_used, is_active 
@@ -299,8 +409,7 @@ impl ProfileManager {
     
     /// Get a specific profile by ID
     pub fn get_profile(&self, id: &str) -> Result<Option<XtreamProfile>> {
-        let db = self.db.lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let db = self.reads.read()?;
             
         let mut stmt = db.prepare(
             "SELECT id, name, url, username, created_at, updated_at, last_used, is_active 
@@ -355,8 +464,7 @@ impl ProfileManager {
         }
         
         // Get from database
-        let db = self.db.lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let db = self.reads.read()?;
             
         let mut stmt = db.prepare("SELECT encrypted_credentials FROM xtream_profiles WHERE id = ?")?;
         let result = stmt.query_row([id], |row| {
@@ -512,10 +620,142 @@ impl ProfileManager {
         self.update_profile(id, request)
     }
     
+    /// Rotate the username/password of a profile after re-authenticating with them
+    ///
+    /// Nothing is written unless the provider accepts the new credentials.
+    pub async fn update_profile_credentials(
+        &self,
+        id: &str,
+        username: Option<String>,
+        password: String,
+    ) -> Result<()> {
+        let current_credentials = self.get_profile_credentials(id)?;
+        
+        let updated_credentials = ProfileCredentials {
+            url: current_credentials.url.clone(),
+            username: username.unwrap_or_else(|| current_credentials.username.clone()),
+            password,
+        };
+        
+        if !self.validate_credentials(&updated_credentials).await? {
+            return Err(XTauriError::XtreamInvalidCredentials);
+        }
+        
+        self.store_rotated_credentials(id, &current_credentials, &updated_credentials)?;
+        
+        Ok(())
+    }
+    
+    /// Persist rotated credentials and refresh the credential cache
+    ///
+    /// Stream URLs embed the credentials as `/username/password/`, so the cached
+    /// content's URLs and the snapshots in favorites and history are rewritten
+    /// in the same transaction as the profile. A failure leaves everything on
+    /// the old credentials. Returns the number of rows rewritten.
+    fn store_rotated_credentials(
+        &self,
+        id: &str,
+        previous: &ProfileCredentials,
+        credentials: &ProfileCredentials,
+    ) -> Result<usize> {
+        let encrypted = self.credential_manager.encrypt_credentials(credentials)?;
+        let encoded = self.credential_manager.encode_for_storage(&encrypted);
+        let now_str = Utc::now().to_rfc3339();
+        let from = format!("/{}/{}/", previous.username, previous.password);
+        let to = format!("/{}/{}/", credentials.username, credentials.password);
+        let rewritten;
+        
+        {
+            let mut db = self.db.lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            
+            let tx = db.transaction()?;
+            let updated = tx.execute(
+                "UPDATE xtream_profiles SET username = ?, encrypted_credentials = ?, updated_at = ? WHERE id = ?",
+                (&credentials.username, &encoded, &now_str, id),
+            )?;
+            
+            if updated == 0 {
+                return Err(XTauriError::xtream_profile_not_found(id.to_string()));
+            }
+            
+            rewritten = crate::content_cache::rewrite_profile_stream_urls(&tx, id, &from, &to)?
+                + rewrite_content_data(&tx, id, |data| rewrite_url_segment(data, &from, &to))?;
+            
+            tx.commit()?;
+        }
+        
+        self.credential_manager.cache_credentials(id, credentials)?;
+        
+        Ok(rewritten)
+    }
+    
+    /// Move a profile to a new provider base URL
+    ///
+    /// The new URL is authenticated before anything is written. The profile and the
+    /// URLs stored in its favorites/history are then updated in one transaction.
+    /// Returns the previous base URL.
+    pub async fn change_profile_url(&self, id: &str, new_url: &str) -> Result<String> {
+        let current_credentials = self.get_profile_credentials(id)?;
+        let previous_url = current_credentials.url.trim_end_matches('/').to_string();
+        
+        let updated_credentials = ProfileCredentials {
+            url: new_url.trim().trim_end_matches('/').to_string(),
+            ..current_credentials
+        };
+        
+        if !self.validate_credentials(&updated_credentials).await? {
+            return Err(XTauriError::XtreamInvalidCredentials);
+        }
+        
+        self.store_profile_url(id, &updated_credentials, &previous_url)?;
+        
+        Ok(previous_url)
+    }
+    
+    /// Persist a new base URL and rewrite stored content URLs in a single transaction
+    ///
+    /// Covers the content cache's stream URLs as well as the snapshots in favorites
+    /// and history. Returns the number of rows rewritten.
+    fn store_profile_url(
+        &self,
+        id: &str,
+        credentials: &ProfileCredentials,
+        previous_url: &str,
+    ) -> Result<usize> {
+        let encrypted = self.credential_manager.encrypt_credentials(credentials)?;
+        let encoded = self.credential_manager.encode_for_storage(&encrypted);
+        let now_str = Utc::now().to_rfc3339();
+        let rewritten;
+        
+        {
+            let mut db = self.db.lock()
+                .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+            
+            let tx = db.transaction()?;
+            let updated = tx.execute(
+                "UPDATE xtream_profiles SET url = ?, encrypted_credentials = ?, updated_at = ? WHERE id = ?",
+                (&credentials.url, &encoded, &now_str, id),
+            )?;
+            
+            if updated == 0 {
+                return Err(XTauriError::xtream_profile_not_found(id.to_string()));
+            }
+            
+            rewritten = crate::content_cache::rewrite_profile_stream_url_prefix(&tx, id, previous_url, &credentials.url)?
+                + rewrite_content_data(&tx, id, |data| rewrite_url_prefix(data, previous_url, &credentials.url))?;
+            
+            tx.commit()?;
+        }
+        
+        self.credential_manager.cache_credentials(id, credentials)?;
+        
+        Ok(rewritten)
+    }
+    
     /// Get the currently active profile
     pub fn get_active_profile(&self) -> Result<Option<XtreamProfile>> {
-        let db = self.db.lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let db = self.reads.read()?;
             
         let mut stmt = db.prepare(
             "SELECT id, name, url, username, created_at, updated_at, last_used, is_active 
@@ -583,8 +823,7 @@ impl ProfileManager {
     
     /// Check if a profile name already exists
     fn profile_name_exists(&self, name: &str) -> Result<bool> {
-        let db = self.db.lock()
-            .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+        let db = self.reads.read()?;
             
         let count: i64 = db.query_row(
             "SELECT COUNT(*) FROM xtream_profiles WHERE name = ?",
@@ -760,16 +999,31 @@ impl ProfileManager {
             return Err(XTauriError::xtream_profile_not_found(id.to_string()));
         }
         
-        // Clear cached credentials
-        credential_manager.clear_cached_credentials(id)?;
-        
-        // Delete from database (cascade will handle related data)
         let db_conn = db.lock()
             .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
         
-        db_conn.execute("DELETE FROM xtream_profiles WHERE id = ?", [id])?;
-        
-        Ok(())
+        // Foreign key cascades aren't enabled on the connection, so the profile's
+        // data is deleted explicitly, all of it or none
+        unit_of_work::run(&db_conn, |unit| {
+            unit.enlist("Deleting cached content", |conn| {
+                crate::content_cache::delete_profile_data(conn, id)
+            })?;
+            unit.enlist("Deleting favorites, history and settings", |conn| {
+                for table in PROFILE_TABLES {
+                    conn.execute(&format!("DELETE FROM {} WHERE profile_id = ?1", table), [id])?;
+                }
+                Ok(())
+            })?;
+            unit.enlist("Deleting the profile", |conn| {
+                Ok(conn.execute("DELETE FROM xtream_profiles WHERE id = ?1", [id])?)
+            })?;
+            unit.after_commit(|| {
+                if let Err(e) = credential_manager.clear_cached_credentials(id) {
+                    eprintln!("Failed to clear cached credentials for profile {}: {}", id, e);
+                }
+            });
+            Ok(())
+        })
     }
     
     fn get_profiles_sync_static(db: &Arc<Mutex<Connection>>) -> Result<Vec<XtreamProfile>> {
@@ -909,7 +1163,7 @@ impl ProfileManager {
 
     /// Get playback history for a profile
     pub async fn get_playback_history(&self, profile_id: &str) -> Result<serde_json::Value> {
-        let db = self.db.lock().unwrap();
+        let db = self.reads.read()?;
         
         let mut stmt = db.prepare(
             "SELECT id, content_type, content_id, content_data, watched_at, position, duration 
@@ -950,6 +1204,12 @@ impl ProfileManager {
         position: Option<f64>,
         duration: Option<f64>,
     ) -> Result<()> {
+        // There is no per-playback private flag here, so only the session setting applies
+        if crate::xtream::history::playback_recording_suppressed(false) {
+            return Ok(());
+        }
+
+        let key = ContentId::new(content_type, content_id)?;
         let history_id = Uuid::new_v4().to_string();
         let now = Utc::now().to_rfc3339();
         let content_data_str = serde_json::to_string(content_data)
@@ -964,8 +1224,8 @@ impl ProfileManager {
             rusqlite::params![
                 history_id,
                 profile_id,
-                content_type,
-                content_id,
+                key.content_type(),
+                key.id(),
                 content_data_str,
                 now,
                 position,
@@ -985,6 +1245,11 @@ impl ProfileManager {
         position: f64,
         duration: Option<f64>,
     ) -> Result<()> {
+        if crate::xtream::history::playback_recording_suppressed(false) {
+            return Ok(());
+        }
+
+        let key = ContentId::new(content_type, content_id)?;
         let rows_affected = {
             let db = self.db.lock().unwrap();
             db.execute(
@@ -996,31 +1261,149 @@ impl ProfileManager {
                     duration,
                     Utc::now().to_rfc3339(),
                     profile_id,
-                    content_type,
-                    content_id
+                    key.content_type(),
+                    key.id()
                 ],
             )?
         };
         
         // If no existing entry was updated, create a new one
         if rows_affected == 0 {
-            self.add_to_playback_history(profile_id, content_type, content_id, &serde_json::Value::Null, Some(position), duration).await?;
+            self.add_to_playback_history(profile_id, key.content_type(), &key.id(), &serde_json::Value::Null, Some(position), duration).await?;
         }
         
         Ok(())
     }
     
+    /// Encrypt credentials as they are stored in `xtream_profiles.encrypted_credentials`
+    pub fn encode_credentials(&self, credentials: &ProfileCredentials) -> Result<String> {
+        let encrypted = self.credential_manager.encrypt_credentials(credentials)?;
+        Ok(self.credential_manager.encode_for_storage(&encrypted))
+    }
+    
+    /// Drop a profile's cached credentials, e.g. after its stored ones were replaced
+    pub fn forget_cached_credentials(&self, id: &str) -> Result<()> {
+        self.credential_manager.clear_cached_credentials(id)
+    }
+    
     /// Get the database connection (for use by other modules)
     pub fn get_db_connection(&self) -> Arc<Mutex<Connection>> {
         Arc::clone(&self.db)
     }
 }
 
+/// Rewrite the snapshots favorites and history keep of a profile's content, including its URLs
+///
+/// `rewrite` is given each snapshot and returns whether it changed it. Runs on
+/// the caller's transaction; returns the number of rows rewritten.
+fn rewrite_content_data(
+    tx: &Connection,
+    id: &str,
+    rewrite: impl Fn(&mut serde_json::Value) -> bool,
+) -> Result<usize> {
+    let mut rewritten = 0;
+    for table in ["xtream_favorites", "xtream_history"] {
+        let rows = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id, content_data FROM {} WHERE profile_id = ?",
+                table
+            ))?;
+            let rows = stmt
+                .query_map([id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            rows
+        };
+        
+        for (row_id, data) in rows {
+            let Ok(mut content_data) = serde_json::from_slice::<serde_json::Value>(&data) else {
+                continue;
+            };
+            
+            if rewrite(&mut content_data) {
+                let data = serde_json::to_vec(&content_data)
+                    .map_err(|e| XTauriError::internal(format!("Failed to serialize content data: {}", e)))?;
+                tx.execute(
+                    &format!("UPDATE {} SET content_data = ? WHERE id = ?", table),
+                    (&data, &row_id),
+                )?;
+                rewritten += 1;
+            }
+        }
+    }
+    Ok(rewritten)
+}
+
+/// Apply `rewrite` to every string in a JSON value; returns whether any changed
+fn rewrite_strings(value: &mut serde_json::Value, rewrite: &impl Fn(&mut String) -> bool) -> bool {
+    match value {
+        serde_json::Value::String(s) => rewrite(s),
+        serde_json::Value::Array(items) => {
+            let mut changed = false;
+            for item in items {
+                changed |= rewrite_strings(item, rewrite);
+            }
+            changed
+        }
+        serde_json::Value::Object(map) => {
+            let mut changed = false;
+            for item in map.values_mut() {
+                changed |= rewrite_strings(item, rewrite);
+            }
+            changed
+        }
+        _ => false,
+    }
+}
+
+/// Replace `from` with `to` at the start of every URL string in a JSON value
+///
+/// Only whole-host prefixes match, so "http://a.com" does not rewrite "http://a.com:8080/...".
+fn rewrite_url_prefix(value: &mut serde_json::Value, from: &str, to: &str) -> bool {
+    rewrite_strings(value, &|s: &mut String| match s.strip_prefix(from) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('?') => {
+            *s = format!("{}{}", to, rest);
+            true
+        }
+        _ => false,
+    })
+}
+
+/// Replace every `from` with `to` in the strings of a JSON value, like the content cache's URL rewrite
+fn rewrite_url_segment(value: &mut serde_json::Value, from: &str, to: &str) -> bool {
+    if from.is_empty() || from == to {
+        return false;
+    }
+    rewrite_strings(value, &|s: &mut String| {
+        if s.contains(from) {
+            *s = s.replace(from, to);
+            true
+        } else {
+            false
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rusqlite::Connection;
     
+    /// A test database that also has every table storing stream URLs
+    fn create_test_db_with_stream_urls() -> Connection {
+        let conn = create_test_db();
+        crate::content_cache::initialize_content_cache_tables(&conn).unwrap();
+        for table in ["xtream_favorites", "xtream_history"] {
+            conn.execute(
+                &format!(
+                    "CREATE TABLE {} (id TEXT PRIMARY KEY, profile_id TEXT NOT NULL, content_data BLOB NOT NULL)",
+                    table
+                ),
+                [],
+            ).unwrap();
+        }
+        conn
+    }
+    
     fn create_test_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
         
@@ -1042,6 +1425,22 @@ mod tests {
         conn
     }
     
+    /// Add every table that keeps rows for a profile
+    fn add_profile_data_tables(conn: &Connection) {
+        crate::content_cache::schema::initialize_content_cache_tables(conn).unwrap();
+        for table in PROFILE_TABLES {
+            conn.execute(&format!("CREATE TABLE IF NOT EXISTS {} (profile_id TEXT NOT NULL)", table), []).unwrap();
+        }
+    }
+    
+    fn create_test_credentials() -> ProfileCredentials {
+        ProfileCredentials {
+            url: "http://example.com:8080".to_string(),
+            username: "testuser".to_string(),
+            password: "testpass123".to_string(),
+        }
+    }
+    
     fn create_test_request() -> CreateProfileRequest {
         CreateProfileRequest {
             name: "Test Profile".to_string(),
@@ -1124,7 +1523,9 @@ mod tests {
     
     #[test]
     fn test_delete_profile() {
-        let db = Arc::new(Mutex::new(create_test_db()));
+        let conn = create_test_db();
+        add_profile_data_tables(&conn);
+        let db = Arc::new(Mutex::new(conn));
         let credential_manager = Arc::new(CredentialManager::new().unwrap());
         let manager = ProfileManager::new(db, credential_manager);
         
@@ -1141,6 +1542,52 @@ mod tests {
         assert!(manager.get_profile(&profile_id).unwrap().is_none());
     }
     
+    #[test]
+    fn test_delete_profile_removes_its_data_atomically() {
+        let conn = create_test_db();
+        add_profile_data_tables(&conn);
+        let db = Arc::new(Mutex::new(conn));
+        let manager = ProfileManager::new(Arc::clone(&db), Arc::new(CredentialManager::with_key([7u8; 32])));
+        
+        let profile_id = manager.create_profile_without_validation(create_test_request()).unwrap();
+        let mut other_request = create_test_request();
+        other_request.name = "Other Profile".to_string();
+        let other_id = manager.create_profile_without_validation(other_request).unwrap();
+        
+        let count = |table: &str, profile_id: &str| -> i64 {
+            db.lock()
+                .unwrap()
+                .query_row(&format!("SELECT COUNT(*) FROM {} WHERE profile_id = ?1", table), [profile_id], |row| row.get(0))
+                .unwrap()
+        };
+        {
+            let conn = db.lock().unwrap();
+            for id in [&profile_id, &other_id] {
+                conn.execute("INSERT INTO xtream_favorites (profile_id) VALUES (?1)", [id]).unwrap();
+                conn.execute(
+                    "INSERT INTO xtream_channel_url_overrides (profile_id, stream_id, url) VALUES (?1, 1, 'http://override')",
+                    [id],
+                )
+                .unwrap();
+            }
+        }
+        
+        // A failing step leaves everything in place
+        db.lock().unwrap().execute("DROP TABLE xtream_content_cache", []).unwrap();
+        assert!(manager.delete_profile(&profile_id).is_err());
+        assert!(manager.get_profile(&profile_id).unwrap().is_some());
+        assert_eq!(count("xtream_favorites", &profile_id), 1);
+        assert_eq!(count("xtream_channel_url_overrides", &profile_id), 1);
+        
+        db.lock().unwrap().execute("CREATE TABLE xtream_content_cache (profile_id TEXT NOT NULL)", []).unwrap();
+        manager.delete_profile(&profile_id).unwrap();
+        assert!(manager.get_profile(&profile_id).unwrap().is_none());
+        assert_eq!(count("xtream_favorites", &profile_id), 0);
+        assert_eq!(count("xtream_channel_url_overrides", &profile_id), 0);
+        assert_eq!(count("xtream_favorites", &other_id), 1);
+        assert_eq!(count("xtream_channel_url_overrides", &other_id), 1);
+    }
+    
     #[test]
     fn test_set_active_profile() {
         let db = Arc::new(Mutex::new(create_test_db()));
@@ -1383,4 +1830,182 @@ mod tests {
         let result = manager.create_profile_without_validation(request);
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_profiles_are_read_from_the_pool_while_the_writer_is_busy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profiles.sqlite");
+        let writer = Connection::open(&path).unwrap();
+        crate::db_pool::configure_writer(&writer).unwrap();
+        let template = create_test_db();
+        let schema: String = template
+            .query_row("SELECT sql FROM sqlite_master WHERE name = 'xtream_profiles'", [], |row| row.get(0))
+            .unwrap();
+        writer.execute(&schema, []).unwrap();
+        
+        let pool = Arc::new(DbPool::open(writer, path, 2));
+        let credential_manager = Arc::new(CredentialManager::with_key([7u8; 32]));
+        let manager = ProfileManager::new(pool.writer(), credential_manager).with_read_pool(Arc::clone(&pool));
+        let profile_id = manager.create_profile_without_validation(create_test_request()).unwrap();
+        
+        // A sync holding the one writer doesn't keep profiles from being listed
+        let _busy = pool.write().unwrap();
+        assert_eq!(manager.get_profiles().unwrap().len(), 1);
+        assert!(manager.get_profile(&profile_id).unwrap().is_some());
+    }
+    
+    #[test]
+    fn test_store_rotated_credentials() {
+        let db = Arc::new(Mutex::new(create_test_db_with_stream_urls()));
+        let credential_manager = Arc::new(CredentialManager::with_key([7u8; 32]));
+        let manager = ProfileManager::new(Arc::clone(&db), Arc::clone(&credential_manager));
+        
+        let profile_id = manager.create_profile_without_validation(create_test_request()).unwrap();
+        
+        let rotated = ProfileCredentials {
+            url: "http://example.com:8080".to_string(),
+            username: "newuser".to_string(),
+            password: "newpass456".to_string(),
+        };
+        let previous = create_test_credentials();
+        manager.store_rotated_credentials(&profile_id, &previous, &rotated).unwrap();
+        
+        // Cached credentials are refreshed immediately
+        let cached = manager.get_profile_credentials(&profile_id).unwrap();
+        assert_eq!(cached.password, "newpass456");
+        
+        // Stored credentials decrypt to the new values
+        credential_manager.clear_cached_credentials(&profile_id).unwrap();
+        let stored = manager.get_profile_credentials(&profile_id).unwrap();
+        assert_eq!(stored.username, "newuser");
+        assert_eq!(stored.password, "newpass456");
+        
+        let profile = manager.get_profile(&profile_id).unwrap().unwrap();
+        assert_eq!(profile.username, "newuser");
+        
+        assert!(manager.store_rotated_credentials("missing", &previous, &rotated).is_err());
+    }
+    
+    #[test]
+    fn test_store_rotated_credentials_rewrites_every_stream_url() {
+        let db = Arc::new(Mutex::new(create_test_db_with_stream_urls()));
+        let credential_manager = Arc::new(CredentialManager::with_key([7u8; 32]));
+        let manager = ProfileManager::new(Arc::clone(&db), credential_manager);
+        let profile_id = manager.create_profile_without_validation(create_test_request()).unwrap();
+        
+        {
+            let conn = db.lock().unwrap();
+            conn.execute(
+                "INSERT INTO xtream_channels (profile_id, stream_id, name, direct_source)
+                 VALUES (?1, 1, 'News', 'http://example.com:8080/testuser/testpass123/1')",
+                [&profile_id],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO xtream_movies (profile_id, stream_id, name, direct_source)
+                 VALUES (?1, 2, 'Heat', 'http://example.com:8080/movie/testuser/testpass123/2.mkv')",
+                [&profile_id],
+            ).unwrap();
+            let favorite = serde_json::json!({
+                "name": "News",
+                "direct_source": "http://example.com:8080/live/testuser/testpass123/1.ts",
+                "nested": ["http://example.com:8080/testuser/testpass123/1"],
+            });
+            conn.execute(
+                "INSERT INTO xtream_favorites (id, profile_id, content_data) VALUES ('f1', ?1, ?2)",
+                rusqlite::params![profile_id, serde_json::to_vec(&favorite).unwrap()],
+            ).unwrap();
+            let watched = serde_json::json!({
+                "name": "Heat",
+                "direct_source": "http://example.com:8080/movie/testuser/testpass123/2.mkv",
+            });
+            conn.execute(
+                "INSERT INTO xtream_history (id, profile_id, content_data) VALUES ('h1', ?1, ?2)",
+                rusqlite::params![profile_id, serde_json::to_vec(&watched).unwrap()],
+            ).unwrap();
+        }
+        
+        let rotated = ProfileCredentials {
+            url: "http://example.com:8080".to_string(),
+            username: "newuser".to_string(),
+            password: "newpass456".to_string(),
+        };
+        let rewritten = manager
+            .store_rotated_credentials(&profile_id, &create_test_credentials(), &rotated)
+            .unwrap();
+        assert_eq!(rewritten, 4);
+        
+        let conn = db.lock().unwrap();
+        let mut urls: Vec<String> = Vec::new();
+        for table in ["xtream_channels", "xtream_movies"] {
+            urls.push(
+                conn.query_row(&format!("SELECT direct_source FROM {}", table), [], |row| row.get(0))
+                    .unwrap(),
+            );
+        }
+        for table in ["xtream_favorites", "xtream_history"] {
+            let data: Vec<u8> = conn
+                .query_row(&format!("SELECT content_data FROM {}", table), [], |row| row.get(0))
+                .unwrap();
+            urls.push(String::from_utf8(data).unwrap());
+        }
+        for url in &urls {
+            assert!(!url.contains("/testuser/testpass123/"), "old credentials left in {}", url);
+            assert!(url.contains("/newuser/newpass456/"), "new credentials missing in {}", url);
+        }
+    }
+    
+    #[test]
+    fn test_store_profile_url_rewrites_favorites_and_history() {
+        let db = Arc::new(Mutex::new(create_test_db_with_stream_urls()));
+        let credential_manager = Arc::new(CredentialManager::with_key([7u8; 32]));
+        let manager = ProfileManager::new(Arc::clone(&db), credential_manager);
+        let profile_id = manager.create_profile_without_validation(create_test_request()).unwrap();
+        
+        {
+            let conn = db.lock().unwrap();
+            let favorite = serde_json::json!({
+                "name": "News",
+                "direct_source": "http://example.com:8080/live/testuser/testpass123/1.ts",
+                "stream_icon": "http://example.com:80801/logo.png",
+            });
+            conn.execute(
+                "INSERT INTO xtream_favorites (id, profile_id, content_data) VALUES ('f1', ?1, ?2)",
+                rusqlite::params![profile_id, serde_json::to_vec(&favorite).unwrap()],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO xtream_history (id, profile_id, content_data) VALUES ('h1', ?1, ?2)",
+                rusqlite::params![profile_id, serde_json::to_vec(&serde_json::json!({"name": "Movie"})).unwrap()],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO xtream_channels (profile_id, stream_id, name, direct_source)
+                 VALUES (?1, 1, 'News', 'http://example.com:8080/testuser/testpass123/1')",
+                [&profile_id],
+            ).unwrap();
+        }
+        
+        let moved = ProfileCredentials {
+            url: "https://new.example.org".to_string(),
+            username: "testuser".to_string(),
+            password: "testpass123".to_string(),
+        };
+        let rewritten = manager.store_profile_url(&profile_id, &moved, "http://example.com:8080").unwrap();
+        assert_eq!(rewritten, 2);
+        
+        let cached_url: String = db.lock().unwrap()
+            .query_row("SELECT direct_source FROM xtream_channels", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cached_url, "https://new.example.org/testuser/testpass123/1");
+        
+        let data: Vec<u8> = db.lock().unwrap()
+            .query_row("SELECT content_data FROM xtream_favorites WHERE id = 'f1'", [], |row| row.get(0))
+            .unwrap();
+        let favorite: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(favorite["direct_source"], "https://new.example.org/live/testuser/testpass123/1.ts");
+        // A different port is a different host and must not be rewritten
+        assert_eq!(favorite["stream_icon"], "http://example.com:80801/logo.png");
+        
+        let profile = manager.get_profile(&profile_id).unwrap().unwrap();
+        assert_eq!(profile.url, "https://new.example.org");
+        assert_eq!(manager.get_profile_credentials(&profile_id).unwrap().url, "https://new.example.org");
+    }
 }
\ No newline at end of file
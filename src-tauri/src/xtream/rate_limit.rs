@@ -0,0 +1,258 @@
+// Per-profile request limits
+//
+// Providers ban accounts that hammer their API, so requests to a profile's
+// provider, from an `XtreamClient` or a content sync, go through the profile's
+// `RequestLimiter`: a token bucket caps the request rate and bursts, and no more
+// than `max_concurrent` requests are in flight at once. The limits are set per
+// profile in `xtream_request_limits`; one limiter per profile is shared by every
+// client and sync of the run, and a change applies to the next request.
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+pub const DEFAULT_BURST: u32 = 10;
+pub const DEFAULT_MAX_CONCURRENT: u32 = 4;
+
+const MAX_REQUESTS_PER_SECOND: f64 = 100.0;
+const MAX_CONCURRENT: u32 = 16;
+
+/// How fast and how many requests at once a profile's provider is sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, specta::Type)]
+pub struct RequestLimits {
+    pub profile_id: String,
+    /// Sustained rate the token bucket refills at
+    pub requests_per_second: f64,
+    /// Requests that may start back to back after a quiet spell
+    pub burst: u32,
+    pub max_concurrent: u32,
+}
+
+impl RequestLimits {
+    pub fn defaults(profile_id: &str) -> Self {
+        Self {
+            profile_id: profile_id.to_string(),
+            requests_per_second: DEFAULT_REQUESTS_PER_SECOND,
+            burst: DEFAULT_BURST,
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(self.requests_per_second > 0.0 && self.requests_per_second <= MAX_REQUESTS_PER_SECOND) {
+            return Err(XTauriError::internal(format!(
+                "Requests per second must be above 0 and at most {}",
+                MAX_REQUESTS_PER_SECOND
+            )));
+        }
+        if self.burst == 0 {
+            return Err(XTauriError::internal("Burst must be at least 1".to_string()));
+        }
+        if !(1..=MAX_CONCURRENT).contains(&self.max_concurrent) {
+            return Err(XTauriError::internal(format!(
+                "Concurrent requests must be between 1 and {}",
+                MAX_CONCURRENT
+            )));
+        }
+        Ok(())
+    }
+}
+
+pub fn load_limits(conn: &Connection, profile_id: &str) -> Result<RequestLimits> {
+    let limits = conn
+        .query_row(
+            "SELECT requests_per_second, burst, max_concurrent FROM xtream_request_limits WHERE profile_id = ?1",
+            [profile_id],
+            |row| {
+                Ok(RequestLimits {
+                    profile_id: profile_id.to_string(),
+                    requests_per_second: row.get(0)?,
+                    burst: row.get(1)?,
+                    max_concurrent: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(limits.unwrap_or_else(|| RequestLimits::defaults(profile_id)))
+}
+
+/// Save a profile's limits and apply them to its limiter
+pub fn save_limits(conn: &Connection, limits: &RequestLimits) -> Result<()> {
+    limits.validate()?;
+    conn.execute(
+        "INSERT INTO xtream_request_limits (profile_id, requests_per_second, burst, max_concurrent)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(profile_id) DO UPDATE SET
+            requests_per_second = excluded.requests_per_second,
+            burst = excluded.burst,
+            max_concurrent = excluded.max_concurrent",
+        params![limits.profile_id, limits.requests_per_second, limits.burst, limits.max_concurrent],
+    )?;
+    if let Some(limiter) = LIMITERS.lock().ok().and_then(|limiters| limiters.get(&limits.profile_id).cloned()) {
+        limiter.set_limits(limits.clone());
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    limits: RequestLimits,
+    tokens: f64,
+    refilled_at: Instant,
+    in_flight: u32,
+}
+
+impl LimiterState {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limits.requests_per_second).min(self.limits.burst as f64);
+        self.refilled_at = now;
+    }
+}
+
+/// Paces the requests to one profile's provider
+#[derive(Debug)]
+pub struct RequestLimiter {
+    state: Mutex<LimiterState>,
+    released: Notify,
+}
+
+/// A request slot, given back when dropped
+pub struct RequestPermit<'a> {
+    limiter: &'a RequestLimiter,
+}
+
+impl Drop for RequestPermit<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.limiter.state.lock() {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+        self.limiter.released.notify_waiters();
+    }
+}
+
+impl RequestLimiter {
+    pub fn new(limits: RequestLimits) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(LimiterState {
+                tokens: limits.burst as f64,
+                limits,
+                refilled_at: Instant::now(),
+                in_flight: 0,
+            }),
+            released: Notify::new(),
+        })
+    }
+
+    pub fn limits(&self) -> Option<RequestLimits> {
+        self.state.lock().ok().map(|state| state.limits.clone())
+    }
+
+    fn set_limits(&self, limits: RequestLimits) {
+        if let Ok(mut state) = self.state.lock() {
+            state.refill(Instant::now());
+            state.tokens = state.tokens.min(limits.burst as f64);
+            state.limits = limits;
+        }
+        // Waiters recheck against a possibly higher concurrency
+        self.released.notify_waiters();
+    }
+
+    /// Wait until a request may start without going over the profile's limits
+    pub async fn acquire(&self) -> RequestPermit<'_> {
+        loop {
+            let released = self.released.notified();
+            let wait = {
+                let Ok(mut state) = self.state.lock() else {
+                    return RequestPermit { limiter: self };
+                };
+                if state.in_flight >= state.limits.max_concurrent {
+                    None
+                } else {
+                    state.refill(Instant::now());
+                    if state.tokens >= 1.0 {
+                        state.tokens -= 1.0;
+                        state.in_flight += 1;
+                        return RequestPermit { limiter: self };
+                    }
+                    Some(Duration::from_secs_f64((1.0 - state.tokens) / state.limits.requests_per_second))
+                }
+            };
+            match wait {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => released.await,
+            }
+        }
+    }
+}
+
+/// Limiters of the profiles that sent requests this run
+static LIMITERS: LazyLock<Mutex<HashMap<String, Arc<RequestLimiter>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The limiter every request to a profile's provider shares, set up from its saved limits
+pub fn limiter_for(conn: &Connection, profile_id: &str) -> Result<Arc<RequestLimiter>> {
+    let mut limiters = LIMITERS.lock().map_err(|_| XTauriError::lock_acquisition("request limiters"))?;
+    if let Some(limiter) = limiters.get(profile_id) {
+        return Ok(Arc::clone(limiter));
+    }
+    let limiter = RequestLimiter::new(load_limits(conn, profile_id)?);
+    limiters.insert(profile_id.to_string(), Arc::clone(&limiter));
+    Ok(limiter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_limiter_caps_rate_and_requests_in_flight() {
+        let limiter = RequestLimiter::new(RequestLimits {
+            requests_per_second: 20.0,
+            burst: 2,
+            max_concurrent: 2,
+            ..RequestLimits::defaults("p1")
+        });
+
+        // The burst starts at once, the next request waits for a token
+        let started = Instant::now();
+        drop(limiter.acquire().await);
+        drop(limiter.acquire().await);
+        assert!(started.elapsed() < Duration::from_millis(40));
+        drop(limiter.acquire().await);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let first = limiter.acquire().await;
+        let _second = limiter.acquire().await;
+        assert!(tokio::time::timeout(Duration::from_millis(100), limiter.acquire()).await.is_err());
+        drop(first);
+        let _third = limiter.acquire().await;
+    }
+
+    #[test]
+    fn test_limits_persist_and_validate() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE xtream_request_limits (
+                profile_id TEXT PRIMARY KEY, requests_per_second REAL NOT NULL,
+                burst INTEGER NOT NULL, max_concurrent INTEGER NOT NULL
+            );",
+        )
+        .unwrap();
+        assert_eq!(load_limits(&conn, "p1").unwrap(), RequestLimits::defaults("p1"));
+
+        let limits = RequestLimits { requests_per_second: 1.5, max_concurrent: 1, ..RequestLimits::defaults("p1") };
+        save_limits(&conn, &limits).unwrap();
+        assert_eq!(load_limits(&conn, "p1").unwrap(), limits);
+        assert_eq!(load_limits(&conn, "p2").unwrap(), RequestLimits::defaults("p2"));
+
+        assert!(save_limits(&conn, &RequestLimits { requests_per_second: 0.0, ..limits.clone() }).is_err());
+        assert!(save_limits(&conn, &RequestLimits { max_concurrent: 0, ..limits.clone() }).is_err());
+        assert!(save_limits(&conn, &RequestLimits { burst: 0, ..limits }).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+// Recorded provider responses
+//
+// In record mode every `player_api.php` response is written to a fixture
+// directory with credentials stripped from the request and masked in the
+// response. In replay mode requests are answered from those fixtures instead of
+// the network, which is what tests use to run the client without a provider.
+// Recording is only allowed in debug builds, since fixtures are written next to
+// whatever the provider sent. The mode is runtime-only, like demo mode.
+//
+// Profiles on `DEMO_HOST` always replay, from the `demo_fixtures` directory in
+// the app data directory, so the whole app can run against recorded data.
+use crate::anonymize;
+use crate::error::{Result, XTauriError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use url::Url;
+
+/// Server host of the demo profile
+pub const DEMO_HOST: &str = "demo.xtauri.invalid";
+
+/// Query parameters left out of fixture names and recorded requests
+const CREDENTIAL_PARAMS: &[&str] = &["username", "password"];
+
+/// Where provider responses come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderMode {
+    /// Requests go to the provider
+    #[default]
+    Live,
+    /// Requests go to the provider and responses are written to fixtures
+    Record,
+    /// Requests are answered from fixtures
+    Replay,
+}
+
+/// A recorded request and its response, as stored in a fixture file
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    /// Request path and query without credentials
+    request: String,
+    response: Value,
+}
+
+/// Current mode and its fixture directory; `None` is live
+static MODE: RwLock<Option<(ProviderMode, PathBuf)>> = RwLock::new(None);
+
+fn current() -> Option<(ProviderMode, PathBuf)> {
+    MODE.read().ok()?.clone()
+}
+
+pub fn mode() -> ProviderMode {
+    current().map_or(ProviderMode::Live, |(mode, _)| mode)
+}
+
+/// Switch modes; `dir` is required to record or replay
+pub fn set_mode(mode: ProviderMode, dir: Option<PathBuf>) -> Result<()> {
+    if mode == ProviderMode::Record && !cfg!(debug_assertions) {
+        return Err(XTauriError::internal("Recording provider responses is only available in debug builds".to_string()));
+    }
+    let next = match (mode, dir) {
+        (ProviderMode::Live, _) => None,
+        (mode, Some(dir)) => {
+            std::fs::create_dir_all(&dir).map_err(|_| XTauriError::directory_creation(dir.display().to_string()))?;
+            Some((mode, dir))
+        }
+        (_, None) => {
+            return Err(XTauriError::internal("A fixture directory is required to record or replay".to_string()));
+        }
+    };
+    *MODE.write().map_err(|_| XTauriError::lock_acquisition("provider mode"))? = next;
+    Ok(())
+}
+
+/// Fixtures the demo profile replays
+pub fn demo_fixture_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("xtauri").join("demo_fixtures"))
+}
+
+/// The request without credentials, with its query sorted so fixture names are stable
+fn sanitized_request(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !CREDENTIAL_PARAMS.contains(&key.as_ref()))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+    let query: Vec<String> = pairs.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+    format!("{}?{}", url.path(), query.join("&"))
+}
+
+/// File name for a request: its action plus a hash of the sanitized request
+pub(crate) fn fixture_name(url: &Url) -> String {
+    let action = url
+        .query_pairs()
+        .find(|(key, _)| key == "action")
+        .map_or("authenticate".to_string(), |(_, action)| action.into_owned());
+    let digest = Sha256::digest(sanitized_request(url).as_bytes());
+    let hex: String = digest[..6].iter().map(|b| format!("{:02x}", b)).collect();
+    let action: String = action
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}-{}.json", action, hex)
+}
+
+/// Fixture directory to replay a request from, if it is replayed
+fn replay_dir(url: &Url) -> Option<PathBuf> {
+    if url.host_str() == Some(DEMO_HOST) {
+        return demo_fixture_dir();
+    }
+    match current()? {
+        (ProviderMode::Replay, dir) => Some(dir),
+        _ => None,
+    }
+}
+
+fn read_fixture(dir: &Path, url: &Url) -> Result<Value> {
+    let path = dir.join(fixture_name(url));
+    let contents = std::fs::read_to_string(&path).map_err(|_| {
+        XTauriError::xtream_api_error(404, format!("No recorded response for {}", sanitized_request(url)))
+    })?;
+    let fixture: Fixture = serde_json::from_str(&contents)
+        .map_err(|e| XTauriError::internal(format!("Invalid fixture {}: {}", path.display(), e)))?;
+    Ok(fixture.response)
+}
+
+/// The recorded response to a request, or `None` if it should go to the provider
+pub fn replay(url: &str) -> Option<Result<Value>> {
+    let url = Url::parse(url).ok()?;
+    let dir = replay_dir(&url)?;
+    Some(read_fixture(&dir, &url))
+}
+
+pub(crate) fn write_fixture(dir: &Path, url: &Url, response: &Value) -> Result<()> {
+    let mut response = response.clone();
+    anonymize::mask_json(&mut response);
+    let fixture = Fixture {
+        request: sanitized_request(url),
+        response,
+    };
+    let contents = serde_json::to_string_pretty(&fixture)
+        .map_err(|e| XTauriError::internal(format!("Failed to serialize fixture: {}", e)))?;
+    std::fs::write(dir.join(fixture_name(url)), contents)?;
+    Ok(())
+}
+
+/// Write a response to the fixture directory when recording
+pub fn record(url: &str, response: &Value) {
+    let Some((ProviderMode::Record, dir)) = current() else {
+        return;
+    };
+    let Ok(url) = Url::parse(url) else {
+        return;
+    };
+    if let Err(e) = write_fixture(&dir, &url, response) {
+        eprintln!("[RECORDING] Failed to record {}: {}", sanitized_request(&url), e);
+    }
+}
+
+#[tauri::command]
+pub fn get_provider_mode() -> ProviderMode {
+    mode()
+}
+
+/// Record provider responses to, or replay them from, a fixture directory
+#[tauri::command]
+pub fn set_provider_mode(mode: ProviderMode, dir: Option<String>) -> std::result::Result<(), String> {
+    set_mode(mode, dir.map(PathBuf::from)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xtream::{ContentCache, ProfileCredentials, XtreamClient};
+    use rusqlite::Connection;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_record_then_replay_without_a_provider() {
+        let dir = std::env::temp_dir().join(format!("xtauri-fixtures-{}", uuid::Uuid::new_v4()));
+        let url = "http://provider.example:8080/player_api.php?username=alice&password=secret&action=get_live_categories";
+
+        set_mode(ProviderMode::Record, Some(dir.clone())).unwrap();
+        record(url, &json!([{ "category_id": "1", "category_name": "News" }]));
+        record(
+            "http://provider.example:8080/player_api.php?username=alice&password=secret",
+            &json!({ "user_info": { "username": "alice", "password": "secret", "auth": 1 } }),
+        );
+        let written: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(written.len(), 2);
+        assert!(written.iter().all(|fixture| !fixture.contains("alice") && !fixture.contains("secret")));
+
+        // Replay doesn't depend on the credentials the request was recorded with
+        set_mode(ProviderMode::Replay, Some(dir.clone())).unwrap();
+        let other_user = "http://provider.example:8080/player_api.php?action=get_live_categories&username=bob&password=x";
+        assert_eq!(replay(other_user).unwrap().unwrap()[0]["category_name"], "News");
+        assert!(replay("http://provider.example:8080/player_api.php?username=a&password=b&action=get_vod_categories")
+            .unwrap()
+            .is_err());
+
+        let credentials = ProfileCredentials {
+            url: "http://provider.example:8080".to_string(),
+            username: "bob".to_string(),
+            password: "x".to_string(),
+        };
+        let cache = Arc::new(ContentCache::new(
+            Arc::new(Mutex::new(Connection::open_in_memory().unwrap())),
+            Duration::from_secs(60),
+        ));
+        let client = XtreamClient::new(credentials, cache).unwrap();
+        let categories = client.get_channel_categories().await.unwrap();
+        assert_eq!(categories[0]["category_name"], "News");
+        assert!(client.get_movie_categories().await.is_err());
+
+        set_mode(ProviderMode::Live, None).unwrap();
+        assert!(replay(url).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,360 @@
+use crate::error::{Result, XTauriError};
+use crate::notifications::{self, Notification, NotificationKind};
+use crate::xtream::XtreamState;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// Minutes before a programme starts that exported calendar events alert
+const ICS_ALARM_MINUTES: u32 = 10;
+
+/// ICS content lines should not exceed 75 octets before folding
+const ICS_LINE_LIMIT: usize = 75;
+
+/// How long before a programme starts its reminder is sent
+const REMINDER_LEAD_SECS: i64 = 2 * 60;
+
+/// How often due reminders are looked for
+const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A reminder for an upcoming EPG programme
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct XtreamEpgReminder {
+    pub id: String,
+    pub profile_id: String,
+    pub channel_id: String,
+    pub channel_name: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub start_timestamp: i64,
+    pub stop_timestamp: i64,
+    pub created_at: String,
+}
+
+/// Request to create an EPG reminder
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct CreateEpgReminderRequest {
+    pub profile_id: String,
+    pub channel_id: String,
+    pub channel_name: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub start_timestamp: i64,
+    pub stop_timestamp: i64,
+}
+
+/// Database operations for EPG reminders
+pub struct XtreamRemindersDb;
+
+impl XtreamRemindersDb {
+    /// Create a reminder; setting the same programme twice returns the existing reminder
+    pub fn add_reminder(conn: &Connection, request: &CreateEpgReminderRequest) -> Result<String> {
+        if request.title.trim().is_empty() {
+            return Err(XTauriError::internal("Reminder title cannot be empty".to_string()));
+        }
+        if request.stop_timestamp <= request.start_timestamp {
+            return Err(XTauriError::internal(
+                "Reminder must end after it starts".to_string(),
+            ));
+        }
+
+        let id = Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT OR IGNORE INTO xtream_epg_reminders
+             (id, profile_id, channel_id, channel_name, title, description, start_timestamp, stop_timestamp, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id,
+                request.profile_id,
+                request.channel_id,
+                request.channel_name,
+                request.title.trim(),
+                request.description,
+                request.start_timestamp,
+                request.stop_timestamp,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+
+        let id = conn.query_row(
+            "SELECT id FROM xtream_epg_reminders
+             WHERE profile_id = ?1 AND channel_id = ?2 AND start_timestamp = ?3",
+            params![request.profile_id, request.channel_id, request.start_timestamp],
+            |row| row.get(0),
+        )?;
+
+        Ok(id)
+    }
+
+    pub fn remove_reminder(conn: &Connection, id: &str) -> Result<()> {
+        conn.execute("DELETE FROM xtream_epg_reminders WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Get a profile's reminders by start time, optionally only those ending after `after`
+    pub fn get_reminders(
+        conn: &Connection,
+        profile_id: &str,
+        after: Option<i64>,
+    ) -> Result<Vec<XtreamEpgReminder>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, channel_id, channel_name, title, description, start_timestamp, stop_timestamp, created_at
+             FROM xtream_epg_reminders
+             WHERE profile_id = ?1 AND (?2 IS NULL OR stop_timestamp > ?2)
+             ORDER BY start_timestamp",
+        )?;
+
+        let reminders = stmt
+            .query_map(params![profile_id, after], reminder_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(reminders)
+    }
+
+    /// Reminders starting within `lead_secs` that haven't been notified, marked notified
+    pub fn take_due_reminders(conn: &Connection, now: i64, lead_secs: i64) -> Result<Vec<XtreamEpgReminder>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, profile_id, channel_id, channel_name, title, description, start_timestamp, stop_timestamp, created_at
+             FROM xtream_epg_reminders
+             WHERE notified = 0 AND start_timestamp <= ?1 AND stop_timestamp > ?2
+             ORDER BY start_timestamp",
+        )?;
+        let due = stmt
+            .query_map(params![now + lead_secs, now], reminder_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        for reminder in &due {
+            conn.execute(
+                "UPDATE xtream_epg_reminders SET notified = 1 WHERE id = ?1",
+                params![reminder.id],
+            )?;
+        }
+        Ok(due)
+    }
+
+    /// Delete reminders for programmes that have already ended
+    pub fn clear_past_reminders(conn: &Connection, now: i64) -> Result<usize> {
+        let removed = conn.execute(
+            "DELETE FROM xtream_epg_reminders WHERE stop_timestamp <= ?1",
+            params![now],
+        )?;
+        Ok(removed)
+    }
+}
+
+fn reminder_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<XtreamEpgReminder> {
+    Ok(XtreamEpgReminder {
+        id: row.get(0)?,
+        profile_id: row.get(1)?,
+        channel_id: row.get(2)?,
+        channel_name: row.get(3)?,
+        title: row.get(4)?,
+        description: row.get(5)?,
+        start_timestamp: row.get(6)?,
+        stop_timestamp: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// Send a notification for each reminder as its programme is about to start
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    loop {
+        let due = match app_handle.state::<XtreamState>().profile_manager.get_db_connection().lock() {
+            Ok(conn) => XtreamRemindersDb::take_due_reminders(&conn, chrono::Utc::now().timestamp(), REMINDER_LEAD_SECS),
+            Err(_) => return,
+        };
+        match due {
+            Ok(due) => {
+                for reminder in due {
+                    notifications::notify(
+                        &app_handle,
+                        Notification {
+                            kind: NotificationKind::Reminder,
+                            title: format!("{} is starting", reminder.title),
+                            body: format!("On {}", reminder.channel_name),
+                            profile_id: Some(reminder.profile_id),
+                        },
+                    );
+                }
+            }
+            Err(e) => eprintln!("[REMINDERS] Failed to check due reminders: {}", e),
+        }
+
+        tokio::time::sleep(REMINDER_CHECK_INTERVAL).await;
+    }
+}
+
+fn ics_timestamp(timestamp: i64) -> Result<String> {
+    DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y%m%dT%H%M%SZ").to_string())
+        .ok_or_else(|| XTauriError::internal(format!("Invalid timestamp: {}", timestamp)))
+}
+
+/// Escape a TEXT value (RFC 5545 section 3.3.11)
+fn ics_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Append a content line, folding it so no line exceeds the octet limit
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > ICS_LINE_LIMIT {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Render reminders as an iCalendar document
+pub fn reminders_to_ics(reminders: &[XtreamEpgReminder]) -> Result<String> {
+    let stamp = ics_timestamp(Utc::now().timestamp())?;
+    let mut out = String::new();
+
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//xTauri//EPG Reminders//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, "X-WR-CALNAME:xTauri reminders");
+
+    for reminder in reminders {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}@xtauri", reminder.id));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut out, &format!("DTSTART:{}", ics_timestamp(reminder.start_timestamp)?));
+        push_line(&mut out, &format!("DTEND:{}", ics_timestamp(reminder.stop_timestamp)?));
+        push_line(&mut out, &format!("SUMMARY:{}", ics_escape(&reminder.title)));
+        push_line(&mut out, &format!("LOCATION:{}", ics_escape(&reminder.channel_name)));
+        if let Some(description) = reminder.description.as_deref().filter(|d| !d.is_empty()) {
+            push_line(&mut out, &format!("DESCRIPTION:{}", ics_escape(description)));
+        }
+        push_line(&mut out, "BEGIN:VALARM");
+        push_line(&mut out, "ACTION:DISPLAY");
+        push_line(&mut out, &format!("DESCRIPTION:{}", ics_escape(&reminder.title)));
+        push_line(&mut out, &format!("TRIGGER:-PT{}M", ICS_ALARM_MINUTES));
+        push_line(&mut out, "END:VALARM");
+        push_line(&mut out, "END:VEVENT");
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_epg_reminders (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                channel_id TEXT NOT NULL,
+                channel_name TEXT NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                start_timestamp INTEGER NOT NULL,
+                stop_timestamp INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                notified BOOLEAN NOT NULL DEFAULT 0,
+                UNIQUE(profile_id, channel_id, start_timestamp)
+            )",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn request(title: &str, start: i64) -> CreateEpgReminderRequest {
+        CreateEpgReminderRequest {
+            profile_id: "p1".to_string(),
+            channel_id: "42".to_string(),
+            channel_name: "Sports HD".to_string(),
+            title: title.to_string(),
+            description: Some("Semi-final, live".to_string()),
+            start_timestamp: start,
+            stop_timestamp: start + 7200,
+        }
+    }
+
+    #[test]
+    fn test_add_reminder_is_idempotent() {
+        let conn = create_test_db();
+        let first = XtreamRemindersDb::add_reminder(&conn, &request("Match", 1_700_000_000)).unwrap();
+        let second = XtreamRemindersDb::add_reminder(&conn, &request("Match", 1_700_000_000)).unwrap();
+        assert_eq!(first, second);
+
+        XtreamRemindersDb::add_reminder(&conn, &request("Final", 1_700_100_000)).unwrap();
+        let reminders = XtreamRemindersDb::get_reminders(&conn, "p1", None).unwrap();
+        assert_eq!(reminders.len(), 2);
+        assert_eq!(reminders[0].title, "Match");
+
+        let upcoming = XtreamRemindersDb::get_reminders(&conn, "p1", Some(1_700_050_000)).unwrap();
+        assert_eq!(upcoming.len(), 1);
+        assert_eq!(upcoming[0].title, "Final");
+
+        assert_eq!(XtreamRemindersDb::clear_past_reminders(&conn, 1_700_050_000).unwrap(), 1);
+        XtreamRemindersDb::remove_reminder(&conn, &upcoming[0].id).unwrap();
+        assert!(XtreamRemindersDb::get_reminders(&conn, "p1", None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_due_reminders_are_taken_once() {
+        let conn = create_test_db();
+        XtreamRemindersDb::add_reminder(&conn, &request("Match", 1_000)).unwrap();
+        XtreamRemindersDb::add_reminder(&conn, &request("Final", 5_000)).unwrap();
+
+        assert!(XtreamRemindersDb::take_due_reminders(&conn, 800, 120).unwrap().is_empty());
+        let due = XtreamRemindersDb::take_due_reminders(&conn, 900, 120).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].title, "Match");
+        assert!(XtreamRemindersDb::take_due_reminders(&conn, 950, 120).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_reminder_validation() {
+        let conn = create_test_db();
+        assert!(XtreamRemindersDb::add_reminder(&conn, &request("  ", 1_700_000_000)).is_err());
+
+        let mut backwards = request("Match", 1_700_000_000);
+        backwards.stop_timestamp = backwards.start_timestamp;
+        assert!(XtreamRemindersDb::add_reminder(&conn, &backwards).is_err());
+    }
+
+    #[test]
+    fn test_reminders_to_ics() {
+        let reminder = XtreamEpgReminder {
+            id: "r1".to_string(),
+            profile_id: "p1".to_string(),
+            channel_id: "42".to_string(),
+            channel_name: "Sports HD".to_string(),
+            title: "Home vs Away; Semi-final, live".to_string(),
+            description: Some("x".repeat(200)),
+            start_timestamp: 1_700_000_000,
+            stop_timestamp: 1_700_007_200,
+            created_at: String::new(),
+        };
+
+        let ics = reminders_to_ics(&[reminder]).unwrap();
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:r1@xtauri\r\n"));
+        assert!(ics.contains("DTSTART:20231114T221320Z\r\n"));
+        assert!(ics.contains("DTEND:20231115T001320Z\r\n"));
+        assert!(ics.contains("SUMMARY:Home vs Away\\; Semi-final\\, live\r\n"));
+        assert!(ics.contains("TRIGGER:-PT10M\r\n"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= ICS_LINE_LIMIT));
+    }
+}
@@ -2,7 +2,7 @@ use rusqlite::{params, Connection, Result, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SavedFilter {
     pub id: String,
     pub profile_id: String,
@@ -13,7 +13,7 @@ pub struct SavedFilter {
     pub last_used: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct CreateSavedFilterRequest {
     pub profile_id: String,
     pub name: String,
@@ -21,7 +21,7 @@ pub struct CreateSavedFilterRequest {
     pub filter_data: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct UpdateSavedFilterRequest {
     pub name: Option<String>,
     pub filter_data: Option<String>,
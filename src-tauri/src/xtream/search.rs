@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use crate::content_cache::{XtreamChannel, XtreamMovie, XtreamSeries};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SearchResult {
     pub channels: Vec<XtreamChannel>,
     pub movies: Vec<XtreamMovie>,
@@ -9,7 +10,7 @@ pub struct SearchResult {
     pub total_results: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SearchOptions {
     pub query: String,
     pub search_channels: bool,
@@ -32,6 +33,53 @@ impl Default for SearchOptions {
     }
 }
 
+/// First tier of a tiered search: the matches in the local cache
+///
+/// Provider matches that aren't cached follow in a `provider_search_results`
+/// event carrying the same `search_id`.
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct TieredSearchStart {
+    pub search_id: String,
+    pub local: SearchResult,
+}
+
+/// Second tier of a tiered search: provider matches the local search didn't return
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ProviderSearchResults {
+    pub search_id: String,
+    pub profile_id: String,
+    pub results: SearchResult,
+    /// Set when the provider couldn't be searched; `results` is then empty
+    pub error: Option<String>,
+}
+
+impl SearchResult {
+    pub fn empty() -> Self {
+        Self {
+            channels: Vec::new(),
+            movies: Vec::new(),
+            series: Vec::new(),
+            total_results: 0,
+        }
+    }
+}
+
+/// Drop the provider matches that are already among the local ones
+pub fn without_local_matches(provider: SearchResult, local: &SearchResult) -> SearchResult {
+    let channel_ids: HashSet<i64> = local.channels.iter().map(|channel| channel.stream_id).collect();
+    let movie_ids: HashSet<i64> = local.movies.iter().map(|movie| movie.stream_id).collect();
+    let series_ids: HashSet<i64> = local.series.iter().map(|show| show.series_id).collect();
+
+    let mut result = SearchResult {
+        channels: provider.channels.into_iter().filter(|channel| !channel_ids.contains(&channel.stream_id)).collect(),
+        movies: provider.movies.into_iter().filter(|movie| !movie_ids.contains(&movie.stream_id)).collect(),
+        series: provider.series.into_iter().filter(|show| !series_ids.contains(&show.series_id)).collect(),
+        total_results: 0,
+    };
+    result.total_results = result.channels.len() + result.movies.len() + result.series.len();
+    result
+}
+
 /// Search across all content types
 pub fn search_all_content(
     channels: &[XtreamChannel],
@@ -45,12 +93,7 @@ pub fn search_all_content(
         options.query.to_lowercase()
     };
 
-    let mut result = SearchResult {
-        channels: Vec::new(),
-        movies: Vec::new(),
-        series: Vec::new(),
-        total_results: 0,
-    };
+    let mut result = SearchResult::empty();
 
     if query.is_empty() {
         return result;
@@ -163,6 +206,10 @@ mod tests {
             tv_archive: None,
             direct_source: None,
             tv_archive_duration: None,
+            url_overridden: false,
+            country: None,
+            country_flag: None,
+            reliability: None,
         }
     }
 
@@ -189,6 +236,7 @@ mod tests {
             director: None,
             plot: None,
             youtube_trailer: None,
+            age_rating: None,
         }
     }
 
@@ -210,6 +258,7 @@ mod tests {
             rating_5based: None,
             episode_run_time: None,
             category_id: None,
+            age_rating: None,
         }
     }
 
@@ -310,4 +359,25 @@ mod tests {
         let results = search_all_content(&channels, &movies, &series, &options);
         assert_eq!(results.channels.len(), 2);
     }
+
+    #[test]
+    fn test_without_local_matches() {
+        let local = SearchResult {
+            channels: vec![create_test_channel("1", "HBO 1")],
+            movies: vec![create_test_movie("10", "The Movie")],
+            series: Vec::new(),
+            total_results: 2,
+        };
+        let provider = SearchResult {
+            channels: vec![create_test_channel("1", "HBO 1"), create_test_channel("2", "HBO 2")],
+            movies: vec![create_test_movie("10", "The Movie")],
+            series: Vec::new(),
+            total_results: 3,
+        };
+
+        let extra = without_local_matches(provider, &local);
+        assert_eq!(extra.total_results, 1);
+        assert_eq!(extra.channels[0].stream_id, 2);
+        assert!(extra.movies.is_empty());
+    }
 }
@@ -2,7 +2,7 @@ use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct SearchHistoryItem {
     pub id: String,
     pub profile_id: String,
@@ -12,7 +12,7 @@ pub struct SearchHistoryItem {
     pub created_at: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct AddSearchHistoryRequest {
     pub profile_id: String,
     pub query: String,
@@ -128,7 +128,7 @@ impl SessionManager {
         }
         
         // Create client and attempt authentication
-        let client = XtreamClient::new(credentials.clone(), cache)?;
+        let client = XtreamClient::new(credentials.clone(), cache)?.for_profile(profile_id);
         
         match self.try_authenticate(&client).await {
             Ok(server_info) => {
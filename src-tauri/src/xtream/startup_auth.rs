@@ -0,0 +1,365 @@
+use crate::error::{Result, XTauriError};
+use crate::events::{self, AppEvent};
+use crate::state::DbState;
+use crate::xtream::{XtreamClient, XtreamState};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State};
+
+/// Differences between the server and local clocks beyond this are reported as skew
+pub const CLOCK_SKEW_THRESHOLD_SECS: i64 = 5 * 60;
+
+/// Last known account state of a profile, refreshed on startup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct AccountStatus {
+    pub profile_id: String,
+    /// Provider account status ("Active", "Expired", "Banned", ...)
+    pub status: Option<String>,
+    pub expires_at: Option<i64>,
+    pub expired: bool,
+    /// Server clock minus local clock, when the server reports its time
+    pub clock_skew_secs: Option<i64>,
+    pub clock_skewed: bool,
+    pub user_info: Option<Value>,
+    pub checked_at: i64,
+    pub error: Option<String>,
+}
+
+fn value_as_i64(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+}
+
+/// Account status implied by a failed authentication, if the failure says so
+fn status_from_auth_error(error: &XTauriError) -> Option<&'static str> {
+    match error {
+        XTauriError::XtreamAuthenticationFailed { reason } => match reason.as_str() {
+            "Account has expired" => Some("Expired"),
+            "Account is banned" => Some("Banned"),
+            "Account is disabled" => Some("Disabled"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Build the account status from an authentication response
+///
+/// Expiry is judged against the server clock when it reports one, so a skewed
+/// local clock neither hides nor invents an expiry.
+pub fn account_status_from_response(profile_id: &str, response: &Value, local_now: i64) -> AccountStatus {
+    let user_info = response.get("user_info").cloned();
+    let status = user_info
+        .as_ref()
+        .and_then(|info| info.get("status"))
+        .and_then(|s| s.as_str())
+        .map(str::to_string);
+    let expires_at = user_info
+        .as_ref()
+        .and_then(|info| info.get("exp_date"))
+        .and_then(value_as_i64)
+        .filter(|exp| *exp > 0);
+
+    let server_now = response
+        .get("server_info")
+        .and_then(|info| info.get("timestamp_now"))
+        .and_then(value_as_i64);
+    let clock_skew_secs = server_now.map(|server_now| server_now - local_now);
+    let now = server_now.unwrap_or(local_now);
+
+    AccountStatus {
+        profile_id: profile_id.to_string(),
+        expired: status.as_deref() == Some("Expired") || expires_at.is_some_and(|exp| exp <= now),
+        status,
+        expires_at,
+        clock_skew_secs,
+        clock_skewed: clock_skew_secs.is_some_and(|skew| skew.abs() > CLOCK_SKEW_THRESHOLD_SECS),
+        user_info,
+        checked_at: local_now,
+        error: None,
+    }
+}
+
+/// Build the account status from a failed authentication, keeping the cached user info
+pub fn account_status_from_error(
+    profile_id: &str,
+    error: &XTauriError,
+    previous: Option<&AccountStatus>,
+    local_now: i64,
+) -> AccountStatus {
+    let status = status_from_auth_error(error);
+    AccountStatus {
+        profile_id: profile_id.to_string(),
+        status: status
+            .map(str::to_string)
+            .or_else(|| previous.and_then(|p| p.status.clone())),
+        expires_at: previous.and_then(|p| p.expires_at),
+        expired: status == Some("Expired") || previous.is_some_and(|p| p.expired),
+        clock_skew_secs: None,
+        clock_skewed: false,
+        user_info: previous.and_then(|p| p.user_info.clone()),
+        checked_at: local_now,
+        error: Some(error.to_string()),
+    }
+}
+
+/// Whether the account went from usable to expired between two checks
+pub fn expired_since(previous: Option<&AccountStatus>, current: &AccountStatus) -> bool {
+    current.expired && !previous.is_some_and(|p| p.expired)
+}
+
+/// Database operations for cached account status
+pub struct XtreamAccountStatusDb;
+
+impl XtreamAccountStatusDb {
+    pub fn save(conn: &Connection, status: &AccountStatus) -> Result<()> {
+        let user_info = status
+            .user_info
+            .as_ref()
+            .map(|info| info.to_string());
+        conn.execute(
+            "INSERT OR REPLACE INTO xtream_account_status
+             (profile_id, status, expires_at, expired, clock_skew_secs, user_info, checked_at, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                status.profile_id,
+                status.status,
+                status.expires_at,
+                status.expired,
+                status.clock_skew_secs,
+                user_info,
+                status.checked_at,
+                status.error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get(conn: &Connection, profile_id: &str) -> Result<Option<AccountStatus>> {
+        let status = conn
+            .query_row(
+                "SELECT profile_id, status, expires_at, expired, clock_skew_secs, user_info, checked_at, error
+                 FROM xtream_account_status WHERE profile_id = ?1",
+                params![profile_id],
+                |row| {
+                    let clock_skew_secs: Option<i64> = row.get(4)?;
+                    let user_info: Option<String> = row.get(5)?;
+                    Ok(AccountStatus {
+                        profile_id: row.get(0)?,
+                        status: row.get(1)?,
+                        expires_at: row.get(2)?,
+                        expired: row.get(3)?,
+                        clock_skew_secs,
+                        clock_skewed: clock_skew_secs
+                            .is_some_and(|skew| skew.abs() > CLOCK_SKEW_THRESHOLD_SECS),
+                        user_info: user_info.and_then(|info| serde_json::from_str(&info).ok()),
+                        checked_at: row.get(6)?,
+                        error: row.get(7)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(status)
+    }
+}
+
+fn auto_authenticate_enabled(conn: &Connection) -> Result<bool> {
+    let enabled = conn.query_row(
+        "SELECT auto_authenticate_on_startup FROM settings WHERE id = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(enabled)
+}
+
+/// Authenticate the active profile and record its account status
+///
+/// Returns the new status and whether the account expired since the last check.
+async fn check_active_profile(state: &XtreamState) -> Result<Option<(AccountStatus, bool)>> {
+    let Some(profile) = state.profile_manager.get_active_profile()? else {
+        return Ok(None);
+    };
+
+    let credentials = state
+        .profile_manager
+        .get_profile_credentials_async_wrapper(&profile.id)
+        .await?;
+    let client = XtreamClient::new(credentials, state.content_cache.clone())?.for_profile(&profile.id);
+    let outcome = client.authenticate_with_retry(1).await;
+    let local_now = Utc::now().timestamp();
+
+    let db = state.profile_manager.get_db_connection();
+    let conn = db
+        .lock()
+        .map_err(|_| XTauriError::lock_acquisition("database connection"))?;
+    let previous = XtreamAccountStatusDb::get(&conn, &profile.id)?;
+
+    let status = match outcome {
+        Ok(response) => account_status_from_response(&profile.id, &response, local_now),
+        Err(e) => account_status_from_error(&profile.id, &e, previous.as_ref(), local_now),
+    };
+    XtreamAccountStatusDb::save(&conn, &status)?;
+
+    let newly_expired = expired_since(previous.as_ref(), &status);
+    Ok(Some((status, newly_expired)))
+}
+
+/// Check the active profile in the background on launch, if enabled
+pub async fn restore_on_startup(app_handle: AppHandle) {
+    let enabled = {
+        let db_state = app_handle.state::<DbState>();
+        let db = match db_state.db.lock() {
+            Ok(db) => db,
+            Err(_) => return,
+        };
+        auto_authenticate_enabled(&db).unwrap_or(false)
+    };
+
+    if !enabled {
+        return;
+    }
+
+    let xtream_state = app_handle.state::<XtreamState>();
+    let task = crate::tasks::register(crate::tasks::TaskKind::AccountCheck, "Checking account status", None);
+    let checked = check_active_profile(&xtream_state).await;
+    drop(task);
+    let (mut status, newly_expired) = match checked {
+        Ok(Some(result)) => result,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("[STARTUP AUTH] Failed to check active profile: {}", e);
+            return;
+        }
+    };
+
+    if crate::anonymize::is_demo_mode() {
+        if let Some(user_info) = status.user_info.as_mut() {
+            crate::anonymize::mask_json(user_info);
+        }
+    }
+
+    if newly_expired {
+        events::emit(&app_handle, AppEvent::XtreamAccountExpired(status.clone()));
+    }
+    events::emit(&app_handle, AppEvent::XtreamStartupAuth(status));
+}
+
+#[tauri::command]
+pub fn get_auto_authenticate_on_startup(state: State<DbState>) -> std::result::Result<bool, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    auto_authenticate_enabled(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_auto_authenticate_on_startup(
+    state: State<DbState>,
+    enabled: bool,
+) -> std::result::Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.execute(
+        "UPDATE settings SET auto_authenticate_on_startup = ?1 WHERE id = 1",
+        params![enabled],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Get the account status recorded by the last startup check of a profile
+#[tauri::command]
+pub async fn get_xtream_account_status(
+    state: State<'_, XtreamState>,
+    profile_id: String,
+) -> std::result::Result<Option<AccountStatus>, String> {
+    let conn = state.profile_manager.get_db_connection();
+    let conn_guard = conn.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+
+    let mut status = XtreamAccountStatusDb::get(&conn_guard, &profile_id).map_err(|e| e.to_string())?;
+    if crate::anonymize::is_demo_mode() {
+        if let Some(user_info) = status.as_mut().and_then(|s| s.user_info.as_mut()) {
+            crate::anonymize::mask_json(user_info);
+        }
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: &str, exp_date: &str, server_now: i64) -> Value {
+        json!({
+            "user_info": { "auth": 1, "status": status, "exp_date": exp_date },
+            "server_info": { "timestamp_now": server_now }
+        })
+    }
+
+    #[test]
+    fn test_clock_skew_detection() {
+        let status = account_status_from_response("p1", &response("Active", "2000000000", 1_700_000_000), 1_700_000_120);
+        assert_eq!(status.clock_skew_secs, Some(-120));
+        assert!(!status.clock_skewed);
+        assert!(!status.expired);
+
+        let status = account_status_from_response("p1", &response("Active", "2000000000", 1_700_000_000), 1_700_003_600);
+        assert_eq!(status.clock_skew_secs, Some(-3600));
+        assert!(status.clock_skewed);
+    }
+
+    #[test]
+    fn test_expiry_uses_server_clock() {
+        // The local clock is behind, but the server says the expiry has passed
+        let status = account_status_from_response("p1", &response("Active", "1700000000", 1_700_000_500), 1_699_990_000);
+        assert!(status.expired);
+        assert_eq!(status.expires_at, Some(1_700_000_000));
+
+        let unlimited = account_status_from_response("p1", &response("Active", "", 1_700_000_000), 1_700_000_000);
+        assert_eq!(unlimited.expires_at, None);
+        assert!(!unlimited.expired);
+    }
+
+    #[test]
+    fn test_expired_since_previous_check() {
+        let active = account_status_from_response("p1", &response("Active", "1800000000", 1_700_000_000), 1_700_000_000);
+        let error = XTauriError::xtream_auth_failed("Account has expired");
+        let expired = account_status_from_error("p1", &error, Some(&active), 1_800_000_100);
+
+        assert_eq!(expired.status.as_deref(), Some("Expired"));
+        assert_eq!(expired.user_info, active.user_info);
+        assert!(expired_since(Some(&active), &expired));
+        assert!(expired_since(None, &expired));
+        assert!(!expired_since(Some(&expired), &expired));
+
+        // A network failure keeps the last known state instead of reporting expiry
+        let offline = account_status_from_error("p1", &XTauriError::timeout("authentication request"), Some(&active), 1_700_000_500);
+        assert!(!offline.expired);
+        assert_eq!(offline.status.as_deref(), Some("Active"));
+    }
+
+    #[test]
+    fn test_account_status_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_account_status (
+                profile_id TEXT PRIMARY KEY,
+                status TEXT,
+                expires_at INTEGER,
+                expired BOOLEAN NOT NULL DEFAULT 0,
+                clock_skew_secs INTEGER,
+                user_info TEXT,
+                checked_at INTEGER NOT NULL,
+                error TEXT
+            )",
+            [],
+        )
+        .unwrap();
+
+        assert!(XtreamAccountStatusDb::get(&conn, "p1").unwrap().is_none());
+        let status = account_status_from_response("p1", &response("Active", "2000000000", 1_700_000_000), 1_700_003_600);
+        XtreamAccountStatusDb::save(&conn, &status).unwrap();
+        assert_eq!(XtreamAccountStatusDb::get(&conn, "p1").unwrap(), Some(status));
+    }
+}
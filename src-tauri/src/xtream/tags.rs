@@ -0,0 +1,227 @@
+use crate::content_id::{canonical_type, ContentId};
+use crate::error::{Result, XTauriError};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+/// Tag reserved for content that should be hidden from listings
+pub const HIDDEN_TAG: &str = "hidden";
+
+/// Create the tags table; the content cache creates it as well because its
+/// listings leave out hidden content
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS xtream_content_tags (
+            profile_id TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            content_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (profile_id, content_type, content_id, tag),
+            FOREIGN KEY (profile_id) REFERENCES xtream_profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_content_tags_tag 
+         ON xtream_content_tags(profile_id, tag)",
+        [],
+    )
+    .ok();
+
+    Ok(())
+}
+
+/// SQL condition leaving out content tagged `HIDDEN_TAG`, to append to a `WHERE`
+///
+/// `table` is the name or alias of the queried table, which has a `profile_id`
+/// column; `content_type` and `content_id` are SQL expressions for the
+/// canonical type and the id of each row's content.
+pub fn not_hidden_clause(table: &str, content_type: &str, content_id: &str) -> String {
+    format!(
+        " AND NOT EXISTS (SELECT 1 FROM xtream_content_tags hidden
+             WHERE hidden.profile_id = {}.profile_id AND hidden.content_type = {}
+               AND hidden.content_id = {} AND hidden.tag = '{}')",
+        table, content_type, content_id, HIDDEN_TAG
+    )
+}
+
+/// A user-defined tag attached to a content item
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct XtreamContentTag {
+    pub profile_id: String,
+    pub content_type: String,
+    pub content_id: String,
+    pub tag: String,
+    pub created_at: String,
+}
+
+/// Database operations for Xtream content tags
+pub struct XtreamTagsDb;
+
+impl XtreamTagsDb {
+    /// Attach a tag to a content item (no-op if already tagged)
+    pub fn add_tag(
+        conn: &Connection,
+        profile_id: &str,
+        content_type: &str,
+        content_id: &str,
+        tag: &str,
+    ) -> Result<()> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(XTauriError::internal("Tag cannot be empty".to_string()));
+        }
+        let key = ContentId::new(content_type, content_id)?;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO xtream_content_tags (profile_id, content_type, content_id, tag)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![profile_id, key.content_type(), key.id(), tag],
+        )?;
+
+        Ok(())
+    }
+
+    /// Remove a tag from a content item
+    pub fn remove_tag(
+        conn: &Connection,
+        profile_id: &str,
+        content_type: &str,
+        content_id: &str,
+        tag: &str,
+    ) -> Result<()> {
+        let key = ContentId::new(content_type, content_id)?;
+        conn.execute(
+            "DELETE FROM xtream_content_tags
+             WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3 AND tag = ?4",
+            params![profile_id, key.content_type(), key.id(), tag],
+        )?;
+
+        Ok(())
+    }
+
+    /// Get all tags attached to a content item
+    pub fn get_tags_for_content(
+        conn: &Connection,
+        profile_id: &str,
+        content_type: &str,
+        content_id: &str,
+    ) -> Result<Vec<String>> {
+        let key = ContentId::new(content_type, content_id)?;
+        let mut stmt = conn.prepare(
+            "SELECT tag FROM xtream_content_tags
+             WHERE profile_id = ?1 AND content_type = ?2 AND content_id = ?3
+             ORDER BY tag",
+        )?;
+
+        let tags = stmt
+            .query_map(params![profile_id, key.content_type(), key.id()], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(tags)
+    }
+
+    /// Get all content items carrying a tag, optionally restricted to a content type
+    pub fn get_content_by_tag(
+        conn: &Connection,
+        profile_id: &str,
+        tag: &str,
+        content_type: Option<&str>,
+    ) -> Result<Vec<XtreamContentTag>> {
+        let content_type = content_type.map(|t| canonical_type(t).unwrap_or(t));
+        let mut stmt = conn.prepare(
+            "SELECT profile_id, content_type, content_id, tag, created_at
+             FROM xtream_content_tags
+             WHERE profile_id = ?1 AND tag = ?2 AND (?3 IS NULL OR content_type = ?3)
+             ORDER BY created_at DESC",
+        )?;
+
+        let tags = stmt
+            .query_map(params![profile_id, tag, content_type], |row| {
+                Ok(XtreamContentTag {
+                    profile_id: row.get(0)?,
+                    content_type: row.get(1)?,
+                    content_id: row.get(2)?,
+                    tag: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
+
+    /// Remove a tag from every item of a profile
+    pub fn clear_tag(conn: &Connection, profile_id: &str, tag: &str) -> Result<usize> {
+        let removed = conn.execute(
+            "DELETE FROM xtream_content_tags WHERE profile_id = ?1 AND tag = ?2",
+            params![profile_id, tag],
+        )?;
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_content_tags (
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (profile_id, content_type, content_id, tag)
+            )",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_add_and_remove_tags() {
+        let conn = create_test_db();
+
+        XtreamTagsDb::add_tag(&conn, "p1", "channel", "1", HIDDEN_TAG).unwrap();
+        XtreamTagsDb::add_tag(&conn, "p1", "channel", "1", HIDDEN_TAG).unwrap();
+        XtreamTagsDb::add_tag(&conn, "p1", "channel", "1", "sports").unwrap();
+
+        let tags = XtreamTagsDb::get_tags_for_content(&conn, "p1", "channel", "1").unwrap();
+        assert_eq!(tags, vec!["hidden".to_string(), "sports".to_string()]);
+
+        XtreamTagsDb::remove_tag(&conn, "p1", "channel", "1", "sports").unwrap();
+        let tags = XtreamTagsDb::get_tags_for_content(&conn, "p1", "channel", "1").unwrap();
+        assert_eq!(tags, vec!["hidden".to_string()]);
+    }
+
+    #[test]
+    fn test_get_content_by_tag() {
+        let conn = create_test_db();
+
+        XtreamTagsDb::add_tag(&conn, "p1", "channel", "1", HIDDEN_TAG).unwrap();
+        XtreamTagsDb::add_tag(&conn, "p1", "movie", "2", HIDDEN_TAG).unwrap();
+        XtreamTagsDb::add_tag(&conn, "p2", "channel", "3", HIDDEN_TAG).unwrap();
+
+        let all = XtreamTagsDb::get_content_by_tag(&conn, "p1", HIDDEN_TAG, None).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let channels =
+            XtreamTagsDb::get_content_by_tag(&conn, "p1", HIDDEN_TAG, Some("channel")).unwrap();
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].content_id, "1");
+
+        assert_eq!(XtreamTagsDb::clear_tag(&conn, "p1", HIDDEN_TAG).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_empty_tag_rejected() {
+        let conn = create_test_db();
+        assert!(XtreamTagsDb::add_tag(&conn, "p1", "channel", "1", "  ").is_err());
+    }
+}
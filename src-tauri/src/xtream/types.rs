@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
 /// Profile credentials for Xtream authentication
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct ProfileCredentials {
     pub url: String,
     pub username: String,
@@ -10,7 +10,7 @@ pub struct ProfileCredentials {
 }
 
 /// Xtream profile stored in the database
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct XtreamProfile {
     pub id: String,
     pub name: String,
@@ -23,7 +23,7 @@ pub struct XtreamProfile {
 }
 
 /// Request to create a new profile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct CreateProfileRequest {
     pub name: String,
     pub url: String,
@@ -32,7 +32,7 @@ pub struct CreateProfileRequest {
 }
 
 /// Request to update an existing profile
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct UpdateProfileRequest {
     pub name: Option<String>,
     pub url: Option<String>,
@@ -89,8 +89,33 @@ impl CacheKey {
     }
 }
 
+/// A playback URL and whether it came from a user override
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+pub struct ResolvedStreamUrl {
+    pub url: String,
+    pub overridden: bool,
+}
+
+/// A chunk of a batched EPG fetch that failed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, specta::Type)]
+pub struct EpgChunkFailure {
+    pub channel_ids: Vec<String>,
+    pub error: String,
+}
+
+/// EPG for many channels fetched in chunks and merged
+#[derive(Debug, Clone, Default, Serialize, Deserialize, specta::Type)]
+pub struct EpgBatchResult {
+    /// Listings keyed by EPG channel id, or by stream id when a listing names no channel
+    pub epg: std::collections::HashMap<String, Vec<serde_json::Value>>,
+    pub chunks_total: usize,
+    pub failed_chunks: Vec<EpgChunkFailure>,
+    /// Listings that couldn't be attributed to a channel
+    pub unassigned_listings: usize,
+}
+
 /// Result of authentication testing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub struct AuthenticationResult {
     pub success: bool,
     pub error_message: Option<String>,
@@ -98,8 +123,15 @@ pub struct AuthenticationResult {
     pub server_info: Option<serde_json::Value>,
 }
 
+/// Result of a connection test: DNS and reachability, then authentication
+#[derive(Debug, Clone, Serialize, specta::Type)]
+pub struct ConnectionTestResult {
+    pub dns: Option<crate::connection::DnsDiagnostics>,
+    pub authentication: AuthenticationResult,
+}
+
 /// Types of authentication errors
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
 pub enum AuthenticationErrorType {
     None,
     ValidationError,
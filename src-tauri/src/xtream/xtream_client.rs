@@ -1,6 +1,9 @@
+use crate::cache_ttl::{self, CacheKind};
 use crate::error::{Result, XTauriError};
-use crate::xtream::types::{ProfileCredentials, StreamURLRequest, ContentType};
+use crate::xtream::types::{ProfileCredentials, StreamURLRequest, ContentType, EpgBatchResult, EpgChunkFailure};
 use crate::xtream::content_cache::ContentCache;
+use crate::xtream::rate_limit::{self, RequestLimiter};
+use crate::xtream::recording;
 use reqwest::Client;
 use serde_json::Value;
 use std::sync::Arc;
@@ -8,23 +11,35 @@ use std::time::Duration;
 use url::Url;
 use chrono;
 
+/// Default number of stream ids per batched EPG request
+pub const DEFAULT_EPG_CHUNK_SIZE: usize = 20;
+
+/// Default number of batched EPG requests in flight at once
+pub const DEFAULT_EPG_CHUNK_CONCURRENCY: usize = 4;
+
+/// Time allowed for downloading the full XMLTV guide
+const XMLTV_TIMEOUT: Duration = Duration::from_secs(300);
+
 /// Client for interacting with Xtream Codes API
+#[derive(Clone)]
 pub struct XtreamClient {
     client: Client,
     base_url: String,
     credentials: ProfileCredentials,
     cache: Arc<ContentCache>,
+    /// Paces requests to the profile's provider; `None` for credentials not saved as a profile
+    limiter: Option<Arc<RequestLimiter>>,
 }
 
 impl XtreamClient {
     /// Create a new Xtream client
     pub fn new(credentials: ProfileCredentials, cache: Arc<ContentCache>) -> Result<Self> {
-        Self::new_with_timeout(credentials, cache, Duration::from_secs(30))
+        Self::new_with_timeout(credentials, cache, crate::user_config::api_timeout())
     }
     
     /// Create a new Xtream client with custom timeout
     pub fn new_with_timeout(credentials: ProfileCredentials, cache: Arc<ContentCache>, timeout: Duration) -> Result<Self> {
-        let client = Client::builder()
+        let client = crate::connection::client_builder()
             .timeout(timeout)
             .build()
             .map_err(|e| XTauriError::internal(format!("Failed to create HTTP client: {}", e)))?;
@@ -37,9 +52,25 @@ impl XtreamClient {
             base_url,
             credentials,
             cache,
+            limiter: None,
         })
     }
     
+    /// Keep this client's requests within the request limits of the profile its credentials belong to
+    pub fn for_profile(mut self, profile_id: &str) -> Self {
+        let limiter = self
+            .cache
+            .get_db()
+            .lock()
+            .map_err(|_| XTauriError::lock_acquisition("database connection"))
+            .and_then(|conn| rate_limit::limiter_for(&conn, profile_id));
+        match limiter {
+            Ok(limiter) => self.limiter = Some(limiter),
+            Err(e) => eprintln!("[WARN] Failed to load request limits for profile {}: {}", profile_id, e),
+        }
+        self
+    }
+    
     /// Authenticate with the Xtream server and get profile information
     pub async fn authenticate(&self) -> Result<Value> {
         self.authenticate_with_retry(3).await
@@ -94,8 +125,19 @@ impl XtreamClient {
     
     /// Single authentication attempt
     async fn try_authenticate(&self, url: &str) -> Result<Value> {
+        if let Some(profile_data) = recording::replay(url) {
+            let profile_data = profile_data?;
+            self.validate_auth_response(&profile_data)?;
+            return Ok(profile_data);
+        }
+        
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await),
+            None => None,
+        };
         let response = self.client
             .get(url)
+            .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
             .send()
             .await
             .map_err(|e| {
@@ -122,13 +164,13 @@ impl XtreamClient {
             return Err(XTauriError::xtream_api_error(status.as_u16(), error_message));
         }
         
-        let profile_data: Value = response
-            .json()
+        let profile_data: Value = crate::transfer::read_json(response, crate::transfer::SOURCE_XTREAM_API)
             .await
             .map_err(|e| XTauriError::xtream_auth_failed(format!("Invalid response format: {}", e)))?;
         
         // Check if authentication was successful
         self.validate_auth_response(&profile_data)?;
+        recording::record(url, &profile_data);
         
         Ok(profile_data)
     }
@@ -212,9 +254,9 @@ impl XtreamClient {
         // Parse and enhance category data
         let enhanced_categories = self.parse_and_enhance_categories(&categories_data)?;
         
-        // Cache categories for 30 minutes (categories don't change often)
-        let cache_ttl = std::time::Duration::from_secs(30 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_categories, Some(cache_ttl));
+        // Categories don't change often
+        let ttl = cache_ttl::ttl(CacheKind::Categories);
+        let _ = self.cache.set(&cache_key, &enhanced_categories, Some(ttl));
         
         Ok(enhanced_categories)
     }
@@ -266,9 +308,8 @@ impl XtreamClient {
         // Parse and enhance channel data with streaming URLs
         let enhanced_channels = self.parse_and_enhance_channels(&channels_data)?;
         
-        // Cache channel data for 10 minutes
-        let cache_ttl = std::time::Duration::from_secs(10 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_channels, Some(cache_ttl));
+        let ttl = cache_ttl::ttl(CacheKind::Channels);
+        let _ = self.cache.set(&cache_key, &enhanced_channels, Some(ttl));
         
         Ok(enhanced_channels)
     }
@@ -292,9 +333,9 @@ impl XtreamClient {
         // Parse and enhance category data
         let enhanced_categories = self.parse_and_enhance_categories(&categories_data)?;
         
-        // Cache categories for 30 minutes (categories don't change often)
-        let cache_ttl = std::time::Duration::from_secs(30 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_categories, Some(cache_ttl));
+        // Categories don't change often
+        let ttl = cache_ttl::ttl(CacheKind::Categories);
+        let _ = self.cache.set(&cache_key, &enhanced_categories, Some(ttl));
         
         Ok(enhanced_categories)
     }
@@ -346,9 +387,8 @@ impl XtreamClient {
         // Parse and enhance movie data with streaming URLs
         let enhanced_movies = self.parse_and_enhance_movies(&movies_data)?;
         
-        // Cache movie data for 15 minutes
-        let cache_ttl = std::time::Duration::from_secs(15 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_movies, Some(cache_ttl));
+        let ttl = cache_ttl::ttl(CacheKind::Movies);
+        let _ = self.cache.set(&cache_key, &enhanced_movies, Some(ttl));
         
         Ok(enhanced_movies)
     }
@@ -372,9 +412,9 @@ impl XtreamClient {
         // Parse and enhance movie detail data
         let enhanced_movie = self.parse_and_enhance_movie_details(&movie_data, movie_id)?;
         
-        // Cache movie details for 60 minutes (details don't change often)
-        let cache_ttl = std::time::Duration::from_secs(60 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_movie, Some(cache_ttl));
+        // Details don't change often
+        let ttl = cache_ttl::ttl(CacheKind::Details);
+        let _ = self.cache.set(&cache_key, &enhanced_movie, Some(ttl));
         
         Ok(enhanced_movie)
     }
@@ -398,9 +438,9 @@ impl XtreamClient {
         // Parse and enhance category data
         let enhanced_categories = self.parse_and_enhance_categories(&categories_data)?;
         
-        // Cache categories for 30 minutes (categories don't change often)
-        let cache_ttl = std::time::Duration::from_secs(30 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_categories, Some(cache_ttl));
+        // Categories don't change often
+        let ttl = cache_ttl::ttl(CacheKind::Categories);
+        let _ = self.cache.set(&cache_key, &enhanced_categories, Some(ttl));
         
         Ok(enhanced_categories)
     }
@@ -452,9 +492,8 @@ impl XtreamClient {
         // Parse and enhance series data with streaming URLs
         let enhanced_series = self.parse_and_enhance_series(&series_data)?;
         
-        // Cache series data for 15 minutes
-        let cache_ttl = std::time::Duration::from_secs(15 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_series, Some(cache_ttl));
+        let ttl = cache_ttl::ttl(CacheKind::Series);
+        let _ = self.cache.set(&cache_key, &enhanced_series, Some(ttl));
         
         Ok(enhanced_series)
     }
@@ -478,9 +517,9 @@ impl XtreamClient {
         // Parse and enhance series detail data with episode URLs
         let enhanced_series = self.parse_and_enhance_series_details(&series_data, series_id)?;
         
-        // Cache series details for 60 minutes (details don't change often)
-        let cache_ttl = std::time::Duration::from_secs(60 * 60);
-        let _ = self.cache.set(&cache_key, &enhanced_series, Some(cache_ttl));
+        // Details don't change often
+        let ttl = cache_ttl::ttl(CacheKind::Details);
+        let _ = self.cache.set(&cache_key, &enhanced_series, Some(ttl));
         
         Ok(enhanced_series)
     }
@@ -501,8 +540,8 @@ impl XtreamClient {
         
         let epg_data = self.make_api_request(&url).await?;
         
-        // Cache EPG data for 15 minutes (EPG changes frequently)
-        let epg_ttl = std::time::Duration::from_secs(15 * 60);
+        // EPG changes frequently; cached longer in low-power mode
+        let epg_ttl = crate::power::scale_interval(cache_ttl::ttl(CacheKind::EpgShort));
         let _ = self.cache.set(&cache_key, &epg_data, Some(epg_ttl));
         
         Ok(epg_data)
@@ -538,13 +577,42 @@ impl XtreamClient {
         
         let epg_data = self.make_api_request(&url).await?;
         
-        // Cache full EPG data for 30 minutes
-        let epg_ttl = std::time::Duration::from_secs(30 * 60);
+        // Cached longer in low-power mode
+        let epg_ttl = crate::power::scale_interval(cache_ttl::ttl(CacheKind::EpgFull));
         let _ = self.cache.set(&cache_key, &epg_data, Some(epg_ttl));
         
         Ok(epg_data)
     }
     
+    /// Download the provider's whole XMLTV guide from `xmltv.php`
+    ///
+    /// The file lists every EPG channel with its display names; it can run to
+    /// many megabytes, so it gets a longer timeout than API requests.
+    pub async fn get_xmltv(&self) -> Result<Vec<u8>> {
+        let url = format!(
+            "{}/xmltv.php?username={}&password={}",
+            self.base_url, self.credentials.username, self.credentials.password
+        );
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await),
+            None => None,
+        };
+        let response = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
+            .timeout(XMLTV_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        let body = crate::transfer::read_body(response, crate::transfer::SOURCE_EPG).await?;
+        // Some providers serve a gzip file rather than a gzip-encoded response
+        if body.starts_with(&[0x1f, 0x8b]) {
+            return crate::transfer::decode_body(Some("gzip"), &body);
+        }
+        Ok(body)
+    }
+    
     /// Get EPG for multiple channels
     pub async fn get_epg_for_channels(&self, channel_ids: &[&str]) -> Result<Value> {
         let channel_list = channel_ids.join(",");
@@ -556,6 +624,95 @@ impl XtreamClient {
         self.make_api_request(&url).await
     }
     
+    /// Get EPG for many channels in chunks, merged into one map keyed by channel
+    ///
+    /// Providers cap how many ids one request may carry, so ids are split into
+    /// chunks of `chunk_size` with at most `max_concurrency` requests in flight.
+    /// Chunks that fail are reported instead of failing the whole batch.
+    pub async fn get_epg_for_channels_chunked(
+        &self,
+        channel_ids: &[String],
+        chunk_size: usize,
+        max_concurrency: usize,
+    ) -> Result<EpgBatchResult> {
+        let chunks = Self::chunk_channel_ids(channel_ids, chunk_size);
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrency.max(1)));
+        let mut requests = tokio::task::JoinSet::new();
+        
+        for (index, chunk) in chunks.iter().cloned().enumerate() {
+            let client = self.clone();
+            let semaphore = Arc::clone(&semaphore);
+            requests.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let ids: Vec<&str> = chunk.iter().map(String::as_str).collect();
+                (index, client.get_epg_for_channels(&ids).await)
+            });
+        }
+        
+        let mut responses = Vec::with_capacity(chunks.len());
+        while let Some(joined) = requests.join_next().await {
+            let (index, response) = joined
+                .map_err(|e| XTauriError::internal(format!("EPG chunk task failed: {}", e)))?;
+            responses.push((index, response));
+        }
+        // Merge in request order so the result doesn't depend on completion order
+        responses.sort_by_key(|(index, _)| *index);
+        
+        let mut result = EpgBatchResult {
+            chunks_total: chunks.len(),
+            ..Default::default()
+        };
+        for (index, response) in responses {
+            match response {
+                Ok(epg_data) => Self::merge_epg_chunk(&mut result, &chunks[index], &epg_data)?,
+                Err(e) => result.failed_chunks.push(EpgChunkFailure {
+                    channel_ids: chunks[index].clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// Split stream ids into request-sized chunks, dropping blanks and duplicates
+    pub fn chunk_channel_ids(channel_ids: &[String], chunk_size: usize) -> Vec<Vec<String>> {
+        let mut seen = std::collections::HashSet::new();
+        let ids: Vec<String> = channel_ids
+            .iter()
+            .map(|id| id.trim())
+            .filter(|id| !id.is_empty() && seen.insert(*id))
+            .map(str::to_string)
+            .collect();
+        
+        ids.chunks(chunk_size.max(1)).map(<[String]>::to_vec).collect()
+    }
+    
+    /// Add one chunk's listings to a batch result
+    ///
+    /// Listings are keyed by their EPG channel id; when a listing doesn't name
+    /// one, it can still be attributed if the chunk only asked for one stream.
+    pub fn merge_epg_chunk(result: &mut EpgBatchResult, chunk: &[String], epg_data: &Value) -> Result<()> {
+        for listing in Self::parse_epg_programs(epg_data)? {
+            let key = ["channel_id", "epg_id"]
+                .iter()
+                .filter_map(|key| listing.get(*key).and_then(|v| v.as_str()))
+                .map(str::trim)
+                .find(|id| !id.is_empty())
+                .map(str::to_string)
+                .or_else(|| match chunk {
+                    [only] => Some(only.clone()),
+                    _ => None,
+                });
+            
+            match key {
+                Some(key) => result.epg.entry(key).or_default().push(listing),
+                None => result.unassigned_listings += 1,
+            }
+        }
+        Ok(())
+    }
+    
     /// Get EPG for a specific date range
     pub async fn get_epg_by_date_range(
         &self, 
@@ -680,6 +837,11 @@ impl XtreamClient {
                     }
                 }
                 
+                // Normalize the programme icon under a single key
+                if let Some(icon_url) = Self::epg_icon_url(&program) {
+                    program["icon_url"] = Value::String(icon_url);
+                }
+                
                 // Ensure required fields have default values
                 if !program.as_object().unwrap().contains_key("title") {
                     program["title"] = Value::String("Unknown Program".to_string());
@@ -790,6 +952,40 @@ impl XtreamClient {
         }
     }
     
+    /// Programme icon URL, whichever key the provider uses for it
+    pub fn epg_icon_url(program: &Value) -> Option<String> {
+        ["icon_url", "icon", "image", "poster"]
+            .iter()
+            .filter_map(|key| program.get(*key).and_then(|v| v.as_str()))
+            .map(str::trim)
+            .find(|url| url.starts_with("http://") || url.starts_with("https://"))
+            .map(str::to_string)
+    }
+    
+    /// Unique programme icon URLs for a guide window, in programme order
+    ///
+    /// Used to prefetch the images for the part of the guide in view.
+    pub fn collect_epg_icon_urls(
+        epg_data: &Value,
+        start_timestamp: Option<i64>,
+        end_timestamp: Option<i64>
+    ) -> Result<Vec<String>> {
+        let programs = Value::Array(Self::parse_epg_programs(epg_data)?);
+        let in_window = Self::filter_epg_by_time_range(&programs, start_timestamp, end_timestamp)?;
+        
+        let mut seen = std::collections::HashSet::new();
+        Ok(in_window
+            .as_array()
+            .map(|programs| {
+                programs
+                    .iter()
+                    .filter_map(Self::epg_icon_url)
+                    .filter(|url| seen.insert(url.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+    
     /// Search EPG programs by title or description
     pub fn search_epg_programs(epg_data: &Value, search_query: &str) -> Result<Value> {
         if let Some(programs) = epg_data.as_array() {
@@ -1947,6 +2143,30 @@ impl XtreamClient {
         Ok(url)
     }
     
+    /// Generate a catch-up URL for a channel's archive, starting at `start`
+    ///
+    /// Providers read the start time in their own clock; `utc_offset_minutes`
+    /// shifts it for servers that don't run on UTC.
+    pub fn generate_timeshift_url(
+        &self,
+        stream_id: i64,
+        start: i64,
+        duration_minutes: i64,
+        utc_offset_minutes: i32,
+    ) -> Result<String> {
+        let start = chrono::DateTime::from_timestamp(start + i64::from(utc_offset_minutes) * 60, 0)
+            .ok_or_else(|| XTauriError::internal(format!("Invalid timeshift start: {}", start)))?;
+        Ok(format!(
+            "{}/timeshift/{}/{}/{}/{}/{}.m3u8",
+            self.base_url,
+            self.credentials.username,
+            self.credentials.password,
+            duration_minutes.max(1),
+            start.format("%Y-%m-%d:%H-%M"),
+            stream_id
+        ))
+    }
+    
     /// Make an API request and handle common errors
     async fn make_api_request(&self, url: &str) -> Result<Value> {
         self.make_api_request_with_retry(url, crate::xtream::retry::RetryConfig::default()).await
@@ -1956,23 +2176,32 @@ impl XtreamClient {
     async fn make_api_request_with_retry(&self, url: &str, retry_config: crate::xtream::retry::RetryConfig) -> Result<Value> {
         use crate::xtream::retry::retry_with_backoff;
         
-        let url = url.to_string();
+        let url = crate::xtream::api_language::apply(url);
+        if let Some(data) = recording::replay(&url) {
+            return data;
+        }
         let client = self.client.clone();
         
-        retry_with_backoff(
+        let data = retry_with_backoff(
             || {
                 let url = url.clone();
                 let client = client.clone();
+                let limiter = self.limiter.clone();
                 async move {
+                    let _permit = match &limiter {
+                        Some(limiter) => Some(limiter.acquire().await),
+                        None => None,
+                    };
                     let response = client
                         .get(&url)
+                        .header(reqwest::header::ACCEPT_ENCODING, crate::transfer::accept_encoding())
                         .send()
                         .await
                         .map_err(|e| {
                             if e.is_timeout() {
                                 XTauriError::timeout("API request")
                             } else {
-                                XTauriError::Network(e)
+                                XTauriError::network(e)
                             }
                         })?;
                     
@@ -1984,8 +2213,7 @@ impl XtreamClient {
                         ));
                     }
                     
-                    let data: Value = response
-                        .json()
+                    let data: Value = crate::transfer::read_json(response, crate::transfer::SOURCE_XTREAM_API)
                         .await
                         .map_err(|e| XTauriError::xtream_api_error(500, format!("Invalid JSON response: {}", e)))?;
                     
@@ -1993,7 +2221,10 @@ impl XtreamClient {
                 }
             },
             retry_config,
-        ).await
+        ).await?;
+        recording::record(&url, &data);
+        
+        Ok(data)
     }
     
     /// Normalize and validate base URL
@@ -2021,4 +2252,65 @@ impl XtreamClient {
         
         Ok(base_url)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_epg_icon_urls() {
+        let epg = json!({
+            "epg_listings": [
+                { "title": "News", "start": "1000", "stop": "2000", "icon": "http://img.example.com/news.png" },
+                { "title": "Weather", "start": "2000", "stop": "3000", "image": "https://img.example.com/weather.png" },
+                { "title": "News", "start": "3000", "stop": "4000", "icon": "http://img.example.com/news.png" },
+                { "title": "Film", "start": "5000", "stop": "6000", "icon": "http://img.example.com/film.png" },
+                { "title": "Late", "start": "6000", "stop": "7000", "icon": "" }
+            ]
+        });
+
+        let urls = XtreamClient::collect_epg_icon_urls(&epg, Some(1500), Some(4500)).unwrap();
+        assert_eq!(
+            urls,
+            vec![
+                "http://img.example.com/news.png".to_string(),
+                "https://img.example.com/weather.png".to_string(),
+            ]
+        );
+
+        let enhanced = XtreamClient::parse_and_enhance_epg_data(&epg, None).unwrap();
+        assert_eq!(enhanced[1]["icon_url"], "https://img.example.com/weather.png");
+        assert!(enhanced[4].get("icon_url").is_none());
+    }
+
+    #[test]
+    fn test_chunk_channel_ids() {
+        let ids: Vec<String> = ["1", "2", " 3 ", "", "2", "4", "5"].iter().map(|s| s.to_string()).collect();
+        let chunks = XtreamClient::chunk_channel_ids(&ids, 2);
+        assert_eq!(chunks, vec![vec!["1", "2"], vec!["3", "4"], vec!["5"]]);
+        assert_eq!(XtreamClient::chunk_channel_ids(&ids, 0).len(), 5);
+    }
+
+    #[test]
+    fn test_merge_epg_chunks() {
+        let mut result = EpgBatchResult::default();
+        let multi = json!({
+            "epg_listings": [
+                { "channel_id": "news.uk", "title": "Headlines" },
+                { "channel_id": "sport.uk", "title": "Match" },
+                { "title": "Mystery" }
+            ]
+        });
+        let single = json!({ "epg_listings": [{ "title": "Film" }, { "channel_id": "news.uk", "title": "Late News" }] });
+
+        XtreamClient::merge_epg_chunk(&mut result, &["1".to_string(), "2".to_string()], &multi).unwrap();
+        XtreamClient::merge_epg_chunk(&mut result, &["3".to_string()], &single).unwrap();
+
+        assert_eq!(result.epg["news.uk"].len(), 2);
+        assert_eq!(result.epg["sport.uk"][0]["title"], "Match");
+        assert_eq!(result.epg["3"][0]["title"], "Film");
+        assert_eq!(result.unassigned_listings, 1);
+    }
+}
@@ -0,0 +1,353 @@
+use crate::error::{Result, XTauriError};
+use base64::{engine::general_purpose, Engine as _};
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// How many channels and genres the review ranks
+const TOP_LIMIT: usize = 5;
+
+/// Size of the rendered monthly chart in pixels
+const CHART_WIDTH: u32 = 520;
+const CHART_HEIGHT: u32 = 260;
+const CHART_MARGIN: u32 = 20;
+
+const CHART_BACKGROUND: [u8; 3] = [0x12, 0x12, 0x1a];
+const CHART_BAR: [u8; 3] = [0x4f, 0x8c, 0xff];
+const CHART_AXIS: [u8; 3] = [0x55, 0x55, 0x66];
+
+/// Watch time for a channel or genre
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct WatchTotal {
+    pub name: String,
+    pub hours: f64,
+    /// Number of titles or channels that count towards it
+    pub items: usize,
+}
+
+/// The day with the most watch time
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct BusiestDay {
+    /// Date as `YYYY-MM-DD`
+    pub date: String,
+    pub hours: f64,
+}
+
+/// A profile's watching over one year, for end-of-year sharing
+///
+/// History keeps one entry per title, so each title counts towards the day and
+/// month it was last watched.
+#[derive(Debug, Clone, Serialize, PartialEq, specta::Type)]
+pub struct YearInReview {
+    pub profile_id: String,
+    pub year: i32,
+    pub total_hours: f64,
+    pub items_watched: usize,
+    pub top_channels: Vec<WatchTotal>,
+    pub top_genres: Vec<WatchTotal>,
+    pub busiest_day: Option<BusiestDay>,
+    /// Hours watched in each month, January first
+    pub monthly_hours: Vec<f64>,
+    /// Bar chart of `monthly_hours` as a PNG data URL, when requested
+    pub chart_png: Option<String>,
+}
+
+/// A history entry as the review needs it
+#[derive(Debug, Clone)]
+struct WatchEntry {
+    content_type: String,
+    name: String,
+    genres: Vec<String>,
+    /// `YYYY-MM-DD`
+    date: String,
+    seconds: f64,
+}
+
+/// Seconds watched: the full duration once marked watched, otherwise the playback position
+fn watched_seconds(position: Option<f64>, duration: Option<f64>, watched: bool) -> f64 {
+    let seconds = if watched {
+        duration.or(position).unwrap_or(0.0)
+    } else {
+        let position = position.unwrap_or(0.0);
+        duration.map_or(position, |duration| position.min(duration))
+    };
+    seconds.max(0.0)
+}
+
+/// Genres of a title, from the provider's comma or slash separated `genre` field
+fn genres(content_data: &serde_json::Value) -> Vec<String> {
+    content_data
+        .get("genre")
+        .and_then(|genre| genre.as_str())
+        .map(split_genres)
+        .unwrap_or_default()
+}
+
+/// Split a provider's `genre` field, which separates genres with commas, slashes or bars
+pub fn split_genres(genre: &str) -> Vec<String> {
+    genre
+        .split([',', '/', '|'])
+        .map(str::trim)
+        .filter(|genre| !genre.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Hours rounded to one decimal
+pub fn hours(seconds: f64) -> f64 {
+    (seconds / 3600.0 * 10.0).round() / 10.0
+}
+
+/// The largest totals, most hours first and then by name
+fn top_totals(totals: HashMap<String, (f64, usize)>) -> Vec<WatchTotal> {
+    let mut totals: Vec<(String, f64, usize)> =
+        totals.into_iter().map(|(name, (seconds, items))| (name, seconds, items)).collect();
+    totals.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+        .into_iter()
+        .take(TOP_LIMIT)
+        .map(|(name, seconds, items)| WatchTotal {
+            name,
+            hours: hours(seconds),
+            items,
+        })
+        .collect()
+}
+
+fn summarize(profile_id: &str, year: i32, entries: &[WatchEntry]) -> YearInReview {
+    let mut channels: HashMap<String, (f64, usize)> = HashMap::new();
+    let mut genres: HashMap<String, (f64, usize)> = HashMap::new();
+    let mut days: HashMap<&str, f64> = HashMap::new();
+    let mut monthly_seconds = [0.0; 12];
+
+    for entry in entries {
+        if entry.content_type == "channel" {
+            let total = channels.entry(entry.name.clone()).or_default();
+            total.0 += entry.seconds;
+            total.1 += 1;
+        }
+        for genre in &entry.genres {
+            let total = genres.entry(genre.clone()).or_default();
+            total.0 += entry.seconds;
+            total.1 += 1;
+        }
+        *days.entry(&entry.date).or_default() += entry.seconds;
+        if let Some(month) = entry.date.get(5..7).and_then(|month| month.parse::<usize>().ok()) {
+            if (1..=12).contains(&month) {
+                monthly_seconds[month - 1] += entry.seconds;
+            }
+        }
+    }
+
+    let busiest_day = days
+        .into_iter()
+        .filter(|(_, seconds)| *seconds > 0.0)
+        .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(date, seconds)| BusiestDay {
+            date: date.to_string(),
+            hours: hours(seconds),
+        });
+
+    YearInReview {
+        profile_id: profile_id.to_string(),
+        year,
+        total_hours: hours(entries.iter().map(|entry| entry.seconds).sum()),
+        items_watched: entries.len(),
+        top_channels: top_totals(channels),
+        top_genres: top_totals(genres),
+        busiest_day,
+        monthly_hours: monthly_seconds.iter().map(|seconds| hours(*seconds)).collect(),
+        chart_png: None,
+    }
+}
+
+/// Build a profile's review of `year` from its watch history
+pub fn year_in_review(conn: &Connection, profile_id: &str, year: i32, render_chart: bool) -> Result<YearInReview> {
+    let mut stmt = conn.prepare(
+        "SELECT content_type, content_id, content_data, watched_at, position, duration, watched
+         FROM xtream_history
+         WHERE profile_id = ?1 AND substr(watched_at, 1, 4) = ?2",
+    )?;
+
+    let entries = stmt
+        .query_map(params![profile_id, format!("{:04}", year)], |row| {
+            let content_id: String = row.get(1)?;
+            let content_data_bytes: Vec<u8> = row.get(2)?;
+            let content_data: serde_json::Value =
+                serde_json::from_slice(&content_data_bytes).unwrap_or(serde_json::Value::Null);
+            let watched_at: String = row.get(3)?;
+            let name = ["name", "title"]
+                .iter()
+                .find_map(|key| content_data.get(key).and_then(|name| name.as_str()))
+                .unwrap_or(&content_id)
+                .to_string();
+
+            Ok(WatchEntry {
+                content_type: row.get(0)?,
+                name,
+                genres: genres(&content_data),
+                date: watched_at.chars().take(10).collect(),
+                seconds: watched_seconds(row.get(4)?, row.get(5)?, row.get(6)?),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut review = summarize(profile_id, year, &entries);
+    if render_chart {
+        let png = render_bar_chart(&review.monthly_hours)?;
+        review.chart_png = Some(format!("data:image/png;base64,{}", general_purpose::STANDARD.encode(png)));
+    }
+    Ok(review)
+}
+
+/// Append a PNG chunk with its length and CRC
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+/// Draw one bar per value, scaled to the largest, as an RGB PNG
+fn render_bar_chart(values: &[f64]) -> Result<Vec<u8>> {
+    let (width, height) = (CHART_WIDTH, CHART_HEIGHT);
+    let plot_height = height - 2 * CHART_MARGIN;
+    let slot = (width - 2 * CHART_MARGIN) / values.len().max(1) as u32;
+    let bar_width = slot * 7 / 10;
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let baseline = height - CHART_MARGIN;
+
+    let mut pixels = vec![CHART_BACKGROUND; (width * height) as usize];
+    for (i, value) in values.iter().enumerate() {
+        let bar_height = if max > 0.0 {
+            (value / max * plot_height as f64).round() as u32
+        } else {
+            0
+        };
+        let left = CHART_MARGIN + i as u32 * slot + (slot - bar_width) / 2;
+        for y in baseline - bar_height..baseline {
+            for x in left..left + bar_width {
+                pixels[(y * width + x) as usize] = CHART_BAR;
+            }
+        }
+    }
+    for x in CHART_MARGIN..width - CHART_MARGIN {
+        pixels[(baseline * width + x) as usize] = CHART_AXIS;
+    }
+
+    // Each scanline starts with filter type 0 (none)
+    let mut raw = Vec::with_capacity(((width * 3 + 1) * height) as usize);
+    for row in pixels.chunks(width as usize) {
+        raw.push(0);
+        raw.extend(row.iter().flatten());
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&raw)
+        .map_err(|e| XTauriError::internal(format!("Failed to compress chart: {}", e)))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|e| XTauriError::internal(format!("Failed to compress chart: {}", e)))?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8-bit RGB, default compression and filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_chunk(&mut png, b"IHDR", &header);
+    push_chunk(&mut png, b"IDAT", &compressed);
+    push_chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE xtream_history (
+                id TEXT PRIMARY KEY,
+                profile_id TEXT NOT NULL,
+                content_type TEXT NOT NULL,
+                content_id TEXT NOT NULL,
+                content_data BLOB NOT NULL,
+                watched_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                position REAL,
+                duration REAL,
+                watched BOOLEAN NOT NULL DEFAULT FALSE
+            )",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add(conn: &Connection, content_type: &str, id: &str, data: serde_json::Value, watched_at: &str, position: f64) {
+        conn.execute(
+            "INSERT INTO xtream_history (id, profile_id, content_type, content_id, content_data, watched_at, position, duration)
+             VALUES (?1, 'p1', ?2, ?1, ?3, ?4, ?5, 7200)",
+            params![id, content_type, serde_json::to_vec(&data).unwrap(), watched_at, position],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_year_in_review() {
+        let conn = create_test_db();
+        add(&conn, "channel", "1", serde_json::json!({"name": "News 24"}), "2025-03-02T20:00:00+00:00", 3600.0);
+        add(&conn, "channel", "2", serde_json::json!({"name": "Sports HD"}), "2025-03-02T21:00:00+00:00", 5400.0);
+        add(
+            &conn,
+            "movie",
+            "3",
+            serde_json::json!({"name": "Heist", "genre": "Action, Thriller"}),
+            "2025-07-14T19:00:00+00:00",
+            1800.0,
+        );
+        add(&conn, "movie", "4", serde_json::json!({"name": "Old"}), "2024-12-31T23:00:00+00:00", 7200.0);
+
+        let review = year_in_review(&conn, "p1", 2025, false).unwrap();
+        assert_eq!(review.items_watched, 3);
+        assert_eq!(review.total_hours, 3.0);
+        assert_eq!(review.top_channels[0].name, "Sports HD");
+        assert_eq!(review.top_channels[0].hours, 1.5);
+        assert_eq!(review.top_genres.len(), 2);
+        assert_eq!(review.top_genres[0].name, "Action");
+        assert_eq!(review.busiest_day.as_ref().unwrap().date, "2025-03-02");
+        assert_eq!(review.monthly_hours[2], 2.5);
+        assert_eq!(review.monthly_hours[6], 0.5);
+        assert!(review.chart_png.is_none());
+
+        let empty = year_in_review(&conn, "p1", 2023, true).unwrap();
+        assert_eq!(empty.total_hours, 0.0);
+        assert!(empty.busiest_day.is_none());
+        assert!(empty.chart_png.unwrap().starts_with("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn test_watched_seconds() {
+        assert_eq!(watched_seconds(Some(600.0), Some(3600.0), false), 600.0);
+        assert_eq!(watched_seconds(Some(600.0), Some(3600.0), true), 3600.0);
+        assert_eq!(watched_seconds(Some(9000.0), Some(3600.0), false), 3600.0);
+        assert_eq!(watched_seconds(None, None, false), 0.0);
+    }
+
+    #[test]
+    fn test_render_bar_chart() {
+        let png = render_bar_chart(&[1.0, 0.0, 2.5]).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &CHART_WIDTH.to_be_bytes());
+        assert!(png.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+    }
+}
@@ -0,0 +1,63 @@
+{ static SET: ::phf::Set<&'static str> = ::phf::Set { map: ::phf::Map {
+    key: 16287231350648472473,
+    disps: &[
+        (0, 0),
+        (0, 0),
+        (0, 40),
+        (1, 2),
+        (0, 21),
+        (0, 8),
+        (3, 22),
+        (0, 2),
+        (19, 40),
+        (0, 6),
+    ],
+    entries: &[
+        ("dir", ()),
+        ("link", ()),
+        ("language", ()),
+        ("clear", ()),
+        ("checked", ()),
+        ("scope", ()),
+        ("noresize", ()),
+        ("align", ()),
+        ("http-equiv", ()),
+        ("nowrap", ()),
+        ("direction", ()),
+        ("lang", ()),
+        ("alink", ()),
+        ("face", ()),
+        ("rel", ()),
+        ("target", ()),
+        ("selected", ()),
+        ("multiple", ()),
+        ("rev", ()),
+        ("vlink", ()),
+        ("type", ()),
+        ("codetype", ()),
+        ("shape", ()),
+        ("valuetype", ()),
+        ("method", ()),
+        ("disabled", ()),
+        ("color", ()),
+        ("accept-charset", ()),
+        ("valign", ()),
+        ("declare", ()),
+        ("readonly", ()),
+        ("enctype", ()),
+        ("noshade", ()),
+        ("compact", ()),
+        ("defer", ()),
+        ("media", ()),
+        ("hreflang", ()),
+        ("rules", ()),
+        ("scrolling", ()),
+        ("accept", ()),
+        ("text", ()),
+        ("axis", ()),
+        ("charset", ()),
+        ("bgcolor", ()),
+        ("frame", ()),
+        ("nohref", ()),
+    ],
+} }; &SET }
\ No newline at end of file
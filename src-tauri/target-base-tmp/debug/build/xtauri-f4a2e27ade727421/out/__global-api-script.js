@@ -0,0 +1 @@
+["/root/.cargo/registry/src/index.crates.io-1949cf8c6b5b557f/tauri-2.12.3/scripts/bundle.global.js","/root/.cargo/registry/src/index.crates.io-1949cf8c6b5b557f/tauri-plugin-opener-2.5.3/api-iife.js"]
\ No newline at end of file
@@ -14,19 +14,25 @@ export default function ContentSyncManager() {
     cancelContentSync,
     getSyncSettings,
     updateSyncSettings,
+    getApiLanguage,
+    setApiLanguage,
     clearContentCache,
     getContentCacheStats,
   } = useXtreamContentStore();
 
   const [showSettings, setShowSettings] = useState(false);
   const [localSettings, setLocalSettings] = useState<SyncSettings | null>(null);
+  const [apiLanguage, setApiLanguageInput] = useState('');
 
   useEffect(() => {
     if (activeProfile) {
       getSyncSettings(activeProfile.id).catch(console.error);
       getContentCacheStats(activeProfile.id).catch(console.error);
+      getApiLanguage(activeProfile.id)
+        .then((language) => setApiLanguageInput(language ?? ''))
+        .catch(console.error);
     }
-  }, [activeProfile, getSyncSettings, getContentCacheStats]);
+  }, [activeProfile, getSyncSettings, getContentCacheStats, getApiLanguage]);
 
   useEffect(() => {
     if (syncSettings) {
@@ -56,7 +62,11 @@ export default function ContentSyncManager() {
     if (!activeProfile || !localSettings) return;
     try {
       await updateSyncSettings(activeProfile.id, localSettings);
+      const resyncNeeded = await setApiLanguage(activeProfile.id, apiLanguage.trim() || null);
       setShowSettings(false);
+      if (resyncNeeded && confirm('Cached names are in the previous language. Run a full sync now?')) {
+        await startContentSync(activeProfile.id, true, true);
+      }
     } catch (error) {
       console.error('Failed to update sync settings:', error);
     }
@@ -270,6 +280,17 @@ export default function ContentSyncManager() {
               Sync Series
             </label>
           </div>
+          <div className="setting-item">
+            <label>
+              Content Language:
+              <input
+                type="text"
+                placeholder="Provider default"
+                value={apiLanguage}
+                onChange={(e) => setApiLanguageInput(e.target.value)}
+              />
+            </label>
+          </div>
           <div className="settings-actions">
             <button className="btn btn-primary" onClick={handleSaveSettings}>
               Save Settings
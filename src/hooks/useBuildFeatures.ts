@@ -0,0 +1,33 @@
+import { invoke } from '@tauri-apps/api/core';
+import { useEffect, useState } from 'react';
+import type { BuildFeatures } from '../types/bindings';
+
+// The build doesn't change while the app runs, so it is fetched once
+let cached: Promise<BuildFeatures> | null = null;
+
+/**
+ * Optional features compiled into the backend
+ * Null until loaded; hide UI for features that are false
+ */
+export function useBuildFeatures(): BuildFeatures | null {
+  const [features, setFeatures] = useState<BuildFeatures | null>(null);
+
+  useEffect(() => {
+    let active = true;
+    cached ??= invoke<BuildFeatures>('get_build_features');
+    cached
+      .then((result) => {
+        if (active) setFeatures(result);
+      })
+      .catch((error) => {
+        console.error('Failed to get build features:', error);
+        cached = null;
+      });
+
+    return () => {
+      active = false;
+    };
+  }, []);
+
+  return features;
+}
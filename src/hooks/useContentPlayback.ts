@@ -170,7 +170,7 @@ export const useContentPlayback = () => {
       try {
         const contentId = getContentId(playbackState.currentContent);
         if (contentId) {
-          await invoke('update_xtream_playback_position', {
+          await invoke('playback_position_heartbeat', {
             profileId: activeProfile.id,
             contentType: playbackState.currentContent.type,
             contentId,
@@ -210,6 +210,9 @@ export const useContentPlayback = () => {
   }, []);
 
   const stop = useCallback(() => {
+    invoke('flush_playback_positions').catch(error => {
+      console.error('Failed to save playback position:', error);
+    });
     setPlaybackState(prev => ({
       ...prev,
       currentContent: null,
@@ -0,0 +1,34 @@
+import { invoke } from '@tauri-apps/api/core';
+import { useEffect, useRef } from 'react';
+import type { SessionState } from '../types/bindings';
+
+/**
+ * Where the user was when the app last closed, or null on first launch
+ */
+export async function loadSessionState(): Promise<SessionState | null> {
+  try {
+    return await invoke<SessionState | null>('get_session_state');
+  } catch (error) {
+    console.error('Failed to load session state:', error);
+    return null;
+  }
+}
+
+/**
+ * Report the navigation state whenever it changes
+ * The backend coalesces saves, so this can run on every scroll anchor change
+ */
+export function useSessionPersistence(session: Omit<SessionState, 'saved_at'>): void {
+  const serialized = JSON.stringify(session);
+  const lastSaved = useRef<string | null>(null);
+
+  useEffect(() => {
+    if (serialized === lastSaved.current) return;
+    lastSaved.current = serialized;
+    invoke('save_session_state', {
+      session: { ...JSON.parse(serialized), saved_at: 0 },
+    }).catch((error) => {
+      console.error('Failed to save session state:', error);
+    });
+  }, [serialized]);
+}
@@ -35,6 +35,19 @@ export interface ProfileCredentials {
   password: string;
 }
 
+export interface ProfileImportRow {
+  row: number;
+  name: string;
+  profile_id: string | null;
+  error: string | null;
+}
+
+export interface ProfileImportReport {
+  created: number;
+  failed: number;
+  rows: ProfileImportRow[];
+}
+
 export interface ProfileValidationResult {
   success: boolean;
   error_message?: string;
@@ -68,6 +81,7 @@ interface ProfileState {
 
   // Profile CRUD actions
   createProfile: (profile: CreateProfileRequest) => Promise<string>;
+  importProfilesCsv: (path: string, authenticate?: boolean) => Promise<ProfileImportReport>;
   updateProfile: (id: string, profile: UpdateProfileRequest) => Promise<void>;
   deleteProfile: (id: string) => Promise<void>;
   fetchProfiles: () => Promise<void>;
@@ -159,6 +173,25 @@ export const useProfileStore = create<ProfileState>()(
         }
       },
 
+      importProfilesCsv: async (path: string, authenticate = false) => {
+        set({ isSaving: true, error: null });
+
+        try {
+          const report = await invoke<ProfileImportReport>('import_xtream_profiles_csv', { path, authenticate });
+
+          if (report.created > 0) {
+            await get().fetchProfiles();
+          }
+
+          set({ isSaving: false });
+          return report;
+        } catch (error) {
+          const errorMessage = error instanceof Error ? error.message : String(error);
+          set({ error: errorMessage, isSaving: false });
+          throw error;
+        }
+      },
+
       updateProfile: async (id: string, profile: UpdateProfileRequest) => {
         set({ isSaving: true, error: null, validationError: null });
 
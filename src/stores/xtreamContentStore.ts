@@ -15,6 +15,26 @@ import type {
   XtreamShowListing
 } from '../types/types';
 
+/**
+ * Stored name of a content type, as the backend keys favorites, history and tags
+ * ("xtream-movie", "Movies" and "vod" are all "movie")
+ */
+export const canonicalContentType = (contentType: string): string => {
+  const type = contentType.trim().toLowerCase().replace(/^xtream-/, '');
+  switch (type) {
+    case 'channels':
+    case 'live':
+      return 'channel';
+    case 'movies':
+    case 'vod':
+      return 'movie';
+    case 'episodes':
+      return 'episode';
+    default:
+      return type;
+  }
+};
+
 // Sync types
 export interface SyncProgress {
   status: 'idle' | 'syncing' | 'completed' | 'failed' | 'cancelled';
@@ -35,6 +55,7 @@ export interface SyncSettings {
   sync_series: boolean;
   wifi_only: boolean;
   notify_on_complete: boolean;
+  favorites_only: boolean;
 }
 
 export interface ContentCacheStats {
@@ -166,11 +187,13 @@ interface XtreamContentState {
   isFavorite: (profileId: string, contentType: string, contentId: string) => boolean;
 
   // Sync actions
-  startContentSync: (profileId: string, fullSync: boolean) => Promise<void>;
+  startContentSync: (profileId: string, fullSync: boolean, runNow?: boolean) => Promise<string | null>;
   cancelContentSync: (profileId: string) => Promise<void>;
   getSyncProgress: (profileId: string) => Promise<void>;
   getSyncSettings: (profileId: string) => Promise<void>;
   updateSyncSettings: (profileId: string, settings: SyncSettings) => Promise<void>;
+  getApiLanguage: (profileId: string) => Promise<string | null>;
+  setApiLanguage: (profileId: string, language: string | null) => Promise<boolean>;
   clearContentCache: (profileId: string) => Promise<void>;
   getContentCacheStats: (profileId: string) => Promise<void>;
 
@@ -1024,7 +1047,7 @@ export const useXtreamContentStore = create<XtreamContentState>((set, get) => ({
       set(state => ({
         history: state.history.map(item =>
           item.profile_id === profileId &&
-            item.content_type === contentType &&
+            item.content_type === canonicalContentType(contentType) &&
             item.content_id === contentId
             ? { ...item, position, duration, watched_at: new Date().toISOString() }
             : item
@@ -1064,7 +1087,7 @@ export const useXtreamContentStore = create<XtreamContentState>((set, get) => ({
     return state.history.find(
       item =>
         item.profile_id === profileId &&
-        item.content_type === contentType &&
+        item.content_type === canonicalContentType(contentType) &&
         item.content_id === contentId
     ) || null;
   },
@@ -1168,7 +1191,7 @@ export const useXtreamContentStore = create<XtreamContentState>((set, get) => ({
         favorites: state.favorites.filter(
           item =>
             !(item.profile_id === profileId &&
-              item.content_type === contentType &&
+              item.content_type === canonicalContentType(contentType) &&
               item.content_id === contentId)
         )
       }));
@@ -1196,20 +1219,26 @@ export const useXtreamContentStore = create<XtreamContentState>((set, get) => ({
     return state.favorites.some(
       item =>
         item.profile_id === profileId &&
-        item.content_type === contentType &&
+        item.content_type === canonicalContentType(contentType) &&
         item.content_id === contentId
     );
   },
 
   // Sync actions
-  startContentSync: async (profileId: string, fullSync: boolean) => {
+  startContentSync: async (profileId: string, fullSync: boolean, runNow?: boolean) => {
     set({ isSyncing: true, syncError: null });
     try {
       // Note: cache_state and xtream_state are managed by Tauri's state system
-      await invoke('start_content_sync', {
+      const deferredUntil = await invoke<string | null>('start_content_sync', {
         profileId,
-        fullSync
+        fullSync,
+        runNow
       });
+      // Full syncs outside the maintenance window start later in the background
+      if (deferredUntil) {
+        set({ isSyncing: false });
+        return deferredUntil;
+      }
       set({ isSyncing: true });
       // Start polling for progress
       const pollProgress = async () => {
@@ -1236,6 +1265,7 @@ export const useXtreamContentStore = create<XtreamContentState>((set, get) => ({
         }
       };
       setTimeout(pollProgress, 1000);
+      return null;
     } catch (error) {
       set({
         syncError: error as string,
@@ -1275,6 +1305,20 @@ export const useXtreamContentStore = create<XtreamContentState>((set, get) => ({
     }
   },
 
+  getApiLanguage: async (profileId: string) => {
+    return await invoke<string | null>('get_xtream_api_language', { profileId });
+  },
+
+  // Resolves to true when cached names are in the previous language and need a re-sync
+  setApiLanguage: async (profileId: string, language: string | null) => {
+    try {
+      return await invoke<boolean>('set_xtream_api_language', { profileId, language });
+    } catch (error) {
+      set({ syncError: error as string });
+      throw error;
+    }
+  },
+
   updateSyncSettings: async (profileId: string, settings: SyncSettings) => {
     try {
       await invoke('update_sync_settings', { profileId, settings });
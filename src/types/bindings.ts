@@ -0,0 +1,1706 @@
+// This file has been generated by Specta. DO NOT EDIT.
+
+/**
+ * Last known account state of a profile, refreshed on startup
+ */
+export type AccountStatus = { profile_id: string; 
+/**
+ * Provider account status ("Active", "Expired", "Banned", ...)
+ */
+status: string | null; expires_at: number | null; expired: boolean; 
+/**
+ * Server clock minus local clock, when the server reports its time
+ */
+clock_skew_secs: number | null; clock_skewed: boolean; user_info: JsonValue | null; checked_at: number; error: string | null }
+
+/**
+ * Request to add a favorite
+ */
+export type AddFavoriteRequest = { profile_id: string; 
+/**
+ * One of `ContentId::TYPES`; other spellings are stored as that type
+ */
+content_type: string; content_id: string; content_data: JsonValue }
+
+/**
+ * Request to add a history item
+ */
+export type AddHistoryRequest = { profile_id: string; content_type: string; content_id: string; content_data: JsonValue; position: number | null; duration: number | null; 
+/**
+ * Play without recording history, regardless of the session setting
+ */
+private?: boolean }
+
+export type AddSearchHistoryRequest = { profile_id: string; query: string; content_types: string[]; results_count: number }
+
+/**
+ * Outcome of a TCP connect to one resolved address
+ */
+export type AddressProbe = { address: string; family: string; reachable: boolean; connect_ms: number | null; error: string | null }
+
+/**
+ * A programme on a catch-up channel that was left part way through
+ * 
+ * Found by matching when a channel was last watched against the programme
+ * airing on it at the time, as long as the provider's archive still has it.
+ */
+export type ArchiveResume = { stream_id: number; channel_name: string; programme_title: string; programme_start: number; programme_stop: number; 
+/**
+ * Seconds into the programme playback stopped at
+ */
+position_secs: number; watched_at: number; 
+/**
+ * When the programme drops out of the provider's archive
+ */
+available_until: number; 
+/**
+ * Timeshift stream starting at the programme's start; filled in with the profile's credentials
+ */
+timeshift_url: string | null }
+
+/**
+ * Types of authentication errors
+ */
+export type AuthenticationErrorType = "None" | "ValidationError" | "InvalidCredentials" | "AuthenticationFailed" | "NetworkError" | "TimeoutError" | "ServerError" | "ClientError" | "UnknownError"
+
+/**
+ * Result of authentication testing
+ */
+export type AuthenticationResult = { success: boolean; error_message: string | null; error_type: AuthenticationErrorType; server_info: JsonValue | null }
+
+/**
+ * Events that automation scripts can be attached to
+ */
+export type AutomationEvent = "after_sync" | "playback_start" | "manual"
+
+/**
+ * A user automation script stored in the database
+ */
+export type AutomationScript = { id: string; profile_id: string; name: string; event: AutomationEvent; source: string; enabled: boolean; last_run_at: string | null; last_error: string | null; created_at: string; updated_at: string }
+
+/**
+ * A background task as reported by `get_background_tasks`
+ */
+export type BackgroundTask = { id: string; kind: TaskKind; name: string; profile_id: string | null; 
+/**
+ * Percent complete, for tasks that can tell
+ */
+progress: number | null; 
+/**
+ * What the task is doing right now
+ */
+detail: string | null; cancellable: boolean; started_at: string }
+
+/**
+ * Unwatched titles of one genre
+ */
+export type BacklogGenre = { name: string; movies: number; episodes: number; hours: number }
+
+/**
+ * What a profile still has to watch among its favorite movies and followed series
+ * 
+ * A title counts as watched once it was marked watched or played past
+ * `CONTINUE_WATCHING_MAX_PROGRESS`; one left part way through counts with the
+ * time that is left.
+ */
+export type BacklogStats = { profile_id: string; movies: number; episodes: number; 
+/**
+ * Followed series with unwatched episodes
+ */
+series: number; 
+/**
+ * Time left in the unwatched titles, from their runtimes
+ */
+hours: number; 
+/**
+ * Unwatched titles with no known runtime, left out of `hours`
+ */
+without_runtime: number; 
+/**
+ * Followed series whose episodes aren't cached yet, so they can't be counted
+ */
+series_without_episodes: number; 
+/**
+ * Most hours first
+ */
+genres: BacklogGenre[] }
+
+/**
+ * What a backup holds, reported after it was written or restored
+ */
+export type BackupSummary = { created_at: string; 
+/**
+ * Version of the app that wrote the backup
+ */
+app_version: string; tables: BackupTableCount[]; profiles: number; playlist_files: number }
+
+export type BackupTableCount = { table: string; rows: number }
+
+export type BandwidthSourceStats = ({ requests: number; compressed_bytes: number; uncompressed_bytes: number }) & { source: string }
+
+export type BuildFeatures = { recordings: boolean; 
+/**
+ * User automation scripts
+ */
+scripting: boolean; 
+/**
+ * Database built on SQLCipher
+ */
+sqlcipher: boolean; profiling: boolean; 
+/**
+ * AVIF and HEIC logo decoding through libheif
+ */
+avif: boolean; heic: boolean }
+
+/**
+ * The day with the most watch time
+ */
+export type BusiestDay = { 
+/**
+ * Date as `YYYY-MM-DD`
+ */
+date: string; hours: number }
+
+/**
+ * Lookups of one class of channel cache keys
+ */
+export type CacheKeyClassStats = { 
+/**
+ * "list" for full lists, "view" for lists narrowed by groups or filters
+ */
+class: string; hits: number; misses: number }
+
+/**
+ * Size of one in-memory cache
+ */
+export type CacheMemoryUsage = { name: string; entries: number; approx_bytes: number }
+
+export type CacheStats = { hits: number; misses: number; entries: number; total_results: number; memory_usage_estimate: number; 
+/**
+ * Channel list cache lookups, by key class
+ */
+channel_cache: CacheKeyClassStats[] }
+
+/**
+ * Minutes each kind of response is cached for
+ */
+export type CacheTtls = { categories: number; channels: number; movies: number; series: number; epg_short: number; epg_full: number; details: number }
+
+/**
+ * Items that appeared in one followed category
+ */
+export type CategoryDigest = { 
+/**
+ * `channel`, `movie` or `series`
+ */
+content_type: string; category_name: string; new_items: number; 
+/**
+ * The first few item names
+ */
+sample: string[] }
+
+/**
+ * A provider category and the number of items a sync would store in it
+ */
+export type CategoryPreview = { category_id: string; name: string; items: number }
+
+export type Channel = { name: string; logo: string; url: string; group_title: string; tvg_id: string; resolution: string; extra_info: string; 
+/**
+ * ISO country code from `tvg-country`, a name tag or the group; empty when unknown
+ */
+country?: string; 
+/**
+ * Flag emoji for `country`
+ */
+country_flag?: string }
+
+export type ChannelFilter = { name: string | null; category_id: string | null; group: string | null; 
+/**
+ * ISO code, tag or name of the channels' country
+ */
+country?: string | null }
+
+export type ChannelList = { id: number; name: string; source: string; is_default: boolean; filepath: string | null; last_fetched: number | null; 
+/**
+ * `auto` or the encoding label the list is always decoded with
+ */
+encoding: string; detected_encoding: string | null; 
+/**
+ * Set when the last decode looked garbled
+ */
+encoding_warning: string | null }
+
+/**
+ * A channel as listed to the UI, optionally with the programme airing now
+ */
+export type ChannelListing = ({ name: string; logo: string; url: string; group_title: string; tvg_id: string; resolution: string; extra_info: string; 
+/**
+ * ISO country code from `tvg-country`, a name tag or the group; empty when unknown
+ */
+country?: string; 
+/**
+ * Flag emoji for `country`
+ */
+country_flag?: string }) & { now_playing?: NowPlaying | null }
+
+/**
+ * Options of the cached channel listing and search commands
+ * 
+ * Every field may be left out. Searches rank by relevance, so they ignore `sort_mode`;
+ * plain listings ignore the search-only fields.
+ */
+export type ChannelListingOptions = { 
+/**
+ * `custom` to use the category's manual order set by `move_xtream_channel`
+ */
+sort_mode: ChannelSortMode; 
+/**
+ * Country filter: an ISO code such as `GB`, a tag such as `UK`, or a name
+ */
+country: string | null; 
+/**
+ * `advanced` to allow wildcards and quoted phrases (see `get_search_syntax_help`)
+ */
+search_mode: SearchMode; 
+/**
+ * Search session; a newer search in the session cancels this one
+ */
+session_id: string | null; 
+/**
+ * Add each search result's current programme from the local EPG store
+ */
+include_now_playing: boolean; 
+/**
+ * Return each icon's file in `cached_icon` when the image cache already holds it
+ */
+resolve_thumbnails: boolean }
+
+export type ChannelLoadingStatus = { progress: number; message: string; channel_count: number | null; is_complete: boolean }
+
+/**
+ * The list a channel is being reordered within
+ */
+export type ChannelOrderScope = { type: "category"; category_id: string } | { type: "favorites" }
+
+/**
+ * How a channel listing is ordered
+ */
+export type ChannelSortMode = 
+/**
+ * The listing's usual order: by name for channels, newest first for favorites
+ */
+"default" | 
+/**
+ * The user's drag-and-drop order, followed by items never moved in the usual order
+ */
+"custom" | 
+/**
+ * Most watched, favorite and recently added first, from `popularity`
+ */
+"popularity"
+
+/**
+ * A user-supplied stream URL replacing a channel's provider URL
+ * 
+ * Stored separately from `xtream_channels`, so syncs and cache clears keep it.
+ */
+export type ChannelUrlOverride = { profile_id: string; stream_id: number; url: string; updated_at: string }
+
+/**
+ * How many calls each subsystem runs at once, and how many may wait
+ */
+export type ConcurrencyLimits = { images: number; search: number; sync: number; epg: number; 
+/**
+ * Calls that may wait for a slot in each subsystem before new ones are rejected
+ */
+max_queued: number }
+
+/**
+ * Where a value in effect comes from
+ */
+export type ConfigSource = "file" | "database" | "default"
+
+/**
+ * One value in effect, by its key in the file
+ */
+export type ConfigValue = { key: string; 
+/**
+ * `None` when nothing is set and the HTTP client's own behavior applies
+ */
+value: string | null; source: ConfigSource }
+
+/**
+ * Which address families outgoing connections use
+ * 
+ * The prefer variants only fall back to the other family when the host has no
+ * address of the preferred one, so a broken AAAA record can't stall a request
+ * under `PreferIpv4`. `HappyEyeballs` hands every address to the connector,
+ * which races the second family shortly after the first one starts.
+ */
+export type ConnectionStrategy = "prefer_ipv_4" | "prefer_ipv_6" | "happy_eyeballs"
+
+/**
+ * Result of a connection test: DNS and reachability, then authentication
+ */
+export type ConnectionTestResult = { dns: DnsDiagnostics | null; authentication: AuthenticationResult }
+
+/**
+ * A channel list or profile with an expiry time
+ */
+export type ContentExpiry = { source: ExpirySource; 
+/**
+ * Channel list id or profile id
+ */
+id: string; name: string; expires_at: number; 
+/**
+ * Whether its content is hidden
+ */
+expired: boolean }
+
+/**
+ * What a sync would fetch for one content type
+ */
+export type ContentTypePreview = { content_type: string; categories: CategoryPreview[]; item_count: number; 
+/**
+ * Items without a category, or in one the category list doesn't include
+ */
+uncategorized_items: number; download_bytes: number; 
+/**
+ * Why this content type couldn't be previewed; the others are still reported
+ */
+error: string | null }
+
+/**
+ * A movie or episode that was left part way through, with its cached details
+ */
+export type ContinueWatchingItem = { profile_id: string; profile_name: string; 
+/**
+ * "movie" or "episode"
+ */
+content_type: string; content_id: string; title: string; cover: string | null; 
+/**
+ * Saved position in seconds
+ */
+position: number; 
+/**
+ * Duration in seconds, from playback or else from the cached metadata
+ */
+duration: number | null; 
+/**
+ * Share of the duration watched, when the duration is known
+ */
+progress: number | null; 
+/**
+ * Series an episode belongs to
+ */
+series_id: number | null; season_number: number | null; episode_num: string | null; watched_at: string }
+
+/**
+ * A country and the number of channels from it
+ */
+export type CountryCount = { code: string; name: string; flag: string; channels: number }
+
+/**
+ * Request to create an automation script
+ */
+export type CreateAutomationScriptRequest = { profile_id: string; name: string; event: AutomationEvent; source: string; enabled: boolean | null }
+
+/**
+ * Request to create an EPG reminder
+ */
+export type CreateEpgReminderRequest = { profile_id: string; channel_id: string; channel_name: string; title: string; description: string | null; start_timestamp: number; stop_timestamp: number }
+
+/**
+ * Request to create a new profile
+ */
+export type CreateProfileRequest = { name: string; url: string; username: string; password: string }
+
+export type CreateSavedFilterRequest = { profile_id: string; name: string; content_type: string; filter_data: string }
+
+/**
+ * A weekly summary
+ */
+export type Digest = { 
+/**
+ * Unix timestamps of the period covered
+ */
+period_start: number; generated_at: number; 
+/**
+ * Profiles with something to report
+ */
+profiles: ProfileDigest[]; storage: StorageUsage }
+
+/**
+ * When the weekly digest is compiled
+ */
+export type DigestSettings = { enabled: boolean; 
+/**
+ * Day of the week, 0 for Monday to 6 for Sunday
+ */
+weekday: number; 
+/**
+ * Hour of the day in local time, 0 to 23
+ */
+hour: number; 
+/**
+ * Send a notification when a digest is ready
+ */
+notify: boolean }
+
+/**
+ * DNS and reachability report for a provider host
+ */
+export type DnsDiagnostics = { host: string; port: number; strategy: ConnectionStrategy; resolve_ms: number; ipv4_addresses: string[]; ipv6_addresses: string[]; 
+/**
+ * Addresses in the order the current strategy would try them
+ */
+connection_order: string[]; probes: AddressProbe[]; error: string | null }
+
+/**
+ * The configuration in effect, merged from the file, the settings and defaults
+ */
+export type EffectiveConfig = { path: string; 
+/**
+ * Whether the file exists
+ */
+file_present: boolean; 
+/**
+ * Why the file was last rejected; the previous contents stay in effect
+ */
+error: string | null; values: ConfigValue[] }
+
+/**
+ * Encoding setting and the outcome of the last decode for a channel list
+ */
+export type EncodingReport = { encoding: string; detected_encoding: string | null; encoding_warning: string | null }
+
+/**
+ * EPG for many channels fetched in chunks and merged
+ */
+export type EpgBatchResult = { 
+/**
+ * Listings keyed by EPG channel id, or by stream id when a listing names no channel
+ */
+epg: Partial<{ [key in string]: JsonValue[] }>; chunks_total: number; failed_chunks: EpgChunkFailure[]; 
+/**
+ * Listings that couldn't be attributed to a channel
+ */
+unassigned_listings: number }
+
+/**
+ * A channel logo in the guide grid
+ */
+export type EpgChannelIcon = { icon_url: string; 
+/**
+ * Cached file of `icon_url`, when it is cached
+ */
+cached_icon: string | null }
+
+/**
+ * A chunk of a batched EPG fetch that failed
+ */
+export type EpgChunkFailure = { channel_ids: string[]; error: string }
+
+/**
+ * The guide for a set of EPG channels, with the images it shows
+ */
+export type EpgGrid = { 
+/**
+ * Programmes by EPG channel id, in start order
+ */
+programmes: Partial<{ [key in string]: EpgProgramme[] }>; 
+/**
+ * Logo of each channel that has one, by EPG channel id
+ */
+channel_icons: Partial<{ [key in string]: EpgChannelIcon }>; 
+/**
+ * Download of the images that weren't cached yet; an `image_fetched` event follows for each
+ */
+icon_batch: ImageBatch | null }
+
+/**
+ * An EPG channel a channel might show the guide of
+ */
+export type EpgMappingCandidate = { epg_channel_id: string; display_name: string; 
+/**
+ * From 0 to 1
+ */
+confidence: number }
+
+/**
+ * What an auto-mapping pass did with the channels lacking an EPG id
+ */
+export type EpgMappingReport = { applied: number; 
+/**
+ * Channels left with suggestions to review
+ */
+suggested: number; unmatched: number }
+
+/**
+ * A channel with the EPG channels it could be mapped to, best first
+ */
+export type EpgMappingSuggestion = { stream_id: number; channel_name: string; candidates: EpgMappingCandidate[] }
+
+/**
+ * A programme in the guide grid
+ */
+export type EpgProgramme = { title: string; description: string | null; start_timestamp: number; stop_timestamp: number; 
+/**
+ * A filler covering a gap the provider left between programmes
+ */
+synthetic: boolean; 
+/**
+ * Image the guide lists for the programme
+ */
+icon_url: string | null; 
+/**
+ * Cached file of `icon_url`; only looked up for the guide grid
+ */
+cached_icon: string | null }
+
+/**
+ * What the sync time estimate is based on
+ */
+export type EstimateBasis = 
+/**
+ * The profile's previous full syncs
+ */
+"history" | 
+/**
+ * The preview's own download time plus an assumed save rate
+ */
+"preview"
+
+/**
+ * What an expiry applies to
+ */
+export type ExpirySource = "channel_list" | "profile"
+
+/**
+ * Identifies a favorite by what it points at, which is unique per profile
+ */
+export type FavoriteIdentity = { content_type: string; content_id: string }
+
+/**
+ * Favorites that changed in one operation, so open views can patch their copy
+ */
+export type FavoritesChange = { profile_id: string; added: FavoriteIdentity[]; removed: FavoriteIdentity[]; 
+/**
+ * Favorites whose position in the custom order changed
+ */
+moved: FavoriteIdentity[] }
+
+/**
+ * A category a profile follows
+ */
+export type FollowedCategory = { 
+/**
+ * `channel`, `movie` or `series`
+ */
+content_type: string; category_id: string; category_name: string; 
+/**
+ * Unix timestamp
+ */
+followed_at: number; 
+/**
+ * Feed entries of this category not read yet
+ */
+unread: number }
+
+/**
+ * An item that appeared in a followed category
+ */
+export type FollowedCategoryUpdate = { id: number; 
+/**
+ * `channel`, `movie` or `series`
+ */
+content_type: string; category_id: string; category_name: string; item: string; item_name: string; 
+/**
+ * Unix timestamp of the sync that found it
+ */
+added_at: number; read: boolean }
+
+/**
+ * A canonical genre
+ */
+export type GenreInfo = { id: string; name: string }
+
+/**
+ * A user's mapping of a provider genre name to a canonical genre
+ */
+export type GenreMapping = { provider_genre: string; genre_id: string }
+
+/**
+ * A cleanup step for a group
+ */
+export type GroupAction = "disable" | "dedupe"
+
+export type GroupAnalysis = { 
+/**
+ * Groups in playlist order
+ */
+groups: GroupStats[]; suggestions: GroupSuggestion[] }
+
+/**
+ * Statistics for one group of a playlist
+ */
+export type GroupStats = { group_name: string; enabled: boolean; 
+/**
+ * Whether repeated URLs are already dropped from the group
+ */
+deduped: boolean; channel_count: number; 
+/**
+ * Share of channels whose last playback attempt failed
+ */
+dead_link_ratio: number; 
+/**
+ * Share of channels that repeat a URL listed earlier in the group
+ */
+duplicate_ratio: number; 
+/**
+ * Channels of the group in the watch history
+ */
+watched_channels: number; 
+/**
+ * When a channel of the group was last watched, as stored in the history
+ */
+last_watched: string | null }
+
+export type GroupSuggestion = { group_name: string; action: GroupAction; 
+/**
+ * Why the step is suggested, for display
+ */
+reason?: string }
+
+/**
+ * What `fetch_images_batch` queued
+ */
+export type ImageBatch = { batch_id: string; 
+/**
+ * Unique URLs queued; one `image_fetched` event follows for each
+ */
+queued: number }
+
+/**
+ * An image the cache couldn't transcode for the webview
+ */
+export type ImageDecodeFailure = { url: string; format: ImageFormat; error: string; 
+/**
+ * Downloads that failed the same way
+ */
+failures: number; last_failed_at: number }
+
+/**
+ * Payload of an `image_fetched` event
+ */
+export type ImageFetched = { batch_id: string; url: string; path: string | null; 
+/**
+ * Whether the image was already on disk
+ */
+cached: boolean; error: string | null }
+
+/**
+ * Formats of downloaded images, as told by their first bytes
+ */
+export type ImageFormat = "jpeg" | "png" | "gif" | "webp" | "avif" | "heic"
+
+/**
+ * How downloaded images the webview may not show are cached
+ */
+export type ImageFormatSettings = { transcode_to: TranscodeTarget; 
+/**
+ * Formats cached as downloaded rather than transcoded
+ */
+pass_through: ImageFormat[] }
+
+/**
+ * URLs that should be fetched together; higher priorities are fetched first
+ */
+export type ImageGroup = { priority?: number; urls: string[] }
+
+/**
+ * How a prefetch run went
+ */
+export type ImagePrefetchReport = { profile_id: string; budget_bytes: number; used_bytes: number; 
+/**
+ * Images not yet cached when the run started
+ */
+candidates: number; fetched: number; 
+/**
+ * Images that failed or didn't fit in what was left of the budget
+ */
+skipped: number; finished_at: number }
+
+/**
+ * Whether images are prefetched after a sync, and how much may be downloaded
+ */
+export type ImagePrefetchSettings = { enabled: boolean; 
+/**
+ * Megabytes downloaded per run at most
+ */
+budget_mb: number }
+
+/**
+ * A notification in the in-app inbox
+ */
+export type InboxNotification = { id: number; kind: NotificationKind; title: string; body: string; profile_id: string | null; 
+/**
+ * Unix timestamp
+ */
+created_at: number; read: boolean }
+
+export type JsonValue = null | boolean | number | string | JsonValue[] | Partial<{ [key in string]: JsonValue }>
+
+/**
+ * Whether low-power mode is on
+ */
+export type LowPowerMode = 
+/**
+ * On while running on battery
+ */
+"auto" | "on" | "off"
+
+/**
+ * Which channels of a list to export; everything when left empty
+ */
+export type M3uExportFilter = { 
+/**
+ * Only channels in these groups
+ */
+groups?: string[] | null; 
+/**
+ * Only channels in the list's enabled groups
+ */
+enabled_groups_only?: boolean; 
+/**
+ * Only channels from this country; an ISO code, a tag like "UK" or a name
+ */
+country?: string | null; 
+/**
+ * Only channels this Xtream profile has in its favorites
+ */
+favorites_profile_id?: string | null }
+
+/**
+ * What a maintenance run did
+ */
+export type MaintenanceRun = { 
+/**
+ * Set when the run waits for the maintenance window; it then happens in the background
+ */
+deferred_until: string | null; 
+/**
+ * Profiles whose search index was rebuilt
+ */
+fts_rebuilt: number; 
+/**
+ * Whether the database was fragmented enough to VACUUM
+ */
+vacuumed: boolean }
+
+/**
+ * Daily period in local time when heavy work may run
+ * 
+ * The end may be earlier than the start for a window that spans midnight.
+ */
+export type MaintenanceWindow = { 
+/**
+ * Start time as `HH:MM`
+ */
+start: string; 
+/**
+ * End time as `HH:MM`
+ */
+end: string }
+
+/**
+ * Sizes of the in-memory caches that grow over a session
+ */
+export type MemoryReport = { caches: CacheMemoryUsage[]; total_bytes: number }
+
+export type MovieFilter = { name: string | null; category_id: string | null; genre: string | null; year: string | null; min_rating: number | null }
+
+/**
+ * What a notification is about
+ */
+export type NotificationKind = 
+/**
+ * An EPG programme with a reminder is about to start
+ */
+"reminder" | 
+/**
+ * A content sync finished or failed
+ */
+"sync_result" | 
+/**
+ * A favorited series has new episodes
+ */
+"new_episode" | 
+/**
+ * A followed category has new items
+ */
+"followed_category" | 
+/**
+ * The weekly digest is ready
+ */
+"digest"
+
+/**
+ * Whether a channel delivers one kind of notification
+ */
+export type NotificationRoute = { kind: NotificationKind; channel: string; enabled: boolean }
+
+/**
+ * The programme currently airing on a channel
+ */
+export type NowPlaying = { title: string; start_timestamp: number; stop_timestamp: number; progress_percent: number }
+
+/**
+ * One page of a list, with the size of the whole list
+ */
+export type Page<T> = { items: T[]; 
+/**
+ * Items in the whole list, not just this page
+ */
+total: number; 
+/**
+ * Page size; `None` when the rest of the list was requested
+ */
+limit: number | null; offset: number; has_more: boolean }
+
+/**
+ * One attempt to unlock parental controls
+ */
+export type ParentalAuditEntry = { id: number; attempted_at: number; success: boolean; 
+/**
+ * What the PIN was entered for, e.g. `age_limit` or a content id
+ */
+target: string }
+
+/**
+ * How listing commands encode their responses
+ */
+export type PayloadEncoding = "json" | "cbor"
+
+/**
+ * A playable copy of a channel
+ */
+export type PlaybackCandidate = { profile_id: string; stream_id: number; name: string; epg_channel_id: string | null; 
+/**
+ * Stored stream URL (with any user override applied); when absent the URL is
+ * generated from the profile credentials
+ */
+direct_source: string | null; url_overridden: boolean; failure_count: number; last_failure_at: number | null; 
+/**
+ * Whether the source failed within the cooldown window
+ */
+failing: boolean; 
+/**
+ * Reliability score from playback reports, see `reliability_score`
+ */
+reliability: number | null }
+
+/**
+ * What happened during playback of a source
+ */
+export type PlaybackEvent = 
+/**
+ * Playback got its first frames
+ */
+"started" | 
+/**
+ * Playback stalled to buffer
+ */
+"buffered" | 
+/**
+ * Playback could not start or stopped with an error
+ */
+"failed"
+
+export type PlaylistFetchStatus = { id: number; status: string; progress: number; message: string; channel_count: number | null; error: string | null }
+
+/**
+ * A playlist search saved to a numbered slot
+ */
+export type PlaylistSavedFilter = { slot_number: number; search_query: string; selected_group: string | null; name: string }
+
+/**
+ * Items carrying one user tag
+ */
+export type PortableGroup = { name: string; items: PortableItem[] }
+
+/**
+ * A favorited or tagged item, described without provider ids
+ */
+export type PortableItem = { 
+/**
+ * "channel", "movie" or "series"
+ */
+content_type: string; name: string; 
+/**
+ * Normalized name shared by copies of the item on different providers
+ */
+match_key: string; 
+/**
+ * EPG channel id of a channel
+ */
+tvg_id: string | null; 
+/**
+ * Release year of a movie or series, to tell remakes apart
+ */
+year: string | null }
+
+/**
+ * Changes the post-processors made to one playlist or Xtream profile
+ */
+export type PostProcessReport = { 
+/**
+ * `playlist:<id>` or `xtream:<profile_id>`
+ */
+source: string; items_processed: number; processors: ProcessorChangeCounts[]; updated_at: number }
+
+/**
+ * A registered post-processor and whether it is enabled
+ */
+export type PostProcessorInfo = { id: string; description: string; enabled: boolean }
+
+/**
+ * Payload of a `power_state_changed` event
+ */
+export type PowerState = { mode: LowPowerMode; 
+/**
+ * `None` when the power source can't be detected on this platform
+ */
+on_battery: boolean | null; 
+/**
+ * Whether background work is cut back right now
+ */
+low_power: boolean }
+
+/**
+ * Changes made by one post-processor
+ */
+export type ProcessorChangeCounts = { processor_id: string; renamed: number; recategorized: number; dropped: number }
+
+/**
+ * Profile credentials for Xtream authentication
+ */
+export type ProfileCredentials = { url: string; username: string; password: string }
+
+/**
+ * The week of one Xtream profile
+ */
+export type ProfileDigest = { profile_id: string; profile_name: string; followed_categories: CategoryDigest[]; new_episodes: SeriesDigest[]; 
+/**
+ * Reminded programmes starting within the next week
+ */
+upcoming_reminders: XtreamEpgReminder[] }
+
+/**
+ * What `dump_profile` wrote
+ */
+export type ProfileDump = { path: string; events: number; 
+/**
+ * Spans not recorded because the session hit the recording limit
+ */
+dropped_events: number }
+
+/**
+ * A genre as it appears in a profile's movies and series
+ */
+export type ProfileGenre = { 
+/**
+ * Canonical id; `None` for provider names nothing maps yet
+ */
+genre_id: string | null; 
+/**
+ * The canonical English name, or the provider's name when unmapped
+ */
+name: string; 
+/**
+ * The provider's names for it, as they appear in the content
+ */
+provider_names: string[]; 
+/**
+ * Movies and series tagged with it
+ */
+count: number }
+
+export type ProfileImportReport = { created: number; failed: number; rows: ProfileImportRow[] }
+
+/**
+ * Outcome of one CSV row
+ */
+export type ProfileImportRow = { 
+/**
+ * Line the row starts on, counting the header
+ */
+row: number; name: string; 
+/**
+ * Id of the created profile; `None` when the row was rejected
+ */
+profile_id: string | null; error: string | null }
+
+/**
+ * Everything a profile's setup needs to be rebuilt on another provider
+ */
+export type ProfilePortables = { format_version: number; exported_at: string; profile_name: string; 
+/**
+ * Favorites in the profile's custom order
+ */
+favorites: PortableItem[]; groups: PortableGroup[]; 
+/**
+ * Items that could not be described, because they are neither cached nor named
+ */
+skipped: number }
+
+/**
+ * Summary of a profile's catalog, gathered without saving it
+ */
+export type ProfilePreview = { 
+/**
+ * Account details from the authentication response
+ */
+user_info: JsonValue | null; content: ContentTypePreview[]; total_categories: number; total_items: number; download_bytes: number; fetch_ms: number; estimated_sync_secs: number; estimate_basis: EstimateBasis }
+
+/**
+ * A profile's sync progress, as sent in the sync progress and completion events
+ */
+export type ProfileSyncProgress = ({ status: SyncStatus; progress: number; current_step: string; channels_synced: number; movies_synced: number; series_synced: number; errors: string[] }) & { profile_id: string }
+
+/**
+ * Where provider responses come from
+ */
+export type ProviderMode = 
+/**
+ * Requests go to the provider
+ */
+"live" | 
+/**
+ * Requests go to the provider and responses are written to fixtures
+ */
+"record" | 
+/**
+ * Requests are answered from fixtures
+ */
+"replay"
+
+/**
+ * Second tier of a tiered search: provider matches the local search didn't return
+ */
+export type ProviderSearchResults = { search_id: string; profile_id: string; results: SearchResult; 
+/**
+ * Set when the provider couldn't be searched; `results` is then empty
+ */
+error: string | null }
+
+/**
+ * A recording as listed by `list_recordings`
+ */
+export type Recording = { id: string; name: string; stream_url: string; profile_id: string | null; channel_id: string | null; 
+/**
+ * Can be played while the recording is still running
+ */
+file_path: string; status: RecordingStatus; started_at: number; ended_at: number | null; 
+/**
+ * Bytes written so far
+ */
+bytes: number; error: string | null }
+
+/**
+ * What `start_recording` should record
+ */
+export type RecordingRequest = { stream_url: string; 
+/**
+ * Shown in the list and used for the file name; usually the channel name
+ */
+name: string; profile_id?: string | null; channel_id?: string | null; 
+/**
+ * Stop after this many minutes; without it the recording runs until stopped
+ */
+duration_minutes?: number | null }
+
+export type RecordingStatus = "recording" | 
+/**
+ * The duration ran out or the stream ended
+ */
+"completed" | 
+/**
+ * Stopped with `stop_recording`
+ */
+"stopped" | "failed"
+
+/**
+ * A downloadable file attached to a release
+ */
+export type ReleaseAsset = { name: string; url: string; size: number; content_type: string | null }
+
+/**
+ * Persisted remote control configuration
+ */
+export type RemoteControlSettings = { enabled: boolean; port: number; pairing_token: string }
+
+/**
+ * Request sent by a remote to start playback of a content item
+ */
+export type RemotePlayRequest = { profile_id: string; content_type: string; content_id: string }
+
+/**
+ * How fast and how many requests at once a profile's provider is sent
+ */
+export type RequestLimits = { profile_id: string; 
+/**
+ * Sustained rate the token bucket refills at
+ */
+requests_per_second: number; 
+/**
+ * Requests that may start back to back after a quiet spell
+ */
+burst: number; max_concurrent: number }
+
+/**
+ * A playback URL and whether it came from a user override
+ */
+export type ResolvedStreamUrl = { url: string; overridden: boolean }
+
+export type SavedFilter = { id: string; profile_id: string; name: string; content_type: string; filter_data: string; created_at: string; last_used: string | null }
+
+/**
+ * Outcome of running a single script
+ */
+export type ScriptRunResult = { script_id: string; success: boolean; output: string[]; error: string | null; duration_ms: number }
+
+export type SearchContentType = "channel" | "movie" | "series"
+
+/**
+ * One source's hits of one content type, best first
+ */
+export type SearchGroup = { source: SearchSource; 
+/**
+ * Channel list id for playlists, profile id for Xtream
+ */
+source_id: string; source_name: string; content_type: SearchContentType; 
+/**
+ * Matches found, of which the best are in `hits`
+ */
+total: number; hits: SearchHit[] }
+
+export type SearchHistoryItem = { id: string; profile_id: string; query: string; content_types: string[]; results_count: number; created_at: string }
+
+export type SearchHit = { relevance_score: number; item: SearchItem }
+
+/**
+ * A matched item, as the source's own search would return it
+ */
+export type SearchItem = { type: "playlist_channel"; data: Channel } | { type: "xtream_channel"; data: XtreamChannel } | { type: "movie"; data: XtreamMovie } | { type: "series"; data: XtreamSeries }
+
+/**
+ * How a search query is interpreted
+ */
+export type SearchMode = 
+/**
+ * The query is plain text; wildcard characters match themselves
+ */
+"simple" | 
+/**
+ * `*`, `?` and double quotes have the meanings in [`search_syntax_help`]
+ */
+"advanced"
+
+export type SearchOptions = { query: string; search_channels: boolean; search_movies: boolean; search_series: boolean; case_sensitive: boolean; max_results_per_type: number | null }
+
+export type SearchProgress = { progress: number; message: string; current_results: number; is_complete: boolean }
+
+export type SearchResult = { channels: XtreamChannel[]; movies: XtreamMovie[]; series: XtreamSeries[]; total_results: number }
+
+/**
+ * Where a search hit comes from
+ */
+export type SearchSource = "playlist" | "xtream"
+
+/**
+ * The supported search grammar
+ */
+export type SearchSyntaxHelp = { modes: SyntaxEntry[]; 
+/**
+ * Syntax understood in advanced mode
+ */
+advanced: SyntaxEntry[]; notes: string[] }
+
+/**
+ * Episodes a favorited series gained
+ */
+export type SeriesDigest = { series_id: number; series_name: string; new_episodes: number }
+
+export type SeriesFilter = { name: string | null; category_id: string | null; genre: string | null; year: string | null; min_rating: number | null }
+
+/**
+ * Watch progress through a series, used for continue watching and the next episode
+ */
+export type SeriesProgress = { profile_id: string; series_id: number; watched_episodes: number; total_episodes: number; 
+/**
+ * The episode watched or played most recently
+ */
+last_watched_episode_id: string | null; 
+/**
+ * The episode to play next; `None` once every episode is watched
+ */
+next_episode_id: string | null; updated_at: string }
+
+/**
+ * Where the user was in the app
+ */
+export type SessionState = { 
+/**
+ * Active Xtream profile
+ */
+profile_id: string | null; 
+/**
+ * Active tab or screen
+ */
+view: string | null; category_id: string | null; 
+/**
+ * Content id of the item the list was scrolled to
+ */
+scroll_anchor_id: string | null; 
+/**
+ * Active filters, as the view keeps them
+ */
+filters?: JsonValue; 
+/**
+ * When the state was saved, as a unix timestamp; set by the backend
+ */
+saved_at?: number }
+
+/**
+ * A setting that changed when a profile was switched to
+ */
+export type SettingChange = { key: string; value: JsonValue; 
+/**
+ * Profile that was switched to
+ */
+profile: string }
+
+/**
+ * A named set of device settings
+ */
+export type SettingsProfile = { name: string; 
+/**
+ * Setting values by key
+ */
+settings: JsonValue; 
+/**
+ * Whether the settings in use were last switched to this profile
+ */
+active: boolean; updated_at: string }
+
+/**
+ * Content that similar items are found for
+ */
+export type SimilarContentType = "movie" | "series"
+
+/**
+ * A cached item ranked by how much it has in common with another
+ */
+export type SimilarItem = { id: number; name: string; icon: string | null; year: string | null; score: number }
+
+/**
+ * Outcome of the latest startup attempt
+ */
+export type StartupReport = { 
+/**
+ * Whether everything commands depend on is up; the image cache is optional
+ */
+ready: boolean; failures: SubsystemFailure[]; attempts: number; checked_at: number }
+
+/**
+ * A part of the app brought up at startup
+ */
+export type StartupSubsystem = "database" | "profiles" | "content_cache" | "image_cache"
+
+/**
+ * The cache budgets in effect
+ */
+export type StoragePolicy = { mobile: boolean; low_storage: boolean; backgrounded: boolean; image_cache_max_bytes: number; 
+/**
+ * Share of each response cache entry limit in use
+ */
+response_cache_percent: number }
+
+/**
+ * Disk space the app uses
+ */
+export type StorageUsage = { database_bytes: number; image_cache_bytes: number; recordings_bytes: number; total_bytes: number; 
+/**
+ * Change since the previous digest; unset for the first one
+ */
+growth_bytes: number | null }
+
+/**
+ * Groups of commands that are limited together
+ */
+export type Subsystem = "images" | "search" | "sync" | "epg"
+
+/**
+ * Why a subsystem didn't come up and what might fix it
+ */
+export type SubsystemFailure = { subsystem: StartupSubsystem; error: string; message: string; suggestions: string[] }
+
+/**
+ * How busy a subsystem is
+ */
+export type SubsystemLoad = { subsystem: Subsystem; limit: number; running: number; queued: number; 
+/**
+ * Calls rejected as overloaded since startup
+ */
+rejected: number }
+
+/**
+ * A recorded sync run, with item deltas relative to the cache before the run
+ */
+export type SyncHistoryEntry = { id: number; profile_id: string; full_sync: boolean; status: SyncStatus; started_at: string; duration_ms: number; channels_count: number; movies_count: number; series_count: number; channels_delta: number; movies_delta: number; series_delta: number; errors: string[] }
+
+/**
+ * Request limits learned for a profile's provider
+ */
+export type SyncLimits = { profile_id: string; max_concurrency: number; 
+/**
+ * Minimum time between the starts of two requests
+ */
+request_delay_ms: number; 
+/**
+ * How often the provider's errors have reduced the limits
+ */
+throttle_count: number; last_throttled_at: number | null }
+
+/**
+ * Synchronization progress information
+ */
+export type SyncProgress = { status: SyncStatus; progress: number; current_step: string; channels_synced: number; movies_synced: number; series_synced: number; errors: string[] }
+
+/**
+ * Synchronization settings
+ */
+export type SyncSettings = { auto_sync_enabled: boolean; sync_interval_hours: number; wifi_only: boolean; notify_on_complete: boolean; 
+/**
+ * Only refresh favorited channels, their EPG and favorited series
+ */
+favorites_only?: boolean }
+
+/**
+ * Synchronization status
+ */
+export type SyncStatus = "pending" | "syncing" | "completed" | "failed" | "partial"
+
+/**
+ * A search mode or syntax element, as described by `get_search_syntax_help`
+ */
+export type SyntaxEntry = { syntax: string; description: string; example: string | null }
+
+/**
+ * What kind of job a background task is
+ */
+export type TaskKind = "sync" | "image_preload" | "playlist_watch" | "update_check" | "account_check" | "automation" | "maintenance" | "recording"
+
+/**
+ * First tier of a tiered search: the matches in the local cache
+ * 
+ * Provider matches that aren't cached follow in a `provider_search_results`
+ * event carrying the same `search_id`.
+ */
+export type TieredSearchStart = { search_id: string; local: SearchResult }
+
+/**
+ * Format images are transcoded to for the webview
+ */
+export type TranscodeTarget = "png" | 
+/**
+ * Lossless WebP, usually smaller than PNG
+ */
+"webp"
+
+/**
+ * What `trim_memory` released
+ */
+export type TrimResult = { entries_cleared: number; approx_bytes_freed: number; 
+/**
+ * Database connections whose page and statement caches were shrunk
+ */
+connections_shrunk: number }
+
+export type UnifiedSearchResults = { query: string; 
+/**
+ * Groups with any hits, the group with the best hit first
+ */
+groups: SearchGroup[] }
+
+/**
+ * Request to update an automation script
+ */
+export type UpdateAutomationScriptRequest = { name: string | null; event: AutomationEvent | null; source: string | null; enabled: boolean | null }
+
+/**
+ * Result of comparing the newest release against the running version
+ */
+export type UpdateInfo = { current_version: string; 
+/**
+ * Newest published release, if the endpoint listed any with a version tag
+ */
+latest_version: string | null; update_available: boolean; release_name: string | null; 
+/**
+ * Release notes as written on the release (usually Markdown)
+ */
+notes: string | null; release_url: string | null; published_at: string | null; assets: ReleaseAsset[]; checked_at: number; 
+/**
+ * Whether this result was served from the last check instead of fetched
+ */
+from_cache: boolean }
+
+/**
+ * Request to update playback position
+ */
+export type UpdatePositionRequest = { profile_id: string; content_type: string; content_id: string; position: number; duration: number | null; 
+/**
+ * Play without recording history, regardless of the session setting
+ */
+private?: boolean }
+
+/**
+ * Request to update an existing profile
+ */
+export type UpdateProfileRequest = { name: string | null; url: string | null; username: string | null; password: string | null }
+
+export type UpdateSavedFilterRequest = { name: string | null; filter_data: string | null }
+
+export type UpdateSettings = { auto_check: boolean; 
+/**
+ * Endpoint in use, custom or default
+ */
+endpoint: string; default_endpoint: string }
+
+/**
+ * Options of the cached movie and series listing and search commands
+ * 
+ * Every field may be left out. Searches rank by relevance, so they ignore `sort_mode`;
+ * plain listings ignore the search-only fields.
+ */
+export type VodListingOptions = { 
+/**
+ * `popularity` to list the most watched, favorite and recently added first
+ */
+sort_mode: VodSortMode; 
+/**
+ * Age rating bounds; the parental limit caps the maximum
+ */
+min_age_rating: number | null; max_age_rating: number | null; 
+/**
+ * `advanced` to allow wildcards and quoted phrases (see `get_search_syntax_help`)
+ */
+search_mode: SearchMode; 
+/**
+ * Search session; a newer search in the session cancels this one
+ */
+session_id: string | null; 
+/**
+ * Return each icon's file in `cached_icon` when the image cache already holds it
+ */
+resolve_thumbnails: boolean }
+
+/**
+ * How a movie or series listing is ordered
+ */
+export type VodSortMode = 
+/**
+ * By name
+ */
+"default" | 
+/**
+ * Most watched, favorite and recently added first
+ */
+"popularity"
+
+/**
+ * Outcome of importing one file from the watch directory
+ */
+export type WatchImportEvent = { file: string; name: string; list_id: number | null; status: string; channel_count: number | null; error: string | null }
+
+/**
+ * Watch time for a channel or genre
+ */
+export type WatchTotal = { name: string; hours: number; 
+/**
+ * Number of titles or channels that count towards it
+ */
+items: number }
+
+/**
+ * A watchlist entry with the cached details of its movie or series
+ */
+export type WatchlistItem = { 
+/**
+ * `movie:<stream id>` or `series:<series id>`
+ */
+content: string; 
+/**
+ * Unix timestamp
+ */
+added_at: number; 
+/**
+ * Whether the content is still in the cached catalog; the details are unset if not
+ */
+available: boolean; name: string | null; 
+/**
+ * Movie poster or series cover
+ */
+image: string | null; year: string | null; genre: string | null; rating_5based: number | null; category_id: string | null }
+
+/**
+ * Represents a channel from Xtream API
+ */
+export type XtreamChannel = { stream_id: number; num: number | null; name: string; stream_type: string | null; stream_icon: string | null; thumbnail: string | null; epg_channel_id: string | null; added: string | null; category_id: string | null; custom_sid: string | null; tv_archive: number | null; direct_source: string | null; tv_archive_duration: number | null; 
+/**
+ * Set when `direct_source` is a user-supplied override rather than the provider URL
+ */
+url_overridden?: boolean; 
+/**
+ * ISO country code detected from the name or category when the channel was synced
+ */
+country?: string | null; 
+/**
+ * Flag emoji for `country`
+ */
+country_flag?: string | null; 
+/**
+ * Reliability score from 0 to 100 from local playback reports; `None` until played
+ */
+reliability?: number | null }
+
+/**
+ * A cached Xtream channel, optionally with the programme airing now
+ */
+export type XtreamChannelListing = ({ stream_id: number; num: number | null; name: string; stream_type: string | null; stream_icon: string | null; thumbnail: string | null; epg_channel_id: string | null; added: string | null; category_id: string | null; custom_sid: string | null; tv_archive: number | null; direct_source: string | null; tv_archive_duration: number | null; 
+/**
+ * Set when `direct_source` is a user-supplied override rather than the provider URL
+ */
+url_overridden?: boolean; 
+/**
+ * ISO country code detected from the name or category when the channel was synced
+ */
+country?: string | null; 
+/**
+ * Flag emoji for `country`
+ */
+country_flag?: string | null; 
+/**
+ * Reliability score from 0 to 100 from local playback reports; `None` until played
+ */
+reliability?: number | null }) & { now_playing?: NowPlaying | null; 
+/**
+ * Cached file of `stream_icon`; `None` when it isn't cached or wasn't asked for
+ */
+cached_icon: string | null }
+
+/**
+ * A user-defined tag attached to a content item
+ */
+export type XtreamContentTag = { profile_id: string; content_type: string; content_id: string; tag: string; created_at: string }
+
+/**
+ * A reminder for an upcoming EPG programme
+ */
+export type XtreamEpgReminder = { id: string; profile_id: string; channel_id: string; channel_name: string; title: string; description: string | null; start_timestamp: number; stop_timestamp: number; created_at: string }
+
+/**
+ * Represents an episode in a series
+ */
+export type XtreamEpisode = { episode_id: string; season_number: number; episode_num: string; title: string | null; container_extension: string | null; custom_sid: string | null; added: string | null; direct_source: string | null; info_json: string | null }
+
+/**
+ * Favorite item for Xtream content
+ */
+export type XtreamFavorite = { id: string; profile_id: string; content_type: string; content_id: string; content_data: JsonValue; created_at: string }
+
+/**
+ * History item for Xtream content
+ */
+export type XtreamHistory = { id: string; profile_id: string; content_type: string; content_id: string; content_data: JsonValue; watched_at: string; position: number | null; duration: number | null; 
+/**
+ * Set when the item was marked as watched, rather than from playback position
+ */
+watched?: boolean }
+
+/**
+ * Represents a movie from Xtream API
+ */
+export type XtreamMovie = { stream_id: number; num: number | null; name: string; title: string | null; year: string | null; stream_type: string | null; stream_icon: string | null; rating: number | null; rating_5based: number | null; genre: string | null; added: string | null; episode_run_time: number | null; category_id: string | null; container_extension: string | null; custom_sid: string | null; direct_source: string | null; release_date: string | null; cast: string | null; director: string | null; plot: string | null; youtube_trailer: string | null; 
+/**
+ * Minimum viewer age from the provider's certificate, if it gave one
+ */
+age_rating?: number | null }
+
+/**
+ * A cached Xtream movie with the cached file of its icon
+ */
+export type XtreamMovieListing = ({ stream_id: number; num: number | null; name: string; title: string | null; year: string | null; stream_type: string | null; stream_icon: string | null; rating: number | null; rating_5based: number | null; genre: string | null; added: string | null; episode_run_time: number | null; category_id: string | null; container_extension: string | null; custom_sid: string | null; direct_source: string | null; release_date: string | null; cast: string | null; director: string | null; plot: string | null; youtube_trailer: string | null; 
+/**
+ * Minimum viewer age from the provider's certificate, if it gave one
+ */
+age_rating?: number | null }) & { 
+/**
+ * Cached file of `stream_icon`; `None` when it isn't cached or wasn't asked for
+ */
+cached_icon: string | null }
+
+/**
+ * Xtream profile stored in the database
+ */
+export type XtreamProfile = { id: string; name: string; url: string; username: string; created_at: string; updated_at: string; last_used: string | null; is_active: boolean }
+
+/**
+ * Represents a season in a series
+ */
+export type XtreamSeason = { season_number: number; name: string | null; episode_count: number | null; overview: string | null; air_date: string | null; cover: string | null; cover_big: string | null; vote_average: number | null }
+
+/**
+ * Represents a series listing from Xtream API
+ */
+export type XtreamSeries = { series_id: number; num: number | null; name: string; title: string | null; year: string | null; cover: string | null; plot: string | null; cast: string | null; director: string | null; genre: string | null; release_date: string | null; last_modified: string | null; rating: string | null; rating_5based: number | null; episode_run_time: string | null; category_id: string | null; 
+/**
+ * Minimum viewer age from the provider's certificate, if it gave one
+ */
+age_rating?: number | null }
+
+/**
+ * Complete series details with seasons and episodes
+ */
+export type XtreamSeriesDetails = { series: XtreamSeries; seasons: XtreamSeason[]; episodes: XtreamEpisode[] }
+
+/**
+ * A cached Xtream series with the cached file of its cover
+ */
+export type XtreamSeriesListing = ({ series_id: number; num: number | null; name: string; title: string | null; year: string | null; cover: string | null; plot: string | null; cast: string | null; director: string | null; genre: string | null; release_date: string | null; last_modified: string | null; rating: string | null; rating_5based: number | null; episode_run_time: string | null; category_id: string | null; 
+/**
+ * Minimum viewer age from the provider's certificate, if it gave one
+ */
+age_rating?: number | null }) & { 
+/**
+ * Cached file of `cover`; `None` when it isn't cached or wasn't asked for
+ */
+cached_icon: string | null }
+
+/**
+ * A profile's watching over one year, for end-of-year sharing
+ * 
+ * History keeps one entry per title, so each title counts towards the day and
+ * month it was last watched.
+ */
+export type YearInReview = { profile_id: string; year: number; total_hours: number; items_watched: number; top_channels: WatchTotal[]; top_genres: WatchTotal[]; busiest_day: BusiestDay | null; 
+/**
+ * Hours watched in each month, January first
+ */
+monthly_hours: number[]; 
+/**
+ * Bar chart of `monthly_hours` as a PNG data URL, when requested
+ */
+chart_png: string | null }
+
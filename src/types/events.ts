@@ -0,0 +1,56 @@
+/**
+ * Events emitted by the backend
+ *
+ * Mirrors `src-tauri/src/events.rs`. A backend test checks that the event
+ * names in `AppEventMap` match the backend catalog, so add or rename events
+ * in both places. Payload types are generated into `./bindings`.
+ */
+
+import type {
+  AccountStatus,
+  ChannelLoadingStatus,
+  ContentExpiry,
+  EffectiveConfig,
+  FavoritesChange,
+  ImageFetched,
+  ImagePrefetchReport,
+  InboxNotification,
+  PlaylistFetchStatus,
+  PowerState,
+  ProfileSyncProgress,
+  ProviderSearchResults,
+  Recording,
+  RemotePlayRequest,
+  SearchProgress,
+  SettingChange,
+  UpdateInfo,
+  WatchImportEvent,
+} from "./bindings";
+
+/** Payload of every backend event, by event name */
+export type AppEventMap = {
+  history_loading: string;
+  search_progress: SearchProgress;
+  groups_loading: ChannelLoadingStatus;
+  channel_loading: ChannelLoadingStatus;
+  image_fetched: ImageFetched;
+  playlist_fetch_status: PlaylistFetchStatus;
+  playlist_watch_import: WatchImportEvent;
+  favorites_changed: FavoritesChange;
+  xtream_startup_auth: AccountStatus;
+  xtream_account_expired: AccountStatus;
+  remote_control_play: RemotePlayRequest;
+  update_available: UpdateInfo;
+  provider_search_results: ProviderSearchResults;
+  content_expired: ContentExpiry;
+  power_state_changed: PowerState;
+  notification_added: InboxNotification;
+  "sync://progress": ProfileSyncProgress;
+  "sync://complete": ProfileSyncProgress;
+  setting_changed: SettingChange;
+  config_reloaded: EffectiveConfig;
+  recording_updated: Recording;
+  image_prefetch_complete: ImagePrefetchReport;
+};
+
+export type AppEventName = keyof AppEventMap;